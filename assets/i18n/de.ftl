@@ -0,0 +1,244 @@
+# Dock
+dock-label = Hauptnavigation
+dock-reset-layout = Layout zurücksetzen
+dock-music-mute = Musik stummschalten
+dock-music-unmute = Musik einschalten
+dock-music-volume = Musiklautstärke
+
+# Panels
+panel-map = Karte
+panel-system-detail = System
+panel-fleet-list = Flotten
+panel-console = Konsole
+panel-inspector = Inspektor
+panel-dock-left = Links andocken
+panel-dock-center = Mittig andocken
+panel-dock-right = Rechts andocken
+panel-dock-bottom = Unten andocken
+panel-close = Panel schließen
+panel-resize = Panelgröße ändern
+panel-placeholder = Noch nichts anzuzeigen.
+panel-loading = Wird geladen…
+
+# World view
+world-view-label = Sternenkarte. Mit der Maus ziehen, um die Kamera zu bewegen.
+minimap-label = Minikarte. Klicken, um die Kamera zu bewegen, scrollen zum Zoomen.
+
+# Notifications
+toast-region-label = Benachrichtigungen
+toast-dismiss = Schließen
+notification-asset-load-failed = Asset konnte nicht geladen werden
+notification-asset-events-disconnected = Verbindung zum Asset-Server verloren
+notification-asset-manifest-unsupported = Das Spiel wurde aktualisiert. Lade die Seite neu, um die neue Version zu erhalten.
+notification-content-pack-failed = Inhaltspaket konnte nicht geladen werden
+notification-graphics-restarted = Die Grafik reagierte nicht mehr und wurde neu gestartet
+
+# Inspector
+inspector-nothing-selected = Nichts ausgewählt. Auf ein Objekt klicken, oder mit gedrückter Umschalttaste ziehen, um mehrere auszuwählen.
+inspector-unnamed = Unbenannt
+inspector-rename = Umbenennen
+inspector-rename-name = Neuer Name
+notification-rename-star-failed = Stern konnte nicht umbenannt werden
+
+# Flotten und Befehle
+panel-orders = Befehle
+fleet-list-refresh = Aktualisieren
+fleet-list-empty = Keine Flotten.
+order-queue-no-fleet = Eine Flotte auswählen, um ihre Befehle anzuzeigen.
+order-move = Bewegen
+order-survey = Erkunden
+order-colonize = Kolonisieren
+order-kind = Befehl
+order-star = Zielstern
+order-add = Befehl hinzufügen
+order-move-up = Nach oben
+order-move-down = Nach unten
+order-remove = Befehl entfernen
+notification-fleet-load-failed = Flotte konnte nicht geladen werden
+notification-orders-rejected = Befehle wurden abgelehnt
+
+# Dashboard
+panel-dashboard = Imperium
+dashboard-refresh = Aktualisieren
+dashboard-no-faction = Keine Fraktion ausgewählt. Lege in der Konfiguration eine fest, um dein Imperium zu sehen.
+dashboard-unavailable = Die Imperiumsübersicht konnte nicht geladen werden.
+dashboard-colonies = Kolonien
+dashboard-no-colonies = Noch keine Kolonien.
+dashboard-fleets = Flotten
+dashboard-fleets-idle = Untätige Flotten
+dashboard-explored-stars = Erforschte Sterne
+dashboard-resources = Ressourcen
+dashboard-resource-amounts = +{ $production }/Tag, { $stockpiled } gelagert
+resource-energy = Energie
+resource-metals = Metalle
+resource-volatiles = Flüchtige Stoffe
+
+# Leaderboard
+panel-leaderboard = Rangliste
+leaderboard-refresh = Aktualisieren
+leaderboard-empty = Es wurden noch keine Bewertungen berechnet.
+leaderboard-rank = Rang
+leaderboard-faction = Fraktion
+leaderboard-rating = Kardaschow-Wert
+leaderboard-history = Verlauf
+
+# Diplomacy
+panel-diplomacy = Diplomatie
+diplomacy-refresh = Aktualisieren
+diplomacy-no-faction = Keine Fraktion ausgewählt. Wähle eine in der Konfiguration, um Diplomatie zu betreiben.
+diplomacy-unavailable = Die Beziehungen konnten nicht geladen werden.
+diplomacy-faction = Fraktion
+diplomacy-stance = Haltung
+diplomacy-treaty = Vertrag
+diplomacy-stance-war = Krieg
+diplomacy-stance-peace = Frieden
+diplomacy-stance-alliance = Bündnis
+diplomacy-treaty-days = noch { $days } Tage
+diplomacy-days = Laufzeit vorgeschlagener Verträge in Tagen
+diplomacy-days-unlimited = Unbefristet
+diplomacy-offered = Bietet { $stance } an
+diplomacy-proposed = { $stance } vorgeschlagen
+diplomacy-accept = Annehmen
+diplomacy-decline = Ablehnen
+diplomacy-withdraw = Zurückziehen
+diplomacy-declare-war = Krieg erklären
+diplomacy-propose-peace = Frieden vorschlagen
+diplomacy-propose-alliance = Bündnis vorschlagen
+notification-diplomacy-failed = Diplomatische Aktion fehlgeschlagen
+
+# Journal
+panel-journal = Logbuch
+journal-filter = Ereignisse filtern
+journal-filter-all = Alle Ereignisse
+journal-empty = Bisher ist nichts passiert.
+journal-load-more = Ältere Einträge laden
+journal-unknown-star = Unbekannter Stern
+journal-battle = Gefecht
+journal-discovery = Stern erforscht
+journal-colony-founded = Kolonie gegründet
+notification-journal-load-failed = Logbuch konnte nicht geladen werden
+
+# Sternsuche
+star-search-label = Sterne suchen
+star-search-placeholder = Sternname, Katalognummer oder Richtung wie "gal 120.5 -3.2"
+notification-star-search-failed = Sternsuche fehlgeschlagen
+
+# Lesezeichen
+panel-bookmarks = Lesezeichen
+bookmarks-name = Name des Lesezeichens
+bookmarks-default-name = Unbenannte Ansicht
+bookmarks-save = Aktuelle Ansicht speichern
+bookmarks-remove = Lesezeichen entfernen
+bookmarks-empty = Noch keine Lesezeichen. Speichere die aktuelle Ansicht, um eines hinzuzufügen.
+notification-bookmarks-sync-failed = Lesezeichen konnten nicht gespeichert werden
+bookmarks-copy = Lesezeichen kopieren
+bookmarks-paste = Lesezeichen einfügen
+bookmarks-galactic-coordinates = Galaktische Länge und Breite, von der Sonne aus gesehen
+notification-bookmark-copied = Lesezeichen in die Zwischenablage kopiert
+notification-bookmark-copy-failed = Lesezeichen konnte nicht kopiert werden
+notification-bookmarks-pasted = Lesezeichen eingefügt
+notification-bookmarks-paste-failed = Lesezeichen konnten nicht eingefügt werden
+
+# Replay
+panel-replay = Wiederholung
+replay-empty = Es wurden noch keine Momentaufnahmen aufgezeichnet.
+replay-play = Abspielen
+replay-pause = Anhalten
+replay-speed = Momentaufnahmen pro Sekunde
+replay-live = Zurück zum Live-Geschehen
+replay-status = Epoche { $epoch } ({ $time }). Flotten: { $fleets }, Kolonien: { $colonies }
+notification-replay-failed = Wiederholung konnte nicht gestartet werden
+
+# Messwerkzeug
+measure-toggle = Entfernungen messen
+measure-pick-start = Klicke auf einen Stern, um mit dem Messen zu beginnen.
+measure-pick-end = Wähle einen zweiten Stern
+measure-unnamed-star = Unbenannter Stern
+measure-speeds = Geschwindigkeiten (c)
+
+# Kartenebenen
+layers-toggle = Kartenebenen
+layers-raise = Nach oben
+layers-lower = Nach unten
+layer-grid = Gitter
+layer-regions = Regionen
+layer-ownership = Territorium
+layer-routes = Flottenrouten
+layer-trade-routes = Handelsrouten
+layer-labels = Beschriftungen
+
+# Heatmap
+heatmap-label = Heatmap
+heatmap-off = Keine Heatmap
+heatmap-ramp = Farbverlauf
+heatmap-temperature = Temperatur (K)
+heatmap-luminosity = Leuchtkraft (L☉)
+heatmap-mass = Masse (M☉)
+heatmap-absolute-magnitude = Absolute Helligkeit
+heatmap-activity = Erkundung
+
+# Build errors
+build-error-ui = UI-Build fehlgeschlagen
+build-error-assets = Asset-Build fehlgeschlagen
+build-error-dismiss = Schließen
+
+# File import
+file-drop-hint = Dateien hier ablegen, um sie zu importieren
+notification-import-started = Dateien werden importiert
+notification-import-succeeded = Dateien importiert
+notification-import-failed = Datei konnte nicht importiert werden
+
+# Fehlerberichte
+notification-crash-report-saved = Etwas ist schiefgelaufen
+notification-crash-report-saved-message = { $count } Fehlerbericht(e) wurden gespeichert. Sie zu senden hilft, das Problem zu beheben.
+crash-report-send = Senden
+crash-report-always-send = Immer senden
+crash-report-view-failed = Diese Ansicht konnte nicht geladen werden. Der Fehler wurde gemeldet.
+
+# Einstellungen
+panel-settings = Einstellungen
+settings-graphics = Grafik
+settings-graphics-not-initialized = Die Grafik ist noch nicht initialisiert.
+settings-graphics-backend = Backend
+settings-graphics-adapter = Grafikkarte
+settings-graphics-enabled = Aktiviert
+settings-graphics-disabled = Deaktiviert
+render-feature-hdr = HDR-Rendering
+render-feature-compute-shaders = Compute-Shader
+render-feature-timestamp-queries = GPU-Zeitmessung
+render-feature-gpu-culling = GPU-Culling
+render-feature-gpu-light-clustering = GPU-Lichtclustering
+settings-accessibility = Barrierefreiheit
+settings-reduced-motion = Bewegung reduzieren
+reduced-motion-system = Wie das System
+reduced-motion-always = An
+reduced-motion-never = Aus
+settings-color-vision = Farben
+color-vision-normal = Standard
+color-vision-deuteranopia = Deuteranopie (Rot-Grün)
+color-vision-protanopia = Protanopie (Rot-Grün)
+color-vision-tritanopia = Tritanopie (Blau-Gelb)
+settings-reports = Berichte
+settings-send-crash-reports = Fehlerberichte automatisch senden
+settings-send-capabilities = Grafikfähigkeiten senden
+notification-settings-sync-failed = Einstellungen konnten nicht synchronisiert werden
+
+# Profiling
+profiling-toggle = Render-Zeiten
+profiling-depth-prepass = Tiefen-Vorpass
+profiling-frame-time = Frame-Zeit
+profiling-fps = FPS
+profiling-record-time = Aufzeichnungszeit
+profiling-with-depth-prepass = Mit Vorpass
+profiling-without-depth-prepass = Ohne Vorpass
+profiling-performance-marks = Performance-Marken
+profiling-record-trace = Trace aufzeichnen
+profiling-stop-trace = Trace beenden und herunterladen
+
+# Ladebildschirm
+startup-phase-wasm = Starten
+startup-phase-config = Einstellungen laden
+startup-phase-graphics = Grafik initialisieren
+startup-phase-assets = Assets herunterladen
+startup-phase-world = Mit der Welt verbinden
+startup-retry = Erneut versuchen
@@ -0,0 +1,244 @@
+# Dock
+dock-label = Main navigation
+dock-reset-layout = Reset layout
+dock-music-mute = Mute music
+dock-music-unmute = Unmute music
+dock-music-volume = Music volume
+
+# Panels
+panel-map = Map
+panel-system-detail = System
+panel-fleet-list = Fleets
+panel-console = Console
+panel-inspector = Inspector
+panel-dock-left = Dock left
+panel-dock-center = Dock center
+panel-dock-right = Dock right
+panel-dock-bottom = Dock bottom
+panel-close = Close panel
+panel-resize = Resize panel
+panel-placeholder = Nothing to show yet.
+panel-loading = Loading…
+
+# World view
+world-view-label = Star map. Drag with the mouse to move the camera.
+minimap-label = Minimap. Click to move the camera, scroll to zoom.
+
+# Notifications
+toast-region-label = Notifications
+toast-dismiss = Dismiss
+notification-asset-load-failed = Failed to load asset
+notification-asset-events-disconnected = Lost connection to the asset server
+notification-asset-manifest-unsupported = The game was updated. Reload the page to get the new version.
+notification-content-pack-failed = Failed to load content pack
+notification-graphics-restarted = Graphics stopped responding and were restarted
+
+# Inspector
+inspector-nothing-selected = Nothing selected. Click on an object, or hold shift and drag to select multiple.
+inspector-unnamed = Unnamed
+inspector-rename = Rename
+inspector-rename-name = New name
+notification-rename-star-failed = Failed to rename star
+
+# Fleets and orders
+panel-orders = Orders
+fleet-list-refresh = Refresh
+fleet-list-empty = No fleets.
+order-queue-no-fleet = Select a fleet to see its orders.
+order-move = Move
+order-survey = Survey
+order-colonize = Colonize
+order-kind = Order
+order-star = Target star
+order-add = Add order
+order-move-up = Move up
+order-move-down = Move down
+order-remove = Remove order
+notification-fleet-load-failed = Failed to load fleet
+notification-orders-rejected = Orders were rejected
+
+# Dashboard
+panel-dashboard = Empire
+dashboard-refresh = Refresh
+dashboard-no-faction = No faction selected. Set one in the configuration to see your empire.
+dashboard-unavailable = The empire overview could not be loaded.
+dashboard-colonies = Colonies
+dashboard-no-colonies = No colonies yet.
+dashboard-fleets = Fleets
+dashboard-fleets-idle = Idle fleets
+dashboard-explored-stars = Explored stars
+dashboard-resources = Resources
+dashboard-resource-amounts = +{ $production }/day, { $stockpiled } stored
+resource-energy = Energy
+resource-metals = Metals
+resource-volatiles = Volatiles
+
+# Leaderboard
+panel-leaderboard = Leaderboard
+leaderboard-refresh = Refresh
+leaderboard-empty = No ratings have been computed yet.
+leaderboard-rank = Rank
+leaderboard-faction = Faction
+leaderboard-rating = Kardashev rating
+leaderboard-history = Trend
+
+# Diplomacy
+panel-diplomacy = Diplomacy
+diplomacy-refresh = Refresh
+diplomacy-no-faction = No faction selected. Set one in the configuration to conduct diplomacy.
+diplomacy-unavailable = Relations could not be loaded.
+diplomacy-faction = Faction
+diplomacy-stance = Stance
+diplomacy-treaty = Treaty
+diplomacy-stance-war = War
+diplomacy-stance-peace = Peace
+diplomacy-stance-alliance = Alliance
+diplomacy-treaty-days = { $days } days left
+diplomacy-days = Duration of proposed treaties in days
+diplomacy-days-unlimited = Unlimited
+diplomacy-offered = Offers { $stance }
+diplomacy-proposed = Proposed { $stance }
+diplomacy-accept = Accept
+diplomacy-decline = Decline
+diplomacy-withdraw = Withdraw
+diplomacy-declare-war = Declare war
+diplomacy-propose-peace = Propose peace
+diplomacy-propose-alliance = Propose alliance
+notification-diplomacy-failed = Diplomatic action failed
+
+# Journal
+panel-journal = Journal
+journal-filter = Filter events
+journal-filter-all = All events
+journal-empty = Nothing has happened yet.
+journal-load-more = Load older entries
+journal-unknown-star = Unknown star
+journal-battle = Battle
+journal-discovery = Star explored
+journal-colony-founded = Colony founded
+notification-journal-load-failed = Failed to load journal
+
+# Star search
+star-search-label = Search stars
+star-search-placeholder = Star name, catalog ID, or direction like "gal 120.5 -3.2"
+notification-star-search-failed = Star search failed
+
+# Bookmarks
+panel-bookmarks = Bookmarks
+bookmarks-name = Bookmark name
+bookmarks-default-name = Unnamed view
+bookmarks-save = Save current view
+bookmarks-remove = Remove bookmark
+bookmarks-empty = No bookmarks yet. Save the current view to add one.
+notification-bookmarks-sync-failed = Failed to save bookmarks
+bookmarks-copy = Copy bookmark
+bookmarks-paste = Paste bookmarks
+bookmarks-galactic-coordinates = Galactic longitude and latitude, as seen from the sun
+notification-bookmark-copied = Bookmark copied to the clipboard
+notification-bookmark-copy-failed = Failed to copy bookmark
+notification-bookmarks-pasted = Bookmarks pasted
+notification-bookmarks-paste-failed = Failed to paste bookmarks
+
+# Replay
+panel-replay = Replay
+replay-empty = No snapshots have been recorded yet.
+replay-play = Play
+replay-pause = Pause
+replay-speed = Snapshots per second
+replay-live = Back to live
+replay-status = Epoch { $epoch } ({ $time }). Fleets: { $fleets }, colonies: { $colonies }
+notification-replay-failed = Failed to start replay
+
+# Measurement tool
+measure-toggle = Measure distances
+measure-pick-start = Click on a star to start measuring.
+measure-pick-end = Pick a second star
+measure-unnamed-star = Unnamed star
+measure-speeds = Speeds (c)
+
+# Map layers
+layers-toggle = Map layers
+layers-raise = Move up
+layers-lower = Move down
+layer-grid = Grid
+layer-regions = Regions
+layer-ownership = Territory
+layer-routes = Fleet routes
+layer-trade-routes = Trade routes
+layer-labels = Labels
+
+# Heatmap
+heatmap-label = Heatmap
+heatmap-off = No heatmap
+heatmap-ramp = Color ramp
+heatmap-temperature = Temperature (K)
+heatmap-luminosity = Luminosity (L☉)
+heatmap-mass = Mass (M☉)
+heatmap-absolute-magnitude = Absolute magnitude
+heatmap-activity = Exploration
+
+# Build errors
+build-error-ui = UI build failed
+build-error-assets = Asset build failed
+build-error-dismiss = Dismiss
+
+# File import
+file-drop-hint = Drop files to import them
+notification-import-started = Importing files
+notification-import-succeeded = Files imported
+notification-import-failed = Failed to import file
+
+# Crash reports
+notification-crash-report-saved = Something went wrong
+notification-crash-report-saved-message = { $count } error report(s) were saved. Sending them helps to fix the problem.
+crash-report-send = Send
+crash-report-always-send = Always send
+crash-report-view-failed = This view failed to load. The error was reported.
+
+# Settings
+panel-settings = Settings
+settings-graphics = Graphics
+settings-graphics-not-initialized = Graphics are not initialized yet.
+settings-graphics-backend = Backend
+settings-graphics-adapter = Adapter
+settings-graphics-enabled = Enabled
+settings-graphics-disabled = Disabled
+render-feature-hdr = HDR rendering
+render-feature-compute-shaders = Compute shaders
+render-feature-timestamp-queries = GPU timings
+render-feature-gpu-culling = GPU culling
+render-feature-gpu-light-clustering = GPU light clustering
+settings-accessibility = Accessibility
+settings-reduced-motion = Reduced motion
+reduced-motion-system = Like the system
+reduced-motion-always = On
+reduced-motion-never = Off
+settings-color-vision = Colors
+color-vision-normal = Default
+color-vision-deuteranopia = Deuteranopia (red-green)
+color-vision-protanopia = Protanopia (red-green)
+color-vision-tritanopia = Tritanopia (blue-yellow)
+settings-reports = Reports
+settings-send-crash-reports = Send crash reports automatically
+settings-send-capabilities = Send graphics capabilities
+notification-settings-sync-failed = Failed to sync settings
+
+# Profiling
+profiling-toggle = Render timings
+profiling-depth-prepass = Depth pre-pass
+profiling-frame-time = Frame time
+profiling-fps = FPS
+profiling-record-time = Record time
+profiling-with-depth-prepass = With pre-pass
+profiling-without-depth-prepass = Without pre-pass
+profiling-performance-marks = Performance marks
+profiling-record-trace = Record trace
+profiling-stop-trace = Stop and download trace
+
+# Loading screen
+startup-phase-wasm = Starting
+startup-phase-config = Loading settings
+startup-phase-graphics = Initializing graphics
+startup-phase-assets = Downloading assets
+startup-phase-world = Connecting to the world
+startup-retry = Retry
@@ -0,0 +1,23 @@
+-- Formulas for colonies.
+--
+-- These are the built-in formulas, as a starting point for balancing. Run the
+-- server with `--rules-dir content/rules` to use them.
+
+-- Energy output in watts of a colony at a star with the given luminosity (in
+-- solar luminosities).
+function colony_power(luminosity)
+    return luminosity * SOLAR_LUMINOSITY * COLONY_ENERGY_FRACTION
+end
+
+-- Amount of a resource ("energy", "metals" or "volatiles") a colony produces
+-- per day, given the luminosity of its star (in solar luminosities) and the
+-- number of planets around it.
+function resource_production(resource, luminosity, planets)
+    if resource == "energy" then
+        return ENERGY_PER_LUMINOSITY * math.max(luminosity, 0)
+    elseif resource == "metals" then
+        return METALS_PER_PLANET * planets
+    else
+        return VOLATILES_PER_COLONY
+    end
+end
@@ -0,0 +1,13 @@
+-- Formulas for the leaderboard.
+--
+-- These are the built-in formulas, as a starting point for balancing.
+
+-- Rating on the Kardashev scale for an energy output in watts, using Carl
+-- Sagan's interpolation.
+function kardashev_rating(power)
+    if power > 1e6 then
+        return (math.log(power, 10) - 6) / 10
+    else
+        return 0
+    end
+end
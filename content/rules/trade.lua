@@ -0,0 +1,8 @@
+-- Formulas for trade.
+--
+-- These are the built-in formulas, as a starting point for balancing.
+
+-- Days freighters take to travel the given distance in light years.
+function trade_travel_days(light_years)
+    return light_years / TRADE_SPEED
+end
@@ -20,6 +20,10 @@ use kardashev_protocol::assets::TextureCrop;
 
 use crate::assets::Error;
 
+/// Default maximum width and height of an atlas page. WebGL only guarantees
+/// that textures of 4096 pixels are supported.
+pub const DEFAULT_MAX_PAGE_SIZE: u32 = 4096;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AtlasBuilderId {
     Default,
@@ -35,76 +39,72 @@ impl Display for AtlasBuilderId {
     }
 }
 
+/// Packs images into pages of an atlas. Images that don't fit into the
+/// existing pages spill into a new one.
 pub struct AtlasBuilder<D> {
-    allocator: AtlasAllocator,
-    allocations: HashMap<AllocId, Allocation<D>>,
+    max_page_size: u32,
+    pages: Vec<Page<D>>,
 }
 
 impl<D: Debug> Debug for AtlasBuilder<D> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AtlasBuilder")
-            .field("allocations", &self.allocations)
-            .finish_non_exhaustive()
+            .field("max_page_size", &self.max_page_size)
+            .field("pages", &self.pages)
+            .finish()
     }
 }
 
-#[derive(Debug)]
-struct Allocation<D> {
-    rectangle: Rectangle,
-    image: RgbaImage,
-    data: D,
-}
-
 impl<D> Default for AtlasBuilder<D> {
     fn default() -> Self {
-        Self {
-            allocator: AtlasAllocator::new(Self::INITIAL_SIZE),
-            allocations: HashMap::new(),
-        }
+        Self::new(DEFAULT_MAX_PAGE_SIZE)
     }
 }
 
 impl<D> AtlasBuilder<D> {
-    const INITIAL_SIZE: Size = Size::new(1024, 1024);
+    /// `max_page_size` is the maximum width and height of a page.
+    pub fn new(max_page_size: u32) -> Self {
+        Self {
+            max_page_size: max_page_size.min(i32::MAX as u32),
+            pages: vec![],
+        }
+    }
 
     pub fn insert(&mut self, image: RgbaImage, data: D) -> Result<(), Error> {
-        let image_size = image.dimensions();
-
-        let allocation = loop {
-            if let Some(allocation) = self
-                .allocator
-                .allocate(Size::new(image_size.0 as i32, image_size.1 as i32))
-            {
-                break allocation;
+        let (width, height) = image.dimensions();
+        if width > self.max_page_size || height > self.max_page_size {
+            return Err(Error::AtlasImageTooLarge {
+                width,
+                height,
+                max_page_size: self.max_page_size,
+            });
+        }
+        let size = Size::new(width as i32, height as i32);
+        let max_size = self.max_page_size as i32;
+
+        let mut allocation = None;
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((id, rectangle)) = page.allocate(size, max_size) {
+                allocation = Some((index, id, rectangle));
+                break;
             }
-            else {
-                let old_size = self.allocator.size();
-                let new_size = old_size * 2;
-                let changes = self.allocator.resize_and_rearrange(new_size);
-                if !changes.failures.is_empty() {
-                    panic!("failed to grow atlas allocator");
-                }
-                let changes = changes
-                    .changes
-                    .into_iter()
-                    .map(|change| (change.old.id, change.new))
-                    .collect::<HashMap<_, _>>();
-                self.allocations = self
-                    .allocations
-                    .drain()
-                    .map(|(old_id, mut allocation)| {
-                        let new = changes.get(&old_id).unwrap();
-                        allocation.rectangle = new.rectangle;
-                        (new.id, allocation)
-                    })
-                    .collect();
+        }
+        let (index, id, rectangle) = match allocation {
+            Some(allocation) => allocation,
+            None => {
+                let mut page = Page::new(max_size);
+                let (id, rectangle) = page
+                    .allocate(size, max_size)
+                    .expect("image doesn't fit into an empty atlas page");
+                self.pages.push(page);
+                (self.pages.len() - 1, id, rectangle)
             }
         };
 
-        self.allocations.insert(
-            allocation.id,
+        self.pages[index].allocations.insert(
+            id,
             Allocation {
-                rectangle: allocation.rectangle,
+                rectangle,
                 image,
                 data,
             },
@@ -113,7 +113,81 @@ impl<D> AtlasBuilder<D> {
         Ok(())
     }
 
-    pub fn finish(self) -> Result<Atlas<D>, Error> {
+    /// Finishes the pages, in the order they were created.
+    pub fn finish(self) -> Result<Vec<Atlas<D>>, Error> {
+        self.pages.into_iter().map(Page::finish).collect()
+    }
+}
+
+struct Page<D> {
+    allocator: AtlasAllocator,
+    allocations: HashMap<AllocId, Allocation<D>>,
+}
+
+impl<D: Debug> Debug for Page<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Page")
+            .field("allocations", &self.allocations)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+struct Allocation<D> {
+    rectangle: Rectangle,
+    image: RgbaImage,
+    data: D,
+}
+
+impl<D> Page<D> {
+    const INITIAL_SIZE: i32 = 1024;
+
+    fn new(max_size: i32) -> Self {
+        let size = Self::INITIAL_SIZE.min(max_size);
+        Self {
+            allocator: AtlasAllocator::new(Size::new(size, size)),
+            allocations: HashMap::new(),
+        }
+    }
+
+    /// Allocates space for an image, growing the page up to `max_size` if
+    /// necessary. Returns `None` if the image doesn't fit.
+    fn allocate(&mut self, size: Size, max_size: i32) -> Option<(AllocId, Rectangle)> {
+        loop {
+            if let Some(allocation) = self.allocator.allocate(size) {
+                return Some((allocation.id, allocation.rectangle));
+            }
+
+            let old_size = self.allocator.size();
+            if old_size.width >= max_size && old_size.height >= max_size {
+                return None;
+            }
+            let new_size = Size::new(
+                (old_size.width * 2).min(max_size),
+                (old_size.height * 2).min(max_size),
+            );
+            let changes = self.allocator.resize_and_rearrange(new_size);
+            if !changes.failures.is_empty() {
+                panic!("failed to grow atlas allocator");
+            }
+            let changes = changes
+                .changes
+                .into_iter()
+                .map(|change| (change.old.id, change.new))
+                .collect::<HashMap<_, _>>();
+            self.allocations = self
+                .allocations
+                .drain()
+                .map(|(old_id, mut allocation)| {
+                    let new = changes.get(&old_id).unwrap();
+                    allocation.rectangle = new.rectangle;
+                    (new.id, allocation)
+                })
+                .collect();
+        }
+    }
+
+    fn finish(self) -> Result<Atlas<D>, Error> {
         let image_size = self.allocator.size();
         let image_size = [image_size.width as u32, image_size.height as u32];
 
@@ -146,6 +220,7 @@ impl<D> AtlasBuilder<D> {
     }
 }
 
+/// A page of an atlas.
 #[derive(Debug)]
 pub struct Atlas<D> {
     pub allocations: Vec<(D, TextureCrop)>,
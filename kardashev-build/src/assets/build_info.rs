@@ -16,6 +16,11 @@ use crate::assets::source::MaterialProperty;
 pub struct BuildInfo {
     pub generated_ids: GeneratedIds,
     pub build_times: HashMap<AssetId, DateTime<Utc>>,
+
+    /// Hashes of the assets' settings in the source manifests, to rebuild
+    /// assets whose settings changed.
+    #[serde(default)]
+    pub settings: HashMap<AssetId, String>,
 }
 
 impl BuildInfo {
@@ -42,6 +47,28 @@ pub enum GeneratedIdKey {
         material: AssetId,
         property: MaterialProperty,
     },
+    ModelPart {
+        model: AssetId,
+        part: ModelPart,
+    },
+}
+
+/// An asset that is imported with a [`Model`](crate::assets::source::Model).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ModelPart {
+    /// The n-th mesh that is imported.
+    Mesh(usize),
+
+    /// Material by its index in the glTF file.
+    Material(usize),
+
+    /// Texture by its index in the glTF file.
+    Texture(usize),
+
+    /// Metalness and roughness, which glTF files combine in one texture, by
+    /// the index of that texture.
+    Metalness(usize),
+    Roughness(usize),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
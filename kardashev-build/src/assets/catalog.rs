@@ -0,0 +1,278 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use kardashev_protocol::assets::AssetId;
+
+use crate::assets::{
+    dist,
+    processor::ProcessContext,
+    source::{
+        Catalog,
+        Manifest,
+    },
+    Asset,
+    Error,
+};
+
+impl Asset for Catalog {
+    fn register_dist_type(dist_asset_types: &mut dist::AssetTypes) {
+        dist_asset_types.register::<dist::Catalog>();
+    }
+
+    fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self> {
+        &manifest.catalogs
+    }
+
+    async fn process<'a, 'b: 'a>(
+        &'a self,
+        id: AssetId,
+        context: &'a mut ProcessContext<'b>,
+    ) -> Result<(), Error> {
+        if !context.processing(id) {
+            return Ok(());
+        }
+
+        let path = context.input_path(&self.path);
+
+        if context.source_path(id, &path)?.is_fresh() {
+            tracing::debug!(%id, "not modified since last build. skipping.");
+            return Ok(());
+        }
+
+        let source = std::fs::read_to_string(&path)?;
+        let messages = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ftl") => parse_fluent(&source, &path)?,
+            Some("json") => parse_json(&source, &path)?,
+            _ => {
+                return Err(CatalogParseError {
+                    path,
+                    line: None,
+                    message: "unsupported file type. expected .ftl or .json".to_owned(),
+                }
+                .into());
+            }
+        };
+
+        let filename = format!("{id}.json");
+        let writer = BufWriter::new(File::create(context.dist_path.join(&filename))?);
+        serde_json::to_writer(writer, &messages)?;
+
+        context.dist_assets.insert(dist::Catalog {
+            id,
+            label: self.label.clone(),
+            build_time: context.build_time,
+            locale: self.locale.clone(),
+            messages: filename,
+        });
+
+        context.set_build_time(id);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("failed to parse catalog {}{}: {message}", path.display(), line.map(|line| format!(":{line}")).unwrap_or_default())]
+pub struct CatalogParseError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Parses a simple subset of [Fluent][1].
+///
+/// Supported are messages (`key = value`), multiline values (indented
+/// continuation lines), attributes (`.attribute = value`, stored as
+/// `key.attribute`) and comments. Placeables are kept as-is.
+///
+/// [1]: https://projectfluent.org/fluent/guide/
+fn parse_fluent(source: &str, path: &Path) -> Result<dist::CatalogMessages, CatalogParseError> {
+    let mut messages = HashMap::new();
+    let mut current_message: Option<String> = None;
+    let mut current_key: Option<String> = None;
+
+    let error = |line: usize, message: &str| {
+        CatalogParseError {
+            path: path.to_owned(),
+            line: Some(line + 1),
+            message: message.to_owned(),
+        }
+    };
+
+    for (line_number, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            let line = line.trim();
+
+            if let Some(attribute) = line.strip_prefix('.') {
+                let message = current_message
+                    .as_ref()
+                    .ok_or_else(|| error(line_number, "attribute without message"))?;
+                let (name, value) = attribute
+                    .split_once('=')
+                    .ok_or_else(|| error(line_number, "expected `=`"))?;
+                let key = format!("{message}.{}", name.trim());
+                messages.insert(key.clone(), value.trim().to_owned());
+                current_key = Some(key);
+            }
+            else {
+                let key = current_key
+                    .as_ref()
+                    .ok_or_else(|| error(line_number, "unexpected indented line"))?;
+                let value: &mut String = messages.get_mut(key).unwrap();
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line);
+            }
+        }
+        else {
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(line_number, "expected `=`"))?;
+            let name = name.trim();
+
+            if name.starts_with('-') {
+                return Err(error(line_number, "terms are not supported"));
+            }
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(error(line_number, "invalid message identifier"));
+            }
+
+            messages.insert(name.to_owned(), value.trim().to_owned());
+            current_message = Some(name.to_owned());
+            current_key = Some(name.to_owned());
+        }
+    }
+
+    Ok(dist::CatalogMessages { messages })
+}
+
+/// Parses a JSON catalog.
+///
+/// Nested objects are flattened, with their keys joined by `.`.
+fn parse_json(source: &str, path: &Path) -> Result<dist::CatalogMessages, Error> {
+    fn flatten(
+        prefix: Option<&str>,
+        value: serde_json::Value,
+        messages: &mut HashMap<String, String>,
+        path: &Path,
+    ) -> Result<(), CatalogParseError> {
+        match value {
+            serde_json::Value::Object(object) => {
+                for (key, value) in object {
+                    let key = match prefix {
+                        Some(prefix) => format!("{prefix}.{key}"),
+                        None => key,
+                    };
+                    flatten(Some(&key), value, messages, path)?;
+                }
+            }
+            serde_json::Value::String(message) => {
+                let key = prefix.ok_or_else(|| {
+                    CatalogParseError {
+                        path: path.to_owned(),
+                        line: None,
+                        message: "expected an object".to_owned(),
+                    }
+                })?;
+                messages.insert(key.to_owned(), message);
+            }
+            _ => {
+                return Err(CatalogParseError {
+                    path: path.to_owned(),
+                    line: None,
+                    message: format!(
+                        "expected a string or object for key `{}`",
+                        prefix.unwrap_or_default()
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    let value: serde_json::Value = serde_json::from_str(source)?;
+    let mut messages = HashMap::new();
+    flatten(None, value, &mut messages, path)?;
+
+    Ok(dist::CatalogMessages { messages })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{
+        parse_fluent,
+        parse_json,
+    };
+
+    #[test]
+    fn parses_fluent_messages() {
+        let source = "\
+# comment
+greeting = Hello, { $name }!
+
+description =
+    First line
+    second line
+button = Click
+    .title = Click me
+";
+        let messages = parse_fluent(source, Path::new("en.ftl")).unwrap().messages;
+
+        assert_eq!(messages["greeting"], "Hello, { $name }!");
+        assert_eq!(messages["description"], "First line\nsecond line");
+        assert_eq!(messages["button"], "Click");
+        assert_eq!(messages["button.title"], "Click me");
+        assert_eq!(messages.len(), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_fluent() {
+        for (source, line) in [
+            ("-term = Term", 1),
+            ("greeting = Hello\nno equals sign", 2),
+            ("  .title = Attribute", 1),
+            ("  continuation", 1),
+            ("gree ting = Hello", 1),
+        ] {
+            let error = parse_fluent(source, Path::new("en.ftl")).unwrap_err();
+            assert_eq!(error.line, Some(line), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn flattens_json_catalogs() {
+        let source =
+            r#"{ "greeting": "Hello", "menu": { "open": "Open", "file": { "save": "Save" } } }"#;
+        let messages = parse_json(source, Path::new("en.json")).unwrap().messages;
+
+        assert_eq!(messages["greeting"], "Hello");
+        assert_eq!(messages["menu.open"], "Open");
+        assert_eq!(messages["menu.file.save"], "Save");
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn rejects_json_that_isnt_messages() {
+        assert!(parse_json(r#""Hello""#, Path::new("en.json")).is_err());
+        assert!(parse_json(r#"{ "count": 3 }"#, Path::new("en.json")).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use kardashev_protocol::assets::AssetId;
+use palette::{
+    LinSrgb,
+    Mix,
+    Srgb,
+};
+
+use crate::assets::{
+    dist,
+    processor::ProcessContext,
+    source::{
+        ColorRamp,
+        Manifest,
+    },
+    Asset,
+    Error,
+};
+
+/// Number of entries in the lookup table of a color ramp.
+const LUT_SIZE: usize = 256;
+
+impl Asset for ColorRamp {
+    fn register_dist_type(dist_asset_types: &mut dist::AssetTypes) {
+        dist_asset_types.register::<dist::ColorRamp>();
+    }
+
+    fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self> {
+        &manifest.color_ramps
+    }
+
+    async fn process<'a, 'b: 'a>(
+        &'a self,
+        id: AssetId,
+        context: &'a mut ProcessContext<'b>,
+    ) -> Result<(), Error> {
+        if !context.processing(id) {
+            return Ok(());
+        }
+
+        // the stops are defined in the manifest itself.
+        let manifest_path = context.manifest_path.to_owned();
+        if context.source_path(id, &manifest_path)?.is_fresh() {
+            tracing::debug!(%id, "not modified since last build. skipping.");
+            return Ok(());
+        }
+
+        let stops = self
+            .stops
+            .iter()
+            .map(|stop| Ok::<_, Error>((stop.position, stop.color.as_srgb()?.into_linear())))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let lut = sample_lut(&stops, LUT_SIZE).map_err(|message| {
+            InvalidColorRamp {
+                id,
+                message: message.to_owned(),
+            }
+        })?;
+
+        context.dist_assets.insert(dist::ColorRamp {
+            id,
+            label: self.label.clone(),
+            build_time: context.build_time,
+            lut,
+        });
+
+        context.set_build_time(id);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid color ramp {id}: {message}")]
+pub struct InvalidColorRamp {
+    pub id: AssetId,
+    pub message: String,
+}
+
+/// Samples a ramp at `size` evenly spaced positions from 0 to 1.
+///
+/// The stops must be sorted by position. Colors are interpolated in linear
+/// space. Before the first and after the last stop, the color of that stop is
+/// used.
+fn sample_lut(stops: &[(f32, LinSrgb<f32>)], size: usize) -> Result<Vec<[u8; 3]>, &'static str> {
+    if stops.is_empty() {
+        return Err("no stops");
+    }
+    if stops.windows(2).any(|pair| pair[0].0 > pair[1].0) {
+        return Err("stops are not sorted by position");
+    }
+
+    let lut = (0..size)
+        .map(|i| {
+            let position = i as f32 / (size - 1).max(1) as f32;
+            let next = stops.partition_point(|(stop, _)| *stop < position);
+            let color = match (next.checked_sub(1), stops.get(next)) {
+                (Some(previous), Some(next)) => {
+                    let (from, from_color) = stops[previous];
+                    let (to, to_color) = *next;
+                    let t = if to > from {
+                        (position - from) / (to - from)
+                    }
+                    else {
+                        0.0
+                    };
+                    from_color.mix(to_color, t)
+                }
+                (Some(previous), None) => stops[previous].1,
+                (None, Some(next)) => next.1,
+                (None, None) => unreachable!("stops are not empty"),
+            };
+            Srgb::from_linear(color).into_format().into()
+        })
+        .collect();
+
+    Ok(lut)
+}
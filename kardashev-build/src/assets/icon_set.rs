@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use image::{
+    GenericImage,
+    ImageFormat,
+    Rgba,
+    RgbaImage,
+};
+use kardashev_protocol::assets::AssetId;
+use resvg::{
+    tiny_skia,
+    usvg,
+};
+
+use crate::assets::{
+    dist,
+    processor::ProcessContext,
+    source::{
+        IconSet,
+        Manifest,
+    },
+    Asset,
+    Error,
+};
+
+impl Asset for IconSet {
+    fn register_dist_type(dist_asset_types: &mut dist::AssetTypes) {
+        dist_asset_types.register::<dist::IconSet>();
+    }
+
+    fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self> {
+        &manifest.icon_sets
+    }
+
+    async fn process<'a, 'b: 'a>(
+        &'a self,
+        id: AssetId,
+        context: &'a mut ProcessContext<'b>,
+    ) -> Result<(), Error> {
+        if !context.processing(id) {
+            return Ok(());
+        }
+
+        let path = context.input_path(&self.path);
+
+        if context.source_path(id, &path)?.is_fresh() {
+            tracing::debug!(%id, "not modified since last build. skipping.");
+            return Ok(());
+        }
+
+        let mut sizes = self.sizes.clone();
+        sizes.sort_unstable();
+        sizes.dedup();
+        if sizes.first().map_or(true, |size| *size == 0) {
+            return Err(InvalidIconSet {
+                id,
+                message: "icon sizes must be positive".to_owned(),
+            }
+            .into());
+        }
+
+        let icons = read_icons(&path)?;
+        if icons.is_empty() {
+            tracing::warn!(%id, path = %path.display(), "icon set without icons");
+        }
+
+        // a row per size, with the icons in the same order in each row.
+        let width = icons.len().max(1) as u32 * sizes.last().unwrap();
+        let height = sizes.iter().sum();
+        let mut image = RgbaImage::new(width, height);
+        for (column, (name, svg_path)) in icons.iter().enumerate() {
+            tracing::debug!(%id, name, "rasterizing icon");
+            let tree = usvg::Tree::from_data(&std::fs::read(svg_path)?, &Default::default())
+                .map_err(|error| {
+                    InvalidIconSet {
+                        id,
+                        message: format!("{}: {error}", svg_path.display()),
+                    }
+                })?;
+
+            let mut y = 0;
+            for &size in &sizes {
+                image.copy_from(&rasterize(&tree, size), column as u32 * size, y)?;
+                y += size;
+            }
+        }
+
+        let filename = format!("{id}.png");
+        image.save_with_format(context.dist_path.join(&filename), ImageFormat::Png)?;
+        context.precompress(&filename)?;
+
+        context.dist_assets.insert(dist::IconSet {
+            id,
+            label: self.label.clone(),
+            build_time: context.build_time,
+            image: filename,
+            size: dist::TextureSize {
+                w: width,
+                h: height,
+            },
+            sizes,
+            icons: icons.into_iter().map(|(name, _)| name).collect(),
+        });
+
+        context.set_build_time(id);
+
+        Ok(())
+    }
+}
+
+/// Returns the names and paths of the SVG files in a directory, sorted by
+/// name.
+fn read_icons(path: &Path) -> Result<Vec<(String, PathBuf)>, Error> {
+    let mut icons = vec![];
+    for result in std::fs::read_dir(path)? {
+        let path = result?.path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "svg")
+        {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                icons.push((name.to_owned(), path.clone()));
+            }
+        }
+    }
+    icons.sort();
+    Ok(icons)
+}
+
+/// Renders an SVG into a square image, scaled to fit and centered.
+fn rasterize(tree: &usvg::Tree, size: u32) -> RgbaImage {
+    let mut pixmap = tiny_skia::Pixmap::new(size, size).expect("icon size is positive");
+
+    let tree_size = tree.size();
+    let scale = size as f32 / tree_size.width().max(tree_size.height());
+    let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        0.5 * (size as f32 - tree_size.width() * scale),
+        0.5 * (size as f32 - tree_size.height() * scale),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia uses premultiplied alpha
+    RgbaImage::from_fn(size, size, |x, y| {
+        let pixel = pixmap.pixel(x, y).expect("pixel in bounds").demultiply();
+        Rgba([pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()])
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid icon set {id}: {message}")]
+pub struct InvalidIconSet {
+    pub id: AssetId,
+    pub message: String,
+}
@@ -76,6 +76,7 @@ impl Asset for Material {
                         material: material_asset_id,
                         property,
                     });
+            context.check_settings(texture_asset_id, texture);
             texture.process(texture_asset_id, context).await?;
             freshness.and(context.source_asset(material_asset_id, texture_asset_id));
             Ok(texture_asset_id)
@@ -240,6 +241,7 @@ impl Asset for Material {
         )
         .await?;
 
+        freshness.and(context.built(id));
         if freshness.is_fresh() {
             tracing::debug!(%id, "not modified since last build. skipping.");
             return Ok(());
@@ -262,6 +264,7 @@ impl Asset for Material {
             dissolve,
             emissive_texture,
             emissive_color,
+            bloom: self.bloom,
             albedo_texture: None,
             metalness_texture: None,
             roughness_texture: None,
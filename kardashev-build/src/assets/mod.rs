@@ -1,14 +1,21 @@
 pub mod atlas;
 pub mod build_info;
+mod catalog;
+mod color_ramp;
+mod icon_set;
 mod material;
 mod mesh;
+mod model;
 pub mod processor;
 mod shader;
+mod sound;
 pub mod source;
+pub mod storage;
 mod texture;
 
 use std::{
     collections::HashMap,
+    fmt::Debug,
     future::Future,
     path::Path,
 };
@@ -27,7 +34,7 @@ use crate::assets::{
     source::Manifest,
 };
 
-pub trait Asset: Sized + Send + Sync + 'static {
+pub trait Asset: Debug + Sized + Send + Sync + 'static {
     fn register_dist_type(dist_asset_types: &mut dist::AssetTypes);
 
     fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self>;
@@ -56,8 +63,36 @@ pub enum Error {
     WgslParse(#[from] naga::front::wgsl::ParseError),
     Watch(#[from] crate::util::watch::Error),
     AssetParse(#[from] kardashev_protocol::assets::AssetParseError),
+    #[error("dist manifest can't be read, try a clean build")]
+    Manifest(#[from] kardashev_protocol::assets::ManifestError),
     NagaValidatation(#[from] naga::WithSpan<naga::valid::ValidationError>),
     InvalidColorName(#[from] crate::assets::source::InvalidColorName),
+    CatalogParse(#[from] crate::assets::catalog::CatalogParseError),
+    InvalidColorRamp(#[from] crate::assets::color_ramp::InvalidColorRamp),
+    InvalidIconSet(#[from] crate::assets::icon_set::InvalidIconSet),
+    #[error("image of {width}x{height} pixels doesn't fit into an atlas page of at most {max_page_size}x{max_page_size} pixels")]
+    AtlasImageTooLarge {
+        width: u32,
+        height: u32,
+        max_page_size: u32,
+    },
+    Gltf(#[from] gltf::Error),
+    #[error("invalid model {path}: {reason}")]
+    InvalidModel {
+        path: std::path::PathBuf,
+        reason: &'static str,
+    },
+    ObjectStore(#[from] object_store::Error),
+    ObjectStorePath(#[from] object_store::path::Error),
+    Cache(#[from] crate::cache::Error),
+    #[error("unsupported sound file: {path}")]
+    UnsupportedSoundFile {
+        path: std::path::PathBuf,
+    },
+    #[error("invalid storage path: {path}")]
+    InvalidStoragePath {
+        path: String,
+    },
 }
 
 pub async fn process(
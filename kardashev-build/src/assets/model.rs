@@ -0,0 +1,661 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    fs::File,
+    io::BufWriter,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use gltf::{
+    image::Format,
+    mesh::Mode,
+    texture::{
+        MagFilter,
+        MinFilter,
+        WrappingMode,
+    },
+};
+use image::{
+    DynamicImage,
+    GrayAlphaImage,
+    GrayImage,
+    ImageFormat,
+    Luma,
+    RgbImage,
+    RgbaImage,
+};
+use kardashev_protocol::assets::{
+    AssetId,
+    MeshData,
+    PrimitiveTopology,
+    TextureEdgeMode,
+    TextureFilter,
+    TextureFormat,
+    Vertex,
+    WindingOrder,
+};
+use nalgebra::{
+    Matrix3,
+    Matrix4,
+    Point3,
+    Vector3,
+};
+use palette::{
+    LinSrgb,
+    Srgb,
+};
+
+use crate::assets::{
+    build_info::{
+        GeneratedIdKey,
+        ModelPart,
+    },
+    dist,
+    processor::ProcessContext,
+    source::{
+        Manifest,
+        Model,
+        ModelHierarchy,
+    },
+    Asset,
+    Error,
+};
+
+impl Asset for Model {
+    fn register_dist_type(dist_asset_types: &mut dist::AssetTypes) {
+        dist_asset_types.register::<dist::Model>();
+    }
+
+    fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self> {
+        &manifest.models
+    }
+
+    async fn process<'a, 'b: 'a>(
+        &'a self,
+        id: AssetId,
+        context: &'a mut ProcessContext<'b>,
+    ) -> Result<(), Error> {
+        if !context.processing(id) {
+            return Ok(());
+        }
+
+        let path = context.input_path(&self.path);
+        let mut freshness = context.source_path(id, &path)?;
+
+        // `.gltf` files can refer to buffers and images in other files.
+        let gltf = gltf::Gltf::open(&path)?;
+        let base_path = path.parent().expect("model path has no parent directory");
+        for uri in external_uris(&gltf.document) {
+            freshness.and(context.source_path(id, base_path.join(uri))?);
+        }
+
+        // the parts from the last build are kept if the model is fresh.
+        let parts = context
+            .dist_assets
+            .get::<dist::Model>(id)
+            .map(|model| model_parts(model, context.dist_assets))
+            .unwrap_or_default();
+        for part in parts {
+            context.processed.insert(part);
+            freshness.and(context.built(part));
+        }
+
+        if freshness.is_fresh() {
+            tracing::debug!(%id, "not modified since last build. skipping.");
+            return Ok(());
+        }
+
+        let gltf::Gltf { document, blob } = gltf;
+        let buffers = gltf::import_buffers(&document, Some(base_path), blob)?;
+        let images = gltf::import_images(&document, Some(base_path), &buffers)?;
+
+        let scene = match self.scene {
+            Some(index) => document.scenes().nth(index),
+            None => {
+                document
+                    .default_scene()
+                    .or_else(|| document.scenes().next())
+            }
+        }
+        .ok_or_else(|| {
+            Error::InvalidModel {
+                path: path.clone(),
+                reason: "scene not found",
+            }
+        })?;
+
+        let mut import = Import {
+            model_id: id,
+            path: &path,
+            label: self.label.clone().unwrap_or_else(|| {
+                path.file_stem().map_or_else(
+                    || id.to_string(),
+                    |stem| stem.to_string_lossy().into_owned(),
+                )
+            }),
+            buffers: &buffers,
+            images: &images,
+            num_meshes: 0,
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+        };
+        let nodes = match self.hierarchy {
+            ModelHierarchy::Flatten => import.flatten(&scene, context)?,
+            ModelHierarchy::Preserve => import.preserve(&scene, context)?,
+        };
+
+        context.dist_assets.insert(dist::Model {
+            id,
+            label: self.label.clone(),
+            build_time: context.build_time,
+            nodes,
+        });
+
+        context.set_build_time(id);
+
+        Ok(())
+    }
+}
+
+/// Meshes, materials and textures that were imported for a model.
+fn model_parts(model: &dist::Model, dist_assets: &dist::Assets) -> Vec<AssetId> {
+    let mut parts = vec![];
+    for part in model.parts() {
+        parts.push(part);
+        if let Some(material) = dist_assets.get::<dist::Material>(part) {
+            parts.extend(
+                [
+                    material.normal_texture,
+                    material.diffuse_texture,
+                    material.emissive_texture,
+                    material.metalness_texture,
+                    material.roughness_texture,
+                ]
+                .into_iter()
+                .flatten(),
+            );
+        }
+    }
+    parts.sort();
+    parts.dedup();
+    parts
+}
+
+/// URIs of buffers and images in other files.
+fn external_uris(document: &gltf::Document) -> Vec<PathBuf> {
+    let buffers = document.buffers().filter_map(|buffer| {
+        match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => Some(uri),
+            gltf::buffer::Source::Bin => None,
+        }
+    });
+    let images = document.images().filter_map(|image| {
+        match image.source() {
+            gltf::image::Source::Uri { uri, .. } => Some(uri),
+            gltf::image::Source::View { .. } => None,
+        }
+    });
+    buffers
+        .chain(images)
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// State of importing a glTF file.
+struct Import<'a> {
+    model_id: AssetId,
+    path: &'a Path,
+    label: String,
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    num_meshes: usize,
+
+    /// Materials and textures that were already imported, by their index.
+    materials: HashMap<usize, AssetId>,
+    textures: HashMap<ModelPart, AssetId>,
+}
+
+impl<'a> Import<'a> {
+    fn flatten(
+        &mut self,
+        scene: &gltf::Scene,
+        context: &mut ProcessContext,
+    ) -> Result<Vec<dist::ModelNode>, Error> {
+        let mut by_material = BTreeMap::<Option<usize>, (gltf::Material, Triangles)>::new();
+
+        let mut stack = scene
+            .nodes()
+            .map(|node| (node, Matrix4::identity()))
+            .collect::<Vec<_>>();
+        while let Some((node, parent_transform)) = stack.pop() {
+            let transform = parent_transform * Matrix4::from(node.transform().matrix());
+            for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+                if let Some(triangles) = self.read_primitive(&primitive, &transform) {
+                    let material = primitive.material();
+                    by_material
+                        .entry(material.index())
+                        .or_insert_with(|| (material, Triangles::default()))
+                        .1
+                        .append(triangles);
+                }
+            }
+            stack.extend(node.children().map(|child| (child, transform)));
+        }
+
+        let mut primitives = vec![];
+        for (index, (material, triangles)) in by_material {
+            let material = index
+                .map(|_| self.import_material(&material, context))
+                .transpose()?;
+            let mesh = self.write_mesh(triangles, None, context)?;
+            primitives.push(dist::ModelPrimitive { mesh, material });
+        }
+
+        Ok(vec![dist::ModelNode {
+            name: None,
+            parent: None,
+            transform: matrix_to_array(&Matrix4::identity()),
+            primitives,
+        }])
+    }
+
+    fn preserve(
+        &mut self,
+        scene: &gltf::Scene,
+        context: &mut ProcessContext,
+    ) -> Result<Vec<dist::ModelNode>, Error> {
+        let mut nodes = vec![];
+
+        // nodes are added before their children are pushed, so parents come first.
+        let mut stack = scene.nodes().map(|node| (node, None)).collect::<Vec<_>>();
+        while let Some((node, parent)) = stack.pop() {
+            let mut primitives = vec![];
+            for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+                let Some(triangles) = self.read_primitive(&primitive, &Matrix4::identity())
+                else {
+                    continue;
+                };
+                let material = primitive
+                    .material()
+                    .index()
+                    .map(|_| self.import_material(&primitive.material(), context))
+                    .transpose()?;
+                let mesh = self.write_mesh(triangles, node.name(), context)?;
+                primitives.push(dist::ModelPrimitive { mesh, material });
+            }
+
+            let index = nodes.len();
+            nodes.push(dist::ModelNode {
+                name: node.name().map(ToOwned::to_owned),
+                parent,
+                transform: matrix_to_array(&Matrix4::from(node.transform().matrix())),
+                primitives,
+            });
+            stack.extend(node.children().map(|child| (child, Some(index))));
+        }
+
+        Ok(nodes)
+    }
+
+    /// Reads the triangles of a primitive, and transforms them. Primitives
+    /// that aren't triangle lists are skipped.
+    fn read_primitive(
+        &self,
+        primitive: &gltf::Primitive,
+        transform: &Matrix4<f32>,
+    ) -> Option<Triangles> {
+        if primitive.mode() != Mode::Triangles {
+            tracing::warn!(path = %self.path.display(), mode = ?primitive.mode(), "skipping primitive that isn't a triangle list");
+            return None;
+        }
+
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let positions = reader.read_positions()?.collect::<Vec<_>>();
+        let num_vertices = positions.len();
+        let normals = reader
+            .read_normals()
+            .map(|normals| normals.collect::<Vec<_>>())
+            .unwrap_or_else(|| vec![[0.0; 3]; num_vertices]);
+        let tex_coords = reader
+            .read_tex_coords(0)
+            .map(|tex_coords| tex_coords.into_f32().collect::<Vec<_>>())
+            .unwrap_or_else(|| vec![[0.0; 2]; num_vertices]);
+        let mut indices = reader
+            .read_indices()
+            .map(|indices| indices.into_u32().collect::<Vec<_>>())
+            .unwrap_or_else(|| (0..num_vertices as u32).collect());
+
+        let linear = transform.fixed_view::<3, 3>(0, 0).into_owned();
+        let normal_matrix = linear
+            .try_inverse()
+            .map_or_else(Matrix3::identity, |inverse| inverse.transpose());
+
+        let vertices = positions
+            .into_iter()
+            .zip(normals)
+            .zip(tex_coords)
+            .map(|((position, normal), tex_coords)| {
+                let normal = (normal_matrix * Vector3::from(normal))
+                    .try_normalize(0.0)
+                    .unwrap_or_default();
+                Vertex {
+                    position: transform.transform_point(&Point3::from(position)).into(),
+                    tex_coords,
+                    normal: normal.into(),
+                    tangent: [0.0; 3],
+                    bitangent: [0.0; 3],
+                }
+            })
+            .collect();
+
+        // mirroring transforms flip the winding order.
+        if linear.determinant() < 0.0 {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        Some(Triangles { vertices, indices })
+    }
+
+    fn write_mesh(
+        &mut self,
+        triangles: Triangles,
+        name: Option<&str>,
+        context: &mut ProcessContext,
+    ) -> Result<AssetId, Error> {
+        let index = self.num_meshes;
+        self.num_meshes += 1;
+        let id = self.part_id(ModelPart::Mesh(index), context);
+
+        let indices = triangles
+            .indices
+            .into_iter()
+            .map(u16::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                Error::InvalidModel {
+                    path: self.path.to_owned(),
+                    reason: "mesh has more vertices than 16 bit indices can address",
+                }
+            })?;
+        let mesh = MeshData {
+            primitive_topology: PrimitiveTopology::TriangleList,
+            winding_order: WindingOrder::CounterClockwise,
+            has_binormals: false,
+            indices,
+            vertices: triangles.vertices,
+        }
+        .with_binormals();
+
+        let filename = format!("{id}.mesh");
+        let mut writer = BufWriter::new(File::create(context.dist_path.join(&filename))?);
+        rmp_serde::encode::write(&mut writer, &mesh)?;
+
+        context.dist_assets.insert(dist::Mesh {
+            id,
+            label: Some(self.part_label(name, "mesh", index)),
+            build_time: context.build_time,
+            mesh: filename,
+        });
+        context.set_build_time(id);
+
+        Ok(id)
+    }
+
+    fn import_material(
+        &mut self,
+        material: &gltf::Material,
+        context: &mut ProcessContext,
+    ) -> Result<AssetId, Error> {
+        let index = material.index().expect("default material is not imported");
+        if let Some(id) = self.materials.get(&index) {
+            return Ok(*id);
+        }
+        let id = self.part_id(ModelPart::Material(index), context);
+
+        let pbr = material.pbr_metallic_roughness();
+        let base_color_texture = pbr
+            .base_color_texture()
+            .map(|info| {
+                self.import_texture(&info.texture(), TextureFormat::Rgba8UnormSrgb, context)
+            })
+            .transpose()?;
+        let normal_texture = material
+            .normal_texture()
+            .map(|info| self.import_texture(&info.texture(), TextureFormat::Rgba8Unorm, context))
+            .transpose()?;
+        let emissive_texture = material
+            .emissive_texture()
+            .map(|info| {
+                self.import_texture(&info.texture(), TextureFormat::Rgba8UnormSrgb, context)
+            })
+            .transpose()?;
+        let (metalness_texture, roughness_texture) = pbr
+            .metallic_roughness_texture()
+            .map(|info| self.import_metallic_roughness(&info.texture(), context))
+            .transpose()?
+            .unzip();
+
+        let [red, green, blue, alpha] = pbr.base_color_factor();
+        let [emissive_red, emissive_green, emissive_blue] = material.emissive_factor();
+        let emissive_color = (emissive_red > 0.0 || emissive_green > 0.0 || emissive_blue > 0.0)
+            .then(|| Srgb::from_linear(LinSrgb::new(emissive_red, emissive_green, emissive_blue)));
+
+        context.dist_assets.insert(dist::Material {
+            id,
+            label: Some(self.part_label(material.name(), "material", index)),
+            build_time: context.build_time,
+            normal_texture,
+            ambient_texture: None,
+            ambient_color: None,
+            diffuse_texture: base_color_texture,
+            diffuse_color: Some(Srgb::from_linear(LinSrgb::new(red, green, blue))),
+            specular_texture: None,
+            specular_color: None,
+            shininess_texture: None,
+            shininess: None,
+            dissolve_texture: None,
+            dissolve: (alpha < 1.0).then_some(alpha),
+            emissive_texture,
+            emissive_color,
+            bloom: None,
+            albedo_texture: base_color_texture,
+            metalness_texture,
+            roughness_texture,
+        });
+        context.set_build_time(id);
+
+        self.materials.insert(index, id);
+        Ok(id)
+    }
+
+    fn import_texture(
+        &mut self,
+        texture: &gltf::Texture,
+        format: TextureFormat,
+        context: &mut ProcessContext,
+    ) -> Result<AssetId, Error> {
+        let part = ModelPart::Texture(texture.index());
+        if let Some(id) = self.textures.get(&part) {
+            return Ok(*id);
+        }
+
+        let image = self.read_image(texture)?;
+        self.write_texture(part, texture, image, format, context)
+    }
+
+    /// Splits the metallic-roughness texture into a metalness texture (blue
+    /// channel) and a roughness texture (green channel).
+    fn import_metallic_roughness(
+        &mut self,
+        texture: &gltf::Texture,
+        context: &mut ProcessContext,
+    ) -> Result<(AssetId, AssetId), Error> {
+        let metalness = ModelPart::Metalness(texture.index());
+        let roughness = ModelPart::Roughness(texture.index());
+        if let (Some(metalness), Some(roughness)) =
+            (self.textures.get(&metalness), self.textures.get(&roughness))
+        {
+            return Ok((*metalness, *roughness));
+        }
+
+        let metallic_roughness = self.read_image(texture)?.to_rgb8();
+        let channel = |channel: usize| {
+            DynamicImage::from(GrayImage::from_fn(
+                metallic_roughness.width(),
+                metallic_roughness.height(),
+                |x, y| Luma([metallic_roughness.get_pixel(x, y)[channel]]),
+            ))
+        };
+        let metalness_id = self.write_texture(
+            metalness,
+            texture,
+            channel(2),
+            TextureFormat::Rgba8Unorm,
+            context,
+        )?;
+        let roughness_id = self.write_texture(
+            roughness,
+            texture,
+            channel(1),
+            TextureFormat::Rgba8Unorm,
+            context,
+        )?;
+        Ok((metalness_id, roughness_id))
+    }
+
+    fn read_image(&self, texture: &gltf::Texture) -> Result<DynamicImage, Error> {
+        let data = &self.images[texture.source().index()];
+        let (width, height, pixels) = (data.width, data.height, data.pixels.clone());
+        let image = match data.format {
+            Format::R8 => GrayImage::from_raw(width, height, pixels).map(DynamicImage::from),
+            Format::R8G8 => GrayAlphaImage::from_raw(width, height, pixels).map(DynamicImage::from),
+            Format::R8G8B8 => RgbImage::from_raw(width, height, pixels).map(DynamicImage::from),
+            Format::R8G8B8A8 => RgbaImage::from_raw(width, height, pixels).map(DynamicImage::from),
+            _ => None,
+        };
+        image.ok_or_else(|| {
+            Error::InvalidModel {
+                path: self.path.to_owned(),
+                reason: "only images with 8 bits per channel are supported",
+            }
+        })
+    }
+
+    fn write_texture(
+        &mut self,
+        part: ModelPart,
+        texture: &gltf::Texture,
+        image: DynamicImage,
+        format: TextureFormat,
+        context: &mut ProcessContext,
+    ) -> Result<AssetId, Error> {
+        let id = self.part_id(part, context);
+
+        let filename = format!("{id}.png");
+        let mut writer = BufWriter::new(File::create(context.dist_path.join(&filename))?);
+        image.write_to(&mut writer, ImageFormat::Png)?;
+
+        let sampler = texture.sampler();
+        let (min_filter, mipmap_filter) = sampler.min_filter().map_or((None, None), |filter| {
+            match filter {
+                MinFilter::Nearest => (Some(TextureFilter::Nearest), None),
+                MinFilter::Linear => (Some(TextureFilter::Linear), None),
+                MinFilter::NearestMipmapNearest => {
+                    (Some(TextureFilter::Nearest), Some(TextureFilter::Nearest))
+                }
+                MinFilter::LinearMipmapNearest => {
+                    (Some(TextureFilter::Linear), Some(TextureFilter::Nearest))
+                }
+                MinFilter::NearestMipmapLinear => {
+                    (Some(TextureFilter::Nearest), Some(TextureFilter::Linear))
+                }
+                MinFilter::LinearMipmapLinear => {
+                    (Some(TextureFilter::Linear), Some(TextureFilter::Linear))
+                }
+            }
+        });
+
+        context.dist_assets.insert(dist::Texture {
+            id,
+            label: Some(self.part_label(texture.name(), "texture", texture.index())),
+            build_time: context.build_time,
+            image: filename,
+            size: dist::TextureSize {
+                w: image.width(),
+                h: image.height(),
+            },
+            format,
+            crop: None,
+            u_edge_mode: Some(edge_mode(sampler.wrap_s())),
+            v_edge_mode: Some(edge_mode(sampler.wrap_t())),
+            mag_filter: sampler.mag_filter().map(|filter| {
+                match filter {
+                    MagFilter::Nearest => TextureFilter::Nearest,
+                    MagFilter::Linear => TextureFilter::Linear,
+                }
+            }),
+            min_filter,
+            mipmap_filter,
+            anisotropy: None,
+            mipmaps: true,
+        });
+        context.set_build_time(id);
+
+        self.textures.insert(part, id);
+        Ok(id)
+    }
+
+    fn part_id(&self, part: ModelPart, context: &mut ProcessContext) -> AssetId {
+        let id = context.build_info.generate_id(GeneratedIdKey::ModelPart {
+            model: self.model_id,
+            part,
+        });
+        context.processed.insert(id);
+        id
+    }
+
+    fn part_label(&self, name: Option<&str>, kind: &str, index: usize) -> String {
+        match name {
+            Some(name) => format!("{}: {name}", self.label),
+            None => format!("{}: {kind} {index}", self.label),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Triangles {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Triangles {
+    fn append(&mut self, other: Triangles) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.into_iter().map(|index| index + offset));
+    }
+}
+
+fn edge_mode(wrapping_mode: WrappingMode) -> TextureEdgeMode {
+    match wrapping_mode {
+        WrappingMode::ClampToEdge => TextureEdgeMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => TextureEdgeMode::MirrorRepeat,
+        WrappingMode::Repeat => TextureEdgeMode::Repeat,
+    }
+}
+
+fn matrix_to_array(matrix: &Matrix4<f32>) -> [f32; 16] {
+    matrix
+        .as_slice()
+        .try_into()
+        .expect("convert model matrix to array")
+}
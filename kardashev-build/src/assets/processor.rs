@@ -1,10 +1,14 @@
 use std::{
     any::type_name,
     collections::{
+        BTreeMap,
         HashMap,
         HashSet,
     },
-    fmt::Debug,
+    fmt::{
+        Debug,
+        Display,
+    },
     fs::File,
     future::Future,
     io::{
@@ -26,6 +30,10 @@ use chrono::{
     Utc,
 };
 use image::ImageFormat;
+use sha2::{
+    Digest,
+    Sha256,
+};
 use tracing::Instrument;
 use walkdir::WalkDir;
 
@@ -34,6 +42,7 @@ use crate::{
         atlas::{
             AtlasBuilder,
             AtlasBuilderId,
+            DEFAULT_MAX_PAGE_SIZE,
         },
         build_info::{
             BuildInfo,
@@ -41,11 +50,18 @@ use crate::{
         },
         dist,
         source::Manifest,
+        storage::DistStorage,
         texture::UnfinishedTexture,
         Asset,
         AssetId,
         Error,
     },
+    cache::{
+        copy_directory,
+        BuildCache,
+        CacheKey,
+        CacheKeyBuilder,
+    },
     util::{
         path_modified_timestamp,
         watch::{
@@ -62,7 +78,10 @@ pub struct Processor {
     dist_path: PathBuf,
     build_info: BuildInfo,
     precompress: HashSet<CompressionFormat>,
+    max_atlas_page_size: u32,
     watch_sources: Option<WatchSources>,
+    storage: Option<DistStorage>,
+    cache: Option<BuildCache>,
 }
 
 impl Processor {
@@ -85,13 +104,21 @@ impl Processor {
                 DynAssetType::new::<source::Material>(),
                 DynAssetType::new::<source::Texture>(),
                 DynAssetType::new::<source::Mesh>(),
+                DynAssetType::new::<source::Model>(),
                 DynAssetType::new::<source::Shader>(),
+                DynAssetType::new::<source::Catalog>(),
+                DynAssetType::new::<source::ColorRamp>(),
+                DynAssetType::new::<source::Sound>(),
+                DynAssetType::new::<source::IconSet>(),
             ],
             source: Source::default(),
             dist_path: dist_path.to_owned(),
             build_info,
             precompress: HashSet::new(),
+            max_atlas_page_size: DEFAULT_MAX_PAGE_SIZE,
             watch_sources: None,
+            storage: None,
+            cache: None,
         })
     }
 
@@ -106,6 +133,20 @@ impl Processor {
         self.watch_sources.as_mut()?.next_changes(debounce).await
     }
 
+    /// Syncs the dist directory to `storage` after every build.
+    pub fn sync_to_storage(&mut self, storage: DistStorage) {
+        self.storage = Some(storage);
+    }
+
+    /// Restores builds from `cache` if the sources haven't changed, and stores
+    /// every build in it.
+    ///
+    /// Builds are not restored when watching source files, because the
+    /// watcher learns which files to watch while processing.
+    pub fn use_cache(&mut self, cache: BuildCache) {
+        self.cache = Some(cache);
+    }
+
     pub fn register_asset_type<A: Asset>(&mut self) {
         self.asset_types.push(DynAssetType::new::<A>());
     }
@@ -114,6 +155,12 @@ impl Processor {
         self.precompress.insert(format);
     }
 
+    /// Sets the maximum width and height of texture atlas pages. Textures that
+    /// don't fit into a page anymore spill into another one.
+    pub fn set_max_atlas_page_size(&mut self, max_page_size: u32) {
+        self.max_atlas_page_size = max_page_size;
+    }
+
     pub fn add_directory(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
         for result in WalkDir::new(path) {
             let entry = result?;
@@ -164,9 +211,29 @@ impl Processor {
         let build_time = Utc::now();
         let mut processed = HashSet::new();
         let mut changed = HashSet::new();
+        let mut reasons = HashMap::new();
         let mut atlas_builders = HashMap::new();
         let mut watch_sources = self.watch_sources.as_ref().map(|_| HashSet::new());
 
+        let cache_key = self.cache.is_some().then(|| self.cache_key()).transpose()?;
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            if self.watch_sources.is_none() && !clean && cache.contains(key) {
+                if self.dist_path.exists() {
+                    std::fs::remove_dir_all(&self.dist_path)?;
+                }
+                cache.restore(key, &self.dist_path)?;
+
+                let reader = BufReader::new(File::open(self.dist_path.join("build_info.json"))?);
+                self.build_info = serde_json::from_reader(reader)?;
+
+                if let Some(storage) = &self.storage {
+                    storage.sync(&self.dist_path).await?;
+                }
+
+                return Ok(Processed::default());
+            }
+        }
+
         // create dist path, if it doesn't exist already
         std::fs::create_dir_all(&self.dist_path)?;
 
@@ -179,8 +246,7 @@ impl Processor {
         let path = self.dist_path.join("assets.json");
         let mut dist_assets = (path.exists() && !clean)
             .then(|| {
-                let reader = BufReader::new(File::open(&path)?);
-                let dist_manifest: dist::Manifest = serde_json::from_reader(reader)?;
+                let dist_manifest = dist::Manifest::from_json(&std::fs::read(&path)?)?;
                 let mut dist_asset_types = dist::AssetTypes::default();
                 dist_asset_types.with_builtin();
                 for asset_type in &self.asset_types {
@@ -190,6 +256,7 @@ impl Processor {
             })
             .transpose()?
             .unwrap_or_default();
+        let previous_blob = dist_assets.blob();
 
         for (path, manifest) in &self.source.manifests {
             tracing::info!(path = %path.display(), "processing manifest file");
@@ -205,9 +272,11 @@ impl Processor {
                         dist_assets: &mut dist_assets,
                         build_info: &mut self.build_info,
                         atlas_builders: &mut atlas_builders,
+                        max_atlas_page_size: self.max_atlas_page_size,
                         build_time,
                         processed: &mut processed,
                         changed: &mut changed,
+                        reasons: &mut reasons,
                         precompress: &self.precompress,
                         watch_sources: watch_sources.as_mut(),
                     };
@@ -226,10 +295,12 @@ impl Processor {
 
         // remove assets that were not generated this time
         let dist_asset_ids = dist_assets.all_asset_ids().collect::<Vec<_>>();
+        let mut removed = HashSet::new();
         for asset_id in dist_asset_ids {
             if !processed.contains(&asset_id) {
                 tracing::info!(%asset_id, "removing old asset");
                 dist_assets.remove(asset_id);
+                removed.insert(asset_id);
             }
         }
 
@@ -246,36 +317,49 @@ impl Processor {
         for (atlas_builder_id, atlas_builder) in atlas_builders {
             tracing::info!(%atlas_builder_id, "building texture atlas");
 
-            let atlas = atlas_builder.finish()?;
-            let filename = format!("atlas_{atlas_builder_id}.png");
-            files.insert(PathBuf::from(&filename));
-            let path = self.dist_path.join(&filename);
-            let mut writer = BufWriter::new(File::create(&path)?);
-            atlas.image.write_to(&mut writer, ImageFormat::Png)?;
-
-            for (data, crop) in atlas.allocations {
-                dist_assets.insert(dist::Texture {
-                    id: data.id,
-                    label: data.label,
-                    build_time,
-                    image: filename.clone(),
-                    size: dist::TextureSize {
-                        w: atlas.image_size[0],
-                        h: atlas.image_size[1],
-                    },
-                    format: data.format,
-                    crop: Some(crop),
-                    u_edge_mode: None,
-                    v_edge_mode: None,
-                });
+            for (page, atlas) in atlas_builder.finish()?.into_iter().enumerate() {
+                let filename = format!("atlas_{atlas_builder_id}_{page}.png");
+                files.insert(PathBuf::from(&filename));
+                let path = self.dist_path.join(&filename);
+                let mut writer = BufWriter::new(File::create(&path)?);
+                atlas.image.write_to(&mut writer, ImageFormat::Png)?;
+
+                for (data, crop) in atlas.allocations {
+                    dist_assets.insert(dist::Texture {
+                        id: data.id,
+                        label: data.label,
+                        build_time,
+                        image: filename.clone(),
+                        size: dist::TextureSize {
+                            w: atlas.image_size[0],
+                            h: atlas.image_size[1],
+                        },
+                        format: data.format,
+                        crop: Some(crop),
+                        // edge modes don't make sense for textures in an atlas
+                        u_edge_mode: None,
+                        v_edge_mode: None,
+                        mag_filter: data.sampler.mag_filter,
+                        min_filter: data.sampler.min_filter,
+                        mipmap_filter: data.sampler.mipmap_filter,
+                        anisotropy: data.sampler.anisotropy,
+                        mipmaps: data.mipmaps,
+                    });
+                }
             }
         }
 
         // write dist manifest
-        let dist_manifest = dist::Manifest {
-            build_time,
-            assets: dist_assets.blob(),
+        let blob = dist_assets.blob();
+        let manifest_diff = ManifestDiff::new(&previous_blob, &blob)?;
+        let added = manifest_diff.added.into_iter().collect::<HashSet<_>>();
+        let modified = manifest_diff.changed.into_iter().collect::<HashSet<_>>();
+        let diff = dist::AssetsDiff {
+            added: blob.filter(|asset_id| added.contains(&asset_id)),
+            modified: blob.filter(|asset_id| modified.contains(&asset_id)),
+            removed: manifest_diff.removed,
         };
+        let dist_manifest = dist::Manifest::new(build_time, blob);
         files.insert(PathBuf::from("assets.json"));
         let path = self.dist_path.join("assets.json");
         tracing::info!(path = %path.display(), "writing dist manifest");
@@ -299,13 +383,232 @@ impl Processor {
             }
         }
 
-        Ok(Processed { changed })
+        if let (Some(cache), Some(key)) = (&self.cache, &cache_key) {
+            cache.store(key, &self.dist_path)?;
+        }
+
+        if let Some(storage) = &self.storage {
+            storage.sync(&self.dist_path).await?;
+        }
+
+        Ok(Processed {
+            changed,
+            reasons,
+            removed,
+            diff,
+        })
+    }
+
+    /// Processes the assets into a temporary copy of the dist directory, and
+    /// reports which assets would be rebuilt and how the dist manifest would
+    /// change.
+    ///
+    /// Neither the dist directory, nor the storage or the cache are written
+    /// to.
+    #[tracing::instrument(skip(self))]
+    pub async fn dry_run(&mut self, clean: bool) -> Result<DryRun, Error> {
+        let temp_path =
+            std::env::temp_dir().join(format!("kardashev-dry-run-{}", std::process::id()));
+        if temp_path.exists() {
+            std::fs::remove_dir_all(&temp_path)?;
+        }
+        if self.dist_path.exists() {
+            copy_directory(&self.dist_path, &temp_path, |_| true)?;
+        }
+
+        let dist_path = std::mem::replace(&mut self.dist_path, temp_path.clone());
+        let build_info = self.build_info.clone();
+        let storage = self.storage.take();
+        let cache = self.cache.take();
+
+        let result = self.process(clean).await;
+
+        self.dist_path = dist_path;
+        self.build_info = build_info;
+        self.storage = storage;
+        self.cache = cache;
+
+        let result = result.and_then(|processed| {
+            let before = read_dist_manifest(&self.dist_path)?;
+            let after = read_dist_manifest(&temp_path)?;
+            Ok(DryRun {
+                processed,
+                diff: ManifestDiff::new(&before, &after)?,
+            })
+        });
+        std::fs::remove_dir_all(&temp_path)?;
+        result
+    }
+
+    /// Computes the cache key from the asset types and the directories that
+    /// contain the manifests.
+    ///
+    /// Source files outside of these directories are not part of the key.
+    fn cache_key(&self) -> Result<CacheKey, Error> {
+        let mut key = CacheKeyBuilder::new("assets");
+
+        for asset_type in &self.asset_types {
+            key.with_value("asset_type", asset_type.type_name());
+        }
+
+        let mut precompress = self
+            .precompress
+            .iter()
+            .map(|format| format!("{format:?}"))
+            .collect::<Vec<_>>();
+        precompress.sort();
+        key.with_value("precompress", precompress.join(","));
+        key.with_value(
+            "max_atlas_page_size",
+            self.max_atlas_page_size.to_le_bytes(),
+        );
+
+        // directories that contain manifests, without the ones that are already
+        // contained in another one.
+        let mut directories = self
+            .source
+            .manifests
+            .iter()
+            .filter_map(|(path, _)| path.parent())
+            .collect::<Vec<_>>();
+        directories.sort();
+        directories.dedup();
+        let roots = directories
+            .iter()
+            .filter(|directory| {
+                !directories
+                    .iter()
+                    .any(|other| other != *directory && directory.starts_with(other))
+            })
+            .collect::<Vec<_>>();
+        for root in roots {
+            key.with_directory(root, &[])?;
+        }
+
+        Ok(key.finish())
     }
 }
 
-#[derive(Clone, Debug)]
+/// Returns the assets in the dist manifest in `dist_path`, if there is one.
+fn read_dist_manifest(dist_path: &Path) -> Result<dist::AssetsBlob, Error> {
+    let path = dist_path.join("assets.json");
+    if !path.exists() {
+        return Ok(Default::default());
+    }
+    let dist_manifest = dist::Manifest::from_json(&std::fs::read(&path)?)?;
+    Ok(dist_manifest.assets)
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Processed {
     pub changed: HashSet<AssetId>,
+
+    /// Why the assets were rebuilt.
+    pub reasons: HashMap<AssetId, Vec<RebuildReason>>,
+
+    /// Assets that were removed from the dist manifest, because they're not
+    /// in the source manifests anymore.
+    pub removed: HashSet<AssetId>,
+
+    /// How the dist manifest changed, with the records of the added and
+    /// modified assets, so that clients can reload only those.
+    pub diff: dist::AssetsDiff,
+}
+
+/// Why an asset is rebuilt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RebuildReason {
+    /// The asset has not been built before.
+    NotBuilt,
+    /// A source file has been modified since the last build.
+    ChangedInput { path: PathBuf },
+    /// An asset this one depends on has been rebuilt since.
+    ChangedDependency { dependency: AssetId },
+    /// The asset's settings in the source manifest have changed.
+    ChangedSettings,
+    /// The asset is missing from the dist manifest, or some of its files are
+    /// missing from the dist directory.
+    MissingOutput,
+}
+
+impl Display for RebuildReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotBuilt => write!(f, "not built yet"),
+            Self::ChangedInput { path } => write!(f, "changed input: {}", path.display()),
+            Self::ChangedDependency { dependency } => {
+                write!(f, "changed dependency: {dependency}")
+            }
+            Self::ChangedSettings => write!(f, "changed settings"),
+            Self::MissingOutput => write!(f, "missing output"),
+        }
+    }
+}
+
+/// Result of a [dry run](Processor::dry_run).
+#[derive(Clone, Debug)]
+pub struct DryRun {
+    pub processed: Processed,
+    pub diff: ManifestDiff,
+}
+
+/// Differences between two dist manifests.
+///
+/// Build times are ignored, so only assets whose outputs changed are
+/// `changed`.
+#[derive(Clone, Debug, Default)]
+pub struct ManifestDiff {
+    pub added: Vec<AssetId>,
+    pub removed: Vec<AssetId>,
+    pub changed: Vec<AssetId>,
+}
+
+impl ManifestDiff {
+    pub fn new(before: &dist::AssetsBlob, after: &dist::AssetsBlob) -> Result<Self, Error> {
+        let before = blob_entries(before)?;
+        let after = blob_entries(after)?;
+        let mut diff = Self::default();
+
+        for (id, entry) in &after {
+            match before.get(id) {
+                None => diff.added.push(*id),
+                Some(previous) if previous != entry => diff.changed.push(*id),
+                Some(_) => {}
+            }
+        }
+        diff.removed = before
+            .keys()
+            .filter(|id| !after.contains_key(id))
+            .copied()
+            .collect();
+
+        Ok(diff)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The serialized assets by ID, without their build times.
+fn blob_entries(blob: &dist::AssetsBlob) -> Result<BTreeMap<AssetId, serde_json::Value>, Error> {
+    #[derive(serde::Deserialize)]
+    struct Entry {
+        id: AssetId,
+        #[serde(flatten)]
+        rest: serde_json::Map<String, serde_json::Value>,
+    }
+
+    let entries: Vec<Entry> = serde_json::from_value(serde_json::to_value(blob)?)?;
+    Ok(entries
+        .into_iter()
+        .map(|mut entry| {
+            if let Some(serde_json::Value::Object(data)) = entry.rest.get_mut("data") {
+                data.remove("build_time");
+            }
+            (entry.id, serde_json::Value::Object(entry.rest))
+        })
+        .collect())
 }
 
 #[derive(Clone, Debug, Default)]
@@ -330,9 +633,11 @@ pub struct ProcessContext<'a> {
     pub dist_assets: &'a mut dist::Assets,
     pub build_info: &'a mut BuildInfo,
     pub atlas_builders: &'a mut HashMap<AtlasBuilderId, AtlasBuilder<UnfinishedTexture>>,
+    pub max_atlas_page_size: u32,
     pub build_time: DateTime<Utc>,
     pub processed: &'a mut HashSet<AssetId>,
     pub changed: &'a mut HashSet<AssetId>,
+    pub reasons: &'a mut HashMap<AssetId, Vec<RebuildReason>>,
     pub precompress: &'a HashSet<CompressionFormat>,
     pub watch_sources: Option<&'a mut HashSet<PathBuf>>,
 }
@@ -350,11 +655,63 @@ impl<'a> ProcessContext<'a> {
         self.changed.insert(id);
     }
 
-    fn freshness(&self, id: AssetId, time: DateTime<Utc>) -> Freshness {
-        let build_time = self.build_info.build_times.get(&id);
-        build_time
-            .and_then(|build_time| (build_time > &time).then_some(Freshness::Fresh))
-            .unwrap_or(Freshness::Stale)
+    fn rebuild_reason(&mut self, id: AssetId, reason: RebuildReason) {
+        let reasons = self.reasons.entry(id).or_default();
+        if !reasons.contains(&reason) {
+            reasons.push(reason);
+        }
+    }
+
+    /// Whether the asset and all its files are in the dist directory.
+    fn has_output(&self, id: AssetId) -> bool {
+        self.dist_assets.files(id).map_or(false, |files| {
+            files
+                .into_iter()
+                .all(|file| self.dist_path.join(file).exists())
+        })
+    }
+
+    fn freshness(
+        &mut self,
+        id: AssetId,
+        time: DateTime<Utc>,
+        reason: impl FnOnce() -> RebuildReason,
+    ) -> Freshness {
+        let Some(build_time) = self.build_info.build_times.get(&id).copied()
+        else {
+            // the build time is also cleared when the settings changed.
+            if !self.reasons.contains_key(&id) {
+                self.rebuild_reason(id, RebuildReason::NotBuilt);
+            }
+            return Freshness::Stale;
+        };
+
+        if !self.has_output(id) {
+            self.rebuild_reason(id, RebuildReason::MissingOutput);
+            Freshness::Stale
+        }
+        else if build_time > time {
+            Freshness::Fresh
+        }
+        else {
+            self.rebuild_reason(id, reason());
+            Freshness::Stale
+        }
+    }
+
+    /// Compares the hash of the asset's settings to the one from the last
+    /// build, and forgets the build time if they changed.
+    ///
+    /// This is done for all assets in the source manifests, but must be called
+    /// for assets that are defined inline.
+    pub fn check_settings(&mut self, id: AssetId, asset: &impl Debug) {
+        let hash = format!("{:x}", Sha256::digest(format!("{asset:?}")));
+        let previous = self.build_info.settings.insert(id, hash.clone());
+        if previous.is_some_and(|previous| previous != hash)
+            && self.build_info.build_times.remove(&id).is_some()
+        {
+            self.rebuild_reason(id, RebuildReason::ChangedSettings);
+        }
     }
 
     pub fn source_path(&mut self, id: AssetId, path: impl AsRef<Path>) -> Result<Freshness, Error> {
@@ -365,18 +722,32 @@ impl<'a> ProcessContext<'a> {
         }
 
         if let Some(modified_time) = path_modified_timestamp(path, std::cmp::max)? {
-            Ok(self.freshness(id, modified_time))
+            Ok(self.freshness(id, modified_time, || {
+                RebuildReason::ChangedInput {
+                    path: path.to_owned(),
+                }
+            }))
         }
         else {
             Ok(Freshness::Fresh)
         }
     }
 
-    pub fn source_asset(&self, id: AssetId, dependency: AssetId) -> Freshness {
-        let dependency_build_time = self.build_info.build_times.get(&dependency);
-        dependency_build_time.map_or(Freshness::Stale, |dependency_build_time| {
-            self.freshness(id, *dependency_build_time)
-        })
+    /// Whether the asset has been built and its outputs still exist, for
+    /// assets that have no source files.
+    pub fn built(&mut self, id: AssetId) -> Freshness {
+        self.freshness(id, DateTime::<Utc>::MIN_UTC, || RebuildReason::NotBuilt)
+    }
+
+    pub fn source_asset(&mut self, id: AssetId, dependency: AssetId) -> Freshness {
+        let reason = || RebuildReason::ChangedDependency { dependency };
+        match self.build_info.build_times.get(&dependency).copied() {
+            Some(dependency_build_time) => self.freshness(id, dependency_build_time, reason),
+            None => {
+                self.rebuild_reason(id, reason());
+                Freshness::Stale
+            }
+        }
     }
 
     pub fn processing(&mut self, id: AssetId) -> bool {
@@ -508,6 +879,7 @@ impl<A: Asset> DynAssetTypeTrait for DynAssetTypeImpl<A> {
         asset_id: AssetId,
     ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send + Sync + 'a>> {
         let asset = context.source.get_asset::<A>(asset_id).unwrap();
+        context.check_settings(asset_id, asset);
         let span = tracing::info_span!("processing asset", id = %asset_id);
         Box::pin(asset.process(asset_id, context).instrument(span))
     }
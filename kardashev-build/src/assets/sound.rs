@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use kardashev_protocol::assets::AssetId;
+
+use crate::assets::{
+    dist,
+    processor::ProcessContext,
+    source::{
+        Manifest,
+        Sound,
+    },
+    Asset,
+    Error,
+};
+
+/// File extensions of audio formats that all browsers can decode.
+const SUPPORTED_EXTENSIONS: &[&str] = &["ogg", "oga", "opus", "mp3", "wav", "flac", "m4a"];
+
+impl Asset for Sound {
+    fn register_dist_type(dist_asset_types: &mut dist::AssetTypes) {
+        dist_asset_types.register::<dist::Sound>();
+    }
+
+    fn get_assets(manifest: &Manifest) -> &HashMap<AssetId, Self> {
+        &manifest.sounds
+    }
+
+    async fn process<'a, 'b: 'a>(
+        &'a self,
+        id: AssetId,
+        context: &'a mut ProcessContext<'b>,
+    ) -> Result<(), Error> {
+        if !context.processing(id) {
+            return Ok(());
+        }
+
+        let path = context.input_path(&self.path);
+
+        if context.source_path(id, &path)?.is_fresh() {
+            tracing::debug!(%id, "not modified since last build. skipping.");
+            return Ok(());
+        }
+
+        // the audio is decoded by the browser, so the file is copied as-is.
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .filter(|extension| SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+            .ok_or_else(|| Error::UnsupportedSoundFile { path: path.clone() })?;
+
+        let filename = format!("{id}.{extension}");
+        std::fs::copy(&path, context.dist_path.join(&filename))?;
+
+        context.dist_assets.insert(dist::Sound {
+            id,
+            label: self.label.clone(),
+            build_time: context.build_time,
+            audio: filename,
+            playlist: self.playlist.clone(),
+        });
+
+        context.set_build_time(id);
+
+        Ok(())
+    }
+}
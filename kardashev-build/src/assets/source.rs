@@ -5,6 +5,8 @@ use std::{
 
 use kardashev_protocol::assets::{
     AssetId,
+    TextureEdgeMode,
+    TextureFilter,
     TextureFormat,
 };
 use palette::Srgb;
@@ -35,6 +37,15 @@ pub struct Manifest {
 
     #[serde(default)]
     pub shaders: HashMap<AssetId, Shader>,
+
+    #[serde(default)]
+    pub catalogs: HashMap<AssetId, Catalog>,
+
+    #[serde(default)]
+    pub color_ramps: HashMap<AssetId, ColorRamp>,
+
+    #[serde(default)]
+    pub icon_sets: HashMap<AssetId, IconSet>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -53,6 +64,21 @@ pub struct Texture {
     pub format: Option<TextureFormat>,
     pub output_format: Option<TextureFileFormat>,
     pub scale_to: Option<ScaleTo>,
+    pub sampler: Option<TextureSampler>,
+    /// Whether mipmaps should be generated for this texture. Defaults to
+    /// `true`, unless the texture is packed into an atlas.
+    pub mipmaps: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextureSampler {
+    pub u_edge_mode: Option<TextureEdgeMode>,
+    pub v_edge_mode: Option<TextureEdgeMode>,
+    pub mag_filter: Option<TextureFilter>,
+    pub min_filter: Option<TextureFilter>,
+    pub mipmap_filter: Option<TextureFilter>,
+    pub anisotropy: Option<u16>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize)]
@@ -158,15 +184,43 @@ pub struct Material {
     pub shininess: Option<MaterialScalarTexture>,
     pub dissolve: Option<MaterialScalarTexture>,
     pub emissive: Option<MaterialColorTexture>,
+    pub bloom: Option<f32>,
     pub albedo: Option<AssetIdOrInline<Texture>>,
     pub metalness: Option<AssetIdOrInline<Texture>>,
     pub roughness: Option<AssetIdOrInline<Texture>>,
 }
 
+/// Meshes, materials and textures imported from a glTF 2.0 file (`.gltf` or
+/// `.glb`).
+///
+/// Every mesh, material and texture in the file becomes an asset of its own,
+/// with a generated ID, and the model references them.
 #[derive(Clone, Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Model {
     pub label: Option<String>,
+    pub path: PathBuf,
+
+    /// Index of the scene to import. Defaults to the file's default scene, or
+    /// its first one.
+    pub scene: Option<usize>,
+
+    #[serde(default)]
+    pub hierarchy: ModelHierarchy,
+}
+
+/// What happens to the node hierarchy of an imported [`Model`].
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModelHierarchy {
+    /// Transforms all meshes into the space of the scene, and merges the ones
+    /// with the same material. The model then has a single node.
+    #[default]
+    Flatten,
+
+    /// Keeps the nodes with their transforms, and imports every primitive as a
+    /// mesh of its own.
+    Preserve,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -174,6 +228,9 @@ pub struct Model {
 pub struct Sound {
     pub label: Option<String>,
     pub path: PathBuf,
+
+    /// Music playlist to add the sound to, e.g. `map` or `combat`.
+    pub playlist: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -183,6 +240,57 @@ pub struct Shader {
     pub path: PathBuf,
 }
 
+/// Translation catalog for one locale.
+///
+/// The catalog file can either be a [Fluent][1] file (`.ftl`) or a JSON file
+/// (`.json`). Only simple Fluent messages and attributes are supported.
+///
+/// [1]: https://projectfluent.org/
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalog {
+    pub label: Option<String>,
+    pub locale: String,
+    pub path: PathBuf,
+}
+
+/// Color ramp, given by colors at positions between 0 and 1.
+///
+/// The ramp is sampled into a lookup table, interpolating linearly between the
+/// stops.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorRamp {
+    pub label: Option<String>,
+    pub stops: Vec<ColorStop>,
+}
+
+/// UI icons from a directory of SVG files.
+///
+/// The icons are named after their files, e.g. `arrow-up.svg` is `arrow-up`,
+/// and rasterized at each size into one sprite sheet.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IconSet {
+    pub label: Option<String>,
+    pub path: PathBuf,
+
+    /// Sizes in pixels. Defaults to 16, 24 and 32 pixels.
+    #[serde(default = "default_icon_sizes")]
+    pub sizes: Vec<u32>,
+}
+
+fn default_icon_sizes() -> Vec<u32> {
+    vec![16, 24, 32]
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorStop {
+    pub position: f32,
+    pub color: Color,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum AssetIdOrInline<T> {
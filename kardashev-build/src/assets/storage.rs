@@ -0,0 +1,275 @@
+//! Object storage for the dist assets.
+//!
+//! Assets are still processed into the local dist directory, which also keeps
+//! the build info for incremental builds. After processing, the directory is
+//! synced to the storage, so that the API host doesn't need it to serve the
+//! assets: it redirects to the storage instead (see [`DistStorage::url_for`]).
+//!
+//! Supported are local directories (`file:///srv/kardashev/assets`) and
+//! S3-compatible buckets (`s3://bucket/prefix`). S3 is configured with the
+//! usual `AWS_*` environment variables, e.g. `AWS_ENDPOINT` for other providers
+//! than AWS.
+
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use futures_util::TryStreamExt;
+use object_store::{
+    aws::{
+        AmazonS3,
+        AmazonS3Builder,
+    },
+    path::Path as ObjectPath,
+    signer::Signer,
+    Attribute,
+    Attributes,
+    ObjectMeta,
+    ObjectStore,
+    PutOptions,
+};
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::assets::Error;
+
+#[derive(Clone, Debug)]
+pub struct DistStorage {
+    url: Url,
+    store: Arc<dyn ObjectStore>,
+    prefix: ObjectPath,
+
+    /// The bucket, if the storage is S3, so that URLs can be presigned.
+    s3: Option<AmazonS3>,
+
+    /// Public URL of the storage's prefix, e.g. a CDN in front of the bucket.
+    public_url: Option<Url>,
+}
+
+impl DistStorage {
+    pub fn from_url(url: Url) -> Result<Self, Error> {
+        let prefix = ObjectPath::from_url_path(url.path())?;
+
+        let (store, s3): (Arc<dyn ObjectStore>, _) = if url.scheme() == "s3" {
+            let s3 = AmazonS3Builder::from_env().with_url(url.as_str()).build()?;
+            (Arc::new(s3.clone()), Some(s3))
+        }
+        else {
+            let (store, _) = object_store::parse_url(&url)?;
+            (store.into(), None)
+        };
+
+        Ok(Self {
+            url,
+            store,
+            prefix,
+            s3,
+            public_url: None,
+        })
+    }
+
+    /// Redirect to `public_url` instead of presigned URLs.
+    ///
+    /// This is necessary for storage that isn't S3, and useful if the bucket
+    /// is public or behind a CDN.
+    pub fn with_public_url(mut self, mut public_url: Url) -> Self {
+        // the trailing slash is important for `Url::join` to work properly
+        if !public_url.path().ends_with('/') {
+            public_url.set_path(&format!("{}/", public_url.path()));
+        }
+        self.public_url = Some(public_url);
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Whether [`url_for`](Self::url_for) can return URLs.
+    pub fn can_redirect(&self) -> bool {
+        self.public_url.is_some() || self.s3.is_some()
+    }
+
+    fn object_path(&self, path: &str) -> ObjectPath {
+        path.split('/')
+            .filter(|part| !part.is_empty())
+            .fold(self.prefix.clone(), |object_path, part| {
+                object_path.child(part)
+            })
+    }
+
+    /// Returns the URL from which the asset file at `path` (relative to the
+    /// dist directory) can be downloaded.
+    ///
+    /// Presigned URLs expire after `expires_in`. Returns `None` if the storage
+    /// has neither a public URL, nor can presign URLs. Fails with
+    /// [`Error::InvalidStoragePath`] if the path would leave the dist
+    /// directory, e.g. with `..`.
+    pub async fn url_for(&self, path: &str, expires_in: Duration) -> Result<Option<Url>, Error> {
+        let invalid_path = || {
+            Error::InvalidStoragePath {
+                path: path.to_owned(),
+            }
+        };
+
+        let parts = path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        if parts.is_empty()
+            || parts
+                .iter()
+                .any(|part| *part == "." || *part == ".." || part.contains('\\'))
+        {
+            return Err(invalid_path());
+        }
+
+        if let Some(public_url) = &self.public_url {
+            // the parts are percent-encoded, so that they can't form `..` either.
+            let mut url = public_url.clone();
+            url.path_segments_mut()
+                .map_err(|_| invalid_path())?
+                .pop_if_empty()
+                .extend(&parts);
+            return Ok(Some(url));
+        }
+
+        if let Some(s3) = &self.s3 {
+            let url = s3
+                .signed_url(http::Method::GET, &self.object_path(path), expires_in)
+                .await?;
+            return Ok(Some(url));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the paths of the files in the storage, relative to the dist
+    /// directory.
+    pub async fn paths(&self) -> Result<HashSet<String>, Error> {
+        Ok(self.list().await?.into_keys().collect())
+    }
+
+    async fn list(&self) -> Result<HashMap<String, ObjectMeta>, Error> {
+        let objects = self
+            .store
+            .list(Some(&self.prefix))
+            .map_ok(|meta| {
+                let relative = meta
+                    .location
+                    .prefix_match(&self.prefix)
+                    .map(|parts| {
+                        parts
+                            .map(|part| part.as_ref().to_owned())
+                            .collect::<Vec<_>>()
+                            .join("/")
+                    })
+                    .unwrap_or_default();
+                (relative, meta)
+            })
+            .try_collect()
+            .await?;
+        Ok(objects)
+    }
+
+    /// Uploads the files in `dist_path` that are missing or changed in the
+    /// storage, and deletes files from the storage that are not in
+    /// `dist_path`.
+    pub async fn sync(&self, dist_path: impl AsRef<Path>) -> Result<SyncStats, Error> {
+        let dist_path = dist_path.as_ref();
+        tracing::info!(url = %self.url, "syncing dist assets to storage");
+
+        let mut remote = self.list().await?;
+        let mut stats = SyncStats::default();
+
+        for result in WalkDir::new(dist_path) {
+            let entry = result?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let relative = entry
+                .path()
+                .strip_prefix(dist_path)
+                .expect("walked path is not in dist directory")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let metadata = entry.metadata()?;
+            let modified: DateTime<Utc> = metadata.modified()?.into();
+            let unchanged = remote.remove(&relative).map_or(false, |meta| {
+                meta.size as u64 == metadata.len() && meta.last_modified >= modified
+            });
+            if unchanged {
+                stats.unchanged += 1;
+                continue;
+            }
+
+            tracing::debug!(path = %relative, "uploading");
+            let data = tokio::fs::read(entry.path()).await?;
+            let mut attributes = Attributes::new();
+            attributes.insert(Attribute::ContentType, content_type(&relative).into());
+            self.store
+                .put_opts(
+                    &self.object_path(&relative),
+                    data.into(),
+                    PutOptions {
+                        attributes,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            stats.uploaded += 1;
+        }
+
+        for (relative, meta) in remote {
+            tracing::debug!(path = %relative, "deleting from storage");
+            self.store.delete(&meta.location).await?;
+            stats.deleted += 1;
+        }
+
+        tracing::info!(
+            uploaded = stats.uploaded,
+            deleted = stats.deleted,
+            unchanged = stats.unchanged,
+            "synced dist assets"
+        );
+
+        Ok(stats)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SyncStats {
+    pub uploaded: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Content type of a dist file, so that browsers accept the redirected
+/// downloads.
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("json") => "application/json",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("tiff") => "image/tiff",
+        Some("wgsl") => "text/wgsl",
+        Some("gz") => "application/gzip",
+        _ => "application/octet-stream",
+    }
+}
@@ -11,6 +11,7 @@ use kardashev_protocol::assets::{
 };
 
 use crate::assets::{
+    atlas::AtlasBuilder,
     dist,
     processor::ProcessContext,
     source::{
@@ -18,6 +19,7 @@ use crate::assets::{
         ScaleTo,
         Texture,
         TextureFileFormat,
+        TextureSampler,
     },
     Asset,
     Error,
@@ -96,13 +98,18 @@ impl Asset for Texture {
         }
 
         if let Some(atlas_builder_id) = self.atlas.clone().unwrap_or_default().into() {
-            let atlas_builder = context.atlas_builders.entry(atlas_builder_id).or_default();
+            let atlas_builder = context
+                .atlas_builders
+                .entry(atlas_builder_id)
+                .or_insert_with(|| AtlasBuilder::new(context.max_atlas_page_size));
             atlas_builder.insert(
                 image.to_rgba8(),
                 UnfinishedTexture {
                     id,
                     label: self.label.clone(),
                     format: self.format.unwrap_or_default(),
+                    sampler: self.sampler.unwrap_or_default(),
+                    mipmaps: self.mipmaps.unwrap_or(false),
                 },
             )?;
         }
@@ -134,6 +141,7 @@ impl Asset for Texture {
                 }
             }
 
+            let sampler = self.sampler.unwrap_or_default();
             context.dist_assets.insert(dist::Texture {
                 id,
                 label: self.label.clone(),
@@ -142,8 +150,13 @@ impl Asset for Texture {
                 size,
                 format: self.format.unwrap_or_default(),
                 crop: None,
-                u_edge_mode: None,
-                v_edge_mode: None,
+                u_edge_mode: sampler.u_edge_mode,
+                v_edge_mode: sampler.v_edge_mode,
+                mag_filter: sampler.mag_filter,
+                min_filter: sampler.min_filter,
+                mipmap_filter: sampler.mipmap_filter,
+                anisotropy: sampler.anisotropy,
+                mipmaps: self.mipmaps.unwrap_or(true),
             });
         }
 
@@ -158,4 +171,6 @@ pub struct UnfinishedTexture {
     pub id: AssetId,
     pub label: Option<String>,
     pub format: TextureFormat,
+    pub sampler: TextureSampler,
+    pub mipmaps: bool,
 }
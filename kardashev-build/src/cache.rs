@@ -0,0 +1,236 @@
+//! Build cache shared by checkouts and worktrees.
+//!
+//! Outputs are stored in the cache directory under a key, which is a hash of
+//! everything that went into the build: the toolchain versions, the options,
+//! and the contents of the input files. Content hashes (unlike modification
+//! times) are the same in a fresh checkout, so a build that has been made in
+//! any worktree can be restored instead of rebuilt.
+//!
+//! Entries are never evicted. The cache directory can be deleted at any time.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use sha2::{
+    Digest,
+    Sha256,
+};
+use walkdir::WalkDir;
+
+#[derive(Debug, thiserror::Error)]
+#[error("build cache error")]
+pub enum Error {
+    Io(#[from] std::io::Error),
+    WalkDir(#[from] walkdir::Error),
+}
+
+#[derive(Clone, Debug)]
+pub struct BuildCache {
+    path: PathBuf,
+}
+
+impl BuildCache {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.path.join(key.kind).join(&key.hash)
+    }
+
+    /// Whether there are cached outputs for `key`.
+    pub fn contains(&self, key: &CacheKey) -> bool {
+        self.entry_path(key).exists()
+    }
+
+    /// Copies the cached outputs for `key` to `output_path`.
+    ///
+    /// Returns `false` if there are none.
+    pub fn restore(&self, key: &CacheKey, output_path: impl AsRef<Path>) -> Result<bool, Error> {
+        let entry_path = self.entry_path(key);
+        if !entry_path.exists() {
+            tracing::debug!(kind = key.kind, hash = %key.hash, "cache miss");
+            return Ok(false);
+        }
+
+        tracing::info!(kind = key.kind, hash = %key.hash, "restoring from build cache");
+        copy_directory(&entry_path, output_path.as_ref(), |_| true)?;
+        Ok(true)
+    }
+
+    /// Stores all files in `output_path` as the outputs for `key`.
+    pub fn store(&self, key: &CacheKey, output_path: impl AsRef<Path>) -> Result<(), Error> {
+        self.store_filtered(key, output_path.as_ref(), |_| true)
+    }
+
+    /// Stores the `files` (relative to `output_path`) as the outputs for
+    /// `key`.
+    pub fn store_files(
+        &self,
+        key: &CacheKey,
+        output_path: impl AsRef<Path>,
+        files: &[&str],
+    ) -> Result<(), Error> {
+        self.store_filtered(key, output_path.as_ref(), |relative| {
+            files.iter().any(|file| relative == Path::new(file))
+        })
+    }
+
+    /// The files are copied to a temporary directory first, so that
+    /// concurrent builds never see partial entries.
+    fn store_filtered(
+        &self,
+        key: &CacheKey,
+        output_path: &Path,
+        filter: impl Fn(&Path) -> bool,
+    ) -> Result<(), Error> {
+        let entry_path = self.entry_path(key);
+        if entry_path.exists() {
+            return Ok(());
+        }
+
+        tracing::debug!(kind = key.kind, hash = %key.hash, "storing in build cache");
+        let temp_path =
+            self.path
+                .join(key.kind)
+                .join(format!("{}.tmp-{}", key.hash, std::process::id()));
+        if temp_path.exists() {
+            std::fs::remove_dir_all(&temp_path)?;
+        }
+        copy_directory(output_path, &temp_path, filter)?;
+
+        if let Err(error) = std::fs::rename(&temp_path, &entry_path) {
+            // another build stored the same entry in the meantime.
+            std::fs::remove_dir_all(&temp_path)?;
+            if !entry_path.exists() {
+                return Err(error.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash of a build's inputs.
+#[derive(Clone, Debug)]
+pub struct CacheKey {
+    kind: &'static str,
+    hash: String,
+}
+
+/// Builds a [`CacheKey`] from a build's inputs.
+#[derive(Clone, Debug)]
+pub struct CacheKeyBuilder {
+    kind: &'static str,
+    hasher: Sha256,
+}
+
+impl CacheKeyBuilder {
+    /// `kind` separates the entries of different builds, e.g. `ui` and
+    /// `assets`, in the cache directory.
+    pub fn new(kind: &'static str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        Self { kind, hasher }
+    }
+
+    /// Adds a named input, e.g. a toolchain version or an option.
+    pub fn with_value(&mut self, name: &str, value: impl AsRef<[u8]>) -> &mut Self {
+        let value = value.as_ref();
+        self.hasher.update(name.as_bytes());
+        self.hasher.update((value.len() as u64).to_le_bytes());
+        self.hasher.update(value);
+        self
+    }
+
+    /// Adds the contents of a file, if it exists.
+    ///
+    /// Only the file name is part of the key, so that the key is the same in
+    /// other checkouts.
+    pub fn with_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let contents = if path.exists() {
+            std::fs::read(path)?
+        }
+        else {
+            vec![]
+        };
+        Ok(self.with_value(&name, contents))
+    }
+
+    /// Adds the contents and relative paths of all files in a directory,
+    /// except for the subdirectories named in `skip` (e.g. `target`).
+    pub fn with_directory(
+        &mut self,
+        path: impl AsRef<Path>,
+        skip: &[&str],
+    ) -> Result<&mut Self, Error> {
+        let path = path.as_ref();
+        let walk = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                !(entry.file_type().is_dir()
+                    && entry.depth() > 0
+                    && entry
+                        .file_name()
+                        .to_str()
+                        .map_or(false, |name| skip.contains(&name) || name.starts_with('.')))
+            });
+
+        for result in walk {
+            let entry = result?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry
+                .path()
+                .strip_prefix(path)
+                .expect("walked path is not in directory");
+            let contents = std::fs::read(entry.path())?;
+            self.with_value(&relative.to_string_lossy(), contents);
+        }
+
+        Ok(self)
+    }
+
+    pub fn finish(&self) -> CacheKey {
+        CacheKey {
+            kind: self.kind,
+            hash: format!("{:x}", self.hasher.clone().finalize()),
+        }
+    }
+}
+
+/// Copies the files for which `filter` returns `true` from `source` to
+/// `destination`, replacing existing ones.
+pub(crate) fn copy_directory(
+    source: &Path,
+    destination: &Path,
+    filter: impl Fn(&Path) -> bool,
+) -> Result<(), Error> {
+    std::fs::create_dir_all(destination)?;
+    for result in WalkDir::new(source) {
+        let entry = result?;
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .expect("walked path is not in directory");
+        let target = destination.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        }
+        else if filter(relative) {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
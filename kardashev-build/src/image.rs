@@ -0,0 +1,177 @@
+//! Container images for deployment.
+//!
+//! The image is assembled in a directory with a release build of the CLI,
+//! which runs the server, and the dist directory with the prebuilt UI and
+//! assets. A `Dockerfile` and a `compose.yaml` (which adds a database) are
+//! generated next to them, so the directory can be built with any OCI image
+//! builder.
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use askama::Template;
+use tokio::process::Command;
+
+use crate::{
+    assets::processor::Processor,
+    cache::BuildCache,
+    ui::{
+        cargo::Cargo,
+        compile_ui,
+        csp::ContentSecurityPolicy,
+    },
+    util::process::{
+        ExitStatusError,
+        ExitStatusExt,
+    },
+};
+
+/// The binary that runs the server.
+const BINARY_NAME: &str = "kardashev-cli";
+
+#[derive(Debug, thiserror::Error)]
+#[error("image build error")]
+pub enum Error {
+    Io(#[from] std::io::Error),
+    Cargo(#[from] crate::ui::cargo::Error),
+    Ui(#[from] crate::ui::Error),
+    Assets(#[from] crate::assets::Error),
+    ExitStatus(#[from] ExitStatusError),
+}
+
+#[derive(Clone, Debug)]
+pub struct ImageOptions {
+    /// Path to any crate in the workspace.
+    pub workspace_path: PathBuf,
+
+    pub assets_path: PathBuf,
+
+    pub ui_path: PathBuf,
+
+    pub content_security_policy: ContentSecurityPolicy,
+
+    pub cache: Option<BuildCache>,
+
+    /// Directory in which the image is assembled. Its contents are replaced.
+    pub output_path: PathBuf,
+
+    pub base_image: String,
+
+    pub tag: String,
+
+    /// Port on which the server listens in the container.
+    pub port: u16,
+
+    /// Builds the image with this tool, e.g. `docker` or `podman`. Without
+    /// it, only the directory is assembled.
+    pub container_tool: Option<String>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn build_image(options: &ImageOptions) -> Result<(), Error> {
+    let output_path = &options.output_path;
+    let bin_path = output_path.join("bin");
+    let dist_path = output_path.join("dist");
+
+    for path in [&bin_path, &dist_path] {
+        if path.exists() {
+            std::fs::remove_dir_all(path)?;
+        }
+    }
+    std::fs::create_dir_all(&bin_path)?;
+
+    tracing::info!("building server");
+    let mut cargo = Cargo::new(&options.workspace_path);
+    cargo.with_package(BINARY_NAME).with_release(true);
+    cargo.build(None).await?;
+    let workspace_path = cargo.locate_workspace().await?;
+    let workspace_path = workspace_path.parent().unwrap();
+    std::fs::copy(
+        workspace_path
+            .join("target")
+            .join(cargo.profile_dir())
+            .join(BINARY_NAME),
+        bin_path.join(BINARY_NAME),
+    )?;
+
+    // the dist directory was removed, so these are clean builds, but they can
+    // still be restored from the cache.
+    tracing::info!("building UI");
+    compile_ui(
+        &options.ui_path,
+        dist_path.join("ui"),
+        false,
+        true,
+        &options.content_security_policy,
+        options.cache.as_ref(),
+    )
+    .await?;
+
+    tracing::info!("building assets");
+    let mut processor = Processor::new(dist_path.join("assets"))?;
+    if let Some(cache) = &options.cache {
+        processor.use_cache(cache.clone());
+    }
+    processor.add_directory(&options.assets_path)?;
+    processor.process(false).await?;
+
+    write_template(
+        output_path.join("Dockerfile"),
+        Dockerfile {
+            base_image: &options.base_image,
+            binary_name: BINARY_NAME,
+            port: options.port,
+        },
+    )?;
+    write_template(
+        output_path.join("compose.yaml"),
+        Compose {
+            tag: &options.tag,
+            port: options.port,
+        },
+    )?;
+
+    if let Some(container_tool) = &options.container_tool {
+        tracing::info!(tag = %options.tag, "running `{container_tool} build`");
+        Command::new(container_tool)
+            .arg("build")
+            .arg("--tag")
+            .arg(&options.tag)
+            .arg(output_path)
+            .spawn()?
+            .wait()
+            .await?
+            .into_result()?;
+    }
+
+    tracing::info!(path = %output_path.display(), "done");
+
+    Ok(())
+}
+
+fn write_template(path: impl AsRef<Path>, template: impl Template) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    template.write_into(&mut writer)?;
+    Ok(())
+}
+
+#[derive(Debug, Template)]
+#[template(path = "image/Dockerfile", escape = "none")]
+struct Dockerfile<'a> {
+    base_image: &'a str,
+    binary_name: &'a str,
+    port: u16,
+}
+
+#[derive(Debug, Template)]
+#[template(path = "image/compose.yaml", escape = "none")]
+struct Compose<'a> {
+    tag: &'a str,
+    port: u16,
+}
@@ -1,3 +1,5 @@
 pub mod assets;
+pub mod cache;
+pub mod image;
 pub mod ui;
 pub mod util;
@@ -16,6 +16,7 @@ use tokio::process::Command;
 use crate::util::process::{
     ExitStatusError,
     ExitStatusExt,
+    OutputExt,
     OutputJsonError,
     OutputJsonExt,
 };
@@ -41,6 +42,8 @@ impl From<OutputJsonError> for Error {
 pub struct Cargo {
     crate_path: PathBuf,
     cargo_path: PathBuf,
+    package: Option<String>,
+    release: bool,
 }
 
 impl Cargo {
@@ -48,6 +51,8 @@ impl Cargo {
         Self {
             crate_path: path.as_ref().to_owned(),
             cargo_path: PathBuf::from("cargo"),
+            package: None,
+            release: false,
         }
     }
 
@@ -56,6 +61,28 @@ impl Cargo {
         self
     }
 
+    /// Builds only `package`, e.g. a binary in the workspace.
+    pub fn with_package(&mut self, package: impl Into<String>) -> &mut Self {
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Builds with the release profile.
+    pub fn with_release(&mut self, release: bool) -> &mut Self {
+        self.release = release;
+        self
+    }
+
+    /// Name of the directory in `target` where the build artifacts are.
+    pub fn profile_dir(&self) -> &'static str {
+        if self.release {
+            "release"
+        }
+        else {
+            "debug"
+        }
+    }
+
     fn command(&self) -> Command {
         let mut command = Command::new(&self.cargo_path);
         command.current_dir(&self.crate_path);
@@ -90,9 +117,30 @@ impl Cargo {
             .into_json_result()?)
     }
 
+    /// Version of the toolchain that builds the crate, which might be
+    /// overridden in the crate's directory.
+    pub async fn toolchain_version(&self) -> Result<String, Error> {
+        let output = Command::new("rustc")
+            .current_dir(&self.crate_path)
+            .arg("-vV")
+            .stdout(Stdio::piped())
+            .spawn()?
+            .wait_with_output()
+            .await?
+            .into_result()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
     pub async fn build(&self, target: Option<&str>) -> Result<(), Error> {
         let mut command = self.command();
         command.arg("build");
+        if let Some(package) = &self.package {
+            command.arg("--package");
+            command.arg(package);
+        }
+        if self.release {
+            command.arg("--release");
+        }
         if let Some(target) = target {
             command.arg("--target");
             command.arg(target);
@@ -140,6 +188,8 @@ pub struct Dependency {
     pub features: Vec<String>,
     pub target: Option<String>,
     pub registry: Option<String>,
+    /// Path of a path dependency.
+    pub path: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -0,0 +1,127 @@
+//! Subresource integrity and the content security policy for `index.html`.
+//!
+//! The JS, wasm and CSS files are loaded with their SRI hashes, so that a CDN
+//! or proxy in front of the UI can't tamper with them. The content security
+//! policy only allows the UI's own origin, and the configured API and asset
+//! origins. Inline `style` attributes are allowed, since the UI's components
+//! set them.
+
+use base64::{
+    engine::general_purpose::STANDARD,
+    Engine,
+};
+use sha2::{
+    Digest,
+    Sha384,
+};
+use url::Url;
+
+/// Origins besides the UI's own, which the content security policy allows.
+#[derive(Clone, Debug, Default)]
+pub struct ContentSecurityPolicy {
+    /// Origins of the API, if it's not served from the same origin as the UI.
+    pub api_origins: Vec<Url>,
+
+    /// Origins from which assets are loaded, e.g. the asset storage or a CDN in
+    /// front of it.
+    pub asset_origins: Vec<Url>,
+}
+
+impl ContentSecurityPolicy {
+    /// Renders the policy for a `<meta http-equiv="Content-Security-Policy">`
+    /// tag.
+    ///
+    /// `script_hashes` and `style_hashes` are the SRI hashes of the inline
+    /// scripts and styles.
+    pub(super) fn render(&self, script_hashes: &[&str], style_hashes: &[&str]) -> String {
+        let api_origins = self.api_origins.iter().flat_map(api_origins);
+        let asset_origins = self.asset_origins.iter().filter_map(origin);
+        let connect_src = api_origins.chain(asset_origins.clone()).collect::<Vec<_>>();
+        let img_src = asset_origins.clone().collect::<Vec<_>>();
+        let media_src = asset_origins.collect::<Vec<_>>();
+
+        let directives = [
+            ("default-src", vec!["'self'".to_owned()]),
+            (
+                "script-src",
+                ["'self'".to_owned(), "'wasm-unsafe-eval'".to_owned()]
+                    .into_iter()
+                    .chain(script_hashes.iter().map(|hash| format!("'{hash}'")))
+                    .collect(),
+            ),
+            (
+                "style-src",
+                std::iter::once("'self'".to_owned())
+                    .chain(style_hashes.iter().map(|hash| format!("'{hash}'")))
+                    .collect(),
+            ),
+            // leptos sets `style` attributes, e.g. for the positions of labels. Unlike
+            // inline `<style>` elements, they can't run anything.
+            ("style-src-attr", vec!["'unsafe-inline'".to_owned()]),
+            (
+                "img-src",
+                ["'self'".to_owned(), "data:".to_owned(), "blob:".to_owned()]
+                    .into_iter()
+                    .chain(img_src)
+                    .collect(),
+            ),
+            // music is streamed by `<audio>` elements, directly from the assets.
+            (
+                "media-src",
+                std::iter::once("'self'".to_owned())
+                    .chain(media_src)
+                    .collect(),
+            ),
+            (
+                "connect-src",
+                std::iter::once("'self'".to_owned())
+                    .chain(connect_src)
+                    .collect(),
+            ),
+            // workers, e.g. the one encoding screenshots, are loaded from blob URLs of scripts
+            // that are compiled into the UI.
+            ("worker-src", vec!["'self'".to_owned(), "blob:".to_owned()]),
+            ("object-src", vec!["'none'".to_owned()]),
+            ("base-uri", vec!["'self'".to_owned()]),
+        ];
+
+        directives
+            .into_iter()
+            .map(|(name, sources)| format!("{name} {}", sources.join(" ")))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// The origin of a URL, in the form used in a content security policy.
+fn origin(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+/// The origins of the API, including the websocket origin for the session
+/// stream.
+fn api_origins(url: &Url) -> Vec<String> {
+    let Some(http_origin) = origin(url)
+    else {
+        return vec![];
+    };
+
+    let websocket_scheme = match url.scheme() {
+        "https" => "wss",
+        "http" => "ws",
+        _ => return vec![http_origin],
+    };
+    let mut websocket_url = url.clone();
+    websocket_url
+        .set_scheme(websocket_scheme)
+        .expect("failed to set websocket scheme");
+    let websocket_origin = websocket_url.origin().ascii_serialization();
+
+    vec![http_origin, websocket_origin]
+}
+
+/// Computes the SRI hash of a file's or inline element's contents.
+pub fn integrity(data: &[u8]) -> String {
+    format!("sha384-{}", STANDARD.encode(Sha384::digest(data)))
+}
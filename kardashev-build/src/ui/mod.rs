@@ -1,15 +1,20 @@
-mod cargo;
+pub(crate) mod cargo;
+pub mod csp;
 mod git;
 mod wasm_bindgen;
 
 use std::{
+    collections::HashSet,
     fs::File,
     io::{
         BufReader,
         BufWriter,
         Read,
     },
-    path::Path,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use askama::Template;
@@ -17,20 +22,34 @@ use chrono::{
     DateTime,
     Utc,
 };
+use kardashev_style_internal::Regenerated;
 use serde::{
     Deserialize,
     Serialize,
 };
 
+pub use crate::ui::wasm_bindgen::wasm_bindgen_version;
 use crate::{
+    cache::{
+        BuildCache,
+        CacheKey,
+        CacheKeyBuilder,
+    },
     ui::{
         cargo::Cargo,
+        csp::{
+            integrity,
+            ContentSecurityPolicy,
+        },
         git::Git,
         wasm_bindgen::wasm_bindgen,
     },
     util::path_modified_timestamp,
 };
 
+/// Target the UI is compiled to.
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
 #[derive(Debug, thiserror::Error)]
 #[error("ui build error")]
 pub enum Error {
@@ -38,6 +57,8 @@ pub enum Error {
     Cargo(#[from] crate::ui::cargo::Error),
     WasmBindgen(#[from] crate::ui::wasm_bindgen::WasmBindgenError),
     Json(#[from] serde_json::Error),
+    Cache(#[from] crate::cache::Error),
+    Style(#[from] kardashev_style_internal::Error),
 }
 
 #[tracing::instrument(skip_all)]
@@ -45,13 +66,17 @@ pub async fn compile_ui(
     input_path: impl AsRef<Path>,
     output_path: impl AsRef<Path>,
     clean: bool,
+    release: bool,
+    content_security_policy: &ContentSecurityPolicy,
+    cache: Option<&BuildCache>,
 ) -> Result<(), Error> {
     let input_path = input_path.as_ref();
     let output_path = output_path.as_ref();
 
     std::fs::create_dir_all(&output_path)?;
 
-    let cargo = Cargo::new(&input_path);
+    let mut cargo = Cargo::new(&input_path);
+    cargo.with_release(release);
 
     let manifest = cargo.manifest().await?;
     if manifest.targets.len() != 1 {
@@ -79,25 +104,17 @@ pub async fn compile_ui(
     let workspace_path = workspace_path.parent().unwrap();
     tracing::debug!(workspace_path = %workspace_path.display());
 
-    let target_wasm_path = workspace_path
-        .join("target")
-        .join("wasm32-unknown-unknown")
-        .join("debug")
-        .join(format!("{target_name}.wasm"));
-    tracing::debug!(target_wasm_path = %target_wasm_path.display());
-
     let wasm_filename = format!("{target_name}_bg.wasm");
     let js_filename = format!("{target_name}.js");
     let css_filename = format!("{target_name}.css");
-    let index_filename = "index.html";
 
     // check if all files exist
-    if !output_path.join(&wasm_filename).exists()
+    let needs_build = if !output_path.join(&wasm_filename).exists()
         || !output_path.join(&js_filename).exists()
         || !output_path.join(&css_filename).exists()
-        || !output_path.join(&index_filename).exists()
     {
         tracing::warn!("input file missing. rebuilding.");
+        true
     }
     else {
         // check freshness
@@ -118,23 +135,116 @@ pub async fn compile_ui(
 
         if is_fresh {
             tracing::debug!("not modified since last build. skipping.");
-            return Ok(());
         }
+        !is_fresh
+    };
+
+    if needs_build {
+        let cache_key = if let Some(cache) = cache {
+            let key = cache_key(&cargo, input_path, workspace_path, release).await?;
+            // a clean build is made from scratch, but still stored in the cache.
+            let restored = !clean && cache.restore(&key, output_path)?;
+            (!restored).then_some((cache, key))
+        }
+        else {
+            None
+        };
+
+        if cache.is_none() || cache_key.is_some() {
+            build(
+                &cargo,
+                input_path,
+                target_name,
+                workspace_path,
+                output_path,
+                &css_filename,
+            )
+            .await?;
+        }
+
+        if let Some((cache, key)) = cache_key {
+            cache.store_files(
+                &key,
+                output_path,
+                &[&wasm_filename, &js_filename, &css_filename],
+            )?;
+        }
+    }
+
+    // the index is generated even if the UI is fresh, because the content security
+    // policy might have changed.
+    write_index(
+        output_path,
+        &js_filename,
+        &wasm_filename,
+        &css_filename,
+        content_security_policy,
+    )?;
+
+    if needs_build {
+        let build_info = BuildInfo {
+            build_time,
+            version: manifest.version,
+            commit,
+        };
+
+        let writer = BufWriter::new(File::create(&build_info_path)?);
+        serde_json::to_writer_pretty(writer, &build_info)?;
     }
 
+    tracing::info!("done");
+
+    Ok(())
+}
+
+async fn build(
+    cargo: &Cargo,
+    input_path: &Path,
+    target_name: &str,
+    workspace_path: &Path,
+    output_path: &Path,
+    css_filename: &str,
+) -> Result<(), Error> {
+    let target_wasm_path = workspace_path
+        .join("target")
+        .join(WASM_TARGET)
+        .join(cargo.profile_dir())
+        .join(format!("{target_name}.wasm"));
+    tracing::debug!(target_wasm_path = %target_wasm_path.display());
+
     tracing::info!(target = %target_name, "running `cargo build`");
-    cargo.build(Some("wasm32-unknown-unknown")).await?;
+    cargo.build(Some(WASM_TARGET)).await?;
 
     tracing::info!(target = %target_name, "running `wasm-bindgen`");
     wasm_bindgen(&target_wasm_path, output_path, &target_name).await?;
 
-    tracing::info!("collecting CSS");
-    let css_path = workspace_path
+    collect_css(input_path, workspace_path, output_path, css_filename)?;
+
+    Ok(())
+}
+
+/// Directory to which the `#[style]` macro writes the compiled stylesheets.
+fn style_output_path(workspace_path: &Path) -> PathBuf {
+    workspace_path
         .join("target")
         .join("css")
-        .join("kardashev-ui");
+        .join("kardashev-ui")
+}
+
+/// Concatenates the compiled stylesheets, after the CSS custom properties of
+/// the UI crate's palettes.
+fn collect_css(
+    input_path: &Path,
+    workspace_path: &Path,
+    output_path: &Path,
+    css_filename: &str,
+) -> Result<(), Error> {
+    tracing::info!("collecting CSS");
     let mut css_buf = vec![];
-    for result in std::fs::read_dir(&css_path)? {
+    if let Some(palettes) = kardashev_style_internal::read_palettes(input_path)? {
+        css_buf.extend_from_slice(palettes.to_css().as_bytes());
+    }
+    for result in std::fs::read_dir(style_output_path(workspace_path))? {
         let entry = result?;
         let mut reader = BufReader::new(File::open(&entry.path())?);
         reader.read_to_end(&mut css_buf)?;
@@ -143,25 +253,140 @@ pub async fn compile_ui(
     tracing::debug!(path = %css_output_path.display(), "writing CSS file");
     std::fs::write(&css_output_path, &css_buf)?;
 
-    tracing::debug!(target = %target_name, "generating `index.html`");
-    let mut writer = BufWriter::new(File::create(output_path.join(&index_filename))?);
-    IndexHtml {
-        js: &js_filename,
-        wasm: &wasm_filename,
-        css: &css_filename,
+    Ok(())
+}
+
+/// Regenerates the stylesheet after changes to `.scss` files, without
+/// rebuilding the wasm module.
+///
+/// Returns `false` if the class names changed, in which case the UI must be
+/// rebuilt with [`compile_ui`].
+#[tracing::instrument(skip_all)]
+pub async fn compile_styles(
+    input_path: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    content_security_policy: &ContentSecurityPolicy,
+) -> Result<bool, Error> {
+    let input_path = input_path.as_ref();
+    let output_path = output_path.as_ref();
+
+    let cargo = Cargo::new(input_path);
+    let manifest = cargo.manifest().await?;
+    let target_name = &manifest.targets[0].name;
+    let workspace_path = cargo.locate_workspace().await?;
+    let workspace_path = workspace_path.parent().unwrap();
+
+    let wasm_filename = format!("{target_name}_bg.wasm");
+    let js_filename = format!("{target_name}.js");
+    let css_filename = format!("{target_name}.css");
+    if !output_path.join(&wasm_filename).exists() || !output_path.join(&js_filename).exists() {
+        return Ok(false);
     }
-    .write_into(&mut writer)?;
 
-    let build_info = BuildInfo {
-        build_time,
-        version: manifest.version,
-        commit,
-    };
+    tracing::info!("regenerating styles");
+    for result in std::fs::read_dir(style_output_path(workspace_path))? {
+        let entry = result?;
+        match kardashev_style_internal::regenerate(&entry.path())? {
+            Regenerated::Css => {}
+            Regenerated::ClassNamesChanged => {
+                tracing::info!(path = %entry.path().display(), "class names changed");
+                return Ok(false);
+            }
+        }
+    }
 
-    let writer = BufWriter::new(File::create(&build_info_path)?);
-    serde_json::to_writer_pretty(writer, &build_info)?;
+    collect_css(input_path, workspace_path, output_path, &css_filename)?;
+    write_index(
+        output_path,
+        &js_filename,
+        &wasm_filename,
+        &css_filename,
+        content_security_policy,
+    )?;
 
-    tracing::info!("done");
+    Ok(true)
+}
+
+/// Computes the cache key of the UI from the toolchain versions and the sources
+/// of the UI crate and its path dependencies.
+async fn cache_key(
+    cargo: &Cargo,
+    input_path: &Path,
+    workspace_path: &Path,
+    release: bool,
+) -> Result<CacheKey, Error> {
+    let mut key = CacheKeyBuilder::new("ui");
+    key.with_value("rustc", cargo.toolchain_version().await?)
+        .with_value("wasm-bindgen", wasm_bindgen_version().await)
+        .with_value("release", [release as u8])
+        .with_file(workspace_path.join("Cargo.toml"))?
+        .with_file(workspace_path.join("Cargo.lock"))?;
+
+    let mut visited = HashSet::new();
+    let mut crate_paths = vec![input_path.canonicalize()?];
+    while let Some(crate_path) = crate_paths.pop() {
+        if !visited.insert(crate_path.clone()) {
+            continue;
+        }
+        key.with_directory(&crate_path, &["target"])?;
+
+        let manifest = Cargo::new(&crate_path).manifest().await?;
+        crate_paths.extend(
+            manifest
+                .dependencies
+                .into_iter()
+                .filter_map(|dependency| dependency.path),
+        );
+    }
+
+    Ok(key.finish())
+}
+
+/// Inline script that loads the wasm module, checking its integrity.
+fn bootstrap_script(js_filename: &str, wasm_filename: &str, wasm_integrity: &str) -> String {
+    format!(
+        "import init from './{js_filename}'; await init({{ module_or_path: fetch('/{wasm_filename}', {{ integrity: '{wasm_integrity}' }}) }});"
+    )
+}
+
+/// Style of the loading screen, which is shown while the wasm module is fetched
+/// and compiled. The UI removes it once it's mounted.
+const LOADING_STYLE: &str = "#loading { position: fixed; inset: 0; display: flex; align-items: center; justify-content: center; background: black; color: gray; font-family: sans-serif; }";
+
+fn write_index(
+    output_path: &Path,
+    js_filename: &str,
+    wasm_filename: &str,
+    css_filename: &str,
+    content_security_policy: &ContentSecurityPolicy,
+) -> Result<(), Error> {
+    tracing::debug!("generating `index.html`");
+
+    let file_integrity =
+        |filename: &str| Ok::<_, Error>(integrity(&std::fs::read(output_path.join(filename))?));
+    let js_integrity = file_integrity(js_filename)?;
+    let wasm_integrity = file_integrity(wasm_filename)?;
+    let css_integrity = file_integrity(css_filename)?;
+
+    let bootstrap_script = bootstrap_script(js_filename, wasm_filename, &wasm_integrity);
+    let content_security_policy = content_security_policy.render(
+        &[&integrity(bootstrap_script.as_bytes())],
+        &[&integrity(LOADING_STYLE.as_bytes())],
+    );
+
+    let mut writer = BufWriter::new(File::create(output_path.join("index.html"))?);
+    IndexHtml {
+        js: js_filename,
+        js_integrity: &js_integrity,
+        wasm: wasm_filename,
+        wasm_integrity: &wasm_integrity,
+        css: css_filename,
+        css_integrity: &css_integrity,
+        bootstrap_script: &bootstrap_script,
+        loading_style: LOADING_STYLE,
+        content_security_policy: &content_security_policy,
+    }
+    .write_into(&mut writer)?;
 
     Ok(())
 }
@@ -170,8 +395,14 @@ pub async fn compile_ui(
 #[template(path = "index.html")]
 struct IndexHtml<'a> {
     js: &'a str,
+    js_integrity: &'a str,
     wasm: &'a str,
+    wasm_integrity: &'a str,
     css: &'a str,
+    css_integrity: &'a str,
+    bootstrap_script: &'a str,
+    loading_style: &'a str,
+    content_security_policy: &'a str,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -180,3 +411,37 @@ struct BuildInfo {
     version: String,
     commit: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use askama::Template;
+
+    use super::{
+        csp::ContentSecurityPolicy,
+        IndexHtml,
+        LOADING_STYLE,
+    };
+
+    #[test]
+    fn index_only_loads_own_files_with_integrity() {
+        let index = IndexHtml {
+            js: "kardashev.js",
+            js_integrity: "sha384-js",
+            wasm: "kardashev.wasm",
+            wasm_integrity: "sha384-wasm",
+            css: "kardashev.css",
+            css_integrity: "sha384-css",
+            bootstrap_script: "",
+            loading_style: LOADING_STYLE,
+            content_security_policy: &ContentSecurityPolicy::default().render(&[], &[]),
+        }
+        .render()
+        .unwrap();
+
+        // e.g. stylesheets from a CDN, which the content security policy blocks.
+        assert!(!index.contains("://"), "{index}");
+        for link in index.split('<').filter(|tag| tag.starts_with("link ")) {
+            assert!(link.contains("integrity="), "{link}");
+        }
+    }
+}
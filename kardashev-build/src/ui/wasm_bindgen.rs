@@ -38,6 +38,30 @@ pub async fn wasm_bindgen(
     Ok(())
 }
 
+/// Version of the wasm-bindgen backend, which must match the version of the
+/// `wasm-bindgen` crate the UI is built with.
+pub async fn wasm_bindgen_version() -> String {
+    #[cfg(feature = "wasm-bindgen-lib")]
+    {
+        "lib 0.2.93".to_owned()
+    }
+
+    #[cfg(not(feature = "wasm-bindgen-lib"))]
+    {
+        let output = Command::new("wasm-bindgen")
+            .arg("--version")
+            .stdout(std::process::Stdio::piped())
+            .output()
+            .await;
+        match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            _ => "unknown".to_owned(),
+        }
+    }
+}
+
 #[cfg(feature = "wasm-bindgen-lib")]
 async fn wasm_bindgen_lib(
     input_path: &Path,
@@ -175,3 +175,14 @@ impl WatchFiles {
 pub struct ChangedPaths {
     pub paths: HashSet<PathBuf>,
 }
+
+impl ChangedPaths {
+    /// Whether only stylesheets changed, so that the UI's CSS can be
+    /// regenerated without rebuilding the UI.
+    pub fn only_styles(&self) -> bool {
+        self.paths.iter().all(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "scss")
+        })
+    }
+}
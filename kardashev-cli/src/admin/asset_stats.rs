@@ -0,0 +1,54 @@
+use kardashev_client::ApiClient;
+
+use crate::admin::{
+    utils::format_bytes,
+    Error,
+};
+
+pub async fn asset_stats(api: &ApiClient, limit: usize) -> Result<(), Error> {
+    let stats = api.asset_stats().await?;
+
+    let total_requests: u64 = stats
+        .downloads
+        .iter()
+        .map(|download| download.requests)
+        .sum();
+    let total_bytes: u64 = stats.downloads.iter().map(|download| download.bytes).sum();
+    println!(
+        "Since {}: {} requests, {}",
+        stats.since,
+        total_requests,
+        format_bytes(total_bytes)
+    );
+
+    println!();
+    println!("Most downloaded:");
+    for download in stats.downloads.iter().take(limit) {
+        let share = if total_bytes > 0 {
+            100.0 * download.bytes as f64 / total_bytes as f64
+        }
+        else {
+            0.0
+        };
+        println!(
+            "{:>10} {:>5.1}% {:>8} requests  {}",
+            format_bytes(download.bytes).to_string(),
+            share,
+            download.requests,
+            download.path
+        );
+    }
+
+    if !stats.unrequested.is_empty() {
+        println!();
+        println!("Never requested:");
+        for path in stats.unrequested.iter().take(limit) {
+            println!("  {path}");
+        }
+        if stats.unrequested.len() > limit {
+            println!("  ... and {} more", stats.unrequested.len() - limit);
+        }
+    }
+
+    Ok(())
+}
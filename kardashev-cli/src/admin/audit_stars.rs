@@ -0,0 +1,81 @@
+use kardashev_client::ApiClient;
+use kardashev_protocol::admin::{
+    StarIssue,
+    StarIssueKind,
+};
+
+use crate::admin::Error;
+
+pub async fn audit_stars(api: &ApiClient, fix: bool, limit: usize) -> Result<(), Error> {
+    let response = api.audit_stars(fix).await?;
+
+    for issue in response.issues.iter().take(limit) {
+        println!("{}", format_issue(issue));
+    }
+    if response.issues.len() > limit {
+        println!("... and {} more", response.issues.len() - limit);
+    }
+
+    let num_fixable = response
+        .issues
+        .iter()
+        .filter(|issue| issue.kind.is_fixable())
+        .count();
+    println!(
+        "Audited {} stars: {} issues, {} fixable",
+        response.num_stars,
+        response.issues.len(),
+        num_fixable
+    );
+    if fix {
+        println!("Fixed {} issues", response.num_fixed);
+    }
+    else if num_fixable > 0 {
+        println!("Run with --fix to fix them");
+    }
+
+    Ok(())
+}
+
+fn format_issue(issue: &StarIssue) -> String {
+    let star = match &issue.name {
+        Some(name) => format!("{name} ({})", issue.star.0),
+        None => issue.star.0.to_string(),
+    };
+
+    let description = match &issue.kind {
+        StarIssueKind::OutOfRange { property, value } => {
+            format!("{property} out of range: {value}")
+        }
+        StarIssueKind::Color { stored, expected } => {
+            format!(
+                "color {:?} should be {:?}",
+                (stored.red, stored.green, stored.blue),
+                (expected.red, expected.green, expected.blue)
+            )
+        }
+        StarIssueKind::AbsoluteMagnitude { stored, expected } => {
+            format!("absolute magnitude {stored} should be {expected}")
+        }
+        StarIssueKind::HabitableZone {
+            stored: Some(stored),
+            expected,
+        } => {
+            format!(
+                "habitable zone {}-{} AU should be {}-{} AU",
+                stored.inner, stored.outer, expected.inner, expected.outer
+            )
+        }
+        StarIssueKind::HabitableZone {
+            stored: None,
+            expected,
+        } => {
+            format!(
+                "habitable zone missing, should be {}-{} AU",
+                expected.inner, expected.outer
+            )
+        }
+    };
+
+    format!("{star}: {description}")
+}
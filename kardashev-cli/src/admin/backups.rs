@@ -0,0 +1,66 @@
+use kardashev_client::ApiClient;
+use kardashev_protocol::admin::Backup;
+
+use crate::admin::{
+    utils::format_bytes,
+    Error,
+};
+
+pub async fn create_backup(api: &ApiClient) -> Result<(), Error> {
+    println!("Backing up...");
+    let backup = api.create_backup().await?;
+    print_backup(&backup);
+    for table in &backup.tables {
+        println!(
+            "  {:<20} {:>10} rows {:>10}",
+            table.table,
+            table.rows,
+            format_bytes(table.bytes).to_string()
+        );
+    }
+    Ok(())
+}
+
+pub async fn list_backups(api: &ApiClient) -> Result<(), Error> {
+    let response = api.get_backups().await?;
+
+    let Some(target) = response.target
+    else {
+        println!("Backups are disabled.");
+        return Ok(());
+    };
+    println!("Target: {target}");
+    match response.interval {
+        Some(interval) => println!("Scheduled every {} minutes", interval / 60),
+        None => println!("Not scheduled"),
+    }
+
+    println!();
+    if response.backups.is_empty() {
+        println!("No backups since the server started.");
+    }
+    for backup in &response.backups {
+        print_backup(backup);
+    }
+
+    Ok(())
+}
+
+fn print_backup(backup: &Backup) {
+    let bytes = backup.tables.iter().map(|table| table.bytes).sum();
+    if let Some(error) = &backup.error {
+        println!("{}  failed: {error}", backup.name);
+    }
+    else if let Some(finished_at) = backup.finished_at {
+        println!(
+            "{}  {} tables, {}, took {}s",
+            backup.name,
+            backup.tables.len(),
+            format_bytes(bytes),
+            (finished_at - backup.started_at).num_seconds()
+        );
+    }
+    else {
+        println!("{}  running", backup.name);
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::Path,
+};
+
+use color_eyre::eyre::Error;
+use serde::Deserialize;
+
+// see: https://exoplanetarchive.ipac.caltech.edu/docs/API_PS_columns.html
+//
+// The CSV must be exported from the "Planetary Systems Composite Parameters"
+// table. Any other columns are ignored, as are the comment lines at the top.
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Record {
+    pub pl_name: String,
+    pub hostname: String,
+    pub hd_name: Option<String>,
+    pub hip_name: Option<String>,
+    /// Orbital period in days
+    pub pl_orbper: Option<f32>,
+    /// Semi-major axis in AU
+    pub pl_orbsmax: Option<f32>,
+    pub pl_orbeccen: Option<f32>,
+    /// Mass (or minimum mass) in earth masses
+    pub pl_bmasse: Option<f32>,
+    /// Radius in earth radii
+    pub pl_rade: Option<f32>,
+    /// Equilibrium temperature in Kelvin
+    pub pl_eqt: Option<f32>,
+    pub disc_year: Option<i32>,
+    /// 1 if the planet's existence has been questioned in the literature
+    pub pl_controv_flag: Option<u8>,
+}
+
+impl Record {
+    pub fn hip(&self) -> Option<u32> {
+        parse_designation(self.hip_name.as_deref()?, "HIP")
+    }
+
+    pub fn hd(&self) -> Option<u32> {
+        parse_designation(self.hd_name.as_deref()?, "HD")
+    }
+
+    pub fn is_controversial(&self) -> bool {
+        self.pl_controv_flag == Some(1)
+    }
+}
+
+/// Parses designations like `HIP 12345` or `HD 1234 A`.
+fn parse_designation(designation: &str, catalog: &str) -> Option<u32> {
+    let mut parts = designation.split_whitespace();
+    if parts.next()? != catalog {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+pub struct Reader {
+    reader: csv::DeserializeRecordsIntoIter<BufReader<File>, Record>,
+}
+
+impl Reader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let reader = csv::ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_reader(reader);
+        let reader = reader.into_deserialize();
+        Ok(Self { reader })
+    }
+
+    pub fn read_record(&mut self) -> Result<Option<Record>, Error> {
+        self.reader.next().transpose().map_err(Into::into)
+    }
+}
+
+impl Iterator for Reader {
+    type Item = Result<Record, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
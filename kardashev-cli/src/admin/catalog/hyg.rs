@@ -7,6 +7,7 @@ use std::{
 };
 
 use color_eyre::eyre::Error;
+use kardashev_protocol::frames::Equatorial;
 use serde::Deserialize;
 
 // see: https://github.com/astronexus/HYG-Database/tree/main/hyg
@@ -58,6 +59,18 @@ pub struct Record {
     pub var_max: Option<f32>,
 }
 
+impl Record {
+    /// Position in equatorial coordinates. The catalog's `x`, `y` and `z` are
+    /// the same position in the game's frame.
+    pub fn equatorial(&self) -> Equatorial {
+        Equatorial {
+            right_ascension: self.rarad,
+            declination: self.decrad,
+            distance: self.dist,
+        }
+    }
+}
+
 pub struct Reader {
     reader: csv::DeserializeRecordsIntoIter<BufReader<File>, Record>,
 }
@@ -1,4 +1,5 @@
 //pub mod bright_stars;
+pub mod exoplanets;
 //pub mod gaia;
 //pub mod gliese;
 pub mod hyg;
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use indicatif::{
+    ProgressBar,
+    ProgressStyle,
+};
+use itertools::Itertools;
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    admin::{
+        CreatePlanet,
+        HostStar,
+    },
+    model::star::CatalogIds,
+};
+
+use crate::admin::{
+    catalog::exoplanets::{
+        self,
+        Record,
+    },
+    Error,
+};
+
+pub async fn import_exoplanets(
+    api: &ApiClient,
+    path: impl AsRef<Path>,
+    batch_size: usize,
+    include_controversial: bool,
+) -> Result<(), Error> {
+    let reader = exoplanets::Reader::open(path)?;
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.blue} {msg}")
+            .unwrap()
+            .tick_strings(&["-", "\\", "|", "/"]),
+    );
+    pb.set_message("reading exoplanets...");
+
+    let planets = reader.collect::<Result<Vec<Record>, Error>>()?;
+
+    let mut num_created = 0;
+    let mut unmatched = vec![];
+
+    let chunks = planets
+        .into_iter()
+        .filter(|record| include_controversial || !record.is_controversial())
+        .chunks(batch_size);
+    for chunk in &chunks {
+        let mut batch = vec![];
+
+        for record in chunk {
+            pb.set_message(record.pl_name.clone());
+            pb.tick();
+
+            batch.push(CreatePlanet {
+                host: HostStar {
+                    name: Some(record.hostname.clone()),
+                    catalog_ids: CatalogIds {
+                        hip: record.hip(),
+                        hd: record.hd(),
+                        ..Default::default()
+                    },
+                },
+                confirmed: !record.is_controversial(),
+                name: Some(record.pl_name),
+                semi_major_axis: record.pl_orbsmax,
+                eccentricity: record.pl_orbeccen,
+                orbital_period: record.pl_orbper,
+                mass: record.pl_bmasse,
+                radius: record.pl_rade,
+                equilibrium_temperature: record.pl_eqt,
+                discovery_year: record.disc_year,
+            });
+        }
+
+        pb.set_message("uploading batch");
+        pb.tick();
+
+        let response = api.create_planets(batch).await?;
+        num_created += response.ids.len();
+        unmatched.extend(response.unmatched);
+    }
+
+    pb.finish_with_message(format!(
+        "imported {num_created} planets, {} without host star",
+        unmatched.len()
+    ));
+
+    for name in unmatched {
+        tracing::debug!(%name, "no host star found");
+    }
+
+    Ok(())
+}
@@ -9,15 +9,19 @@ use kardashev_client::ApiClient;
 use kardashev_protocol::{
     admin::CreateStar,
     model::star::CatalogIds,
+    stellar::{
+        approximate_mass,
+        approximate_radius,
+        approximate_teff,
+        teff_color,
+    },
 };
-use nalgebra::Point3;
 
 use crate::admin::{
     catalog::hyg::{
         self,
         Record,
     },
-    utils::teff_color::teff_color,
     Error,
 };
 
@@ -58,9 +62,14 @@ pub async fn import_stars(
             let r = approximate_radius(m);
             let t_eff = approximate_teff(record.lum, r);
             let color = teff_color(t_eff);
+            let variability = record
+                .var
+                .as_ref()
+                .and(record.var_min.zip(record.var_max))
+                .map(|(min, max)| (max - min).abs());
 
             batch.push(CreateStar {
-                position: Point3::new(record.x, record.y, record.z),
+                position: record.equatorial().to_game(),
                 effective_temperature: t_eff,
                 color: color.into(),
                 absolute_magnitude: record.absmag,
@@ -77,6 +86,7 @@ pub async fn import_stars(
                     gl: record.gl,
                     bf: record.bf,
                 },
+                variability,
             });
 
             if let Some(name) = record.proper {
@@ -94,33 +104,17 @@ pub async fn import_stars(
         api.create_stars(batch).await?;
     }
 
-    Ok(())
-}
+    pb.set_message("computing regions");
+    pb.tick();
+    let num_regions = api.recompute_regions().await?;
 
-fn approximate_mass(lum: f32) -> f32 {
-    if lum < 0.033 {
-        4.3 * lum.powf(0.43)
-    }
-    else if lum < 16. {
-        lum.powf(0.25)
-    }
-    else if lum < 1700000. {
-        0.7 * lum.powf(0.3)
-    }
-    else {
-        0.000031 * lum
-    }
-}
+    pb.set_message("computing impostors");
+    pb.tick();
+    let num_chunks = api.recompute_impostors().await?;
 
-fn approximate_radius(m: f32) -> f32 {
-    if m < 1. {
-        m.powf(0.8)
-    }
-    else {
-        m.powf(0.5)
-    }
-}
+    pb.finish_with_message(format!(
+        "found {num_regions} regions, computed impostors for {num_chunks} chunks"
+    ));
 
-fn approximate_teff(lum: f32, r: f32) -> f32 {
-    (lum / r.powi(2)).powf(0.25) * 5778.0
+    Ok(())
 }
@@ -1,16 +1,59 @@
+mod asset_stats;
+mod audit_stars;
+mod backups;
 mod catalog;
+mod import_exoplanets;
 mod import_stars;
+mod simulation;
 mod utils;
+mod webhooks;
+mod worlds;
 
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
 
 use chrono::Utc;
 use color_eyre::eyre::Error;
-use kardashev_client::ApiClient;
+use kardashev_client::{
+    ApiClient,
+    RetryPolicy,
+};
+use kardashev_protocol::{
+    admin::{
+        WebhookEvent,
+        WebhookId,
+    },
+    model::world::WorldId,
+    uuid::Uuid,
+};
 use url::Url;
 use utils::format_uptime;
 
-use crate::admin::import_stars::import_stars;
+use crate::admin::{
+    asset_stats::asset_stats,
+    audit_stars::audit_stars,
+    backups::{
+        create_backup,
+        list_backups,
+    },
+    import_exoplanets::import_exoplanets,
+    import_stars::import_stars,
+    simulation::{
+        simulation_state,
+        tick,
+    },
+    webhooks::{
+        add_webhook,
+        list_webhooks,
+        remove_webhook,
+    },
+    worlds::{
+        create_world,
+        list_worlds,
+    },
+};
 
 /// Send administrative commands to the server API.
 #[derive(Debug, clap::Args)]
@@ -23,6 +66,24 @@ pub struct Args {
     )]
     api_url: Url,
 
+    /// The world in which commands are run. Defaults to the server's default
+    /// world.
+    #[arg(long, env = "KARDASHEV_WORLD")]
+    world: Option<WorldId>,
+
+    /// How often a request that fails because of the network is attempted.
+    #[arg(long, env = "KARDASHEV_MAX_ATTEMPTS", default_value = "4", value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Seconds after which a request is aborted. Without one, requests don't
+    /// time out, e.g. for large imports.
+    #[arg(long, env = "KARDASHEV_REQUEST_TIMEOUT")]
+    timeout_seconds: Option<u64>,
+
+    /// The server's admin token. The server rejects admin commands without it.
+    #[arg(long, env = "KARDASHEV_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -44,11 +105,121 @@ pub enum Command {
         #[arg(long)]
         num_closest: Option<usize>,
     },
+
+    /// Import real exoplanets into the database.
+    ///
+    /// Input file must be a CSV export of the NASA Exoplanet Archive's
+    /// "Planetary Systems Composite Parameters" table. Planets are matched to
+    /// their host stars, which must have been imported before.
+    ImportExoplanets {
+        /// Input file (NASA Exoplanet Archive CSV)
+        path: PathBuf,
+
+        /// How many planets to send to the server in one request.
+        #[arg(long, default_value = "100")]
+        batch_size: usize,
+
+        /// Also import planets whose existence is controversial. They're not
+        /// marked as confirmed.
+        #[arg(long)]
+        include_controversial: bool,
+    },
+
+    /// Check the stars' derived properties.
+    ///
+    /// Recomputes colors, absolute magnitudes and habitable zones, and reports
+    /// stars for which they differ from the stored ones, or that have
+    /// implausible values.
+    AuditStars {
+        /// Fix the issues by storing the recomputed properties.
+        #[arg(long)]
+        fix: bool,
+
+        /// How many issues to print.
+        #[arg(long, default_value = "50")]
+        limit: usize,
+    },
+
+    /// Show how often the assets were downloaded.
+    ///
+    /// Lists the assets that use the most bandwidth, and the ones that were
+    /// never requested since the server started.
+    AssetStats {
+        /// How many assets to list.
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show the simulation's epoch and the hashes of its state.
+    ///
+    /// Servers that simulated the same epochs from the same state have the
+    /// same hashes.
+    SimulationState,
+
+    /// Run simulation epochs now, instead of waiting for them.
+    ///
+    /// The server must have been started with `--allow-forced-ticks`.
+    Tick {
+        /// How many epochs to run.
+        #[arg(default_value = "1")]
+        ticks: u32,
+    },
+
+    /// Back up the world's tables now.
+    ///
+    /// The server must have been started with a backup target.
+    Backup,
+
+    /// Show the backup configuration and the recent backups.
+    ListBackups,
+
+    /// List the webhooks to which the world's events are posted.
+    ListWebhooks,
+
+    /// Post the world's events to a webhook.
+    ///
+    /// The events are sent as JSON that Discord and Slack webhooks accept.
+    AddWebhook {
+        /// URL of the webhook.
+        url: Url,
+
+        /// Only post this event. Can be repeated. Without it, all events are
+        /// posted.
+        #[arg(long = "event")]
+        events: Vec<WebhookEvent>,
+    },
+
+    /// Stop posting events to a webhook.
+    RemoveWebhook {
+        /// ID of the webhook, as shown by `list-webhooks`.
+        id: Uuid,
+    },
+
+    /// List the worlds hosted by the server.
+    ListWorlds,
+
+    /// Create a new, empty world.
+    ///
+    /// Select it with `--world` to import stars into it.
+    CreateWorld {
+        /// Unique name of the world.
+        name: String,
+    },
 }
 
 impl Args {
     pub async fn run(self) -> Result<(), Error> {
-        let api = ApiClient::new(self.api_url);
+        let mut api = ApiClient::new(self.api_url).with_retry_policy(
+            RetryPolicy::default()
+                .with_max_attempts(self.max_attempts)
+                .with_timeout(self.timeout_seconds.map(Duration::from_secs)),
+        );
+        if let Some(world) = self.world {
+            api = api.with_world(world);
+        }
+        if let Some(admin_token) = self.admin_token {
+            api = api.with_admin_token(admin_token);
+        }
 
         let status = api.status().await?;
         println!("Server version: {}", status.server_version);
@@ -66,6 +237,22 @@ impl Args {
                     batch_size,
                     num_closest,
                 } => import_stars(&api, path, batch_size, num_closest).await?,
+                Command::ImportExoplanets {
+                    path,
+                    batch_size,
+                    include_controversial,
+                } => import_exoplanets(&api, path, batch_size, include_controversial).await?,
+                Command::AuditStars { fix, limit } => audit_stars(&api, fix, limit).await?,
+                Command::AssetStats { limit } => asset_stats(&api, limit).await?,
+                Command::SimulationState => simulation_state(&api).await?,
+                Command::Tick { ticks } => tick(&api, ticks).await?,
+                Command::Backup => create_backup(&api).await?,
+                Command::ListBackups => list_backups(&api).await?,
+                Command::ListWebhooks => list_webhooks(&api).await?,
+                Command::AddWebhook { url, events } => add_webhook(&api, url, events).await?,
+                Command::RemoveWebhook { id } => remove_webhook(&api, WebhookId(id)).await?,
+                Command::ListWorlds => list_worlds(&api).await?,
+                Command::CreateWorld { name } => create_world(&api, name).await?,
             }
         }
 
@@ -0,0 +1,26 @@
+use kardashev_client::ApiClient;
+
+use crate::admin::Error;
+
+pub async fn simulation_state(api: &ApiClient) -> Result<(), Error> {
+    let state = api.simulation_state().await?;
+
+    println!("Epoch: {} (at {})", state.epoch, state.epoch_at);
+    println!(
+        "State hash after epoch: {}",
+        state.epoch_state_hash.as_deref().unwrap_or("-")
+    );
+    println!("Current state hash: {}", state.current_state_hash);
+
+    Ok(())
+}
+
+pub async fn tick(api: &ApiClient, ticks: u32) -> Result<(), Error> {
+    let response = api.simulate(ticks).await?;
+
+    println!("Simulated {} epochs.", response.ticks);
+    println!("Epoch: {}", response.epoch);
+    println!("State hash: {}", response.state_hash);
+
+    Ok(())
+}
@@ -1,5 +1,3 @@
-pub mod teff_color;
-
 use std::fmt::Display;
 
 use chrono::TimeDelta;
@@ -23,3 +21,28 @@ impl Display for FormattedUptime {
         write!(f, "{hours}h {minutes}m {seconds}s")
     }
 }
+
+pub fn format_bytes(bytes: u64) -> FormattedBytes {
+    FormattedBytes(bytes)
+}
+
+#[derive(Debug)]
+pub struct FormattedBytes(u64);
+
+impl Display for FormattedBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{value:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,50 @@
+use kardashev_client::ApiClient;
+use kardashev_protocol::admin::{
+    Webhook,
+    WebhookEvent,
+    WebhookId,
+};
+use url::Url;
+
+use crate::admin::Error;
+
+pub async fn list_webhooks(api: &ApiClient) -> Result<(), Error> {
+    let webhooks = api.get_webhooks().await?;
+
+    if webhooks.is_empty() {
+        println!("No webhooks.");
+    }
+    for webhook in &webhooks {
+        print_webhook(webhook);
+    }
+
+    Ok(())
+}
+
+pub async fn add_webhook(
+    api: &ApiClient,
+    url: Url,
+    events: Vec<WebhookEvent>,
+) -> Result<(), Error> {
+    let webhook = api.create_webhook(url, events).await?;
+    print!("Added ");
+    print_webhook(&webhook);
+    Ok(())
+}
+
+pub async fn remove_webhook(api: &ApiClient, id: WebhookId) -> Result<(), Error> {
+    api.delete_webhook(id).await?;
+    println!("Removed webhook {}", id.0);
+    Ok(())
+}
+
+fn print_webhook(webhook: &Webhook) {
+    let events = webhook
+        .events
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("{}  {}", webhook.id.0, webhook.url);
+    println!("  events: {events}");
+}
@@ -0,0 +1,23 @@
+use kardashev_client::ApiClient;
+
+use crate::admin::Error;
+
+pub async fn list_worlds(api: &ApiClient) -> Result<(), Error> {
+    let worlds = api.get_worlds().await?;
+
+    for world in worlds {
+        println!(
+            "{}  {:<20} created {}",
+            world.id.0, world.name, world.created_at
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn create_world(api: &ApiClient, name: String) -> Result<(), Error> {
+    let world = api.create_world(name).await?;
+    println!("Created world {} ({})", world.name, world.id.0);
+    println!("Select it with --world {}", world.id.0);
+    Ok(())
+}
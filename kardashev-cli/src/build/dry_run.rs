@@ -0,0 +1,77 @@
+use kardashev_build::assets::processor::{
+    DryRun,
+    Processor,
+};
+
+use crate::{
+    build::BuildOptions,
+    Error,
+};
+
+/// Processes the assets without writing to the dist directory, and prints
+/// which assets would be rebuilt and why.
+///
+/// With `diff`, also prints how the dist manifest would change.
+pub async fn dry_run(build_options: &BuildOptions, diff: bool) -> Result<(), Error> {
+    let mut processor = Processor::new(build_options.dist_path.join("assets"))?;
+    processor.set_max_atlas_page_size(build_options.max_atlas_size);
+    processor.add_directory(&build_options.assets_path)?;
+    let DryRun {
+        processed,
+        diff: manifest_diff,
+    } = processor.dry_run(build_options.clean).await?;
+
+    let mut rebuilt = processed.changed.into_iter().collect::<Vec<_>>();
+    rebuilt.sort();
+    if rebuilt.is_empty() {
+        println!("Nothing to rebuild.");
+    }
+    else {
+        println!("Would rebuild:");
+        for id in rebuilt {
+            let reasons = processed
+                .reasons
+                .get(&id)
+                .map(|reasons| {
+                    reasons
+                        .iter()
+                        .map(|reason| reason.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            println!("  {id}: {reasons}");
+        }
+    }
+
+    let mut removed = processed.removed.into_iter().collect::<Vec<_>>();
+    removed.sort();
+    if !removed.is_empty() {
+        println!();
+        println!("Would remove:");
+        for id in removed {
+            println!("  {id}");
+        }
+    }
+
+    if diff {
+        println!();
+        if manifest_diff.is_empty() {
+            println!("The dist manifest would not change.");
+        }
+        else {
+            println!("Dist manifest changes:");
+            for id in &manifest_diff.added {
+                println!("  + {id}");
+            }
+            for id in &manifest_diff.removed {
+                println!("  - {id}");
+            }
+            for id in &manifest_diff.changed {
+                println!("  ~ {id}");
+            }
+        }
+    }
+
+    Ok(())
+}
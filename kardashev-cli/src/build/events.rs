@@ -0,0 +1,108 @@
+//! Events about rebuilds while watching, which the server streams to the
+//! browser.
+//!
+//! The UI events are streamed as server-sent events, while the diffs of the
+//! asset rebuilds are sent on the asset server's `events` websocket.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use kardashev_protocol::{
+    assets as dist,
+    ui::{
+        BuildTarget,
+        Event,
+    },
+};
+use tokio::sync::broadcast;
+
+#[derive(Clone, Debug)]
+pub struct BuildEvents {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    sender: broadcast::Sender<Event>,
+
+    assets: broadcast::Sender<dist::Event>,
+
+    /// The failed builds, so that browsers that connect later also show the
+    /// errors.
+    failures: Mutex<BTreeMap<BuildTarget, String>>,
+}
+
+impl BuildEvents {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(16);
+        let (assets, _) = broadcast::channel(16);
+        Self {
+            inner: Arc::new(Inner {
+                sender,
+                assets,
+                failures: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    pub fn send(&self, event: Event) {
+        let mut failures = self.inner.failures.lock().unwrap();
+        match &event {
+            Event::StyleChanged | Event::Rebuilt => {
+                failures.remove(&BuildTarget::Ui);
+            }
+            Event::AssetsRebuilt => {
+                failures.remove(&BuildTarget::Assets);
+            }
+            Event::BuildFailed { target, message } => {
+                failures.insert(*target, message.clone());
+            }
+        }
+        // nobody might be listening.
+        let _ = self.inner.sender.send(event);
+    }
+
+    /// Sends that the assets were rebuilt, and the diff to the asset events.
+    pub fn assets_rebuilt(&self, diff: dist::AssetsDiff) {
+        self.send(Event::AssetsRebuilt);
+        if !diff.is_empty() {
+            let _ = self.inner.assets.send(dist::Event::Rebuilt { diff });
+        }
+    }
+
+    /// Sends the error and its sources.
+    pub fn failed(&self, target: BuildTarget, error: &(dyn std::error::Error + 'static)) {
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(error) = source {
+            message.push_str(&format!("\n\nCaused by: {error}"));
+            source = error.source();
+        }
+        self.send(Event::BuildFailed { target, message });
+    }
+
+    /// Returns the current failures, and a receiver for the following events.
+    pub fn subscribe(&self) -> (Vec<Event>, broadcast::Receiver<Event>) {
+        let failures = self.inner.failures.lock().unwrap();
+        let current = failures
+            .iter()
+            .map(|(target, message)| {
+                Event::BuildFailed {
+                    target: *target,
+                    message: message.clone(),
+                }
+            })
+            .collect();
+        (current, self.inner.sender.subscribe())
+    }
+
+    /// Returns a receiver for the asset events.
+    pub fn subscribe_assets(&self) -> broadcast::Receiver<dist::Event> {
+        self.inner.assets.subscribe()
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use kardashev_build::image::{
+    build_image,
+    ImageOptions,
+};
+
+use crate::{
+    build::BuildOptions,
+    Error,
+};
+
+/// Build a container image with the server, UI and assets.
+///
+/// Makes a release build and assembles it with a `Dockerfile` and a
+/// `compose.yaml` in the output directory. The paths to the assets and UI are
+/// taken from the build options.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Directory in which the image is assembled. Its contents are replaced.
+    #[arg(long, default_value = "./target/image/")]
+    output: PathBuf,
+
+    /// Tag of the image.
+    #[arg(long, default_value = "kardashev:latest")]
+    tag: String,
+
+    /// Image on which the image is based. It must have a C library that is
+    /// compatible with the one the server was built with.
+    #[arg(long, default_value = "debian:bookworm-slim")]
+    base_image: String,
+
+    /// Port on which the server listens in the container.
+    #[arg(long, default_value = "3333")]
+    port: u16,
+
+    /// Only assemble the directory, don't build the image.
+    #[arg(long)]
+    no_build: bool,
+
+    /// Tool with which the image is built, e.g. `podman`.
+    #[arg(long, env = "KARDASHEV_CONTAINER_TOOL", default_value = "docker")]
+    container_tool: String,
+}
+
+impl Args {
+    pub async fn run(self, build_options: &BuildOptions) -> Result<(), Error> {
+        build_image(&ImageOptions {
+            workspace_path: build_options.ui_path.clone(),
+            assets_path: build_options.assets_path.clone(),
+            ui_path: build_options.ui_path.clone(),
+            content_security_policy: build_options.content_security_policy(),
+            cache: build_options.build_cache(),
+            output_path: self.output.clone(),
+            base_image: self.base_image,
+            tag: self.tag.clone(),
+            port: self.port,
+            container_tool: (!self.no_build).then_some(self.container_tool),
+        })
+        .await?;
+
+        if self.no_build {
+            println!("Image assembled in {}", self.output.display());
+        }
+        else {
+            println!("Built image {}", self.tag);
+        }
+        println!(
+            "Run it with `docker compose up` in {}",
+            self.output.display()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,305 @@
+mod dry_run;
+mod events;
+mod image;
+
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
+
+use kardashev_build::{
+    assets::{
+        atlas::DEFAULT_MAX_PAGE_SIZE,
+        processor::Processor,
+        storage::DistStorage,
+    },
+    cache::BuildCache,
+    ui::{
+        compile_styles,
+        compile_ui,
+        csp::ContentSecurityPolicy,
+    },
+    util::watch::WatchFiles,
+};
+use kardashev_protocol::ui::{
+    BuildTarget,
+    Event as UiEvent,
+};
+use url::Url;
+
+pub use crate::build::events::BuildEvents;
+use crate::{
+    util::shutdown::GracefulShutdown,
+    Error,
+};
+
+/// Build assets and UI.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    #[command(flatten)]
+    build_options: BuildOptions,
+
+    /// Only report which assets would be rebuilt and why, without writing to
+    /// the dist directory.
+    #[arg(long, conflicts_with = "watch")]
+    dry_run: bool,
+
+    /// Compare the dist manifest that would be built with the current one.
+    /// Implies `--dry-run`.
+    #[arg(long, conflicts_with = "watch")]
+    diff: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    Image(crate::build::image::Args),
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        if let Some(command) = self.command {
+            match command {
+                Command::Image(args) => args.run(&self.build_options).await?,
+            }
+            return Ok(());
+        }
+
+        if self.dry_run || self.diff {
+            return dry_run::dry_run(&self.build_options, self.diff).await;
+        }
+
+        let mut shutdown = GracefulShutdown::new();
+
+        self.build_options.spawn(&mut shutdown).await?;
+
+        shutdown.join().await
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BuildOptions {
+    /// Path to the dist directory. This is where the generated files will be
+    /// stored.
+    #[arg(long = "dist", env = "KARDASHEV_DIST", default_value = "./dist/")]
+    pub dist_path: PathBuf,
+
+    /// Build assets
+    #[arg(long)]
+    pub assets: bool,
+
+    /// Path to the assets directory. This should contain one or more
+    /// `Asset.toml` files.
+    #[arg(long, env = "KARDASHEV_ASSETS", default_value = "./assets/")]
+    pub assets_path: PathBuf,
+
+    /// Object storage to which the processed assets are synced, e.g.
+    /// `s3://bucket/assets`. S3 is configured with the `AWS_*` environment
+    /// variables. When serving, asset requests are redirected to the storage.
+    #[arg(long, env = "KARDASHEV_ASSET_STORAGE")]
+    pub asset_storage: Option<Url>,
+
+    /// Public URL of the asset storage, e.g. a CDN. Asset requests are
+    /// redirected to it, instead of to presigned URLs.
+    #[arg(long, env = "KARDASHEV_ASSET_PUBLIC_URL", requires = "asset_storage")]
+    pub asset_public_url: Option<Url>,
+
+    /// Maximum width and height of texture atlas pages, in pixels. Textures
+    /// that don't fit into a page spill into another one.
+    #[arg(long, env = "KARDASHEV_MAX_ATLAS_SIZE", default_value_t = DEFAULT_MAX_PAGE_SIZE)]
+    pub max_atlas_size: u32,
+
+    /// Build UI
+    #[arg(long)]
+    pub ui: bool,
+
+    /// Path to the UI crate.
+    #[arg(long, env = "KARDASHEV_UI", default_value = "./kardashev-ui/")]
+    pub ui_path: PathBuf,
+
+    /// Origin of the API, if it's not served from the same origin as the UI.
+    /// It is allowed by the UI's content security policy.
+    #[arg(long, env = "KARDASHEV_API_ORIGIN", value_delimiter = ',')]
+    pub api_origin: Vec<Url>,
+
+    /// Origin from which assets are loaded, e.g. the endpoint of the asset
+    /// storage, to which presigned URLs point. It is allowed by the UI's
+    /// content security policy. The public URL of the asset storage is always
+    /// allowed.
+    #[arg(long, env = "KARDASHEV_ASSET_ORIGIN", value_delimiter = ',')]
+    pub asset_origin: Vec<Url>,
+
+    /// Watch for file changes.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// After a file change, wait N seconds before rebuilding to avoid to many
+    /// rebuild events.
+    #[arg(long, default_value = "2")]
+    pub debounce: f32,
+
+    /// Disable debounce.
+    #[arg(long)]
+    pub no_debounce: bool,
+
+    /// Start with a clean build.
+    #[arg(long)]
+    pub clean: bool,
+
+    /// Build the UI with the release profile.
+    #[arg(long)]
+    pub release: bool,
+
+    /// Directory in which build outputs are cached by the hashes of their
+    /// inputs, e.g. `~/.cache/kardashev`. Builds are restored from it if
+    /// nothing changed, even in other checkouts of the repository.
+    #[arg(long, env = "KARDASHEV_BUILD_CACHE")]
+    pub build_cache: Option<PathBuf>,
+}
+
+impl BuildOptions {
+    /// The asset storage, if one is configured.
+    pub fn asset_storage(&self) -> Result<Option<DistStorage>, Error> {
+        let Some(url) = &self.asset_storage
+        else {
+            return Ok(None);
+        };
+
+        let mut storage = DistStorage::from_url(url.clone())?;
+        if let Some(public_url) = &self.asset_public_url {
+            storage = storage.with_public_url(public_url.clone());
+        }
+        Ok(Some(storage))
+    }
+
+    pub fn build_cache(&self) -> Option<BuildCache> {
+        self.build_cache.as_ref().map(BuildCache::new)
+    }
+
+    /// The origins that the UI's content security policy allows.
+    pub fn content_security_policy(&self) -> ContentSecurityPolicy {
+        ContentSecurityPolicy {
+            api_origins: self.api_origin.clone(),
+            asset_origins: self
+                .asset_origin
+                .iter()
+                .chain(&self.asset_public_url)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Builds the assets and UI, and spawns the tasks that rebuild them when
+    /// watching.
+    ///
+    /// Returns the events about the rebuilds, to which the server can
+    /// subscribe.
+    pub async fn spawn(&self, shutdown: &mut GracefulShutdown) -> Result<BuildEvents, Error> {
+        let build_events = BuildEvents::new();
+        let debounce = (!self.no_debounce).then(|| Duration::from_secs_f32(self.debounce));
+
+        if self.assets {
+            let dist_assets = self.dist_path.join("assets");
+            let mut processor = Processor::new(&dist_assets)?;
+            processor.set_max_atlas_page_size(self.max_atlas_size);
+            if self.watch {
+                processor.watch_source_files()?;
+            }
+            if let Some(storage) = self.asset_storage()? {
+                processor.sync_to_storage(storage);
+            }
+            if let Some(cache) = self.build_cache() {
+                processor.use_cache(cache);
+            }
+            processor.add_directory(&self.assets_path)?;
+            processor.process(self.clean).await?;
+
+            if self.watch {
+                let build_events = build_events.clone();
+                let token = shutdown.token();
+                shutdown.spawn(async move {
+                    loop {
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            changes_option = processor.wait_for_changes(debounce) => {
+                                let Some(_changes) = changes_option else { break; };
+                                match processor.process(false).await {
+                                    Ok(processed) => {
+                                        build_events.assets_rebuilt(processed.diff);
+                                    }
+                                    Err(error) => {
+                                        tracing::error!(%error);
+                                        build_events.failed(BuildTarget::Assets, &error);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    Ok(())
+                });
+            }
+        }
+
+        if self.ui {
+            let dist_ui = self.dist_path.join("ui");
+            let content_security_policy = self.content_security_policy();
+            let cache = self.build_cache();
+            compile_ui(
+                &self.ui_path,
+                &dist_ui,
+                self.clean,
+                self.release,
+                &content_security_policy,
+                cache.as_ref(),
+            )
+            .await?;
+
+            if self.watch {
+                let ui_path = self.ui_path.clone();
+                let release = self.release;
+                let build_events = build_events.clone();
+                let mut watch_files = WatchFiles::new()?;
+                watch_files.watch(&ui_path)?;
+
+                let token = shutdown.token();
+                shutdown.spawn(async move {
+                    loop {
+                        tokio::select! {
+                            _ = token.cancelled() => break,
+                            changes_option = watch_files.next(debounce) => {
+                                let Some(changes) = changes_option else { break; };
+                                let result = async {
+                                    if changes.only_styles() && compile_styles(&ui_path, &dist_ui, &content_security_policy).await? {
+                                        return Ok(UiEvent::StyleChanged);
+                                    }
+                                    compile_ui(&ui_path, &dist_ui, false, release, &content_security_policy, cache.as_ref()).await?;
+                                    Ok::<_, kardashev_build::ui::Error>(UiEvent::Rebuilt)
+                                }.await;
+                                match result {
+                                    Ok(event) => {
+                                        build_events.send(event);
+                                    }
+                                    Err(error) => {
+                                        tracing::error!(%error);
+                                        build_events.failed(BuildTarget::Ui, &error);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    Ok(())
+                });
+            }
+        }
+
+        if self.watch {
+            tracing::info!("Watching for file changes...");
+        }
+
+        Ok(build_events)
+    }
+}
@@ -1,5 +1,8 @@
 mod admin;
 mod build;
+mod doctor;
+mod schema;
+mod selftest;
 mod serve;
 mod util;
 
@@ -8,7 +11,11 @@ use clap::{
     Parser,
 };
 use color_eyre::eyre::Error;
-use tracing_subscriber::EnvFilter;
+
+use crate::util::telemetry::{
+    self,
+    Telemetry,
+};
 
 const STYLES: styling::Styles = styling::Styles::styled()
     .header(styling::AnsiColor::Green.on_default().bold())
@@ -19,20 +26,35 @@ const STYLES: styling::Styles = styling::Styles::styled()
 /// Kardashev command line interface
 ///
 /// `kardashev-cli` can be used to send administrative commands to the server,
-/// build assets and UI and run the server.
+/// build assets and UI, export the protocol's schemas, check the local
+/// environment, run the server and test it end-to-end.
 #[derive(Debug, Parser)]
 #[command(version = clap::crate_version!(), styles = STYLES)]
 pub enum Args {
     Admin(crate::admin::Args),
     Build(crate::build::Args),
+    Doctor(crate::doctor::Args),
+    Schema(crate::schema::Args),
+    Selftest(crate::selftest::Args),
     Serve(crate::serve::Args),
 }
 
 impl Args {
+    /// Sets up logging, and for the server the export of traces and metrics.
+    pub fn init_telemetry(&self) -> Result<Telemetry, Error> {
+        match self {
+            Self::Serve(args) => telemetry::init(Some(&args.telemetry)),
+            _ => telemetry::init(None),
+        }
+    }
+
     pub async fn run(self) -> Result<(), Error> {
         match self {
             Self::Admin(args) => args.run().await?,
             Self::Build(args) => args.run().await?,
+            Self::Doctor(args) => args.run().await?,
+            Self::Schema(args) => args.run()?,
+            Self::Selftest(args) => args.run().await?,
             Self::Serve(args) => args.run().await?,
         }
 
@@ -44,12 +66,9 @@ impl Args {
 async fn main() -> Result<(), Error> {
     dotenvy::dotenv().ok();
     color_eyre::install()?;
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .pretty()
-        .init();
 
     let args = Args::parse();
+    let _telemetry = args.init_telemetry()?;
     args.run().await?;
 
     Ok(())
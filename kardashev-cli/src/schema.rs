@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use kardashev_protocol::schema::{
+    openapi,
+    replay_controls,
+    replay_events,
+    session_events,
+    star_events,
+};
+
+use crate::Error;
+
+/// Write JSON schemas of the protocol, for clients that aren't written in
+/// Rust.
+///
+/// Writes `openapi.json`, an OpenAPI 3.0 document of the HTTP API,
+/// `session-events.schema.json`, a JSON schema of the messages that the server
+/// sends over the `/session` websocket, and `replay-events.schema.json` and
+/// `replay-controls.schema.json` for the messages in both directions of the
+/// `/replay` websocket.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Directory to write the schemas to.
+    #[arg(default_value = "schema")]
+    output: PathBuf,
+}
+
+impl Args {
+    pub fn run(self) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.output)?;
+
+        let path = self.output.join("openapi.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&openapi())?)?;
+        println!("Wrote {}", path.display());
+
+        let path = self.output.join("session-events.schema.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&session_events())?)?;
+        println!("Wrote {}", path.display());
+
+        let path = self.output.join("star-events.schema.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&star_events())?)?;
+        println!("Wrote {}", path.display());
+
+        let path = self.output.join("replay-events.schema.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&replay_events())?)?;
+        println!("Wrote {}", path.display());
+
+        let path = self.output.join("replay-controls.schema.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&replay_controls())?)?;
+        println!("Wrote {}", path.display());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::bail;
+use kardashev_e2e::{
+    flows,
+    Harness,
+    HarnessConfig,
+};
+
+use crate::{
+    doctor::error_chain,
+    Error,
+};
+
+/// Start a throwaway server and run the end-to-end flows against it.
+///
+/// The server gets an embedded database and its own assets, which are removed
+/// afterwards. Checks that status, star imports, asset downloads and sessions
+/// work.
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Asset tree to process and serve. Defaults to the test fixtures.
+    #[arg(long)]
+    assets_path: Option<PathBuf>,
+}
+
+impl Args {
+    pub async fn run(self) -> Result<(), Error> {
+        let mut config = HarnessConfig::default();
+        if let Some(assets_path) = self.assets_path {
+            config.assets = assets_path;
+        }
+
+        let harness = Harness::start(config).await?;
+        println!("Server listening on {}", harness.address());
+
+        let results = flows::run_all(&harness).await;
+        harness.stop().await?;
+
+        let mut failed = 0;
+        for result in &results {
+            match &result.result {
+                Ok(()) => println!("✔ {} ({:?})", result.flow.name(), result.duration),
+                Err(error) => {
+                    failed += 1;
+                    println!("✘ {}: {}", result.flow.name(), error_chain(error));
+                }
+            }
+        }
+
+        if failed > 0 {
+            bail!("{} of {} flows failed", failed, results.len());
+        }
+        println!("All {} flows passed", results.len());
+        Ok(())
+    }
+}
@@ -1,13 +1,67 @@
-use std::net::SocketAddr;
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
 
 use axum::{
     extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
         MatchedPath,
+        Path,
         Request,
+        State,
+        WebSocketUpgrade,
+    },
+    http::StatusCode,
+    middleware,
+    response::{
+        sse::{
+            Event as SseEvent,
+            KeepAlive,
+            Sse,
+        },
+        IntoResponse,
+        Redirect,
+        Response,
     },
+    routing,
+    Json,
     Router,
 };
-use tokio::net::TcpListener;
+use color_eyre::eyre::bail;
+use kardashev_build::assets::storage::DistStorage;
+use kardashev_protocol::assets::{
+    self as dist,
+    ContentPack,
+    ContentPacks,
+    Manifest,
+    CONTENT_PACKS_FILE,
+};
+use kardashev_server::{
+    AssetStats,
+    StorageRedirect,
+};
+use tokio::{
+    net::TcpListener,
+    sync::{
+        broadcast,
+        Mutex,
+    },
+};
+use tokio_stream::{
+    wrappers::BroadcastStream,
+    Stream,
+    StreamExt,
+};
 use tower::ServiceBuilder;
 use tower_http::{
     services::{
@@ -20,10 +74,17 @@ use tower_http::{
         TraceLayer,
     },
 };
+use url::Url;
 
 use crate::{
-    build::BuildOptions,
-    util::shutdown::GracefulShutdown,
+    build::{
+        BuildEvents,
+        BuildOptions,
+    },
+    util::{
+        shutdown::GracefulShutdown,
+        telemetry::TelemetryArgs,
+    },
     Error,
 };
 
@@ -38,31 +99,263 @@ pub struct Args {
     address: SocketAddr,
 
     /// URL to the server's postgresql database.
-    #[arg(long, env = "DATABASE_URL")]
-    database_url: String,
+    #[arg(long, env = "DATABASE_URL", required_unless_present = "embedded_db")]
+    database_url: Option<String>,
+
+    /// Run an embedded PostgreSQL server instead of connecting to one, e.g.
+    /// for demos. It's migrated on startup.
+    #[arg(long, env = "EMBEDDED_DB", conflicts_with = "database_url")]
+    embedded_db: bool,
+
+    /// Where the embedded database is stored. Without it, the database is
+    /// removed when the server stops.
+    #[arg(long, env = "EMBEDDED_DB_DIR", requires = "embedded_db")]
+    embedded_db_dir: Option<PathBuf>,
+
+    /// Seconds between simulation epochs.
+    #[arg(long, env = "EPOCH_SECONDS", default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+    epoch_seconds: u64,
+
+    /// Maximum number of missed epochs to catch up on startup. Any further
+    /// missed epochs are skipped.
+    #[arg(long, env = "MAX_CATCH_UP_EPOCHS", default_value = "1440")]
+    max_catch_up_epochs: u32,
+
+    /// Maximum number of missed epochs that are caught up at once. The rest
+    /// are caught up in the following epochs.
+    #[arg(long, env = "MAX_EPOCHS_PER_TICK", default_value = "60", value_parser = clap::value_parser!(u32).range(1..))]
+    max_epochs_per_tick: u32,
+
+    /// Allow admins to run simulation epochs on demand, e.g. with `admin
+    /// tick`. Meant for development and single-player servers.
+    #[arg(long, env = "ALLOW_FORCED_TICKS")]
+    allow_forced_ticks: bool,
+
+    /// Epochs between snapshots of the world for replays. `0` disables
+    /// snapshots.
+    #[arg(long, env = "SNAPSHOT_INTERVAL_EPOCHS", default_value = "10")]
+    snapshot_interval_epochs: u32,
+
+    /// Maximum number of snapshots to keep. The oldest ones are deleted first.
+    #[arg(long, env = "MAX_SNAPSHOTS", default_value = "10000")]
+    max_snapshots: u32,
+
+    /// Minutes between computing the factions' ratings for the leaderboard.
+    /// `0` disables the leaderboard.
+    #[arg(long, env = "LEADERBOARD_INTERVAL_MINUTES", default_value = "60")]
+    leaderboard_interval_minutes: u64,
+
+    /// Where to write backups to, e.g. `file:///var/backups/kardashev` or
+    /// `s3://bucket/kardashev`. S3 is configured with the `AWS_*` environment
+    /// variables.
+    #[arg(long, env = "BACKUP_TARGET")]
+    backup_target: Option<Url>,
+
+    /// Hours between scheduled backups of every world. Without it, backups are
+    /// only made on request.
+    #[arg(long, env = "BACKUP_INTERVAL_HOURS", value_parser = clap::value_parser!(u64).range(1..))]
+    backup_interval_hours: Option<u64>,
+
+    /// Seconds for which presigned asset URLs are valid.
+    #[arg(long, env = "ASSET_URL_EXPIRY", default_value = "3600")]
+    asset_url_expiry: u64,
+
+    /// Serve the assets and UI that are already in the dist directory, instead
+    /// of building them.
+    #[arg(long, env = "KARDASHEV_PREBUILT")]
+    prebuilt: bool,
+
+    /// Directory with the Lua scripts that define gameplay formulas, e.g.
+    /// `content/rules`. Without it, the built-in formulas are used.
+    #[arg(long, env = "RULES_DIR")]
+    rules_dir: Option<PathBuf>,
+
+    /// Reload the rules scripts when they change. This is always done with
+    /// `--watch`.
+    #[arg(long, env = "HOT_RELOAD_RULES")]
+    hot_reload_rules: bool,
+
+    /// Game rule modules that are disabled, e.g. `fleets`.
+    #[arg(long, env = "DISABLED_MODULES", value_delimiter = ',')]
+    disabled_modules: Vec<String>,
+
+    /// Content packs to serve in addition to the built-in assets, as
+    /// `name=directory`. Each directory is a dist directory with its own asset
+    /// manifest.
+    #[arg(long = "content-pack", env = "CONTENT_PACKS", value_delimiter = ',', value_parser = parse_content_pack)]
+    content_packs: Vec<ContentPackDir>,
+
+    /// Run the database migrations before serving.
+    #[arg(long, env = "MIGRATE")]
+    migrate: bool,
+
+    /// Token for admin requests and the admin dashboard, with which they also
+    /// see the whole universe. Without it, admin requests are rejected, and
+    /// requests without a faction only see what is visible to everyone.
+    #[arg(long, env = "KARDASHEV_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    #[command(flatten)]
+    pub telemetry: TelemetryArgs,
 }
 
 impl Args {
     pub async fn run(self) -> Result<(), Error> {
         let mut shutdown = GracefulShutdown::new();
 
-        self.build_options.spawn(&mut shutdown).await?;
+        let build_events = if self.prebuilt {
+            None
+        }
+        else {
+            Some(self.build_options.spawn(&mut shutdown).await?)
+        };
 
-        let mut router = Router::new().nest(
-            "/api",
-            kardashev_server::Builder::default()
-                .with_shutdown(shutdown.token())
-                .with_connect_db(&self.database_url)
-                .await?
-                .build(),
-        );
+        let embedded_db = if self.embedded_db {
+            Some(
+                kardashev_server::EmbeddedDb::start(kardashev_server::EmbeddedDbConfig {
+                    data_dir: self.embedded_db_dir,
+                })
+                .await?,
+            )
+        }
+        else {
+            None
+        };
+        let database_url = match (&embedded_db, &self.database_url) {
+            (Some(embedded_db), _) => embedded_db.url(),
+            (None, Some(database_url)) => database_url,
+            (None, None) => bail!("Either DATABASE_URL or --embedded-db is required"),
+        };
 
-        if self.build_options.assets {
-            let dist_assets = self.build_options.dist_path.join("assets");
-            router = router.nest_service("/assets", ServeDir::new(&dist_assets));
+        let dist_assets = self.build_options.dist_path.join("assets");
+        let asset_stats = AssetStats::new(&dist_assets);
+
+        let mut server = kardashev_server::Builder::default()
+            .with_shutdown(shutdown.token())
+            .with_simulation(kardashev_server::SimulationConfig {
+                epoch: Duration::from_secs(self.epoch_seconds),
+                max_catch_up: self.max_catch_up_epochs,
+                max_epochs_per_tick: self.max_epochs_per_tick,
+                allow_forced_ticks: self.allow_forced_ticks,
+                snapshot_interval: self.snapshot_interval_epochs,
+                max_snapshots: self.max_snapshots,
+            })
+            .with_asset_stats(asset_stats.clone())
+            .with_rules(kardashev_server::RulesConfig {
+                directory: self.rules_dir,
+                hot_reload: self.hot_reload_rules || self.build_options.watch,
+            })?
+            .with_modules(
+                kardashev_server::modules::Modules::builtin().retain(|name| {
+                    !self
+                        .disabled_modules
+                        .iter()
+                        .any(|disabled| disabled == name)
+                }),
+            )
+            .with_backups(kardashev_server::BackupConfig {
+                target: self.backup_target,
+                interval: self
+                    .backup_interval_hours
+                    .map(|hours| Duration::from_secs(hours * 60 * 60)),
+            })?
+            .with_connect_db(database_url)
+            .await?;
+        if let Some(admin_token) = self.admin_token {
+            server = server.with_admin_token(admin_token);
+        }
+        if self.leaderboard_interval_minutes > 0 {
+            server = server.with_leaderboard_interval(Duration::from_secs(
+                self.leaderboard_interval_minutes * 60,
+            ));
+        }
+        // nothing else uses the embedded database, so it's always migrated.
+        if self.migrate || embedded_db.is_some() {
+            server = server.migrate().await?;
+        }
+
+        let mut router = Router::new().nest("/api", server.build());
+
+        if let Some(storage) = self.build_options.asset_storage()? {
+            if !storage.can_redirect() {
+                bail!("Asset storage {} needs a public URL", storage.url());
+            }
+            router = router.nest(
+                "/assets",
+                Router::new()
+                    .route("/*path", routing::get(redirect_to_storage))
+                    .with_state(AssetRedirect {
+                        storage,
+                        expires_in: Duration::from_secs(self.asset_url_expiry),
+                        known_paths: Default::default(),
+                    })
+                    .layer(middleware::from_fn_with_state(
+                        asset_stats,
+                        kardashev_server::record_asset_download,
+                    )),
+            );
+        }
+        else if self.build_options.assets {
+            router = router.nest_service(
+                "/assets",
+                ServiceBuilder::new()
+                    .layer(middleware::from_fn_with_state(
+                        asset_stats,
+                        kardashev_server::record_asset_download,
+                    ))
+                    .service(ServeDir::new(&dist_assets)),
+            );
+        }
+
+        if !self.content_packs.is_empty() {
+            let mut content_packs = ContentPacks::default();
+            for content_pack in &self.content_packs {
+                let manifest_path = content_pack.path.join("assets.json");
+                let Ok(manifest) = tokio::fs::read(&manifest_path).await
+                else {
+                    bail!(
+                        "Content pack {} has no asset manifest at {}",
+                        content_pack.name,
+                        manifest_path.display()
+                    );
+                };
+                let manifest = Manifest::from_json(&manifest)?;
+                tracing::info!(name = %content_pack.name, path = %content_pack.path.display(), build_time = %manifest.build_time, "serving content pack");
+
+                let url = format!("packs/{}/", content_pack.name);
+                router = router
+                    .nest_service(&format!("/assets/{url}"), ServeDir::new(&content_pack.path));
+                content_packs.packs.push(ContentPack {
+                    name: content_pack.name.clone(),
+                    url,
+                });
+            }
+            router = router.route(
+                &format!("/assets/{CONTENT_PACKS_FILE}"),
+                routing::get(move || {
+                    let content_packs = content_packs.clone();
+                    async move { Json(content_packs) }
+                }),
+            );
+        }
+
+        if self.build_options.assets && self.build_options.watch {
+            if let Some(build_events) = &build_events {
+                router = router.route(
+                    "/assets/events",
+                    routing::get(asset_events).with_state(build_events.clone()),
+                );
+            }
         }
 
         if self.build_options.ui {
+            if let Some(build_events) = build_events.filter(|_| self.build_options.watch) {
+                router = router.route(
+                    &format!("/{}", kardashev_protocol::ui::EVENTS_PATH),
+                    routing::get(stream_build_events).with_state(build_events),
+                );
+            }
+
             let dist_ui = self.build_options.dist_path.join("ui");
             router = router.fallback_service(ServeDir::new(&dist_ui).fallback(
                 ServeFile::new_with_mime(dist_ui.join("index.html"), &mime::TEXT_HTML_UTF_8),
@@ -101,6 +394,156 @@ impl Args {
             }
         });
 
-        shutdown.join().await
+        let result = shutdown.join().await;
+        if let Some(embedded_db) = embedded_db {
+            embedded_db.stop().await?;
+        }
+        result
+    }
+}
+
+/// Content pack served from a local directory.
+#[derive(Clone, Debug)]
+struct ContentPackDir {
+    name: String,
+    path: PathBuf,
+}
+
+fn parse_content_pack(s: &str) -> Result<ContentPackDir, String> {
+    let (name, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=directory`, but got `{s}`"))?;
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "content pack names can only contain letters, digits, `-` and `_`, but got `{name}`"
+        ));
+    }
+    Ok(ContentPackDir {
+        name: name.to_owned(),
+        path: path.into(),
+    })
+}
+
+/// How often the asset storage is listed at most, when an unknown asset is
+/// requested, e.g. after a rebuild.
+const RELIST_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+struct AssetRedirect {
+    storage: DistStorage,
+    expires_in: Duration,
+    known_paths: Arc<Mutex<KnownPaths>>,
+}
+
+/// Paths of the files in the asset storage, so that only those are
+/// redirected.
+#[derive(Debug, Default)]
+struct KnownPaths {
+    paths: HashSet<String>,
+    listed_at: Option<Instant>,
+}
+
+impl AssetRedirect {
+    /// Whether `path` is a file in the asset storage.
+    ///
+    /// If it's not, the storage is listed again, since the assets might have
+    /// been rebuilt. But only once per [`RELIST_INTERVAL`], so that requests
+    /// for paths that don't exist don't list the storage every time.
+    async fn is_known(&self, path: &str) -> bool {
+        let mut known_paths = self.known_paths.lock().await;
+        if known_paths.paths.contains(path) {
+            return true;
+        }
+        if known_paths
+            .listed_at
+            .is_some_and(|listed_at| listed_at.elapsed() < RELIST_INTERVAL)
+        {
+            return false;
+        }
+
+        match self.storage.paths().await {
+            Ok(paths) => known_paths.paths = paths,
+            Err(error) => tracing::error!(?error, "failed to list asset storage"),
+        }
+        known_paths.listed_at = Some(Instant::now());
+        known_paths.paths.contains(path)
+    }
+}
+
+/// Redirects requests for assets in the asset storage to it.
+async fn redirect_to_storage(
+    State(redirect): State<AssetRedirect>,
+    Path(path): Path<String>,
+) -> Response {
+    if !redirect.is_known(&path).await {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match redirect.storage.url_for(&path, redirect.expires_in).await {
+        Ok(Some(url)) => {
+            let mut response = Redirect::temporary(url.as_str()).into_response();
+            response.extensions_mut().insert(StorageRedirect);
+            response
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!(%path, ?error, "failed to get asset url");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Streams the build events to the browser, so that it can swap the
+/// stylesheet, reload after a rebuild, or show build errors.
+async fn stream_build_events(
+    State(build_events): State<BuildEvents>,
+) -> Sse<impl Stream<Item = Result<SseEvent, axum::Error>>> {
+    let (current, receiver) = build_events.subscribe();
+    let stream = tokio_stream::iter(current)
+        .chain(BroadcastStream::new(receiver).filter_map(|result| result.ok()))
+        .map(|event| SseEvent::default().json_data(event));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Sends the diffs of the asset rebuilds to the UI, so that it can reload the
+/// changed assets.
+async fn asset_events(
+    State(build_events): State<BuildEvents>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let receiver = build_events.subscribe_assets();
+    upgrade.on_upgrade(move |socket| send_asset_events(receiver, socket))
+}
+
+async fn send_asset_events(mut receiver: broadcast::Receiver<dist::Event>, mut socket: WebSocket) {
+    loop {
+        let event = tokio::select! {
+            message = socket.recv() => {
+                match message {
+                    // the client doesn't send anything, but we need to notice when it disconnects.
+                    Some(Ok(_)) => continue,
+                    None | Some(Err(_)) => break,
+                }
+            }
+            event = receiver.recv() => {
+                match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(num_skipped)) => {
+                        tracing::warn!(num_skipped, "asset events lagged");
+                        dist::Event::Lagged
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        };
+
+        let message = serde_json::to_string(&event).expect("failed to serialize asset event");
+        if socket.send(Message::Text(message)).await.is_err() {
+            break;
+        }
     }
 }
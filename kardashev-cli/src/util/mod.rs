@@ -1 +1,2 @@
 pub mod shutdown;
+pub mod telemetry;
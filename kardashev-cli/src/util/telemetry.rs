@@ -0,0 +1,126 @@
+//! Logging, and optional export of traces and metrics via OTLP.
+//!
+//! Logs are printed according to `RUST_LOG`. If an OTLP endpoint is
+//! configured, spans are exported as traces, and events with
+//! `monotonic_counter.*`, `counter.*` or `histogram.*` fields as metrics (see
+//! [`MetricsLayer`]).
+
+use opentelemetry::{
+    global,
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    metrics::SdkMeterProvider,
+    runtime,
+    trace::Config,
+    Resource,
+};
+use tracing_opentelemetry::{
+    MetricsLayer,
+    OpenTelemetryLayer,
+};
+use tracing_subscriber::{
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter,
+    Layer,
+};
+use url::Url;
+
+use crate::Error;
+
+#[derive(Debug, clap::Args)]
+pub struct TelemetryArgs {
+    /// OTLP (gRPC) endpoint to export traces and metrics to, e.g.
+    /// `http://localhost:4317`. Without it nothing is exported.
+    #[arg(long, env = "OTLP_ENDPOINT")]
+    otlp_endpoint: Option<Url>,
+
+    /// Service name that is reported with the traces and metrics.
+    #[arg(long, env = "OTLP_SERVICE_NAME", default_value = "kardashev-server")]
+    otlp_service_name: String,
+
+    /// Which spans and events are exported, in the same syntax as `RUST_LOG`.
+    #[arg(
+        long,
+        env = "OTLP_FILTER",
+        default_value = "info,kardashev_server=trace"
+    )]
+    otlp_filter: String,
+}
+
+/// Flushes and shuts down the exporters when dropped.
+#[must_use]
+pub struct Telemetry {
+    meter_provider: Option<SdkMeterProvider>,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(meter_provider) = self.meter_provider.take() {
+            global::shutdown_tracer_provider();
+            if let Err(error) = meter_provider.shutdown() {
+                eprintln!("failed to shut down metrics exporter: {error}");
+            }
+        }
+    }
+}
+
+/// Sets up the global tracing subscriber.
+pub fn init(args: Option<&TelemetryArgs>) -> Result<Telemetry, Error> {
+    let fmt = tracing_subscriber::fmt::layer()
+        .pretty()
+        .with_filter(EnvFilter::from_default_env());
+
+    let mut meter_provider = None;
+    let otlp = if let Some((args, endpoint)) =
+        args.and_then(|args| Some((args, args.otlp_endpoint.as_ref()?)))
+    {
+        let resource = Resource::new([KeyValue::new(
+            "service.name",
+            args.otlp_service_name.clone(),
+        )]);
+
+        let tracer_provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint.as_str()),
+            )
+            .with_trace_config(Config::default().with_resource(resource.clone()))
+            .install_batch(runtime::Tokio)?;
+        let tracer = tracer_provider.tracer("kardashev");
+        global::set_tracer_provider(tracer_provider);
+
+        let metrics = opentelemetry_otlp::new_pipeline()
+            .metrics(runtime::Tokio)
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint.as_str()),
+            )
+            .with_resource(resource)
+            .build()?;
+        meter_provider = Some(metrics.clone());
+
+        Some(
+            OpenTelemetryLayer::new(tracer)
+                .and_then(MetricsLayer::new(metrics))
+                .with_filter(EnvFilter::try_new(&args.otlp_filter)?),
+        )
+    }
+    else {
+        None
+    };
+
+    tracing_subscriber::registry().with(fmt).with(otlp).init();
+
+    if let Some(endpoint) = args.and_then(|args| args.otlp_endpoint.as_ref()) {
+        tracing::info!(%endpoint, "exporting traces and metrics");
+    }
+
+    Ok(Telemetry { meter_provider })
+}
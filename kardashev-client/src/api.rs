@@ -1,23 +1,130 @@
 use std::sync::Arc;
 
+use futures_util::{
+    SinkExt,
+    TryStreamExt,
+};
 use kardashev_protocol::{
     admin::{
+        AuditStarsRequest,
+        AuditStarsResponse,
+        Backup,
+        CreateFleet,
+        CreateFleetsRequest,
+        CreateJournalEntriesRequest,
+        CreateJournalEntry,
+        CreatePlanet,
+        CreatePlanetsRequest,
+        CreatePlanetsResponse,
         CreateStar,
         CreateStarsRequest,
-        CreateStarsResponse,
+        CreateWebhookRequest,
+        CreateWorldRequest,
+        GetAssetStatsResponse,
+        GetBackupsResponse,
+        GetSimulationStateResponse,
+        RenameStarRequest,
+        SimulateQuery,
+        SimulateResponse,
+        Webhook,
+        WebhookEvent,
+        WebhookId,
+    },
+    endpoints::{
+        self,
+        Endpoint,
+        REPLAY_PATH,
+        SESSION_PATH,
+        STAR_EVENTS_PATH,
+    },
+    model::{
+        bookmark::Bookmark,
+        diplomacy::{
+            Relation,
+            Stance,
+            TreatyProposal,
+        },
+        empire::{
+            ColonyId,
+            EmpireSummary,
+        },
+        faction::FactionId,
+        fleet::{
+            Fleet,
+            FleetId,
+            Order,
+            OrderKind,
+        },
+        impostor::StarChunk,
+        journal::JournalEntryId,
+        planet::Planet,
+        region::Region,
+        star::{
+            Star,
+            StarEvent,
+            StarId,
+            StarSearchResult,
+        },
+        trade::{
+            Resource,
+            TradeRoute,
+            TradeRouteId,
+        },
+        world::{
+            World,
+            WorldId,
+        },
     },
-    model::star::{
-        Star,
-        StarId,
+    replay::{
+        ReplayControl,
+        ReplayEvent,
+        ReplayQuery,
+        SnapshotInfo,
     },
-    GetStarsResponse,
+    ClientCapabilityReport,
+    ClientErrorReport,
+    CreateTradeRouteRequest,
+    GetDiplomacyResponse,
+    GetJournalQuery,
+    GetJournalResponse,
+    GetLeaderboardResponse,
+    GetTradeRoutesResponse,
+    PlayerSettings,
+    ProposeTreatyRequest,
+    PutBookmarksRequest,
+    PutSettingsRequest,
+    PutSettingsResponse,
+    SearchStarsQuery,
     ServerStatus,
+    SessionEvent,
+    SetOrdersRequest,
+    ADMIN_TOKEN_HEADER,
+    FACTION_HEADER,
+    WORLD_HEADER,
+};
+use reqwest::header::{
+    HeaderMap,
+    HeaderValue,
+};
+use reqwest_websocket::{
+    Message,
+    RequestBuilderExt,
+    WebSocket,
+};
+use serde::{
+    de::{
+        value::UnitDeserializer,
+        IntoDeserializer,
+    },
+    Deserialize,
+    Serialize,
 };
 use url::Url;
 
 use crate::{
     add_trailing_slash,
     Error,
+    RetryPolicy,
     UrlExt,
 };
 
@@ -25,6 +132,10 @@ use crate::{
 pub struct ApiClient {
     client: reqwest::Client,
     api_url: Arc<Url>,
+    faction: Option<FactionId>,
+    world: Option<WorldId>,
+    admin_token: Option<Arc<str>>,
+    retry_policy: RetryPolicy,
 }
 
 impl ApiClient {
@@ -37,43 +148,571 @@ impl ApiClient {
         Self {
             client,
             api_url: Arc::new(api_url),
+            faction: None,
+            world: None,
+            admin_token: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Retries requests that fail with transient errors according to `policy`.
+    ///
+    /// By default the client uses [`RetryPolicy::default`]. Websockets
+    /// aren't retried.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Makes all requests on behalf of `faction`.
+    ///
+    /// The server then only returns what is visible to this faction.
+    pub fn with_faction(mut self, faction: FactionId) -> Self {
+        self.faction = Some(faction);
+        self.rebuild_client();
+        self
+    }
+
+    /// Makes all requests in `world`.
+    ///
+    /// Without a world, the server's default world is used.
+    pub fn with_world(mut self, world: WorldId) -> Self {
+        self.world = Some(world);
+        self.rebuild_client();
+        self
+    }
+
+    /// Makes all requests with the server's admin token.
+    ///
+    /// Without a faction, the server then returns everything.
+    pub fn with_admin_token(mut self, token: impl Into<Arc<str>>) -> Self {
+        self.admin_token = Some(token.into());
+        self.rebuild_client();
+        self
+    }
+
+    fn rebuild_client(&mut self) {
+        let mut headers = HeaderMap::new();
+        if let Some(faction) = self.faction {
+            headers.insert(
+                FACTION_HEADER,
+                HeaderValue::from_str(&faction.0.to_string())
+                    .expect("uuid is a valid header value"),
+            );
+        }
+        if let Some(world) = self.world {
+            headers.insert(
+                WORLD_HEADER,
+                HeaderValue::from_str(&world.0.to_string()).expect("uuid is a valid header value"),
+            );
+        }
+        if let Some(admin_token) = &self.admin_token {
+            let mut value = HeaderValue::from_str(admin_token)
+                .expect("admin token is not a valid header value");
+            value.set_sensitive(true);
+            headers.insert(ADMIN_TOKEN_HEADER, value);
+        }
+        self.client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .expect("failed to build http client");
+    }
+
+    /// The faction set with [`with_faction`](Self::with_faction).
+    pub fn faction(&self) -> Option<FactionId> {
+        self.faction
+    }
+
+    /// The world set with [`with_world`](Self::with_world).
+    pub fn world(&self) -> Option<WorldId> {
+        self.world
+    }
+
+    /// Sends a request to an endpoint, and decodes its response.
+    async fn call<E: Endpoint>(
+        &self,
+        params: E::Params,
+        query: &E::Query,
+        request: &E::Request,
+    ) -> Result<E::Response, Error> {
+        let mut url = Url::clone(&self.api_url);
+        for segment in E::path(&params).split('/').filter(|s| !s.is_empty()) {
+            url = url.joined(segment);
+        }
+
+        let mut builder = self.client.request(E::METHOD, url);
+        if E::HAS_QUERY {
+            builder = builder.query(query);
+        }
+        if E::HAS_REQUEST {
+            builder = builder.json(request);
+        }
+        let response = self.retry_policy.send(&E::METHOD, builder).await?;
+
+        if E::HAS_RESPONSE {
+            Ok(response.json().await?)
+        }
+        else {
+            // endpoints without a response have the unit type as response.
+            let unit: UnitDeserializer<serde::de::value::Error> = ().into_deserializer();
+            Ok(E::Response::deserialize(unit).expect("response is the unit type"))
         }
     }
 
     pub async fn status(&self) -> Result<ServerStatus, Error> {
-        let status: ServerStatus = self
-            .client
-            .get(Url::clone(&self.api_url).joined("status"))
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
-            .await?;
-        Ok(status)
+        self.call::<endpoints::GetStatus>((), &(), &()).await
     }
 
     pub async fn create_stars(&self, stars: Vec<CreateStar>) -> Result<Vec<StarId>, Error> {
-        let response: CreateStarsResponse = self
-            .client
-            .post(Url::clone(&self.api_url).joined("admin").joined("star"))
-            .json(&CreateStarsRequest { stars })
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
+        let response = self
+            .call::<endpoints::CreateStars>((), &(), &CreateStarsRequest { stars })
             .await?;
         Ok(response.ids)
     }
 
+    /// Renames a star, and returns its previous name.
+    pub async fn rename_star(
+        &self,
+        star_id: StarId,
+        name: Option<String>,
+    ) -> Result<Option<String>, Error> {
+        let response = self
+            .call::<endpoints::RenameStar>(star_id, &(), &RenameStarRequest { name })
+            .await?;
+        Ok(response.previous_name)
+    }
+
+    /// Deletes a star. This fails if it has colonies, or if fleets are ordered
+    /// to it.
+    pub async fn delete_star(&self, star_id: StarId) -> Result<(), Error> {
+        self.call::<endpoints::DeleteStar>(star_id, &(), &()).await
+    }
+
+    /// Returns the backup configuration and the recent backups.
+    pub async fn get_backups(&self) -> Result<GetBackupsResponse, Error> {
+        self.call::<endpoints::GetBackups>((), &(), &()).await
+    }
+
+    /// Backs up the world now, and returns once the backup is finished.
+    pub async fn create_backup(&self) -> Result<Backup, Error> {
+        let response = self.call::<endpoints::CreateBackup>((), &(), &()).await?;
+        Ok(response.backup)
+    }
+
+    pub async fn get_worlds(&self) -> Result<Vec<World>, Error> {
+        let response = self.call::<endpoints::GetWorlds>((), &(), &()).await?;
+        Ok(response.worlds)
+    }
+
+    pub async fn create_world(&self, name: impl Into<String>) -> Result<World, Error> {
+        let response = self
+            .call::<endpoints::CreateWorld>((), &(), &CreateWorldRequest { name: name.into() })
+            .await?;
+        Ok(response.world)
+    }
+
+    /// Returns the world's webhooks.
+    pub async fn get_webhooks(&self) -> Result<Vec<Webhook>, Error> {
+        let response = self.call::<endpoints::GetWebhooks>((), &(), &()).await?;
+        Ok(response.webhooks)
+    }
+
+    /// Posts the world's `events` to `url`. With no events, all are posted.
+    pub async fn create_webhook(
+        &self,
+        url: impl Into<String>,
+        events: Vec<WebhookEvent>,
+    ) -> Result<Webhook, Error> {
+        self.call::<endpoints::CreateWebhook>(
+            (),
+            &(),
+            &CreateWebhookRequest {
+                url: url.into(),
+                events,
+            },
+        )
+        .await
+    }
+
+    pub async fn delete_webhook(&self, id: WebhookId) -> Result<(), Error> {
+        self.call::<endpoints::DeleteWebhook>(id, &(), &()).await
+    }
+
+    /// Returns how often the assets were downloaded.
+    pub async fn asset_stats(&self) -> Result<GetAssetStatsResponse, Error> {
+        self.call::<endpoints::GetAssetStats>((), &(), &()).await
+    }
+
+    /// Returns the simulation's epoch and the hashes of its state.
+    pub async fn simulation_state(&self) -> Result<GetSimulationStateResponse, Error> {
+        self.call::<endpoints::GetSimulationState>((), &(), &())
+            .await
+    }
+
+    /// Runs `ticks` simulation epochs now. The server must allow forced ticks.
+    pub async fn simulate(&self, ticks: u32) -> Result<SimulateResponse, Error> {
+        self.call::<endpoints::Simulate>((), &SimulateQuery { ticks }, &())
+            .await
+    }
+
+    /// Audits the stars' derived properties. If `fix` is set, the fixable
+    /// issues are fixed.
+    pub async fn audit_stars(&self, fix: bool) -> Result<AuditStarsResponse, Error> {
+        self.call::<endpoints::AuditStars>((), &(), &AuditStarsRequest { fix })
+            .await
+    }
+
+    /// Creates planets around existing stars. The response also contains the
+    /// planets for which no host star was found.
+    pub async fn create_planets(
+        &self,
+        planets: Vec<CreatePlanet>,
+    ) -> Result<CreatePlanetsResponse, Error> {
+        self.call::<endpoints::CreatePlanets>((), &(), &CreatePlanetsRequest { planets })
+            .await
+    }
+
+    pub async fn get_planets(&self, star_id: StarId) -> Result<Vec<Planet>, Error> {
+        let response = self
+            .call::<endpoints::GetPlanets>(star_id, &(), &())
+            .await?;
+        Ok(response.planets)
+    }
+
     pub async fn get_stars(&self) -> Result<Vec<Star>, Error> {
-        let response: GetStarsResponse = self
+        let response = self.call::<endpoints::GetStars>((), &(), &()).await?;
+        Ok(response.stars)
+    }
+
+    /// Searches stars by name or catalog designation, e.g. `Vega` or `HIP
+    /// 91262`.
+    pub async fn search_stars(
+        &self,
+        query: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<StarSearchResult>, Error> {
+        let query = SearchStarsQuery {
+            q: query.to_owned(),
+            limit,
+        };
+        let response = self.call::<endpoints::SearchStars>((), &query, &()).await?;
+        Ok(response.results)
+    }
+
+    pub async fn get_regions(&self) -> Result<Vec<Region>, Error> {
+        let response = self.call::<endpoints::GetRegions>((), &(), &()).await?;
+        Ok(response.regions)
+    }
+
+    /// Recomputes the regions from the current stars. Returns the number of
+    /// regions found.
+    pub async fn recompute_regions(&self) -> Result<usize, Error> {
+        let response = self
+            .call::<endpoints::RecomputeRegions>((), &(), &())
+            .await?;
+        Ok(response.num_regions)
+    }
+
+    pub async fn get_star_chunks(&self) -> Result<Vec<StarChunk>, Error> {
+        let response = self.call::<endpoints::GetStarChunks>((), &(), &()).await?;
+        Ok(response.chunks)
+    }
+
+    /// Recomputes the impostors for distant stars. Returns the number of
+    /// chunks.
+    pub async fn recompute_impostors(&self) -> Result<usize, Error> {
+        let response = self
+            .call::<endpoints::RecomputeImpostors>((), &(), &())
+            .await?;
+        Ok(response.num_chunks)
+    }
+
+    pub async fn create_fleets(&self, fleets: Vec<CreateFleet>) -> Result<Vec<FleetId>, Error> {
+        let response = self
+            .call::<endpoints::CreateFleets>((), &(), &CreateFleetsRequest { fleets })
+            .await?;
+        Ok(response.ids)
+    }
+
+    pub async fn get_fleets(&self) -> Result<Vec<Fleet>, Error> {
+        let response = self.call::<endpoints::GetFleets>((), &(), &()).await?;
+        Ok(response.fleets)
+    }
+
+    pub async fn get_fleet(&self, fleet_id: FleetId) -> Result<Fleet, Error> {
+        self.call::<endpoints::GetFleet>(fleet_id, &(), &()).await
+    }
+
+    /// Replaces the order queue of a fleet.
+    pub async fn set_fleet_orders(
+        &self,
+        fleet_id: FleetId,
+        orders: Vec<OrderKind>,
+    ) -> Result<Vec<Order>, Error> {
+        let response = self
+            .call::<endpoints::SetFleetOrders>(fleet_id, &(), &SetOrdersRequest { orders })
+            .await?;
+        Ok(response.orders)
+    }
+
+    /// Returns an overview of the empire of the faction set with
+    /// [`with_faction`](Self::with_faction).
+    pub async fn get_empire_summary(&self) -> Result<EmpireSummary, Error> {
+        self.call::<endpoints::GetEmpireSummary>((), &(), &()).await
+    }
+
+    pub async fn get_leaderboard(&self) -> Result<GetLeaderboardResponse, Error> {
+        self.call::<endpoints::GetLeaderboard>((), &(), &()).await
+    }
+
+    /// Returns the relations of the faction set with
+    /// [`with_faction`](Self::with_faction), and its treaty proposals.
+    pub async fn get_diplomacy(&self) -> Result<GetDiplomacyResponse, Error> {
+        self.call::<endpoints::GetDiplomacy>((), &(), &()).await
+    }
+
+    pub async fn declare_war(&self, faction: FactionId) -> Result<Relation, Error> {
+        self.call::<endpoints::DeclareWar>(faction, &(), &()).await
+    }
+
+    /// Offers a treaty to `faction`. Treaties without `days` don't expire.
+    pub async fn propose_treaty(
+        &self,
+        faction: FactionId,
+        stance: Stance,
+        days: Option<u32>,
+    ) -> Result<TreatyProposal, Error> {
+        self.call::<endpoints::ProposeTreaty>(faction, &(), &ProposeTreatyRequest { stance, days })
+            .await
+    }
+
+    pub async fn accept_treaty(&self, faction: FactionId) -> Result<Relation, Error> {
+        self.call::<endpoints::AcceptTreaty>(faction, &(), &())
+            .await
+    }
+
+    /// Withdraws the proposal to `faction`, or declines the one from it.
+    pub async fn delete_proposal(&self, faction: FactionId) -> Result<(), Error> {
+        self.call::<endpoints::DeleteProposal>(faction, &(), &())
+            .await
+    }
+
+    /// Returns the trade routes of the faction set with
+    /// [`with_faction`](Self::with_faction), and the stockpiles of its
+    /// colonies.
+    pub async fn get_trade_routes(&self) -> Result<GetTradeRoutesResponse, Error> {
+        self.call::<endpoints::GetTradeRoutes>((), &(), &()).await
+    }
+
+    /// Opens a trade route that ships `capacity` of `resource` per day from
+    /// one colony to another.
+    pub async fn create_trade_route(
+        &self,
+        from: ColonyId,
+        to: ColonyId,
+        resource: Resource,
+        capacity: f32,
+    ) -> Result<TradeRoute, Error> {
+        self.call::<endpoints::CreateTradeRoute>(
+            (),
+            &(),
+            &CreateTradeRouteRequest {
+                from,
+                to,
+                resource,
+                capacity,
+            },
+        )
+        .await
+    }
+
+    pub async fn cancel_trade_route(&self, id: TradeRouteId) -> Result<(), Error> {
+        self.call::<endpoints::CancelTradeRoute>(id, &(), &()).await
+    }
+
+    /// Returns a page of the journal of the faction set with
+    /// [`with_faction`](Self::with_faction).
+    pub async fn get_journal(&self, query: &GetJournalQuery) -> Result<GetJournalResponse, Error> {
+        self.call::<endpoints::GetJournal>((), query, &()).await
+    }
+
+    /// Returns the bookmarks of the faction set with
+    /// [`with_faction`](Self::with_faction).
+    pub async fn get_bookmarks(&self) -> Result<Vec<Bookmark>, Error> {
+        let response = self.call::<endpoints::GetBookmarks>((), &(), &()).await?;
+        Ok(response.bookmarks)
+    }
+
+    /// Replaces the bookmarks of the faction set with
+    /// [`with_faction`](Self::with_faction).
+    pub async fn put_bookmarks(&self, bookmarks: Vec<Bookmark>) -> Result<Vec<Bookmark>, Error> {
+        let response = self
+            .call::<endpoints::PutBookmarks>((), &(), &PutBookmarksRequest { bookmarks })
+            .await?;
+        Ok(response.bookmarks)
+    }
+
+    /// Returns the UI settings of the faction set with
+    /// [`with_faction`](Self::with_faction), if they were ever stored.
+    pub async fn get_settings(&self) -> Result<Option<PlayerSettings>, Error> {
+        let response = self.call::<endpoints::GetSettings>((), &(), &()).await?;
+        Ok(response.settings)
+    }
+
+    /// Stores the UI settings of the faction set with
+    /// [`with_faction`](Self::with_faction), unless they were changed since
+    /// `base_version`. In that case the response is a conflict with the
+    /// stored settings.
+    pub async fn put_settings(
+        &self,
+        base_version: Option<u64>,
+        settings: serde_json::Value,
+    ) -> Result<PutSettingsResponse, Error> {
+        self.call::<endpoints::PutSettings>(
+            (),
+            &(),
+            &PutSettingsRequest {
+                base_version,
+                settings,
+            },
+        )
+        .await
+    }
+
+    pub async fn create_journal_entries(
+        &self,
+        entries: Vec<CreateJournalEntry>,
+    ) -> Result<Vec<JournalEntryId>, Error> {
+        let response = self
+            .call::<endpoints::CreateJournalEntries>(
+                (),
+                &(),
+                &CreateJournalEntriesRequest { entries },
+            )
+            .await?;
+        Ok(response.ids)
+    }
+
+    /// Sends a crash or error report to the server.
+    pub async fn report_client_error(&self, report: &ClientErrorReport) -> Result<(), Error> {
+        self.call::<endpoints::ReportClientError>((), &(), report)
+            .await
+    }
+
+    /// Sends a report of the client's graphics capabilities to the server.
+    pub async fn report_client_capabilities(
+        &self,
+        report: &ClientCapabilityReport,
+    ) -> Result<(), Error> {
+        self.call::<endpoints::ReportClientCapabilities>((), &(), report)
+            .await
+    }
+
+    /// Connects to the session stream.
+    pub async fn session(&self) -> Result<Session, Error> {
+        let websocket = self.websocket(SESSION_PATH, &()).await?;
+        Ok(Session { websocket })
+    }
+
+    /// Connects to the stream of changes to the stars.
+    pub async fn star_events(&self) -> Result<StarEvents, Error> {
+        let websocket = self.websocket(STAR_EVENTS_PATH, &()).await?;
+        Ok(StarEvents { websocket })
+    }
+
+    /// Returns the snapshots that can be replayed, oldest first.
+    pub async fn get_snapshots(&self) -> Result<Vec<SnapshotInfo>, Error> {
+        let response = self.call::<endpoints::GetSnapshots>((), &(), &()).await?;
+        Ok(response.snapshots)
+    }
+
+    /// Starts a replay of the snapshots, as seen by the faction set with
+    /// [`with_faction`](Self::with_faction).
+    pub async fn replay(&self, query: &ReplayQuery) -> Result<Replay, Error> {
+        let websocket = self.websocket(REPLAY_PATH, query).await?;
+        Ok(Replay { websocket })
+    }
+
+    async fn websocket(&self, path: &str, query: &impl Serialize) -> Result<WebSocket, Error> {
+        let mut url = Url::clone(&self.api_url).joined(path.trim_start_matches('/'));
+        if let Some(faction) = self.faction {
+            // browsers can't set headers for websockets, so we pass the faction in the
+            // query.
+            url.query_pairs_mut()
+                .append_pair("faction", &faction.0.to_string());
+        }
+        if let Some(world) = self.world {
+            url.query_pairs_mut()
+                .append_pair("world", &world.0.to_string());
+        }
+
+        let websocket = self
             .client
-            .get(Url::clone(&self.api_url).joined("star"))
+            .get(url)
+            .query(query)
+            .upgrade()
             .send()
             .await?
-            .error_for_status()?
-            .json()
+            .into_websocket()
             .await?;
-        Ok(response.stars)
+        Ok(websocket)
+    }
+}
+
+/// Stream of [`SessionEvent`]s from the server.
+#[derive(Debug)]
+pub struct Session {
+    websocket: WebSocket,
+}
+
+impl Session {
+    pub async fn next(&mut self) -> Result<SessionEvent, Error> {
+        let message = self
+            .websocket
+            .try_next()
+            .await?
+            .ok_or_else(|| Error::UnexpectedEof)?;
+        Ok(message.json()?)
+    }
+}
+
+/// Changes to the stars, see [`ApiClient::star_events`].
+#[derive(Debug)]
+pub struct StarEvents {
+    websocket: WebSocket,
+}
+
+impl StarEvents {
+    pub async fn next(&mut self) -> Result<StarEvent, Error> {
+        let message = self
+            .websocket
+            .try_next()
+            .await?
+            .ok_or_else(|| Error::UnexpectedEof)?;
+        Ok(message.json()?)
+    }
+}
+
+/// A replay started with [`ApiClient::replay`].
+#[derive(Debug)]
+pub struct Replay {
+    websocket: WebSocket,
+}
+
+impl Replay {
+    pub async fn next(&mut self) -> Result<ReplayEvent, Error> {
+        let message = self
+            .websocket
+            .try_next()
+            .await?
+            .ok_or_else(|| Error::UnexpectedEof)?;
+        Ok(message.json()?)
+    }
+
+    pub async fn send(&mut self, control: ReplayControl) -> Result<(), Error> {
+        self.websocket
+            .send(Message::text_from_json(&control)?)
+            .await?;
+        Ok(())
     }
 }
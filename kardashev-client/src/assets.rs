@@ -6,8 +6,15 @@ use bytes::{
 };
 use futures_util::TryStreamExt;
 use kardashev_protocol::assets::{
+    ContentPack,
+    ContentPacks,
     Event,
     Manifest,
+    CONTENT_PACKS_FILE,
+};
+use reqwest::{
+    Method,
+    StatusCode,
 };
 use reqwest_websocket::{
     RequestBuilderExt,
@@ -19,6 +26,7 @@ use url::Url;
 use crate::{
     add_trailing_slash,
     Error,
+    RetryPolicy,
     UrlExt,
 };
 
@@ -26,6 +34,7 @@ use crate::{
 pub struct AssetClient {
     client: reqwest::Client,
     asset_url: Arc<Url>,
+    retry_policy: RetryPolicy,
 }
 
 impl AssetClient {
@@ -38,23 +47,62 @@ impl AssetClient {
         Self {
             client,
             asset_url: Arc::new(asset_url),
+            retry_policy: RetryPolicy::default(),
         }
     }
 
+    /// Retries requests that fail with transient errors according to `policy`.
+    ///
+    /// The [timeout](RetryPolicy::timeout) doesn't apply to
+    /// [downloads](Self::download_file), since large files can take longer.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
     pub fn asset_url(&self) -> &Url {
         &self.asset_url
     }
 
+    /// Downloads the asset manifest, and migrates it if it's from an older
+    /// build.
     pub async fn get_manifest(&self) -> Result<Manifest, Error> {
-        let manifest = self
+        let request = self
             .client
-            .get(Url::clone(&self.asset_url).joined("assets.json"))
-            .send()
+            .get(Url::clone(&self.asset_url).joined("assets.json"));
+        let json = self
+            .retry_policy
+            .send(&Method::GET, request)
             .await?
-            .error_for_status()?
-            .json()
+            .bytes()
             .await?;
-        Ok(manifest)
+        Ok(Manifest::from_json(&json)?)
+    }
+
+    /// Downloads the list of content packs the server offers. Servers without
+    /// content packs may not have one.
+    pub async fn get_content_packs(&self) -> Result<ContentPacks, Error> {
+        let request = self
+            .client
+            .get(Url::clone(&self.asset_url).joined(CONTENT_PACKS_FILE));
+        match self.retry_policy.send(&Method::GET, request).await {
+            Ok(response) => Ok(response.json().await?),
+            Err(error) if error.status() == Some(StatusCode::NOT_FOUND) => {
+                Ok(ContentPacks::default())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Returns a client for the assets of a content pack.
+    pub fn for_content_pack(&self, content_pack: &ContentPack) -> Result<Self, Error> {
+        let mut asset_url = self.asset_url.join(&content_pack.url)?;
+        add_trailing_slash(&mut asset_url);
+        Ok(Self {
+            client: self.client.clone(),
+            asset_url: Arc::new(asset_url),
+            retry_policy: self.retry_policy,
+        })
     }
 
     pub async fn events(&self) -> Result<Events, Error> {
@@ -81,12 +129,10 @@ impl AssetClient {
         };
 
         let response = self
-            .client
-            .get(url.clone())
-            .send()
+            .retry_policy
+            .with_timeout(None)
+            .send(&Method::GET, self.client.get(url.clone()))
             .await
-            .map_err(err)?
-            .error_for_status()
             .map_err(err)?;
 
         let content_length = response
@@ -1,16 +1,23 @@
 mod api;
 mod assets;
+mod retry;
 
 use url::Url;
 
 pub use crate::{
-    api::ApiClient,
+    api::{
+        ApiClient,
+        Replay,
+        Session,
+        StarEvents,
+    },
     assets::{
         AssetClient,
         DownloadError,
         DownloadFile,
         Events,
     },
+    retry::RetryPolicy,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -23,6 +30,12 @@ pub enum Error {
 
     #[error("unexpected end of stream")]
     UnexpectedEof,
+
+    #[error("invalid asset manifest")]
+    Manifest(#[from] kardashev_protocol::assets::ManifestError),
+
+    #[error("invalid url")]
+    Url(#[from] url::ParseError),
 }
 
 trait UrlExt {
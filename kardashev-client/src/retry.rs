@@ -0,0 +1,180 @@
+//! Retries of requests that fail because of the network or an overloaded
+//! server.
+
+use std::{
+    hash::BuildHasher,
+    time::Duration,
+};
+
+use reqwest::{
+    Method,
+    RequestBuilder,
+    Response,
+    StatusCode,
+};
+
+/// How often and how long requests are retried, and how long a single attempt
+/// may take.
+///
+/// Requests that change state (e.g. `POST`) are only retried if they didn't
+/// reach the server, or the server asked the client to retry.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one. With 1 requests
+    /// aren't retried.
+    pub max_attempts: u32,
+
+    /// Time to wait before the first retry.
+    pub initial_backoff: Duration,
+
+    /// Longest time to wait between retries.
+    pub max_backoff: Duration,
+
+    /// Factor by which the backoff grows with every retry.
+    pub multiplier: f32,
+
+    /// Fraction of the backoff that is random, between 0 and 1, so that
+    /// clients that failed at the same time don't retry at the same time.
+    pub jitter: f32,
+
+    /// Time after which a single attempt is aborted.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            timeout: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sends requests only once, without a timeout.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            timeout: None,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Time to wait before the `retry`th retry, starting at 0, without
+    /// jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.min(64) as i32);
+        self.initial_backoff
+            .mul_f32(factor.min(u32::MAX as f32))
+            .min(self.max_backoff)
+    }
+
+    /// Reduces `backoff` by a random part of up to [`jitter`](Self::jitter).
+    fn with_random_jitter(&self, backoff: Duration) -> Duration {
+        // `RandomState` is seeded differently every time, so we don't need a
+        // random number generator for this.
+        let random = std::collections::hash_map::RandomState::new().hash_one(backoff) as f32
+            / u64::MAX as f32;
+        backoff.mul_f32(1.0 - self.jitter.clamp(0.0, 1.0) * random)
+    }
+
+    /// Sends a request, and retries it if it fails with a transient error.
+    ///
+    /// Responses with an error status are returned as error.
+    pub(crate) async fn send(
+        &self,
+        method: &Method,
+        request: RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let request = match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        };
+
+        let mut retry = 0;
+        loop {
+            // requests with streaming bodies can't be cloned, so they're only sent once.
+            let Some(attempt) = request.try_clone()
+            else {
+                return request.send().await?.error_for_status();
+            };
+
+            match attempt.send().await.and_then(Response::error_for_status) {
+                Err(error)
+                    if retry + 1 < self.max_attempts
+                        && is_transient(&error, method.is_idempotent()) =>
+                {
+                    let backoff = self.with_random_jitter(self.backoff(retry));
+                    tracing::debug!(%error, retry, ?backoff, "request failed, retrying");
+                    sleep(backoff).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Returns whether a request that failed with `error` might succeed if it's
+/// retried.
+fn is_transient(error: &reqwest::Error, idempotent: bool) -> bool {
+    if let Some(status) = error.status() {
+        // the server rejected these before doing anything.
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            return true;
+        }
+        return idempotent && (status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT);
+    }
+
+    // the request never reached the server.
+    #[cfg(not(target_arch = "wasm32"))]
+    if error.is_connect() {
+        return true;
+    }
+
+    idempotent && (error.is_timeout() || error.is_request())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await;
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sleep(duration: Duration) {
+    let millis = duration.as_millis().try_into().unwrap_or(u32::MAX);
+    gloo_timers::future::TimeoutFuture::new(millis).await;
+}
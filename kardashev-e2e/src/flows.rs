@@ -0,0 +1,291 @@
+//! Flows that drive the clients through what players and admins do, and
+//! check the responses.
+//!
+//! The flows don't depend on each other, so that one failing doesn't hide
+//! failures in the others.
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use kardashev_protocol::{
+    admin::CreateStar,
+    assets::{
+        AssetTypes,
+        ColorRamp,
+        IconSet,
+    },
+    model::star::{
+        CatalogIds,
+        StarEvent,
+        StarId,
+    },
+    stellar,
+};
+use nalgebra::Point3;
+
+use crate::{
+    check,
+    Error,
+    Harness,
+};
+
+/// How long to wait for events from the server.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    /// Asks the server for its status.
+    Status,
+
+    /// Imports stars, and loads them again as admin and as visitor.
+    ImportStars,
+
+    /// Loads the manifest, and downloads assets from it.
+    FetchAssets,
+
+    /// Opens a session, and waits for imported stars to be streamed, and for
+    /// a deleted star to be removed.
+    OpenSession,
+
+    /// Creates a second world, and imports stars into it.
+    SecondWorld,
+}
+
+impl Flow {
+    pub const ALL: [Self; 5] = [
+        Self::Status,
+        Self::ImportStars,
+        Self::FetchAssets,
+        Self::OpenSession,
+        Self::SecondWorld,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::ImportStars => "import stars",
+            Self::FetchAssets => "fetch assets",
+            Self::OpenSession => "open session",
+            Self::SecondWorld => "second world",
+        }
+    }
+
+    pub async fn run(&self, harness: &Harness) -> Result<(), Error> {
+        match self {
+            Self::Status => status(harness).await,
+            Self::ImportStars => import_stars(harness).await,
+            Self::FetchAssets => fetch_assets(harness).await,
+            Self::OpenSession => open_session(harness).await,
+            Self::SecondWorld => second_world(harness).await,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FlowResult {
+    pub flow: Flow,
+    pub duration: Duration,
+    pub result: Result<(), Error>,
+}
+
+/// Runs all flows, one after the other.
+pub async fn run_all(harness: &Harness) -> Vec<FlowResult> {
+    let mut results = Vec::with_capacity(Flow::ALL.len());
+
+    for flow in Flow::ALL {
+        tracing::info!(flow = flow.name(), "running flow");
+        let start = Instant::now();
+        let result = flow.run(harness).await;
+        results.push(FlowResult {
+            flow,
+            duration: start.elapsed(),
+            result,
+        });
+    }
+
+    results
+}
+
+async fn status(harness: &Harness) -> Result<(), Error> {
+    let status = harness.api().status().await?;
+    check(status.up_since <= chrono::Utc::now(), || {
+        format!("server is up since the future: {}", status.up_since)
+    })?;
+
+    let worlds = harness.api().get_worlds().await?;
+    check(!worlds.is_empty(), || "server has no worlds".to_owned())?;
+
+    Ok(())
+}
+
+async fn import_stars(harness: &Harness) -> Result<(), Error> {
+    let ids = harness.api().create_stars(fixture_stars()).await?;
+    check(ids.len() == 2, || {
+        format!("created {} stars instead of 2", ids.len())
+    })?;
+
+    let stars = harness.api().get_stars().await?;
+    for id in &ids {
+        let star = stars
+            .iter()
+            .find(|star| star.id == *id)
+            .ok_or_else(|| Error::Check(format!("star {} wasn't created", id.0)))?;
+        check(star.name.is_some(), || {
+            format!("star {} wasn't given a name", id.0)
+        })?;
+    }
+
+    let sol = stars
+        .iter()
+        .find(|star| star.id == ids[0])
+        .and_then(|star| star.name.as_deref());
+    check(sol == Some("Sol"), || format!("Sol is called {sol:?}"))?;
+
+    // nobody explored the stars, so visitors don't know their names.
+    let stars = harness.anonymous_api().get_stars().await?;
+    for id in &ids {
+        let star = stars
+            .iter()
+            .find(|star| star.id == *id)
+            .ok_or_else(|| Error::Check(format!("star {} is hidden from visitors", id.0)))?;
+        check(star.name.is_none(), || {
+            format!("visitors know the name of star {}", id.0)
+        })?;
+    }
+
+    Ok(())
+}
+
+async fn fetch_assets(harness: &Harness) -> Result<(), Error> {
+    let manifest = harness.assets().get_manifest().await?;
+    let mut asset_types = AssetTypes::default();
+    asset_types.with_builtin();
+    let assets = manifest.assets.parse(&asset_types)?;
+
+    let icon_set = assets
+        .iter::<IconSet>()
+        .find(|icon_set| icon_set.label.as_deref() == Some("e2e icons"))
+        .ok_or_else(|| Error::Check("fixture icon set is missing".to_owned()))?;
+    check(icon_set.icons.iter().any(|icon| icon == "star"), || {
+        format!("icon set has the wrong icons: {:?}", icon_set.icons)
+    })?;
+
+    let image = harness
+        .assets()
+        .download_file(&icon_set.image)
+        .await?
+        .bytes()
+        .await?;
+    check(!image.is_empty(), || "icon set image is empty".to_owned())?;
+
+    let color_ramp = assets
+        .iter::<ColorRamp>()
+        .find(|color_ramp| color_ramp.label.as_deref() == Some("e2e ramp"))
+        .ok_or_else(|| Error::Check("fixture color ramp is missing".to_owned()))?;
+    check(!color_ramp.lut.is_empty(), || {
+        "color ramp is empty".to_owned()
+    })?;
+
+    Ok(())
+}
+
+async fn open_session(harness: &Harness) -> Result<(), Error> {
+    let _session = harness.api().session().await?;
+    let mut star_events = harness.api().star_events().await?;
+
+    let ids = harness.api().create_stars(fixture_stars()).await?;
+
+    let mut inserted = Vec::<StarId>::new();
+    tokio::time::timeout(EVENT_TIMEOUT, async {
+        while !ids.iter().all(|id| inserted.contains(id)) {
+            match star_events.next().await? {
+                StarEvent::Inserted { star } => inserted.push(star.id),
+                StarEvent::Reload => {
+                    return Err(Error::Check("star events asked to reload".to_owned()))
+                }
+                _ => {}
+            }
+        }
+        Ok::<_, Error>(())
+    })
+    .await
+    .map_err(|_| Error::Timeout("inserted stars"))??;
+
+    harness.api().delete_star(ids[1]).await?;
+    tokio::time::timeout(EVENT_TIMEOUT, async {
+        loop {
+            match star_events.next().await? {
+                StarEvent::Deleted { id } if id == ids[1] => break,
+                StarEvent::Reload => {
+                    return Err(Error::Check("star events asked to reload".to_owned()))
+                }
+                _ => {}
+            }
+        }
+        Ok::<_, Error>(())
+    })
+    .await
+    .map_err(|_| Error::Timeout("deleted star"))??;
+
+    Ok(())
+}
+
+async fn second_world(harness: &Harness) -> Result<(), Error> {
+    let name = format!("e2e {}", chrono::Utc::now().timestamp_micros());
+    let world = harness.api().create_world(name).await?;
+    let world_api = harness.api().clone().with_world(world.id);
+
+    // the world's queries run on several connections, which must all use the
+    // world's types.
+    let ids = world_api.create_stars(fixture_stars()).await?;
+    for _ in 0..4 {
+        let stars = world_api.get_stars().await?;
+        check(stars.len() == ids.len(), || {
+            format!(
+                "second world has {} stars instead of {}",
+                stars.len(),
+                ids.len()
+            )
+        })?;
+        let stars = harness.api().get_stars().await?;
+        check(!stars.iter().any(|star| ids.contains(&star.id)), || {
+            "stars of the second world are in the default world".to_owned()
+        })?;
+    }
+
+    Ok(())
+}
+
+/// The Sun, and Proxima Centauri without a name, so that it's generated.
+fn fixture_stars() -> Vec<CreateStar> {
+    vec![
+        CreateStar {
+            position: Point3::origin(),
+            effective_temperature: 5772.0,
+            color: stellar::teff_color(5772.0),
+            absolute_magnitude: stellar::SOLAR_ABSOLUTE_MAGNITUDE,
+            luminousity: 1.0,
+            radius: 1.0,
+            mass: 1.0,
+            spectral_type: "G2V".to_owned(),
+            name: Some("Sol".to_owned()),
+            catalog_ids: CatalogIds::default(),
+            variability: None,
+        },
+        CreateStar {
+            position: Point3::new(-0.47, -0.36, -1.16),
+            effective_temperature: 3042.0,
+            color: stellar::teff_color(3042.0),
+            absolute_magnitude: 15.5,
+            luminousity: 0.0017,
+            radius: 0.154,
+            mass: 0.122,
+            spectral_type: "M5.5Ve".to_owned(),
+            name: None,
+            catalog_ids: CatalogIds::default(),
+            variability: None,
+        },
+    ]
+}
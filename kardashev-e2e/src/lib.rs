@@ -0,0 +1,252 @@
+//! End-to-end tests of the server, the asset pipeline and the client.
+//!
+//! A [`Harness`] processes an asset tree, starts a server with a throwaway
+//! database on a random port, and connects clients to it. The database is
+//! created on an existing PostgreSQL server, or an
+//! [embedded one](kardashev_server::EmbeddedDb) is started. The [`flows`] drive
+//! the clients like players and admins do. They run in this crate's tests, and
+//! with `kardashev-cli selftest` against a fresh server.
+
+pub mod flows;
+
+use std::{
+    net::SocketAddr,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use axum::Router;
+use kardashev_client::{
+    ApiClient,
+    AssetClient,
+};
+use kardashev_protocol::uuid::Uuid;
+use kardashev_server::{
+    EmbeddedDb,
+    EmbeddedDbConfig,
+};
+use sqlx::PgPool;
+use tempfile::TempDir;
+use tokio::{
+    net::TcpListener,
+    task::JoinHandle,
+};
+use tokio_util::sync::CancellationToken;
+use tower_http::services::ServeDir;
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+#[error("end-to-end test error")]
+pub enum Error {
+    Io(#[from] std::io::Error),
+    Server(#[from] kardashev_server::Error),
+    EmbeddedDb(#[from] kardashev_server::EmbeddedDbError),
+    Sqlx(#[from] sqlx::Error),
+    Client(#[from] kardashev_client::Error),
+    Download(#[from] kardashev_client::DownloadError),
+    Assets(#[from] kardashev_build::assets::Error),
+    AssetParse(#[from] kardashev_protocol::assets::AssetParseError),
+    Url(#[from] url::ParseError),
+    #[error("check failed: {0}")]
+    Check(String),
+    #[error("timed out waiting for {0}")]
+    Timeout(&'static str),
+}
+
+#[derive(Clone, Debug)]
+pub struct HarnessConfig {
+    /// Asset tree that is processed and served.
+    pub assets: PathBuf,
+
+    /// PostgreSQL server on which the throwaway database is created. Without
+    /// it, an embedded server is started, which is downloaded on first use.
+    pub database_url: Option<String>,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        Self {
+            assets: fixtures_path().join("assets"),
+            database_url: None,
+        }
+    }
+}
+
+/// Directory with the fixtures of this crate.
+pub fn fixtures_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// A server with its own database and assets, and clients connected to it.
+///
+/// Everything is removed when the harness is [stopped](Self::stop).
+#[derive(Debug)]
+pub struct Harness {
+    address: SocketAddr,
+    api: ApiClient,
+    anonymous_api: ApiClient,
+    assets: AssetClient,
+    shutdown: CancellationToken,
+    server: JoinHandle<Result<(), std::io::Error>>,
+    db: HarnessDb,
+    _dist: TempDir,
+}
+
+impl Harness {
+    pub async fn start(config: HarnessConfig) -> Result<Self, Error> {
+        let dist = tempfile::tempdir()?;
+        let dist_assets = dist.path().join("assets");
+        let processed =
+            kardashev_build::assets::process(&config.assets, &dist_assets, true).await?;
+        tracing::info!(num_assets = processed.changed.len(), "processed assets");
+
+        let db = HarnessDb::start(config.database_url.as_deref()).await?;
+
+        let admin_token = Uuid::new_v4().to_string();
+        let shutdown = CancellationToken::new();
+        let server = kardashev_server::Builder::default()
+            .with_shutdown(shutdown.clone())
+            .with_admin_token(admin_token.as_str())
+            .with_simulation(kardashev_server::SimulationConfig {
+                allow_forced_ticks: true,
+                ..Default::default()
+            })
+            .with_connect_db(db.url())
+            .await?
+            .migrate()
+            .await?;
+
+        let router = Router::new()
+            .nest("/api", server.build())
+            .nest_service("/assets", ServeDir::new(&dist_assets));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let server = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                axum::serve(listener, router)
+                    .with_graceful_shutdown(async move { shutdown.cancelled().await })
+                    .await
+            }
+        });
+        tracing::info!(%address, "started server");
+
+        let url = Url::parse(&format!("http://{address}/"))?;
+        let anonymous_api = ApiClient::new(url.join("api/")?);
+        Ok(Self {
+            address,
+            api: anonymous_api.clone().with_admin_token(admin_token),
+            anonymous_api,
+            assets: AssetClient::new(url.join("assets/")?),
+            shutdown,
+            server,
+            db,
+            _dist: dist,
+        })
+    }
+
+    /// Address on which the server listens.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Client with the server's admin token.
+    pub fn api(&self) -> &ApiClient {
+        &self.api
+    }
+
+    /// Client without admin token or faction, like a visitor's.
+    pub fn anonymous_api(&self) -> &ApiClient {
+        &self.anonymous_api
+    }
+
+    pub fn assets(&self) -> &AssetClient {
+        &self.assets
+    }
+
+    /// Shuts the server down, and removes the database and assets.
+    pub async fn stop(self) -> Result<(), Error> {
+        self.shutdown.cancel();
+        match self.server.await {
+            Ok(result) => result?,
+            Err(error) => tracing::error!(?error, "server task failed"),
+        }
+        self.db.stop().await?;
+        Ok(())
+    }
+}
+
+/// The throwaway database of a [`Harness`].
+#[derive(Debug)]
+enum HarnessDb {
+    /// A database created on an existing server, which is dropped when the
+    /// harness is stopped.
+    Created {
+        server: PgPool,
+        name: String,
+        url: String,
+    },
+    Embedded(EmbeddedDb),
+}
+
+impl HarnessDb {
+    async fn start(server_url: Option<&str>) -> Result<Self, Error> {
+        let Some(server_url) = server_url
+        else {
+            return Ok(Self::Embedded(
+                EmbeddedDb::start(EmbeddedDbConfig::default()).await?,
+            ));
+        };
+
+        let server = PgPool::connect(server_url).await?;
+        let name = format!("kardashev_e2e_{}", Uuid::new_v4().simple());
+        sqlx::query(&format!("CREATE DATABASE {name}"))
+            .execute(&server)
+            .await?;
+        tracing::info!(%name, "created database");
+
+        let mut url = Url::parse(server_url)?;
+        url.set_path(&name);
+
+        Ok(Self::Created {
+            server,
+            name,
+            url: url.into(),
+        })
+    }
+
+    fn url(&self) -> &str {
+        match self {
+            Self::Created { url, .. } => url,
+            Self::Embedded(db) => db.url(),
+        }
+    }
+
+    async fn stop(self) -> Result<(), Error> {
+        match self {
+            Self::Created { server, name, .. } => {
+                // the server's background jobs might still be connected.
+                sqlx::query(&format!("DROP DATABASE {name} WITH (FORCE)"))
+                    .execute(&server)
+                    .await?;
+                server.close().await;
+                tracing::info!(%name, "dropped database");
+            }
+            Self::Embedded(db) => db.stop().await?,
+        }
+        Ok(())
+    }
+}
+
+/// Fails with [`Error::Check`] if `condition` doesn't hold.
+pub(crate) fn check(condition: bool, message: impl FnOnce() -> String) -> Result<(), Error> {
+    if condition {
+        Ok(())
+    }
+    else {
+        Err(Error::Check(message()))
+    }
+}
@@ -0,0 +1,24 @@
+use kardashev_e2e::{
+    flows,
+    Harness,
+    HarnessConfig,
+};
+
+/// Runs the flows on a throwaway database on the server at `DATABASE_URL`,
+/// which is also needed to compile the server's queries.
+#[tokio::test]
+async fn all_flows_pass() {
+    let config = HarnessConfig {
+        database_url: dotenvy::var("DATABASE_URL").ok(),
+        ..Default::default()
+    };
+    let harness = Harness::start(config).await.unwrap();
+    let results = flows::run_all(&harness).await;
+    harness.stop().await.unwrap();
+
+    for result in results {
+        if let Err(error) = result.result {
+            panic!("flow {} failed: {error:?}", result.flow.name());
+        }
+    }
+}
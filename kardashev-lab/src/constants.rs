@@ -3,3 +3,7 @@ pub const SUN_GALACTIC_PERIOD: f64 = 212000000.0; // in yr
 pub const PROXIMA_CENTAURI_DINSTANCE: f64 = 4.2465; // in lyr
 pub const STARS_IN_MILKY_WAY: u64 = 1_000_000_000; // the milkyway has way more stars, but that's the approximate amount in the
                                                    // gaia data
+pub const STAR_DENSITY: f64 = 0.004; // stars per lyr^3 in the solar neighbourhood
+pub const PLANCK_CONSTANT: f64 = 6.62607015e-34; // in J s
+pub const SPEED_OF_LIGHT: f64 = 299792458.0; // in m/s
+pub const BOLTZMANN_CONSTANT: f64 = 1.380649e-23; // in J/K
@@ -0,0 +1,473 @@
+//! Headless simulation of the economy, for evaluating balance changes.
+//!
+//! This runs the same rules as the server's simulation epochs: fleets travel
+//! to stars and colonize them as [`advance_fleet`] does, and every colony
+//! produces the [`colony_power`] of its star. Instead of the catalog, every
+//! empire gets its own random neighbourhood of stars, and its fleets always
+//! colonize the next star picked by the empire's [`Strategy`].
+
+use std::{
+    f64::consts::PI,
+    fmt::Write as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+    time::Duration,
+};
+
+use kardashev_protocol::model::{
+    fleet::{
+        advance_fleet,
+        OrderKind,
+    },
+    leaderboard::{
+        colony_power,
+        kardashev_rating,
+    },
+    star::StarId,
+};
+use nalgebra::{
+    Point3,
+    Vector3,
+};
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+use rand_distr::Normal;
+use tabled::{
+    builder::Builder,
+    settings::Style,
+};
+use uuid::Uuid;
+
+use crate::{
+    constants::STAR_DENSITY,
+    Error,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct EconomyArgs {
+    /// Number of epochs to simulate.
+    #[arg(long, default_value = "10000")]
+    ticks: u32,
+
+    /// Game time per epoch.
+    ///
+    /// This is coarser than the server's epochs, so that the default run
+    /// covers more than a year.
+    #[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+    epoch: Duration,
+
+    /// An empire, e.g.
+    /// `name=Sol,fleets=2,speed=0.01,home=1,stars=2000,strategy=brightest`.
+    ///
+    /// Can be given multiple times. All fields are optional.
+    #[arg(long = "empire")]
+    empires: Vec<EmpireConfig>,
+
+    /// Number of samples that are printed.
+    #[arg(long, default_value = "20")]
+    samples: u32,
+
+    /// Seed for the generated stars.
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Plot the Kardashev ratings.
+    #[arg(long)]
+    plot: bool,
+
+    /// Writes all samples to a CSV file, e.g. for plotting them elsewhere.
+    #[arg(long)]
+    csv: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+struct EmpireConfig {
+    name: String,
+
+    /// Number of fleets, which colonize stars.
+    fleets: usize,
+
+    /// Speed of the fleets in light-years per day.
+    speed: f32,
+
+    /// Luminosity of the home star in solar luminosities.
+    home: f32,
+
+    /// Number of stars in the empire's neighbourhood.
+    stars: usize,
+
+    strategy: Strategy,
+}
+
+impl Default for EmpireConfig {
+    fn default() -> Self {
+        Self {
+            name: "Sol".to_owned(),
+            fleets: 1,
+            speed: 0.01,
+            home: 1.0,
+            stars: 2000,
+            strategy: Strategy::Nearest,
+        }
+    }
+}
+
+impl FromStr for EmpireConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("invalid value for {key}: {value}"))
+        }
+
+        let mut config = Self::default();
+        for field in s.split(',').filter(|field| !field.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value: {field}"))?;
+            match key {
+                "name" => config.name = value.to_owned(),
+                "fleets" => config.fleets = parse(key, value)?,
+                "speed" => config.speed = parse(key, value)?,
+                "home" => config.home = parse(key, value)?,
+                "stars" => config.stars = parse(key, value)?,
+                "strategy" => config.strategy = parse(key, value)?,
+                _ => return Err(format!("unknown field: {key}")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// How an empire picks the next star to colonize.
+#[derive(Clone, Copy, Debug)]
+enum Strategy {
+    /// The nearest star.
+    Nearest,
+
+    /// The star with the highest luminosity per light-year of travel.
+    Brightest,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "nearest" => Ok(Self::Nearest),
+            "brightest" => Ok(Self::Brightest),
+            _ => Err(format!("unknown strategy: {s}")),
+        }
+    }
+}
+
+struct Star {
+    position: Point3<f32>,
+    luminosity: f32,
+    claimed: bool,
+}
+
+struct Fleet {
+    position: Point3<f32>,
+    work: f32,
+    order: Option<OrderKind>,
+}
+
+struct Empire {
+    config: EmpireConfig,
+    stars: Vec<Star>,
+    fleets: Vec<Fleet>,
+    colonies: u32,
+
+    /// Energy output in watts.
+    power: f64,
+
+    /// Energy produced so far in joules.
+    energy: f64,
+}
+
+impl Empire {
+    fn new(config: EmpireConfig, rng: &mut impl Rng) -> Self {
+        // the home star is at the center of the neighbourhood.
+        let radius = (3.0 * config.stars as f64 / (4.0 * PI * STAR_DENSITY)).cbrt() as f32;
+        let log_luminosity = Normal::new(-1.5f32, 1.0).expect("invalid distribution");
+        let mut stars = vec![Star {
+            position: Point3::origin(),
+            luminosity: config.home,
+            claimed: true,
+        }];
+        stars.extend((1..config.stars).map(|_| {
+            Star {
+                position: Point3::from(random_in_ball(rng) * radius),
+                luminosity: 10f32.powf(rng.sample(log_luminosity).clamp(-4.0, 5.0)),
+                claimed: false,
+            }
+        }));
+
+        let fleets = (0..config.fleets)
+            .map(|_| {
+                Fleet {
+                    position: Point3::origin(),
+                    work: 0.0,
+                    order: None,
+                }
+            })
+            .collect();
+
+        Self {
+            power: colony_power(config.home.into()),
+            config,
+            stars,
+            fleets,
+            colonies: 1,
+            energy: 0.0,
+        }
+    }
+
+    /// Picks an unclaimed star for a fleet at `position`, and claims it.
+    fn pick_target(&mut self, position: Point3<f32>) -> Option<StarId> {
+        let strategy = self.config.strategy;
+        let (index, star) = self
+            .stars
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, star)| !star.claimed)
+            .max_by(|(_, a), (_, b)| {
+                let score = |star: &Star| {
+                    let distance = (star.position - position).norm();
+                    match strategy {
+                        Strategy::Nearest => -distance,
+                        Strategy::Brightest => star.luminosity / distance.max(1.0),
+                    }
+                };
+                score(a).total_cmp(&score(b))
+            })?;
+        star.claimed = true;
+        Some(star_id(index))
+    }
+
+    /// Advances the empire by one epoch of `days`.
+    fn tick(&mut self, days: f32) {
+        self.energy += self.power * f64::from(days) * 86_400.0;
+
+        for i in 0..self.fleets.len() {
+            if self.fleets[i].order.is_none() {
+                let position = self.fleets[i].position;
+                self.fleets[i].order = self
+                    .pick_target(position)
+                    .map(|star| OrderKind::Colonize { star });
+            }
+
+            let fleet = &mut self.fleets[i];
+            let advance = advance_fleet(
+                fleet.position,
+                self.config.speed,
+                fleet.work,
+                fleet.order.iter(),
+                |star| self.stars.get(star_index(star)).map(|star| star.position),
+                days,
+            );
+            fleet.position = advance.position;
+            fleet.work = advance.work;
+
+            if advance.completed > 0 {
+                if let Some(order) = fleet.order.take() {
+                    let luminosity = self.stars[star_index(order.star())].luminosity;
+                    self.colonies += 1;
+                    self.power += colony_power(luminosity.into());
+                }
+            }
+        }
+    }
+
+    fn sample(&self) -> EmpireSample {
+        EmpireSample {
+            colonies: self.colonies,
+            power: self.power,
+            energy: self.energy,
+            rating: kardashev_rating(self.power),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct EmpireSample {
+    colonies: u32,
+    power: f64,
+    energy: f64,
+    rating: f64,
+}
+
+struct Sample {
+    day: f64,
+    empires: Vec<EmpireSample>,
+}
+
+pub fn economy(args: EconomyArgs) -> Result<(), Error> {
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let configs = if args.empires.is_empty() {
+        vec![EmpireConfig::default()]
+    }
+    else {
+        args.empires
+    };
+    let names = configs
+        .iter()
+        .map(|config| config.name.clone())
+        .collect::<Vec<_>>();
+    let mut empires = configs
+        .into_iter()
+        .map(|config| Empire::new(config, &mut rng))
+        .collect::<Vec<_>>();
+
+    let days = args.epoch.as_secs_f32() / 86_400.0;
+    let sample_every = (args.ticks / args.samples.max(1)).max(1);
+    let take_sample = |tick: u32, empires: &[Empire]| {
+        Sample {
+            day: f64::from(tick) * f64::from(days),
+            empires: empires.iter().map(Empire::sample).collect(),
+        }
+    };
+
+    let mut samples = vec![take_sample(0, &empires)];
+    for tick in 1..=args.ticks {
+        for empire in &mut empires {
+            empire.tick(days);
+        }
+        if tick % sample_every == 0 || tick == args.ticks {
+            samples.push(take_sample(tick, &empires));
+        }
+    }
+
+    print_table(&names, &samples);
+
+    if args.plot {
+        println!();
+        print_plot(&names, &samples);
+    }
+
+    if let Some(path) = &args.csv {
+        write_csv(path, &names, &samples)?;
+        tracing::info!(path = %path.display(), "wrote samples");
+    }
+
+    Ok(())
+}
+
+fn print_table(names: &[String], samples: &[Sample]) {
+    let mut builder = Builder::default();
+    builder.push_record(
+        std::iter::once("Day".to_owned()).chain(names.iter().flat_map(|name| {
+            [
+                format!("{name} colonies"),
+                format!("{name} power (W)"),
+                format!("{name} energy (J)"),
+                format!("{name} rating"),
+            ]
+        })),
+    );
+    for sample in samples {
+        builder.push_record(std::iter::once(format!("{:.0}", sample.day)).chain(
+            sample.empires.iter().flat_map(|empire| {
+                [
+                    empire.colonies.to_string(),
+                    format!("{:.3e}", empire.power),
+                    format!("{:.3e}", empire.energy),
+                    format!("{:.3}", empire.rating),
+                ]
+            }),
+        ));
+    }
+
+    let mut table = builder.build();
+    table.with(Style::markdown());
+    println!("{table}");
+}
+
+/// Plots the ratings of all empires over time, with one column per sample.
+fn print_plot(names: &[String], samples: &[Sample]) {
+    const HEIGHT: usize = 16;
+    const MARKERS: &[char] = &['*', '+', 'o', 'x', '#', '@'];
+
+    let ratings = || {
+        samples
+            .iter()
+            .flat_map(|sample| sample.empires.iter().map(|empire| empire.rating))
+    };
+    let min = ratings().fold(f64::INFINITY, f64::min);
+    let max = ratings().fold(f64::NEG_INFINITY, f64::max);
+    let range = (max - min).max(1e-6);
+
+    let mut grid = vec![vec![' '; samples.len()]; HEIGHT];
+    for (column, sample) in samples.iter().enumerate() {
+        for (i, empire) in sample.empires.iter().enumerate() {
+            let row = ((empire.rating - min) / range * (HEIGHT - 1) as f64).round() as usize;
+            grid[HEIGHT - 1 - row][column] = MARKERS[i % MARKERS.len()];
+        }
+    }
+
+    for (i, row) in grid.into_iter().enumerate() {
+        let label = if i == 0 {
+            format!("{max:.3}")
+        }
+        else if i == HEIGHT - 1 {
+            format!("{min:.3}")
+        }
+        else {
+            String::new()
+        };
+        println!("{label:>8} |{}", row.into_iter().collect::<String>());
+    }
+    println!("{:>8} +{}", "", "-".repeat(samples.len()));
+    if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+        println!("{:>8}  day {:.0} .. {:.0}", "", first.day, last.day);
+    }
+    for (i, name) in names.iter().enumerate() {
+        println!("{:>8}  {} {name}", "", MARKERS[i % MARKERS.len()]);
+    }
+}
+
+fn write_csv(path: &Path, names: &[String], samples: &[Sample]) -> Result<(), Error> {
+    let mut csv = "day,empire,colonies,power,energy,rating\n".to_owned();
+    for sample in samples {
+        for (name, empire) in names.iter().zip(&sample.empires) {
+            writeln!(
+                csv,
+                "{},{name:?},{},{},{},{}",
+                sample.day, empire.colonies, empire.power, empire.energy, empire.rating
+            )?;
+        }
+    }
+    std::fs::write(path, csv)?;
+    Ok(())
+}
+
+fn star_id(index: usize) -> StarId {
+    StarId(Uuid::from_u128(index as u128))
+}
+
+fn star_index(star: StarId) -> usize {
+    star.0.as_u128() as usize
+}
+
+/// Returns a uniformly distributed point in the unit ball.
+fn random_in_ball(rng: &mut impl Rng) -> Vector3<f32> {
+    loop {
+        let point = Vector3::new(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+        );
+        if point.norm_squared() <= 1.0 {
+            return point;
+        }
+    }
+}
@@ -1,18 +1,36 @@
 mod constants;
+mod economy;
 mod galaxy;
+mod star_colors;
 mod time_scale;
 
-use std::collections::BTreeSet;
-
 use clap::Parser;
 pub use color_eyre::eyre::Error;
 
-use crate::time_scale::time_scale;
+use crate::{
+    economy::{
+        economy,
+        EconomyArgs,
+    },
+    star_colors::{
+        star_colors,
+        StarColorsArgs,
+    },
+    time_scale::time_scale,
+};
 
 #[derive(Debug, Parser)]
 enum Args {
-    TimeScale { time_per_year: Vec<String> },
-    KeyCodes,
+    TimeScale {
+        time_per_year: Vec<String>,
+    },
+
+    /// Simulates the economy of some empires, to evaluate balance changes.
+    Economy(EconomyArgs),
+
+    /// Generates the table and lookup texture of star colors by effective
+    /// temperature.
+    StarColors(StarColorsArgs),
 }
 
 fn main() -> Result<(), Error> {
@@ -23,38 +41,9 @@ fn main() -> Result<(), Error> {
     let args = Args::parse();
     match args {
         Args::TimeScale { time_per_year } => time_scale(time_per_year)?,
-        Args::KeyCodes => key_codes()?,
-    }
-
-    Ok(())
-}
-
-fn key_codes() -> Result<(), Error> {
-    let s = include_str!("../key_codes.txt");
-    let mut codes = BTreeSet::new();
-    for line in s.lines() {
-        let line = line.trim();
-        if !line.is_empty() {
-            let parts = line.split('\t').collect::<Vec<_>>();
-            if let Some(name) = parts.get(1).and_then(|name| str_between(name, "\"", "\"")) {
-                if name != "Unidentified" {
-                    codes.insert(name);
-                }
-            }
-        }
-    }
-
-    println!("key_codes!{{");
-    for code in codes {
-        println!("    {code},");
+        Args::Economy(args) => economy(args)?,
+        Args::StarColors(args) => star_colors(args)?,
     }
-    println!("}}");
 
     Ok(())
 }
-
-fn str_between<'a>(s: &'a str, start: &str, end: &str) -> Option<&'a str> {
-    let start = s.find(start)? + start.len();
-    let end = s[start..].find(end)?;
-    Some(&s[start..=end])
-}
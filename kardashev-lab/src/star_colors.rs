@@ -0,0 +1,154 @@
+//! Generates the colors of stars from their effective temperature.
+//!
+//! The color of a black body is computed by integrating Planck's law over the
+//! visible spectrum, weighted with the CIE 1931 color matching functions, and
+//! converting the result to linear sRGB. The brightest channel is normalized
+//! to 1, since the brightness of stars is rendered separately.
+//!
+//! This writes the table that
+//! [`teff_color`](kardashev_protocol::stellar::teff_color) interpolates, and a
+//! lookup texture with the entries of
+//! [`teff_lut`](kardashev_protocol::stellar::teff_lut).
+
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+};
+
+use image::{
+    Rgba,
+    RgbaImage,
+};
+use kardashev_protocol::stellar::{
+    teff_lut_temperature,
+    MAX_EFFECTIVE_TEMPERATURE,
+    MIN_EFFECTIVE_TEMPERATURE,
+    TEFF_LUT_SIZE,
+};
+use palette::{
+    LinSrgb,
+    Srgb,
+};
+
+use crate::{
+    constants::{
+        BOLTZMANN_CONSTANT,
+        PLANCK_CONSTANT,
+        SPEED_OF_LIGHT,
+    },
+    Error,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct StarColorsArgs {
+    /// Lowest effective temperature in the table.
+    #[arg(long, default_value_t = MIN_EFFECTIVE_TEMPERATURE)]
+    min: f32,
+
+    /// Highest effective temperature in the table.
+    #[arg(long, default_value_t = MAX_EFFECTIVE_TEMPERATURE)]
+    max: f32,
+
+    /// Difference between the effective temperatures in the table.
+    #[arg(long, default_value = "100")]
+    step: f32,
+
+    /// Path of the table.
+    #[arg(long, default_value = "kardashev-protocol/src/stellar/teff-rgb.csv")]
+    table: PathBuf,
+
+    /// Path of the lookup texture.
+    #[arg(long, default_value = "assets/stars/teff-lut.png")]
+    lut: PathBuf,
+}
+
+pub fn star_colors(args: StarColorsArgs) -> Result<(), Error> {
+    let mut table = "t_eff,r,g,b\n".to_owned();
+    let mut t_eff = args.min;
+    while t_eff <= args.max {
+        let color = blackbody_color(t_eff.into());
+        writeln!(
+            table,
+            "{t_eff},{:.3},{:.3},{:.3}",
+            color.red, color.green, color.blue
+        )?;
+        t_eff += args.step;
+    }
+    std::fs::write(&args.table, table)?;
+    tracing::info!(path = %args.table.display(), "wrote table");
+
+    let mut lut = RgbaImage::new(TEFF_LUT_SIZE as u32, 1);
+    for (index, pixel) in lut.pixels_mut().enumerate() {
+        let color = blackbody_color(teff_lut_temperature(index).into());
+        let color: Srgb<u8> = Srgb::from_linear(color);
+        *pixel = Rgba([color.red, color.green, color.blue, 255]);
+    }
+    if let Some(parent) = args.lut.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    lut.save(&args.lut)?;
+    tracing::info!(path = %args.lut.display(), "wrote lookup texture");
+
+    Ok(())
+}
+
+/// Color of a black body with the effective temperature `t_eff` in Kelvin,
+/// normalized so that the brightest channel is 1.
+fn blackbody_color(t_eff: f64) -> LinSrgb {
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for wavelength in (380..=780).step_by(5) {
+        let wavelength = f64::from(wavelength);
+        let radiance = planck(wavelength, t_eff);
+        let (x_bar, y_bar, z_bar) = color_matching(wavelength);
+        x += radiance * x_bar;
+        y += radiance * y_bar;
+        z += radiance * z_bar;
+    }
+
+    // XYZ to linear sRGB. Colors outside of the gamut are clipped.
+    let rgb = [
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    ]
+    .map(|channel: f64| channel.max(0.0));
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+
+    LinSrgb::new(
+        (rgb[0] / max) as f32,
+        (rgb[1] / max) as f32,
+        (rgb[2] / max) as f32,
+    )
+}
+
+/// Spectral radiance of a black body, with the wavelength in nm.
+fn planck(wavelength: f64, t_eff: f64) -> f64 {
+    let wavelength = wavelength * 1e-9;
+    2.0 * PLANCK_CONSTANT * SPEED_OF_LIGHT.powi(2)
+        / wavelength.powi(5)
+        / ((PLANCK_CONSTANT * SPEED_OF_LIGHT / (wavelength * BOLTZMANN_CONSTANT * t_eff)).exp()
+            - 1.0)
+}
+
+/// CIE 1931 color matching functions, with the wavelength in nm.
+///
+/// This uses the multi-lobe fit from Wyman, Sloan and Shirley, "Simple
+/// Analytic Approximations to the CIE XYZ Color Matching Functions" (2013).
+fn color_matching(wavelength: f64) -> (f64, f64, f64) {
+    let lobe = |mean: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if wavelength < mean {
+            sigma_below
+        }
+        else {
+            sigma_above
+        };
+        (-0.5 * ((wavelength - mean) / sigma).powi(2)).exp()
+    };
+
+    let x = 1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+        - 0.065 * lobe(501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8);
+
+    (x, y, z)
+}
@@ -1,29 +1,58 @@
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
 use nalgebra::Point3;
 use palette::LinSrgb;
 use serde::{
     Deserialize,
     Serialize,
 };
+use uuid::Uuid;
 
-use crate::model::star::{
-    CatalogIds,
-    StarId,
+use crate::model::{
+    faction::FactionId,
+    fleet::FleetId,
+    journal::{
+        JournalEntryId,
+        JournalEvent,
+    },
+    planet::PlanetId,
+    star::{
+        CatalogIds,
+        HabitableZone,
+        StarId,
+    },
+    world::{
+        World,
+        WorldId,
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CreateStarsRequest {
     pub stars: Vec<CreateStar>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CreateStarsResponse {
     pub ids: Vec<StarId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CreateStar {
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
     pub position: Point3<f32>,
     pub effective_temperature: f32,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::LinSrgb"))]
     pub color: LinSrgb,
     pub absolute_magnitude: f32,
     pub luminousity: f32,
@@ -33,4 +62,416 @@ pub struct CreateStar {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     pub catalog_ids: CatalogIds,
+
+    /// Peak-to-peak variability in magnitudes, if the star is variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variability: Option<f32>,
+}
+
+/// Renames a star. Names set this way are never replaced by generated names.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RenameStarRequest {
+    /// The new name, or `None` to remove the name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RenameStarResponse {
+    /// Name of the star before it was renamed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_name: Option<String>,
+}
+
+/// Creates planets around existing stars.
+///
+/// The host star of each planet is looked up by its catalog IDs and name.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreatePlanetsRequest {
+    pub planets: Vec<CreatePlanet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreatePlanetsResponse {
+    pub ids: Vec<PlanetId>,
+
+    /// Names of the planets for which no host star was found, or of their host
+    /// star, if they didn't have a name.
+    pub unmatched: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreatePlanet {
+    pub host: HostStar,
+
+    /// Name of the planet. Planets without a name are named after their host
+    /// star, e.g. "Sol b".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    pub confirmed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semi_major_axis: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eccentricity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orbital_period: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equilibrium_temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery_year: Option<i32>,
+}
+
+/// Identifies the host star of a planet.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HostStar {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub catalog_ids: CatalogIds,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateFleetsRequest {
+    pub fleets: Vec<CreateFleet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateFleetsResponse {
+    pub ids: Vec<FleetId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateFleet {
+    pub name: String,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+    pub speed: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faction: Option<FactionId>,
+    pub sensor_range: f32,
+}
+
+/// Appends events to factions' journals.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateJournalEntriesRequest {
+    pub entries: Vec<CreateJournalEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateJournalEntriesResponse {
+    pub ids: Vec<JournalEntryId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateJournalEntry {
+    pub faction: FactionId,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RecomputeRegionsResponse {
+    pub num_regions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RecomputeImpostorsResponse {
+    pub num_chunks: usize,
+}
+
+/// Audits the derived properties of all stars.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditStarsRequest {
+    /// Whether to fix the issues that can be fixed, by recomputing the derived
+    /// properties.
+    pub fix: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditStarsResponse {
+    pub num_stars: usize,
+    pub issues: Vec<StarIssue>,
+    pub num_fixed: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StarIssue {
+    pub star: StarId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub kind: StarIssueKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "issue", rename_all = "kebab-case")]
+pub enum StarIssueKind {
+    /// A property has an implausible value. Properties derived from it are not
+    /// checked.
+    OutOfRange { property: String, value: f32 },
+
+    /// The color doesn't match the effective temperature.
+    Color {
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::LinSrgb"))]
+        stored: LinSrgb,
+        #[cfg_attr(feature = "schema", schemars(with = "crate::schema::LinSrgb"))]
+        expected: LinSrgb,
+    },
+
+    /// The absolute magnitude doesn't match the luminosity.
+    AbsoluteMagnitude { stored: f32, expected: f32 },
+
+    /// The habitable zone doesn't match the luminosity, or is missing.
+    HabitableZone {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        stored: Option<HabitableZone>,
+        expected: HabitableZone,
+    },
+}
+
+impl StarIssueKind {
+    /// Whether the issue is fixed by recomputing the derived properties.
+    pub fn is_fixable(&self) -> bool {
+        !matches!(self, Self::OutOfRange { .. })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetAssetStatsResponse {
+    /// Time since which downloads are counted.
+    pub since: DateTime<Utc>,
+
+    /// Downloads per asset file, most bytes first.
+    pub downloads: Vec<AssetDownloads>,
+
+    /// Asset files that were never requested.
+    pub unrequested: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AssetDownloads {
+    /// Path of the file, relative to the dist directory.
+    pub path: String,
+    pub requests: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetSimulationStateResponse {
+    /// Number of epochs that were simulated.
+    pub epoch: i64,
+    pub epoch_at: DateTime<Utc>,
+
+    /// Hex-encoded hash of the state after the last epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epoch_state_hash: Option<String>,
+
+    /// Hex-encoded hash of the current state. This differs from the hash after
+    /// the last epoch if e.g. players gave orders since.
+    pub current_state_hash: String,
+}
+
+/// Runs simulation epochs now. The server must allow forced ticks.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SimulateQuery {
+    /// Number of epochs to run.
+    pub ticks: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SimulateResponse {
+    pub ticks: u32,
+
+    /// Number of epochs that were simulated, including the forced ones.
+    pub epoch: i64,
+
+    /// Hex-encoded hash of the state after the last epoch.
+    pub state_hash: String,
+}
+
+/// Creates a new, empty world.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateWorldRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateWorldResponse {
+    pub world: World,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetBackupsResponse {
+    /// Where backups are written to, or `None` if backups are disabled.
+    pub target: Option<String>,
+
+    /// Time between scheduled backups, in seconds. `None` if backups are only
+    /// made on request.
+    pub interval: Option<u64>,
+
+    /// Recent backups of all worlds, the newest first.
+    pub backups: Vec<Backup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateBackupResponse {
+    pub backup: Backup,
+}
+
+/// A logical backup of a world's tables.
+///
+/// All tables are exported from the same snapshot of the database.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Backup {
+    /// Name of the backup, which is also its directory in the target.
+    pub name: String,
+    pub world: WorldId,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub tables: Vec<TableBackup>,
+
+    /// Why the backup failed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TableBackup {
+    pub table: String,
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+/// An event that can be sent to webhooks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookEvent {
+    /// A player connected, and no one else of their faction was connected.
+    PlayerJoined,
+    WarDeclared,
+    ServerStarted,
+    BackupFailed,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 4] = [
+        Self::PlayerJoined,
+        Self::WarDeclared,
+        Self::ServerStarted,
+        Self::BackupFailed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PlayerJoined => "player-joined",
+            Self::WarDeclared => "war-declared",
+            Self::ServerStarted => "server-started",
+            Self::BackupFailed => "backup-failed",
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown webhook event: {0}")]
+pub struct UnknownWebhookEvent(pub String);
+
+impl FromStr for WebhookEvent {
+    type Err = UnknownWebhookEvent;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == s)
+            .ok_or_else(|| UnknownWebhookEvent(s.to_owned()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct WebhookId(pub Uuid);
+
+/// A URL to which a world's events are posted, as JSON that Discord and Slack
+/// understand.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Webhook {
+    pub id: WebhookId,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetWebhooksResponse {
+    pub webhooks: Vec<Webhook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateWebhookRequest {
+    /// An `http` or `https` URL.
+    pub url: String,
+
+    /// The events that are sent to the webhook. If empty, all are.
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WebhookEvent;
+
+    #[test]
+    fn webhook_events_parse_their_names() {
+        for event in WebhookEvent::ALL {
+            assert_eq!(event.as_str().parse::<WebhookEvent>().unwrap(), event);
+            assert_eq!(
+                serde_json::to_string(&event).unwrap(),
+                format!("\"{}\"", event.as_str())
+            );
+        }
+        assert!("player-left".parse::<WebhookEvent>().is_err());
+    }
 }
@@ -40,7 +40,7 @@ use uuid::{
     Uuid,
 };
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(transparent)]
 pub struct AssetId(Uuid);
 
@@ -50,9 +50,18 @@ impl AssetId {
     }
 
     #[doc(hidden)]
-    pub fn from_uuid(uuid: Uuid) -> Self {
+    pub const fn from_uuid(uuid: Uuid) -> Self {
         Self(uuid)
     }
+
+    /// ID of the asset in a content pack's namespace.
+    ///
+    /// The IDs are derived from the pack's name, so that they're the same
+    /// every time the pack is loaded, but don't collide with other packs.
+    pub fn namespaced(&self, namespace: &str) -> Self {
+        let namespace = Uuid::new_v5(&CONTENT_PACK_NAMESPACE, namespace.as_bytes());
+        Self(Uuid::new_v5(&namespace, self.0.as_bytes()))
+    }
 }
 
 impl Display for AssetId {
@@ -68,12 +77,124 @@ macro_rules! asset_id {
     };
 }
 
+/// Namespace from which the namespaces of content packs are derived.
+const CONTENT_PACK_NAMESPACE: Uuid = uuid!("9b3e5f0c-71d2-4a86-b4c9-2e8d6a1f7c35");
+
+/// File next to the asset manifest that lists the [`ContentPacks`] a server
+/// offers.
+pub const CONTENT_PACKS_FILE: &str = "packs.json";
+
+/// Additional assets that are loaded on top of the built-in ones.
+///
+/// A content pack is a dist directory with its own manifest, built separately
+/// from the game. Its assets are moved into the pack's
+/// [namespace](AssetId::namespaced) when it's loaded. Assets from a pack can
+/// still refer to the built-in assets.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentPack {
+    /// Name of the pack, which is also its namespace.
+    pub name: String,
+
+    /// URL of the pack's dist directory, relative to the asset URL.
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ContentPacks {
+    pub packs: Vec<ContentPack>,
+}
+
+/// Version of the [`Manifest`] format written by this build.
+///
+/// Version 0 is the format from before manifests were versioned. Fields that
+/// older readers can ignore don't need a new version.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Oldest version a reader must support to read the manifests written by this
+/// build. This only needs to be bumped for changes that older readers would
+/// misinterpret.
+pub const MANIFEST_COMPATIBLE_VERSION: u32 = 0;
+
+/// Migrations of older manifests, such that `MANIFEST_MIGRATIONS[i]` migrates
+/// from version `i` to `i + 1`.
+const MANIFEST_MIGRATIONS: [fn(&mut serde_json::Value); MANIFEST_VERSION as usize] = [
+    // version 1 only added the version fields.
+    |_manifest| {},
+];
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Manifest {
+    /// Version of the format this manifest was written in.
+    #[serde(default)]
+    pub version: u32,
+
+    /// Oldest version a reader must support to read this manifest.
+    #[serde(default)]
+    pub compatible_version: u32,
+
     pub build_time: DateTime<Utc>,
     pub assets: AssetsBlob,
 }
 
+impl Manifest {
+    pub fn new(build_time: DateTime<Utc>, assets: AssetsBlob) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            compatible_version: MANIFEST_COMPATIBLE_VERSION,
+            build_time,
+            assets,
+        }
+    }
+
+    /// Parses a manifest, migrating it if it was written in an older version.
+    ///
+    /// Manifests of newer versions are parsed as long as they're compatible
+    /// with this version. Fields that were added since are ignored.
+    pub fn from_json(json: &[u8]) -> Result<Self, ManifestError> {
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default)]
+            version: u32,
+            #[serde(default)]
+            compatible_version: u32,
+        }
+
+        let mut manifest: serde_json::Value = serde_json::from_slice(json)?;
+        let header = Header::deserialize(&manifest)?;
+
+        if header.compatible_version > MANIFEST_VERSION {
+            return Err(ManifestError::Unsupported {
+                version: header.version,
+                compatible_version: header.compatible_version,
+            });
+        }
+
+        for (from, migrate) in MANIFEST_MIGRATIONS
+            .iter()
+            .enumerate()
+            .skip(header.version as usize)
+        {
+            tracing::debug!(from, "migrating asset manifest");
+            migrate(&mut manifest);
+            manifest["version"] = (from as u32 + 1).into();
+        }
+
+        Ok(serde_json::from_value(manifest)?)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("manifest error")]
+pub enum ManifestError {
+    Json(#[from] serde_json::Error),
+
+    #[error("manifest version {version} requires support for version {compatible_version}, but only versions up to {MANIFEST_VERSION} are supported")]
+    Unsupported {
+        version: u32,
+        compatible_version: u32,
+    },
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Texture {
     pub id: AssetId,
@@ -98,6 +219,24 @@ pub struct Texture {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub v_edge_mode: Option<TextureEdgeMode>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mag_filter: Option<TextureFilter>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_filter: Option<TextureFilter>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mipmap_filter: Option<TextureFilter>,
+
+    /// Maximum anisotropy used for sampling. Only applies if all filters are
+    /// linear.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anisotropy: Option<u16>,
+
+    /// Whether the client should generate mipmaps when uploading the texture.
+    #[serde(default)]
+    pub mipmaps: bool,
 }
 
 impl HasAssetId for Texture {
@@ -113,6 +252,10 @@ impl Asset for Texture {
     fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
         std::iter::once(&*self.image)
     }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.image)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
@@ -137,7 +280,7 @@ pub struct TextureCrop {
     pub h: u32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TextureEdgeMode {
     ClampToEdge,
     Repeat,
@@ -145,6 +288,13 @@ pub enum TextureEdgeMode {
     ClampToBorder,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
 // todo: how do we handle different materials here?
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Material {
@@ -196,6 +346,11 @@ pub struct Material {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub emissive_color: Option<Srgb<f32>>,
 
+    /// Scales the emissive color that feeds the bloom. Materials without it
+    /// don't bloom.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bloom: Option<f32>,
+
     // pbr
     // todo: colors
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -208,6 +363,26 @@ pub struct Material {
     pub roughness_texture: Option<AssetId>,
 }
 
+impl Material {
+    /// IDs of all textures the material uses.
+    pub fn textures(&self) -> impl Iterator<Item = AssetId> {
+        [
+            self.normal_texture,
+            self.ambient_texture,
+            self.diffuse_texture,
+            self.specular_texture,
+            self.shininess_texture,
+            self.dissolve_texture,
+            self.emissive_texture,
+            self.albedo_texture,
+            self.metalness_texture,
+            self.roughness_texture,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
 impl HasAssetId for Material {
     fn asset_id(&self) -> AssetId {
         self.id
@@ -221,6 +396,10 @@ impl Asset for Material {
     fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
         std::iter::empty()
     }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::empty()
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -248,6 +427,78 @@ impl Asset for Mesh {
     fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
         std::iter::once(&*self.mesh)
     }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.mesh)
+    }
+}
+
+/// Meshes with their materials, arranged in a hierarchy of nodes, e.g.
+/// imported from a glTF file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Model {
+    pub id: AssetId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    pub build_time: DateTime<Utc>,
+
+    /// Parents come before their children.
+    pub nodes: Vec<ModelNode>,
+}
+
+impl Model {
+    /// The meshes and materials of all nodes.
+    pub fn parts(&self) -> impl Iterator<Item = AssetId> + '_ {
+        self.nodes
+            .iter()
+            .flat_map(|node| &node.primitives)
+            .flat_map(|primitive| std::iter::once(primitive.mesh).chain(primitive.material))
+    }
+}
+
+impl HasAssetId for Model {
+    fn asset_id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl Asset for Model {
+    const TYPE_NAME: &'static str = "model";
+    const TYPE_ID: Uuid = uuid!("c2a7e4f1-58b3-4d96-8e0a-1f6b39d7a524");
+
+    fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        std::iter::empty()
+    }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Index of the parent node.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+
+    /// Transform relative to the parent, as a column-major matrix.
+    pub transform: [f32; 16],
+
+    pub primitives: Vec<ModelPrimitive>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ModelPrimitive {
+    pub mesh: AssetId,
+
+    /// Without one, the renderer's default material is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<AssetId>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -364,12 +615,65 @@ pub struct Vertex {
     pub bitangent: [f32; 3],
 }
 
+/// Events that the asset server sends on the `events` websocket while it
+/// watches the asset sources.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Event {
-    Changed { asset_ids: Vec<AssetId> },
+    /// The assets were rebuilt.
+    Rebuilt { diff: AssetsDiff },
+
+    /// Events were dropped, because the client didn't receive them fast
+    /// enough.
     Lagged,
 }
 
+/// How the dist manifest changed with a rebuild.
+///
+/// Assets that were rebuilt with the same output are not part of the diff.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AssetsDiff {
+    pub added: AssetsBlob,
+    pub modified: AssetsBlob,
+    pub removed: Vec<AssetId>,
+}
+
+impl AssetsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// IDs of all assets that were added, modified or removed.
+    pub fn asset_ids(&self) -> impl Iterator<Item = AssetId> + '_ {
+        self.added
+            .asset_ids()
+            .chain(self.modified.asset_ids())
+            .chain(self.removed.iter().copied())
+    }
+
+    /// Applies the diff to the assets of the previous build.
+    ///
+    /// If an asset can't be parsed, the assets are left unchanged.
+    pub fn apply(
+        self,
+        assets: &mut Assets,
+        asset_types: &AssetTypes,
+    ) -> Result<(), AssetParseError> {
+        let mut changed = self.added;
+        changed.list.extend(self.modified.list);
+        let changed = changed.parse(asset_types)?;
+
+        for asset_id in self.removed {
+            assets.remove(asset_id);
+        }
+        assets
+            .unrecognized
+            .retain(|asset| !changed.assets.contains_key(&asset.id));
+        assets.assets.extend(changed.assets);
+        assets.unrecognized.extend(changed.unrecognized);
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Shader {
     pub id: AssetId,
@@ -395,6 +699,10 @@ impl Asset for Shader {
     fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
         std::iter::once(&*self.naga_ir)
     }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.naga_ir)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -404,6 +712,188 @@ pub struct CompiledShader {
     pub module_info: naga::valid::ModuleInfo,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Catalog {
+    pub id: AssetId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    pub build_time: DateTime<Utc>,
+
+    /// Language tag of the catalog's locale, e.g. `en` or `de-AT`.
+    pub locale: String,
+
+    pub messages: String,
+}
+
+impl HasAssetId for Catalog {
+    fn asset_id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl Asset for Catalog {
+    const TYPE_NAME: &'static str = "catalog";
+    const TYPE_ID: Uuid = uuid!("7d0e6c52-3b91-4f0a-a6f4-d2c85e1b9a37");
+
+    fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        std::iter::once(&*self.messages)
+    }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.messages)
+    }
+}
+
+/// Translated messages of a [`Catalog`], keyed by message ID.
+///
+/// Messages can contain placeholders of the form `{ $name }`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CatalogMessages {
+    pub messages: HashMap<String, String>,
+}
+
+/// Sound effect or music track.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sound {
+    pub id: AssetId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    pub build_time: DateTime<Utc>,
+
+    /// Encoded audio file, in a format that browsers can decode, e.g. Ogg
+    /// Vorbis or MP3.
+    pub audio: String,
+
+    /// Name of the music playlist the sound belongs to, if it's a music track.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist: Option<String>,
+}
+
+impl HasAssetId for Sound {
+    fn asset_id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl Asset for Sound {
+    const TYPE_NAME: &'static str = "sound";
+    const TYPE_ID: Uuid = uuid!("b5e2d9a4-0c7f-4e18-9a36-7f1d84c2e05b");
+
+    fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        std::iter::once(&*self.audio)
+    }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.audio)
+    }
+}
+
+/// Color ramp for mapping scalar values to colors.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ColorRamp {
+    pub id: AssetId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    pub build_time: DateTime<Utc>,
+
+    /// sRGB colors, evenly spaced from 0 to 1.
+    pub lut: Vec<[u8; 3]>,
+}
+
+impl HasAssetId for ColorRamp {
+    fn asset_id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl Asset for ColorRamp {
+    const TYPE_NAME: &'static str = "color_ramp";
+    const TYPE_ID: Uuid = uuid!("3c1f8a52-96d4-4e0b-b7a3-58e2d90c4f16");
+
+    fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        std::iter::empty()
+    }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::empty()
+    }
+}
+
+/// UI icons, rasterized at several sizes into one sprite sheet.
+///
+/// The sheet has a row for each size, from the smallest to the largest. Each
+/// row has the icons in the order of [`icons`](Self::icons), as squares with
+/// the row's size.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IconSet {
+    pub id: AssetId,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    pub build_time: DateTime<Utc>,
+
+    pub image: String,
+
+    pub size: TextureSize,
+
+    /// Sizes of the icons in pixels, in ascending order.
+    pub sizes: Vec<u32>,
+
+    /// Names of the icons, e.g. `arrow-up`.
+    pub icons: Vec<String>,
+}
+
+impl IconSet {
+    /// Smallest size that is at least `size`, or the largest size if there
+    /// is none.
+    pub fn best_size(&self, size: u32) -> Option<u32> {
+        self.sizes
+            .iter()
+            .copied()
+            .find(|available| *available >= size)
+            .or_else(|| self.sizes.last().copied())
+    }
+
+    /// Position of an icon with the given size in the sprite sheet.
+    pub fn sprite(&self, name: &str, size: u32) -> Option<TextureCrop> {
+        let column = self.icons.iter().position(|icon| icon == name)?;
+        let row = self.sizes.iter().position(|available| *available == size)?;
+        Some(TextureCrop {
+            x: column as u32 * size,
+            y: self.sizes[..row].iter().sum(),
+            w: size,
+            h: size,
+        })
+    }
+}
+
+impl HasAssetId for IconSet {
+    fn asset_id(&self) -> AssetId {
+        self.id
+    }
+}
+
+impl Asset for IconSet {
+    const TYPE_NAME: &'static str = "icon_set";
+    const TYPE_ID: Uuid = uuid!("6e1b9d47-2f5c-4a83-b0d6-94c7e3a58f12");
+
+    fn files<'a>(&'a self) -> impl Iterator<Item = &'a str> {
+        std::iter::once(&*self.image)
+    }
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String> {
+        std::iter::once(&mut self.image)
+    }
+}
+
 pub trait HasAssetId {
     fn asset_id(&self) -> AssetId;
 }
@@ -413,6 +903,8 @@ pub trait Asset: HasAssetId + Serialize + DeserializeOwned + Send + Sync + 'stat
     const TYPE_ID: Uuid;
 
     fn files<'a>(&'a self) -> impl Iterator<Item = &'a str>;
+
+    fn files_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut String>;
 }
 
 #[derive(Default)]
@@ -427,6 +919,12 @@ impl Assets {
         asset.downcast_ref()
     }
 
+    pub fn iter<A: Asset>(&self) -> impl Iterator<Item = &A> {
+        self.assets
+            .values()
+            .filter_map(|(asset, _)| asset.downcast_ref())
+    }
+
     pub fn insert<A: Asset>(&mut self, asset: A) {
         self.assets.insert(
             asset.asset_id(),
@@ -464,6 +962,14 @@ impl Assets {
         files
     }
 
+    /// Files of a single asset, or `None` if there is no such asset.
+    pub fn files(&self, asset_id: AssetId) -> Option<HashSet<&str>> {
+        let (asset, asset_type) = self.assets.get(&asset_id)?;
+        let mut files = HashSet::new();
+        asset_type.collect_files(&**asset, &mut files);
+        Some(files)
+    }
+
     pub fn all_asset_ids(&self) -> impl Iterator<Item = AssetId> + '_ {
         self.assets.keys().copied()
     }
@@ -471,6 +977,28 @@ impl Assets {
     pub fn remove(&mut self, asset_id: AssetId) {
         self.assets.remove(&asset_id);
     }
+
+    /// Changes the paths of all files, e.g. to make them relative to a content
+    /// pack's URL.
+    pub fn map_files(&mut self, mut f: impl FnMut(&mut String)) {
+        for (asset, asset_type) in self.assets.values_mut() {
+            asset_type.map_files(&mut **asset, &mut f);
+        }
+    }
+
+    /// Adds the assets of a content pack. Assets that are already present are
+    /// kept.
+    pub fn merge(&mut self, other: Assets) {
+        for (asset_id, asset) in other.assets {
+            if self.assets.contains_key(&asset_id) {
+                tracing::warn!(%asset_id, "duplicate asset");
+            }
+            else {
+                self.assets.insert(asset_id, asset);
+            }
+        }
+        self.unrecognized.extend(other.unrecognized);
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -526,6 +1054,11 @@ impl AssetTypes {
         self.register::<Material>();
         self.register::<Mesh>();
         self.register::<Shader>();
+        self.register::<Catalog>();
+        self.register::<ColorRamp>();
+        self.register::<Sound>();
+        self.register::<IconSet>();
+        self.register::<Model>();
         self
     }
 }
@@ -541,6 +1074,62 @@ impl AssetsBlob {
         self.list.is_empty()
     }
 
+    pub fn asset_ids(&self) -> impl Iterator<Item = AssetId> + '_ {
+        self.list.iter().map(|asset| asset.id)
+    }
+
+    /// Returns the assets for whose IDs `f` returns `true`.
+    pub fn filter(&self, mut f: impl FnMut(AssetId) -> bool) -> Self {
+        Self {
+            list: self
+                .list
+                .iter()
+                .filter(|asset| f(asset.id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Moves the assets into a content pack's [namespace](AssetId::namespaced).
+    ///
+    /// References between the assets are changed too, while references to
+    /// assets that are not in the blob are kept.
+    pub fn namespaced(mut self, namespace: &str) -> Self {
+        let asset_ids = self
+            .list
+            .iter()
+            .map(|asset| (asset.id.to_string(), asset.id.namespaced(namespace)))
+            .collect::<HashMap<_, _>>();
+
+        fn rename(value: &mut serde_json::Value, asset_ids: &HashMap<String, AssetId>) {
+            match value {
+                serde_json::Value::String(string) => {
+                    if let Some(asset_id) = asset_ids.get(string.as_str()) {
+                        *string = asset_id.to_string();
+                    }
+                }
+                serde_json::Value::Array(values) => {
+                    for value in values {
+                        rename(value, asset_ids);
+                    }
+                }
+                serde_json::Value::Object(fields) => {
+                    for value in fields.values_mut() {
+                        rename(value, asset_ids);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for asset in &mut self.list {
+            asset.id = asset_ids[&asset.id.to_string()];
+            rename(&mut asset.data, &asset_ids);
+        }
+
+        self
+    }
+
     pub fn parse(self, asset_types: &AssetTypes) -> Result<Assets, AssetParseError> {
         let mut assets = Assets::default();
 
@@ -618,6 +1207,7 @@ trait DynAssetTypeTrait: Send + Sync + 'static {
         data: &serde_json::Value,
     ) -> Result<Box<dyn Any + Send + Sync + 'static>, serde_json::Error>;
     fn collect_files<'a>(&self, asset: &'a dyn Any, files: &mut HashSet<&'a str>);
+    fn map_files(&self, asset: &mut dyn Any, f: &mut dyn FnMut(&mut String));
 }
 
 struct DynAssetTypeImpl<A> {
@@ -649,4 +1239,208 @@ impl<A: Asset> DynAssetTypeTrait for DynAssetTypeImpl<A> {
     fn collect_files<'a>(&self, asset: &'a dyn Any, files: &mut HashSet<&'a str>) {
         files.extend(A::files(asset.downcast_ref::<A>().unwrap()));
     }
+
+    fn map_files(&self, asset: &mut dyn Any, f: &mut dyn FnMut(&mut String)) {
+        A::files_mut(asset.downcast_mut::<A>().unwrap()).for_each(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::{
+        AssetId,
+        AssetTypes,
+        Assets,
+        AssetsDiff,
+        IconSet,
+        Manifest,
+        ManifestError,
+        Material,
+        Texture,
+        TextureFormat,
+        TextureSize,
+        MANIFEST_VERSION,
+    };
+
+    #[test]
+    fn it_migrates_unversioned_manifests() {
+        let json = br#"{"build_time": "2024-10-01T00:00:00Z", "assets": []}"#;
+        let manifest = Manifest::from_json(json).unwrap();
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        assert!(manifest.assets.is_empty());
+    }
+
+    #[test]
+    fn it_reads_compatible_newer_manifests() {
+        let json = br#"{"version": 1000, "compatible_version": 0, "build_time": "2024-10-01T00:00:00Z", "assets": [], "added_field": 42}"#;
+        let manifest = Manifest::from_json(json).unwrap();
+        assert_eq!(manifest.version, 1000);
+    }
+
+    #[test]
+    fn it_rejects_incompatible_manifests() {
+        let json = br#"{"version": 1000, "compatible_version": 1000, "build_time": "2024-10-01T00:00:00Z", "assets": []}"#;
+        assert!(matches!(
+            Manifest::from_json(json),
+            Err(ManifestError::Unsupported {
+                compatible_version: 1000,
+                ..
+            })
+        ));
+    }
+
+    fn icon_set() -> IconSet {
+        IconSet {
+            id: AssetId::from_uuid(Uuid::nil()),
+            label: None,
+            build_time: Utc::now(),
+            image: "icons.png".to_owned(),
+            size: TextureSize { w: 96, h: 72 },
+            sizes: vec![16, 24, 32],
+            icons: vec!["x".to_owned(), "trash".to_owned(), "pencil".to_owned()],
+        }
+    }
+
+    #[test]
+    fn it_picks_the_best_icon_size() {
+        let icon_set = icon_set();
+        assert_eq!(icon_set.best_size(12), Some(16));
+        assert_eq!(icon_set.best_size(24), Some(24));
+        assert_eq!(icon_set.best_size(25), Some(32));
+        assert_eq!(icon_set.best_size(64), Some(32));
+    }
+
+    #[test]
+    fn it_finds_icon_sprites() {
+        let icon_set = icon_set();
+        let sprite = icon_set.sprite("pencil", 32).unwrap();
+        assert_eq!((sprite.x, sprite.y, sprite.w, sprite.h), (64, 40, 32, 32));
+        assert!(icon_set.sprite("pencil", 20).is_none());
+        assert!(icon_set.sprite("missing", 16).is_none());
+    }
+
+    fn texture(id: AssetId) -> Texture {
+        Texture {
+            id,
+            label: None,
+            build_time: Utc::now(),
+            image: "texture.png".to_owned(),
+            size: TextureSize { w: 1, h: 1 },
+            format: TextureFormat::default(),
+            crop: None,
+            u_edge_mode: None,
+            v_edge_mode: None,
+            mag_filter: None,
+            min_filter: None,
+            mipmap_filter: None,
+            anisotropy: None,
+            mipmaps: false,
+        }
+    }
+
+    #[test]
+    fn it_namespaces_content_packs() {
+        let builtin_texture = AssetId::generate();
+        let pack_texture = AssetId::generate();
+        let pack_material = AssetId::generate();
+
+        let mut pack = Assets::default();
+        pack.insert(texture(pack_texture));
+        pack.insert(Material {
+            id: pack_material,
+            label: None,
+            build_time: Utc::now(),
+            normal_texture: Some(builtin_texture),
+            ambient_texture: None,
+            ambient_color: None,
+            diffuse_texture: Some(pack_texture),
+            diffuse_color: None,
+            specular_texture: None,
+            specular_color: None,
+            shininess_texture: None,
+            shininess: None,
+            dissolve_texture: None,
+            dissolve: None,
+            emissive_texture: None,
+            emissive_color: None,
+            bloom: None,
+            albedo_texture: None,
+            metalness_texture: None,
+            roughness_texture: None,
+        });
+
+        let mut asset_types = AssetTypes::default();
+        asset_types.with_builtin();
+        let mut pack = pack.blob().namespaced("pack").parse(&asset_types).unwrap();
+        pack.map_files(|file| *file = format!("packs/pack/{file}"));
+
+        let mut assets = Assets::default();
+        assets.insert(texture(builtin_texture));
+        assets.insert(texture(pack_texture));
+        assets.merge(pack);
+
+        assert_eq!(assets.all_asset_ids().count(), 4);
+        let material = assets
+            .get::<Material>(pack_material.namespaced("pack"))
+            .unwrap();
+        assert_eq!(material.normal_texture, Some(builtin_texture));
+        assert_eq!(
+            material.diffuse_texture,
+            Some(pack_texture.namespaced("pack"))
+        );
+        assert_eq!(
+            assets
+                .get::<Texture>(pack_texture.namespaced("pack"))
+                .unwrap()
+                .image,
+            "packs/pack/texture.png"
+        );
+        assert_ne!(
+            pack_texture.namespaced("pack"),
+            pack_texture.namespaced("other")
+        );
+    }
+
+    #[test]
+    fn it_applies_diffs() {
+        let kept = AssetId::generate();
+        let modified = AssetId::generate();
+        let removed = AssetId::generate();
+        let added = AssetId::generate();
+
+        let mut assets = Assets::default();
+        for asset_id in [kept, modified, removed] {
+            assets.insert(texture(asset_id));
+        }
+
+        let mut rebuilt = Assets::default();
+        rebuilt.insert(texture(added));
+        rebuilt.insert(Texture {
+            image: "modified.png".to_owned(),
+            ..texture(modified)
+        });
+        let blob = rebuilt.blob();
+        let diff = AssetsDiff {
+            added: blob.filter(|asset_id| asset_id == added),
+            modified: blob.filter(|asset_id| asset_id == modified),
+            removed: vec![removed],
+        };
+        assert_eq!(diff.asset_ids().count(), 3);
+
+        let mut asset_types = AssetTypes::default();
+        asset_types.with_builtin();
+        diff.apply(&mut assets, &asset_types).unwrap();
+
+        assert_eq!(assets.all_asset_ids().count(), 3);
+        assert!(assets.get::<Texture>(kept).is_some());
+        assert!(assets.get::<Texture>(added).is_some());
+        assert!(assets.get::<Texture>(removed).is_none());
+        assert_eq!(
+            assets.get::<Texture>(modified).unwrap().image,
+            "modified.png"
+        );
+    }
 }
@@ -0,0 +1,627 @@
+//! Collision detection between simple shapes.
+//!
+//! Bodies have a [`Shape`], a pose and [`CollisionLayers`]. Collisions are
+//! found in two phases: A [`BroadphaseGrid`] finds the pairs of bodies whose
+//! bounding boxes overlap, and [`contact`] then tests the actual shapes. This
+//! is shared with the server, so that simulations on both sides agree on what
+//! collides.
+
+use std::collections::{
+    BTreeSet,
+    HashMap,
+};
+
+use nalgebra::{
+    Point3,
+    Similarity3,
+    Unit,
+    Vector3,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Maximum number of grid cells a body is inserted into. Bodies that would
+/// cover more cells, e.g. planets in a grid with small cells, are tested
+/// against all other bodies instead.
+const MAX_CELLS_PER_BODY: usize = 512;
+
+/// Maximum number of iterations of the GJK algorithm. It converges much
+/// earlier for the shapes we have, except for degenerate cases like touching
+/// shapes, which are then considered to collide.
+const MAX_GJK_ITERATIONS: usize = 64;
+
+/// A collision shape in the local space of a body.
+///
+/// Poses are [`Similarity3`]s, so shapes are scaled uniformly with their
+/// bodies.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Shape {
+    Sphere {
+        radius: f32,
+    },
+
+    /// A box that stays aligned to the world's axes, i.e. the rotation of the
+    /// body is ignored.
+    Aabb {
+        half_extents: Vector3<f32>,
+    },
+
+    /// The convex hull of the points.
+    Convex {
+        points: Vec<Point3<f32>>,
+    },
+}
+
+impl Shape {
+    /// Point of the shape that is furthest in `direction`, in world space.
+    fn support(&self, pose: &Similarity3<f32>, direction: &Vector3<f32>) -> Point3<f32> {
+        let center = Point3::from(pose.isometry.translation.vector);
+        let scale = pose.scaling();
+        match self {
+            Self::Sphere { radius } => {
+                center + direction.try_normalize(0.0).unwrap_or_default() * *radius * scale
+            }
+            Self::Aabb { half_extents } => {
+                center + direction.map(|x| x.signum()).component_mul(half_extents) * scale
+            }
+            Self::Convex { points } => {
+                let local = pose.isometry.rotation.inverse_transform_vector(direction);
+                points
+                    .iter()
+                    .max_by(|a, b| a.coords.dot(&local).total_cmp(&b.coords.dot(&local)))
+                    .map_or(center, |point| pose.transform_point(point))
+            }
+        }
+    }
+
+    /// Axis-aligned bounding box of the shape in world space.
+    pub fn bounds(&self, pose: &Similarity3<f32>) -> Aabb {
+        let center = Point3::from(pose.isometry.translation.vector);
+        let scale = pose.scaling();
+        match self {
+            Self::Sphere { radius } => Aabb::from_center(center, Vector3::repeat(*radius * scale)),
+            Self::Aabb { half_extents } => Aabb::from_center(center, half_extents * scale),
+            Self::Convex { points } => {
+                let mut points = points.iter().map(|point| pose.transform_point(point));
+                let Some(first) = points.next()
+                else {
+                    return Aabb::from_center(center, Vector3::zeros());
+                };
+                points.fold(Aabb::from_center(first, Vector3::zeros()), |aabb, point| {
+                    Aabb {
+                        min: aabb.min.inf(&point),
+                        max: aabb.max.sup(&point),
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Axis-aligned bounding box in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn from_center(center: Point3<f32>, half_extents: Vector3<f32>) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        0.5 * (self.max - self.min)
+    }
+
+    /// Whether the boxes overlap or touch.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+}
+
+/// Which bodies collide with which.
+///
+/// Two bodies collide if each is a member of a layer the other one's filter
+/// contains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CollisionLayers {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl CollisionLayers {
+    pub const SHIPS: u32 = 1 << 0;
+    pub const PROJECTILES: u32 = 1 << 1;
+    pub const PLANETS: u32 = 1 << 2;
+    pub const DOCKS: u32 = 1 << 3;
+    pub const CAMERA: u32 = 1 << 4;
+    pub const ALL: u32 = u32::MAX;
+
+    pub const fn new(memberships: u32, filter: u32) -> Self {
+        Self {
+            memberships,
+            filter,
+        }
+    }
+
+    pub fn interacts_with(&self, other: &CollisionLayers) -> bool {
+        self.memberships & other.filter != 0 && other.memberships & self.filter != 0
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        Self::new(Self::ALL, Self::ALL)
+    }
+}
+
+/// How two shapes overlap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Direction from the first shape to the second one.
+    pub normal: Unit<Vector3<f32>>,
+
+    /// Distance the second shape has to move along the normal for the shapes
+    /// to only touch.
+    pub depth: f32,
+}
+
+/// Tests whether two shapes overlap.
+///
+/// The contacts of spheres and boxes are exact. For convex shapes, the normal
+/// points from one center to the other, and the depth is how much the shapes
+/// overlap along it, which separates them, but not necessarily by the shortest
+/// way.
+pub fn contact(
+    a: &Shape,
+    pose_a: &Similarity3<f32>,
+    b: &Shape,
+    pose_b: &Similarity3<f32>,
+) -> Option<Contact> {
+    let center_a = Point3::from(pose_a.isometry.translation.vector);
+    let center_b = Point3::from(pose_b.isometry.translation.vector);
+
+    match (a, b) {
+        (Shape::Sphere { radius: radius_a }, Shape::Sphere { radius: radius_b }) => {
+            let radius = radius_a * pose_a.scaling() + radius_b * pose_b.scaling();
+            let distance = (center_b - center_a).norm();
+            (distance < radius).then(|| {
+                Contact {
+                    normal: direction_or_x(center_b - center_a),
+                    depth: radius - distance,
+                }
+            })
+        }
+        (Shape::Sphere { radius }, Shape::Aabb { .. }) => {
+            sphere_aabb(center_a, radius * pose_a.scaling(), &b.bounds(pose_b))
+        }
+        (Shape::Aabb { .. }, Shape::Sphere { radius }) => {
+            sphere_aabb(center_b, radius * pose_b.scaling(), &a.bounds(pose_a)).map(flip)
+        }
+        (Shape::Aabb { .. }, Shape::Aabb { .. }) => aabb_aabb(&a.bounds(pose_a), &b.bounds(pose_b)),
+        _ => {
+            let support = |direction: &Vector3<f32>| {
+                a.support(pose_a, direction) - b.support(pose_b, &-direction)
+            };
+            if !gjk(support) {
+                return None;
+            }
+            let normal = direction_or_x(center_b - center_a);
+            let depth = (a.support(pose_a, &normal) - b.support(pose_b, &-normal)).dot(&normal);
+            Some(Contact {
+                normal,
+                depth: depth.max(0.0),
+            })
+        }
+    }
+}
+
+fn direction_or_x(vector: Vector3<f32>) -> Unit<Vector3<f32>> {
+    Unit::try_new(vector, f32::EPSILON).unwrap_or_else(Vector3::x_axis)
+}
+
+fn flip(contact: Contact) -> Contact {
+    Contact {
+        normal: -contact.normal,
+        depth: contact.depth,
+    }
+}
+
+/// Contact from the sphere to the box.
+fn sphere_aabb(center: Point3<f32>, radius: f32, aabb: &Aabb) -> Option<Contact> {
+    let closest = center.sup(&aabb.min).inf(&aabb.max);
+    let offset = closest - center;
+    let distance = offset.norm();
+
+    if distance > f32::EPSILON {
+        return (distance < radius).then(|| {
+            Contact {
+                normal: Unit::new_unchecked(offset / distance),
+                depth: radius - distance,
+            }
+        });
+    }
+
+    // the center is inside the box, so it is pushed out through the nearest face.
+    let to_center = center - aabb.center();
+    let half_extents = aabb.half_extents();
+    let axis = (0..3)
+        .min_by(|&i, &j| {
+            (half_extents[i] - to_center[i].abs())
+                .total_cmp(&(half_extents[j] - to_center[j].abs()))
+        })
+        .unwrap();
+    let mut normal = Vector3::zeros();
+    normal[axis] = -to_center[axis].signum();
+    Some(Contact {
+        normal: Unit::new_unchecked(normal),
+        depth: half_extents[axis] - to_center[axis].abs() + radius,
+    })
+}
+
+fn aabb_aabb(a: &Aabb, b: &Aabb) -> Option<Contact> {
+    let offset = b.center() - a.center();
+    let overlap = a.half_extents() + b.half_extents() - offset.abs();
+    if overlap.iter().any(|overlap| *overlap <= 0.0) {
+        return None;
+    }
+
+    let axis = overlap.imin();
+    let mut normal = Vector3::zeros();
+    normal[axis] = if offset[axis] < 0.0 { -1.0 } else { 1.0 };
+    Some(Contact {
+        normal: Unit::new_unchecked(normal),
+        depth: overlap[axis],
+    })
+}
+
+/// Whether the Minkowski difference with the given support function contains
+/// the origin, i.e. whether the shapes overlap.
+fn gjk(support: impl Fn(&Vector3<f32>) -> Vector3<f32>) -> bool {
+    // the newest point comes first.
+    let mut simplex = vec![support(&Vector3::x())];
+    let mut direction = -simplex[0];
+
+    for _ in 0..MAX_GJK_ITERATIONS {
+        if direction.norm_squared() <= f32::EPSILON * f32::EPSILON {
+            // the origin is on the simplex.
+            return true;
+        }
+
+        let point = support(&direction);
+        if point.dot(&direction) < 0.0 {
+            return false;
+        }
+        simplex.insert(0, point);
+
+        if next_simplex(&mut simplex, &mut direction) {
+            return true;
+        }
+    }
+
+    true
+}
+
+/// Reduces the simplex to the feature closest to the origin, and returns
+/// whether it contains the origin. Otherwise `direction` is set to point from
+/// that feature towards the origin.
+fn next_simplex(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool {
+    match simplex.len() {
+        2 => line(simplex, direction),
+        3 => triangle(simplex, direction),
+        _ => tetrahedron(simplex, direction),
+    }
+}
+
+fn line(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool {
+    let (a, b) = (simplex[0], simplex[1]);
+    let ab = b - a;
+    let ao = -a;
+
+    if ab.dot(&ao) > 0.0 {
+        *direction = ab.cross(&ao).cross(&ab);
+    }
+    else {
+        *simplex = vec![a];
+        *direction = ao;
+    }
+    false
+}
+
+fn triangle(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool {
+    let (a, b, c) = (simplex[0], simplex[1], simplex[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ao = -a;
+    let abc = ab.cross(&ac);
+
+    if abc.cross(&ac).dot(&ao) > 0.0 {
+        if ac.dot(&ao) > 0.0 {
+            *simplex = vec![a, c];
+            *direction = ac.cross(&ao).cross(&ac);
+            false
+        }
+        else {
+            *simplex = vec![a, b];
+            line(simplex, direction)
+        }
+    }
+    else if ab.cross(&abc).dot(&ao) > 0.0 {
+        *simplex = vec![a, b];
+        line(simplex, direction)
+    }
+    else if abc.dot(&ao) > 0.0 {
+        *direction = abc;
+        false
+    }
+    else {
+        *simplex = vec![a, c, b];
+        *direction = -abc;
+        false
+    }
+}
+
+fn tetrahedron(simplex: &mut Vec<Vector3<f32>>, direction: &mut Vector3<f32>) -> bool {
+    let (a, b, c, d) = (simplex[0], simplex[1], simplex[2], simplex[3]);
+    let ab = b - a;
+    let ac = c - a;
+    let ad = d - a;
+    let ao = -a;
+
+    if ab.cross(&ac).dot(&ao) > 0.0 {
+        *simplex = vec![a, b, c];
+        triangle(simplex, direction)
+    }
+    else if ac.cross(&ad).dot(&ao) > 0.0 {
+        *simplex = vec![a, c, d];
+        triangle(simplex, direction)
+    }
+    else if ad.cross(&ab).dot(&ao) > 0.0 {
+        *simplex = vec![a, d, b];
+        triangle(simplex, direction)
+    }
+    else {
+        true
+    }
+}
+
+/// A body that is tested for collisions.
+#[derive(Clone, Copy, Debug)]
+pub struct Body<'a, K> {
+    pub key: K,
+    pub shape: &'a Shape,
+    pub pose: Similarity3<f32>,
+    pub layers: CollisionLayers,
+}
+
+/// Two bodies that collide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collision<K> {
+    pub a: K,
+    pub b: K,
+
+    /// Contact from `a` to `b`.
+    pub contact: Contact,
+}
+
+/// Uniform grid over space, to find the bodies that might collide without
+/// testing every pair.
+#[derive(Debug)]
+pub struct BroadphaseGrid {
+    cell_size: f32,
+    cells: HashMap<[i32; 3], Vec<usize>>,
+    bounds: Vec<Aabb>,
+
+    /// Bodies that cover too many cells.
+    large: Vec<usize>,
+}
+
+impl BroadphaseGrid {
+    /// `cell_size` should be about the size of the common bodies.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: vec![],
+            large: vec![],
+        }
+    }
+
+    /// Inserts a bounding box, and returns its index.
+    pub fn insert(&mut self, bounds: Aabb) -> usize {
+        let index = self.bounds.len();
+        self.bounds.push(bounds);
+
+        let min = bounds.min.map(|x| (x / self.cell_size).floor() as i32);
+        let max = bounds.max.map(|x| (x / self.cell_size).floor() as i32);
+        let num_cells = (0..3)
+            .map(|i| (max[i] as i64 - min[i] as i64 + 1) as usize)
+            .try_fold(1usize, |num_cells, n| num_cells.checked_mul(n))
+            .unwrap_or(usize::MAX);
+
+        if num_cells > MAX_CELLS_PER_BODY {
+            self.large.push(index);
+        }
+        else {
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        self.cells.entry([x, y, z]).or_default().push(index);
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Pairs of indices whose bounding boxes overlap, in ascending order.
+    pub fn pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = BTreeSet::new();
+
+        let mut add = |i: usize, j: usize| {
+            if i != j && self.bounds[i].intersects(&self.bounds[j]) {
+                pairs.insert((i.min(j), i.max(j)));
+            }
+        };
+
+        for indices in self.cells.values() {
+            for (n, &i) in indices.iter().enumerate() {
+                for &j in &indices[n + 1..] {
+                    add(i, j);
+                }
+            }
+        }
+        for &i in &self.large {
+            for j in 0..self.bounds.len() {
+                add(i, j);
+            }
+        }
+
+        pairs.into_iter().collect()
+    }
+}
+
+/// Finds all collisions between the bodies, in the order of the bodies.
+pub fn detect_collisions<K: Copy>(bodies: &[Body<K>], cell_size: f32) -> Vec<Collision<K>> {
+    let mut grid = BroadphaseGrid::new(cell_size);
+    for body in bodies {
+        grid.insert(body.shape.bounds(&body.pose));
+    }
+
+    grid.pairs()
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let (a, b) = (&bodies[i], &bodies[j]);
+            if !a.layers.interacts_with(&b.layers) {
+                return None;
+            }
+            let contact = contact(a.shape, &a.pose, b.shape, &b.pose)?;
+            Some(Collision {
+                a: a.key,
+                b: b.key,
+                contact,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{
+        Point3,
+        Similarity3,
+        Vector3,
+    };
+
+    use super::{
+        contact,
+        detect_collisions,
+        Body,
+        CollisionLayers,
+        Shape,
+    };
+
+    fn at(x: f32, y: f32, z: f32) -> Similarity3<f32> {
+        Similarity3::new(Vector3::new(x, y, z), Vector3::zeros(), 1.0)
+    }
+
+    fn cube() -> Shape {
+        let points = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { -0.5 } else { 0.5 },
+                    if i & 2 == 0 { -0.5 } else { 0.5 },
+                    if i & 4 == 0 { -0.5 } else { 0.5 },
+                )
+            })
+            .collect();
+        Shape::Convex { points }
+    }
+
+    #[test]
+    fn spheres_and_boxes_have_exact_contacts() {
+        let sphere = Shape::Sphere { radius: 1.0 };
+        let aabb = Shape::Aabb {
+            half_extents: Vector3::repeat(1.0),
+        };
+
+        let hit = contact(&sphere, &at(0.0, 0.0, 0.0), &sphere, &at(1.5, 0.0, 0.0)).unwrap();
+        assert_eq!(hit.normal.into_inner(), Vector3::x());
+        assert!((hit.depth - 0.5).abs() < 1e-6);
+        assert!(contact(&sphere, &at(0.0, 0.0, 0.0), &sphere, &at(2.5, 0.0, 0.0)).is_none());
+
+        let hit = contact(&aabb, &at(0.0, 0.0, 0.0), &sphere, &at(0.0, 1.75, 0.0)).unwrap();
+        assert_eq!(hit.normal.into_inner(), Vector3::y());
+        assert!((hit.depth - 0.25).abs() < 1e-6);
+
+        // scaled bodies scale their shapes.
+        let scaled = Similarity3::new(Vector3::new(2.5, 0.0, 0.0), Vector3::zeros(), 2.0);
+        assert!(contact(&aabb, &at(0.0, 0.0, 0.0), &aabb, &scaled).is_some());
+    }
+
+    #[test]
+    fn convex_shapes_collide_where_their_hulls_overlap() {
+        let sphere = Shape::Sphere { radius: 0.5 };
+        for (x, collides) in [(0.5, true), (0.95, true), (1.05, false), (3.0, false)] {
+            assert_eq!(
+                contact(&cube(), &at(0.0, 0.0, 0.0), &cube(), &at(x, 0.2, -0.1)).is_some(),
+                collides,
+                "cubes at {x}"
+            );
+        }
+
+        // the sphere is beyond the cube's edge, but inside its bounding sphere.
+        assert!(contact(&cube(), &at(0.0, 0.0, 0.0), &sphere, &at(0.9, 0.9, 0.0)).is_none());
+        assert!(contact(&cube(), &at(0.0, 0.0, 0.0), &sphere, &at(0.8, 0.0, 0.0)).is_some());
+    }
+
+    #[test]
+    fn only_bodies_on_interacting_layers_collide() {
+        let sphere = Shape::Sphere { radius: 1.0 };
+        let planet = CollisionLayers::new(CollisionLayers::PLANETS, CollisionLayers::ALL);
+        let bodies = [
+            Body {
+                key: "camera",
+                shape: &sphere,
+                pose: at(0.0, 0.0, 0.0),
+                layers: CollisionLayers::new(CollisionLayers::CAMERA, CollisionLayers::PLANETS),
+            },
+            Body {
+                key: "planet",
+                shape: &sphere,
+                pose: at(1.0, 0.0, 0.0),
+                layers: planet,
+            },
+            Body {
+                key: "ship",
+                shape: &sphere,
+                pose: at(0.0, 1.0, 0.0),
+                layers: CollisionLayers::new(CollisionLayers::SHIPS, CollisionLayers::ALL),
+            },
+            Body {
+                key: "far away",
+                shape: &sphere,
+                pose: at(100.0, 0.0, 0.0),
+                layers: planet,
+            },
+        ];
+
+        let collisions = detect_collisions(&bodies, 2.0)
+            .into_iter()
+            .map(|collision| (collision.a, collision.b))
+            .collect::<Vec<_>>();
+        assert_eq!(collisions, [("camera", "planet"), ("planet", "ship")]);
+    }
+}
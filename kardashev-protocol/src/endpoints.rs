@@ -0,0 +1,430 @@
+//! Typed definitions of the HTTP API's endpoints.
+//!
+//! Every endpoint is a type implementing [`Endpoint`], which ties its method
+//! and path to the types of its path parameters, query, request and response.
+//! The server registers its handlers by endpoint, and the client builds its
+//! requests from them, so they can't disagree about URLs or payloads. The
+//! [schema](crate::schema) of the API is generated from them too.
+//!
+//! Paths are relative to the API's base URL, and have their parameters in
+//! braces, e.g. `/fleet/{id}`.
+
+use http::Method;
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::{
+    admin::{
+        AuditStarsRequest,
+        AuditStarsResponse,
+        CreateBackupResponse,
+        CreateFleetsRequest,
+        CreateFleetsResponse,
+        CreateJournalEntriesRequest,
+        CreateJournalEntriesResponse,
+        CreatePlanetsRequest,
+        CreatePlanetsResponse,
+        CreateStarsRequest,
+        CreateStarsResponse,
+        CreateWebhookRequest,
+        CreateWorldRequest,
+        CreateWorldResponse,
+        GetAssetStatsResponse,
+        GetBackupsResponse,
+        GetSimulationStateResponse,
+        GetWebhooksResponse,
+        RecomputeImpostorsResponse,
+        RecomputeRegionsResponse,
+        RenameStarRequest,
+        RenameStarResponse,
+        SimulateQuery,
+        SimulateResponse,
+        Webhook,
+        WebhookId,
+    },
+    model::{
+        diplomacy::{
+            Relation,
+            TreatyProposal,
+        },
+        empire::EmpireSummary,
+        faction::FactionId,
+        fleet::{
+            Fleet,
+            FleetId,
+        },
+        star::StarId,
+        trade::{
+            TradeRoute,
+            TradeRouteId,
+        },
+    },
+    replay::GetSnapshotsResponse,
+    ClientCapabilityReport,
+    ClientErrorReport,
+    CreateTradeRouteRequest,
+    GetBookmarksResponse,
+    GetDiplomacyResponse,
+    GetFleetsResponse,
+    GetJournalQuery,
+    GetJournalResponse,
+    GetLeaderboardResponse,
+    GetPlanetsResponse,
+    GetRegionsResponse,
+    GetSettingsResponse,
+    GetStarChunksResponse,
+    GetStarsResponse,
+    GetTradeRoutesResponse,
+    GetWorldsResponse,
+    ProposeTreatyRequest,
+    PutBookmarksRequest,
+    PutSettingsRequest,
+    PutSettingsResponse,
+    SearchStarsQuery,
+    SearchStarsResponse,
+    ServerStatus,
+    SetOrdersRequest,
+    SetOrdersResponse,
+};
+
+/// Path of the websocket on which the server sends
+/// [`SessionEvent`](crate::SessionEvent)s. It's not an [`Endpoint`], since it
+/// doesn't respond with JSON.
+pub const SESSION_PATH: &str = "/session";
+
+/// Path of the websocket on which the server streams a replay, see
+/// [`replay`](crate::replay). The query is a
+/// [`ReplayQuery`](crate::replay::ReplayQuery).
+pub const REPLAY_PATH: &str = "/replay";
+
+/// Path of the websocket on which the server sends
+/// [`StarEvent`](crate::model::star::StarEvent)s when stars are created,
+/// changed or deleted.
+pub const STAR_EVENTS_PATH: &str = "/star/events";
+
+/// An endpoint of the HTTP API.
+///
+/// Endpoints without a query, request or response use `()` for its type.
+pub trait Endpoint {
+    const METHOD: Method;
+
+    /// Path with the parameters in braces.
+    const PATH: &'static str;
+
+    /// One-line description of the endpoint.
+    const SUMMARY: &'static str;
+
+    /// Whether the endpoint takes a query string.
+    const HAS_QUERY: bool;
+
+    /// Whether the endpoint takes a JSON request body.
+    const HAS_REQUEST: bool;
+
+    /// Whether the endpoint responds with a JSON body. Endpoints without one
+    /// respond with an empty body.
+    const HAS_RESPONSE: bool;
+
+    /// Values of the path's parameters.
+    type Params: PathParams;
+    type Query: Serialize + DeserializeOwned;
+    type Request: Serialize + DeserializeOwned;
+    type Response: Serialize + DeserializeOwned;
+
+    /// The path with the parameters filled in.
+    fn path(params: &Self::Params) -> String {
+        let mut values = params.to_segments().into_iter();
+        Self::PATH
+            .split('/')
+            .map(|segment| {
+                if segment.starts_with('{') && segment.ends_with('}') {
+                    values
+                        .next()
+                        .expect("endpoint has more path parameters than values")
+                }
+                else {
+                    segment.to_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Values of an endpoint's path parameters, in the order they appear in the
+/// path.
+pub trait PathParams {
+    fn to_segments(&self) -> Vec<String>;
+}
+
+impl PathParams for () {
+    fn to_segments(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+impl PathParams for Uuid {
+    fn to_segments(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl PathParams for StarId {
+    fn to_segments(&self) -> Vec<String> {
+        self.0.to_segments()
+    }
+}
+
+impl PathParams for FleetId {
+    fn to_segments(&self) -> Vec<String> {
+        self.0.to_segments()
+    }
+}
+
+impl PathParams for TradeRouteId {
+    fn to_segments(&self) -> Vec<String> {
+        self.0.to_segments()
+    }
+}
+
+impl PathParams for WebhookId {
+    fn to_segments(&self) -> Vec<String> {
+        self.0.to_segments()
+    }
+}
+
+impl PathParams for FactionId {
+    fn to_segments(&self) -> Vec<String> {
+        self.0.to_segments()
+    }
+}
+
+macro_rules! or_unit {
+    () => {
+        ()
+    };
+    ($ty:ty) => {
+        $ty
+    };
+}
+
+macro_rules! is_some {
+    () => {
+        false
+    };
+    ($ty:ty) => {
+        true
+    };
+}
+
+/// Defines endpoints like this:
+///
+/// ```ignore
+/// /// Summary of the endpoint.
+/// Name: METHOD "/path/{id}", params = Id, query = Query, request = Request => Response;
+/// ```
+///
+/// Everything after the path is optional.
+macro_rules! endpoints {
+    ($(
+        #[doc = $summary:literal]
+        $name:ident: $method:ident $path:literal
+            $(, params = $params:ty)?
+            $(, query = $query:ty)?
+            $(, request = $request:ty)?
+            $(=> $response:ty)?;
+    )*) => {
+        $(
+            #[doc = $summary]
+            #[derive(Debug)]
+            pub enum $name {}
+
+            impl Endpoint for $name {
+                const METHOD: Method = Method::$method;
+                const PATH: &'static str = $path;
+                const SUMMARY: &'static str = $summary.trim_ascii();
+                const HAS_QUERY: bool = is_some!($($query)?);
+                const HAS_REQUEST: bool = is_some!($($request)?);
+                const HAS_RESPONSE: bool = is_some!($($response)?);
+
+                type Params = or_unit!($($params)?);
+                type Query = or_unit!($($query)?);
+                type Request = or_unit!($($request)?);
+                type Response = or_unit!($($response)?);
+            }
+        )*
+
+        /// Schemas of all endpoints.
+        #[cfg(feature = "schema")]
+        pub(crate) fn routes() -> Vec<crate::schema::Route> {
+            vec![$(crate::schema::Route::for_endpoint::<$name>(),)*]
+        }
+    };
+}
+
+endpoints! {
+    /// Server version and uptime.
+    GetStatus: GET "/status" => ServerStatus;
+
+    /// Worlds hosted by the server.
+    GetWorlds: GET "/worlds" => GetWorldsResponse;
+
+    /// Report a crash or error of a client.
+    ReportClientError: POST "/client-errors", request = ClientErrorReport;
+
+    /// Report the graphics capabilities of a client.
+    ReportClientCapabilities: POST "/client-capabilities", request = ClientCapabilityReport;
+
+    /// All stars.
+    GetStars: GET "/star" => GetStarsResponse;
+
+    /// Search stars by name or catalog designation.
+    SearchStars: GET "/star/search", query = SearchStarsQuery => SearchStarsResponse;
+
+    /// Impostors of all chunks.
+    GetStarChunks: GET "/star/chunks" => GetStarChunksResponse;
+
+    /// Planets of a star.
+    GetPlanets: GET "/star/{id}/planets", params = StarId => GetPlanetsResponse;
+
+    /// All regions.
+    GetRegions: GET "/regions" => GetRegionsResponse;
+
+    /// Factions by Kardashev rating.
+    GetLeaderboard: GET "/leaderboard" => GetLeaderboardResponse;
+
+    /// A page of the faction's journal, newest first.
+    GetJournal: GET "/journal", query = GetJournalQuery => GetJournalResponse;
+
+    /// The faction's bookmarks.
+    GetBookmarks: GET "/me/bookmarks" => GetBookmarksResponse;
+
+    /// Replace the faction's bookmarks.
+    PutBookmarks: PUT "/me/bookmarks", request = PutBookmarksRequest => GetBookmarksResponse;
+
+    /// The faction's UI settings.
+    GetSettings: GET "/me/settings" => GetSettingsResponse;
+
+    /// Store the faction's UI settings, unless another client changed them.
+    PutSettings: PUT "/me/settings", request = PutSettingsRequest => PutSettingsResponse;
+
+    /// Snapshots that can be replayed, oldest first.
+    GetSnapshots: GET "/replay/snapshots" => GetSnapshotsResponse;
+
+    /// Overview of the faction's empire.
+    GetEmpireSummary: GET "/empire/summary" => EmpireSummary;
+
+    /// The faction's relations to other factions, and pending treaties.
+    GetDiplomacy: GET "/diplomacy" => GetDiplomacyResponse;
+
+    /// Declare war on a faction. This breaks any treaty with it.
+    DeclareWar: POST "/diplomacy/{id}/war", params = FactionId => Relation;
+
+    /// Propose a treaty to a faction, replacing an earlier proposal to it.
+    ProposeTreaty: PUT "/diplomacy/{id}/proposal", params = FactionId, request = ProposeTreatyRequest => TreatyProposal;
+
+    /// Accept the treaty a faction proposed.
+    AcceptTreaty: POST "/diplomacy/{id}/proposal/accept", params = FactionId => Relation;
+
+    /// Withdraw the proposal to a faction, or decline the one from it.
+    DeleteProposal: DELETE "/diplomacy/{id}/proposal", params = FactionId;
+
+    /// Fleets visible to the faction.
+    GetFleets: GET "/fleet" => GetFleetsResponse;
+
+    /// A fleet.
+    GetFleet: GET "/fleet/{id}", params = FleetId => Fleet;
+
+    /// Replace a fleet's order queue.
+    SetFleetOrders: PUT "/fleet/{id}/orders", params = FleetId, request = SetOrdersRequest => SetOrdersResponse;
+
+    /// The faction's trade routes and the stockpiles of its colonies.
+    GetTradeRoutes: GET "/trade/route" => GetTradeRoutesResponse;
+
+    /// Open a trade route between two of the faction's colonies.
+    CreateTradeRoute: POST "/trade/route", request = CreateTradeRouteRequest => TradeRoute;
+
+    /// Close a trade route. Shipments on their way still arrive.
+    CancelTradeRoute: DELETE "/trade/route/{id}", params = TradeRouteId;
+
+    /// Create stars.
+    CreateStars: POST "/admin/star", request = CreateStarsRequest => CreateStarsResponse;
+
+    /// Audit the stars' derived properties.
+    AuditStars: POST "/admin/star/audit", request = AuditStarsRequest => AuditStarsResponse;
+
+    /// Rename a star.
+    RenameStar: PUT "/admin/star/{id}/name", params = StarId, request = RenameStarRequest => RenameStarResponse;
+
+    /// Delete a star and its planets. Stars with colonies or fleets ordered to
+    /// them can't be deleted.
+    DeleteStar: DELETE "/admin/star/{id}", params = StarId;
+
+    /// Create planets around existing stars.
+    CreatePlanets: POST "/admin/planet", request = CreatePlanetsRequest => CreatePlanetsResponse;
+
+    /// Create fleets.
+    CreateFleets: POST "/admin/fleet", request = CreateFleetsRequest => CreateFleetsResponse;
+
+    /// Append events to factions' journals.
+    CreateJournalEntries: POST "/admin/journal", request = CreateJournalEntriesRequest => CreateJournalEntriesResponse;
+
+    /// Recompute the regions.
+    RecomputeRegions: POST "/admin/regions/recompute" => RecomputeRegionsResponse;
+
+    /// Recompute the impostors.
+    RecomputeImpostors: POST "/admin/impostors/recompute" => RecomputeImpostorsResponse;
+
+    /// Asset downloads since the server started.
+    GetAssetStats: GET "/admin/assets/stats" => GetAssetStatsResponse;
+
+    /// Epoch and state hashes of the simulation.
+    GetSimulationState: GET "/admin/simulation" => GetSimulationStateResponse;
+
+    /// Run simulation epochs now.
+    Simulate: POST "/admin/simulate", query = SimulateQuery => SimulateResponse;
+
+    /// Create a world.
+    CreateWorld: POST "/admin/worlds", request = CreateWorldRequest => CreateWorldResponse;
+
+    /// Backup configuration and recent backups.
+    GetBackups: GET "/admin/backups" => GetBackupsResponse;
+
+    /// Back up the world now.
+    CreateBackup: POST "/admin/backups" => CreateBackupResponse;
+
+    /// The world's webhooks.
+    GetWebhooks: GET "/admin/webhooks" => GetWebhooksResponse;
+
+    /// Post the world's events to a URL.
+    CreateWebhook: POST "/admin/webhooks", request = CreateWebhookRequest => Webhook;
+
+    /// Stop posting events to a webhook.
+    DeleteWebhook: DELETE "/admin/webhooks/{id}", params = WebhookId;
+
+    /// Shut the server down.
+    Shutdown: GET "/admin/shutdown";
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{
+        Endpoint,
+        GetStatus,
+        SetFleetOrders,
+    };
+    use crate::model::fleet::FleetId;
+
+    #[test]
+    fn it_fills_in_path_parameters() {
+        assert_eq!(GetStatus::path(&()), "/status");
+        assert_eq!(
+            SetFleetOrders::path(&FleetId(Uuid::from_u128(1))),
+            "/fleet/00000000-0000-0000-0000-000000000001/orders"
+        );
+    }
+}
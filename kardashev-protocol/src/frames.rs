@@ -0,0 +1,157 @@
+//! Coordinate frames.
+//!
+//! Positions in the game are Cartesian coordinates in parsecs with the sun at
+//! the origin. The axes are those of the equatorial (ICRS) frame, as in the
+//! HYG catalog the stars are imported from: X points towards the vernal
+//! equinox (RA 0h, Dec 0°), Y towards RA 6h, and Z towards the north celestial
+//! pole.
+//!
+//! Catalogs give positions as spherical coordinates in the equatorial or the
+//! galactic frame, which are converted to and from the game's frame with
+//! [`Equatorial`] and [`Galactic`]. Angles are in radians.
+
+use nalgebra::{
+    Matrix3,
+    Point3,
+    Vector3,
+};
+
+/// Rotation from the equatorial (ICRS) to the galactic frame, as defined for
+/// the Hipparcos catalog.
+fn equatorial_to_galactic() -> Matrix3<f64> {
+    Matrix3::new(
+        -0.054_875_560_416_215_4,
+        -0.873_437_090_234_885,
+        -0.483_835_015_548_713_2,
+        0.494_109_427_875_583_7,
+        -0.444_829_629_960_011_2,
+        0.746_982_244_497_218_9,
+        -0.867_666_149_019_004_7,
+        -0.198_076_373_431_201_5,
+        0.455_983_776_175_066_9,
+    )
+}
+
+fn to_cartesian(longitude: f32, latitude: f32, distance: f32) -> Vector3<f64> {
+    let (longitude, latitude) = (f64::from(longitude), f64::from(latitude));
+    Vector3::new(
+        latitude.cos() * longitude.cos(),
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+    ) * f64::from(distance)
+}
+
+/// Returns longitude in `0..TAU`, latitude and distance.
+fn to_spherical(vector: &Vector3<f64>) -> (f32, f32, f32) {
+    let distance = vector.norm();
+    if distance == 0.0 {
+        return (0.0, 0.0, 0.0);
+    }
+    let longitude = vector.y.atan2(vector.x).rem_euclid(std::f64::consts::TAU);
+    let latitude = (vector.z / distance).clamp(-1.0, 1.0).asin();
+    (longitude as f32, latitude as f32, distance as f32)
+}
+
+/// Equatorial (ICRS) coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Equatorial {
+    pub right_ascension: f32,
+    pub declination: f32,
+
+    /// Distance in parsecs.
+    pub distance: f32,
+}
+
+impl Equatorial {
+    pub fn from_game(position: &Point3<f32>) -> Self {
+        let (right_ascension, declination, distance) = to_spherical(&position.coords.cast::<f64>());
+        Self {
+            right_ascension,
+            declination,
+            distance,
+        }
+    }
+
+    pub fn to_game(&self) -> Point3<f32> {
+        Point3::from(
+            to_cartesian(self.right_ascension, self.declination, self.distance).cast::<f32>(),
+        )
+    }
+
+    pub fn to_galactic(&self) -> Galactic {
+        Galactic::from_game(&self.to_game())
+    }
+}
+
+/// Galactic coordinates, with the galactic center at longitude and latitude
+/// 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Galactic {
+    pub longitude: f32,
+    pub latitude: f32,
+
+    /// Distance in parsecs.
+    pub distance: f32,
+}
+
+impl Galactic {
+    pub fn from_game(position: &Point3<f32>) -> Self {
+        let (longitude, latitude, distance) =
+            to_spherical(&(equatorial_to_galactic() * position.coords.cast::<f64>()));
+        Self {
+            longitude,
+            latitude,
+            distance,
+        }
+    }
+
+    pub fn to_game(&self) -> Point3<f32> {
+        let galactic = to_cartesian(self.longitude, self.latitude, self.distance);
+        // the rotation is orthogonal, so its inverse is its transpose.
+        Point3::from((equatorial_to_galactic().transpose() * galactic).cast::<f32>())
+    }
+
+    pub fn to_equatorial(&self) -> Equatorial {
+        Equatorial::from_game(&self.to_game())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Point3;
+
+    use super::{
+        Equatorial,
+        Galactic,
+    };
+
+    fn equatorial_degrees(right_ascension: f32, declination: f32) -> Equatorial {
+        Equatorial {
+            right_ascension: right_ascension.to_radians(),
+            declination: declination.to_radians(),
+            distance: 1.0,
+        }
+    }
+
+    #[test]
+    fn it_converts_equatorial_to_galactic() {
+        let center = equatorial_degrees(266.405, -28.936).to_galactic();
+        assert!(
+            center.longitude.to_degrees() < 0.01 || center.longitude.to_degrees() > 359.99,
+            "galactic center longitude: {center:?}"
+        );
+        assert!(center.latitude.to_degrees().abs() < 0.01);
+
+        let pole = equatorial_degrees(192.859, 27.128).to_galactic();
+        assert!((pole.latitude.to_degrees() - 90.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn game_positions_round_trip() {
+        let position = Point3::new(-1.2, 3.4, 0.5);
+        let galactic = Galactic::from_game(&position);
+        assert!((galactic.to_game() - position).norm() < 1e-4);
+        assert!((galactic.to_equatorial().to_game() - position).norm() < 1e-4);
+        assert!((galactic.distance - position.coords.norm()).abs() < 1e-4);
+    }
+}
@@ -1,8 +1,21 @@
 pub mod admin;
 pub mod assets;
+pub mod collision;
+pub mod endpoints;
+pub mod frames;
 pub mod model;
+pub mod names;
+pub mod replay;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod stellar;
+pub mod ui;
+pub mod units;
 
-use std::fmt::Display;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+};
 
 use chrono::{
     DateTime,
@@ -17,21 +30,363 @@ use serde::{
 /// re-export for the `asset_id!` macro
 pub use uuid;
 
-use crate::model::star::Star;
+use crate::model::{
+    bookmark::Bookmark,
+    diplomacy::{
+        Relation,
+        Stance,
+        TreatyProposal,
+    },
+    empire::ColonyId,
+    fleet::{
+        Fleet,
+        Order,
+        OrderKind,
+    },
+    impostor::StarChunk,
+    journal::{
+        JournalEntry,
+        JournalEntryId,
+        JournalEventKind,
+    },
+    leaderboard::LeaderboardEntry,
+    planet::Planet,
+    region::Region,
+    star::{
+        Star,
+        StarSearchResult,
+    },
+    trade::{
+        Resource,
+        Stockpile,
+        TradeRoute,
+    },
+    world::World,
+};
 
 pub const PROTOCOL_VERSION: Version = semver_macro::version!("0.1.0");
 
+/// Header with the faction on whose behalf a request is made.
+///
+/// The server only returns what is visible to this faction. Requests without
+/// it see only what is visible to everyone, unless they have the
+/// [`ADMIN_TOKEN_HEADER`].
+pub const FACTION_HEADER: &str = "x-kardashev-faction";
+
+/// Header with the server's admin token.
+///
+/// Requests with it and without a [`FACTION_HEADER`] see everything.
+pub const ADMIN_TOKEN_HEADER: &str = "x-kardashev-admin-token";
+
+/// Header with the [world][crate::model::world] in which a request is made.
+///
+/// Requests without it are made in the
+/// [default world][crate::model::world::WorldId::DEFAULT].
+pub const WORLD_HEADER: &str = "x-kardashev-world";
+
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ServerStatus {
     pub server_version: Version,
     pub up_since: DateTime<Utc>,
+
+    /// The server's clock when it responded, so that clients can tell how far
+    /// their clock is off. Older servers don't send it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime<Utc>>,
+
+    /// Number of simulation epochs the world of the request has run, if it
+    /// has been simulated yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GetStarsResponse {
     pub stars: Vec<Star>,
 }
 
+/// Query parameters for a star search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchStarsQuery {
+    /// A star's name, or a catalog designation like `HIP 32349`.
+    pub q: String,
+
+    /// Maximum number of results to return.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SearchStarsResponse {
+    /// Results ordered by relevance.
+    pub results: Vec<StarSearchResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetFleetsResponse {
+    pub fleets: Vec<Fleet>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SetOrdersRequest {
+    pub orders: Vec<OrderKind>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SetOrdersResponse {
+    pub orders: Vec<Order>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetLeaderboardResponse {
+    /// Entries ordered by rating, highest first. Only the highest rated
+    /// factions are included.
+    pub entries: Vec<LeaderboardEntry>,
+
+    /// Time at which the ratings were last computed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub computed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetRegionsResponse {
+    pub regions: Vec<Region>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetStarChunksResponse {
+    pub chunks: Vec<StarChunk>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetWorldsResponse {
+    pub worlds: Vec<World>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetPlanetsResponse {
+    pub planets: Vec<Planet>,
+}
+
+/// Query parameters for a page of the journal.
+///
+/// Entries are returned newest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetJournalQuery {
+    /// Only return entries older than this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before: Option<JournalEntryId>,
+
+    /// Only return entries of this kind.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<JournalEventKind>,
+
+    /// Maximum number of entries to return.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetJournalResponse {
+    pub entries: Vec<JournalEntry>,
+
+    /// Cursor for the next page, if there are more entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<JournalEntryId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetDiplomacyResponse {
+    /// The faction's relations to the factions it had dealings with. All
+    /// other factions are at peace with it.
+    pub relations: Vec<Relation>,
+
+    /// Treaties the faction proposed or was offered.
+    pub proposals: Vec<TreatyProposal>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProposeTreatyRequest {
+    pub stance: Stance,
+
+    /// Duration of the treaty in days, or `None` if it doesn't expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetTradeRoutesResponse {
+    pub routes: Vec<TradeRoute>,
+
+    /// Stockpiles of the faction's colonies.
+    pub stockpiles: Vec<Stockpile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreateTradeRouteRequest {
+    pub from: ColonyId,
+    pub to: ColonyId,
+    pub resource: Resource,
+
+    /// Amount to ship per day.
+    pub capacity: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetBookmarksResponse {
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Replaces all bookmarks of the faction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PutBookmarksRequest {
+    pub bookmarks: Vec<Bookmark>,
+}
+
+/// Settings of a faction's UI, e.g. key bindings and panel layout, synced
+/// between browsers.
+///
+/// The server doesn't interpret the settings. Every write increments the
+/// version.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlayerSettings {
+    pub version: u64,
+    pub settings: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetSettingsResponse {
+    /// `None` if the settings were never stored.
+    pub settings: Option<PlayerSettings>,
+}
+
+/// Stores the faction's settings, if they weren't changed since
+/// `base_version`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PutSettingsRequest {
+    /// Version the settings are based on, or `None` if they're not based on
+    /// stored settings.
+    pub base_version: Option<u64>,
+    pub settings: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PutSettingsResponse {
+    /// The stored settings. On a conflict these are the settings that were
+    /// stored by another client, which should be merged with the local ones
+    /// before trying again.
+    pub settings: PlayerSettings,
+
+    /// Whether the settings were changed since `base_version`, in which case
+    /// they weren't stored.
+    pub conflict: bool,
+}
+
+/// Event sent to clients over the session stream.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SessionEvent {
+    Journal { entry: JournalEntry },
+}
+
+/// Report of a crash or unhandled error in a client, sent to
+/// `/client-errors`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClientErrorReport {
+    pub kind: ClientErrorKind,
+    pub message: String,
+
+    /// Source location, for panics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+
+    pub time: DateTime<Utc>,
+    pub client_version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+
+    /// Log events that happened before the error, the oldest first.
+    #[serde(default)]
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ClientErrorKind {
+    Panic,
+    Error,
+}
+
+/// A log event that is attached to a [`ClientErrorReport`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Breadcrumb {
+    pub time: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Graphics capabilities of a client, sent to `/client-capabilities` if the
+/// player opted in.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClientCapabilityReport {
+    pub client_version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+
+    pub backend: String,
+    pub adapter: String,
+    pub driver: String,
+
+    /// Enabled device features.
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// Device limits by name.
+    #[serde(default)]
+    pub limits: BTreeMap<String, u64>,
+
+    /// What the texture formats support, by format.
+    #[serde(default)]
+    pub texture_formats: BTreeMap<String, Vec<String>>,
+
+    /// Rendering features that were disabled, and why.
+    #[serde(default)]
+    pub disabled: BTreeMap<String, String>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub struct PrettyJsonError {
     #[source]
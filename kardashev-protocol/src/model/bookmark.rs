@@ -0,0 +1,48 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use nalgebra::{
+    Point3,
+    UnitQuaternion,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::model::star::StarId;
+
+/// ID of a bookmark.
+///
+/// Bookmarks are created offline by the client, so their IDs are generated
+/// client-side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct BookmarkId(pub Uuid);
+
+/// A saved camera view.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Bookmark {
+    pub id: BookmarkId,
+    pub name: String,
+
+    /// Position of the camera.
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+
+    /// Orientation of the camera.
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::UnitQuaternion"))]
+    pub rotation: UnitQuaternion<f32>,
+
+    /// The star the camera was looking at, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub star: Option<StarId>,
+
+    /// Time of the last change. When syncing, the newer version of a bookmark
+    /// wins.
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,124 @@
+//! Partitioning of space into chunks.
+//!
+//! Space is divided into cubic chunks of [`CHUNK_SIZE`], aligned to the origin
+//! of the game's [frame][crate::frames]. The server and clients use this same
+//! partitioning, e.g. for [impostors][super::impostor] and for finding what is
+//! within sensor range, so that they agree on which chunk a position belongs
+//! to.
+
+use nalgebra::{
+    Point3,
+    Vector3,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Edge length of a chunk in parsecs.
+pub const CHUNK_SIZE: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChunkCoords {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkCoords {
+    /// Returns the chunk containing `position`.
+    ///
+    /// Positions on the boundary between chunks belong to the chunk with the
+    /// larger coordinates.
+    pub fn containing(position: &Point3<f32>) -> Self {
+        Self {
+            x: (position.x / CHUNK_SIZE).floor() as i32,
+            y: (position.y / CHUNK_SIZE).floor() as i32,
+            z: (position.z / CHUNK_SIZE).floor() as i32,
+        }
+    }
+
+    /// Corner of the chunk with the smallest coordinates.
+    pub fn min(&self) -> Point3<f32> {
+        Point3::new(
+            self.x as f32 * CHUNK_SIZE,
+            self.y as f32 * CHUNK_SIZE,
+            self.z as f32 * CHUNK_SIZE,
+        )
+    }
+
+    /// Corner of the chunk with the largest coordinates.
+    pub fn max(&self) -> Point3<f32> {
+        self.min() + Vector3::repeat(CHUNK_SIZE)
+    }
+
+    /// Center of the chunk.
+    pub fn center(&self) -> Point3<f32> {
+        self.min() + Vector3::repeat(0.5 * CHUNK_SIZE)
+    }
+
+    /// Distance from `position` to the closest point of the chunk, which is
+    /// `0.0` if the position is inside of it.
+    pub fn distance_to(&self, position: &Point3<f32>) -> f32 {
+        let closest = position
+            .coords
+            .sup(&self.min().coords)
+            .inf(&self.max().coords);
+        (position.coords - closest).norm()
+    }
+
+    /// Returns the chunks that are at least partially within `radius` of
+    /// `center`.
+    pub fn within(center: Point3<f32>, radius: f32) -> impl Iterator<Item = ChunkCoords> {
+        let min = Self::containing(&(center - Vector3::repeat(radius)));
+        let max = Self::containing(&(center + Vector3::repeat(radius)));
+
+        (min.x..=max.x)
+            .flat_map(move |x| {
+                (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| Self { x, y, z }))
+            })
+            .filter(move |coords| coords.distance_to(&center) <= radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Point3;
+
+    use super::{
+        ChunkCoords,
+        CHUNK_SIZE,
+    };
+
+    #[test]
+    fn it_finds_the_containing_chunk() {
+        let coords = ChunkCoords::containing(&Point3::new(0.0, -0.5, CHUNK_SIZE * 1.5));
+        assert_eq!(coords, ChunkCoords { x: 0, y: -1, z: 1 });
+        assert_eq!(
+            ChunkCoords::containing(&coords.center()),
+            coords,
+            "the center is inside the chunk"
+        );
+        assert_eq!(
+            ChunkCoords::containing(&coords.min()),
+            coords,
+            "the min corner is inside the chunk"
+        );
+        assert_ne!(ChunkCoords::containing(&coords.max()), coords);
+    }
+
+    #[test]
+    fn it_finds_chunks_within_a_radius() {
+        let center = Point3::new(1.0, 1.0, 1.0);
+        let chunks = ChunkCoords::within(center, 2.0).collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 8, "touches the chunks around the origin");
+
+        let chunks = ChunkCoords::within(Point3::new(10.0, 10.0, 10.0), 5.0).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![ChunkCoords { x: 0, y: 0, z: 0 }]);
+
+        // e.g. the chunk at (1, 1, 1) is within the bounding box, but not the radius.
+        let chunks = ChunkCoords::within(center, CHUNK_SIZE).count();
+        assert!(chunks < 27);
+    }
+}
@@ -0,0 +1,166 @@
+//! Relations between factions.
+//!
+//! Every pair of factions has a [`Stance`] towards each other, which is the
+//! same for both sides. Factions that never dealt with each other are at
+//! peace. A faction can declare war on its own, while peace and alliances
+//! need a [treaty](Treaty) that the other faction accepts. Treaties can have a
+//! duration, after which the factions return to their previous stance.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::faction::FactionId;
+
+/// Longest duration of a treaty in days.
+pub const MAX_TREATY_DAYS: u32 = 3650;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Stance {
+    War,
+    #[default]
+    Peace,
+    Alliance,
+}
+
+impl Stance {
+    /// Whether fleets of the factions fight when they meet.
+    pub fn is_hostile(&self) -> bool {
+        *self == Self::War
+    }
+
+    /// Whether the factions see what each other's sensors observe.
+    pub fn shares_sensors(&self) -> bool {
+        *self == Self::Alliance
+    }
+}
+
+/// The relation of the viewer to another faction.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Relation {
+    pub faction: FactionId,
+    pub stance: Stance,
+
+    /// When the stance last changed.
+    pub since: DateTime<Utc>,
+
+    /// The treaty that set the stance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub treaty: Option<Treaty>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Treaty {
+    pub signed_at: DateTime<Utc>,
+
+    /// Days of game time until the treaty expires. Treaties without a
+    /// duration last until war is declared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_days: Option<f32>,
+
+    /// The stance the factions return to when the treaty expires.
+    pub reverts_to: Stance,
+}
+
+/// A treaty that a faction offered another one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TreatyProposal {
+    pub from: FactionId,
+    pub to: FactionId,
+    pub stance: Stance,
+
+    /// Duration of the treaty in days, or `None` if it doesn't expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+
+    pub proposed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DiplomacyError {
+    #[error("a faction can't have relations with itself")]
+    SameFaction,
+
+    #[error("treaties can only be made for peace or an alliance")]
+    InvalidTreatyStance { stance: Stance },
+
+    #[error("treaties last between 1 and {MAX_TREATY_DAYS} days")]
+    InvalidTreatyDuration { days: u32 },
+
+    #[error("the factions already have this stance")]
+    UnchangedStance,
+}
+
+/// Checks that `from` can propose a treaty with `stance` and a duration of
+/// `days` to `to`, who it currently has the stance `current` with.
+pub fn validate_proposal(
+    from: FactionId,
+    to: FactionId,
+    current: Stance,
+    stance: Stance,
+    days: Option<u32>,
+) -> Result<(), DiplomacyError> {
+    if from == to {
+        return Err(DiplomacyError::SameFaction);
+    }
+    if stance == Stance::War {
+        return Err(DiplomacyError::InvalidTreatyStance { stance });
+    }
+    if let Some(days) = days {
+        if days == 0 || days > MAX_TREATY_DAYS {
+            return Err(DiplomacyError::InvalidTreatyDuration { days });
+        }
+    }
+    if stance == current {
+        return Err(DiplomacyError::UnchangedStance);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{
+        validate_proposal,
+        DiplomacyError,
+        Stance,
+        MAX_TREATY_DAYS,
+    };
+    use crate::model::faction::FactionId;
+
+    #[test]
+    fn it_validates_proposals() {
+        let a = FactionId(Uuid::from_u128(1));
+        let b = FactionId(Uuid::from_u128(2));
+
+        assert!(validate_proposal(a, b, Stance::War, Stance::Peace, Some(30)).is_ok());
+        assert!(validate_proposal(a, b, Stance::Peace, Stance::Alliance, None).is_ok());
+        assert!(matches!(
+            validate_proposal(a, a, Stance::Peace, Stance::Alliance, None),
+            Err(DiplomacyError::SameFaction)
+        ));
+        assert!(matches!(
+            validate_proposal(a, b, Stance::Peace, Stance::War, None),
+            Err(DiplomacyError::InvalidTreatyStance { .. })
+        ));
+        assert!(matches!(
+            validate_proposal(a, b, Stance::War, Stance::Peace, Some(MAX_TREATY_DAYS + 1)),
+            Err(DiplomacyError::InvalidTreatyDuration { .. })
+        ));
+        assert!(matches!(
+            validate_proposal(a, b, Stance::Alliance, Stance::Alliance, None),
+            Err(DiplomacyError::UnchangedStance)
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::model::{
+    faction::FactionId,
+    star::StarId,
+    trade::Resource,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct ColonyId(pub Uuid);
+
+/// Overview of a faction's empire.
+///
+/// This aggregates everything the dashboard shows, so that it can be fetched
+/// with a single request. It doesn't include research progress, since the
+/// server doesn't model research yet.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EmpireSummary {
+    pub faction: FactionId,
+    pub name: String,
+    pub colonies: Vec<Colony>,
+    pub fleets: FleetSummary,
+
+    /// Number of stars the faction has explored.
+    pub explored_stars: u32,
+
+    /// Production and stock of every resource. Empty if the server doesn't
+    /// run the trade module.
+    pub resources: Vec<ResourceSummary>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Colony {
+    pub id: ColonyId,
+    pub name: String,
+    pub star: StarId,
+    pub founded_at: DateTime<Utc>,
+}
+
+/// A resource, summed over all colonies of a faction.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResourceSummary {
+    pub resource: Resource,
+
+    /// Amount produced per day.
+    pub production: f32,
+
+    /// Amount stored in the stockpiles.
+    pub stockpiled: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FleetSummary {
+    pub total: u32,
+
+    /// Fleets with an empty order queue.
+    pub idle: u32,
+}
@@ -0,0 +1,10 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct FactionId(pub Uuid);
@@ -0,0 +1,227 @@
+use chrono::{
+    DateTime,
+    TimeDelta,
+    Utc,
+};
+use nalgebra::Point3;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::model::{
+    faction::FactionId,
+    star::StarId,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct FleetId(pub Uuid);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct OrderId(pub Uuid);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fleet {
+    pub id: FleetId,
+    pub name: String,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+
+    /// Speed of the fleet in light years per day.
+    pub speed: f32,
+
+    /// Faction owning the fleet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub faction: Option<FactionId>,
+
+    /// Range of the fleet's sensors in light years.
+    pub sensor_range: f32,
+
+    /// The fleet's order queue. The first order is executed first.
+    pub orders: Vec<Order>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Order {
+    pub id: OrderId,
+    #[serde(flatten)]
+    pub kind: OrderKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "order", rename_all = "kebab-case")]
+pub enum OrderKind {
+    /// Move to a star.
+    Move { star: StarId },
+
+    /// Move to a star and survey it.
+    Survey { star: StarId },
+
+    /// Move to a star and found a colony there.
+    Colonize { star: StarId },
+}
+
+impl OrderKind {
+    /// The star targeted by this order.
+    pub fn star(&self) -> StarId {
+        match self {
+            Self::Move { star } | Self::Survey { star } | Self::Colonize { star } => *star,
+        }
+    }
+
+    /// Time the order takes after the fleet arrived at the star.
+    pub fn duration(&self) -> TimeDelta {
+        match self {
+            Self::Move { .. } => TimeDelta::zero(),
+            Self::Survey { .. } => TimeDelta::days(1),
+            Self::Colonize { .. } => TimeDelta::days(7),
+        }
+    }
+}
+
+/// Maximum number of orders a fleet can have queued.
+pub const MAX_ORDERS: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OrderError {
+    #[error("too many orders (max {MAX_ORDERS})")]
+    TooManyOrders,
+
+    #[error("order {index} targets unknown star {star:?}")]
+    UnknownStar { index: usize, star: StarId },
+
+    #[error("fleet can't move with speed {speed}")]
+    InvalidSpeed { speed: f32 },
+}
+
+/// Predicted times for an order.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OrderPrediction {
+    /// Time at which the fleet arrives at the order's star.
+    pub arrival: DateTime<Utc>,
+
+    /// Time at which the order is completed.
+    pub completion: DateTime<Utc>,
+}
+
+/// Predicts when the orders in a queue will be completed.
+///
+/// The fleet starts at `position` at time `start`, and travels in a straight
+/// line with `speed` light years per day. `star_position` is used to look up
+/// the positions of the targeted stars.
+///
+/// This is used by the server to validate order queues, and by the UI to show
+/// arrival times.
+pub fn predict_orders<'a>(
+    position: Point3<f32>,
+    speed: f32,
+    start: DateTime<Utc>,
+    orders: impl IntoIterator<Item = &'a OrderKind>,
+    mut star_position: impl FnMut(StarId) -> Option<Point3<f32>>,
+) -> Result<Vec<OrderPrediction>, OrderError> {
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(OrderError::InvalidSpeed { speed });
+    }
+
+    let mut position = position;
+    let mut time = start;
+    let mut predictions = vec![];
+
+    for (index, order) in orders.into_iter().enumerate() {
+        if index >= MAX_ORDERS {
+            return Err(OrderError::TooManyOrders);
+        }
+
+        let star = order.star();
+        let destination = star_position(star).ok_or(OrderError::UnknownStar { index, star })?;
+
+        let days = f64::from((destination - position).norm() / speed);
+        let arrival = time + TimeDelta::milliseconds((days * 86_400_000.0) as i64);
+        let completion = arrival + order.duration();
+
+        predictions.push(OrderPrediction {
+            arrival,
+            completion,
+        });
+        position = destination;
+        time = completion;
+    }
+
+    Ok(predictions)
+}
+
+/// Result of [`advance_fleet`].
+#[derive(Clone, Copy, Debug)]
+pub struct FleetAdvance {
+    pub position: Point3<f32>,
+
+    /// Days of work done on the current order, after the fleet arrived.
+    pub work: f32,
+
+    /// Number of orders from the front of the queue that were completed.
+    pub completed: usize,
+}
+
+/// Advances a fleet along its order queue by `days`.
+///
+/// This uses the same model as [`predict_orders`]: the fleet travels in a
+/// straight line to an order's star, and then works on the order for its
+/// [duration](OrderKind::duration). `work` is the time already spent working on
+/// the first order. Orders targeting unknown stars stop the fleet.
+pub fn advance_fleet<'a>(
+    position: Point3<f32>,
+    speed: f32,
+    work: f32,
+    orders: impl IntoIterator<Item = &'a OrderKind>,
+    mut star_position: impl FnMut(StarId) -> Option<Point3<f32>>,
+    days: f32,
+) -> FleetAdvance {
+    let mut advance = FleetAdvance {
+        position,
+        work,
+        completed: 0,
+    };
+    if !(speed > 0.0 && speed.is_finite()) {
+        return advance;
+    }
+
+    let mut remaining = days;
+    for order in orders {
+        let Some(destination) = star_position(order.star())
+        else {
+            break;
+        };
+
+        let distance = (destination - advance.position).norm();
+        if distance > 0.0 {
+            let travel = distance / speed;
+            if travel > remaining {
+                advance.position += (destination - advance.position) * (remaining / travel);
+                break;
+            }
+            advance.position = destination;
+            remaining -= travel;
+        }
+
+        let duration = order.duration().num_seconds() as f32 / 86_400.0;
+        let needed = duration - advance.work;
+        if needed > remaining {
+            advance.work += remaining;
+            break;
+        }
+        remaining -= needed;
+        advance.work = 0.0;
+        advance.completed += 1;
+    }
+
+    advance
+}
@@ -0,0 +1,36 @@
+//! Impostors for rendering distant stars.
+//!
+//! For each [chunk][super::chunk] the server precomputes a [`StarChunk`], a
+//! small point cloud that looks like the chunk's stars from afar. Clients draw
+//! it instead of the individual stars, when the chunk is far enough away.
+
+use nalgebra::Point3;
+use palette::LinSrgb;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::chunk::ChunkCoords;
+
+/// Point of an impostor, standing in for one or more stars.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ImpostorPoint {
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::LinSrgb"))]
+    pub color: LinSrgb,
+
+    /// Combined luminosity of the stars relative to the sun.
+    pub luminosity: f32,
+}
+
+/// Impostor for the stars in a chunk.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StarChunk {
+    pub coords: ChunkCoords,
+    pub num_stars: u32,
+    pub points: Vec<ImpostorPoint>,
+}
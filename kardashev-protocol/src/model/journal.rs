@@ -0,0 +1,71 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::{
+    empire::ColonyId,
+    faction::FactionId,
+    fleet::FleetId,
+    star::StarId,
+};
+
+/// ID of a journal entry.
+///
+/// IDs increase monotonically, so they double as a cursor for pagination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct JournalEntryId(pub i64);
+
+/// An entry in a faction's journal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JournalEntry {
+    pub id: JournalEntryId,
+    pub faction: FactionId,
+    pub time: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum JournalEvent {
+    /// A fleet was involved in a battle.
+    Battle { star: StarId, fleets: Vec<FleetId> },
+
+    /// A star was explored.
+    Discovery { star: StarId },
+
+    /// A fleet founded a colony.
+    ColonyFounded { colony: ColonyId, star: StarId },
+}
+
+impl JournalEvent {
+    pub fn kind(&self) -> JournalEventKind {
+        match self {
+            Self::Battle { .. } => JournalEventKind::Battle,
+            Self::Discovery { .. } => JournalEventKind::Discovery,
+            Self::ColonyFounded { .. } => JournalEventKind::ColonyFounded,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum JournalEventKind {
+    Battle,
+    Discovery,
+    ColonyFounded,
+}
+
+impl JournalEventKind {
+    pub const ALL: [Self; 3] = [Self::Battle, Self::Discovery, Self::ColonyFounded];
+}
@@ -0,0 +1,75 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::faction::FactionId;
+
+/// Luminosity of the sun in watts.
+pub const SOLAR_LUMINOSITY: f64 = 3.828e26;
+
+/// Fraction of its star's luminosity that a colony harvests.
+///
+/// Megastructures will harvest larger fractions, once they exist.
+pub const COLONY_ENERGY_FRACTION: f64 = 1e-10;
+
+/// Energy output in watts of a colony at a star with the given luminosity (in
+/// solar luminosities).
+pub fn colony_power(luminosity: f64) -> f64 {
+    luminosity * SOLAR_LUMINOSITY * COLONY_ENERGY_FRACTION
+}
+
+/// Computes the rating on the Kardashev scale for an energy output in watts.
+///
+/// This uses Carl Sagan's interpolation `K = (log10(P) - 6) / 10`, which gives
+/// a rating of 1 for 10^16 W, 2 for 10^26 W and 3 for 10^36 W. Outputs below
+/// 1 MW are rated 0.
+pub fn kardashev_rating(power: f64) -> f64 {
+    if power > 1e6 {
+        (power.log10() - 6.0) / 10.0
+    }
+    else {
+        0.0
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LeaderboardEntry {
+    pub faction: FactionId,
+    pub name: String,
+
+    /// Energy output in watts.
+    pub power: f64,
+
+    /// Rating on the Kardashev scale, see [`kardashev_rating`].
+    pub rating: f64,
+
+    /// Past ratings, oldest first. The history is downsampled to a fixed number
+    /// of samples, and includes the current rating.
+    pub history: Vec<RatingSample>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RatingSample {
+    pub computed_at: DateTime<Utc>,
+    pub rating: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::kardashev_rating;
+
+    #[test]
+    fn it_rates_sagan_reference_points() {
+        assert_eq!(kardashev_rating(0.0), 0.0);
+        assert!((kardashev_rating(1e16) - 1.0).abs() < 1e-9);
+        assert!((kardashev_rating(1e26) - 2.0).abs() < 1e-9);
+        assert!((kardashev_rating(1e36) - 3.0).abs() < 1e-9);
+    }
+}
@@ -1 +1,15 @@
+pub mod bookmark;
+pub mod chunk;
+pub mod diplomacy;
+pub mod empire;
+pub mod faction;
+pub mod fleet;
+pub mod impostor;
+pub mod journal;
+pub mod leaderboard;
+pub mod network;
+pub mod planet;
+pub mod region;
 pub mod star;
+pub mod trade;
+pub mod world;
@@ -0,0 +1,69 @@
+//! Stable IDs of server entities.
+//!
+//! The client spawns server entities into its own ECS, where they get new
+//! entity handles every time they're loaded. References between entities, like
+//! the star an order targets, use these IDs instead, which are the same on the
+//! server and every client.
+
+use std::fmt::Display;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::{
+    fleet::FleetId,
+    star::StarId,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", content = "id", rename_all = "kebab-case")]
+pub enum NetworkId {
+    Star(StarId),
+    Fleet(FleetId),
+}
+
+impl From<StarId> for NetworkId {
+    fn from(value: StarId) -> Self {
+        Self::Star(value)
+    }
+}
+
+impl From<FleetId> for NetworkId {
+    fn from(value: FleetId) -> Self {
+        Self::Fleet(value)
+    }
+}
+
+impl Display for NetworkId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Star(id) => write!(f, "star:{}", id.0),
+            Self::Fleet(id) => write!(f, "fleet:{}", id.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::NetworkId;
+    use crate::model::star::StarId;
+
+    #[test]
+    fn it_serializes_the_kind_with_the_id() {
+        let id = NetworkId::from(StarId(Uuid::from_u128(1)));
+        let json = serde_json::to_value(id).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "star",
+                "id": "00000000-0000-0000-0000-000000000001",
+            })
+        );
+        assert_eq!(serde_json::from_value::<NetworkId>(json).unwrap(), id);
+    }
+}
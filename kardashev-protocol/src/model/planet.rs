@@ -0,0 +1,54 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::model::star::StarId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct PlanetId(pub Uuid);
+
+/// A planet orbiting a star.
+///
+/// Orbital and physical parameters are optional, since they're not known for
+/// all real exoplanets.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Planet {
+    pub id: PlanetId,
+    pub star: StarId,
+    pub name: String,
+
+    /// Whether this is a confirmed real exoplanet, rather than a generated
+    /// one.
+    pub confirmed: bool,
+
+    /// Semi-major axis in AU.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semi_major_axis: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eccentricity: Option<f32>,
+
+    /// Orbital period in days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orbital_period: Option<f32>,
+
+    /// Mass relative to the earth.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f32>,
+
+    /// Radius relative to the earth.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f32>,
+
+    /// Equilibrium temperature in Kelvin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equilibrium_temperature: Option<f32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discovery_year: Option<i32>,
+}
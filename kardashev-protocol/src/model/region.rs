@@ -0,0 +1,36 @@
+use nalgebra::{
+    Point2,
+    Point3,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct RegionId(pub Uuid);
+
+/// A region of high star density, like a cluster or association.
+///
+/// Regions are found by clustering the stars' positions on the server. Their
+/// outline is the convex hull of their stars, projected onto the XZ-plane.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Region {
+    pub id: RegionId,
+    pub name: String,
+
+    /// Mean position of the region's stars.
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub center: Point3<f32>,
+
+    /// Vertices of the outline on the XZ-plane, in counter-clockwise order.
+    #[cfg_attr(feature = "schema", schemars(with = "Vec<crate::schema::Point2>"))]
+    pub hull: Vec<Point2<f32>>,
+
+    /// Number of stars in the region.
+    pub num_stars: u32,
+}
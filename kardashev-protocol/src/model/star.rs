@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use nalgebra::Point3;
 use palette::LinSrgb;
 use serde::{
@@ -6,11 +8,13 @@ use serde::{
 };
 use uuid::Uuid;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(transparent)]
 pub struct StarId(pub Uuid);
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CatalogIds {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hyg: Option<u32>,
@@ -26,11 +30,62 @@ pub struct CatalogIds {
     pub bf: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl CatalogIds {
+    /// Returns the most common designation of the star, e.g. `HIP 32349`.
+    pub fn designation(&self) -> Option<String> {
+        self.bf
+            .clone()
+            .or_else(|| self.hip.map(|id| format!("HIP {id}")))
+            .or_else(|| self.hd.map(|id| format!("HD {id}")))
+            .or_else(|| self.hr.map(|id| format!("HR {id}")))
+            .or_else(|| self.gl.as_ref().map(|id| format!("Gl {id}")))
+            .or_else(|| self.hyg.map(|id| format!("HYG {id}")))
+    }
+}
+
+/// How much a faction knows about a star.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum StarVisibility {
+    /// The star has never been explored. Only what can be observed from afar
+    /// is known about it.
+    Unexplored,
+
+    /// The star has been explored, but is currently not within sensor range.
+    Explored,
+
+    /// The star is currently within sensor range.
+    #[default]
+    Observed,
+}
+
+/// Distances from a star in AU, in between which planets could have liquid
+/// water.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HabitableZone {
+    pub inner: f32,
+    pub outer: f32,
+}
+
+impl From<Range<f32>> for HabitableZone {
+    fn from(value: Range<f32>) -> Self {
+        Self {
+            inner: value.start,
+            outer: value.end,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Star {
     pub id: StarId,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
     pub position: Point3<f32>,
     pub effective_temperature: f32,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::LinSrgb"))]
     pub color: LinSrgb,
     pub absolute_magnitude: f32,
     pub luminousity: f32,
@@ -39,5 +94,53 @@ pub struct Star {
     pub spectral_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+
+    /// Whether the name was [generated][crate::names], because the star
+    /// doesn't have a proper name.
+    #[serde(default)]
+    pub name_generated: bool,
     pub catalog_ids: CatalogIds,
+    #[serde(default)]
+    pub visibility: StarVisibility,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habitable_zone: Option<HabitableZone>,
+
+    /// Peak-to-peak variability in magnitudes, if the star is variable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variability: Option<f32>,
+}
+
+/// A star found by a search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StarSearchResult {
+    pub id: StarId,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub catalog_ids: CatalogIds,
+}
+
+/// A change to the stars, sent over the
+/// [`STAR_EVENTS_PATH`](crate::endpoints::STAR_EVENTS_PATH) websocket.
+///
+/// Stars are filtered by the viewer's visibility, like in `GET /star`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum StarEvent {
+    Inserted {
+        star: Star,
+    },
+    Updated {
+        star: Star,
+    },
+    Deleted {
+        id: StarId,
+    },
+
+    /// The client fell behind and missed events, and should load all stars
+    /// again.
+    Reload,
 }
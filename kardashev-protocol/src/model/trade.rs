@@ -0,0 +1,165 @@
+//! Trade between the colonies of a faction.
+//!
+//! Colonies produce [`Resource`]s into their stockpiles. A [`TradeRoute`]
+//! loads up to its capacity from the stockpile of one colony every day, and
+//! the shipments arrive at the other colony after the route's travel time.
+//! Stockpiles are limited to [`STOCKPILE_CAPACITY`], and what doesn't fit is
+//! lost.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        empire::ColonyId,
+        star::StarId,
+    },
+    units::Distance,
+};
+
+/// Speed of freighters in light years per day.
+pub const TRADE_SPEED: f32 = 2.0;
+
+/// Largest amount a route can ship per day.
+pub const MAX_ROUTE_CAPACITY: f32 = 1000.0;
+
+/// Largest amount of each resource a colony can store.
+pub const STOCKPILE_CAPACITY: f32 = 10_000.0;
+
+/// Energy a colony collects per day and solar luminosity of its star.
+pub const ENERGY_PER_LUMINOSITY: f32 = 10.0;
+
+/// Metals a colony mines per day and planet of its star.
+pub const METALS_PER_PLANET: f32 = 5.0;
+
+/// Volatiles a colony harvests per day.
+pub const VOLATILES_PER_COLONY: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Resource {
+    Energy,
+    Metals,
+    Volatiles,
+}
+
+impl Resource {
+    pub const ALL: [Self; 3] = [Self::Energy, Self::Metals, Self::Volatiles];
+
+    /// Amount a colony produces per day, given the luminosity of its star and
+    /// the number of planets around it.
+    pub fn production(&self, luminosity: f32, planets: u32) -> f32 {
+        match self {
+            Self::Energy => ENERGY_PER_LUMINOSITY * luminosity.max(0.0),
+            Self::Metals => METALS_PER_PLANET * planets as f32,
+            Self::Volatiles => VOLATILES_PER_COLONY,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct TradeRouteId(pub Uuid);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TradeRoute {
+    pub id: TradeRouteId,
+    pub from: ColonyId,
+    pub from_star: StarId,
+    pub to: ColonyId,
+    pub to_star: StarId,
+    pub resource: Resource,
+
+    /// Amount shipped per day.
+    pub capacity: f32,
+
+    /// Days a shipment takes from one colony to the other.
+    pub travel_days: f32,
+
+    /// Amount that is on its way.
+    pub in_transit: f32,
+
+    pub created_at: DateTime<Utc>,
+}
+
+/// Amount of a resource stored at a colony.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stockpile {
+    pub colony: ColonyId,
+    pub resource: Resource,
+    pub amount: f32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TradeError {
+    #[error("a trade route needs two different colonies")]
+    SameColony,
+
+    #[error("trade routes can ship between 0 and {MAX_ROUTE_CAPACITY} per day, not {capacity}")]
+    InvalidCapacity { capacity: f32 },
+}
+
+/// Days freighters take to travel `distance`.
+pub fn travel_days(distance: Distance) -> f32 {
+    distance.light_years() / TRADE_SPEED
+}
+
+/// Checks that a route from `from` to `to` can ship `capacity` per day.
+pub fn validate_route(from: ColonyId, to: ColonyId, capacity: f32) -> Result<(), TradeError> {
+    if from == to {
+        return Err(TradeError::SameColony);
+    }
+    if !(capacity > 0.0 && capacity <= MAX_ROUTE_CAPACITY) {
+        return Err(TradeError::InvalidCapacity { capacity });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{
+        travel_days,
+        validate_route,
+        TradeError,
+        MAX_ROUTE_CAPACITY,
+        TRADE_SPEED,
+    };
+    use crate::{
+        model::empire::ColonyId,
+        units::Distance,
+    };
+
+    #[test]
+    fn it_validates_routes() {
+        let a = ColonyId(Uuid::from_u128(1));
+        let b = ColonyId(Uuid::from_u128(2));
+
+        assert!(validate_route(a, b, 10.0).is_ok());
+        assert!(matches!(
+            validate_route(a, a, 10.0),
+            Err(TradeError::SameColony)
+        ));
+        for capacity in [0.0, -1.0, f32::NAN, MAX_ROUTE_CAPACITY * 2.0] {
+            assert!(matches!(
+                validate_route(a, b, capacity),
+                Err(TradeError::InvalidCapacity { .. })
+            ));
+        }
+
+        let days = travel_days(Distance::from_light_years(TRADE_SPEED * 3.0));
+        assert!((days - 3.0).abs() < 1e-5);
+    }
+}
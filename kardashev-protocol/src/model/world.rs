@@ -0,0 +1,46 @@
+//! Worlds are separate galaxies hosted by the same server, e.g. one for testing
+//! and the main one.
+
+use std::str::FromStr;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct WorldId(pub Uuid);
+
+impl WorldId {
+    /// The world that requests are made in, if they don't select one.
+    pub const DEFAULT: Self = Self(Uuid::nil());
+}
+
+impl Default for WorldId {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FromStr for WorldId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.parse()?))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct World {
+    pub id: WorldId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
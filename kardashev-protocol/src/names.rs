@@ -0,0 +1,118 @@
+//! Deterministic generation of names for stars and planets.
+//!
+//! Names are built from syllables picked by a small PRNG that is seeded by
+//! the ID of the named object, so the same object always gets the same name.
+
+use crate::model::star::StarId;
+
+const ONSETS: &[&str] = &[
+    "", "b", "c", "d", "f", "g", "h", "k", "l", "m", "n", "p", "r", "s", "t", "v", "z", "br", "dr",
+    "gl", "kr", "ph", "st", "th", "tr", "x",
+];
+const NUCLEI: &[&str] = &["a", "e", "i", "o", "u", "y", "ae", "ai", "ei", "ou"];
+const CODAS: &[&str] = &["", "", "", "", "l", "m", "n", "r", "s", "th", "x"];
+
+/// [SplitMix64](https://prng.di.unimi.it/splitmix64.c) PRNG.
+#[derive(Clone, Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[(self.next() % items.len() as u64) as usize]
+    }
+}
+
+/// Generates a name from a seed.
+///
+/// Names have 2 or 3 syllables and start with an upper-case letter.
+pub fn generate_name(seed: u64) -> String {
+    let mut rng = SplitMix64(seed);
+    let num_syllables = 2 + rng.next() % 2;
+
+    let mut name = String::new();
+    for i in 0..num_syllables {
+        name.push_str(rng.pick(ONSETS));
+        name.push_str(rng.pick(NUCLEI));
+        // only the last syllable gets a coda more often, which reads nicer.
+        if i + 1 == num_syllables || rng.next() % 4 == 0 {
+            name.push_str(rng.pick(CODAS));
+        }
+    }
+
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Generates the name of a star.
+pub fn star_name(star: StarId) -> String {
+    let (high, low) = star.0.as_u64_pair();
+    generate_name(high ^ low)
+}
+
+/// Generates the name of a region.
+///
+/// Regions are recomputed with new IDs, so they're named after a star in them
+/// instead, which keeps the name stable as long as that star stays in the
+/// region.
+pub fn region_name(star: StarId) -> String {
+    let (high, low) = star.0.as_u64_pair();
+    // salted, so the region isn't named like the star.
+    generate_name(high ^ low ^ 0x5265_6769_6f6e_0000)
+}
+
+/// Name of the `index`-th planet of a star, following the convention for
+/// exoplanets: The first planet is "b", the second "c", and so on.
+pub fn planet_name(star_name: &str, index: usize) -> String {
+    let mut suffix = String::new();
+    let mut index = index + 1;
+    loop {
+        suffix.insert(0, char::from(b'a' + (index % 26) as u8));
+        index /= 26;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    format!("{star_name} {suffix}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        generate_name,
+        planet_name,
+    };
+
+    #[test]
+    fn it_generates_the_same_name_for_the_same_seed() {
+        assert_eq!(generate_name(42), generate_name(42));
+        assert_ne!(generate_name(42), generate_name(43));
+    }
+
+    #[test]
+    fn it_capitalizes_names() {
+        for seed in 0..100 {
+            let name = generate_name(seed);
+            assert!(name.chars().next().unwrap().is_uppercase(), "{name}");
+        }
+    }
+
+    #[test]
+    fn it_names_planets_with_letters() {
+        assert_eq!(planet_name("Sol", 0), "Sol b");
+        assert_eq!(planet_name("Sol", 1), "Sol c");
+        assert_eq!(planet_name("Sol", 24), "Sol z");
+        assert_eq!(planet_name("Sol", 25), "Sol aa");
+    }
+}
@@ -0,0 +1,131 @@
+//! Replays of the world's history.
+//!
+//! The server records a [`WorldSnapshot`] every few simulation epochs.
+//! Spectators connect to the websocket at
+//! [`REPLAY_PATH`](crate::endpoints::REPLAY_PATH), on which the server streams
+//! the snapshots as [`ReplayEvent`]s, and controls the playback by sending
+//! [`ReplayControl`]s.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use nalgebra::Point3;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::model::{
+    faction::FactionId,
+    fleet::Fleet,
+    star::StarId,
+};
+
+/// Snapshots per second a replay plays at, unless another speed is requested.
+pub const DEFAULT_SPEED: f32 = 1.0;
+
+/// Maximum snapshots per second a replay plays at.
+pub const MAX_SPEED: f32 = 30.0;
+
+/// A recorded snapshot, without its state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SnapshotInfo {
+    pub epoch: u64,
+    pub taken_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GetSnapshotsResponse {
+    /// Oldest first.
+    pub snapshots: Vec<SnapshotInfo>,
+}
+
+/// State of the world after an epoch, as far as the viewer could see it then.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorldSnapshot {
+    pub epoch: u64,
+    pub taken_at: DateTime<Utc>,
+    pub fleets: Vec<Fleet>,
+    pub colonies: Vec<SnapshotColony>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SnapshotColony {
+    pub faction: FactionId,
+    pub star: StarId,
+    #[cfg_attr(feature = "schema", schemars(with = "crate::schema::Point3"))]
+    pub position: Point3<f32>,
+}
+
+/// Query parameters of the replay websocket.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReplayQuery {
+    /// Epoch to start at. Defaults to the oldest snapshot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+
+    /// Snapshots per second. Defaults to [`DEFAULT_SPEED`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+}
+
+/// Sent by the server on the replay websocket.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ReplayEvent {
+    Snapshot {
+        snapshot: WorldSnapshot,
+    },
+
+    /// The newest snapshot was sent, and the replay paused. Playing continues
+    /// with snapshots that are recorded later.
+    End,
+}
+
+/// Sent by the client on the replay websocket.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ReplayControl {
+    Pause,
+    Play,
+
+    /// Changes the snapshots per second, which are clamped to
+    /// [`MAX_SPEED`].
+    SetSpeed {
+        speed: f32,
+    },
+
+    /// Sends the first snapshot at or after `epoch` right away, and continues
+    /// from there.
+    Seek {
+        epoch: u64,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::ReplayControl;
+
+    #[test]
+    fn controls_are_tagged() {
+        assert_eq!(
+            serde_json::to_value(ReplayControl::SetSpeed { speed: 2.0 }).unwrap(),
+            json!({ "type": "set-speed", "speed": 2.0 })
+        );
+        assert_eq!(
+            serde_json::from_value::<ReplayControl>(json!({ "type": "seek", "epoch": 12 }))
+                .unwrap(),
+            ReplayControl::Seek { epoch: 12 }
+        );
+    }
+}
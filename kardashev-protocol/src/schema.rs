@@ -0,0 +1,288 @@
+//! JSON schemas of the protocol, for clients that aren't written in Rust.
+//!
+//! [`openapi`] describes the HTTP API with an OpenAPI 3.0 document, and
+//! [`session_events`] the [`SessionEvent`]s sent over the `/session`
+//! websocket. [`replay_events`] and [`replay_controls`] describe the messages
+//! in both directions of the `/replay` websocket, and [`star_events`] the
+//! messages of the `/star/events` websocket. `kardashev-cli schema`
+//! writes them all to files.
+//!
+//! The schemas are generated from the serde types, so they can't drift from
+//! what the server actually sends. Types of foreign crates are described by the
+//! stand-ins in this module, which serialize the same way.
+
+use http::Method;
+use schemars::{
+    gen::{
+        SchemaGenerator,
+        SchemaSettings,
+    },
+    schema::{
+        RootSchema,
+        Schema,
+    },
+    JsonSchema,
+};
+use serde_json::{
+    json,
+    Map,
+    Value,
+};
+
+use crate::{
+    endpoints::{
+        self,
+        Endpoint,
+    },
+    model::star::StarEvent,
+    replay::{
+        ReplayControl,
+        ReplayEvent,
+    },
+    SessionEvent,
+    FACTION_HEADER,
+    PROTOCOL_VERSION,
+    WORLD_HEADER,
+};
+
+/// A point, serialized as `[x, y, z]`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+pub struct Point3([f32; 3]);
+
+/// A point, serialized as `[x, y]`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+pub struct Point2([f32; 2]);
+
+/// A unit quaternion, serialized as `[i, j, k, w]`.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+pub struct UnitQuaternion([f32; 4]);
+
+/// A color in linear sRGB.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+pub struct LinSrgb {
+    red: f32,
+    green: f32,
+    blue: f32,
+}
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// Schema of an [`Endpoint`].
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub method: Method,
+
+    /// Path with the parameters in braces, e.g. `/fleet/{id}`.
+    pub path: &'static str,
+    pub summary: &'static str,
+    query: Option<SchemaFn>,
+    request: Option<SchemaFn>,
+    response: Option<SchemaFn>,
+}
+
+impl Route {
+    /// The route of an endpoint.
+    pub fn for_endpoint<E>() -> Self
+    where
+        E: Endpoint,
+        E::Query: JsonSchema,
+        E::Request: JsonSchema,
+        E::Response: JsonSchema,
+    {
+        Self {
+            method: E::METHOD,
+            path: E::PATH,
+            summary: E::SUMMARY,
+            query: E::HAS_QUERY.then_some(E::Query::json_schema as SchemaFn),
+            request: E::HAS_REQUEST
+                .then_some(SchemaGenerator::subschema_for::<E::Request> as SchemaFn),
+            response: E::HAS_RESPONSE
+                .then_some(SchemaGenerator::subschema_for::<E::Response> as SchemaFn),
+        }
+    }
+
+    fn operation(&self, generator: &mut SchemaGenerator) -> Value {
+        let mut parameters = vec![
+            json!({ "$ref": "#/components/parameters/faction" }),
+            json!({ "$ref": "#/components/parameters/world" }),
+        ];
+
+        for name in path_parameters(self.path) {
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string", "format": "uuid" },
+            }));
+        }
+
+        if let Some(query) = self.query {
+            if let Schema::Object(schema) = query(generator) {
+                if let Some(object) = schema.object {
+                    let object = *object;
+                    for (name, schema) in object.properties {
+                        parameters.push(json!({
+                            "name": name,
+                            "in": "query",
+                            "required": object.required.contains(&name),
+                            "schema": schema,
+                        }));
+                    }
+                }
+            }
+        }
+
+        let mut operation = json!({
+            "summary": self.summary,
+            "parameters": parameters,
+        });
+
+        if let Some(request) = self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": request(generator) } },
+            });
+        }
+
+        operation["responses"] = if let Some(response) = self.response {
+            json!({
+                "200": {
+                    "description": "OK",
+                    "content": { "application/json": { "schema": response(generator) } },
+                },
+            })
+        }
+        else {
+            json!({ "2XX": { "description": "Empty response" } })
+        };
+
+        operation
+    }
+}
+
+/// Names of the parameters in a path like `/fleet/{id}/orders`.
+fn path_parameters(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter_map(|segment| {
+        segment
+            .strip_prefix('{')
+            .and_then(|segment| segment.strip_suffix('}'))
+    })
+}
+
+/// All routes of the API, including those of the built-in game rule modules.
+///
+/// The `/session` websocket isn't included, since OpenAPI can't describe it.
+/// Its messages are described by [`session_events`].
+pub fn routes() -> Vec<Route> {
+    endpoints::routes()
+}
+
+/// OpenAPI 3.0 document describing the HTTP API.
+pub fn openapi() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+
+    let mut paths = Map::new();
+    for route in routes() {
+        let operation = route.operation(&mut generator);
+        paths
+            .entry(route.path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .expect("path item is an object")
+            .insert(route.method.as_str().to_lowercase(), operation);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "kardashev",
+            "version": PROTOCOL_VERSION.to_string(),
+        },
+        "servers": [{ "url": "/api" }],
+        "paths": paths,
+        "components": {
+            "schemas": generator.take_definitions(),
+            "parameters": {
+                "faction": {
+                    "name": FACTION_HEADER,
+                    "in": "header",
+                    "required": false,
+                    "description": "Faction on whose behalf the request is made. The server only returns what is visible to it.",
+                    "schema": { "type": "string", "format": "uuid" },
+                },
+                "world": {
+                    "name": WORLD_HEADER,
+                    "in": "header",
+                    "required": false,
+                    "description": "World in which the request is made. Defaults to the default world.",
+                    "schema": { "type": "string", "format": "uuid" },
+                },
+            },
+        },
+    })
+}
+
+/// JSON schema of the messages sent over the `/session` websocket.
+pub fn session_events() -> RootSchema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<SessionEvent>()
+}
+
+/// JSON schema of the messages sent over the `/star/events` websocket.
+pub fn star_events() -> RootSchema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<StarEvent>()
+}
+
+/// JSON schema of the messages the server sends over the `/replay` websocket.
+pub fn replay_events() -> RootSchema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<ReplayEvent>()
+}
+
+/// JSON schema of the messages the client sends over the `/replay` websocket.
+pub fn replay_controls() -> RootSchema {
+    SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<ReplayControl>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        openapi,
+        path_parameters,
+    };
+
+    #[test]
+    fn it_finds_path_parameters() {
+        assert_eq!(
+            path_parameters("/fleet/{id}/orders").collect::<Vec<_>>(),
+            vec!["id"]
+        );
+        assert_eq!(path_parameters("/star/search").count(), 0);
+    }
+
+    #[test]
+    fn it_references_defined_schemas() {
+        let openapi = openapi();
+        let schemas = openapi["components"]["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("Star"));
+        assert!(schemas.contains_key("Point3"));
+
+        let json = serde_json::to_string(&openapi).unwrap();
+        for reference in json.split("\"$ref\":\"").skip(1) {
+            let reference = &reference[..reference.find('"').unwrap()];
+            if let Some(name) = reference.strip_prefix("#/components/schemas/") {
+                assert!(schemas.contains_key(name), "undefined schema {name}");
+            }
+        }
+    }
+}
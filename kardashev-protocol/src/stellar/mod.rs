@@ -0,0 +1,205 @@
+//! Stellar astrophysics shared by the importers and the server.
+//!
+//! Catalogs don't agree on which properties they provide, so the missing ones
+//! are derived with the approximations in here. The server uses the same
+//! functions to audit the stars in the database.
+
+use std::{
+    ops::Range,
+    sync::OnceLock,
+};
+
+use palette::LinSrgb;
+
+/// Absolute visual magnitude of the sun, as used by the HYG catalog.
+pub const SOLAR_ABSOLUTE_MAGNITUDE: f32 = 4.85;
+
+/// Lowest effective temperature in Kelvin, that is considered plausible.
+pub const MIN_EFFECTIVE_TEMPERATURE: f32 = 1_000.0;
+
+/// Highest effective temperature in Kelvin, that is considered plausible.
+pub const MAX_EFFECTIVE_TEMPERATURE: f32 = 100_000.0;
+
+/// Number of entries in the lookup texture of effective temperature colors,
+/// see [`teff_lut`].
+pub const TEFF_LUT_SIZE: usize = 256;
+
+/// The table of effective temperatures and their colors, which is generated by
+/// `kardashev-lab star-colors`.
+fn teff_colors() -> &'static [(f32, LinSrgb)] {
+    static TEFF_COLORS: OnceLock<Vec<(f32, LinSrgb)>> = OnceLock::new();
+    TEFF_COLORS.get_or_init(|| {
+        include_str!("teff-rgb.csv")
+            .lines()
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let columns = line
+                    .split(',')
+                    .map(|column| column.trim().parse::<f32>().expect("invalid teff-rgb.csv"))
+                    .collect::<Vec<_>>();
+                (columns[0], LinSrgb::new(columns[1], columns[2], columns[3]))
+            })
+            .collect()
+    })
+}
+
+/// Color of a black body with the effective temperature `t_eff` in Kelvin.
+///
+/// Interpolates a table, temperatures outside of it are clamped.
+pub fn teff_color(t_eff: f32) -> LinSrgb {
+    let colors = teff_colors();
+    let upper = colors.partition_point(|(t, _)| *t < t_eff);
+
+    if upper == 0 {
+        return colors[0].1;
+    }
+    if upper == colors.len() {
+        return colors[colors.len() - 1].1;
+    }
+
+    let (t_lower, rgb_lower) = colors[upper - 1];
+    let (t_upper, rgb_upper) = colors[upper];
+    let k = (t_eff - t_lower) / (t_upper - t_lower);
+    LinSrgb::new(
+        (1.0 - k) * rgb_lower.red + k * rgb_upper.red,
+        (1.0 - k) * rgb_lower.green + k * rgb_upper.green,
+        (1.0 - k) * rgb_lower.blue + k * rgb_upper.blue,
+    )
+}
+
+/// Effective temperature of the `index`th entry in the [lookup
+/// texture](teff_lut).
+///
+/// The entries are spaced logarithmically between
+/// [`MIN_EFFECTIVE_TEMPERATURE`] and [`MAX_EFFECTIVE_TEMPERATURE`].
+pub fn teff_lut_temperature(index: usize) -> f32 {
+    let k = index as f32 / (TEFF_LUT_SIZE - 1) as f32;
+    MIN_EFFECTIVE_TEMPERATURE * (MAX_EFFECTIVE_TEMPERATURE / MIN_EFFECTIVE_TEMPERATURE).powf(k)
+}
+
+/// Texture coordinate at which the [lookup texture](teff_lut) has the color
+/// for `t_eff`.
+///
+/// This is the center of the texel for temperatures of the entries, so that
+/// linear filtering interpolates between neighbouring entries.
+pub fn teff_lut_coordinate(t_eff: f32) -> f32 {
+    let k = ((t_eff / MIN_EFFECTIVE_TEMPERATURE).ln()
+        / (MAX_EFFECTIVE_TEMPERATURE / MIN_EFFECTIVE_TEMPERATURE).ln())
+    .clamp(0.0, 1.0);
+    (k * (TEFF_LUT_SIZE - 1) as f32 + 0.5) / TEFF_LUT_SIZE as f32
+}
+
+/// Colors for a lookup texture, which shaders sample at
+/// [`teff_lut_coordinate`].
+pub fn teff_lut() -> Vec<LinSrgb> {
+    (0..TEFF_LUT_SIZE)
+        .map(|index| teff_color(teff_lut_temperature(index)))
+        .collect()
+}
+
+/// Mass relative to the sun, from the luminosity relative to the sun.
+pub fn approximate_mass(luminosity: f32) -> f32 {
+    if luminosity < 0.033 {
+        4.3 * luminosity.powf(0.43)
+    }
+    else if luminosity < 16. {
+        luminosity.powf(0.25)
+    }
+    else if luminosity < 1700000. {
+        0.7 * luminosity.powf(0.3)
+    }
+    else {
+        0.000031 * luminosity
+    }
+}
+
+/// Radius relative to the sun, from the mass relative to the sun.
+pub fn approximate_radius(mass: f32) -> f32 {
+    if mass < 1. {
+        mass.powf(0.8)
+    }
+    else {
+        mass.powf(0.5)
+    }
+}
+
+/// Effective temperature in Kelvin, from luminosity and radius relative to the
+/// sun.
+pub fn approximate_teff(luminosity: f32, radius: f32) -> f32 {
+    (luminosity / radius.powi(2)).powf(0.25) * 5778.0
+}
+
+/// Absolute magnitude from the luminosity relative to the sun.
+pub fn absolute_magnitude(luminosity: f32) -> f32 {
+    SOLAR_ABSOLUTE_MAGNITUDE - 2.5 * luminosity.log10()
+}
+
+/// Apparent magnitude of a star at a distance in parsecs.
+pub fn apparent_magnitude(absolute_magnitude: f32, distance: f32) -> f32 {
+    absolute_magnitude + 5.0 * distance.max(0.01).log10() - 5.0
+}
+
+/// Conservative habitable zone in AU, from the luminosity relative to the sun.
+pub fn habitable_zone(luminosity: f32) -> Range<f32> {
+    (luminosity / 1.1).sqrt()..(luminosity / 0.53).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        absolute_magnitude,
+        apparent_magnitude,
+        habitable_zone,
+        teff_color,
+        teff_lut_coordinate,
+        teff_lut_temperature,
+        MAX_EFFECTIVE_TEMPERATURE,
+        MIN_EFFECTIVE_TEMPERATURE,
+        SOLAR_ABSOLUTE_MAGNITUDE,
+        TEFF_LUT_SIZE,
+    };
+
+    #[test]
+    fn it_interpolates_teff_colors() {
+        let below = teff_color(2300.0);
+        let above = teff_color(2400.0);
+        let between = teff_color(2350.0);
+        assert!((between.green - 0.5 * (below.green + above.green)).abs() < 1e-4);
+
+        assert_eq!(
+            teff_color(0.0),
+            teff_color(MIN_EFFECTIVE_TEMPERATURE),
+            "clamped below the table"
+        );
+        assert_eq!(
+            teff_color(1e6),
+            teff_color(MAX_EFFECTIVE_TEMPERATURE),
+            "clamped above"
+        );
+    }
+
+    #[test]
+    fn lut_entries_are_sampled_at_texel_centers() {
+        for index in [0, 1, TEFF_LUT_SIZE / 2, TEFF_LUT_SIZE - 1] {
+            let coordinate = teff_lut_coordinate(teff_lut_temperature(index));
+            let center = (index as f32 + 0.5) / TEFF_LUT_SIZE as f32;
+            assert!((coordinate - center).abs() < 1e-4, "entry {index}");
+        }
+    }
+
+    #[test]
+    fn the_sun_is_the_reference() {
+        assert_eq!(absolute_magnitude(1.0), SOLAR_ABSOLUTE_MAGNITUDE);
+        assert!((absolute_magnitude(100.0) - (SOLAR_ABSOLUTE_MAGNITUDE - 5.0)).abs() < 1e-4);
+
+        assert!(
+            (apparent_magnitude(SOLAR_ABSOLUTE_MAGNITUDE, 10.0) - SOLAR_ABSOLUTE_MAGNITUDE).abs()
+                < 1e-4
+        );
+        assert!((apparent_magnitude(0.0, 100.0) - 5.0).abs() < 1e-4);
+
+        let zone = habitable_zone(1.0);
+        assert!(zone.contains(&1.0), "earth is in the sun's habitable zone");
+    }
+}
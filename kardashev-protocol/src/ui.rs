@@ -0,0 +1,37 @@
+//! Events about builds of the UI and assets, which the server sends to the
+//! browser when it watches their sources.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// Path under which the server streams the events as server-sent events.
+pub const EVENTS_PATH: &str = "ui-events";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Only the stylesheet changed, so it can be swapped without reloading
+    /// the page.
+    StyleChanged,
+
+    /// The UI was rebuilt and must be reloaded.
+    Rebuilt,
+
+    /// The assets were rebuilt.
+    AssetsRebuilt,
+
+    /// A rebuild failed, so the browser still has the last successful build.
+    BuildFailed {
+        target: BuildTarget,
+        message: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildTarget {
+    Ui,
+    Assets,
+}
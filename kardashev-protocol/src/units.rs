@@ -0,0 +1,73 @@
+//! Units of measurement.
+//!
+//! Positions on the star map are in parsecs, as in the HYG catalog the stars
+//! are imported from.
+
+use std::time::Duration;
+
+/// Light years in a parsec.
+pub const LIGHT_YEARS_PER_PARSEC: f32 = 3.261_564;
+
+/// Seconds in a Julian year, which is what light years are defined with.
+pub const SECONDS_PER_YEAR: f64 = 365.25 * 86_400.0;
+
+/// A distance on the star map.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Distance {
+    parsecs: f32,
+}
+
+impl Distance {
+    pub fn from_parsecs(parsecs: f32) -> Self {
+        Self { parsecs }
+    }
+
+    pub fn from_light_years(light_years: f32) -> Self {
+        Self {
+            parsecs: light_years / LIGHT_YEARS_PER_PARSEC,
+        }
+    }
+
+    pub fn parsecs(&self) -> f32 {
+        self.parsecs
+    }
+
+    pub fn light_years(&self) -> f32 {
+        self.parsecs * LIGHT_YEARS_PER_PARSEC
+    }
+
+    /// Time it takes to travel this distance at `speed`, given as a fraction
+    /// of the speed of light.
+    ///
+    /// Returns `None` if `speed` isn't positive.
+    pub fn travel_time(&self, speed: f32) -> Option<Duration> {
+        (speed > 0.0 && speed.is_finite()).then(|| {
+            Duration::from_secs_f64(f64::from(self.light_years() / speed) * SECONDS_PER_YEAR)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Distance,
+        SECONDS_PER_YEAR,
+    };
+
+    #[test]
+    fn it_converts_parsecs_to_light_years() {
+        let distance = Distance::from_parsecs(1.3012);
+        assert!((distance.light_years() - 4.2439).abs() < 1e-3);
+        assert!(
+            (Distance::from_light_years(distance.light_years()).parsecs() - 1.3012).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn it_computes_travel_times() {
+        let distance = Distance::from_light_years(10.0);
+        let years = distance.travel_time(0.5).unwrap().as_secs_f64() / SECONDS_PER_YEAR;
+        assert!((years - 20.0).abs() < 1e-3);
+        assert!(distance.travel_time(0.0).is_none());
+    }
+}
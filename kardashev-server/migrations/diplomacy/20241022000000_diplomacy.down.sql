@@ -0,0 +1,3 @@
+DROP TABLE treaty_proposal;
+DROP TABLE relation;
+DROP TYPE diplomatic_stance;
@@ -0,0 +1,36 @@
+-- relations between factions
+
+CREATE TYPE diplomatic_stance AS ENUM ('war', 'peace', 'alliance');
+
+-- relations are symmetric, so every pair is stored once, with the smaller id
+-- first. factions without a relation are at peace.
+CREATE TABLE relation (
+    faction_a UUID NOT NULL REFERENCES faction(faction_id),
+    faction_b UUID NOT NULL REFERENCES faction(faction_id),
+    stance diplomatic_stance NOT NULL,
+    since TIMESTAMPTZ NOT NULL,
+    -- the treaty that set the stance, if any
+    treaty_signed_at TIMESTAMPTZ,
+    -- game days until the treaty expires. NULL if it doesn't
+    treaty_remaining_days REAL,
+    -- stance the factions return to when the treaty expires
+    treaty_reverts_to diplomatic_stance,
+    PRIMARY KEY (faction_a, faction_b),
+    CHECK (faction_a < faction_b)
+);
+
+CREATE INDEX index_relation_by_faction_b ON relation(faction_b);
+
+
+-- treaties offered to other factions
+
+CREATE TABLE treaty_proposal (
+    from_faction UUID NOT NULL REFERENCES faction(faction_id),
+    to_faction UUID NOT NULL REFERENCES faction(faction_id),
+    stance diplomatic_stance NOT NULL,
+    days INT,
+    proposed_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (from_faction, to_faction)
+);
+
+CREATE INDEX index_treaty_proposal_by_to_faction ON treaty_proposal(to_faction);
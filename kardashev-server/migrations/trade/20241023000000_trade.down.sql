@@ -0,0 +1,4 @@
+DROP TABLE trade_shipment;
+DROP TABLE trade_route;
+DROP TABLE colony_stockpile;
+DROP TYPE trade_resource;
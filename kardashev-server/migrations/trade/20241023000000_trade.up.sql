@@ -0,0 +1,37 @@
+-- trade between colonies
+
+CREATE TYPE trade_resource AS ENUM ('energy', 'metals', 'volatiles');
+
+CREATE TABLE colony_stockpile (
+    colony_id UUID NOT NULL REFERENCES colony(id) ON DELETE CASCADE,
+    resource trade_resource NOT NULL,
+    amount REAL NOT NULL,
+    PRIMARY KEY (colony_id, resource)
+);
+
+CREATE TABLE trade_route (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    from_colony UUID NOT NULL REFERENCES colony(id) ON DELETE CASCADE,
+    to_colony UUID NOT NULL REFERENCES colony(id) ON DELETE CASCADE,
+    resource trade_resource NOT NULL,
+    -- amount shipped per day
+    capacity REAL NOT NULL,
+    travel_days REAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT utc_now(),
+    CHECK (from_colony <> to_colony)
+);
+
+CREATE INDEX index_trade_route_by_faction_id ON trade_route(faction_id);
+
+-- shipments on their way. they still arrive if their route is cancelled.
+CREATE TABLE trade_shipment (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    route_id UUID REFERENCES trade_route(id) ON DELETE SET NULL,
+    to_colony UUID NOT NULL REFERENCES colony(id) ON DELETE CASCADE,
+    resource trade_resource NOT NULL,
+    amount REAL NOT NULL,
+    remaining_days REAL NOT NULL
+);
+
+CREATE INDEX index_trade_shipment_by_route_id ON trade_shipment(route_id);
@@ -0,0 +1,175 @@
+//! Activity on the server, shown on the admin dashboard.
+//!
+//! This is only kept in memory, and shared by all worlds.
+
+use std::{
+    collections::{
+        BTreeMap,
+        VecDeque,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_protocol::model::{
+    faction::FactionId,
+    world::WorldId,
+};
+
+/// How many admin actions are remembered.
+const MAX_RECENT_ACTIONS: usize = 20;
+
+#[derive(Clone, Debug, Default)]
+pub struct Activity {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_session_id: u64,
+    sessions: BTreeMap<u64, ConnectedSession>,
+    recent_actions: VecDeque<AdminAction>,
+}
+
+/// A client connected to the session stream.
+#[derive(Clone, Debug)]
+pub struct ConnectedSession {
+    pub world: WorldId,
+    pub faction: Option<FactionId>,
+    pub connected_since: DateTime<Utc>,
+}
+
+/// An import or maintenance action made through the admin API.
+#[derive(Clone, Debug)]
+pub struct AdminAction {
+    pub world: WorldId,
+    pub at: DateTime<Utc>,
+    pub description: String,
+}
+
+impl Activity {
+    /// Registers a connected session, until the returned guard is dropped.
+    pub fn session_connected(&self, world: WorldId, faction: Option<FactionId>) -> SessionGuard {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_session_id;
+        inner.next_session_id += 1;
+        inner.sessions.insert(
+            id,
+            ConnectedSession {
+                world,
+                faction,
+                connected_since: Utc::now(),
+            },
+        );
+        SessionGuard {
+            activity: self.clone(),
+            id,
+        }
+    }
+
+    /// Returns whether a client of `faction` is connected to `world`.
+    pub fn is_connected(&self, world: WorldId, faction: FactionId) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .sessions
+            .values()
+            .any(|session| session.world == world && session.faction == Some(faction))
+    }
+
+    /// Returns the connected sessions, the oldest first.
+    pub fn sessions(&self) -> Vec<ConnectedSession> {
+        self.inner
+            .lock()
+            .unwrap()
+            .sessions
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Remembers an admin action, forgetting the oldest one if there are too
+    /// many.
+    pub fn record(&self, world: WorldId, description: impl Into<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.recent_actions.len() == MAX_RECENT_ACTIONS {
+            inner.recent_actions.pop_back();
+        }
+        inner.recent_actions.push_front(AdminAction {
+            world,
+            at: Utc::now(),
+            description: description.into(),
+        });
+    }
+
+    /// Returns the recent admin actions, the newest first.
+    pub fn recent_actions(&self) -> Vec<AdminAction> {
+        self.inner
+            .lock()
+            .unwrap()
+            .recent_actions
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Unregisters a session when dropped.
+#[derive(Debug)]
+pub struct SessionGuard {
+    activity: Activity,
+    id: u64,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.activity
+            .inner
+            .lock()
+            .unwrap()
+            .sessions
+            .remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::model::world::WorldId;
+
+    use super::{
+        Activity,
+        MAX_RECENT_ACTIONS,
+    };
+
+    #[test]
+    fn sessions_are_removed_when_disconnected() {
+        let activity = Activity::default();
+        let first = activity.session_connected(WorldId::DEFAULT, None);
+        let _second = activity.session_connected(WorldId::DEFAULT, None);
+        assert_eq!(activity.sessions().len(), 2);
+
+        drop(first);
+        assert_eq!(activity.sessions().len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_the_newest_actions() {
+        let activity = Activity::default();
+        for i in 0..MAX_RECENT_ACTIONS + 5 {
+            activity.record(WorldId::DEFAULT, format!("action {i}"));
+        }
+
+        let actions = activity.recent_actions();
+        assert_eq!(actions.len(), MAX_RECENT_ACTIONS);
+        assert_eq!(
+            actions[0].description,
+            format!("action {}", MAX_RECENT_ACTIONS + 4)
+        );
+    }
+}
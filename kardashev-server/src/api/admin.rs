@@ -1,47 +1,195 @@
 use axum::{
-    extract::State,
-    routing,
+    extract::{
+        Path,
+        Query,
+        Request,
+        State,
+    },
+    http::{
+        header,
+        HeaderMap,
+    },
+    middleware::{
+        self,
+        Next,
+    },
+    response::Response,
     Json,
     Router,
 };
 use kardashev_protocol::{
     admin::{
+        AuditStarsRequest,
+        AuditStarsResponse,
+        CreateBackupResponse,
+        CreateFleetsRequest,
+        CreateFleetsResponse,
+        CreateJournalEntriesRequest,
+        CreateJournalEntriesResponse,
+        CreatePlanetsRequest,
+        CreatePlanetsResponse,
         CreateStarsRequest,
         CreateStarsResponse,
+        CreateWebhookRequest,
+        CreateWorldRequest,
+        CreateWorldResponse,
+        GetAssetStatsResponse,
+        GetBackupsResponse,
+        GetSimulationStateResponse,
+        GetWebhooksResponse,
+        RecomputeImpostorsResponse,
+        RecomputeRegionsResponse,
+        RenameStarRequest,
+        RenameStarResponse,
+        SimulateQuery,
+        SimulateResponse,
+        Webhook,
+        WebhookEvent,
+        WebhookId,
     },
-    model::star::StarId,
+    endpoints,
+    model::{
+        fleet::FleetId,
+        planet::PlanetId,
+        star::{
+            StarEvent,
+            StarId,
+        },
+    },
+    stellar,
+    uuid::Uuid,
+    ADMIN_TOKEN_HEADER,
 };
 
 use crate::{
+    api::{
+        admin_ui,
+        EndpointRouter,
+    },
+    backup,
     context::Context,
     error::Error,
+    impostors,
+    journal,
+    names,
+    regions,
+    simulation,
+    star_audit,
+    stars,
+    state_hash::StateHash,
     util::sqlx::{
         Rgb,
         Vec3,
     },
+    visibility::constant_time_eq,
+    webhooks,
+    worlds::Worlds,
 };
 
-pub fn router() -> Router<Context> {
+/// Name of the cookie with which the dashboard's login authenticates the
+/// browser.
+pub(crate) const ADMIN_TOKEN_COOKIE: &str = "kardashev-admin-token";
+
+/// Builds the admin router.
+///
+/// All its routes need the admin token, so admin routes are disabled if the
+/// server has none.
+pub fn router(worlds: &Worlds) -> Router<Worlds> {
     Router::new()
-        .route("/star", routing::post(create_stars))
-        .route(
-            "/shutdown",
-            routing::get(|State(context): State<Context>| {
-                async move {
-                    context.shutdown.cancel();
-                }
-            }),
-        )
+        .endpoint::<endpoints::CreateStars, _>(create_stars)
+        .endpoint::<endpoints::AuditStars, _>(audit_stars)
+        .endpoint::<endpoints::RenameStar, _>(rename_star)
+        .endpoint::<endpoints::DeleteStar, _>(delete_star)
+        .endpoint::<endpoints::CreatePlanets, _>(create_planets)
+        .endpoint::<endpoints::CreateFleets, _>(create_fleets)
+        .endpoint::<endpoints::CreateJournalEntries, _>(create_journal_entries)
+        .endpoint::<endpoints::RecomputeRegions, _>(recompute_regions)
+        .endpoint::<endpoints::RecomputeImpostors, _>(recompute_impostors)
+        .endpoint::<endpoints::GetAssetStats, _>(get_asset_stats)
+        .endpoint::<endpoints::GetSimulationState, _>(get_simulation_state)
+        .endpoint::<endpoints::Simulate, _>(simulate)
+        .endpoint::<endpoints::CreateWorld, _>(create_world)
+        .endpoint::<endpoints::GetBackups, _>(get_backups)
+        .endpoint::<endpoints::CreateBackup, _>(create_backup)
+        .endpoint::<endpoints::GetWebhooks, _>(get_webhooks)
+        .endpoint::<endpoints::CreateWebhook, _>(create_webhook)
+        .endpoint::<endpoints::DeleteWebhook, _>(delete_webhook)
+        .endpoint::<endpoints::Shutdown, _>(|context: Context| {
+            async move {
+                context.shutdown.cancel();
+            }
+        })
+        .route_layer(middleware::from_fn_with_state(
+            worlds.clone(),
+            require_admin_token,
+        ))
+        .nest("/admin/ui", admin_ui::router(worlds))
+}
+
+/// Rejects requests without the admin token.
+async fn require_admin_token(
+    State(worlds): State<Worlds>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    if !has_admin_token(&worlds, request.headers()) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Checks whether a request has the admin token, either in the
+/// [`ADMIN_TOKEN_HEADER`], or in the [`ADMIN_TOKEN_COOKIE`].
+pub(crate) fn has_admin_token(worlds: &Worlds, headers: &HeaderMap) -> bool {
+    if let Some(header) = headers.get(ADMIN_TOKEN_HEADER) {
+        return is_admin_token(worlds, header.as_bytes());
+    }
+
+    let Some(admin_token) = worlds.default_context().admin_token.as_deref()
+    else {
+        return false;
+    };
+    let cookie_value = admin_token_cookie_value(admin_token);
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .any(|(name, value)| {
+            name == ADMIN_TOKEN_COOKIE
+                && constant_time_eq(value.as_bytes(), cookie_value.as_bytes())
+        })
+}
+
+/// Checks a token against the admin token. No token is valid if the server
+/// has none.
+pub(crate) fn is_admin_token(worlds: &Worlds, token: &[u8]) -> bool {
+    worlds
+        .default_context()
+        .admin_token
+        .as_deref()
+        .map_or(false, |admin_token| {
+            constant_time_eq(token, admin_token.as_bytes())
+        })
+}
+
+/// Encodes a token as hex, so that it's a valid cookie value.
+pub(crate) fn admin_token_cookie_value(token: &str) -> String {
+    token.bytes().map(|byte| format!("{byte:02x}")).collect()
 }
 
 async fn create_stars(
-    State(context): State<Context>,
+    context: Context,
     Json(request): Json<CreateStarsRequest>,
 ) -> Result<Json<CreateStarsResponse>, Error> {
     let mut tx = context.transaction().await?;
 
     let mut star_ids = vec![];
     for star in request.stars {
+        let habitable_zone =
+            (star.luminousity > 0.0).then(|| stellar::habitable_zone(star.luminousity));
+
         let row = sqlx::query!(
             r#"
             INSERT INTO star (
@@ -59,9 +207,12 @@ async fn create_stars(
                 id_hd,
                 id_hr,
                 id_gl,
-                id_bf
+                id_bf,
+                habitable_zone_inner,
+                habitable_zone_outer,
+                variability
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
             RETURNING id
             "#,
             Vec3::from(star.position) as _,
@@ -79,13 +230,528 @@ async fn create_stars(
             star.catalog_ids.hr.map(|id| id as i32),
             star.catalog_ids.gl,
             star.catalog_ids.bf,
+            habitable_zone.as_ref().map(|zone| zone.start),
+            habitable_zone.as_ref().map(|zone| zone.end),
+            star.variability,
         )
         .fetch_one(&mut **tx)
         .await?;
         star_ids.push(StarId(row.id));
     }
 
+    names::name_stars(&mut tx, Some(&star_ids)).await?;
+    let stars = stars::fetch_stars(&mut tx, Some(&star_ids)).await?;
     tx.commit().await?;
 
+    context
+        .star_updates
+        .publish(stars.into_iter().map(|star| StarEvent::Inserted { star }));
+    context
+        .activity
+        .record(context.world, format!("imported {} stars", star_ids.len()));
+
     Ok(Json(CreateStarsResponse { ids: star_ids }))
 }
+
+async fn rename_star(
+    context: Context,
+    Path(id): Path<Uuid>,
+    Json(request): Json<RenameStarRequest>,
+) -> Result<Json<RenameStarResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let previous = sqlx::query!(
+        r#"
+        SELECT name
+        FROM star
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE star
+        SET name = $2, name_generated = FALSE
+        WHERE id = $1
+        "#,
+        id,
+        request.name,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    let stars = stars::fetch_stars(&mut tx, Some(&[StarId(id)])).await?;
+    tx.commit().await?;
+
+    context
+        .star_updates
+        .publish(stars.into_iter().map(|star| StarEvent::Updated { star }));
+    context.activity.record(
+        context.world,
+        format!(
+            "renamed star {} to {}",
+            previous.name.as_deref().unwrap_or("(unnamed)"),
+            request.name.as_deref().unwrap_or("(unnamed)"),
+        ),
+    );
+
+    Ok(Json(RenameStarResponse {
+        previous_name: previous.name,
+    }))
+}
+
+/// Deletes a star.
+///
+/// Its planets are deleted with it, and factions forget that they explored it.
+/// Stars that are still used by colonies or fleet orders are kept.
+async fn delete_star(context: Context, Path(id): Path<Uuid>) -> Result<(), Error> {
+    let mut tx = context.transaction().await?;
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            name,
+            EXISTS (SELECT FROM colony WHERE star_id = $1)
+                OR EXISTS (SELECT FROM fleet_order WHERE star_id = $1) AS "in_use!"
+        FROM star
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    if row.in_use {
+        return Err(Error::StarInUse);
+    }
+
+    sqlx::query!("DELETE FROM explored_star WHERE star_id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query!("UPDATE bookmark SET star_id = NULL WHERE star_id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query!("DELETE FROM star WHERE id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+
+    tx.commit().await?;
+
+    context
+        .star_updates
+        .publish([StarEvent::Deleted { id: StarId(id) }]);
+    context.activity.record(
+        context.world,
+        format!(
+            "deleted star {}",
+            row.name.as_deref().unwrap_or("(unnamed)")
+        ),
+    );
+
+    Ok(())
+}
+
+/// Creates planets, matching them to their host stars.
+///
+/// Host stars are matched by their Hipparcos or Henry Draper ID, or by their
+/// proper name. Planets that already exist are updated, so that catalogs can be
+/// re-imported. Planets without a name are given one.
+async fn create_planets(
+    context: Context,
+    Json(request): Json<CreatePlanetsRequest>,
+) -> Result<Json<CreatePlanetsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let mut planet_ids = vec![];
+    let mut unmatched = vec![];
+    for planet in request.planets {
+        let host = sqlx::query!(
+            r#"
+            SELECT id
+            FROM star
+            WHERE
+                id_hip = $1
+                OR id_hd = $2
+                OR (NOT name_generated AND lower(name) = lower($3))
+            ORDER BY (id_hip = $1) IS TRUE DESC, (id_hd = $2) IS TRUE DESC
+            LIMIT 1
+            "#,
+            planet.host.catalog_ids.hip.map(|id| id as i32),
+            planet.host.catalog_ids.hd.map(|id| id as i32),
+            planet.host.name,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        let Some(host) = host
+        else {
+            unmatched.push(
+                planet
+                    .name
+                    .or(planet.host.name)
+                    .unwrap_or_else(|| "unnamed planet".to_owned()),
+            );
+            continue;
+        };
+
+        let name = match planet.name {
+            Some(name) => name,
+            None => names::next_planet_name(&mut tx, StarId(host.id)).await?,
+        };
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO planet (
+                star_id,
+                name,
+                confirmed,
+                semi_major_axis,
+                eccentricity,
+                orbital_period,
+                mass,
+                radius,
+                equilibrium_temperature,
+                discovery_year
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (star_id, name) DO UPDATE SET
+                confirmed = EXCLUDED.confirmed,
+                semi_major_axis = EXCLUDED.semi_major_axis,
+                eccentricity = EXCLUDED.eccentricity,
+                orbital_period = EXCLUDED.orbital_period,
+                mass = EXCLUDED.mass,
+                radius = EXCLUDED.radius,
+                equilibrium_temperature = EXCLUDED.equilibrium_temperature,
+                discovery_year = EXCLUDED.discovery_year
+            RETURNING id
+            "#,
+            host.id,
+            name,
+            planet.confirmed,
+            planet.semi_major_axis,
+            planet.eccentricity,
+            planet.orbital_period,
+            planet.mass,
+            planet.radius,
+            planet.equilibrium_temperature,
+            planet.discovery_year,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        planet_ids.push(PlanetId(row.id));
+    }
+
+    tx.commit().await?;
+
+    context.activity.record(
+        context.world,
+        format!(
+            "imported {} planets ({} without host star)",
+            planet_ids.len(),
+            unmatched.len()
+        ),
+    );
+
+    Ok(Json(CreatePlanetsResponse {
+        ids: planet_ids,
+        unmatched,
+    }))
+}
+
+/// Audits the stars' derived properties, and optionally fixes them.
+pub(super) async fn audit_stars(
+    context: Context,
+    Json(request): Json<AuditStarsRequest>,
+) -> Result<Json<AuditStarsResponse>, Error> {
+    let response = star_audit::audit(&context, request.fix).await?;
+    if request.fix {
+        context.activity.record(
+            context.world,
+            format!("fixed {} star issues", response.num_fixed),
+        );
+    }
+    Ok(Json(response))
+}
+
+/// Recomputes the regions, e.g. after stars were imported.
+pub(super) async fn recompute_regions(
+    context: Context,
+) -> Result<Json<RecomputeRegionsResponse>, Error> {
+    let num_regions = regions::recompute(&context).await?;
+    context
+        .activity
+        .record(context.world, format!("recomputed {num_regions} regions"));
+    Ok(Json(RecomputeRegionsResponse { num_regions }))
+}
+
+/// Recomputes the impostors for distant stars, e.g. after stars were imported.
+pub(super) async fn recompute_impostors(
+    context: Context,
+) -> Result<Json<RecomputeImpostorsResponse>, Error> {
+    let num_chunks = impostors::recompute(&context).await?;
+    context.activity.record(
+        context.world,
+        format!("recomputed impostors for {num_chunks} chunks"),
+    );
+    Ok(Json(RecomputeImpostorsResponse { num_chunks }))
+}
+
+/// Returns how often the assets were downloaded since the server started.
+async fn get_asset_stats(context: Context) -> Result<Json<GetAssetStatsResponse>, Error> {
+    Ok(Json(context.asset_stats.report().await?))
+}
+
+/// Returns the epoch and the hashes of the simulation state.
+async fn get_simulation_state(
+    State(worlds): State<Worlds>,
+    context: Context,
+) -> Result<Json<GetSimulationStateResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let Some(state) = sqlx::query!("SELECT epoch, epoch_at, state_hash FROM simulation_state")
+        .fetch_optional(&mut **tx)
+        .await?
+    else {
+        return Err(Error::NotFound);
+    };
+
+    let current_state_hash =
+        simulation::hash_state(&mut tx, state.epoch, &worlds.modules().simulation_steps()).await?;
+
+    Ok(Json(GetSimulationStateResponse {
+        epoch: state.epoch,
+        epoch_at: state.epoch_at,
+        epoch_state_hash: state
+            .state_hash
+            .as_deref()
+            .and_then(StateHash::from_slice)
+            .map(|state_hash| state_hash.to_string()),
+        current_state_hash: current_state_hash.to_string(),
+    }))
+}
+
+/// Runs simulation epochs now, if the server allows it.
+async fn simulate(
+    State(worlds): State<Worlds>,
+    context: Context,
+    Query(query): Query<SimulateQuery>,
+) -> Result<Json<SimulateResponse>, Error> {
+    let (epoch, state_hash) = simulation::force_epochs(
+        &context,
+        worlds.simulation(),
+        &worlds.modules().simulation_steps(),
+        query.ticks,
+    )
+    .await?;
+    context.activity.record(
+        context.world,
+        format!("forced {} simulation epochs", query.ticks),
+    );
+    Ok(Json(SimulateResponse {
+        ticks: query.ticks,
+        epoch,
+        state_hash: state_hash.to_string(),
+    }))
+}
+
+/// Returns the backup configuration and the recent backups.
+async fn get_backups(context: Context) -> Json<GetBackupsResponse> {
+    Json(GetBackupsResponse {
+        target: context.backups.target().map(ToString::to_string),
+        interval: context
+            .backups
+            .interval()
+            .map(|interval| interval.as_secs()),
+        backups: context.backups.history(),
+    })
+}
+
+/// Backs up the world now.
+pub(super) async fn create_backup(context: Context) -> Result<Json<CreateBackupResponse>, Error> {
+    let backup = backup::backup(&context).await?;
+    context.activity.record(
+        context.world,
+        format!(
+            "backed up {} tables to {}",
+            backup.tables.len(),
+            backup.name
+        ),
+    );
+    Ok(Json(CreateBackupResponse { backup }))
+}
+
+/// Creates a new, empty world.
+async fn create_world(
+    State(worlds): State<Worlds>,
+    Json(request): Json<CreateWorldRequest>,
+) -> Result<Json<CreateWorldResponse>, Error> {
+    let world = worlds.create(request.name).await?;
+    worlds
+        .default_context()
+        .activity
+        .record(world.id, format!("created world {}", world.name));
+    Ok(Json(CreateWorldResponse { world }))
+}
+
+/// Parses the events stored with a webhook, skipping ones this version
+/// doesn't know.
+fn parse_events(events: &[String]) -> Vec<WebhookEvent> {
+    events
+        .iter()
+        .filter_map(|event| event.parse().ok())
+        .collect()
+}
+
+/// Returns the world's webhooks.
+async fn get_webhooks(context: Context) -> Result<Json<GetWebhooksResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let webhooks =
+        sqlx::query!("SELECT id, url, events, created_at FROM webhook ORDER BY created_at")
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .map(|row| {
+                Webhook {
+                    id: WebhookId(row.id),
+                    url: row.url,
+                    events: parse_events(&row.events),
+                    created_at: row.created_at,
+                }
+            })
+            .collect();
+
+    Ok(Json(GetWebhooksResponse { webhooks }))
+}
+
+/// Adds a webhook to which the world's events are posted.
+async fn create_webhook(
+    context: Context,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<Json<Webhook>, Error> {
+    let url = webhooks::parse_url(&request.url)?;
+
+    let mut events = vec![];
+    let requested = if request.events.is_empty() {
+        WebhookEvent::ALL.to_vec()
+    }
+    else {
+        request.events
+    };
+    for event in requested {
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    let event_names = events
+        .iter()
+        .map(|event| event.as_str().to_owned())
+        .collect::<Vec<_>>();
+
+    let mut tx = context.transaction().await?;
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO webhook (url, events)
+        VALUES ($1, $2)
+        RETURNING id, created_at
+        "#,
+        url.as_str(),
+        &event_names,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    tx.commit().await?;
+
+    context
+        .activity
+        .record(context.world, format!("added webhook {}", row.id));
+
+    Ok(Json(Webhook {
+        id: WebhookId(row.id),
+        url: url.into(),
+        events,
+        created_at: row.created_at,
+    }))
+}
+
+/// Removes a webhook.
+async fn delete_webhook(context: Context, Path(id): Path<Uuid>) -> Result<(), Error> {
+    let mut tx = context.transaction().await?;
+
+    let result = sqlx::query!("DELETE FROM webhook WHERE id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    tx.commit().await?;
+
+    context
+        .activity
+        .record(context.world, format!("removed webhook {id}"));
+
+    Ok(())
+}
+
+async fn create_fleets(
+    context: Context,
+    Json(request): Json<CreateFleetsRequest>,
+) -> Result<Json<CreateFleetsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let mut fleet_ids = vec![];
+    for fleet in request.fleets {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO fleet (name, position, speed, faction_id, sensor_range)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+            fleet.name,
+            Vec3::from(fleet.position) as _,
+            fleet.speed,
+            fleet.faction.map(|faction| faction.0),
+            fleet.sensor_range,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        fleet_ids.push(FleetId(row.id));
+    }
+
+    tx.commit().await?;
+
+    context
+        .activity
+        .record(context.world, format!("created {} fleets", fleet_ids.len()));
+
+    Ok(Json(CreateFleetsResponse { ids: fleet_ids }))
+}
+
+async fn create_journal_entries(
+    context: Context,
+    Json(request): Json<CreateJournalEntriesRequest>,
+) -> Result<Json<CreateJournalEntriesResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let mut entries = vec![];
+    for entry in request.entries {
+        entries.push(journal::append(&mut tx, entry.faction, entry.event).await?);
+    }
+
+    tx.commit().await?;
+
+    let ids = entries.iter().map(|entry| entry.id).collect();
+    context.journal.publish(entries);
+
+    Ok(Json(CreateJournalEntriesResponse { ids }))
+}
@@ -0,0 +1,413 @@
+//! Admin dashboard for operators without CLI access.
+//!
+//! Server-rendered pages under `/admin/ui`, which show the server's status
+//! and activity, and have buttons for the maintenance actions. The actions
+//! redirect back to the dashboard, where their result shows up in the recent
+//! actions.
+//!
+//! The pages need the admin token, which the login form stores in a cookie.
+//! Forms can be submitted from other sites, so requests that aren't
+//! same-origin are rejected.
+
+use askama::Template;
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    http::{
+        header,
+        HeaderMap,
+        Method,
+        StatusCode,
+    },
+    middleware::{
+        self,
+        Next,
+    },
+    response::{
+        Html,
+        IntoResponse,
+        Redirect,
+        Response,
+    },
+    routing,
+    Form,
+    Json,
+    Router,
+};
+use chrono::{
+    TimeDelta,
+    Utc,
+};
+use kardashev_protocol::{
+    admin::AuditStarsRequest,
+    model::world::WorldId,
+    ClientErrorKind,
+};
+use serde::Deserialize;
+
+use crate::{
+    api::admin,
+    context::Context,
+    error::Error,
+    worlds::Worlds,
+};
+
+pub fn router(worlds: &Worlds) -> Router<Worlds> {
+    Router::new()
+        .route("/", routing::get(dashboard))
+        .route("/recompute-regions", routing::post(recompute_regions))
+        .route("/recompute-impostors", routing::post(recompute_impostors))
+        .route("/fix-stars", routing::post(fix_stars))
+        .route("/backup", routing::post(backup))
+        .route("/shutdown", routing::post(shutdown))
+        .route_layer(middleware::from_fn_with_state(
+            worlds.clone(),
+            require_login,
+        ))
+        .route("/login", routing::post(login))
+        .route_layer(middleware::from_fn(reject_cross_origin))
+}
+
+/// Shows the login form instead of the dashboard, and rejects actions, if the
+/// request doesn't have the admin token.
+async fn require_login(
+    State(worlds): State<Worlds>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Error> {
+    if admin::has_admin_token(&worlds, request.headers()) {
+        Ok(next.run(request).await)
+    }
+    else if request.method() == Method::GET {
+        login_page("ui/login", false)
+    }
+    else {
+        Err(Error::Unauthorized)
+    }
+}
+
+/// Rejects requests that a browser made on behalf of another site.
+///
+/// Browsers send `Sec-Fetch-Site` with every request, and older ones at least
+/// send the `Origin` with form submissions, which must match the `Host`.
+/// Requests with neither are not made by a browser, e.g. from the CLI.
+async fn reject_cross_origin(request: Request, next: Next) -> Result<Response, Error> {
+    if request.method() != Method::GET && !is_same_origin(request.headers()) {
+        return Err(Error::CrossOrigin);
+    }
+    Ok(next.run(request).await)
+}
+
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return site == "same-origin" || site == "none";
+    }
+
+    let Some(origin) = headers.get(header::ORIGIN)
+    else {
+        return true;
+    };
+    let origin_host = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+        .map(|(_, host)| host);
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    origin_host.is_some() && origin_host == host
+}
+
+#[derive(Template)]
+#[template(path = "admin/login.html")]
+struct LoginTemplate {
+    /// Where the form is posted to, relative to the page that shows it.
+    action: &'static str,
+    invalid: bool,
+}
+
+fn login_page(action: &'static str, invalid: bool) -> Result<Response, Error> {
+    let page = LoginTemplate { action, invalid }.render()?;
+    Ok((StatusCode::UNAUTHORIZED, Html(page)).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginForm {
+    token: String,
+}
+
+async fn login(
+    State(worlds): State<Worlds>,
+    Form(form): Form<LoginForm>,
+) -> Result<Response, Error> {
+    if !admin::is_admin_token(&worlds, form.token.as_bytes()) {
+        return login_page("login", true);
+    }
+
+    let cookie = format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Strict",
+        admin::ADMIN_TOKEN_COOKIE,
+        admin::admin_token_cookie_value(&form.token),
+    );
+    Ok(([(header::SET_COOKIE, cookie)], Redirect::to("../ui")).into_response())
+}
+
+#[derive(Template)]
+#[template(path = "admin/dashboard.html")]
+struct DashboardTemplate {
+    server_version: String,
+    up_since: String,
+    uptime: String,
+    world: WorldId,
+    worlds: Vec<WorldRow>,
+    counts: Vec<(&'static str, i64)>,
+    asset_requests: u64,
+    asset_mebibytes: f64,
+    sessions: Vec<SessionRow>,
+    recent_actions: Vec<ActionRow>,
+    client_errors: Vec<ClientErrorRow>,
+    client_capabilities: Vec<ClientCapabilityRow>,
+}
+
+struct WorldRow {
+    id: WorldId,
+    name: String,
+    selected: bool,
+}
+
+struct SessionRow {
+    world: String,
+    faction: String,
+    connected_since: String,
+}
+
+struct ActionRow {
+    at: String,
+    world: String,
+    description: String,
+}
+
+struct ClientErrorRow {
+    last_seen: String,
+    count: u64,
+    kind: &'static str,
+    message: String,
+    location: String,
+    client: String,
+    breadcrumbs: Vec<String>,
+}
+
+struct ClientCapabilityRow {
+    last_seen: String,
+    count: u64,
+    backend: String,
+    adapter: String,
+    disabled: Vec<String>,
+}
+
+async fn dashboard(State(worlds): State<Worlds>, context: Context) -> Result<Html<String>, Error> {
+    let all_worlds = worlds.list().await?;
+    let world_name = |world: WorldId| {
+        all_worlds
+            .iter()
+            .find(|w| w.id == world)
+            .map_or_else(|| world.0.to_string(), |w| w.name.clone())
+    };
+
+    let mut tx = context.transaction().await?;
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM star) AS "stars!",
+            (SELECT COUNT(*) FROM planet) AS "planets!",
+            (SELECT COUNT(*) FROM region) AS "regions!",
+            (SELECT COUNT(*) FROM faction) AS "factions!",
+            (SELECT COUNT(*) FROM fleet) AS "fleets!",
+            (SELECT COUNT(*) FROM colony) AS "colonies!"
+        "#
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    tx.commit().await?;
+
+    let asset_stats = context.asset_stats.report().await?;
+
+    let template = DashboardTemplate {
+        server_version: env!("CARGO_PKG_VERSION").to_owned(),
+        up_since: context.up_since.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        uptime: format_uptime(Utc::now() - context.up_since),
+        world: context.world,
+        worlds: all_worlds
+            .iter()
+            .map(|world| {
+                WorldRow {
+                    id: world.id,
+                    name: world.name.clone(),
+                    selected: world.id == context.world,
+                }
+            })
+            .collect(),
+        counts: vec![
+            ("Stars", row.stars),
+            ("Planets", row.planets),
+            ("Regions", row.regions),
+            ("Factions", row.factions),
+            ("Fleets", row.fleets),
+            ("Colonies", row.colonies),
+        ],
+        asset_requests: asset_stats
+            .downloads
+            .iter()
+            .map(|download| download.requests)
+            .sum(),
+        asset_mebibytes: asset_stats
+            .downloads
+            .iter()
+            .map(|download| download.bytes as f64)
+            .sum::<f64>()
+            / (1024.0 * 1024.0),
+        sessions: context
+            .activity
+            .sessions()
+            .into_iter()
+            .map(|session| {
+                SessionRow {
+                    world: world_name(session.world),
+                    faction: session
+                        .faction
+                        .map_or_else(|| "-".to_owned(), |faction| faction.0.to_string()),
+                    connected_since: session.connected_since.format("%H:%M:%S").to_string(),
+                }
+            })
+            .collect(),
+        recent_actions: context
+            .activity
+            .recent_actions()
+            .into_iter()
+            .map(|action| {
+                ActionRow {
+                    at: action.at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    world: world_name(action.world),
+                    description: action.description,
+                }
+            })
+            .collect(),
+        client_errors: context
+            .client_errors
+            .groups()
+            .into_iter()
+            .map(|group| {
+                ClientErrorRow {
+                    last_seen: group.last_seen.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    count: group.count,
+                    kind: match group.kind {
+                        ClientErrorKind::Panic => "Panic",
+                        ClientErrorKind::Error => "Error",
+                    },
+                    message: group.message,
+                    location: group.location.unwrap_or_else(|| "-".to_owned()),
+                    client: match group.latest.user_agent {
+                        Some(user_agent) => {
+                            format!("{} ({user_agent})", group.latest.client_version)
+                        }
+                        None => group.latest.client_version,
+                    },
+                    breadcrumbs: group
+                        .latest
+                        .breadcrumbs
+                        .into_iter()
+                        .map(|breadcrumb| {
+                            format!(
+                                "{} {} {}: {}",
+                                breadcrumb.time.format("%H:%M:%S%.3f"),
+                                breadcrumb.level,
+                                breadcrumb.target,
+                                breadcrumb.message
+                            )
+                        })
+                        .collect(),
+                }
+            })
+            .collect(),
+        client_capabilities: context
+            .client_capabilities
+            .groups()
+            .into_iter()
+            .map(|group| {
+                ClientCapabilityRow {
+                    last_seen: group.last_seen.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    count: group.count,
+                    backend: group.backend,
+                    adapter: group.adapter,
+                    disabled: group
+                        .disabled
+                        .into_iter()
+                        .map(|(feature, reason)| format!("{feature}: {reason}"))
+                        .collect(),
+                }
+            })
+            .collect(),
+    };
+
+    Ok(Html(template.render()?))
+}
+
+/// Redirects from an action back to the dashboard of the world it was run
+/// in.
+///
+/// The location is relative, because the API can be nested anywhere.
+fn back_to_dashboard(context: &Context) -> Redirect {
+    Redirect::to(&format!("../ui?world={}", context.world.0))
+}
+
+async fn recompute_regions(context: Context) -> Result<Redirect, Error> {
+    admin::recompute_regions(context.clone()).await?;
+    Ok(back_to_dashboard(&context))
+}
+
+async fn recompute_impostors(context: Context) -> Result<Redirect, Error> {
+    admin::recompute_impostors(context.clone()).await?;
+    Ok(back_to_dashboard(&context))
+}
+
+async fn fix_stars(context: Context) -> Result<Redirect, Error> {
+    admin::audit_stars(context.clone(), Json(AuditStarsRequest { fix: true })).await?;
+    Ok(back_to_dashboard(&context))
+}
+
+async fn backup(context: Context) -> Result<Redirect, Error> {
+    admin::create_backup(context.clone()).await?;
+    Ok(back_to_dashboard(&context))
+}
+
+async fn shutdown(context: Context) -> &'static str {
+    context.shutdown.cancel();
+    "The server is shutting down."
+}
+
+fn format_uptime(uptime: TimeDelta) -> String {
+    let days = uptime.num_days();
+    let hours = uptime.num_hours() % 24;
+    let minutes = uptime.num_minutes() % 60;
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    }
+    else {
+        format!("{hours}h {minutes}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::format_uptime;
+
+    #[test]
+    fn it_formats_uptime() {
+        assert_eq!(format_uptime(TimeDelta::minutes(75)), "1h 15m");
+        assert_eq!(format_uptime(TimeDelta::hours(49)), "2d 1h 0m");
+    }
+}
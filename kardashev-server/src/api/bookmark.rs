@@ -0,0 +1,123 @@
+use axum::{
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        bookmark::{
+            Bookmark,
+            BookmarkId,
+        },
+        star::StarId,
+    },
+    GetBookmarksResponse,
+    PutBookmarksRequest,
+};
+use nalgebra::{
+    Quaternion,
+    UnitQuaternion,
+};
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    util::sqlx::Vec3,
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetBookmarks, _>(get_bookmarks)
+        .endpoint::<endpoints::PutBookmarks, _>(put_bookmarks)
+}
+
+/// Returns the viewer's bookmarks.
+async fn get_bookmarks(
+    context: Context,
+    viewer: Viewer,
+) -> Result<Json<GetBookmarksResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let bookmarks = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            position AS "position: Vec3",
+            rotation,
+            star_id,
+            updated_at
+        FROM bookmark
+        WHERE faction_id = $1
+        ORDER BY name
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .filter_map(|row| {
+        let [i, j, k, w] = row.rotation[..]
+        else {
+            tracing::warn!(id = %row.id, "bookmark with invalid rotation");
+            return None;
+        };
+        Some(Bookmark {
+            id: BookmarkId(row.id),
+            name: row.name,
+            position: row.position.into(),
+            rotation: UnitQuaternion::from_quaternion(Quaternion::new(w, i, j, k)),
+            star: row.star_id.map(StarId),
+            updated_at: row.updated_at,
+        })
+    })
+    .collect();
+
+    Ok(Json(GetBookmarksResponse { bookmarks }))
+}
+
+/// Replaces the viewer's bookmarks.
+///
+/// The client merges its local bookmarks with the ones returned by
+/// [`get_bookmarks`], and then stores the result with this.
+async fn put_bookmarks(
+    context: Context,
+    viewer: Viewer,
+    Json(request): Json<PutBookmarksRequest>,
+) -> Result<Json<GetBookmarksResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    sqlx::query!("DELETE FROM bookmark WHERE faction_id = $1", faction.0)
+        .execute(&mut **tx)
+        .await?;
+
+    for bookmark in &request.bookmarks {
+        let rotation = bookmark.rotation.coords;
+        sqlx::query!(
+            r#"
+            INSERT INTO bookmark (id, faction_id, name, position, rotation, star_id, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            bookmark.id.0,
+            faction.0,
+            bookmark.name,
+            Vec3::from(bookmark.position) as _,
+            &[rotation.x, rotation.y, rotation.z, rotation.w][..],
+            bookmark.star.map(|star| star.0),
+            bookmark.updated_at,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Json(GetBookmarksResponse {
+        bookmarks: request.bookmarks,
+    }))
+}
@@ -0,0 +1,425 @@
+use axum::{
+    extract::Path,
+    Json,
+    Router,
+};
+use chrono::Utc;
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        diplomacy::{
+            validate_proposal,
+            DiplomacyError,
+            Relation,
+            Stance,
+            Treaty,
+            TreatyProposal,
+        },
+        faction::FactionId,
+    },
+    uuid::Uuid,
+    GetDiplomacyResponse,
+    ProposeTreatyRequest,
+};
+
+use crate::{
+    api::EndpointRouter,
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    visibility::Viewer,
+    webhooks::{
+        self,
+        faction_name,
+        Notification,
+    },
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetDiplomacy, _>(get_diplomacy)
+        .endpoint::<endpoints::DeclareWar, _>(declare_war)
+        .endpoint::<endpoints::ProposeTreaty, _>(propose_treaty)
+        .endpoint::<endpoints::AcceptTreaty, _>(accept_treaty)
+        .endpoint::<endpoints::DeleteProposal, _>(delete_proposal)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "diplomatic_stance", rename_all = "lowercase")]
+pub(crate) enum StanceColumn {
+    War,
+    Peace,
+    Alliance,
+}
+
+impl From<Stance> for StanceColumn {
+    fn from(value: Stance) -> Self {
+        match value {
+            Stance::War => Self::War,
+            Stance::Peace => Self::Peace,
+            Stance::Alliance => Self::Alliance,
+        }
+    }
+}
+
+impl From<StanceColumn> for Stance {
+    fn from(value: StanceColumn) -> Self {
+        match value {
+            StanceColumn::War => Self::War,
+            StanceColumn::Peace => Self::Peace,
+            StanceColumn::Alliance => Self::Alliance,
+        }
+    }
+}
+
+/// Relations are stored once per pair of factions, with the smaller ID first.
+pub(crate) fn ordered(a: FactionId, b: FactionId) -> (Uuid, Uuid) {
+    if a.0 < b.0 {
+        (a.0, b.0)
+    }
+    else {
+        (b.0, a.0)
+    }
+}
+
+/// Returns the relations of `faction` to the factions it has dealt with.
+async fn fetch_relations(
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+) -> Result<Vec<Relation>, Error> {
+    let relations = sqlx::query!(
+        r#"
+        SELECT
+            CASE WHEN faction_a = $1 THEN faction_b ELSE faction_a END AS "faction!",
+            stance AS "stance: StanceColumn",
+            since,
+            treaty_signed_at,
+            treaty_remaining_days,
+            treaty_reverts_to AS "treaty_reverts_to: StanceColumn"
+        FROM relation
+        WHERE faction_a = $1 OR faction_b = $1
+        ORDER BY since
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Relation {
+            faction: FactionId(row.faction),
+            stance: row.stance.into(),
+            since: row.since,
+            treaty: row.treaty_signed_at.map(|signed_at| {
+                Treaty {
+                    signed_at,
+                    remaining_days: row.treaty_remaining_days,
+                    reverts_to: row.treaty_reverts_to.map_or(Stance::Peace, Into::into),
+                }
+            }),
+        }
+    })
+    .collect();
+
+    Ok(relations)
+}
+
+/// Returns the stance between two factions. Factions that never dealt with
+/// each other are at peace.
+async fn fetch_stance(
+    tx: &mut Transaction<'_>,
+    a: FactionId,
+    b: FactionId,
+) -> Result<Stance, Error> {
+    let (faction_a, faction_b) = ordered(a, b);
+    let stance = sqlx::query_scalar!(
+        r#"
+        SELECT stance AS "stance: StanceColumn"
+        FROM relation
+        WHERE faction_a = $1 AND faction_b = $2
+        FOR UPDATE
+        "#,
+        faction_a,
+        faction_b,
+    )
+    .fetch_optional(&mut ***tx)
+    .await?;
+
+    Ok(stance.map_or(Stance::default(), Into::into))
+}
+
+/// Sets the stance between `faction` and `other`, and returns the relation
+/// from the point of view of `faction`.
+async fn set_stance(
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+    other: FactionId,
+    stance: Stance,
+    treaty: Option<Treaty>,
+) -> Result<Relation, Error> {
+    let (faction_a, faction_b) = ordered(faction, other);
+    let since = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO relation (
+            faction_a,
+            faction_b,
+            stance,
+            since,
+            treaty_signed_at,
+            treaty_remaining_days,
+            treaty_reverts_to
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (faction_a, faction_b) DO UPDATE SET
+            stance = EXCLUDED.stance,
+            since = EXCLUDED.since,
+            treaty_signed_at = EXCLUDED.treaty_signed_at,
+            treaty_remaining_days = EXCLUDED.treaty_remaining_days,
+            treaty_reverts_to = EXCLUDED.treaty_reverts_to
+        "#,
+        faction_a,
+        faction_b,
+        StanceColumn::from(stance) as _,
+        since,
+        treaty.as_ref().map(|treaty| treaty.signed_at),
+        treaty.as_ref().and_then(|treaty| treaty.remaining_days),
+        treaty
+            .as_ref()
+            .map(|treaty| StanceColumn::from(treaty.reverts_to)) as _,
+    )
+    .execute(&mut ***tx)
+    .await?;
+
+    // proposals are made for the old stance.
+    delete_proposals(tx, faction, other).await?;
+
+    Ok(Relation {
+        faction: other,
+        stance,
+        since,
+        treaty,
+    })
+}
+
+/// Deletes the proposals between two factions, and returns how many there
+/// were.
+async fn delete_proposals(
+    tx: &mut Transaction<'_>,
+    a: FactionId,
+    b: FactionId,
+) -> Result<u64, Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM treaty_proposal
+        WHERE (from_faction = $1 AND to_faction = $2) OR (from_faction = $2 AND to_faction = $1)
+        "#,
+        a.0,
+        b.0,
+    )
+    .execute(&mut ***tx)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn faction_exists(tx: &mut Transaction<'_>, faction: FactionId) -> Result<bool, Error> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM faction WHERE faction_id = $1) AS "exists!""#,
+        faction.0,
+    )
+    .fetch_one(&mut ***tx)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Returns the viewer's relations, and the treaties it proposed or was
+/// offered.
+async fn get_diplomacy(
+    context: Context,
+    viewer: Viewer,
+) -> Result<Json<GetDiplomacyResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let relations = fetch_relations(&mut tx, faction).await?;
+
+    let proposals = sqlx::query!(
+        r#"
+        SELECT
+            from_faction,
+            to_faction,
+            stance AS "stance: StanceColumn",
+            days,
+            proposed_at
+        FROM treaty_proposal
+        WHERE from_faction = $1 OR to_faction = $1
+        ORDER BY proposed_at
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        TreatyProposal {
+            from: FactionId(row.from_faction),
+            to: FactionId(row.to_faction),
+            stance: row.stance.into(),
+            days: row.days.map(|days| days as u32),
+            proposed_at: row.proposed_at,
+        }
+    })
+    .collect();
+
+    Ok(Json(GetDiplomacyResponse {
+        relations,
+        proposals,
+    }))
+}
+
+/// Declares war on another faction, which ends any treaty with it.
+async fn declare_war(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Relation>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let other = FactionId(id);
+    if faction == other {
+        return Err(DiplomacyError::SameFaction.into());
+    }
+
+    let mut tx = context.transaction().await?;
+    if !faction_exists(&mut tx, other).await? {
+        return Err(Error::NotFound);
+    }
+    if fetch_stance(&mut tx, faction, other).await? == Stance::War {
+        return Err(DiplomacyError::UnchangedStance.into());
+    }
+
+    let relation = set_stance(&mut tx, faction, other, Stance::War, None).await?;
+    let notification = Notification::WarDeclared {
+        aggressor: faction_name(&mut tx, faction).await?,
+        defender: faction_name(&mut tx, other).await?,
+    };
+    tx.commit().await?;
+
+    webhooks::notify(&context, notification);
+
+    Ok(Json(relation))
+}
+
+/// Offers a treaty to another faction, replacing the viewer's previous offer.
+async fn propose_treaty(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ProposeTreatyRequest>,
+) -> Result<Json<TreatyProposal>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let other = FactionId(id);
+
+    let mut tx = context.transaction().await?;
+    if !faction_exists(&mut tx, other).await? {
+        return Err(Error::NotFound);
+    }
+    let current = fetch_stance(&mut tx, faction, other).await?;
+    validate_proposal(faction, other, current, request.stance, request.days)?;
+
+    let proposal = TreatyProposal {
+        from: faction,
+        to: other,
+        stance: request.stance,
+        days: request.days,
+        proposed_at: Utc::now(),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO treaty_proposal (from_faction, to_faction, stance, days, proposed_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (from_faction, to_faction) DO UPDATE SET
+            stance = EXCLUDED.stance,
+            days = EXCLUDED.days,
+            proposed_at = EXCLUDED.proposed_at
+        "#,
+        proposal.from.0,
+        proposal.to.0,
+        StanceColumn::from(proposal.stance) as _,
+        proposal.days.map(|days| days as i32),
+        proposal.proposed_at,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(proposal))
+}
+
+/// Accepts the treaty that another faction offered the viewer.
+async fn accept_treaty(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Relation>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let other = FactionId(id);
+
+    let mut tx = context.transaction().await?;
+    let proposal = sqlx::query!(
+        r#"
+        SELECT
+            stance AS "stance: StanceColumn",
+            days
+        FROM treaty_proposal
+        WHERE from_faction = $1 AND to_faction = $2
+        FOR UPDATE
+        "#,
+        other.0,
+        faction.0,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let stance = proposal.stance.into();
+    let current = fetch_stance(&mut tx, faction, other).await?;
+    if stance == current {
+        return Err(DiplomacyError::UnchangedStance.into());
+    }
+
+    let treaty = Treaty {
+        signed_at: Utc::now(),
+        remaining_days: proposal.days.map(|days| days as f32),
+        reverts_to: current,
+    };
+    let relation = set_stance(&mut tx, faction, other, stance, Some(treaty)).await?;
+    tx.commit().await?;
+
+    Ok(Json(relation))
+}
+
+/// Withdraws the viewer's proposal to another faction, or declines the one
+/// it received from it.
+async fn delete_proposal(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<(), Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+
+    let mut tx = context.transaction().await?;
+    if delete_proposals(&mut tx, faction, FactionId(id)).await? == 0 {
+        return Err(Error::NotFound);
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
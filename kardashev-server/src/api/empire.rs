@@ -0,0 +1,182 @@
+use axum::{
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        empire::{
+            Colony,
+            ColonyId,
+            EmpireSummary,
+            FleetSummary,
+            ResourceSummary,
+        },
+        faction::FactionId,
+        star::StarId,
+        trade::Resource,
+    },
+};
+
+use crate::{
+    api::{
+        trade::ResourceColumn,
+        EndpointRouter,
+    },
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new().endpoint::<endpoints::GetEmpireSummary, _>(get_summary)
+}
+
+/// Returns an overview of the viewer's empire.
+async fn get_summary(context: Context, viewer: Viewer) -> Result<Json<EmpireSummary>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let name = sqlx::query!("SELECT name FROM faction WHERE faction_id = $1", faction.0)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or(Error::NotFound)?
+        .name;
+
+    let colonies = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            star_id,
+            founded_at
+        FROM colony
+        WHERE faction_id = $1
+        ORDER BY founded_at
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Colony {
+            id: ColonyId(row.id),
+            name: row.name,
+            star: StarId(row.star_id),
+            founded_at: row.founded_at,
+        }
+    })
+    .collect();
+
+    let fleets = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "total!",
+            COUNT(*) FILTER (
+                WHERE NOT EXISTS (SELECT 1 FROM fleet_order WHERE fleet_order.fleet_id = fleet.id)
+            ) AS "idle!"
+        FROM fleet
+        WHERE faction_id = $1
+        "#,
+        faction.0,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    let explored_stars = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!" FROM explored_star WHERE faction_id = $1"#,
+        faction.0,
+    )
+    .fetch_one(&mut **tx)
+    .await?
+    .count;
+
+    // the stockpiles are the trade module's.
+    let resources = if context.modules.names().any(|name| name == "trade") {
+        fetch_resources(&context, &mut tx, faction).await?
+    }
+    else {
+        vec![]
+    };
+
+    Ok(Json(EmpireSummary {
+        faction,
+        name,
+        colonies,
+        fleets: FleetSummary {
+            total: fleets.total as u32,
+            idle: fleets.idle as u32,
+        },
+        explored_stars: explored_stars as u32,
+        resources,
+    }))
+}
+
+/// Sums the production and stockpiles of the faction's colonies.
+async fn fetch_resources(
+    context: &Context,
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+) -> Result<Vec<ResourceSummary>, Error> {
+    let mut resources = Resource::ALL.map(|resource| {
+        ResourceSummary {
+            resource,
+            production: 0.0,
+            stockpiled: 0.0,
+        }
+    });
+
+    let colonies = sqlx::query!(
+        r#"
+        SELECT
+            star.luminousity,
+            (SELECT COUNT(*) FROM planet WHERE planet.star_id = colony.star_id) AS "planets!"
+        FROM colony
+        JOIN star ON star.id = colony.star_id
+        WHERE colony.faction_id = $1
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut ***tx)
+    .await?;
+    for colony in colonies {
+        let planets = colony.planets.try_into().unwrap_or(u32::MAX);
+        for summary in &mut resources {
+            summary.production +=
+                context
+                    .rules
+                    .resource_production(summary.resource, colony.luminousity, planets);
+        }
+    }
+
+    let stockpiles = sqlx::query!(
+        r#"
+        SELECT
+            colony_stockpile.resource AS "resource: ResourceColumn",
+            SUM(colony_stockpile.amount) AS "amount!"
+        FROM colony_stockpile
+        JOIN colony ON colony.id = colony_stockpile.colony_id
+        WHERE colony.faction_id = $1
+        GROUP BY colony_stockpile.resource
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut ***tx)
+    .await?;
+    for row in stockpiles {
+        let resource = Resource::from(row.resource);
+        if let Some(summary) = resources
+            .iter_mut()
+            .find(|summary| summary.resource == resource)
+        {
+            summary.stockpiled = row.amount;
+        }
+    }
+
+    Ok(resources.into())
+}
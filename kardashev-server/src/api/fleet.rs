@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Path,
+    Json,
+    Router,
+};
+use chrono::Utc;
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        faction::FactionId,
+        fleet::{
+            predict_orders,
+            Fleet,
+            FleetId,
+            Order,
+            OrderId,
+            OrderKind,
+        },
+        star::StarId,
+    },
+    uuid::Uuid,
+    GetFleetsResponse,
+    SetOrdersRequest,
+    SetOrdersResponse,
+};
+use nalgebra::Point3;
+
+use crate::{
+    api::EndpointRouter,
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    util::sqlx::Vec3,
+    visibility::{
+        Viewer,
+        Visibility,
+    },
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetFleets, _>(get_fleets)
+        .endpoint::<endpoints::GetFleet, _>(get_fleet)
+        .endpoint::<endpoints::SetFleetOrders, _>(set_orders)
+}
+
+#[derive(Clone, Copy, Debug, sqlx::Type)]
+#[sqlx(type_name = "fleet_order_kind", rename_all = "lowercase")]
+enum OrderKindColumn {
+    Move,
+    Survey,
+    Colonize,
+}
+
+impl OrderKindColumn {
+    fn from_order(order: &OrderKind) -> Self {
+        match order {
+            OrderKind::Move { .. } => Self::Move,
+            OrderKind::Survey { .. } => Self::Survey,
+            OrderKind::Colonize { .. } => Self::Colonize,
+        }
+    }
+
+    fn into_order(self, star: StarId) -> OrderKind {
+        match self {
+            Self::Move => OrderKind::Move { star },
+            Self::Survey => OrderKind::Survey { star },
+            Self::Colonize => OrderKind::Colonize { star },
+        }
+    }
+}
+
+/// Returns whether the viewer can see a fleet.
+///
+/// Fleets are visible to their own faction, and to factions that observe
+/// their position.
+pub(crate) fn is_visible(
+    visibility: &Visibility,
+    faction: Option<FactionId>,
+    position: &Point3<f32>,
+) -> bool {
+    visibility.owns(faction) || visibility.is_observed(position)
+}
+
+async fn get_fleets(context: Context, viewer: Viewer) -> Result<Json<GetFleetsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            position AS "position: Vec3",
+            speed,
+            faction_id,
+            sensor_range
+        FROM fleet
+        "#,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let mut orders = fetch_orders(&mut tx, None).await?;
+
+    let fleets = rows
+        .into_iter()
+        .filter_map(|row| {
+            let faction = row.faction_id.map(FactionId);
+            let position = row.position.into();
+            if !is_visible(&visibility, faction, &position) {
+                return None;
+            }
+
+            // only the owner gets to see the orders.
+            let orders = orders.remove(&FleetId(row.id)).unwrap_or_default();
+            Some(Fleet {
+                id: FleetId(row.id),
+                name: row.name,
+                position,
+                speed: row.speed,
+                faction,
+                sensor_range: row.sensor_range,
+                orders: if visibility.owns(faction) {
+                    orders
+                }
+                else {
+                    vec![]
+                },
+            })
+        })
+        .collect();
+
+    Ok(Json(GetFleetsResponse { fleets }))
+}
+
+async fn get_fleet(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Fleet>, Error> {
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            position AS "position: Vec3",
+            speed,
+            faction_id,
+            sensor_range
+        FROM fleet
+        WHERE id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let faction = row.faction_id.map(FactionId);
+    let position = row.position.into();
+    if !is_visible(&visibility, faction, &position) {
+        return Err(Error::NotFound);
+    }
+
+    let orders = if visibility.owns(faction) {
+        fetch_orders(&mut tx, Some(FleetId(id)))
+            .await?
+            .remove(&FleetId(row.id))
+            .unwrap_or_default()
+    }
+    else {
+        vec![]
+    };
+
+    Ok(Json(Fleet {
+        id: FleetId(row.id),
+        name: row.name,
+        position,
+        speed: row.speed,
+        faction,
+        sensor_range: row.sensor_range,
+        orders,
+    }))
+}
+
+/// Fetches the order queues of all fleets, or of a single fleet.
+pub async fn fetch_orders(
+    tx: &mut Transaction<'_>,
+    fleet_id: Option<FleetId>,
+) -> Result<HashMap<FleetId, Vec<Order>>, Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            fleet_id,
+            kind AS "kind: OrderKindColumn",
+            star_id
+        FROM fleet_order
+        WHERE $1::UUID IS NULL OR fleet_id = $1
+        ORDER BY fleet_id, queue_index
+        "#,
+        fleet_id.map(|fleet_id| fleet_id.0),
+    )
+    .fetch_all(&mut ***tx)
+    .await?;
+
+    let mut orders: HashMap<FleetId, Vec<Order>> = HashMap::new();
+    for row in rows {
+        orders
+            .entry(FleetId(row.fleet_id))
+            .or_default()
+            .push(Order {
+                id: OrderId(row.id),
+                kind: row.kind.into_order(StarId(row.star_id)),
+            });
+    }
+
+    Ok(orders)
+}
+
+/// Replaces the order queue of a fleet.
+///
+/// The queue is validated with [`predict_orders`] before it's stored. Only
+/// the faction owning the fleet can give it orders.
+async fn set_orders(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+    Json(request): Json<SetOrdersRequest>,
+) -> Result<Json<SetOrdersResponse>, Error> {
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
+
+    let fleet = sqlx::query!(
+        r#"
+        SELECT
+            position AS "position: Vec3",
+            speed,
+            faction_id
+        FROM fleet
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .filter(|fleet| visibility.owns(fleet.faction_id.map(FactionId)))
+    .ok_or(Error::NotFound)?;
+
+    let star_ids = request
+        .orders
+        .iter()
+        .map(|order| order.star().0)
+        .collect::<Vec<_>>();
+    let star_positions = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3"
+        FROM star
+        WHERE id = ANY($1)
+        "#,
+        &star_ids,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| (StarId(row.id), Point3::from(row.position)))
+    .collect::<HashMap<_, _>>();
+
+    predict_orders(
+        fleet.position.into(),
+        fleet.speed,
+        Utc::now(),
+        &request.orders,
+        |star| star_positions.get(&star).copied(),
+    )?;
+
+    // work done on the current order is lost, if it's replaced.
+    let current_order = fetch_orders(&mut tx, Some(FleetId(id)))
+        .await?
+        .remove(&FleetId(id))
+        .and_then(|orders| orders.into_iter().next())
+        .map(|order| order.kind);
+    if current_order != request.orders.first().copied() {
+        sqlx::query!("UPDATE fleet SET order_work = 0 WHERE id = $1", id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    sqlx::query!("DELETE FROM fleet_order WHERE fleet_id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+
+    let mut orders = Vec::with_capacity(request.orders.len());
+    for (queue_index, order) in request.orders.into_iter().enumerate() {
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO fleet_order (fleet_id, queue_index, kind, star_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+            id,
+            queue_index as i32,
+            OrderKindColumn::from_order(&order) as _,
+            order.star().0,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        orders.push(Order {
+            id: OrderId(row.id),
+            kind: order,
+        });
+    }
+
+    tx.commit().await?;
+
+    tracing::debug!(fleet_id = %id, num_orders = orders.len(), "updated order queue");
+
+    Ok(Json(SetOrdersResponse { orders }))
+}
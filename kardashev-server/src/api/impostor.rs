@@ -0,0 +1,54 @@
+use axum::Json;
+use kardashev_protocol::{
+    model::{
+        chunk::ChunkCoords,
+        impostor::{
+            ImpostorPoint,
+            StarChunk,
+        },
+    },
+    GetStarChunksResponse,
+};
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    context::Context,
+    error::Error,
+};
+
+/// Returns the impostors of all chunks.
+///
+/// Like the stars' positions and colors, impostors can be observed from afar,
+/// so they're not filtered by visibility.
+pub async fn get_star_chunks(context: Context) -> Result<Json<GetStarChunksResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let chunks = sqlx::query!(
+        r#"
+        SELECT
+            x,
+            y,
+            z,
+            num_stars,
+            points AS "points: SqlJson<Vec<ImpostorPoint>>"
+        FROM star_chunk
+        "#
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        StarChunk {
+            coords: ChunkCoords {
+                x: row.x,
+                y: row.y,
+                z: row.z,
+            },
+            num_stars: row.num_stars as u32,
+            points: row.points.0,
+        }
+    })
+    .collect();
+
+    Ok(Json(GetStarChunksResponse { chunks }))
+}
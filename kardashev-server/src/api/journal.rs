@@ -0,0 +1,91 @@
+use axum::{
+    extract::Query,
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints,
+    model::journal::{
+        JournalEntry,
+        JournalEntryId,
+        JournalEvent,
+    },
+    GetJournalQuery,
+    GetJournalResponse,
+};
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    journal::JournalEventKindColumn,
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 200;
+
+pub fn router() -> Router<Worlds> {
+    Router::new().endpoint::<endpoints::GetJournal, _>(get_journal)
+}
+
+/// Returns a page of the viewer's journal, newest entries first.
+async fn get_journal(
+    context: Context,
+    viewer: Viewer,
+    Query(query): Query<GetJournalQuery>,
+) -> Result<Json<GetJournalResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let mut tx = context.transaction().await?;
+
+    // fetch one more entry than requested, to know if there's a next page.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            time,
+            event AS "event: SqlJson<JournalEvent>"
+        FROM journal_entry
+        WHERE
+            faction_id = $1
+            AND ($2::BIGINT IS NULL OR id < $2)
+            AND ($3::journal_event_kind IS NULL OR kind = $3)
+        ORDER BY id DESC
+        LIMIT $4
+        "#,
+        faction.0,
+        query.before.map(|id| id.0),
+        query.kind.map(JournalEventKindColumn::from) as _,
+        i64::from(limit) + 1,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let mut entries = rows
+        .into_iter()
+        .map(|row| {
+            JournalEntry {
+                id: JournalEntryId(row.id),
+                faction,
+                time: row.time,
+                event: row.event.0,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let next = if entries.len() > limit as usize {
+        entries.truncate(limit as usize);
+        entries.last().map(|entry| entry.id)
+    }
+    else {
+        None
+    };
+
+    Ok(Json(GetJournalResponse { entries, next }))
+}
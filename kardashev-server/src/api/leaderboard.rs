@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    Router,
+};
+use chrono::{
+    TimeDelta,
+    Utc,
+};
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        faction::FactionId,
+        leaderboard::{
+            LeaderboardEntry,
+            RatingSample,
+        },
+    },
+    GetLeaderboardResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    worlds::Worlds,
+};
+
+/// How far back the history of ratings goes.
+const HISTORY_DAYS: i64 = 30;
+
+/// Number of samples the history is downsampled to.
+const HISTORY_SAMPLES: i64 = 60;
+
+/// Maximum number of factions on the leaderboard.
+const MAX_ENTRIES: i64 = 100;
+
+pub fn router() -> Router<Worlds> {
+    Router::new().endpoint::<endpoints::GetLeaderboard, _>(get_leaderboard)
+}
+
+/// Returns the highest rated factions' latest ratings, with their recent
+/// history.
+async fn get_leaderboard(context: Context) -> Result<Json<GetLeaderboardResponse>, Error> {
+    let mut tx = context.transaction().await?;
+    let since = Utc::now() - TimeDelta::days(HISTORY_DAYS);
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            latest.faction_id AS "faction_id!",
+            faction.name,
+            latest.computed_at AS "computed_at!",
+            latest.power AS "power!",
+            latest.rating AS "rating!"
+        FROM (
+            SELECT DISTINCT ON (faction_id) faction_id, computed_at, power, rating
+            FROM kardashev_rating
+            WHERE computed_at >= $1
+            ORDER BY faction_id, computed_at DESC
+        ) AS latest
+        JOIN faction ON faction.faction_id = latest.faction_id
+        ORDER BY latest.rating DESC, latest.faction_id
+        LIMIT $2
+        "#,
+        since,
+        MAX_ENTRIES,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let computed_at = rows.iter().map(|row| row.computed_at).max();
+    let faction_ids = rows.iter().map(|row| row.faction_id).collect::<Vec<Uuid>>();
+
+    // the latest rating in each period, so that the history doesn't grow with
+    // the number of times the ratings were computed.
+    let period = (HISTORY_DAYS * 24 * 60 * 60) as f64 / HISTORY_SAMPLES as f64;
+    let samples = sqlx::query!(
+        r#"
+        SELECT DISTINCT ON (faction_id, period)
+            faction_id AS "faction_id!",
+            computed_at AS "computed_at!",
+            rating AS "rating!"
+        FROM (
+            SELECT
+                faction_id,
+                computed_at,
+                rating,
+                FLOOR(EXTRACT(EPOCH FROM computed_at - $1)::DOUBLE PRECISION / $2) AS period
+            FROM kardashev_rating
+            WHERE computed_at >= $1 AND faction_id = ANY($3)
+        ) AS samples
+        ORDER BY faction_id, period, computed_at DESC
+        "#,
+        since,
+        period,
+        &faction_ids,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let mut histories: HashMap<FactionId, Vec<RatingSample>> = HashMap::new();
+    for sample in samples {
+        // samples are ordered by period, so the history is oldest first.
+        histories
+            .entry(FactionId(sample.faction_id))
+            .or_default()
+            .push(RatingSample {
+                computed_at: sample.computed_at,
+                rating: sample.rating,
+            });
+    }
+
+    let entries = rows
+        .into_iter()
+        .map(|row| {
+            let faction = FactionId(row.faction_id);
+            LeaderboardEntry {
+                faction,
+                name: row.name,
+                power: row.power,
+                rating: row.rating,
+                history: histories.remove(&faction).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    Ok(Json(GetLeaderboardResponse {
+        entries,
+        computed_at,
+    }))
+}
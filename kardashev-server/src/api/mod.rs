@@ -1,107 +1,272 @@
 pub mod admin;
+pub mod admin_ui;
+pub mod bookmark;
+pub mod diplomacy;
+pub mod empire;
+pub mod fleet;
+pub mod impostor;
+pub mod journal;
+pub mod leaderboard;
+pub mod planet;
+pub mod region;
+pub mod replay;
+pub mod search;
+pub mod session;
+pub mod settings;
+pub mod star_events;
+pub mod trade;
 
 use axum::{
     extract::State,
+    handler::Handler,
     http::StatusCode,
     response::{
         IntoResponse,
         Response,
     },
-    routing,
+    routing::{
+        self,
+        MethodFilter,
+    },
     Json,
     Router,
 };
+use chrono::Utc;
 use kardashev_protocol::{
-    model::star::{
-        CatalogIds,
-        Star,
-        StarId,
+    endpoints::{
+        self,
+        Endpoint,
+        SESSION_PATH,
+        STAR_EVENTS_PATH,
     },
+    ClientCapabilityReport,
+    ClientErrorReport,
     GetStarsResponse,
+    GetWorldsResponse,
     ServerStatus,
 };
 
 use crate::{
     context::Context,
     error::Error,
-    util::sqlx::{
-        Rgb,
-        Vec3,
+    modules::Modules,
+    stars,
+    visibility::{
+        Viewer,
+        Visibility,
     },
+    worlds::Worlds,
 };
 
-pub fn router() -> Router<Context> {
+/// Builds the API router, with the routes of the modules.
+pub fn router(modules: &Modules, worlds: &Worlds) -> Router<Worlds> {
     Router::new()
-        .route("/status", routing::get(get_status))
-        .nest("/admin", admin::router())
-        .route("/star", routing::get(get_stars))
+        .endpoint::<endpoints::GetStatus, _>(get_status)
+        .endpoint::<endpoints::GetWorlds, _>(get_worlds)
+        .endpoint::<endpoints::ReportClientError, _>(report_client_error)
+        .endpoint::<endpoints::ReportClientCapabilities, _>(report_client_capabilities)
+        .merge(admin::router(worlds))
+        .endpoint::<endpoints::GetStars, _>(get_stars)
+        .route(STAR_EVENTS_PATH, routing::get(star_events::star_events))
+        .endpoint::<endpoints::SearchStars, _>(search::search_stars)
+        .endpoint::<endpoints::GetStarChunks, _>(impostor::get_star_chunks)
+        .endpoint::<endpoints::GetPlanets, _>(planet::get_planets)
+        .endpoint::<endpoints::GetRegions, _>(region::get_regions)
+        .merge(leaderboard::router())
+        .merge(journal::router())
+        .route(SESSION_PATH, routing::get(session::session))
+        .merge(bookmark::router())
+        .merge(settings::router())
+        .merge(replay::router())
+        .merge(modules.router())
+}
+
+/// Registers handlers for [`Endpoint`]s, so that they're served at the path
+/// and with the method the client uses.
+pub(crate) trait EndpointRouter {
+    fn endpoint<E: Endpoint, T: 'static>(self, handler: impl Handler<T, Worlds>) -> Self;
+}
+
+impl EndpointRouter for Router<Worlds> {
+    fn endpoint<E: Endpoint, T: 'static>(self, handler: impl Handler<T, Worlds>) -> Self {
+        let method = MethodFilter::try_from(E::METHOD).expect("unsupported endpoint method");
+        self.route(&axum_path(E::PATH), routing::on(method, handler))
+    }
+}
+
+/// Converts an endpoint's path like `/fleet/{id}` to axum's syntax
+/// `/fleet/:id`.
+fn axum_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            match segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'))
+            {
+                Some(name) => format!(":{name}"),
+                None => segment.to_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        tracing::error!(error = ?self, "Internal server error");
-        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+        match self {
+            Error::NotFound => StatusCode::NOT_FOUND.into_response(),
+            Error::Unauthorized => {
+                (StatusCode::UNAUTHORIZED, "invalid admin token").into_response()
+            }
+            Error::CrossOrigin => {
+                (
+                    StatusCode::FORBIDDEN,
+                    "cross-origin requests are not allowed",
+                )
+                    .into_response()
+            }
+            Error::NoFaction => {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "request must be made on behalf of a faction",
+                )
+                    .into_response()
+            }
+            Error::InvalidWorld => (StatusCode::BAD_REQUEST, "invalid world").into_response(),
+            Error::UnknownWorld => (StatusCode::NOT_FOUND, "unknown world").into_response(),
+            Error::WorldExists => {
+                (StatusCode::CONFLICT, "a world with this name exists").into_response()
+            }
+            Error::StarInUse => {
+                (
+                    StatusCode::CONFLICT,
+                    "the star has colonies or fleets ordered to it",
+                )
+                    .into_response()
+            }
+            Error::BackupsDisabled => {
+                (StatusCode::CONFLICT, "backups are not configured").into_response()
+            }
+            Error::BackupRunning => {
+                (StatusCode::CONFLICT, "another backup is running").into_response()
+            }
+            Error::ForcedTicksDisabled => {
+                (
+                    StatusCode::FORBIDDEN,
+                    "forcing simulation ticks is disabled",
+                )
+                    .into_response()
+            }
+            Error::TooManyTicks { max } => {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("the number of ticks must be between 1 and {max}"),
+                )
+                    .into_response()
+            }
+            Error::InvalidWebhookUrl => {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "webhooks need an http or https URL",
+                )
+                    .into_response()
+            }
+            Error::InvalidOrders(error) => {
+                (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+            }
+            Error::InvalidDiplomacy(error) => {
+                (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+            }
+            Error::InvalidTradeRoute(error) => {
+                (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+            }
+            _ => {
+                tracing::error!(error = ?self, "Internal server error");
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+            }
+        }
     }
 }
 
-async fn get_status(State(context): State<Context>) -> Json<ServerStatus> {
-    Json(ServerStatus {
+async fn get_status(context: Context) -> Result<Json<ServerStatus>, Error> {
+    let mut tx = context.transaction().await?;
+    let epoch = sqlx::query_scalar!("SELECT epoch FROM simulation_state")
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    Ok(Json(ServerStatus {
         server_version: semver_macro::env_version!("CARGO_PKG_VERSION"),
         up_since: context.up_since,
-    })
+        time: Some(Utc::now()),
+        epoch,
+    }))
 }
 
-async fn get_stars(State(context): State<Context>) -> Result<Json<GetStarsResponse>, Error> {
+/// Returns the worlds hosted by the server.
+async fn get_worlds(State(worlds): State<Worlds>) -> Result<Json<GetWorldsResponse>, Error> {
+    Ok(Json(GetWorldsResponse {
+        worlds: worlds.list().await?,
+    }))
+}
+
+/// Receives a crash or error report from a client.
+///
+/// Reports are grouped and shown on the admin dashboard.
+async fn report_client_error(
+    context: Context,
+    Json(report): Json<ClientErrorReport>,
+) -> StatusCode {
+    tracing::warn!(
+        kind = ?report.kind,
+        message = %report.message,
+        location = ?report.location,
+        client_version = %report.client_version,
+        "client error reported"
+    );
+    context.client_errors.record(report);
+    StatusCode::NO_CONTENT
+}
+
+/// Receives a report of a client's graphics capabilities.
+///
+/// Reports are grouped and shown on the admin dashboard.
+async fn report_client_capabilities(
+    context: Context,
+    Json(report): Json<ClientCapabilityReport>,
+) -> StatusCode {
+    tracing::debug!(
+        backend = %report.backend,
+        adapter = %report.adapter,
+        disabled = ?report.disabled,
+        "client capabilities reported"
+    );
+    context.client_capabilities.record(report);
+    StatusCode::NO_CONTENT
+}
+
+/// Returns all stars.
+///
+/// Stars that the viewer hasn't explored and can't observe are returned
+/// without their names and catalog IDs.
+async fn get_stars(context: Context, viewer: Viewer) -> Result<Json<GetStarsResponse>, Error> {
     let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
 
-    let stars = sqlx::query!(
-        r#"
-        SELECT
-            id,
-            position AS "position: Vec3",
-            effective_temperature,
-            color AS "color: Rgb",
-            absolute_magnitude,
-            luminousity,
-            radius,
-            mass,
-            spectral_type,
-            name,
-            id_hyg,
-            id_hip,
-            id_hd,
-            id_hr,
-            id_gl,
-            id_bf
-        FROM star
-        "#,
-    )
-    .fetch_all(&mut **tx)
-    .await?
-    .into_iter()
-    .map(|row| {
-        Star {
-            id: StarId(row.id),
-            position: row.position.into(),
-            effective_temperature: row.effective_temperature,
-            color: row.color.into(),
-            absolute_magnitude: row.absolute_magnitude,
-            luminousity: row.luminousity,
-            radius: row.radius,
-            mass: row.mass,
-            spectral_type: row.spectral_type,
-            name: row.name,
-            catalog_ids: CatalogIds {
-                hyg: row.id_hyg.map(|id| id as u32),
-                hip: row.id_hip.map(|id| id as u32),
-                hd: row.id_hd.map(|id| id as u32),
-                hr: row.id_hr.map(|id| id as u32),
-                gl: row.id_gl,
-                bf: row.id_bf,
-            },
-        }
-    })
-    .collect();
+    let mut stars = stars::fetch_stars(&mut tx, None).await?;
+    for star in &mut stars {
+        visibility.filter_star(star);
+    }
 
     Ok(Json(GetStarsResponse { stars }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::axum_path;
+
+    #[test]
+    fn it_converts_path_parameters() {
+        assert_eq!(axum_path("/fleet/{id}/orders"), "/fleet/:id/orders");
+        assert_eq!(axum_path("/star/search"), "/star/search");
+    }
+}
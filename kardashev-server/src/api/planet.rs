@@ -0,0 +1,98 @@
+use axum::{
+    extract::Path,
+    Json,
+};
+use kardashev_protocol::{
+    model::{
+        planet::{
+            Planet,
+            PlanetId,
+        },
+        star::{
+            StarId,
+            StarVisibility,
+        },
+    },
+    GetPlanetsResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    context::Context,
+    error::Error,
+    util::sqlx::Vec3,
+    visibility::{
+        Viewer,
+        Visibility,
+    },
+};
+
+/// Returns the planets of a star.
+///
+/// Planets can't be observed from afar, so the star must have been explored by
+/// the viewer. Otherwise no planets are returned.
+pub async fn get_planets(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<Json<GetPlanetsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
+
+    let star = sqlx::query!(
+        r#"
+        SELECT position AS "position: Vec3"
+        FROM star
+        WHERE id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    if visibility.star_visibility(StarId(id), &star.position.into()) == StarVisibility::Unexplored {
+        return Ok(Json(GetPlanetsResponse { planets: vec![] }));
+    }
+
+    let planets = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            confirmed,
+            semi_major_axis,
+            eccentricity,
+            orbital_period,
+            mass,
+            radius,
+            equilibrium_temperature,
+            discovery_year
+        FROM planet
+        WHERE star_id = $1
+        ORDER BY semi_major_axis ASC NULLS LAST, name ASC
+        "#,
+        id,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Planet {
+            id: PlanetId(row.id),
+            star: StarId(id),
+            name: row.name,
+            confirmed: row.confirmed,
+            semi_major_axis: row.semi_major_axis,
+            eccentricity: row.eccentricity,
+            orbital_period: row.orbital_period,
+            mass: row.mass,
+            radius: row.radius,
+            equilibrium_temperature: row.equilibrium_temperature,
+            discovery_year: row.discovery_year,
+        }
+    })
+    .collect();
+
+    Ok(Json(GetPlanetsResponse { planets }))
+}
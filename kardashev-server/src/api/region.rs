@@ -0,0 +1,56 @@
+use axum::Json;
+use kardashev_protocol::{
+    model::region::{
+        Region,
+        RegionId,
+    },
+    GetRegionsResponse,
+};
+use nalgebra::Point2;
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    context::Context,
+    error::Error,
+    util::sqlx::Vec3,
+};
+
+/// Returns all regions.
+///
+/// Regions are public knowledge, so they're not filtered by visibility.
+pub async fn get_regions(context: Context) -> Result<Json<GetRegionsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+
+    let regions = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            center AS "center: Vec3",
+            hull AS "hull: SqlJson<Vec<[f32; 2]>>",
+            num_stars
+        FROM region
+        ORDER BY name
+        "#
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Region {
+            id: RegionId(row.id),
+            name: row.name,
+            center: row.center.into(),
+            hull: row
+                .hull
+                .0
+                .into_iter()
+                .map(|[x, z]| Point2::new(x, z))
+                .collect(),
+            num_stars: row.num_stars as u32,
+        }
+    })
+    .collect();
+
+    Ok(Json(GetRegionsResponse { regions }))
+}
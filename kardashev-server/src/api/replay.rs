@@ -0,0 +1,164 @@
+//! Replays of the recorded [snapshots](crate::replay).
+
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
+        Query,
+        WebSocketUpgrade,
+    },
+    response::Response,
+    routing,
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints::{
+        self,
+        REPLAY_PATH,
+    },
+    replay::{
+        GetSnapshotsResponse,
+        ReplayControl,
+        ReplayEvent,
+        ReplayQuery,
+        DEFAULT_SPEED,
+        MAX_SPEED,
+    },
+};
+use tokio::time::{
+    Instant,
+    Interval,
+    MissedTickBehavior,
+};
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    replay::{
+        list_snapshots,
+        load_snapshot,
+    },
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+/// Slowest replay speed in snapshots per second.
+const MIN_SPEED: f32 = 0.1;
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetSnapshots, _>(get_snapshots)
+        .route(REPLAY_PATH, routing::get(replay))
+}
+
+/// Returns the snapshots that can be replayed.
+async fn get_snapshots(context: Context) -> Result<Json<GetSnapshotsResponse>, Error> {
+    let mut tx = context.transaction().await?;
+    let snapshots = list_snapshots(&mut tx).await?;
+    Ok(Json(GetSnapshotsResponse { snapshots }))
+}
+
+/// Upgrades to a websocket on which the snapshots are streamed as
+/// [`ReplayEvent`]s, starting at the query's epoch.
+async fn replay(
+    context: Context,
+    viewer: Viewer,
+    Query(query): Query<ReplayQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade.on_upgrade(move |socket| run_replay(context, viewer, query, socket))
+}
+
+async fn run_replay(context: Context, viewer: Viewer, query: ReplayQuery, mut socket: WebSocket) {
+    let mut next_epoch = query.from.unwrap_or_default();
+    let mut playing = true;
+    let mut interval = playback_interval(query.speed.unwrap_or(DEFAULT_SPEED));
+    interval.reset_immediately();
+
+    loop {
+        let send_next = tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    None | Some(Err(_)) => break,
+                };
+                match serde_json::from_str::<ReplayControl>(&text) {
+                    Ok(ReplayControl::Pause) => {
+                        playing = false;
+                        false
+                    }
+                    Ok(ReplayControl::Play) => {
+                        playing = true;
+                        interval.reset();
+                        false
+                    }
+                    Ok(ReplayControl::SetSpeed { speed }) => {
+                        interval = playback_interval(speed);
+                        false
+                    }
+                    Ok(ReplayControl::Seek { epoch }) => {
+                        // the snapshot is shown right away, even when paused.
+                        next_epoch = epoch;
+                        interval.reset();
+                        true
+                    }
+                    Err(error) => {
+                        tracing::debug!(%error, "invalid replay control");
+                        false
+                    }
+                }
+            }
+            _ = interval.tick(), if playing => true,
+        };
+        if !send_next {
+            continue;
+        }
+
+        let result = async {
+            let mut tx = context.transaction().await?;
+            load_snapshot(&mut tx, &viewer, next_epoch).await
+        }
+        .await;
+        let event = match result {
+            Ok(Some(snapshot)) => {
+                next_epoch = snapshot.epoch + 1;
+                ReplayEvent::Snapshot { snapshot }
+            }
+            Ok(None) => {
+                playing = false;
+                ReplayEvent::End
+            }
+            Err(error) => {
+                tracing::error!(?error, "failed to load snapshot");
+                break;
+            }
+        };
+
+        let message = serde_json::to_string(&event).expect("failed to serialize replay event");
+        if socket.send(Message::Text(message)).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Interval between snapshots at `speed` snapshots per second.
+fn playback_interval(speed: f32) -> Interval {
+    let speed = if speed.is_finite() {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    }
+    else {
+        DEFAULT_SPEED
+    };
+    let period = Duration::from_secs_f32(1.0 / speed);
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
@@ -0,0 +1,321 @@
+use axum::{
+    extract::Query,
+    Json,
+};
+use kardashev_protocol::{
+    frames::{
+        Equatorial,
+        Galactic,
+    },
+    model::star::{
+        CatalogIds,
+        StarId,
+        StarSearchResult,
+        StarVisibility,
+    },
+    uuid::Uuid,
+    SearchStarsQuery,
+    SearchStarsResponse,
+};
+use nalgebra::Vector3;
+
+use crate::{
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    util::sqlx::Vec3,
+    visibility::{
+        Viewer,
+        Visibility,
+    },
+};
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+
+/// Angle in degrees within which stars are found by a [`DirectionQuery`].
+const MAX_SEPARATION: f64 = 1.0;
+
+/// A search for a catalog ID, e.g. `HIP 32349`.
+#[derive(Debug, Default)]
+struct CatalogQuery {
+    hyg: Option<i32>,
+    hip: Option<i32>,
+    hd: Option<i32>,
+    hr: Option<i32>,
+    gl: Option<String>,
+}
+
+impl CatalogQuery {
+    /// Parses a catalog designation. The catalog prefix is case-insensitive.
+    fn parse(query: &str) -> Option<Self> {
+        let (catalog, id) = query.trim().split_once(char::is_whitespace)?;
+        let id = id.trim();
+        let number = || id.parse::<i32>().ok();
+
+        let mut query = Self::default();
+        match catalog.to_lowercase().as_str() {
+            "hyg" => query.hyg = Some(number()?),
+            "hip" => query.hip = Some(number()?),
+            "hd" => query.hd = Some(number()?),
+            "hr" => query.hr = Some(number()?),
+            "gl" | "gj" => query.gl = Some(id.to_owned()),
+            _ => return None,
+        }
+        Some(query)
+    }
+}
+
+/// A search for stars in a direction from the sun, given in degrees as
+/// galactic (`gal 120.5 -3.2`) or equatorial (`eq 101.3 -16.7`) coordinates.
+#[derive(Debug)]
+struct DirectionQuery {
+    /// Unit vector in the game's frame.
+    direction: Vector3<f32>,
+}
+
+impl DirectionQuery {
+    fn parse(query: &str) -> Option<Self> {
+        let mut parts = query.split_whitespace();
+        let frame = parts.next()?.to_lowercase();
+        let mut angle = || parts.next()?.parse::<f32>().ok().map(f32::to_radians);
+        let (longitude, latitude) = (angle()?, angle()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let position = match frame.as_str() {
+            "gal" | "galactic" => {
+                Galactic {
+                    longitude,
+                    latitude,
+                    distance: 1.0,
+                }
+                .to_game()
+            }
+            "eq" | "equatorial" => {
+                Equatorial {
+                    right_ascension: longitude,
+                    declination: latitude,
+                    distance: 1.0,
+                }
+                .to_game()
+            }
+            _ => return None,
+        };
+        Some(Self {
+            direction: position.coords,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct SearchRow {
+    id: Uuid,
+    position: Vec3,
+    name: Option<String>,
+    id_hyg: Option<i32>,
+    id_hip: Option<i32>,
+    id_hd: Option<i32>,
+    id_hr: Option<i32>,
+    id_gl: Option<String>,
+    id_bf: Option<String>,
+}
+
+impl From<SearchRow> for StarSearchResult {
+    fn from(row: SearchRow) -> Self {
+        StarSearchResult {
+            id: StarId(row.id),
+            position: row.position.into(),
+            name: row.name,
+            catalog_ids: CatalogIds {
+                hyg: row.id_hyg.map(|id| id as u32),
+                hip: row.id_hip.map(|id| id as u32),
+                hd: row.id_hd.map(|id| id as u32),
+                hr: row.id_hr.map(|id| id as u32),
+                gl: row.id_gl,
+                bf: row.id_bf,
+            },
+        }
+    }
+}
+
+/// Escapes the wildcards of a `LIKE` pattern.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Searches stars by name, Bayer/Flamsteed designation or catalog ID, or by
+/// their direction from the sun.
+///
+/// Stars the viewer hasn't explored are not found, as their names aren't
+/// known. Since that depends on the viewer's sensors, matches are fetched
+/// page by page until enough of them are known to the viewer.
+pub async fn search_stars(
+    context: Context,
+    viewer: Viewer,
+    Query(query): Query<SearchStarsQuery>,
+) -> Result<Json<SearchStarsResponse>, Error> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Ok(Json(SearchStarsResponse { results: vec![] }));
+    }
+
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, &viewer, &context.modules).await?;
+    if matches!(visibility, Visibility::Restricted) {
+        // nothing is explored.
+        return Ok(Json(SearchStarsResponse { results: vec![] }));
+    }
+
+    let search = if let Some(direction_query) = DirectionQuery::parse(q) {
+        Search::Direction(direction_query)
+    }
+    else {
+        Search::Text {
+            catalog_query: CatalogQuery::parse(q),
+            q: q.to_owned(),
+        }
+    };
+
+    let mut results = Vec::with_capacity(limit);
+    let mut offset = 0;
+    loop {
+        let rows = search.fetch_page(&mut tx, limit, offset).await?;
+        let num_rows = rows.len();
+        results.extend(
+            rows.into_iter()
+                .map(StarSearchResult::from)
+                .filter(|result| {
+                    visibility.star_visibility(result.id, &result.position)
+                        != StarVisibility::Unexplored
+                }),
+        );
+
+        if results.len() >= limit || num_rows < limit {
+            break;
+        }
+        offset += limit;
+    }
+    results.truncate(limit);
+
+    Ok(Json(SearchStarsResponse { results }))
+}
+
+#[derive(Debug)]
+enum Search {
+    Direction(DirectionQuery),
+    Text {
+        catalog_query: Option<CatalogQuery>,
+        q: String,
+    },
+}
+
+impl Search {
+    /// Fetches the matches from `offset` on, best first.
+    async fn fetch_page(
+        &self,
+        tx: &mut Transaction<'_>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<SearchRow>, Error> {
+        let (limit, offset) = (limit as i64, offset as i64);
+
+        let rows = match self {
+            Self::Direction(direction_query) => {
+                let direction = direction_query.direction;
+                // stars ordered by the cosine of their angle to the direction.
+                sqlx::query_as!(
+                    SearchRow,
+                    r#"
+                    SELECT
+                        id,
+                        position AS "position: Vec3",
+                        name,
+                        id_hyg,
+                        id_hip,
+                        id_hd,
+                        id_hr,
+                        id_gl,
+                        id_bf
+                    FROM star
+                    WHERE
+                        ((position).x * $1 + (position).y * $2 + (position).z * $3)
+                            / GREATEST(sqrt((position).x ^ 2 + (position).y ^ 2 + (position).z ^ 2), 1e-6)
+                            >= $4
+                    ORDER BY
+                        ((position).x * $1 + (position).y * $2 + (position).z * $3)
+                            / GREATEST(sqrt((position).x ^ 2 + (position).y ^ 2 + (position).z ^ 2), 1e-6)
+                            DESC,
+                        id
+                    LIMIT $5
+                    OFFSET $6
+                    "#,
+                    direction.x,
+                    direction.y,
+                    direction.z,
+                    MAX_SEPARATION.to_radians().cos(),
+                    limit,
+                    offset,
+                )
+                .fetch_all(&mut ***tx)
+                .await?
+            }
+            Self::Text { catalog_query, q } => {
+                let catalog_query = catalog_query.as_ref();
+                let pattern = format!("{}%", escape_like(q));
+
+                sqlx::query_as!(
+                    SearchRow,
+                    r#"
+                    SELECT
+                        id,
+                        position AS "position: Vec3",
+                        name,
+                        id_hyg,
+                        id_hip,
+                        id_hd,
+                        id_hr,
+                        id_gl,
+                        id_bf
+                    FROM star
+                    WHERE
+                        id_hyg = $1
+                        OR id_hip = $2
+                        OR id_hd = $3
+                        OR id_hr = $4
+                        OR lower(id_gl) = lower($5)
+                        OR name ILIKE $6
+                        OR id_bf ILIKE $6
+                        OR name % $7
+                    ORDER BY
+                        lower(name) = lower($7) DESC,
+                        name ILIKE $6 DESC,
+                        similarity(COALESCE(name, id_bf, ''), $7) DESC,
+                        id
+                    LIMIT $8
+                    OFFSET $9
+                    "#,
+                    catalog_query.and_then(|query| query.hyg),
+                    catalog_query.and_then(|query| query.hip),
+                    catalog_query.and_then(|query| query.hd),
+                    catalog_query.and_then(|query| query.hr),
+                    catalog_query.and_then(|query| query.gl.clone()),
+                    pattern,
+                    q,
+                    limit,
+                    offset,
+                )
+                .fetch_all(&mut ***tx)
+                .await?
+            }
+        };
+
+        Ok(rows)
+    }
+}
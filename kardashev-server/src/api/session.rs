@@ -0,0 +1,106 @@
+//! Stream of events for a connected client.
+
+use axum::{
+    extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
+        WebSocketUpgrade,
+    },
+    response::Response,
+};
+use kardashev_protocol::{
+    model::faction::FactionId,
+    SessionEvent,
+};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+
+use crate::{
+    context::Context,
+    error::Error,
+    visibility::Viewer,
+    webhooks::{
+        self,
+        faction_name,
+        Notification,
+    },
+};
+
+/// Upgrades to a websocket on which [`SessionEvent`]s are sent.
+///
+/// Viewers with a faction only receive events that concern their faction.
+pub async fn session(context: Context, viewer: Viewer, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| run_session(context, viewer, socket))
+}
+
+async fn run_session(context: Context, viewer: Viewer, mut socket: WebSocket) {
+    let mut journal = context.journal.subscribe();
+
+    // a player joins with their first session, not with every tab they open.
+    let joined = viewer
+        .faction
+        .filter(|faction| !context.activity.is_connected(context.world, *faction));
+    let _session = context
+        .activity
+        .session_connected(context.world, viewer.faction);
+    if let Some(faction) = joined {
+        if let Err(error) = notify_joined(&context, faction).await {
+            tracing::warn!(?error, "failed to notify that a player joined");
+        }
+    }
+
+    loop {
+        let event = tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            message = socket.recv() => {
+                match message {
+                    // the client doesn't send anything, but we need to notice when it disconnects.
+                    Some(Ok(_)) => continue,
+                    None | Some(Err(_)) => break,
+                }
+            }
+            entry = journal.recv() => {
+                match entry {
+                    Ok(entry) => {
+                        // only admins see the journals of all factions.
+                        let visible = viewer
+                            .faction
+                            .map_or(viewer.admin, |faction| faction == entry.faction);
+                        if !visible {
+                            continue;
+                        }
+                        SessionEvent::Journal { entry }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(num_skipped)) => {
+                        tracing::warn!(num_skipped, "session lagged behind journal");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        };
+
+        let span = tracing::debug_span!("session_event", world = %context.world.0);
+        let sent = async {
+            let message = serde_json::to_string(&event).expect("failed to serialize session event");
+            let sent = socket.send(Message::Text(message)).await.is_ok();
+            // exported as a metric.
+            tracing::trace!(monotonic_counter.session_events_sent = 1u64);
+            sent
+        }
+        .instrument(span)
+        .await;
+        if !sent {
+            break;
+        }
+    }
+}
+
+async fn notify_joined(context: &Context, faction: FactionId) -> Result<(), Error> {
+    let mut tx = context.transaction().await?;
+    let faction = faction_name(&mut tx, faction).await?;
+    webhooks::notify(context, Notification::PlayerJoined { faction });
+    Ok(())
+}
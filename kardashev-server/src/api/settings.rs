@@ -0,0 +1,133 @@
+use axum::{
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints,
+    GetSettingsResponse,
+    PlayerSettings,
+    PutSettingsRequest,
+    PutSettingsResponse,
+};
+use sqlx::types::Json as SqlJson;
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetSettings, _>(get_settings)
+        .endpoint::<endpoints::PutSettings, _>(put_settings)
+}
+
+/// Returns the viewer's UI settings.
+async fn get_settings(
+    context: Context,
+    viewer: Viewer,
+) -> Result<Json<GetSettingsResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let settings = sqlx::query!(
+        r#"
+        SELECT
+            version,
+            settings AS "settings: SqlJson<serde_json::Value>",
+            updated_at
+        FROM player_settings
+        WHERE faction_id = $1
+        "#,
+        faction.0,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .map(|row| {
+        PlayerSettings {
+            version: row.version as u64,
+            settings: row.settings.0,
+            updated_at: row.updated_at,
+        }
+    });
+
+    Ok(Json(GetSettingsResponse { settings }))
+}
+
+/// Stores the viewer's UI settings, if the stored ones still have the
+/// request's base version.
+///
+/// Otherwise the stored settings are returned as a conflict. The client then
+/// merges them with its local settings, and tries again with their version.
+async fn put_settings(
+    context: Context,
+    viewer: Viewer,
+    Json(request): Json<PutSettingsRequest>,
+) -> Result<Json<PutSettingsResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    // the update only happens if the versions match. a missing base version
+    // never matches, so settings that were stored by another client are not
+    // overwritten.
+    let stored = sqlx::query!(
+        r#"
+        INSERT INTO player_settings (faction_id, version, settings, updated_at)
+        VALUES ($1, 1, $2, NOW())
+        ON CONFLICT (faction_id) DO UPDATE
+        SET
+            version = player_settings.version + 1,
+            settings = EXCLUDED.settings,
+            updated_at = EXCLUDED.updated_at
+        WHERE player_settings.version = $3
+        RETURNING version, updated_at
+        "#,
+        faction.0,
+        SqlJson(&request.settings) as _,
+        request.base_version.map(|version| version as i64),
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let response = if let Some(stored) = stored {
+        PutSettingsResponse {
+            settings: PlayerSettings {
+                version: stored.version as u64,
+                settings: request.settings,
+                updated_at: stored.updated_at,
+            },
+            conflict: false,
+        }
+    }
+    else {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                version,
+                settings AS "settings: SqlJson<serde_json::Value>",
+                updated_at
+            FROM player_settings
+            WHERE faction_id = $1
+            "#,
+            faction.0,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        PutSettingsResponse {
+            settings: PlayerSettings {
+                version: row.version as u64,
+                settings: row.settings.0,
+                updated_at: row.updated_at,
+            },
+            conflict: true,
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,110 @@
+//! Stream of changes to the stars, so that clients don't have to poll
+//! `GET /star`.
+
+use axum::{
+    extract::{
+        ws::{
+            Message,
+            WebSocket,
+        },
+        WebSocketUpgrade,
+    },
+    response::Response,
+};
+use kardashev_protocol::model::star::StarEvent;
+use tokio::sync::broadcast;
+
+use crate::{
+    context::Context,
+    error::Error,
+    visibility::{
+        Viewer,
+        Visibility,
+    },
+};
+
+/// Upgrades to a websocket on which [`StarEvent`]s are sent.
+pub async fn star_events(context: Context, viewer: Viewer, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| run_star_events(context, viewer, socket))
+}
+
+async fn run_star_events(context: Context, viewer: Viewer, mut socket: WebSocket) {
+    let mut updates = context.star_updates.subscribe();
+
+    loop {
+        let mut events = tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            message = socket.recv() => {
+                match message {
+                    // the client doesn't send anything, but we need to notice when it disconnects.
+                    Some(Ok(_)) => continue,
+                    None | Some(Err(_)) => break,
+                }
+            }
+            event = updates.recv() => {
+                match event {
+                    Ok(event) => vec![event],
+                    Err(broadcast::error::RecvError::Lagged(num_skipped)) => {
+                        tracing::debug!(num_skipped, "star events lagged behind");
+                        vec![StarEvent::Reload]
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        };
+
+        // changes are published in batches, e.g. by imports, so the visibility is
+        // loaded once per batch.
+        loop {
+            match updates.try_recv() {
+                Ok(event) => events.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => events.push(StarEvent::Reload),
+                Err(_) => break,
+            }
+        }
+
+        let events = match filter_events(&context, &viewer, events).await {
+            Ok(events) => events,
+            Err(error) => {
+                tracing::error!(?error, "failed to filter star events");
+                break;
+            }
+        };
+
+        for event in events {
+            let message = serde_json::to_string(&event).expect("failed to serialize star event");
+            if socket.send(Message::Text(message)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Hides what the viewer doesn't know about the stars.
+///
+/// Once the client has to reload, the other events are dropped.
+async fn filter_events(
+    context: &Context,
+    viewer: &Viewer,
+    mut events: Vec<StarEvent>,
+) -> Result<Vec<StarEvent>, Error> {
+    if events
+        .iter()
+        .any(|event| matches!(event, StarEvent::Reload))
+    {
+        return Ok(vec![StarEvent::Reload]);
+    }
+
+    let mut tx = context.transaction().await?;
+    let visibility = Visibility::load(&mut tx, viewer, &context.modules).await?;
+    for event in &mut events {
+        match event {
+            StarEvent::Inserted { star } | StarEvent::Updated { star } => {
+                visibility.filter_star(star)
+            }
+            StarEvent::Deleted { .. } | StarEvent::Reload => {}
+        }
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,243 @@
+use axum::{
+    extract::Path,
+    Json,
+    Router,
+};
+use kardashev_protocol::{
+    endpoints,
+    model::{
+        empire::ColonyId,
+        star::StarId,
+        trade::{
+            validate_route,
+            Resource,
+            Stockpile,
+            TradeRoute,
+            TradeRouteId,
+        },
+    },
+    units::Distance,
+    uuid::Uuid,
+    CreateTradeRouteRequest,
+    GetTradeRoutesResponse,
+};
+use nalgebra::Point3;
+
+use crate::{
+    api::EndpointRouter,
+    context::Context,
+    error::Error,
+    util::sqlx::Vec3,
+    visibility::Viewer,
+    worlds::Worlds,
+};
+
+pub fn router() -> Router<Worlds> {
+    Router::new()
+        .endpoint::<endpoints::GetTradeRoutes, _>(get_trade_routes)
+        .endpoint::<endpoints::CreateTradeRoute, _>(create_trade_route)
+        .endpoint::<endpoints::CancelTradeRoute, _>(cancel_trade_route)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "trade_resource", rename_all = "lowercase")]
+pub(crate) enum ResourceColumn {
+    Energy,
+    Metals,
+    Volatiles,
+}
+
+impl From<Resource> for ResourceColumn {
+    fn from(value: Resource) -> Self {
+        match value {
+            Resource::Energy => Self::Energy,
+            Resource::Metals => Self::Metals,
+            Resource::Volatiles => Self::Volatiles,
+        }
+    }
+}
+
+impl From<ResourceColumn> for Resource {
+    fn from(value: ResourceColumn) -> Self {
+        match value {
+            ResourceColumn::Energy => Self::Energy,
+            ResourceColumn::Metals => Self::Metals,
+            ResourceColumn::Volatiles => Self::Volatiles,
+        }
+    }
+}
+
+/// Returns the viewer's trade routes, and the stockpiles of its colonies.
+async fn get_trade_routes(
+    context: Context,
+    viewer: Viewer,
+) -> Result<Json<GetTradeRoutesResponse>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let routes = sqlx::query!(
+        r#"
+        SELECT
+            trade_route.id,
+            trade_route.from_colony,
+            source.star_id AS from_star,
+            trade_route.to_colony,
+            destination.star_id AS to_star,
+            trade_route.resource AS "resource: ResourceColumn",
+            trade_route.capacity,
+            trade_route.travel_days,
+            trade_route.created_at,
+            COALESCE(
+                (SELECT SUM(amount) FROM trade_shipment WHERE route_id = trade_route.id),
+                0
+            ) AS "in_transit!"
+        FROM trade_route
+        JOIN colony AS source ON source.id = trade_route.from_colony
+        JOIN colony AS destination ON destination.id = trade_route.to_colony
+        WHERE trade_route.faction_id = $1
+        ORDER BY trade_route.created_at, trade_route.id
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        TradeRoute {
+            id: TradeRouteId(row.id),
+            from: ColonyId(row.from_colony),
+            from_star: StarId(row.from_star),
+            to: ColonyId(row.to_colony),
+            to_star: StarId(row.to_star),
+            resource: row.resource.into(),
+            capacity: row.capacity,
+            travel_days: row.travel_days,
+            in_transit: row.in_transit,
+            created_at: row.created_at,
+        }
+    })
+    .collect();
+
+    let stockpiles = sqlx::query!(
+        r#"
+        SELECT
+            colony_stockpile.colony_id,
+            colony_stockpile.resource AS "resource: ResourceColumn",
+            colony_stockpile.amount
+        FROM colony_stockpile
+        JOIN colony ON colony.id = colony_stockpile.colony_id
+        WHERE colony.faction_id = $1
+        ORDER BY colony_stockpile.colony_id, colony_stockpile.resource
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Stockpile {
+            colony: ColonyId(row.colony_id),
+            resource: row.resource.into(),
+            amount: row.amount,
+        }
+    })
+    .collect();
+
+    Ok(Json(GetTradeRoutesResponse { routes, stockpiles }))
+}
+
+/// Opens a trade route between two of the viewer's colonies.
+async fn create_trade_route(
+    context: Context,
+    viewer: Viewer,
+    Json(request): Json<CreateTradeRouteRequest>,
+) -> Result<Json<TradeRoute>, Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    validate_route(request.from, request.to, request.capacity)?;
+
+    let mut tx = context.transaction().await?;
+
+    let colony = |id: ColonyId| {
+        sqlx::query!(
+            r#"
+            SELECT
+                colony.star_id,
+                star.position AS "position: Vec3"
+            FROM colony
+            JOIN star ON star.id = colony.star_id
+            WHERE colony.id = $1 AND colony.faction_id = $2
+            "#,
+            id.0,
+            faction.0,
+        )
+    };
+    let from = colony(request.from)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let to = colony(request.to)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let distance = Point3::from(from.position) - Point3::from(to.position);
+    let travel_days = context
+        .rules
+        .trade_travel_days(Distance::from_parsecs(distance.norm()));
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO trade_route (faction_id, from_colony, to_colony, resource, capacity, travel_days)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, created_at
+        "#,
+        faction.0,
+        request.from.0,
+        request.to.0,
+        ResourceColumn::from(request.resource) as _,
+        request.capacity,
+        travel_days,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(TradeRoute {
+        id: TradeRouteId(row.id),
+        from: request.from,
+        from_star: StarId(from.star_id),
+        to: request.to,
+        to_star: StarId(to.star_id),
+        resource: request.resource,
+        capacity: request.capacity,
+        travel_days,
+        in_transit: 0.0,
+        created_at: row.created_at,
+    }))
+}
+
+/// Closes one of the viewer's trade routes.
+async fn cancel_trade_route(
+    context: Context,
+    viewer: Viewer,
+    Path(id): Path<Uuid>,
+) -> Result<(), Error> {
+    let faction = viewer.faction.ok_or(Error::NoFaction)?;
+    let mut tx = context.transaction().await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM trade_route WHERE id = $1 AND faction_id = $2",
+        id,
+        faction.0,
+    )
+    .execute(&mut **tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(Error::NotFound);
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Download statistics for the served assets.
+//!
+//! The assets are not served by the API, but by whoever nests it into their
+//! router, e.g. `kardashev-cli serve`. They wrap the asset service with
+//! [`record_asset_download`] to count the downloads, which are then reported
+//! by the admin API.
+
+use std::{
+    collections::HashMap,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use axum::{
+    extract::{
+        Request,
+        State,
+    },
+    http::{
+        header,
+        StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_protocol::admin::{
+    AssetDownloads,
+    GetAssetStatsResponse,
+};
+
+use crate::error::Error;
+
+#[derive(Clone, Debug)]
+pub struct AssetStats {
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    dist_path: Option<PathBuf>,
+    since: DateTime<Utc>,
+    downloads: HashMap<String, Counters>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Counters {
+    requests: u64,
+    bytes: u64,
+}
+
+impl Default for AssetStats {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                dist_path: None,
+                since: Utc::now(),
+                downloads: HashMap::new(),
+            })),
+        }
+    }
+}
+
+impl AssetStats {
+    /// Creates statistics for the assets in `dist_path`.
+    ///
+    /// The directory is used to find the assets that were never requested.
+    pub fn new(dist_path: impl Into<PathBuf>) -> Self {
+        let stats = Self::default();
+        stats.inner.lock().unwrap().dist_path = Some(dist_path.into());
+        stats
+    }
+
+    fn record(&self, path: &str, bytes: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let counters = inner.downloads.entry(path.to_owned()).or_default();
+        counters.requests += 1;
+        counters.bytes += bytes;
+    }
+
+    /// Returns the downloads ordered by bytes sent, and the assets that were
+    /// never requested.
+    pub async fn report(&self) -> Result<GetAssetStatsResponse, Error> {
+        let (dist_path, since, mut downloads) = {
+            let inner = self.inner.lock().unwrap();
+            let downloads = inner
+                .downloads
+                .iter()
+                .map(|(path, counters)| {
+                    AssetDownloads {
+                        path: path.clone(),
+                        requests: counters.requests,
+                        bytes: counters.bytes,
+                    }
+                })
+                .collect::<Vec<_>>();
+            (inner.dist_path.clone(), inner.since, downloads)
+        };
+        downloads.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+
+        let unrequested = if let Some(dist_path) = dist_path {
+            let mut files = tokio::task::spawn_blocking(move || list_files(&dist_path)).await??;
+            files.retain(|path| !downloads.iter().any(|download| download.path == *path));
+            files.sort();
+            files
+        }
+        else {
+            vec![]
+        };
+
+        Ok(GetAssetStatsResponse {
+            since,
+            downloads,
+            unrequested,
+        })
+    }
+}
+
+/// Lists the files in `dist_path` recursively, as paths relative to it.
+fn list_files(dist_path: &Path) -> Result<Vec<String>, std::io::Error> {
+    let mut files = vec![];
+    let mut directories = vec![dist_path.to_owned()];
+
+    while let Some(directory) = directories.pop() {
+        for entry in std::fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                directories.push(path);
+            }
+            else if let Ok(relative) = path.strip_prefix(dist_path) {
+                let relative = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push(relative);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Marks a response as redirect to the asset storage, so that
+/// [`record_asset_download`] counts it.
+///
+/// Insert it into the response's extensions.
+#[derive(Clone, Copy, Debug)]
+pub struct StorageRedirect;
+
+/// Middleware that records downloads of assets.
+///
+/// Counted are complete and partial downloads, and redirects that are marked
+/// with [`StorageRedirect`], which are counted as requests without bytes.
+/// Other redirects and cached responses (`304 Not Modified`) aren't counted.
+///
+/// Must be applied to the service that serves the assets, so that request
+/// paths are relative to the dist directory.
+pub async fn record_asset_download(
+    State(stats): State<AssetStats>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().trim_start_matches('/').to_owned();
+    let response = next.run(request).await;
+
+    let downloaded = matches!(
+        response.status(),
+        StatusCode::OK | StatusCode::PARTIAL_CONTENT
+    ) || response.extensions().get::<StorageRedirect>().is_some();
+    if downloaded {
+        let bytes = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        tracing::debug!(%path, bytes, "asset downloaded");
+        stats.record(&path, bytes);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AssetStats;
+
+    #[tokio::test]
+    async fn it_orders_downloads_by_bytes() {
+        let stats = AssetStats::default();
+        stats.record("textures/small.png", 100);
+        stats.record("models/large.glb", 10_000);
+        stats.record("textures/small.png", 100);
+
+        let report = stats.report().await.unwrap();
+        assert_eq!(report.downloads[0].path, "models/large.glb");
+        assert_eq!(report.downloads[1].requests, 2);
+        assert_eq!(report.downloads[1].bytes, 200);
+        assert!(report.unrequested.is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+//! Logical backups of the worlds' tables.
+//!
+//! Every table is exported with `COPY ... TO STDOUT` as CSV, all from the same
+//! snapshot, so that the backup is consistent. The rows are streamed into
+//! multipart uploads, so that tables don't have to fit into memory. The files
+//! and a manifest are written to an [`object_store`] target, which is either a
+//! local directory (`file:///var/backups/kardashev`) or an S3-compatible bucket
+//! (`s3://bucket/prefix`, configured with the usual `AWS_*` environment
+//! variables, e.g. `AWS_ENDPOINT` for other providers than AWS).
+//!
+//! Each backup is stored in `<world id>/<timestamp>/` relative to the target.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use futures_util::TryStreamExt;
+use kardashev_protocol::{
+    admin::{
+        Backup,
+        TableBackup,
+    },
+    model::world::WorldId,
+};
+use object_store::{
+    path::Path,
+    ObjectStore,
+    WriteMultipart,
+};
+use url::Url;
+
+use crate::{
+    context::Context,
+    error::Error,
+    webhooks::{
+        self,
+        Notification,
+    },
+};
+
+/// The tables that are backed up, in an order in which they can be restored.
+///
+/// Tables of modules that were never enabled don't exist, and are skipped.
+const TABLES: &[&str] = &[
+    "user",
+    "faction",
+    "faction_user",
+    "star",
+    "planet",
+    "region",
+    "star_chunk",
+    "fleet",
+    "fleet_order",
+    "explored_star",
+    "colony",
+    "kardashev_rating",
+    "journal_entry",
+    "simulation_state",
+    "bookmark",
+    "player_settings",
+    "relation",
+    "treaty_proposal",
+    "colony_stockpile",
+    "trade_route",
+    "trade_shipment",
+    "world_snapshot",
+];
+
+/// How many backups are remembered for the admin API.
+const MAX_HISTORY: usize = 20;
+
+/// How many parts of a table are uploaded at once. The export waits for the
+/// uploads, if the target is slower than the database.
+const MAX_CONCURRENT_PARTS: usize = 4;
+
+#[derive(Clone, Debug, Default)]
+pub struct BackupConfig {
+    /// Where backups are written to. Without a target, backups are disabled.
+    pub target: Option<Url>,
+
+    /// Time between scheduled backups of every world. Without an interval,
+    /// backups are only made on request.
+    pub interval: Option<Duration>,
+}
+
+/// The backup target and the recent backups, shared by all worlds.
+#[derive(Clone, Debug, Default)]
+pub struct Backups {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    target: Option<Target>,
+    interval: Option<Duration>,
+    history: Mutex<VecDeque<Backup>>,
+
+    /// Held while a backup is running, so that backups don't compete for the
+    /// database.
+    running: tokio::sync::Mutex<()>,
+}
+
+#[derive(Debug)]
+struct Target {
+    url: Url,
+    store: Box<dyn ObjectStore>,
+    prefix: Path,
+}
+
+impl Backups {
+    pub fn new(config: BackupConfig) -> Result<Self, Error> {
+        let target = config
+            .target
+            .map(|url| {
+                // the S3 configuration keys are the lowercase names of the environment
+                // variables.
+                let options = std::env::vars()
+                    .filter(|(key, _)| key.starts_with("AWS_"))
+                    .map(|(key, value)| (key.to_ascii_lowercase(), value));
+                let (store, prefix) = object_store::parse_url_opts(&url, options)?;
+                Ok::<_, Error>(Target { url, store, prefix })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                target,
+                interval: config.interval,
+                ..Default::default()
+            }),
+        })
+    }
+
+    /// The URL of the target, if backups are enabled.
+    pub fn target(&self) -> Option<&Url> {
+        self.inner.target.as_ref().map(|target| &target.url)
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        self.inner.interval
+    }
+
+    /// Returns the recent backups, the newest first.
+    pub fn history(&self) -> Vec<Backup> {
+        self.inner.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn remember(&self, backup: Backup) {
+        let mut history = self.inner.history.lock().unwrap();
+        if history.len() == MAX_HISTORY {
+            history.pop_back();
+        }
+        history.push_front(backup);
+    }
+}
+
+/// Name of a backup, which is also its path relative to the target.
+fn backup_name(world: WorldId, started_at: DateTime<Utc>) -> String {
+    format!("{}/{}", world.0, started_at.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// Backs up the tables of the context's world.
+///
+/// Fails with [`Error::BackupsDisabled`] if there's no target, and with
+/// [`Error::BackupRunning`] if another backup is still running.
+pub async fn backup(context: &Context) -> Result<Backup, Error> {
+    let backups = &context.backups;
+    let target = backups
+        .inner
+        .target
+        .as_ref()
+        .ok_or(Error::BackupsDisabled)?;
+    let _running = backups
+        .inner
+        .running
+        .try_lock()
+        .map_err(|_| Error::BackupRunning)?;
+
+    let started_at = Utc::now();
+    let name = backup_name(context.world, started_at);
+    tracing::info!(%name, "starting backup");
+
+    let mut backup = Backup {
+        name,
+        world: context.world,
+        started_at,
+        finished_at: None,
+        tables: vec![],
+        error: None,
+    };
+
+    match export(context, target, &backup.name).await {
+        Ok(tables) => {
+            backup.tables = tables;
+            backup.finished_at = Some(Utc::now());
+
+            let manifest = serde_json::to_vec_pretty(&backup).expect("failed to serialize backup");
+            target
+                .store
+                .put(&target.path(&backup.name, "manifest.json"), manifest.into())
+                .await?;
+
+            tracing::info!(name = %backup.name, "backup finished");
+            backups.remember(backup.clone());
+            Ok(backup)
+        }
+        Err(error) => {
+            tracing::error!(name = %backup.name, ?error, "backup failed");
+            let message = format!("{error:?}");
+            webhooks::notify(
+                context,
+                Notification::BackupFailed {
+                    name: backup.name.clone(),
+                    error: message.clone(),
+                },
+            );
+            backup.error = Some(message);
+            backups.remember(backup);
+            Err(error)
+        }
+    }
+}
+
+impl Target {
+    fn path(&self, name: &str, file: &str) -> Path {
+        name.split('/')
+            .chain([file])
+            .fold(self.prefix.clone(), |path, part| path.child(part))
+    }
+}
+
+async fn export(context: &Context, target: &Target, name: &str) -> Result<Vec<TableBackup>, Error> {
+    let mut tx = context.transaction().await?;
+
+    // all tables are exported from the same snapshot.
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut **tx)
+        .await?;
+
+    let mut tables = vec![];
+    for table in TABLES {
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(format!(r#""{table}""#))
+            .fetch_one(&mut **tx)
+            .await?;
+        if !exists {
+            tracing::debug!(table, "skipping table that doesn't exist");
+            continue;
+        }
+
+        let rows: i64 = sqlx::query_scalar(&format!(r#"SELECT COUNT(*) FROM "{table}""#))
+            .fetch_one(&mut **tx)
+            .await?;
+
+        let mut upload = WriteMultipart::new(
+            target
+                .store
+                .put_multipart(&target.path(name, &format!("{table}.csv")))
+                .await?,
+        );
+        let mut bytes = 0;
+        let mut stream = tx
+            .copy_out_raw(&format!(
+                r#"COPY "{table}" TO STDOUT WITH (FORMAT csv, HEADER)"#
+            ))
+            .await?;
+        let result = async {
+            while let Some(chunk) = stream.try_next().await? {
+                upload.wait_for_capacity(MAX_CONCURRENT_PARTS).await?;
+                bytes += chunk.len() as u64;
+                upload.put(chunk);
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        drop(stream);
+
+        match result {
+            Ok(()) => {
+                upload.finish().await?;
+            }
+            Err(error) => {
+                // don't leave the parts behind.
+                let _ = upload.abort().await;
+                return Err(error);
+            }
+        }
+
+        tracing::debug!(table, rows, bytes, "exported table");
+        tables.push(TableBackup {
+            table: table.to_string(),
+            rows: rows as u64,
+            bytes,
+        });
+    }
+
+    tx.commit().await?;
+
+    Ok(tables)
+}
+
+/// Backs up the context's world in the configured interval.
+pub async fn run(context: Context) {
+    let (Some(period), Some(_)) = (context.backups.interval(), context.backups.target())
+    else {
+        return;
+    };
+
+    // the first backup is made one interval after startup, not on every restart.
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            _ = interval.tick() => {
+                if let Err(error) = backup(&context).await {
+                    tracing::error!(world = %context.world.0, ?error, "scheduled backup failed");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{
+        TimeZone,
+        Utc,
+    };
+    use kardashev_protocol::model::world::WorldId;
+
+    use super::backup_name;
+
+    #[test]
+    fn backups_are_named_by_world_and_time() {
+        let started_at = Utc.with_ymd_and_hms(2024, 10, 17, 1, 2, 3).unwrap();
+        assert_eq!(
+            backup_name(WorldId::DEFAULT, started_at),
+            "00000000-0000-0000-0000-000000000000/20241017T010203Z"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Graphics capabilities reported by clients, shown on the admin dashboard.
+//!
+//! Reports from the same backend and adapter, with the same rendering features
+//! disabled, are grouped. This shows which GPUs players have, and how many of
+//! them miss out on which features. Like the
+//! [client errors](crate::client_errors), this is only kept in memory.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_protocol::ClientCapabilityReport;
+
+/// How many groups of reports are remembered.
+const MAX_GROUPS: usize = 100;
+
+/// Strings in a report are truncated to this many bytes.
+const MAX_STRING_LENGTH: usize = 256;
+
+/// How many entries of the report's lists and maps are kept.
+const MAX_ENTRIES: usize = 256;
+
+#[derive(Clone, Debug, Default)]
+pub struct ClientCapabilities {
+    inner: Arc<Mutex<Vec<ClientCapabilityGroup>>>,
+}
+
+/// Reports with the same backend, adapter and disabled features.
+#[derive(Clone, Debug)]
+pub struct ClientCapabilityGroup {
+    pub backend: String,
+    pub adapter: String,
+    pub disabled: BTreeMap<String, String>,
+    pub count: u64,
+    pub last_seen: DateTime<Utc>,
+
+    /// The most recent report.
+    pub latest: ClientCapabilityReport,
+}
+
+impl ClientCapabilities {
+    /// Adds a report to its group, forgetting the group that wasn't seen for
+    /// the longest time if there are too many.
+    pub fn record(&self, mut report: ClientCapabilityReport) {
+        limit(&mut report);
+
+        let now = Utc::now();
+        let mut groups = self.inner.lock().unwrap();
+
+        if let Some(group) = groups.iter_mut().find(|group| {
+            group.backend == report.backend
+                && group.adapter == report.adapter
+                && group.disabled == report.disabled
+        }) {
+            group.count += 1;
+            group.last_seen = now;
+            group.latest = report;
+            return;
+        }
+
+        if groups.len() == MAX_GROUPS {
+            if let Some((oldest, _)) = groups
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, group)| group.last_seen)
+            {
+                groups.swap_remove(oldest);
+            }
+        }
+
+        groups.push(ClientCapabilityGroup {
+            backend: report.backend.clone(),
+            adapter: report.adapter.clone(),
+            disabled: report.disabled.clone(),
+            count: 1,
+            last_seen: now,
+            latest: report,
+        });
+    }
+
+    /// Returns the groups, the most common first.
+    pub fn groups(&self) -> Vec<ClientCapabilityGroup> {
+        let mut groups = self.inner.lock().unwrap().clone();
+        groups.sort_by(|a, b| b.count.cmp(&a.count));
+        groups
+    }
+}
+
+fn limit(report: &mut ClientCapabilityReport) {
+    truncate(&mut report.client_version);
+    if let Some(user_agent) = &mut report.user_agent {
+        truncate(user_agent);
+    }
+    truncate(&mut report.backend);
+    truncate(&mut report.adapter);
+    truncate(&mut report.driver);
+
+    report.features.truncate(MAX_ENTRIES);
+    report.features.iter_mut().for_each(truncate);
+    limit_map(&mut report.limits);
+    limit_map(&mut report.texture_formats);
+    limit_map(&mut report.disabled);
+    report.disabled.values_mut().for_each(truncate);
+}
+
+fn limit_map<V>(map: &mut BTreeMap<String, V>) {
+    while map.len() > MAX_ENTRIES {
+        map.pop_last();
+    }
+}
+
+fn truncate(s: &mut String) {
+    if s.len() > MAX_STRING_LENGTH {
+        let mut end = MAX_STRING_LENGTH;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::ClientCapabilityReport;
+
+    use super::ClientCapabilities;
+
+    fn report(adapter: &str, disabled: &[(&str, &str)]) -> ClientCapabilityReport {
+        ClientCapabilityReport {
+            client_version: "0.1.0".to_owned(),
+            user_agent: None,
+            backend: "WebGl".to_owned(),
+            adapter: adapter.to_owned(),
+            driver: String::new(),
+            features: vec![],
+            limits: Default::default(),
+            texture_formats: Default::default(),
+            disabled: disabled
+                .iter()
+                .map(|(feature, reason)| ((*feature).to_owned(), (*reason).to_owned()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn it_groups_reports_by_adapter_and_disabled_features() {
+        let client_capabilities = ClientCapabilities::default();
+        client_capabilities.record(report("ANGLE", &[("hdr", "unsupported")]));
+        client_capabilities.record(report("ANGLE", &[("hdr", "unsupported")]));
+        client_capabilities.record(report("ANGLE", &[]));
+        client_capabilities.record(report("llvmpipe", &[]));
+
+        let groups = client_capabilities.groups();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].count, 2);
+        assert_eq!(groups[0].disabled.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Crash and error reports sent by clients, shown on the admin dashboard.
+//!
+//! Reports with the same kind, message and location are grouped, so that an
+//! error that many clients run into shows up once with a count. Like the
+//! [activity](crate::activity), this is only kept in memory, and shared by
+//! all worlds.
+
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_protocol::{
+    ClientErrorKind,
+    ClientErrorReport,
+};
+
+/// How many groups of reports are remembered.
+const MAX_GROUPS: usize = 100;
+
+/// How many breadcrumbs of a report are kept.
+const MAX_BREADCRUMBS: usize = 50;
+
+/// Messages are truncated to this many bytes.
+const MAX_MESSAGE_LENGTH: usize = 4096;
+
+#[derive(Clone, Debug, Default)]
+pub struct ClientErrors {
+    inner: Arc<Mutex<Vec<ClientErrorGroup>>>,
+}
+
+/// Reports with the same kind, message and location.
+#[derive(Clone, Debug)]
+pub struct ClientErrorGroup {
+    pub kind: ClientErrorKind,
+    pub message: String,
+    pub location: Option<String>,
+    pub count: u64,
+
+    /// When the server first received a report of this group. The times in
+    /// the reports are from the clients' clocks.
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+
+    /// The most recent report.
+    pub latest: ClientErrorReport,
+}
+
+impl ClientErrors {
+    /// Adds a report to its group, forgetting the group that wasn't seen for
+    /// the longest time if there are too many.
+    pub fn record(&self, mut report: ClientErrorReport) {
+        truncate(&mut report.message, MAX_MESSAGE_LENGTH);
+        if report.breadcrumbs.len() > MAX_BREADCRUMBS {
+            report
+                .breadcrumbs
+                .drain(..report.breadcrumbs.len() - MAX_BREADCRUMBS);
+        }
+        for breadcrumb in &mut report.breadcrumbs {
+            truncate(&mut breadcrumb.message, MAX_MESSAGE_LENGTH);
+        }
+
+        let now = Utc::now();
+        let mut groups = self.inner.lock().unwrap();
+
+        if let Some(group) = groups.iter_mut().find(|group| {
+            group.kind == report.kind
+                && group.message == report.message
+                && group.location == report.location
+        }) {
+            group.count += 1;
+            group.last_seen = now;
+            group.latest = report;
+            return;
+        }
+
+        if groups.len() == MAX_GROUPS {
+            if let Some((oldest, _)) = groups
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, group)| group.last_seen)
+            {
+                groups.swap_remove(oldest);
+            }
+        }
+
+        groups.push(ClientErrorGroup {
+            kind: report.kind,
+            message: report.message.clone(),
+            location: report.location.clone(),
+            count: 1,
+            first_seen: now,
+            last_seen: now,
+            latest: report,
+        });
+    }
+
+    /// Returns the groups, the most recently seen first.
+    pub fn groups(&self) -> Vec<ClientErrorGroup> {
+        let mut groups = self.inner.lock().unwrap().clone();
+        groups.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        groups
+    }
+}
+
+fn truncate(s: &mut String, max_length: usize) {
+    if s.len() > max_length {
+        let mut end = max_length;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use kardashev_protocol::{
+        Breadcrumb,
+        ClientErrorKind,
+        ClientErrorReport,
+    };
+
+    use super::{
+        ClientErrors,
+        MAX_BREADCRUMBS,
+        MAX_GROUPS,
+    };
+
+    fn report(message: &str, location: Option<&str>) -> ClientErrorReport {
+        ClientErrorReport {
+            kind: ClientErrorKind::Panic,
+            message: message.to_owned(),
+            location: location.map(ToOwned::to_owned),
+            time: Utc::now(),
+            client_version: "0.1.0".to_owned(),
+            user_agent: None,
+            breadcrumbs: vec![],
+        }
+    }
+
+    #[test]
+    fn it_groups_reports_by_message_and_location() {
+        let client_errors = ClientErrors::default();
+        client_errors.record(report("index out of bounds", Some("src/a.rs:1:1")));
+        client_errors.record(report("index out of bounds", Some("src/a.rs:1:1")));
+        client_errors.record(report("index out of bounds", Some("src/b.rs:2:2")));
+
+        let groups = client_errors.groups();
+        assert_eq!(groups.len(), 2);
+        let counts = groups.iter().map(|group| group.count).sum::<u64>();
+        assert_eq!(counts, 3);
+    }
+
+    #[test]
+    fn it_limits_reports() {
+        let client_errors = ClientErrors::default();
+        for i in 0..MAX_GROUPS + 5 {
+            let mut report = report(&format!("error {i}"), None);
+            report.breadcrumbs = (0..MAX_BREADCRUMBS + 5)
+                .map(|j| {
+                    Breadcrumb {
+                        time: Utc::now(),
+                        level: "INFO".to_owned(),
+                        target: "kardashev_ui".to_owned(),
+                        message: format!("event {j}"),
+                    }
+                })
+                .collect();
+            client_errors.record(report);
+        }
+
+        let groups = client_errors.groups();
+        assert_eq!(groups.len(), MAX_GROUPS);
+        let breadcrumbs = &groups[0].latest.breadcrumbs;
+        assert_eq!(breadcrumbs.len(), MAX_BREADCRUMBS);
+        assert_eq!(
+            breadcrumbs.last().unwrap().message,
+            format!("event {}", MAX_BREADCRUMBS + 4)
+        );
+    }
+}
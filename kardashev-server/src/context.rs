@@ -1,45 +1,138 @@
-use std::ops::{
-    Deref,
-    DerefMut,
+use std::{
+    ops::{
+        Deref,
+        DerefMut,
+    },
+    sync::Arc,
 };
 
 use chrono::{
     DateTime,
     Utc,
 };
+use kardashev_protocol::model::world::WorldId;
 use sqlx::{
+    postgres::PgConnectOptions,
     PgPool,
     Postgres,
 };
 use tokio_util::sync::CancellationToken;
+use tracing::{
+    Instrument,
+    Span,
+};
 
-use crate::error::Error;
+use crate::{
+    activity::Activity,
+    asset_stats::AssetStats,
+    backup::Backups,
+    client_capabilities::ClientCapabilities,
+    client_errors::ClientErrors,
+    error::Error,
+    journal::Journal,
+    modules::Modules,
+    regions::RegionConfig,
+    scripting::Rules,
+    stars::StarUpdates,
+    webhooks::Webhooks,
+};
 
 #[derive(Clone)]
 pub struct Context {
+    pub world: WorldId,
     pub shutdown: CancellationToken,
     pub up_since: DateTime<Utc>,
+    pub journal: Journal,
+    pub star_updates: StarUpdates,
+    pub regions: RegionConfig,
+    pub asset_stats: AssetStats,
+    pub activity: Activity,
+    pub backups: Backups,
+    pub rules: Rules,
+    pub client_errors: ClientErrors,
+    pub client_capabilities: ClientCapabilities,
+    pub webhooks: Webhooks,
+
+    /// The modules of game rules that the server runs.
+    pub modules: Modules,
+
+    /// Token with which requests see everything, see
+    /// [`Viewer`](crate::visibility::Viewer).
+    pub admin_token: Option<Arc<str>>,
+
+    /// Connections whose search path starts with the world's schema.
     db: PgPool,
 }
 
 impl Context {
     pub fn new(db: PgPool) -> Self {
         Self {
+            world: WorldId::DEFAULT,
             shutdown: CancellationToken::new(),
             up_since: Utc::now(),
+            journal: Journal::default(),
+            star_updates: StarUpdates::default(),
+            regions: RegionConfig::default(),
+            asset_stats: AssetStats::default(),
+            activity: Activity::default(),
+            backups: Backups::default(),
+            rules: Rules::default(),
+            client_errors: ClientErrors::default(),
+            client_capabilities: ClientCapabilities::default(),
+            webhooks: Webhooks::default(),
+            modules: Modules::default(),
+            admin_token: None,
             db,
         }
     }
 
-    pub async fn transaction<'a>(&'a self) -> Result<Transaction<'a>, Error> {
-        let transaction = self.db.begin().await?;
+    /// Returns a context for another world, whose tables are in `schema`, or
+    /// in the public schema if it's `None`.
+    ///
+    /// The world shares the configuration with this one, but has its own
+    /// database pool, journal and star updates.
+    pub fn for_world(&self, world: WorldId, schema: Option<&str>) -> Self {
+        Self {
+            world,
+            journal: Journal::default(),
+            star_updates: StarUpdates::default(),
+            db: schema.map_or_else(|| self.db.clone(), |schema| world_pool(&self.db, schema)),
+            ..self.clone()
+        }
+    }
 
-        Ok(Transaction { transaction })
+    /// Returns the world's database pool.
+    pub fn db(&self) -> &PgPool {
+        &self.db
     }
+
+    /// Begins a transaction in which all tables are the world's.
+    ///
+    /// The transaction has a span that lasts until it's committed or dropped,
+    /// so that the time spent in the database shows up in traces.
+    pub async fn transaction<'a>(&'a self) -> Result<Transaction<'a>, Error> {
+        let span = tracing::debug_span!("db_transaction", world = %self.world.0);
+        let transaction = self.db.begin().instrument(span.clone()).await?;
+        Ok(Transaction { transaction, span })
+    }
+}
+
+/// Creates a pool whose connections use a world's schema.
+///
+/// Every world has its own types (e.g. `vec3`) in its schema, and connections
+/// cache the types' OIDs and prepared statements. So connections must not
+/// switch between worlds, and the search path is set when they connect,
+/// instead of per transaction.
+pub fn world_pool(db: &PgPool, schema: &str) -> PgPool {
+    // schema names are generated from world IDs, so they don't need quoting.
+    let connect_options = PgConnectOptions::clone(&db.connect_options())
+        .options([("search_path", format!("{schema},public"))]);
+    db.options().clone().connect_lazy_with(connect_options)
 }
 
 pub struct Transaction<'a> {
     transaction: sqlx::Transaction<'a, Postgres>,
+    span: Span,
 }
 
 impl<'a> Deref for Transaction<'a> {
@@ -58,12 +151,12 @@ impl<'a> DerefMut for Transaction<'a> {
 
 impl<'a> Transaction<'a> {
     pub async fn commit(self) -> Result<(), Error> {
-        self.transaction.commit().await?;
+        self.transaction.commit().instrument(self.span).await?;
         Ok(())
     }
 
     pub async fn rollback(self) -> Result<(), Error> {
-        self.transaction.rollback().await?;
+        self.transaction.rollback().instrument(self.span).await?;
         Ok(())
     }
 }
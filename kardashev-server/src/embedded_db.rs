@@ -0,0 +1,71 @@
+//! A PostgreSQL server that runs alongside the game server, for tests, demos
+//! and development without a provisioned database.
+//!
+//! The queries are checked against PostgreSQL at compile time, and use its
+//! schemas, arrays and `COPY`, so the embedded database is a real PostgreSQL.
+//! Its binaries are downloaded on first use and cached in
+//! `~/.theseus/postgresql`. Without a data directory, the database is
+//! temporary and removed when it's stopped.
+
+use std::path::PathBuf;
+
+pub use postgresql_embedded::Error as EmbeddedDbError;
+use postgresql_embedded::{
+    PostgreSQL,
+    Settings,
+};
+
+/// Name of the database that is created in the embedded server.
+const DATABASE_NAME: &str = "kardashev";
+
+#[derive(Clone, Debug, Default)]
+pub struct EmbeddedDbConfig {
+    /// Where the database is stored. Without it, the database only lives
+    /// until it's stopped.
+    pub data_dir: Option<PathBuf>,
+}
+
+/// A running embedded database.
+///
+/// It's stopped when dropped, but [`stop`](Self::stop) waits until it's shut
+/// down.
+#[derive(Debug)]
+pub struct EmbeddedDb {
+    postgres: PostgreSQL,
+    url: String,
+}
+
+impl EmbeddedDb {
+    /// Starts the database server, and creates the database if it doesn't
+    /// exist yet.
+    pub async fn start(config: EmbeddedDbConfig) -> Result<Self, EmbeddedDbError> {
+        let mut settings = Settings::default();
+        if let Some(data_dir) = config.data_dir {
+            settings.data_dir = data_dir;
+            settings.temporary = false;
+        }
+
+        let mut postgres = PostgreSQL::new(settings);
+        postgres.setup().await?;
+        postgres.start().await?;
+        if !postgres.database_exists(DATABASE_NAME).await? {
+            postgres.create_database(DATABASE_NAME).await?;
+        }
+
+        let url = postgres.settings().url(DATABASE_NAME);
+        tracing::info!(port = postgres.settings().port, "started embedded database");
+
+        Ok(Self { postgres, url })
+    }
+
+    /// URL with which the server connects to the database.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub async fn stop(self) -> Result<(), EmbeddedDbError> {
+        self.postgres.stop().await?;
+        tracing::info!("stopped embedded database");
+        Ok(())
+    }
+}
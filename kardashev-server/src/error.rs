@@ -5,4 +5,24 @@ pub enum Error {
     Sqlx(#[from] sqlx::Error),
     Io(#[from] std::io::Error),
     SqlxMigrate(#[from] sqlx::migrate::MigrateError),
+    Join(#[from] tokio::task::JoinError),
+    Template(#[from] askama::Error),
+    ObjectStore(#[from] object_store::Error),
+    Lua(#[from] mlua::Error),
+    NotFound,
+    Unauthorized,
+    CrossOrigin,
+    NoFaction,
+    InvalidWorld,
+    UnknownWorld,
+    WorldExists,
+    StarInUse,
+    BackupsDisabled,
+    BackupRunning,
+    ForcedTicksDisabled,
+    TooManyTicks { max: u32 },
+    InvalidWebhookUrl,
+    InvalidOrders(#[from] kardashev_protocol::model::fleet::OrderError),
+    InvalidDiplomacy(#[from] kardashev_protocol::model::diplomacy::DiplomacyError),
+    InvalidTradeRoute(#[from] kardashev_protocol::model::trade::TradeError),
 }
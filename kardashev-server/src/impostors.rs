@@ -0,0 +1,223 @@
+//! Impostors for rendering distant stars.
+//!
+//! The stars of each [chunk](ChunkCoords) are merged into a few points: The
+//! chunk is divided into [`CELLS_PER_AXIS`]³ cells, and the stars in a cell
+//! become one point at their luminosity-weighted mean position and color.
+
+use std::collections::BTreeMap;
+
+use kardashev_protocol::model::{
+    chunk::{
+        ChunkCoords,
+        CHUNK_SIZE,
+    },
+    impostor::{
+        ImpostorPoint,
+        StarChunk,
+    },
+};
+use nalgebra::{
+    Point3,
+    Vector3,
+};
+use palette::LinSrgb;
+use sqlx::types::Json;
+
+use crate::{
+    context::Context,
+    error::Error,
+    util::sqlx::{
+        Rgb,
+        Vec3,
+    },
+};
+
+/// Number of cells along each axis of a chunk.
+const CELLS_PER_AXIS: i32 = 4;
+
+#[derive(Clone, Copy, Debug)]
+struct StarSample {
+    position: Point3<f32>,
+    color: LinSrgb,
+    luminosity: f32,
+}
+
+#[derive(Debug, Default)]
+struct CellSum {
+    weight: f32,
+    position: Vector3<f32>,
+    color: LinSrgb,
+    luminosity: f32,
+}
+
+fn build_chunks(stars: &[StarSample]) -> Vec<StarChunk> {
+    let cell_size = CHUNK_SIZE / CELLS_PER_AXIS as f32;
+    let cell = |position: &Point3<f32>| {
+        (
+            (position.x / cell_size).floor() as i32,
+            (position.y / cell_size).floor() as i32,
+            (position.z / cell_size).floor() as i32,
+        )
+    };
+
+    // ordered maps, so that the output doesn't depend on hashing.
+    let mut chunks: BTreeMap<_, (u32, BTreeMap<_, CellSum>)> = BTreeMap::new();
+
+    for star in stars {
+        let coords = ChunkCoords::containing(&star.position);
+        let (num_stars, cells) = chunks.entry(coords).or_default();
+        *num_stars += 1;
+
+        // dim stars still need some weight, or they'd vanish from the mean.
+        let weight = star.luminosity.max(f32::EPSILON);
+        let sum = cells.entry(cell(&star.position)).or_default();
+        sum.weight += weight;
+        sum.position += weight * star.position.coords;
+        sum.color += star.color * weight;
+        sum.luminosity += star.luminosity;
+    }
+
+    chunks
+        .into_iter()
+        .map(|(coords, (num_stars, cells))| {
+            let mut points = cells
+                .into_values()
+                .map(|sum| {
+                    ImpostorPoint {
+                        position: Point3::from(sum.position / sum.weight),
+                        color: sum.color / sum.weight,
+                        luminosity: sum.luminosity,
+                    }
+                })
+                .collect::<Vec<_>>();
+            points.sort_by(|a, b| b.luminosity.total_cmp(&a.luminosity));
+
+            StarChunk {
+                coords,
+                num_stars,
+                points,
+            }
+        })
+        .collect()
+}
+
+/// Recomputes the impostors of all chunks and returns the number of chunks.
+pub async fn recompute(context: &Context) -> Result<usize, Error> {
+    let mut tx = context.transaction().await?;
+
+    let stars = sqlx::query!(
+        r#"
+        SELECT
+            position AS "position: Vec3",
+            color AS "color: Rgb",
+            luminousity
+        FROM star
+        "#
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        StarSample {
+            position: row.position.into(),
+            color: row.color.into(),
+            luminosity: row.luminousity,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let chunks = tokio::task::spawn_blocking(move || build_chunks(&stars)).await?;
+
+    sqlx::query!("DELETE FROM star_chunk")
+        .execute(&mut **tx)
+        .await?;
+
+    for chunk in &chunks {
+        sqlx::query!(
+            r#"
+            INSERT INTO star_chunk (x, y, z, num_stars, points)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            chunk.coords.x,
+            chunk.coords.y,
+            chunk.coords.z,
+            chunk.num_stars as i32,
+            Json(&chunk.points) as _,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(num_chunks = chunks.len(), "computed star impostors");
+
+    Ok(chunks.len())
+}
+
+/// Computes the impostors, if they haven't been computed yet.
+pub async fn backfill(context: Context) {
+    let result = async {
+        let mut tx = context.transaction().await?;
+        let computed = sqlx::query!(r#"SELECT EXISTS(SELECT 1 FROM star_chunk) AS "exists!""#)
+            .fetch_one(&mut **tx)
+            .await?
+            .exists;
+        tx.rollback().await?;
+
+        if !computed {
+            recompute(&context).await?;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(?error, "failed to compute star impostors");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Point3;
+    use palette::LinSrgb;
+
+    use super::{
+        build_chunks,
+        StarSample,
+    };
+
+    #[test]
+    fn it_merges_stars_in_a_cell() {
+        let stars = [
+            StarSample {
+                position: Point3::new(1.0, 1.0, 1.0),
+                color: LinSrgb::new(1.0, 0.0, 0.0),
+                luminosity: 3.0,
+            },
+            StarSample {
+                position: Point3::new(2.0, 1.0, 1.0),
+                color: LinSrgb::new(0.0, 0.0, 1.0),
+                luminosity: 1.0,
+            },
+            // in another chunk
+            StarSample {
+                position: Point3::new(-1.0, 1.0, 1.0),
+                color: LinSrgb::new(1.0, 1.0, 1.0),
+                luminosity: 1.0,
+            },
+        ];
+
+        let chunks = build_chunks(&stars);
+        assert_eq!(chunks.len(), 2);
+
+        let chunk = chunks.iter().find(|chunk| chunk.coords.x == 0).unwrap();
+        assert_eq!(chunk.num_stars, 2);
+        assert_eq!(chunk.points.len(), 1);
+
+        let point = &chunk.points[0];
+        assert_eq!(point.luminosity, 4.0);
+        assert!((point.position.x - 1.25).abs() < 1e-6);
+        assert!((point.color.red - 0.75).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,94 @@
+//! Append-only journal of events per faction.
+//!
+//! Entries are stored in the database, and published to the session streams
+//! of connected clients once the transaction that appended them is committed.
+
+use kardashev_protocol::model::{
+    faction::FactionId,
+    journal::{
+        JournalEntry,
+        JournalEntryId,
+        JournalEvent,
+        JournalEventKind,
+    },
+};
+use sqlx::types::Json;
+use tokio::sync::broadcast;
+
+use crate::{
+    context::Transaction,
+    error::Error,
+};
+
+#[derive(Clone, Copy, Debug, sqlx::Type)]
+#[sqlx(type_name = "journal_event_kind", rename_all = "kebab-case")]
+pub enum JournalEventKindColumn {
+    Battle,
+    Discovery,
+    ColonyFounded,
+}
+
+impl From<JournalEventKind> for JournalEventKindColumn {
+    fn from(kind: JournalEventKind) -> Self {
+        match kind {
+            JournalEventKind::Battle => Self::Battle,
+            JournalEventKind::Discovery => Self::Discovery,
+            JournalEventKind::ColonyFounded => Self::ColonyFounded,
+        }
+    }
+}
+
+/// Publishes journal entries to subscribers.
+#[derive(Clone, Debug)]
+pub struct Journal {
+    tx: broadcast::Sender<JournalEntry>,
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        let (tx, _rx) = broadcast::channel(256);
+        Self { tx }
+    }
+}
+
+impl Journal {
+    pub fn subscribe(&self) -> broadcast::Receiver<JournalEntry> {
+        self.tx.subscribe()
+    }
+
+    /// Publishes entries. This must only be called after the entries have been
+    /// committed.
+    pub fn publish(&self, entries: impl IntoIterator<Item = JournalEntry>) {
+        for entry in entries {
+            // this only fails if nobody is subscribed.
+            let _ = self.tx.send(entry);
+        }
+    }
+}
+
+/// Appends an event to a faction's journal.
+pub async fn append(
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+    event: JournalEvent,
+) -> Result<JournalEntry, Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO journal_entry (faction_id, kind, event)
+        VALUES ($1, $2, $3)
+        RETURNING id, time
+        "#,
+        faction.0,
+        JournalEventKindColumn::from(event.kind()) as _,
+        Json(&event) as _,
+    )
+    .fetch_one(&mut ***tx)
+    .await?;
+
+    Ok(JournalEntry {
+        id: JournalEntryId(row.id),
+        faction,
+        time: row.time,
+        event,
+    })
+}
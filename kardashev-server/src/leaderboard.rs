@@ -0,0 +1,86 @@
+//! Periodic computation of the factions' ratings on the Kardashev scale.
+//!
+//! The ratings are stored with the time they were computed, so that the
+//! leaderboard can show how they progressed.
+
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use crate::{
+    context::Context,
+    error::Error,
+};
+
+/// Computes and stores the current rating of every faction.
+pub async fn compute_ratings(context: &Context) -> Result<(), Error> {
+    let mut tx = context.transaction().await?;
+    let computed_at = Utc::now();
+
+    // the power is computed per colony, since the formula needn't be linear in the
+    // luminosity.
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            faction.faction_id,
+            star.luminousity::DOUBLE PRECISION AS "luminosity?"
+        FROM faction
+        LEFT JOIN colony ON colony.faction_id = faction.faction_id
+        LEFT JOIN star ON star.id = colony.star_id
+        "#,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let mut powers = HashMap::new();
+    for row in rows {
+        let power = powers.entry(row.faction_id).or_insert(0.0);
+        if let Some(luminosity) = row.luminosity {
+            *power += context.rules.colony_power(luminosity);
+        }
+    }
+
+    let num_factions = powers.len();
+    for (faction_id, power) in powers {
+        let rating = context.rules.kardashev_rating(power);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO kardashev_rating (faction_id, computed_at, power, rating)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            faction_id,
+            computed_at,
+            power,
+            rating,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::debug!(num_factions, "computed kardashev ratings");
+
+    Ok(())
+}
+
+/// Computes the ratings every `interval`, until the server shuts down.
+pub async fn run(context: Context, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            _ = interval.tick() => {
+                if let Err(error) = compute_ratings(&context).await {
+                    tracing::error!(?error, "failed to compute kardashev ratings");
+                }
+            }
+        }
+    }
+}
@@ -1,20 +1,84 @@
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
 use axum::Router;
 use sqlx::PgPool;
 use tokio_util::sync::CancellationToken;
 
-use crate::context::Context;
+use crate::{
+    backup::Backups,
+    context::Context,
+    modules::Modules,
+    scripting::Rules,
+    worlds::{
+        JobsConfig,
+        Worlds,
+    },
+};
 
+mod activity;
 mod api;
+mod asset_stats;
+mod backup;
+mod client_capabilities;
+mod client_errors;
 mod context;
+#[cfg(feature = "embedded-db")]
+mod embedded_db;
 mod error;
+mod impostors;
+mod journal;
+mod leaderboard;
+pub mod modules;
+mod names;
+mod regions;
+mod replay;
+mod scripting;
+mod simulation;
+mod spatial;
+mod star_audit;
+mod stars;
+mod state_hash;
 mod util;
+mod visibility;
+mod webhooks;
+mod worlds;
 
-pub use crate::error::Error;
+#[cfg(feature = "embedded-db")]
+pub use crate::embedded_db::{
+    EmbeddedDb,
+    EmbeddedDbConfig,
+    EmbeddedDbError,
+};
+pub use crate::{
+    asset_stats::{
+        record_asset_download,
+        AssetStats,
+        StorageRedirect,
+    },
+    backup::BackupConfig,
+    error::Error,
+    regions::RegionConfig,
+    scripting::RulesConfig,
+    simulation::SimulationConfig,
+    worlds::PendingMigrations,
+};
 
 #[derive(Clone, Debug, Default)]
 pub struct Builder {
     shutdown: Option<CancellationToken>,
     db: Option<PgPool>,
+    leaderboard_interval: Option<Duration>,
+    simulation: SimulationConfig,
+    regions: RegionConfig,
+    asset_stats: AssetStats,
+    backups: Backups,
+    rules: Rules,
+    hot_reload_rules: bool,
+    modules: Option<Modules>,
+    admin_token: Option<Arc<str>>,
 }
 
 impl Builder {
@@ -28,18 +92,130 @@ impl Builder {
         self
     }
 
+    /// Enables the job that computes the factions' ratings for the leaderboard
+    /// every `interval`. Without it, no ratings are recorded.
+    pub fn with_leaderboard_interval(mut self, interval: Duration) -> Self {
+        self.leaderboard_interval = Some(interval);
+        self
+    }
+
+    pub fn with_simulation(mut self, simulation: SimulationConfig) -> Self {
+        self.simulation = simulation;
+        self
+    }
+
+    /// Sets how stars are clustered into regions.
+    pub fn with_regions(mut self, regions: RegionConfig) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Sets the statistics of the served assets, which are reported by the
+    /// admin API.
+    pub fn with_asset_stats(mut self, asset_stats: AssetStats) -> Self {
+        self.asset_stats = asset_stats;
+        self
+    }
+
+    /// Enables backups.
+    ///
+    /// Fails if the target isn't a supported object store URL.
+    pub fn with_backups(mut self, config: BackupConfig) -> Result<Self, Error> {
+        self.backups = Backups::new(config)?;
+        Ok(self)
+    }
+
+    /// Loads the scripts that define gameplay formulas, see
+    /// [`scripting`](crate::scripting).
+    ///
+    /// Fails if a script doesn't run.
+    pub fn with_rules(mut self, config: RulesConfig) -> Result<Self, Error> {
+        self.rules = Rules::new(&config)?;
+        self.hot_reload_rules = config.hot_reload;
+        Ok(self)
+    }
+
+    /// Sets the modules of game rules the server runs. Defaults to
+    /// [`Modules::builtin`].
+    pub fn with_modules(mut self, modules: Modules) -> Self {
+        self.modules = Some(modules);
+        self
+    }
+
+    /// Sets the token with which admins see the whole universe, instead of
+    /// only what is visible to everyone. Without it, nobody does.
+    pub fn with_admin_token(mut self, token: impl Into<Arc<str>>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
     pub async fn with_connect_db(self, database_url: &str) -> Result<Self, Error> {
         let db = PgPool::connect(database_url).await?;
         Ok(self.with_db(db))
     }
 
+    /// Runs the database migrations for all worlds.
+    ///
+    /// The database must have been set before.
+    pub async fn migrate(self) -> Result<Self, Error> {
+        crate::worlds::migrate(
+            self.db.as_ref().expect("no database provided"),
+            &self.modules(),
+        )
+        .await?;
+        Ok(self)
+    }
+
+    /// Returns the database migrations that haven't been run yet, for all
+    /// worlds.
+    ///
+    /// The database must have been set before.
+    pub async fn pending_migrations(&self) -> Result<Vec<PendingMigrations>, Error> {
+        crate::worlds::pending_migrations(
+            self.db.as_ref().expect("no database provided"),
+            &self.modules(),
+        )
+        .await
+    }
+
+    fn modules(&self) -> Modules {
+        self.modules.clone().unwrap_or_else(Modules::builtin)
+    }
+
+    /// Builds the API router.
+    ///
+    /// This spawns background jobs for every world, so it must be called from
+    /// within a tokio runtime.
     pub fn build(self) -> Router<()> {
+        let modules = self.modules();
+        tracing::info!(modules = ?modules, "building server");
+
         let mut context = Context::new(self.db.expect("no database provided"));
 
         if let Some(shutdown) = self.shutdown {
             context.shutdown = shutdown;
         }
+        context.regions = self.regions;
+        context.asset_stats = self.asset_stats;
+        context.backups = self.backups;
+        context.rules = self.rules;
+        context.admin_token = self.admin_token;
+        context.modules = modules.clone();
+
+        if self.hot_reload_rules {
+            tokio::spawn(context.rules.clone().hot_reload(context.shutdown.clone()));
+        }
+
+        let worlds = Worlds::new(
+            context,
+            JobsConfig {
+                simulation: self.simulation,
+                leaderboard_interval: self.leaderboard_interval,
+                modules: modules.clone(),
+            },
+        );
+        tokio::spawn(worlds.clone().load());
 
-        crate::api::router().with_state(context)
+        crate::api::router(&modules, &worlds).with_state(worlds)
     }
 }
@@ -0,0 +1,425 @@
+//! Relations between factions, and battles between fleets of factions at war.
+//!
+//! Fleets that come within [`ENGAGEMENT_RANGE`] of a fleet of a faction their
+//! own faction is at war with are intercepted: the fleets in the battle lose
+//! their orders, and the factions involved get a journal entry. Fleets that
+//! stay in range without new orders don't fight again.
+
+use std::{
+    collections::{
+        BTreeSet,
+        HashSet,
+    },
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    Router,
+};
+use kardashev_protocol::model::{
+    diplomacy::Stance,
+    faction::FactionId,
+    fleet::FleetId,
+    journal::{
+        JournalEntry,
+        JournalEvent,
+    },
+    star::StarId,
+};
+use nalgebra::Point3;
+use sqlx::migrate::Migrator;
+
+use crate::{
+    api::diplomacy::{
+        ordered,
+        StanceColumn,
+    },
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    journal,
+    modules::{
+        Module,
+        SensorSharing,
+        SimulationStep,
+    },
+    state_hash::StateHasher,
+    util::sqlx::Vec3,
+    worlds::Worlds,
+};
+
+/// Distance in light years within which hostile fleets fight.
+pub const ENGAGEMENT_RANGE: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DiplomacyModule;
+
+impl Module for DiplomacyModule {
+    fn name(&self) -> &'static str {
+        "diplomacy"
+    }
+
+    fn router(&self) -> Router<Worlds> {
+        crate::api::diplomacy::router()
+    }
+
+    fn migrator(&self) -> Option<Migrator> {
+        Some(sqlx::migrate!("./migrations/diplomacy"))
+    }
+
+    fn simulation_steps(&self) -> Vec<Arc<dyn SimulationStep>> {
+        vec![Arc::new(ExpireTreaties), Arc::new(Battles)]
+    }
+
+    fn sensor_sharing(&self) -> Option<Arc<dyn SensorSharing>> {
+        Some(Arc::new(Alliances))
+    }
+}
+
+/// Allies see what each other's fleets see.
+#[derive(Clone, Copy, Debug)]
+struct Alliances;
+
+#[async_trait]
+impl SensorSharing for Alliances {
+    async fn shared_with(
+        &self,
+        tx: &mut Transaction<'_>,
+        faction: FactionId,
+    ) -> Result<Vec<FactionId>, Error> {
+        fetch_allies(tx, faction).await
+    }
+}
+
+/// Returns the factions that `faction` is allied with.
+async fn fetch_allies(
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+) -> Result<Vec<FactionId>, Error> {
+    let allies = sqlx::query_scalar!(
+        r#"
+        SELECT CASE WHEN faction_a = $1 THEN faction_b ELSE faction_a END AS "faction!"
+        FROM relation
+        WHERE (faction_a = $1 OR faction_b = $1) AND stance = 'alliance'
+        "#,
+        faction.0,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(FactionId)
+    .collect();
+
+    Ok(allies)
+}
+
+/// Counts down the durations of treaties, and ends the ones that expired.
+#[derive(Clone, Copy, Debug)]
+struct ExpireTreaties;
+
+#[async_trait]
+impl SimulationStep for ExpireTreaties {
+    async fn run(
+        &self,
+        _context: &Context,
+        tx: &mut Transaction<'_>,
+        days: f32,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        sqlx::query!(
+            r#"
+            UPDATE relation
+            SET treaty_remaining_days = treaty_remaining_days - $1
+            WHERE treaty_remaining_days IS NOT NULL
+            "#,
+            days,
+        )
+        .execute(&mut ***tx)
+        .await?;
+
+        let expired = sqlx::query!(
+            r#"
+            UPDATE relation
+            SET
+                stance = COALESCE(treaty_reverts_to, 'peace'),
+                since = utc_now(),
+                treaty_signed_at = NULL,
+                treaty_remaining_days = NULL,
+                treaty_reverts_to = NULL
+            WHERE treaty_remaining_days <= 0
+            "#,
+        )
+        .execute(&mut ***tx)
+        .await?;
+        if expired.rows_affected() > 0 {
+            tracing::debug!(expired = expired.rows_affected(), "treaties expired");
+        }
+
+        Ok(vec![])
+    }
+
+    async fn hash_state(
+        &self,
+        tx: &mut Transaction<'_>,
+        hasher: &mut StateHasher,
+    ) -> Result<(), Error> {
+        // when the stances changed is wall-clock time, so it's not hashed.
+        let relations = sqlx::query!(
+            r#"
+            SELECT
+                faction_a,
+                faction_b,
+                stance AS "stance: StanceColumn",
+                treaty_remaining_days
+            FROM relation
+            ORDER BY faction_a, faction_b
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?;
+
+        hasher.section("relations").write(&relations.len());
+        for row in relations {
+            hasher
+                .write(&row.faction_a)
+                .write(&row.faction_b)
+                .write(&Stance::from(row.stance))
+                .write(&row.treaty_remaining_days);
+        }
+
+        Ok(())
+    }
+}
+
+/// Fights battles between fleets of factions at war.
+#[derive(Clone, Copy, Debug)]
+struct Battles;
+
+#[async_trait]
+impl SimulationStep for Battles {
+    async fn run(
+        &self,
+        _context: &Context,
+        tx: &mut Transaction<'_>,
+        _days: f32,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        fight_battles(tx).await
+    }
+}
+
+/// A fleet that can take part in a battle.
+#[derive(Clone, Debug)]
+struct CombatFleet {
+    id: FleetId,
+    position: Point3<f32>,
+    faction: FactionId,
+    has_orders: bool,
+}
+
+/// Groups the fleets into battles.
+///
+/// Fleets of factions for which `is_hostile` returns `true` fight if they're
+/// within [`ENGAGEMENT_RANGE`] of each other, and fleets that fight the same
+/// enemy are in the same battle. Returns the indices of the fleets in each
+/// battle, ordered by the smallest index.
+fn find_battles(
+    fleets: &[CombatFleet],
+    is_hostile: impl Fn(FactionId, FactionId) -> bool,
+) -> Vec<Vec<usize>> {
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    // union-find over the fleets, with an edge for every pair of hostile fleets
+    // in range.
+    let mut parents = (0..fleets.len()).collect::<Vec<_>>();
+
+    let mut engaged = vec![false; fleets.len()];
+    for (i, a) in fleets.iter().enumerate() {
+        for (j, b) in fleets.iter().enumerate().skip(i + 1) {
+            if a.faction == b.faction
+                || (a.position - b.position).norm() > ENGAGEMENT_RANGE
+                || !is_hostile(a.faction, b.faction)
+            {
+                continue;
+            }
+            engaged[i] = true;
+            engaged[j] = true;
+            let (root_a, root_b) = (root(&mut parents, i), root(&mut parents, j));
+            parents[root_a.max(root_b)] = root_a.min(root_b);
+        }
+    }
+
+    let mut battles: Vec<(usize, Vec<usize>)> = vec![];
+    for (i, engaged) in engaged.into_iter().enumerate() {
+        if !engaged {
+            continue;
+        }
+        let root = root(&mut parents, i);
+        match battles
+            .iter_mut()
+            .find(|(battle_root, _)| *battle_root == root)
+        {
+            Some((_, battle)) => battle.push(i),
+            None => battles.push((root, vec![i])),
+        }
+    }
+
+    battles.into_iter().map(|(_, battle)| battle).collect()
+}
+
+/// Stops fleets that met hostile fleets, and records the battles in the
+/// journals of the factions involved.
+async fn fight_battles(tx: &mut Transaction<'_>) -> Result<Vec<JournalEntry>, Error> {
+    let hostile = sqlx::query!("SELECT faction_a, faction_b FROM relation WHERE stance = 'war'")
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| (row.faction_a, row.faction_b))
+        .collect::<HashSet<_>>();
+    if hostile.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let fleets = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3",
+            faction_id AS "faction_id!",
+            EXISTS(SELECT 1 FROM fleet_order WHERE fleet_id = fleet.id) AS "has_orders!"
+        FROM fleet
+        WHERE faction_id IS NOT NULL
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        CombatFleet {
+            id: FleetId(row.id),
+            position: row.position.into(),
+            faction: FactionId(row.faction_id),
+            has_orders: row.has_orders,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let battles = find_battles(&fleets, |a, b| hostile.contains(&ordered(a, b)));
+
+    let mut entries = vec![];
+    for battle in battles {
+        let battle = battle.into_iter().map(|i| &fleets[i]).collect::<Vec<_>>();
+        if !battle.iter().any(|fleet| fleet.has_orders) {
+            continue;
+        }
+
+        let fleet_ids = battle.iter().map(|fleet| fleet.id).collect::<Vec<_>>();
+        let ids = fleet_ids.iter().map(|fleet| fleet.0).collect::<Vec<_>>();
+        sqlx::query!("DELETE FROM fleet_order WHERE fleet_id = ANY($1)", &ids)
+            .execute(&mut ***tx)
+            .await?;
+        sqlx::query!("UPDATE fleet SET order_work = 0 WHERE id = ANY($1)", &ids)
+            .execute(&mut ***tx)
+            .await?;
+
+        let center = battle.iter().fold(Point3::origin(), |center, fleet| {
+            center + fleet.position.coords
+        }) / battle.len() as f32;
+        let Some(star) = sqlx::query_scalar!(
+            r#"
+            SELECT id
+            FROM star
+            ORDER BY ((position).x - $1) ^ 2 + ((position).y - $2) ^ 2 + ((position).z - $3) ^ 2
+            LIMIT 1
+            "#,
+            center.x,
+            center.y,
+            center.z,
+        )
+        .fetch_optional(&mut ***tx)
+        .await?
+        else {
+            continue;
+        };
+
+        let factions = battle
+            .iter()
+            .map(|fleet| fleet.faction.0)
+            .collect::<BTreeSet<_>>();
+        for faction in factions {
+            entries.push(
+                journal::append(
+                    tx,
+                    FactionId(faction),
+                    JournalEvent::Battle {
+                        star: StarId(star),
+                        fleets: fleet_ids.clone(),
+                    },
+                )
+                .await?,
+            );
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::{
+        model::{
+            faction::FactionId,
+            fleet::FleetId,
+        },
+        uuid::Uuid,
+    };
+    use nalgebra::Point3;
+
+    use super::{
+        find_battles,
+        CombatFleet,
+    };
+
+    #[test]
+    fn hostile_fleets_in_range_fight() {
+        let fleet = |i: u128, faction: u128, x: f32| {
+            CombatFleet {
+                id: FleetId(Uuid::from_u128(i)),
+                position: Point3::new(x, 0.0, 0.0),
+                faction: FactionId(Uuid::from_u128(faction)),
+                has_orders: true,
+            }
+        };
+        let fleets = vec![
+            fleet(0, 1, 0.0),
+            fleet(1, 2, 0.05),
+            // at peace with both.
+            fleet(2, 3, 0.05),
+            // out of range.
+            fleet(3, 2, 5.0),
+            // fights fleet 1, so it's in the same battle.
+            fleet(4, 1, 0.12),
+            // a separate battle.
+            fleet(5, 1, 10.0),
+            fleet(6, 2, 10.0),
+        ];
+        let at_war = |a: FactionId, b: FactionId| {
+            let mut pair = [a.0.as_u128(), b.0.as_u128()];
+            pair.sort();
+            pair == [1, 2]
+        };
+
+        assert_eq!(
+            find_battles(&fleets, at_war),
+            vec![vec![0, 1, 4], vec![5, 6]]
+        );
+    }
+}
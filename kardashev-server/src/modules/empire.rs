@@ -0,0 +1,21 @@
+//! Empires, i.e. the colonies and fleets of a faction.
+
+use axum::Router;
+
+use crate::{
+    modules::Module,
+    worlds::Worlds,
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmpireModule;
+
+impl Module for EmpireModule {
+    fn name(&self) -> &'static str {
+        "empire"
+    }
+
+    fn router(&self) -> Router<Worlds> {
+        crate::api::empire::router()
+    }
+}
@@ -0,0 +1,530 @@
+//! Fleets, which move along their order queues every simulation epoch, and
+//! explore the stars their sensors observe.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    Router,
+};
+use kardashev_protocol::model::{
+    empire::ColonyId,
+    faction::FactionId,
+    fleet::{
+        advance_fleet,
+        FleetId,
+        Order,
+        OrderKind,
+    },
+    journal::{
+        JournalEntry,
+        JournalEvent,
+    },
+    star::StarId,
+};
+use nalgebra::Point3;
+
+use crate::{
+    api::fleet::fetch_orders,
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    journal,
+    modules::{
+        Module,
+        Modules,
+        SimulationStep,
+    },
+    state_hash::StateHasher,
+    util::sqlx::Vec3,
+    visibility::{
+        Viewer,
+        Visibility,
+    },
+    worlds::Worlds,
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FleetsModule;
+
+impl Module for FleetsModule {
+    fn name(&self) -> &'static str {
+        "fleets"
+    }
+
+    fn router(&self) -> Router<Worlds> {
+        crate::api::fleet::router()
+    }
+
+    fn simulation_steps(&self) -> Vec<Arc<dyn SimulationStep>> {
+        vec![Arc::new(MoveFleets), Arc::new(ExploreStars)]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct MoveFleets;
+
+#[async_trait]
+impl SimulationStep for MoveFleets {
+    async fn run(
+        &self,
+        _context: &Context,
+        tx: &mut Transaction<'_>,
+        days: f32,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        move_fleets(tx, days).await
+    }
+
+    async fn hash_state(
+        &self,
+        tx: &mut Transaction<'_>,
+        hasher: &mut StateHasher,
+    ) -> Result<(), Error> {
+        let orders = fetch_orders(tx, None).await?;
+        let fleets = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                position AS "position: Vec3",
+                speed,
+                faction_id,
+                order_work
+            FROM fleet
+            ORDER BY id
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            FleetState {
+                id: FleetId(row.id),
+                position: row.position.into(),
+                speed: row.speed,
+                faction: row.faction_id.map(FactionId),
+                order_work: row.order_work,
+            }
+        })
+        .collect::<Vec<_>>();
+        hash_fleets(hasher, &fleets, &orders);
+
+        // what the fleets' orders did.
+        let explored = sqlx::query!(
+            "SELECT faction_id, star_id FROM explored_star ORDER BY faction_id, star_id"
+        )
+        .fetch_all(&mut ***tx)
+        .await?;
+        hasher.section("explored_stars").write(&explored.len());
+        for row in explored {
+            hasher.write(&row.faction_id).write(&row.star_id);
+        }
+
+        // colony IDs are random, so they're not hashed.
+        let colonies =
+            sqlx::query!("SELECT faction_id, star_id FROM colony ORDER BY faction_id, star_id")
+                .fetch_all(&mut ***tx)
+                .await?;
+        hasher.section("colonies").write(&colonies.len());
+        for row in colonies {
+            hasher.write(&row.faction_id).write(&row.star_id);
+        }
+
+        Ok(())
+    }
+}
+
+/// Explores the stars that factions observe, after the fleets moved.
+///
+/// What was explored is hashed by [`MoveFleets`].
+#[derive(Clone, Copy, Debug)]
+struct ExploreStars;
+
+#[async_trait]
+impl SimulationStep for ExploreStars {
+    async fn run(
+        &self,
+        context: &Context,
+        tx: &mut Transaction<'_>,
+        _days: f32,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        explore_stars(tx, &context.modules).await
+    }
+}
+
+/// The part of a fleet that the simulation changes.
+#[derive(Clone, Debug)]
+struct FleetState {
+    id: FleetId,
+    position: Point3<f32>,
+    speed: f32,
+    faction: Option<FactionId>,
+    order_work: f32,
+}
+
+impl FleetState {
+    /// Advances the fleet along its orders, and returns how many of them it
+    /// completed.
+    fn advance(
+        &mut self,
+        orders: &[Order],
+        star_positions: &HashMap<StarId, Point3<f32>>,
+        days: f32,
+    ) -> usize {
+        let advance = advance_fleet(
+            self.position,
+            self.speed,
+            self.order_work,
+            orders.iter().map(|order| &order.kind),
+            |star| star_positions.get(&star).copied(),
+            days,
+        );
+        self.position = advance.position;
+        self.order_work = advance.work;
+        advance.completed
+    }
+}
+
+/// Hashes the fleets and their order queues, ordered by the fleets' IDs.
+fn hash_fleets(
+    hasher: &mut StateHasher,
+    fleets: &[FleetState],
+    orders: &HashMap<FleetId, Vec<Order>>,
+) {
+    let mut fleets = fleets.iter().collect::<Vec<_>>();
+    fleets.sort_by_key(|fleet| fleet.id.0);
+
+    hasher.section("fleets").write(&fleets.len());
+    for fleet in fleets {
+        hasher
+            .write(&fleet.id)
+            .write(&fleet.position)
+            .write(&fleet.speed)
+            .write(&fleet.faction)
+            .write(&fleet.order_work);
+
+        let orders = orders.get(&fleet.id).map_or(&[][..], Vec::as_slice);
+        hasher.write(&orders.len());
+        for order in orders {
+            hasher.write(&order.id).write(&order.kind);
+        }
+    }
+}
+
+/// Advances all fleets along their order queues, and executes the orders
+/// they complete.
+async fn move_fleets(tx: &mut Transaction<'_>, days: f32) -> Result<Vec<JournalEntry>, Error> {
+    let mut orders = fetch_orders(tx, None).await?;
+    if orders.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let fleets = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3",
+            speed,
+            faction_id,
+            order_work
+        FROM fleet
+        WHERE id = ANY($1)
+        FOR UPDATE
+        "#,
+        &orders.keys().map(|fleet_id| fleet_id.0).collect::<Vec<_>>(),
+    )
+    .fetch_all(&mut ***tx)
+    .await?;
+
+    let star_ids = orders
+        .values()
+        .flatten()
+        .map(|order| order.kind.star().0)
+        .collect::<Vec<_>>();
+    let star_positions = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3"
+        FROM star
+        WHERE id = ANY($1)
+        "#,
+        &star_ids,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| (StarId(row.id), Point3::from(row.position)))
+    .collect::<HashMap<_, _>>();
+
+    let mut entries = vec![];
+    for fleet in fleets {
+        let mut fleet = FleetState {
+            id: FleetId(fleet.id),
+            position: fleet.position.into(),
+            speed: fleet.speed,
+            faction: fleet.faction_id.map(FactionId),
+            order_work: fleet.order_work,
+        };
+        let fleet_orders = orders.remove(&fleet.id).unwrap_or_default();
+        let completed = fleet.advance(&fleet_orders, &star_positions, days);
+
+        sqlx::query!(
+            "UPDATE fleet SET position = $2, order_work = $3 WHERE id = $1",
+            fleet.id.0,
+            Vec3::from(fleet.position) as _,
+            fleet.order_work,
+        )
+        .execute(&mut ***tx)
+        .await?;
+
+        let completed = &fleet_orders[..completed];
+        if completed.is_empty() {
+            continue;
+        }
+
+        sqlx::query!(
+            "DELETE FROM fleet_order WHERE id = ANY($1)",
+            &completed.iter().map(|order| order.id.0).collect::<Vec<_>>(),
+        )
+        .execute(&mut ***tx)
+        .await?;
+
+        if let Some(faction) = fleet.faction {
+            for order in completed {
+                entries.extend(complete_order(tx, faction, &order.kind).await?);
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Records the stars within sensor range as explored by the factions that
+/// observe them, including through their allies' sensors.
+async fn explore_stars(
+    tx: &mut Transaction<'_>,
+    modules: &Modules,
+) -> Result<Vec<JournalEntry>, Error> {
+    let stars = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3"
+        FROM star
+        WHERE EXISTS (
+            SELECT 1 FROM fleet
+            WHERE
+                sensor_range > 0
+                AND ((star.position).x - (fleet.position).x) ^ 2
+                    + ((star.position).y - (fleet.position).y) ^ 2
+                    + ((star.position).z - (fleet.position).z) ^ 2
+                    <= sensor_range ^ 2
+        )
+        "#,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| (StarId(row.id), Point3::from(row.position)))
+    .collect::<Vec<_>>();
+    if stars.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let factions = sqlx::query_scalar!("SELECT faction_id FROM faction ORDER BY faction_id")
+        .fetch_all(&mut ***tx)
+        .await?;
+
+    let mut entries = vec![];
+    for faction in factions {
+        let visibility =
+            Visibility::load(tx, &Viewer::faction(FactionId(faction)), modules).await?;
+        entries.extend(visibility.record_explored(tx, &stars).await?);
+    }
+
+    Ok(entries)
+}
+
+/// Executes the effect of a completed order.
+async fn complete_order(
+    tx: &mut Transaction<'_>,
+    faction: FactionId,
+    order: &OrderKind,
+) -> Result<Option<JournalEntry>, Error> {
+    match order {
+        OrderKind::Move { .. } => Ok(None),
+        OrderKind::Survey { star } => {
+            let explored = sqlx::query!(
+                r#"
+                INSERT INTO explored_star (faction_id, star_id)
+                SELECT $1, id FROM star WHERE id = $2
+                ON CONFLICT DO NOTHING
+                RETURNING star_id
+                "#,
+                faction.0,
+                star.0,
+            )
+            .fetch_optional(&mut ***tx)
+            .await?;
+
+            if explored.is_none() {
+                return Ok(None);
+            }
+            let entry =
+                journal::append(tx, faction, JournalEvent::Discovery { star: *star }).await?;
+            Ok(Some(entry))
+        }
+        OrderKind::Colonize { star } => {
+            // the order fails if the star was deleted, or already has a colony.
+            let row = sqlx::query!(
+                r#"
+                INSERT INTO colony (faction_id, star_id, name)
+                SELECT $1, id, COALESCE(name, 'Colony')
+                FROM star
+                WHERE id = $2
+                ON CONFLICT (star_id) DO NOTHING
+                RETURNING id
+                "#,
+                faction.0,
+                star.0,
+            )
+            .fetch_optional(&mut ***tx)
+            .await?;
+            let Some(row) = row
+            else {
+                tracing::debug!(faction = %faction.0, star = %star.0, "colonization failed");
+                return Ok(None);
+            };
+
+            let entry = journal::append(
+                tx,
+                faction,
+                JournalEvent::ColonyFounded {
+                    colony: ColonyId(row.id),
+                    star: *star,
+                },
+            )
+            .await?;
+            Ok(Some(entry))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use kardashev_protocol::{
+        model::{
+            faction::FactionId,
+            fleet::{
+                FleetId,
+                Order,
+                OrderId,
+                OrderKind,
+            },
+            star::StarId,
+        },
+        uuid::Uuid,
+    };
+    use nalgebra::Point3;
+
+    use super::{
+        hash_fleets,
+        FleetState,
+    };
+    use crate::state_hash::{
+        replay::{
+            assert_deterministic,
+            Replay,
+        },
+        StateHasher,
+    };
+
+    /// Fleets moving between stars, like [`move_fleets`](super::move_fleets)
+    /// does in the database.
+    struct Fleets {
+        fleets: Vec<FleetState>,
+        orders: HashMap<FleetId, Vec<Order>>,
+        star_positions: HashMap<StarId, Point3<f32>>,
+    }
+
+    impl Replay for Fleets {
+        fn run_epoch(&mut self, days: f32) {
+            for fleet in &mut self.fleets {
+                let orders = self.orders.entry(fleet.id).or_default();
+                let completed = fleet.advance(orders, &self.star_positions, days);
+                orders.drain(..completed);
+            }
+        }
+
+        fn hash_state(&self, hasher: &mut StateHasher) {
+            hash_fleets(hasher, &self.fleets, &self.orders);
+        }
+    }
+
+    fn setup() -> Fleets {
+        let star = |i: u128| StarId(Uuid::from_u128(i));
+        let star_positions = (0..4)
+            .map(|i| (star(i), Point3::new(i as f32 * 3.0, 0.0, (i % 2) as f32)))
+            .collect::<HashMap<_, _>>();
+
+        let mut fleets = vec![];
+        let mut orders = HashMap::new();
+        for i in 0..8u128 {
+            let id = FleetId(Uuid::from_u128(100 + i));
+            fleets.push(FleetState {
+                id,
+                position: Point3::new(i as f32, 1.0, 0.0),
+                speed: 1.0 + i as f32 * 0.25,
+                faction: Some(FactionId(Uuid::from_u128(i % 2))),
+                order_work: 0.0,
+            });
+            orders.insert(
+                id,
+                vec![
+                    Order {
+                        id: OrderId(Uuid::from_u128(200 + 2 * i)),
+                        kind: OrderKind::Survey { star: star(i % 4) },
+                    },
+                    Order {
+                        id: OrderId(Uuid::from_u128(201 + 2 * i)),
+                        kind: OrderKind::Colonize {
+                            star: star((i + 1) % 4),
+                        },
+                    },
+                ],
+            );
+        }
+
+        Fleets {
+            fleets,
+            orders,
+            star_positions,
+        }
+    }
+
+    #[test]
+    fn moving_fleets_is_deterministic() {
+        let hashes = assert_deterministic(setup, 20, 1.0);
+
+        let mut replay = setup();
+        for _ in 0..20 {
+            replay.run_epoch(1.0);
+        }
+        assert!(
+            replay.orders.values().all(Vec::is_empty),
+            "the fleets completed their orders"
+        );
+        assert_eq!(
+            hashes.last(),
+            hashes.get(hashes.len() - 2),
+            "the state doesn't change once the fleets are idle"
+        );
+    }
+}
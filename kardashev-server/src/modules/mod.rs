@@ -0,0 +1,223 @@
+//! Modules of game rules.
+//!
+//! Gameplay subsystems, like [fleets](fleets::FleetsModule),
+//! [empires](empire::EmpireModule), [diplomacy](diplomacy::DiplomacyModule)
+//! and [trade](trade::TradeModule), are [`Module`]s. A module registers its
+//! routes, migrations, simulation steps and background jobs, so that new game
+//! features don't all have to be wired into the API router and the simulation.
+//! Which modules a deployment runs is configured with
+//! [`Builder::with_modules`](crate::Builder::with_modules).
+
+pub mod diplomacy;
+pub mod empire;
+pub mod fleets;
+pub mod trade;
+
+use std::{
+    fmt::Debug,
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    Router,
+};
+use kardashev_protocol::model::{
+    faction::FactionId,
+    journal::JournalEntry,
+};
+use sqlx::{
+    migrate::Migrator,
+    Postgres,
+};
+
+use crate::{
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    state_hash::StateHasher,
+    worlds::Worlds,
+};
+
+/// A gameplay subsystem.
+pub trait Module: Send + Sync + 'static {
+    /// Name of the module, which is logged when it's started.
+    fn name(&self) -> &'static str;
+
+    /// Routes of the module, which are merged into the API router.
+    fn router(&self) -> Router<Worlds> {
+        Router::new()
+    }
+
+    /// Migrations for the module's tables, which are run after the core
+    /// migrations, in every world. They're in `migrations/<module name>` of
+    /// the server crate, while the core migrations are in the workspace's
+    /// `migrations`.
+    ///
+    /// The versions must not collide with the ones of other modules, since
+    /// all migrations are tracked in the same table.
+    fn migrator(&self) -> Option<Migrator> {
+        None
+    }
+
+    /// Steps that are run every simulation epoch, in order.
+    fn simulation_steps(&self) -> Vec<Arc<dyn SimulationStep>> {
+        vec![]
+    }
+
+    /// Which factions share what their fleets see with each other, see
+    /// [`visibility`](crate::visibility).
+    fn sensor_sharing(&self) -> Option<Arc<dyn SensorSharing>> {
+        None
+    }
+
+    /// Spawns the module's background jobs for a world.
+    fn spawn_jobs(&self, _context: &Context) {}
+}
+
+/// Part of a simulation epoch, see [`simulation`](crate::simulation).
+#[async_trait]
+pub trait SimulationStep: Send + Sync + 'static {
+    /// Advances the simulation by `days` of game time, and returns the journal
+    /// entries to publish once the epoch is committed.
+    async fn run(
+        &self,
+        context: &Context,
+        tx: &mut Transaction<'_>,
+        days: f32,
+    ) -> Result<Vec<JournalEntry>, Error>;
+
+    /// Hashes the state the step simulates, see
+    /// [`state_hash`](crate::state_hash).
+    async fn hash_state(
+        &self,
+        _tx: &mut Transaction<'_>,
+        _hasher: &mut StateHasher,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Shares what a faction's fleets see with other factions, see
+/// [`Module::sensor_sharing`].
+#[async_trait]
+pub trait SensorSharing: Send + Sync + 'static {
+    /// Returns the factions whose fleets' sensors `faction` sees through.
+    async fn shared_with(
+        &self,
+        tx: &mut Transaction<'_>,
+        faction: FactionId,
+    ) -> Result<Vec<FactionId>, Error>;
+}
+
+/// The modules a server runs.
+#[derive(Clone, Default)]
+pub struct Modules {
+    modules: Vec<Arc<dyn Module>>,
+}
+
+impl Modules {
+    /// All modules that come with the server.
+    pub fn builtin() -> Self {
+        Self::default()
+            .with_module(fleets::FleetsModule)
+            .with_module(empire::EmpireModule)
+            .with_module(diplomacy::DiplomacyModule)
+            .with_module(trade::TradeModule)
+    }
+
+    pub fn with_module(mut self, module: impl Module) -> Self {
+        self.modules.push(Arc::new(module));
+        self
+    }
+
+    /// Removes the modules for whose name `keep` returns `false`.
+    pub fn retain(mut self, mut keep: impl FnMut(&str) -> bool) -> Self {
+        self.modules.retain(|module| keep(module.name()));
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.modules.iter().map(|module| module.name())
+    }
+
+    pub(crate) fn router(&self) -> Router<Worlds> {
+        self.modules.iter().fold(Router::new(), |router, module| {
+            router.merge(module.router())
+        })
+    }
+
+    pub(crate) fn simulation_steps(&self) -> Vec<Arc<dyn SimulationStep>> {
+        self.modules
+            .iter()
+            .flat_map(|module| module.simulation_steps())
+            .collect()
+    }
+
+    /// Returns the factions whose fleets' sensors `faction` sees through,
+    /// without `faction` itself.
+    pub(crate) async fn sensors_shared_with(
+        &self,
+        tx: &mut Transaction<'_>,
+        faction: FactionId,
+    ) -> Result<Vec<FactionId>, Error> {
+        let mut factions = vec![];
+        for module in &self.modules {
+            if let Some(sensor_sharing) = module.sensor_sharing() {
+                for other in sensor_sharing.shared_with(tx, faction).await? {
+                    if other != faction && !factions.contains(&other) {
+                        factions.push(other);
+                    }
+                }
+            }
+        }
+        Ok(factions)
+    }
+
+    pub(crate) fn spawn_jobs(&self, context: &Context) {
+        for module in &self.modules {
+            module.spawn_jobs(context);
+        }
+    }
+
+    /// The modules' migrations.
+    pub(crate) fn migrators(&self) -> impl Iterator<Item = Migrator> + '_ {
+        self.modules.iter().filter_map(|module| module.migrator())
+    }
+
+    /// Runs the modules' migrations.
+    pub(crate) async fn migrate(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> Result<(), Error> {
+        for module in &self.modules {
+            if let Some(mut migrator) = module.migrator() {
+                tracing::debug!(module = module.name(), "running module migrations");
+                // the core's and other modules' migrations are in the same table.
+                migrator.set_ignore_missing(true);
+                migrator.run(&mut **tx).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Debug for Modules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Modules;
+
+    #[test]
+    fn modules_can_be_disabled() {
+        let modules = Modules::builtin().retain(|name| name == "empire");
+        assert_eq!(modules.names().collect::<Vec<_>>(), vec!["empire"]);
+        assert!(modules.simulation_steps().is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! Production of resources at colonies, and trade routes that ship them
+//! between colonies.
+//!
+//! Every epoch the colonies first produce into their stockpiles, then
+//! shipments that arrive are unloaded, and then every route loads what it can
+//! ship in that time into a new shipment. Routes are served in the order they
+//! were created, so older routes get a share of a stockpile first.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    Router,
+};
+use kardashev_protocol::model::{
+    empire::ColonyId,
+    faction::FactionId,
+    journal::JournalEntry,
+    star::StarId,
+    trade::{
+        Resource,
+        TradeRouteId,
+        STOCKPILE_CAPACITY,
+    },
+};
+use sqlx::migrate::Migrator;
+
+use crate::{
+    api::trade::ResourceColumn,
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    modules::{
+        Module,
+        SimulationStep,
+    },
+    scripting::Rules,
+    state_hash::StateHasher,
+    worlds::Worlds,
+};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TradeModule;
+
+impl Module for TradeModule {
+    fn name(&self) -> &'static str {
+        "trade"
+    }
+
+    fn router(&self) -> Router<Worlds> {
+        crate::api::trade::router()
+    }
+
+    fn migrator(&self) -> Option<Migrator> {
+        Some(sqlx::migrate!("./migrations/trade"))
+    }
+
+    fn simulation_steps(&self) -> Vec<Arc<dyn SimulationStep>> {
+        vec![Arc::new(ResolveTrade)]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ResolveTrade;
+
+#[async_trait]
+impl SimulationStep for ResolveTrade {
+    async fn run(
+        &self,
+        context: &Context,
+        tx: &mut Transaction<'_>,
+        days: f32,
+    ) -> Result<Vec<JournalEntry>, Error> {
+        let mut trade = Trade::load(tx).await?;
+        if trade.colonies.is_empty() {
+            return Ok(vec![]);
+        }
+        trade.advance(&context.rules, days);
+        trade.store(tx).await?;
+        Ok(vec![])
+    }
+
+    async fn hash_state(
+        &self,
+        tx: &mut Transaction<'_>,
+        hasher: &mut StateHasher,
+    ) -> Result<(), Error> {
+        Trade::load(tx).await?.hash_state(hasher);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ColonyState {
+    faction: FactionId,
+    star: StarId,
+    luminosity: f32,
+    planets: u32,
+    stockpile: HashMap<Resource, f32>,
+}
+
+impl ColonyState {
+    fn store(&mut self, resource: Resource, amount: f32) {
+        let stock = self.stockpile.entry(resource).or_default();
+        *stock = (*stock + amount).min(STOCKPILE_CAPACITY);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RouteState {
+    id: TradeRouteId,
+    from: ColonyId,
+    to: ColonyId,
+    resource: Resource,
+    capacity: f32,
+    travel_days: f32,
+}
+
+#[derive(Clone, Debug)]
+struct Shipment {
+    route: Option<TradeRouteId>,
+    to: ColonyId,
+    resource: Resource,
+    amount: f32,
+    remaining_days: f32,
+}
+
+/// The colonies, routes and shipments that trade changes.
+#[derive(Clone, Debug)]
+struct Trade {
+    colonies: HashMap<ColonyId, ColonyState>,
+
+    /// Ordered by when they were created.
+    routes: Vec<RouteState>,
+
+    shipments: Vec<Shipment>,
+}
+
+impl Trade {
+    fn advance(&mut self, rules: &Rules, days: f32) {
+        for colony in self.colonies.values_mut() {
+            for resource in Resource::ALL {
+                let produced =
+                    rules.resource_production(resource, colony.luminosity, colony.planets) * days;
+                colony.store(resource, produced);
+            }
+        }
+
+        let mut shipments = Vec::with_capacity(self.shipments.len());
+        for mut shipment in self.shipments.drain(..) {
+            shipment.remaining_days -= days;
+            if shipment.remaining_days > 0.0 {
+                shipments.push(shipment);
+            }
+            else if let Some(colony) = self.colonies.get_mut(&shipment.to) {
+                colony.store(shipment.resource, shipment.amount);
+            }
+        }
+        self.shipments = shipments;
+
+        for route in &self.routes {
+            let Some(colony) = self.colonies.get_mut(&route.from)
+            else {
+                continue;
+            };
+            let stock = colony.stockpile.entry(route.resource).or_default();
+            let amount = (route.capacity * days).min(*stock);
+            if amount <= 0.0 {
+                continue;
+            }
+            *stock -= amount;
+            self.shipments.push(Shipment {
+                route: Some(route.id),
+                to: route.to,
+                resource: route.resource,
+                amount,
+                remaining_days: route.travel_days,
+            });
+        }
+    }
+
+    /// Hashes the state. Colonies are identified by their faction and star,
+    /// since the IDs of colonies founded during an epoch are random.
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        let star = |colony: &ColonyId| self.colonies.get(colony).map(|colony| colony.star);
+
+        let mut colonies = self.colonies.values().collect::<Vec<_>>();
+        colonies.sort_by_key(|colony| (colony.faction.0, colony.star.0));
+        hasher.section("stockpiles").write(&colonies.len());
+        for colony in colonies {
+            hasher.write(&colony.faction).write(&colony.star);
+            for resource in Resource::ALL {
+                hasher.write(&colony.stockpile.get(&resource).copied().unwrap_or_default());
+            }
+        }
+
+        let mut routes = self.routes.iter().collect::<Vec<_>>();
+        routes.sort_by_key(|route| route.id.0);
+        hasher.section("trade_routes").write(&routes.len());
+        for route in routes {
+            hasher
+                .write(&route.id)
+                .write(&star(&route.from))
+                .write(&star(&route.to))
+                .write(&route.resource)
+                .write(&route.capacity)
+                .write(&route.travel_days);
+        }
+
+        // shipment IDs are random, so they're ordered by their contents.
+        let key = |shipment: &Shipment| {
+            (
+                shipment.route.map(|route| route.0),
+                star(&shipment.to).map(|star| star.0),
+                shipment.resource,
+            )
+        };
+        let mut shipments = self.shipments.iter().collect::<Vec<_>>();
+        shipments.sort_by(|a, b| {
+            key(a)
+                .cmp(&key(b))
+                .then(a.remaining_days.total_cmp(&b.remaining_days))
+                .then(a.amount.total_cmp(&b.amount))
+        });
+        hasher.section("trade_shipments").write(&shipments.len());
+        for shipment in shipments {
+            hasher
+                .write(&shipment.route)
+                .write(&star(&shipment.to))
+                .write(&shipment.resource)
+                .write(&shipment.amount)
+                .write(&shipment.remaining_days);
+        }
+    }
+
+    async fn load(tx: &mut Transaction<'_>) -> Result<Self, Error> {
+        let mut colonies = sqlx::query!(
+            r#"
+            SELECT
+                colony.id,
+                colony.faction_id,
+                colony.star_id,
+                star.luminousity,
+                (SELECT COUNT(*) FROM planet WHERE planet.star_id = colony.star_id) AS "planets!"
+            FROM colony
+            JOIN star ON star.id = colony.star_id
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                ColonyId(row.id),
+                ColonyState {
+                    faction: FactionId(row.faction_id),
+                    star: StarId(row.star_id),
+                    luminosity: row.luminousity,
+                    planets: row.planets.try_into().unwrap_or(u32::MAX),
+                    stockpile: HashMap::new(),
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+        let stockpiles = sqlx::query!(
+            r#"
+            SELECT
+                colony_id,
+                resource AS "resource: ResourceColumn",
+                amount
+            FROM colony_stockpile
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?;
+        for row in stockpiles {
+            if let Some(colony) = colonies.get_mut(&ColonyId(row.colony_id)) {
+                colony.stockpile.insert(row.resource.into(), row.amount);
+            }
+        }
+
+        let routes = sqlx::query!(
+            r#"
+            SELECT
+                id,
+                from_colony,
+                to_colony,
+                resource AS "resource: ResourceColumn",
+                capacity,
+                travel_days
+            FROM trade_route
+            ORDER BY created_at, id
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            RouteState {
+                id: TradeRouteId(row.id),
+                from: ColonyId(row.from_colony),
+                to: ColonyId(row.to_colony),
+                resource: row.resource.into(),
+                capacity: row.capacity,
+                travel_days: row.travel_days,
+            }
+        })
+        .collect();
+
+        let shipments = sqlx::query!(
+            r#"
+            SELECT
+                route_id,
+                to_colony,
+                resource AS "resource: ResourceColumn",
+                amount,
+                remaining_days
+            FROM trade_shipment
+            ORDER BY remaining_days, route_id, to_colony, resource, amount
+            "#,
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            Shipment {
+                route: row.route_id.map(TradeRouteId),
+                to: ColonyId(row.to_colony),
+                resource: row.resource.into(),
+                amount: row.amount,
+                remaining_days: row.remaining_days,
+            }
+        })
+        .collect();
+
+        Ok(Self {
+            colonies,
+            routes,
+            shipments,
+        })
+    }
+
+    async fn store(&self, tx: &mut Transaction<'_>) -> Result<(), Error> {
+        for (colony_id, colony) in &self.colonies {
+            for (resource, amount) in &colony.stockpile {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO colony_stockpile (colony_id, resource, amount)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (colony_id, resource) DO UPDATE SET amount = EXCLUDED.amount
+                    "#,
+                    colony_id.0,
+                    ResourceColumn::from(*resource) as _,
+                    amount,
+                )
+                .execute(&mut ***tx)
+                .await?;
+            }
+        }
+
+        // shipments don't have an identity besides their contents, so they're
+        // replaced.
+        sqlx::query!("DELETE FROM trade_shipment")
+            .execute(&mut ***tx)
+            .await?;
+        for shipment in &self.shipments {
+            sqlx::query!(
+                r#"
+                INSERT INTO trade_shipment (route_id, to_colony, resource, amount, remaining_days)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                shipment.route.map(|route| route.0),
+                shipment.to.0,
+                ResourceColumn::from(shipment.resource) as _,
+                shipment.amount,
+                shipment.remaining_days,
+            )
+            .execute(&mut ***tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use kardashev_protocol::{
+        model::{
+            empire::ColonyId,
+            faction::FactionId,
+            star::StarId,
+            trade::{
+                Resource,
+                TradeRouteId,
+            },
+        },
+        uuid::Uuid,
+    };
+
+    use super::{
+        ColonyState,
+        RouteState,
+        Trade,
+    };
+    use crate::{
+        scripting::Rules,
+        state_hash::{
+            replay::{
+                assert_deterministic,
+                Replay,
+            },
+            StateHasher,
+        },
+    };
+
+    impl Replay for Trade {
+        fn run_epoch(&mut self, days: f32) {
+            self.advance(&Rules::default(), days);
+        }
+
+        fn hash_state(&self, hasher: &mut StateHasher) {
+            Trade::hash_state(self, hasher);
+        }
+    }
+
+    fn setup() -> Trade {
+        let colony = |i: u128| ColonyId(Uuid::from_u128(i));
+
+        let colonies = (0..4)
+            .map(|i| {
+                (
+                    colony(i),
+                    ColonyState {
+                        faction: FactionId(Uuid::from_u128(i % 2)),
+                        star: StarId(Uuid::from_u128(100 + i)),
+                        luminosity: 0.5 + i as f32,
+                        planets: i as u32,
+                        stockpile: HashMap::new(),
+                    },
+                )
+            })
+            .collect();
+
+        // two routes draw from the same stockpile, and one ships back.
+        let route = |i: u128, from: u128, to: u128, resource: Resource, capacity: f32| {
+            RouteState {
+                id: TradeRouteId(Uuid::from_u128(200 + i)),
+                from: colony(from),
+                to: colony(to),
+                resource,
+                capacity,
+                travel_days: 1.5 + i as f32,
+            }
+        };
+        let routes = vec![
+            route(0, 0, 2, Resource::Energy, 4.0),
+            route(1, 0, 2, Resource::Energy, 4.0),
+            route(2, 2, 0, Resource::Metals, 25.0),
+            route(3, 1, 3, Resource::Volatiles, 1.0),
+        ];
+
+        Trade {
+            colonies,
+            routes,
+            shipments: vec![],
+        }
+    }
+
+    #[test]
+    fn trading_is_deterministic() {
+        assert_deterministic(setup, 20, 1.0);
+
+        let mut trade = setup();
+        trade.run_epoch(1.0);
+        let shipped = trade
+            .shipments
+            .iter()
+            .map(|shipment| shipment.amount)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            shipped,
+            [4.0, 1.0, 10.0, 1.0],
+            "the older route is served first"
+        );
+    }
+}
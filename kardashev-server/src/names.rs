@@ -0,0 +1,106 @@
+//! Generated names for objects that don't have one.
+//!
+//! See [`kardashev_protocol::names`] for how names are generated.
+
+use std::collections::HashSet;
+
+use kardashev_protocol::{
+    model::star::StarId,
+    names::{
+        planet_name,
+        star_name,
+    },
+};
+
+use crate::{
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+};
+
+/// Gives unnamed stars a generated name.
+///
+/// If `ids` is given, only those stars are named, e.g. the ones that were just
+/// imported. Otherwise all stars are.
+pub async fn name_stars(tx: &mut Transaction<'_>, ids: Option<&[StarId]>) -> Result<(), Error> {
+    let ids = ids.map(|ids| ids.iter().map(|id| id.0).collect::<Vec<_>>());
+
+    let (ids, names): (Vec<_>, Vec<_>) = sqlx::query!(
+        r#"
+        SELECT id
+        FROM star
+        WHERE name IS NULL AND ($1::UUID[] IS NULL OR id = ANY($1))
+        "#,
+        ids.as_deref(),
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| (row.id, star_name(StarId(row.id))))
+    .unzip();
+
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE star
+        SET name = generated.name, name_generated = TRUE
+        FROM UNNEST($1::UUID[], $2::TEXT[]) AS generated(id, name)
+        WHERE star.id = generated.id
+        "#,
+        &ids,
+        &names,
+    )
+    .execute(&mut ***tx)
+    .await?;
+
+    tracing::debug!(num_stars = ids.len(), "generated star names");
+
+    Ok(())
+}
+
+/// Generates a name for a new planet of a star.
+///
+/// Planets are named after their star, and get the first letter that none of
+/// the star's planets has yet.
+pub async fn next_planet_name(tx: &mut Transaction<'_>, star: StarId) -> Result<String, Error> {
+    let host_name = sqlx::query!("SELECT name FROM star WHERE id = $1", star.0)
+        .fetch_one(&mut ***tx)
+        .await?
+        .name
+        .unwrap_or_else(|| star_name(star));
+
+    let taken = sqlx::query!("SELECT name FROM planet WHERE star_id = $1", star.0)
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| row.name)
+        .collect::<HashSet<_>>();
+
+    let mut index = 0;
+    loop {
+        let name = planet_name(&host_name, index);
+        if !taken.contains(&name) {
+            return Ok(name);
+        }
+        index += 1;
+    }
+}
+
+/// Names stars that were created before names were generated.
+pub async fn backfill(context: Context) {
+    let result = async {
+        let mut tx = context.transaction().await?;
+        name_stars(&mut tx, None).await?;
+        tx.commit().await
+    }
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(?error, "failed to generate star names");
+    }
+}
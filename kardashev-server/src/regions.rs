@@ -0,0 +1,320 @@
+//! Regions of high star density.
+//!
+//! Stars are clustered with [DBSCAN](https://en.wikipedia.org/wiki/DBSCAN):
+//! Stars with at least [`min_stars`](RegionConfig::min_stars) neighbors within
+//! [`radius`](RegionConfig::radius) form the core of a region, and the regions
+//! grow over neighboring stars. Each region is stored with the convex hull of
+//! its stars on the XZ-plane, which the UI draws as an overlay.
+
+use std::collections::HashMap;
+
+use kardashev_protocol::{
+    model::star::StarId,
+    names::region_name,
+};
+use nalgebra::{
+    Point2,
+    Point3,
+    Vector3,
+};
+use sqlx::types::Json;
+
+use crate::{
+    context::Context,
+    error::Error,
+    util::sqlx::Vec3,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RegionConfig {
+    /// Distance in parsecs within which stars are neighbors.
+    pub radius: f32,
+
+    /// Number of neighbors a star needs to be in the core of a region.
+    pub min_stars: usize,
+}
+
+impl Default for RegionConfig {
+    fn default() -> Self {
+        Self {
+            radius: 2.0,
+            min_stars: 8,
+        }
+    }
+}
+
+/// A region found by [`cluster`].
+#[derive(Clone, Debug)]
+struct Cluster {
+    /// Indices of the stars in the region.
+    members: Vec<usize>,
+}
+
+/// Clusters `positions` with DBSCAN. Stars that are not dense enough to be
+/// part of a region are left out.
+fn cluster(positions: &[Point3<f32>], config: RegionConfig) -> Vec<Cluster> {
+    // spatial hash with cells as large as the radius, so that all neighbors of a
+    // star are in the cells around it.
+    let cell = |position: &Point3<f32>| {
+        (
+            (position.x / config.radius).floor() as i32,
+            (position.y / config.radius).floor() as i32,
+            (position.z / config.radius).floor() as i32,
+        )
+    };
+    let mut grid: HashMap<_, Vec<usize>> = HashMap::new();
+    for (index, position) in positions.iter().enumerate() {
+        grid.entry(cell(position)).or_default().push(index);
+    }
+
+    let radius_squared = config.radius * config.radius;
+    let neighbors = |index: usize| {
+        let position = &positions[index];
+        let (x, y, z) = cell(position);
+        let mut neighbors = vec![];
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(candidates) = grid.get(&(x + dx, y + dy, z + dz)) {
+                        neighbors.extend(candidates.iter().copied().filter(|candidate| {
+                            *candidate != index
+                                && (positions[*candidate] - position).norm_squared()
+                                    <= radius_squared
+                        }));
+                    }
+                }
+            }
+        }
+        neighbors
+    };
+
+    let mut assigned = vec![false; positions.len()];
+    let mut clusters = vec![];
+
+    for start in 0..positions.len() {
+        if assigned[start] {
+            continue;
+        }
+        let start_neighbors = neighbors(start);
+        if start_neighbors.len() < config.min_stars {
+            continue;
+        }
+
+        assigned[start] = true;
+        let mut members = vec![start];
+        let mut queue = start_neighbors;
+
+        while let Some(index) = queue.pop() {
+            if assigned[index] {
+                continue;
+            }
+            assigned[index] = true;
+            members.push(index);
+
+            let index_neighbors = neighbors(index);
+            if index_neighbors.len() >= config.min_stars {
+                queue.extend(
+                    index_neighbors
+                        .into_iter()
+                        .filter(|neighbor| !assigned[*neighbor]),
+                );
+            }
+        }
+
+        clusters.push(Cluster { members });
+    }
+
+    clusters
+}
+
+/// Computes the convex hull of `points` with
+/// [Andrew's monotone chain algorithm](https://en.wikibooks.org/wiki/Algorithm_Implementation/Geometry/Convex_hull/Monotone_chain).
+///
+/// The vertices are returned in counter-clockwise order.
+fn convex_hull(mut points: Vec<Point2<f32>>) -> Vec<Point2<f32>> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: &Point2<f32>, a: &Point2<f32>, b: &Point2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+
+    let half_hull = |points: &mut dyn Iterator<Item = &Point2<f32>>| {
+        let mut hull: Vec<Point2<f32>> = vec![];
+        for point in points {
+            while hull.len() >= 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        // the last point is the first of the other half.
+        hull.pop();
+        hull
+    };
+
+    let mut hull = half_hull(&mut points.iter());
+    hull.extend(half_hull(&mut points.iter().rev()));
+    hull
+}
+
+/// Recomputes all regions from the current stars.
+///
+/// Returns the number of regions found.
+pub async fn recompute(context: &Context) -> Result<usize, Error> {
+    let mut tx = context.transaction().await?;
+
+    let (ids, positions): (Vec<_>, Vec<Point3<f32>>) = sqlx::query!(
+        r#"
+        SELECT id, position AS "position: Vec3"
+        FROM star
+        ORDER BY id
+        "#
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|row| (StarId(row.id), row.position.into()))
+    .unzip();
+
+    let config = context.regions;
+    let (positions, clusters) = tokio::task::spawn_blocking(move || {
+        let clusters = cluster(&positions, config);
+        (positions, clusters)
+    })
+    .await?;
+
+    sqlx::query!("DELETE FROM region")
+        .execute(&mut **tx)
+        .await?;
+
+    for cluster in &clusters {
+        let num_stars = cluster.members.len();
+        let center = Point3::from(
+            cluster
+                .members
+                .iter()
+                .map(|index| positions[*index].coords)
+                .sum::<Vector3<f32>>()
+                / num_stars as f32,
+        );
+        let hull = convex_hull(
+            cluster
+                .members
+                .iter()
+                .map(|index| Point2::new(positions[*index].x, positions[*index].z))
+                .collect(),
+        );
+        let hull = hull
+            .iter()
+            .map(|point| [point.x, point.y])
+            .collect::<Vec<_>>();
+        // stars are ordered by ID, so this is the star with the lowest ID.
+        let name_star = ids[*cluster.members.iter().min().unwrap()];
+
+        sqlx::query!(
+            r#"
+            INSERT INTO region (name, center, hull, num_stars)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            region_name(name_star),
+            Vec3::from(center) as _,
+            Json(&hull) as _,
+            num_stars as i32,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(num_regions = clusters.len(), "computed regions");
+
+    Ok(clusters.len())
+}
+
+/// Computes the regions if they haven't been computed yet.
+pub async fn backfill(context: Context) {
+    let result = async {
+        let mut tx = context.transaction().await?;
+        let computed = sqlx::query!(r#"SELECT EXISTS(SELECT 1 FROM region) AS "exists!""#)
+            .fetch_one(&mut **tx)
+            .await?
+            .exists;
+        tx.rollback().await?;
+
+        if !computed {
+            recompute(&context).await?;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(?error, "failed to compute regions");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{
+        Point2,
+        Point3,
+        Vector3,
+    };
+
+    use super::{
+        cluster,
+        convex_hull,
+        RegionConfig,
+    };
+
+    #[test]
+    fn it_finds_dense_clusters() {
+        let mut positions = vec![];
+        for center in [Point3::new(0.0, 0.0, 0.0), Point3::new(50.0, 0.0, 0.0)] {
+            for i in 0..10 {
+                positions.push(center + Vector3::new(0.1 * i as f32, 0.0, 0.0));
+            }
+        }
+        // a lone star
+        positions.push(Point3::new(-50.0, 0.0, 0.0));
+
+        let config = RegionConfig {
+            radius: 1.0,
+            min_stars: 3,
+        };
+        let mut clusters = cluster(&positions, config);
+        clusters
+            .iter_mut()
+            .for_each(|cluster| cluster.members.sort());
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].members, (0..10).collect::<Vec<_>>());
+        assert_eq!(clusters[1].members, (10..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_computes_convex_hulls() {
+        let hull = convex_hull(vec![
+            Point2::new(0.0, 0.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.5, 0.5),
+            Point2::new(1.0, 1.0),
+            Point2::new(0.0, 1.0),
+        ]);
+        assert_eq!(
+            hull,
+            vec![
+                Point2::new(0.0, 0.0),
+                Point2::new(1.0, 0.0),
+                Point2::new(1.0, 1.0),
+                Point2::new(0.0, 1.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Snapshots of the world for replays.
+//!
+//! Every [`snapshot_interval`](SimulationConfig::snapshot_interval) epochs,
+//! the simulation records the fleets and colonies in the same transaction as
+//! the epoch. Spectators replay them over the
+//! [replay websocket](crate::api::replay), which only shows them what their
+//! faction's sensors observed at the time.
+
+use kardashev_protocol::{
+    model::{
+        faction::FactionId,
+        fleet::{
+            Fleet,
+            FleetId,
+        },
+        star::StarId,
+    },
+    replay::{
+        SnapshotColony,
+        SnapshotInfo,
+        WorldSnapshot,
+    },
+};
+use sqlx::types::Json;
+
+use crate::{
+    api::fleet::{
+        fetch_orders,
+        is_visible,
+    },
+    context::Transaction,
+    error::Error,
+    simulation::SimulationConfig,
+    util::sqlx::Vec3,
+    visibility::{
+        Sensor,
+        Viewer,
+        Visibility,
+    },
+};
+
+/// Records a snapshot of the world after `epoch`, if one is due, and deletes
+/// the snapshots beyond [`max_snapshots`](SimulationConfig::max_snapshots).
+pub(crate) async fn record_snapshot(
+    tx: &mut Transaction<'_>,
+    config: &SimulationConfig,
+    epoch: i64,
+) -> Result<(), Error> {
+    if config.snapshot_interval == 0 || epoch % i64::from(config.snapshot_interval) != 0 {
+        return Ok(());
+    }
+
+    let mut orders = fetch_orders(tx, None).await?;
+    let fleets = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            position AS "position: Vec3",
+            speed,
+            faction_id,
+            sensor_range
+        FROM fleet
+        ORDER BY id
+        "#,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Fleet {
+            id: FleetId(row.id),
+            name: row.name,
+            position: row.position.into(),
+            speed: row.speed,
+            faction: row.faction_id.map(FactionId),
+            sensor_range: row.sensor_range,
+            orders: orders.remove(&FleetId(row.id)).unwrap_or_default(),
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let colonies = sqlx::query!(
+        r#"
+        SELECT
+            colony.faction_id,
+            colony.star_id,
+            star.position AS "position: Vec3"
+        FROM colony
+        JOIN star ON star.id = colony.star_id
+        ORDER BY colony.faction_id, colony.star_id
+        "#,
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        SnapshotColony {
+            faction: FactionId(row.faction_id),
+            star: StarId(row.star_id),
+            position: row.position.into(),
+        }
+    })
+    .collect::<Vec<_>>();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO world_snapshot (epoch, taken_at, fleets, colonies)
+        VALUES ($1, utc_now(), $2, $3)
+        ON CONFLICT (epoch) DO NOTHING
+        "#,
+        epoch,
+        Json(&fleets) as _,
+        Json(&colonies) as _,
+    )
+    .execute(&mut ***tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM world_snapshot
+        WHERE epoch <= (
+            SELECT epoch FROM world_snapshot
+            ORDER BY epoch DESC
+            OFFSET $1
+            LIMIT 1
+        )
+        "#,
+        i64::from(config.max_snapshots),
+    )
+    .execute(&mut ***tx)
+    .await?;
+
+    tracing::debug!(
+        epoch,
+        num_fleets = fleets.len(),
+        num_colonies = colonies.len(),
+        "recorded snapshot"
+    );
+
+    Ok(())
+}
+
+/// Lists the recorded snapshots, oldest first.
+pub(crate) async fn list_snapshots(tx: &mut Transaction<'_>) -> Result<Vec<SnapshotInfo>, Error> {
+    let snapshots = sqlx::query!("SELECT epoch, taken_at FROM world_snapshot ORDER BY epoch")
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            SnapshotInfo {
+                epoch: row.epoch as u64,
+                taken_at: row.taken_at,
+            }
+        })
+        .collect();
+    Ok(snapshots)
+}
+
+/// Loads the first snapshot at or after `epoch`, as the viewer saw the world
+/// then.
+pub(crate) async fn load_snapshot(
+    tx: &mut Transaction<'_>,
+    viewer: &Viewer,
+    epoch: u64,
+) -> Result<Option<WorldSnapshot>, Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            epoch,
+            taken_at,
+            fleets AS "fleets: Json<Vec<Fleet>>",
+            colonies AS "colonies: Json<Vec<SnapshotColony>>"
+        FROM world_snapshot
+        WHERE epoch >= $1
+        ORDER BY epoch
+        LIMIT 1
+        "#,
+        epoch as i64,
+    )
+    .fetch_optional(&mut ***tx)
+    .await?;
+
+    Ok(row.map(|row| {
+        let mut snapshot = WorldSnapshot {
+            epoch: row.epoch as u64,
+            taken_at: row.taken_at,
+            fleets: row.fleets.0,
+            colonies: row.colonies.0,
+        };
+        filter_snapshot(&mut snapshot, viewer);
+        snapshot
+    }))
+}
+
+/// Removes what the viewer's faction didn't observe at the time of the
+/// snapshot, and the orders of other factions' fleets.
+fn filter_snapshot(snapshot: &mut WorldSnapshot, viewer: &Viewer) {
+    let visibility = match viewer.faction {
+        Some(faction) => {
+            Visibility::from_sensors(
+                faction,
+                snapshot
+                    .fleets
+                    .iter()
+                    .filter(|fleet| fleet.faction == Some(faction) && fleet.sensor_range > 0.0)
+                    .map(|fleet| {
+                        Sensor {
+                            position: fleet.position,
+                            range: fleet.sensor_range,
+                        }
+                    }),
+            )
+        }
+        None if viewer.admin => return,
+        None => Visibility::Restricted,
+    };
+
+    snapshot
+        .fleets
+        .retain(|fleet| is_visible(&visibility, fleet.faction, &fleet.position));
+    for fleet in &mut snapshot.fleets {
+        if !visibility.owns(fleet.faction) {
+            fleet.orders.clear();
+        }
+    }
+    snapshot.colonies.retain(|colony| {
+        visibility.owns(Some(colony.faction)) || visibility.is_observed(&colony.position)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use kardashev_protocol::{
+        model::{
+            faction::FactionId,
+            fleet::{
+                Fleet,
+                FleetId,
+                Order,
+                OrderId,
+                OrderKind,
+            },
+            star::StarId,
+        },
+        replay::{
+            SnapshotColony,
+            WorldSnapshot,
+        },
+        uuid::Uuid,
+    };
+    use nalgebra::Point3;
+
+    use super::filter_snapshot;
+    use crate::visibility::Viewer;
+
+    fn fleet(faction: FactionId, position: Point3<f32>) -> Fleet {
+        Fleet {
+            id: FleetId(Uuid::new_v4()),
+            name: "fleet".to_owned(),
+            position,
+            speed: 1.0,
+            faction: Some(faction),
+            sensor_range: 2.0,
+            orders: vec![Order {
+                id: OrderId(Uuid::new_v4()),
+                kind: OrderKind::Move {
+                    star: StarId(Uuid::new_v4()),
+                },
+            }],
+        }
+    }
+
+    #[test]
+    fn snapshots_only_show_what_was_observed() {
+        let ours = FactionId(Uuid::new_v4());
+        let theirs = FactionId(Uuid::new_v4());
+        let mut snapshot = WorldSnapshot {
+            epoch: 10,
+            taken_at: Utc::now(),
+            fleets: vec![
+                fleet(ours, Point3::origin()),
+                fleet(theirs, Point3::new(1.0, 0.0, 0.0)),
+                fleet(theirs, Point3::new(100.0, 0.0, 0.0)),
+            ],
+            colonies: vec![
+                SnapshotColony {
+                    faction: theirs,
+                    star: StarId(Uuid::new_v4()),
+                    position: Point3::new(0.0, 0.0, 1.5),
+                },
+                SnapshotColony {
+                    faction: theirs,
+                    star: StarId(Uuid::new_v4()),
+                    position: Point3::new(0.0, 0.0, 50.0),
+                },
+            ],
+        };
+
+        let mut anonymous = snapshot.clone();
+
+        filter_snapshot(&mut snapshot, &Viewer::faction(ours));
+
+        assert_eq!(snapshot.fleets.len(), 2);
+        assert_eq!(snapshot.fleets[0].orders.len(), 1);
+        assert!(snapshot.fleets[1].orders.is_empty());
+        assert_eq!(snapshot.colonies.len(), 1);
+
+        filter_snapshot(&mut anonymous, &Viewer::default());
+
+        assert!(anonymous.fleets.is_empty());
+        assert!(anonymous.colonies.is_empty());
+    }
+}
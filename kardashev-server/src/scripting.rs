@@ -0,0 +1,476 @@
+//! Gameplay formulas that are defined by scripts.
+//!
+//! Balancing the game shouldn't require recompiling the server, so formulas,
+//! like the power output of colonies, the production of resources or the
+//! travel time of freighters, can be defined in Lua scripts. The
+//! scripts are loaded from a directory, e.g. `content/rules`, and define global
+//! functions named after the formulas of [`Rules`]. Formulas that no script
+//! defines are the built-in ones, which scripts can call through the `builtin`
+//! table.
+//!
+//! Research and events aren't modelled yet, so there are no formulas for
+//! research costs or event triggers.
+//!
+//! The scripts are sandboxed. They only have Lua's `math`, `string` and `table`
+//! libraries, can't load other code, and are aborted if they use too much
+//! memory or run for too long. If a formula fails, the error is logged and the
+//! built-in formula is used instead, so that a broken script doesn't stop the
+//! simulation.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::{
+        atomic::{
+            AtomicU32,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use kardashev_protocol::{
+    model::{
+        leaderboard::{
+            colony_power,
+            kardashev_rating,
+            COLONY_ENERGY_FRACTION,
+            SOLAR_LUMINOSITY,
+        },
+        trade::{
+            travel_days,
+            Resource,
+            ENERGY_PER_LUMINOSITY,
+            METALS_PER_PLANET,
+            TRADE_SPEED,
+            VOLATILES_PER_COLONY,
+        },
+    },
+    units::Distance,
+};
+use mlua::{
+    FromLuaMulti,
+    Function,
+    HookTriggers,
+    IntoLuaMulti,
+    Lua,
+    LuaOptions,
+    StdLib,
+    VmState,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::Error;
+
+/// Names of the formulas that scripts can define.
+const FORMULAS: [&str; 4] = [
+    "colony_power",
+    "kardashev_rating",
+    "resource_production",
+    "trade_travel_days",
+];
+
+/// Globals of the base library that would let scripts load code or files.
+const UNSAFE_GLOBALS: [&str; 5] = ["dofile", "load", "loadfile", "loadstring", "require"];
+
+/// Memory a script state can allocate, in bytes.
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Instructions after which the budget of a formula call is checked.
+const HOOK_INTERVAL: u32 = 1000;
+
+/// Number of [`HOOK_INTERVAL`]s a formula call can run for.
+const INSTRUCTION_BUDGET: u32 = 1000;
+
+/// How often the scripts are checked for changes, with hot reload.
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Default)]
+pub struct RulesConfig {
+    /// Directory with the `*.lua` scripts. Without one, the built-in formulas
+    /// are used.
+    pub directory: Option<PathBuf>,
+
+    /// Reload the scripts when they change. Meant for development.
+    pub hot_reload: bool,
+}
+
+/// The gameplay formulas, shared by all worlds.
+#[derive(Clone, Default)]
+pub struct Rules {
+    directory: Option<Arc<Path>>,
+    scripts: Arc<Mutex<Option<Scripts>>>,
+}
+
+impl Rules {
+    /// Loads the scripts in the configured directory.
+    pub fn new(config: &RulesConfig) -> Result<Self, Error> {
+        let Some(directory) = &config.directory
+        else {
+            return Ok(Self::default());
+        };
+
+        let scripts = Scripts::load(directory)?;
+        tracing::info!(directory = %directory.display(), formulas = ?scripts.formulas.keys().collect::<Vec<_>>(), "loaded rules");
+
+        Ok(Self {
+            directory: Some(directory.as_path().into()),
+            scripts: Arc::new(Mutex::new(Some(scripts))),
+        })
+    }
+
+    /// Energy output in watts of a colony at a star with the given luminosity
+    /// (in solar luminosities).
+    ///
+    /// The built-in formula is [`colony_power`].
+    pub fn colony_power(&self, luminosity: f64) -> f64 {
+        self.call("colony_power", luminosity)
+            .filter(|power: &f64| *power >= 0.0 && power.is_finite())
+            .unwrap_or_else(|| colony_power(luminosity))
+    }
+
+    /// Rating on the Kardashev scale for an energy output in watts.
+    ///
+    /// The built-in formula is [`kardashev_rating`].
+    pub fn kardashev_rating(&self, power: f64) -> f64 {
+        self.call("kardashev_rating", power)
+            .filter(|rating: &f64| *rating >= 0.0 && rating.is_finite())
+            .unwrap_or_else(|| kardashev_rating(power))
+    }
+
+    /// Amount of a resource a colony produces per day, given the luminosity
+    /// of its star (in solar luminosities) and the number of planets around
+    /// it. Scripts get the resource's name, e.g. `"energy"`.
+    ///
+    /// The built-in formula is [`Resource::production`].
+    pub fn resource_production(&self, resource: Resource, luminosity: f32, planets: u32) -> f32 {
+        self.call(
+            "resource_production",
+            (resource_name(resource), luminosity, planets),
+        )
+        .filter(|amount: &f32| *amount >= 0.0 && amount.is_finite())
+        .unwrap_or_else(|| resource.production(luminosity, planets))
+    }
+
+    /// Days freighters take to travel `distance`. Scripts get the distance in
+    /// light years.
+    ///
+    /// The built-in formula is [`travel_days`].
+    pub fn trade_travel_days(&self, distance: Distance) -> f32 {
+        self.call("trade_travel_days", distance.light_years())
+            .filter(|days: &f32| *days >= 0.0 && days.is_finite())
+            .unwrap_or_else(|| travel_days(distance))
+    }
+
+    /// Calls the formula if a script defines it. Returns `None` if no script
+    /// does, or if it fails.
+    fn call<A: IntoLuaMulti, R: FromLuaMulti>(&self, formula: &'static str, args: A) -> Option<R> {
+        let scripts = self.scripts.lock().unwrap();
+        let scripts = scripts.as_ref()?;
+        let function = scripts.formulas.get(formula)?;
+
+        scripts.budget.store(INSTRUCTION_BUDGET, Ordering::Relaxed);
+        function
+            .call(args)
+            .inspect_err(
+                |error| tracing::warn!(formula, %error, "formula failed. using the built-in one."),
+            )
+            .ok()
+    }
+
+    /// Reloads the scripts when they change, until `shutdown` is cancelled.
+    ///
+    /// If the changed scripts fail to load, the previous ones are kept.
+    pub async fn hot_reload(self, shutdown: CancellationToken) {
+        let Some(directory) = self.directory.clone()
+        else {
+            return;
+        };
+
+        let mut interval = tokio::time::interval(HOT_RELOAD_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_modified = modification_times(&directory);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+
+            let modified = modification_times(&directory);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            match Scripts::load(&directory) {
+                Ok(scripts) => {
+                    *self.scripts.lock().unwrap() = Some(scripts);
+                    tracing::info!(directory = %directory.display(), "reloaded rules");
+                }
+                Err(error) => {
+                    tracing::error!(?error, "failed to reload rules. keeping the previous ones.");
+                }
+            }
+        }
+    }
+}
+
+impl Debug for Rules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rules")
+            .field("directory", &self.directory)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A sandboxed Lua state with the formulas the scripts defined.
+struct Scripts {
+    /// Keeps the state alive. The formulas hold references into it.
+    _lua: Lua,
+    formulas: BTreeMap<&'static str, Function>,
+
+    /// Remaining [`HOOK_INTERVAL`]s of the current formula call.
+    budget: Arc<AtomicU32>,
+}
+
+impl Scripts {
+    /// Runs the `*.lua` scripts in `directory` in alphabetical order.
+    fn load(directory: &Path) -> Result<Self, Error> {
+        let mut paths = vec![];
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "lua") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut sources = vec![];
+        for path in paths {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            sources.push((name, std::fs::read_to_string(&path)?));
+        }
+
+        Self::from_sources(sources)
+    }
+
+    /// Runs the scripts, given as `(name, source)` pairs, in order.
+    fn from_sources(sources: impl IntoIterator<Item = (String, String)>) -> Result<Self, Error> {
+        let lua = Lua::new_with(
+            StdLib::MATH | StdLib::STRING | StdLib::TABLE,
+            LuaOptions::default(),
+        )?;
+        lua.set_memory_limit(MEMORY_LIMIT)?;
+
+        let globals = lua.globals();
+        for name in UNSAFE_GLOBALS {
+            globals.raw_remove(name)?;
+        }
+
+        let builtin = lua.create_table()?;
+        builtin.set(
+            "colony_power",
+            lua.create_function(|_, luminosity: f64| Ok(colony_power(luminosity)))?,
+        )?;
+        builtin.set(
+            "kardashev_rating",
+            lua.create_function(|_, power: f64| Ok(kardashev_rating(power)))?,
+        )?;
+        builtin.set(
+            "resource_production",
+            lua.create_function(|_, (resource, luminosity, planets): (String, f32, u32)| {
+                let resource = parse_resource(&resource)
+                    .ok_or_else(|| mlua::Error::runtime(format!("unknown resource: {resource}")))?;
+                Ok(resource.production(luminosity, planets))
+            })?,
+        )?;
+        builtin.set(
+            "trade_travel_days",
+            lua.create_function(|_, light_years: f32| {
+                Ok(travel_days(Distance::from_light_years(light_years)))
+            })?,
+        )?;
+        globals.set("builtin", builtin)?;
+        globals.set("SOLAR_LUMINOSITY", SOLAR_LUMINOSITY)?;
+        globals.set("COLONY_ENERGY_FRACTION", COLONY_ENERGY_FRACTION)?;
+        globals.set("ENERGY_PER_LUMINOSITY", ENERGY_PER_LUMINOSITY)?;
+        globals.set("METALS_PER_PLANET", METALS_PER_PLANET)?;
+        globals.set("VOLATILES_PER_COLONY", VOLATILES_PER_COLONY)?;
+        globals.set("TRADE_SPEED", TRADE_SPEED)?;
+
+        // the budget is reset for every formula call, so that scripts can't hang the
+        // simulation.
+        let budget = Arc::new(AtomicU32::new(INSTRUCTION_BUDGET));
+        lua.set_hook(HookTriggers::new().every_nth_instruction(HOOK_INTERVAL), {
+            let budget = budget.clone();
+            move |_lua, _debug| {
+                if budget.fetch_sub(1, Ordering::Relaxed) == 0 {
+                    return Err(mlua::Error::runtime("script ran for too long"));
+                }
+                Ok(VmState::Continue)
+            }
+        });
+
+        for (name, source) in sources {
+            budget.store(INSTRUCTION_BUDGET, Ordering::Relaxed);
+            lua.load(source).set_name(name).exec()?;
+        }
+
+        let mut formulas = BTreeMap::new();
+        for formula in FORMULAS {
+            // fails if the global is something other than a function.
+            if let Some(function) = globals.get::<Option<Function>>(formula)? {
+                formulas.insert(formula, function);
+            }
+        }
+
+        Ok(Self {
+            _lua: lua,
+            formulas,
+            budget,
+        })
+    }
+}
+
+/// Name of a resource in scripts.
+fn resource_name(resource: Resource) -> &'static str {
+    match resource {
+        Resource::Energy => "energy",
+        Resource::Metals => "metals",
+        Resource::Volatiles => "volatiles",
+    }
+}
+
+fn parse_resource(name: &str) -> Option<Resource> {
+    Resource::ALL
+        .into_iter()
+        .find(|resource| resource_name(*resource) == name)
+}
+
+/// Modification times of the files in `directory`, to detect changed scripts.
+fn modification_times(directory: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Ok(entries) = std::fs::read_dir(directory)
+    else {
+        return vec![];
+    };
+    let mut times = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let modified = entry.metadata().and_then(|meta| meta.modified()).ok();
+            Some((entry.path(), modified))
+        })
+        .collect::<Vec<_>>();
+    times.sort();
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+
+    use kardashev_protocol::{
+        model::{
+            leaderboard::colony_power,
+            trade::{
+                travel_days,
+                Resource,
+            },
+        },
+        units::Distance,
+    };
+
+    use super::{
+        Rules,
+        Scripts,
+    };
+
+    fn rules(source: &str) -> Rules {
+        let scripts = Scripts::from_sources([("rules.lua".to_owned(), source.to_owned())]).unwrap();
+        Rules {
+            directory: None,
+            scripts: Arc::new(Mutex::new(Some(scripts))),
+        }
+    }
+
+    #[test]
+    fn scripts_override_formulas() {
+        let rules = rules(
+            "function colony_power(luminosity) return 2 * builtin.colony_power(luminosity) end",
+        );
+        assert_eq!(rules.colony_power(1.0), 2.0 * colony_power(1.0));
+        assert_eq!(Rules::default().colony_power(1.0), colony_power(1.0));
+    }
+
+    #[test]
+    fn scripts_override_trade_formulas() {
+        let rules = rules(
+            r#"
+            function resource_production(resource, luminosity, planets)
+                if resource == "metals" then
+                    return 2 * builtin.resource_production(resource, luminosity, planets)
+                end
+                return builtin.resource_production(resource, luminosity, planets)
+            end
+
+            function trade_travel_days(light_years)
+                return light_years / (2 * TRADE_SPEED)
+            end
+            "#,
+        );
+        assert_eq!(
+            rules.resource_production(Resource::Metals, 1.0, 3),
+            2.0 * Resource::Metals.production(1.0, 3)
+        );
+        assert_eq!(
+            rules.resource_production(Resource::Energy, 1.0, 3),
+            Resource::Energy.production(1.0, 3)
+        );
+
+        let distance = Distance::from_light_years(8.0);
+        assert_eq!(
+            rules.trade_travel_days(distance),
+            travel_days(distance) / 2.0
+        );
+    }
+
+    #[test]
+    fn failing_formulas_fall_back_to_the_builtin_ones() {
+        let rules = rules("function colony_power(luminosity) error('oops') end");
+        assert_eq!(rules.colony_power(1.0), colony_power(1.0));
+
+        let rules = rules("function colony_power(luminosity) while true do end end");
+        assert_eq!(rules.colony_power(1.0), colony_power(1.0));
+    }
+
+    #[test]
+    fn scripts_are_sandboxed() {
+        for source in [
+            "os.exit()",
+            "io.open('/etc/passwd')",
+            "load('return 1')",
+            "require('os')",
+        ] {
+            assert!(
+                Scripts::from_sources([("rules.lua".to_owned(), source.to_owned())]).is_err(),
+                "{source}"
+            );
+        }
+        assert!(
+            Scripts::from_sources([("rules.lua".to_owned(), "colony_power = 1".to_owned())])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Simulation of the universe in discrete epochs.
+//!
+//! Every epoch advances the simulation by a fixed amount of game time. The
+//! time of the last epoch is persisted, so when the server was down, the
+//! missed epochs are caught up on startup. At most
+//! [`SimulationConfig::max_catch_up`] epochs are run at once; if more were
+//! missed, the rest are skipped, so that small deployments that aren't always
+//! on don't spend ages fast-forwarding. The caught up epochs are spread over
+//! several ticks, with at most [`SimulationConfig::max_epochs_per_tick`] per
+//! tick, so that catching up doesn't block the world for long.
+//!
+//! What happens in an epoch is up to the [modules](crate::modules), which
+//! register [`SimulationStep`]s. After every epoch, the state is
+//! [hashed](crate::state_hash) and the hash is stored with the epoch. Every
+//! [`SimulationConfig::snapshot_interval`] epochs, a
+//! [snapshot](crate::replay) of the world is recorded for replays.
+//!
+//! If [`SimulationConfig::allow_forced_ticks`] is set, admins can also
+//! [force](force_epochs) epochs, e.g. to test gameplay without waiting. Forced
+//! epochs advance the game like scheduled ones, but don't move the schedule.
+
+use std::{
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use chrono::{
+    DateTime,
+    TimeDelta,
+    Utc,
+};
+
+use crate::{
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+    modules::SimulationStep,
+    replay,
+    state_hash::{
+        StateHash,
+        StateHasher,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SimulationConfig {
+    /// Real time between epochs. Every epoch advances the game by the same
+    /// amount of time.
+    pub epoch: Duration,
+
+    /// Maximum number of missed epochs that are caught up at once. This also
+    /// limits how many epochs can be forced at once.
+    pub max_catch_up: u32,
+
+    /// Maximum number of due epochs that are run at once. The rest are run in
+    /// the following ticks.
+    pub max_epochs_per_tick: u32,
+
+    /// Whether admins can force epochs. Meant for development and
+    /// single-player servers.
+    pub allow_forced_ticks: bool,
+
+    /// Number of epochs between [snapshots](crate::replay) of the world. `0`
+    /// disables snapshots.
+    pub snapshot_interval: u32,
+
+    /// Maximum number of snapshots that are kept. The oldest ones are deleted
+    /// first.
+    pub max_snapshots: u32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            epoch: Duration::from_secs(60),
+            max_catch_up: 24 * 60,
+            max_epochs_per_tick: 60,
+            allow_forced_ticks: false,
+            snapshot_interval: 10,
+            max_snapshots: 10_000,
+        }
+    }
+}
+
+/// Runs the simulation with the steps until the server shuts down.
+pub async fn run(context: Context, config: SimulationConfig, steps: Vec<Arc<dyn SimulationStep>>) {
+    let mut interval = tokio::time::interval(config.epoch);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = context.shutdown.cancelled() => break,
+            _ = interval.tick() => {
+                if let Err(error) = run_due_epochs(&context, &config, &steps).await {
+                    tracing::error!(?error, "simulation failed");
+                }
+            }
+        }
+    }
+}
+
+/// Runs the epochs that are due, but at most
+/// [`max_epochs_per_tick`](SimulationConfig::max_epochs_per_tick). Epochs
+/// beyond [`max_catch_up`](SimulationConfig::max_catch_up) are skipped.
+#[tracing::instrument(name = "simulation_tick", skip_all, fields(world = %context.world.0))]
+async fn run_due_epochs(
+    context: &Context,
+    config: &SimulationConfig,
+    steps: &[Arc<dyn SimulationStep>],
+) -> Result<(), Error> {
+    let epoch_length = TimeDelta::from_std(config.epoch).expect("epoch too long");
+
+    let mut tx = context.transaction().await?;
+    let epoch_at = load_epoch_at(&mut tx).await?;
+    let mut due = (Utc::now() - epoch_at).num_milliseconds() / epoch_length.num_milliseconds();
+
+    if due > i64::from(config.max_catch_up) {
+        let skipped = due - i64::from(config.max_catch_up);
+        tracing::warn!(skipped, "too many missed epochs, skipping some");
+        sqlx::query!(
+            "UPDATE simulation_state SET epoch_at = $1",
+            epoch_at + TimeDelta::milliseconds(epoch_length.num_milliseconds() * skipped),
+        )
+        .execute(&mut **tx)
+        .await?;
+        due = i64::from(config.max_catch_up);
+    }
+    tx.commit().await?;
+
+    let run = due.min(i64::from(config.max_epochs_per_tick.max(1)));
+    if due > 1 {
+        tracing::info!(due, run, "catching up with missed epochs");
+    }
+    for _ in 0..run {
+        run_epoch(context, config, steps, false).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs `ticks` epochs now, regardless of when the next one is due.
+///
+/// Returns the epoch and the state hash after the last one.
+///
+/// Fails with [`Error::ForcedTicksDisabled`] if this isn't allowed, and with
+/// [`Error::TooManyTicks`] if more than
+/// [`max_catch_up`](SimulationConfig::max_catch_up) epochs are requested.
+#[tracing::instrument(
+    name = "simulation_forced_ticks",
+    skip(context, config, steps),
+    fields(world = %context.world.0)
+)]
+pub(crate) async fn force_epochs(
+    context: &Context,
+    config: &SimulationConfig,
+    steps: &[Arc<dyn SimulationStep>],
+    ticks: u32,
+) -> Result<(i64, StateHash), Error> {
+    if !config.allow_forced_ticks {
+        return Err(Error::ForcedTicksDisabled);
+    }
+    if ticks == 0 || ticks > config.max_catch_up {
+        return Err(Error::TooManyTicks {
+            max: config.max_catch_up,
+        });
+    }
+
+    // make sure the simulation state exists.
+    let mut tx = context.transaction().await?;
+    load_epoch_at(&mut tx).await?;
+    tx.commit().await?;
+
+    let mut last = None;
+    for _ in 0..ticks {
+        last = Some(run_epoch(context, config, steps, true).await?);
+    }
+    Ok(last.expect("at least one tick"))
+}
+
+/// Returns the time of the last epoch, initializing the simulation state if
+/// necessary.
+async fn load_epoch_at(tx: &mut Transaction<'_>) -> Result<DateTime<Utc>, Error> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO simulation_state (epoch, epoch_at)
+        VALUES (0, utc_now())
+        ON CONFLICT (id) DO UPDATE SET id = simulation_state.id
+        RETURNING epoch_at
+        "#,
+    )
+    .fetch_one(&mut ***tx)
+    .await?;
+    Ok(row.epoch_at)
+}
+
+/// Runs a single epoch, and returns its number and state hash.
+///
+/// A `forced` epoch doesn't advance the time of the last epoch, so the
+/// scheduled epochs aren't delayed by it.
+#[tracing::instrument(name = "simulation_epoch", skip_all, fields(forced = forced))]
+async fn run_epoch(
+    context: &Context,
+    config: &SimulationConfig,
+    steps: &[Arc<dyn SimulationStep>],
+    forced: bool,
+) -> Result<(i64, StateHash), Error> {
+    let started = Instant::now();
+    let epoch_length = TimeDelta::from_std(config.epoch).expect("epoch too long");
+    let mut tx = context.transaction().await?;
+
+    let state = sqlx::query!("SELECT epoch FROM simulation_state FOR UPDATE")
+        .fetch_one(&mut **tx)
+        .await?;
+
+    let days = epoch_length.num_milliseconds() as f32 / 86_400_000.0;
+    let mut entries = vec![];
+    for step in steps {
+        entries.extend(step.run(context, &mut tx, days).await?);
+    }
+
+    let epoch = state.epoch + 1;
+    let state_hash = hash_state(&mut tx, epoch, steps).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE simulation_state
+        SET epoch = $1, epoch_at = epoch_at + $2, state_hash = $3
+        "#,
+        epoch,
+        if forced {
+            TimeDelta::zero()
+        }
+        else {
+            epoch_length
+        },
+        &state_hash.0[..],
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    replay::record_snapshot(&mut tx, config, epoch).await?;
+
+    tx.commit().await?;
+    context.journal.publish(entries);
+
+    // the counter and histogram are exported as metrics.
+    tracing::trace!(
+        epoch,
+        %state_hash,
+        monotonic_counter.simulation_epochs = 1u64,
+        histogram.simulation_epoch_seconds = started.elapsed().as_secs_f64(),
+        "simulated epoch"
+    );
+
+    Ok((epoch, state_hash))
+}
+
+/// Hashes the state of the steps after `epoch`.
+pub(crate) async fn hash_state(
+    tx: &mut Transaction<'_>,
+    epoch: i64,
+    steps: &[Arc<dyn SimulationStep>],
+) -> Result<StateHash, Error> {
+    let mut hasher = StateHasher::default();
+    hasher.section("epoch").write(&epoch);
+    for step in steps {
+        step.hash_state(tx, &mut hasher).await?;
+    }
+    Ok(hasher.finish())
+}
@@ -0,0 +1,81 @@
+//! Spatial index over the [chunks](ChunkCoords) that clients use too.
+
+use std::collections::HashMap;
+
+use kardashev_protocol::model::chunk::ChunkCoords;
+use nalgebra::Point3;
+
+/// Values by the chunks they're in.
+#[derive(Debug)]
+pub struct ChunkIndex<T> {
+    chunks: HashMap<ChunkCoords, Vec<T>>,
+}
+
+impl<T> Default for ChunkIndex<T> {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+        }
+    }
+}
+
+impl<T> ChunkIndex<T> {
+    /// Inserts a value at a position.
+    #[allow(dead_code)]
+    pub fn insert(&mut self, position: &Point3<f32>, value: T) {
+        self.chunks
+            .entry(ChunkCoords::containing(position))
+            .or_default()
+            .push(value);
+    }
+
+    /// Returns the values in the chunk containing `position`.
+    pub fn get(&self, position: &Point3<f32>) -> &[T] {
+        self.chunks
+            .get(&ChunkCoords::containing(position))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<T: Clone> ChunkIndex<T> {
+    /// Inserts a value that covers a sphere, e.g. a sensor range, into all
+    /// chunks the sphere overlaps.
+    pub fn insert_sphere(&mut self, center: Point3<f32>, radius: f32, value: T) {
+        for coords in ChunkCoords::within(center, radius) {
+            self.chunks.entry(coords).or_default().push(value.clone());
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<(Point3<f32>, f32, T)> for ChunkIndex<T> {
+    fn from_iter<I: IntoIterator<Item = (Point3<f32>, f32, T)>>(iter: I) -> Self {
+        let mut index = Self::default();
+        for (center, radius, value) in iter {
+            index.insert_sphere(center, radius, value);
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::model::chunk::CHUNK_SIZE;
+    use nalgebra::Point3;
+
+    use super::ChunkIndex;
+
+    #[test]
+    fn spheres_are_found_in_all_chunks_they_overlap() {
+        let mut index = ChunkIndex::default();
+        index.insert_sphere(Point3::new(CHUNK_SIZE - 1.0, 1.0, 1.0), 2.0, "sensor");
+
+        assert_eq!(index.get(&Point3::new(1.0, 1.0, 1.0)), &["sensor"]);
+        assert_eq!(
+            index.get(&Point3::new(CHUNK_SIZE + 0.5, 1.0, 1.0)),
+            &["sensor"]
+        );
+        assert!(index
+            .get(&Point3::new(3.0 * CHUNK_SIZE, 1.0, 1.0))
+            .is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+//! Auditing of the stars' derived properties.
+//!
+//! Stars imported from different catalogs don't follow the same conventions,
+//! e.g. some colors were computed from the effective temperature with a
+//! different table. The audit recomputes the derived properties with
+//! [`stellar`] and reports where they differ from the stored ones.
+
+use kardashev_protocol::{
+    admin::{
+        AuditStarsResponse,
+        StarIssue,
+        StarIssueKind,
+    },
+    model::star::{
+        HabitableZone,
+        StarEvent,
+        StarId,
+    },
+    stellar,
+};
+use palette::LinSrgb;
+
+use crate::{
+    context::Context,
+    error::Error,
+    stars,
+    util::sqlx::Rgb,
+};
+
+/// Largest difference of a color channel, that is not reported.
+const COLOR_TOLERANCE: f32 = 0.01;
+
+/// Largest difference of the absolute magnitude, that is not reported.
+const MAGNITUDE_TOLERANCE: f32 = 0.05;
+
+/// Largest relative difference of the habitable zone, that is not reported.
+const HABITABLE_ZONE_TOLERANCE: f32 = 0.01;
+
+/// The stored properties of a star that are audited.
+#[derive(Clone, Debug)]
+struct StoredProperties {
+    effective_temperature: f32,
+    color: LinSrgb,
+    absolute_magnitude: f32,
+    luminosity: f32,
+    radius: f32,
+    mass: f32,
+    habitable_zone: Option<HabitableZone>,
+}
+
+/// Derived properties recomputed from the stored ones. Properties that can't
+/// be recomputed, because their source is out of range, are `None`.
+#[derive(Clone, Debug, Default)]
+struct DerivedProperties {
+    color: Option<LinSrgb>,
+    absolute_magnitude: Option<f32>,
+    habitable_zone: Option<HabitableZone>,
+}
+
+fn is_positive(value: f32) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+/// Checks a star, returning its issues and the recomputed properties.
+fn check_star(stored: &StoredProperties) -> (Vec<StarIssueKind>, DerivedProperties) {
+    let mut issues = vec![];
+    let mut derived = DerivedProperties::default();
+
+    let mut out_of_range = |property: &str, value: f32| {
+        issues.push(StarIssueKind::OutOfRange {
+            property: property.to_owned(),
+            value,
+        });
+    };
+
+    let t_eff_valid = (stellar::MIN_EFFECTIVE_TEMPERATURE..=stellar::MAX_EFFECTIVE_TEMPERATURE)
+        .contains(&stored.effective_temperature);
+    if !t_eff_valid {
+        out_of_range("effective_temperature", stored.effective_temperature);
+    }
+    let luminosity_valid = is_positive(stored.luminosity);
+    if !luminosity_valid {
+        out_of_range("luminosity", stored.luminosity);
+    }
+    if !is_positive(stored.mass) {
+        out_of_range("mass", stored.mass);
+    }
+    if !is_positive(stored.radius) {
+        out_of_range("radius", stored.radius);
+    }
+
+    if t_eff_valid {
+        let expected = stellar::teff_color(stored.effective_temperature);
+        let difference = (stored.color.red - expected.red)
+            .abs()
+            .max((stored.color.green - expected.green).abs())
+            .max((stored.color.blue - expected.blue).abs());
+        if difference > COLOR_TOLERANCE {
+            issues.push(StarIssueKind::Color {
+                stored: stored.color,
+                expected,
+            });
+        }
+        derived.color = Some(expected);
+    }
+
+    if luminosity_valid {
+        let expected = stellar::absolute_magnitude(stored.luminosity);
+        if (stored.absolute_magnitude - expected).abs() > MAGNITUDE_TOLERANCE {
+            issues.push(StarIssueKind::AbsoluteMagnitude {
+                stored: stored.absolute_magnitude,
+                expected,
+            });
+        }
+        derived.absolute_magnitude = Some(expected);
+
+        let expected = HabitableZone::from(stellar::habitable_zone(stored.luminosity));
+        let matches = stored.habitable_zone.map_or(false, |stored| {
+            (stored.inner - expected.inner).abs() <= HABITABLE_ZONE_TOLERANCE * expected.inner
+                && (stored.outer - expected.outer).abs()
+                    <= HABITABLE_ZONE_TOLERANCE * expected.outer
+        });
+        if !matches {
+            issues.push(StarIssueKind::HabitableZone {
+                stored: stored.habitable_zone,
+                expected,
+            });
+        }
+        derived.habitable_zone = Some(expected);
+    }
+
+    (issues, derived)
+}
+
+/// Audits all stars. If `fix` is set, the derived properties of stars with
+/// fixable issues are recomputed.
+pub async fn audit(context: &Context, fix: bool) -> Result<AuditStarsResponse, Error> {
+    let mut tx = context.transaction().await?;
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            name,
+            effective_temperature,
+            color AS "color: Rgb",
+            absolute_magnitude,
+            luminousity,
+            radius,
+            mass,
+            habitable_zone_inner,
+            habitable_zone_outer
+        FROM star
+        ORDER BY id
+        "#
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let num_stars = rows.len();
+    let mut issues = vec![];
+    let mut num_fixed = 0;
+    let mut fixed_stars = vec![];
+
+    for row in rows {
+        let stored = StoredProperties {
+            effective_temperature: row.effective_temperature,
+            color: row.color.into(),
+            absolute_magnitude: row.absolute_magnitude,
+            luminosity: row.luminousity,
+            radius: row.radius,
+            mass: row.mass,
+            habitable_zone: row
+                .habitable_zone_inner
+                .zip(row.habitable_zone_outer)
+                .map(|(inner, outer)| HabitableZone { inner, outer }),
+        };
+        let (star_issues, derived) = check_star(&stored);
+
+        if fix && star_issues.iter().any(StarIssueKind::is_fixable) {
+            sqlx::query!(
+                r#"
+                UPDATE star
+                SET
+                    color = COALESCE($2, color),
+                    absolute_magnitude = COALESCE($3, absolute_magnitude),
+                    habitable_zone_inner = COALESCE($4, habitable_zone_inner),
+                    habitable_zone_outer = COALESCE($5, habitable_zone_outer)
+                WHERE id = $1
+                "#,
+                row.id,
+                derived.color.map(Rgb::from) as _,
+                derived.absolute_magnitude,
+                derived.habitable_zone.map(|zone| zone.inner),
+                derived.habitable_zone.map(|zone| zone.outer),
+            )
+            .execute(&mut **tx)
+            .await?;
+            num_fixed += star_issues
+                .iter()
+                .filter(|issue| issue.is_fixable())
+                .count();
+            fixed_stars.push(StarId(row.id));
+        }
+
+        issues.extend(star_issues.into_iter().map(|kind| {
+            StarIssue {
+                star: StarId(row.id),
+                name: row.name.clone(),
+                kind,
+            }
+        }));
+    }
+
+    let fixed_stars = stars::fetch_stars(&mut tx, Some(&fixed_stars)).await?;
+    tx.commit().await?;
+
+    context.star_updates.publish(
+        fixed_stars
+            .into_iter()
+            .map(|star| StarEvent::Updated { star }),
+    );
+
+    Ok(AuditStarsResponse {
+        num_stars,
+        issues,
+        num_fixed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::{
+        admin::StarIssueKind,
+        model::star::HabitableZone,
+        stellar,
+    };
+
+    use super::{
+        check_star,
+        StoredProperties,
+    };
+
+    fn sun() -> StoredProperties {
+        StoredProperties {
+            effective_temperature: 5778.0,
+            color: stellar::teff_color(5778.0),
+            absolute_magnitude: stellar::SOLAR_ABSOLUTE_MAGNITUDE,
+            luminosity: 1.0,
+            radius: 1.0,
+            mass: 1.0,
+            habitable_zone: Some(HabitableZone::from(stellar::habitable_zone(1.0))),
+        }
+    }
+
+    #[test]
+    fn it_accepts_consistent_stars() {
+        let (issues, _) = check_star(&sun());
+        assert!(issues.is_empty(), "{issues:?}");
+    }
+
+    #[test]
+    fn it_reports_inconsistent_stars() {
+        let mut star = sun();
+        star.absolute_magnitude = 4.0;
+        star.habitable_zone = None;
+        let (issues, derived) = check_star(&star);
+        assert_eq!(issues.len(), 2, "{issues:?}");
+        assert!(issues.iter().all(StarIssueKind::is_fixable));
+        assert_eq!(
+            derived.absolute_magnitude,
+            Some(stellar::SOLAR_ABSOLUTE_MAGNITUDE)
+        );
+    }
+
+    #[test]
+    fn it_does_not_derive_from_out_of_range_values() {
+        let mut star = sun();
+        star.luminosity = 0.0;
+        let (issues, derived) = check_star(&star);
+        assert_eq!(
+            issues,
+            vec![StarIssueKind::OutOfRange {
+                property: "luminosity".to_owned(),
+                value: 0.0,
+            }]
+        );
+        assert!(derived.absolute_magnitude.is_none());
+        assert!(derived.habitable_zone.is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Loading stars, and publishing changes to them.
+//!
+//! Changes are published to the [star event streams](crate::api::star_events)
+//! of connected clients once the transaction that made them is committed.
+
+use kardashev_protocol::model::star::{
+    CatalogIds,
+    HabitableZone,
+    Star,
+    StarEvent,
+    StarId,
+    StarVisibility,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    context::Transaction,
+    error::Error,
+    util::sqlx::{
+        Rgb,
+        Vec3,
+    },
+};
+
+/// Publishes changes to the stars to subscribers.
+///
+/// The stars aren't filtered by visibility yet.
+#[derive(Clone, Debug)]
+pub struct StarUpdates {
+    tx: broadcast::Sender<StarEvent>,
+}
+
+impl Default for StarUpdates {
+    fn default() -> Self {
+        // imports publish a batch of stars at once.
+        let (tx, _rx) = broadcast::channel(1024);
+        Self { tx }
+    }
+}
+
+impl StarUpdates {
+    pub fn subscribe(&self) -> broadcast::Receiver<StarEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Publishes events. This must only be called after the changes have been
+    /// committed.
+    pub fn publish(&self, events: impl IntoIterator<Item = StarEvent>) {
+        for event in events {
+            // this only fails if nobody is subscribed.
+            let _ = self.tx.send(event);
+        }
+    }
+}
+
+/// Loads the stars with the given IDs, or all stars.
+///
+/// The stars are returned as if they were observed, and must be filtered by
+/// the viewer's [`Visibility`](crate::visibility::Visibility).
+pub async fn fetch_stars(
+    tx: &mut Transaction<'_>,
+    ids: Option<&[StarId]>,
+) -> Result<Vec<Star>, Error> {
+    let ids = ids.map(|ids| ids.iter().map(|id| id.0).collect::<Vec<_>>());
+
+    let stars = sqlx::query!(
+        r#"
+        SELECT
+            id,
+            position AS "position: Vec3",
+            effective_temperature,
+            color AS "color: Rgb",
+            absolute_magnitude,
+            luminousity,
+            radius,
+            mass,
+            spectral_type,
+            name,
+            name_generated,
+            id_hyg,
+            id_hip,
+            id_hd,
+            id_hr,
+            id_gl,
+            id_bf,
+            habitable_zone_inner,
+            habitable_zone_outer,
+            variability
+        FROM star
+        WHERE $1::UUID[] IS NULL OR id = ANY($1)
+        "#,
+        ids.as_deref(),
+    )
+    .fetch_all(&mut ***tx)
+    .await?
+    .into_iter()
+    .map(|row| {
+        Star {
+            id: StarId(row.id),
+            position: row.position.into(),
+            effective_temperature: row.effective_temperature,
+            color: row.color.into(),
+            absolute_magnitude: row.absolute_magnitude,
+            luminousity: row.luminousity,
+            radius: row.radius,
+            mass: row.mass,
+            spectral_type: row.spectral_type,
+            name: row.name,
+            name_generated: row.name_generated,
+            catalog_ids: CatalogIds {
+                hyg: row.id_hyg.map(|id| id as u32),
+                hip: row.id_hip.map(|id| id as u32),
+                hd: row.id_hd.map(|id| id as u32),
+                hr: row.id_hr.map(|id| id as u32),
+                gl: row.id_gl,
+                bf: row.id_bf,
+            },
+            visibility: StarVisibility::Observed,
+            habitable_zone: row
+                .habitable_zone_inner
+                .zip(row.habitable_zone_outer)
+                .map(|(inner, outer)| HabitableZone { inner, outer }),
+            variability: row.variability,
+        }
+    })
+    .collect();
+
+    Ok(stars)
+}
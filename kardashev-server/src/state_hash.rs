@@ -0,0 +1,315 @@
+//! Stable hashes of the simulation state.
+//!
+//! After every epoch, the [`SimulationStep`](crate::modules::SimulationStep)s
+//! hash the state they simulate into a [`StateHash`], which is stored with the
+//! epoch. Servers that simulated the same epochs from the same state have the
+//! same hash, so refactors of the simulation can be checked to not change
+//! outcomes, e.g. with the [`replay`] harness.
+//!
+//! The hash doesn't depend on the platform or the Rust version: values are
+//! hashed with SHA-256 in a fixed byte order. Steps must hash rows in a stable
+//! order, e.g. by ID, and not in the order of a `HashMap`.
+
+use std::fmt::{
+    Debug,
+    Display,
+};
+
+use kardashev_protocol::{
+    model::{
+        diplomacy::Stance,
+        faction::FactionId,
+        fleet::{
+            FleetId,
+            OrderId,
+            OrderKind,
+        },
+        star::StarId,
+        trade::{
+            Resource,
+            TradeRouteId,
+        },
+    },
+    uuid::Uuid,
+};
+use nalgebra::Point3;
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateHash(pub [u8; 32]);
+
+impl StateHash {
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Some(Self(bytes.try_into().ok()?))
+    }
+}
+
+impl Display for StateHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for StateHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StateHash({self})")
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct StateHasher {
+    sha: Sha256,
+}
+
+impl StateHasher {
+    /// Starts the state of a step, so that the states of different steps
+    /// can't collide.
+    pub fn section(&mut self, name: &str) -> &mut Self {
+        self.write(name)
+    }
+
+    pub fn write<T: HashState + ?Sized>(&mut self, value: &T) -> &mut Self {
+        value.hash_state(self);
+        self
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.sha.update(bytes);
+    }
+
+    pub fn finish(self) -> StateHash {
+        StateHash(self.sha.finalize().into())
+    }
+}
+
+/// Values that can be hashed into a [`StateHash`].
+pub trait HashState {
+    fn hash_state(&self, hasher: &mut StateHasher);
+}
+
+macro_rules! impl_hash_state_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl HashState for $ty {
+                fn hash_state(&self, hasher: &mut StateHasher) {
+                    hasher.write_bytes(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_state_for_int!(u8, u32, u64, i32, i64);
+
+impl HashState for usize {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        (*self as u64).hash_state(hasher);
+    }
+}
+
+impl HashState for bool {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        u8::from(*self).hash_state(hasher);
+    }
+}
+
+impl HashState for f32 {
+    /// `-0.0` and `0.0`, and all NaNs, are hashed the same, since they're not
+    /// different outcomes.
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        let value = if *self == 0.0 {
+            0.0
+        }
+        else if self.is_nan() {
+            f32::NAN
+        }
+        else {
+            *self
+        };
+        value.to_bits().hash_state(hasher);
+    }
+}
+
+impl HashState for str {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.len().hash_state(hasher);
+        hasher.write_bytes(self.as_bytes());
+    }
+}
+
+impl<T: HashState> HashState for [T] {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.len().hash_state(hasher);
+        for value in self {
+            value.hash_state(hasher);
+        }
+    }
+}
+
+impl<T: HashState> HashState for Option<T> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        match self {
+            None => false.hash_state(hasher),
+            Some(value) => {
+                true.hash_state(hasher);
+                value.hash_state(hasher);
+            }
+        }
+    }
+}
+
+impl<T: HashState + ?Sized> HashState for &T {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        (**self).hash_state(hasher);
+    }
+}
+
+impl HashState for Uuid {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_bytes(self.as_bytes());
+    }
+}
+
+impl HashState for Point3<f32> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.x.hash_state(hasher);
+        self.y.hash_state(hasher);
+        self.z.hash_state(hasher);
+    }
+}
+
+macro_rules! impl_hash_state_for_id {
+    ($($ty:ty),*) => {
+        $(
+            impl HashState for $ty {
+                fn hash_state(&self, hasher: &mut StateHasher) {
+                    self.0.hash_state(hasher);
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_state_for_id!(FactionId, FleetId, OrderId, StarId, TradeRouteId);
+
+impl HashState for Stance {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        let stance = match self {
+            Self::War => "war",
+            Self::Peace => "peace",
+            Self::Alliance => "alliance",
+        };
+        hasher.write(stance);
+    }
+}
+
+impl HashState for Resource {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        let resource = match self {
+            Self::Energy => "energy",
+            Self::Metals => "metals",
+            Self::Volatiles => "volatiles",
+        };
+        hasher.write(resource);
+    }
+}
+
+impl HashState for OrderKind {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        let kind = match self {
+            Self::Move { .. } => "move",
+            Self::Survey { .. } => "survey",
+            Self::Colonize { .. } => "colonize",
+        };
+        hasher.write(kind).write(&self.star());
+    }
+}
+
+/// Harness for tests that check that a simulation step is deterministic.
+///
+/// A step's logic is replayed on an in-memory copy of its state, which is
+/// hashed the same way as the state in the database.
+#[cfg(test)]
+pub(crate) mod replay {
+    use super::{
+        StateHash,
+        StateHasher,
+    };
+
+    /// In-memory state of a simulation step.
+    pub trait Replay {
+        /// Runs the step's logic for an epoch.
+        fn run_epoch(&mut self, days: f32);
+
+        /// Hashes the state like the step does in the database.
+        fn hash_state(&self, hasher: &mut StateHasher);
+    }
+
+    pub fn hash<R: Replay>(replay: &R) -> StateHash {
+        let mut hasher = StateHasher::default();
+        replay.hash_state(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Replays `epochs` epochs on two states created by `setup`, and asserts
+    /// that their hashes are the same after every epoch.
+    ///
+    /// `setup` is called for every run, so that e.g. the iteration order of
+    /// `HashMap`s, which is random, differs between them.
+    ///
+    /// Returns the hashes after every epoch, so that tests can compare them
+    /// to the hashes of a known good version of the simulation.
+    pub fn assert_deterministic<R: Replay>(
+        setup: impl Fn() -> R,
+        epochs: usize,
+        days: f32,
+    ) -> Vec<StateHash> {
+        let mut first = setup();
+        let mut second = setup();
+        assert_eq!(hash(&first), hash(&second), "initial states differ");
+
+        (0..epochs)
+            .map(|epoch| {
+                first.run_epoch(days);
+                second.run_epoch(days);
+                let state_hash = hash(&first);
+                assert_eq!(
+                    state_hash,
+                    hash(&second),
+                    "states differ after epoch {epoch}"
+                );
+                state_hash
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StateHasher;
+
+    #[test]
+    fn hashes_are_stable() {
+        let mut hasher = StateHasher::default();
+        hasher.section("test").write(&1u32).write(&Some(-0.0f32));
+        let hash = hasher.finish();
+
+        let mut hasher = StateHasher::default();
+        hasher.section("test").write(&1u32).write(&Some(0.0f32));
+        assert_eq!(hash, hasher.finish());
+
+        let mut hasher = StateHasher::default();
+        hasher
+            .section("tes")
+            .write("t")
+            .write(&1u32)
+            .write(&Some(0.0f32));
+        assert_ne!(hash, hasher.finish(), "strings are length-prefixed");
+    }
+}
@@ -0,0 +1,346 @@
+//! Visibility of the universe to factions.
+//!
+//! A faction knows the stars it has explored, and observes everything within
+//! sensor range of its fleets and the fleets of its allies. Endpoints use
+//! [`Visibility`] to filter what they return to the [`Viewer`].
+//!
+//! There is no authentication yet, so the viewer's faction is taken from the
+//! [`FACTION_HEADER`], or the `faction` query parameter. Requests without
+//! either only see what is visible to everyone, unless they have the admin
+//! token in the [`ADMIN_TOKEN_HEADER`].
+//!
+//! Factions explore the stars they observe in the simulation, see
+//! [`Visibility::record_explored`].
+
+use std::collections::HashSet;
+
+use axum::{
+    async_trait,
+    extract::{
+        FromRequestParts,
+        Query,
+    },
+    http::{
+        request::Parts,
+        StatusCode,
+    },
+};
+use kardashev_protocol::{
+    model::{
+        faction::FactionId,
+        journal::{
+            JournalEntry,
+            JournalEvent,
+        },
+        star::{
+            CatalogIds,
+            Star,
+            StarId,
+            StarVisibility,
+        },
+    },
+    uuid::Uuid,
+    ADMIN_TOKEN_HEADER,
+    FACTION_HEADER,
+};
+use nalgebra::Point3;
+use serde::Deserialize;
+
+use crate::{
+    context::Transaction,
+    error::Error,
+    journal,
+    modules::Modules,
+    spatial::ChunkIndex,
+    util::sqlx::Vec3,
+    worlds::Worlds,
+};
+
+/// The faction on whose behalf a request is made.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Viewer {
+    pub faction: Option<FactionId>,
+
+    /// Whether the request has the admin token. Admins without a faction see
+    /// everything.
+    pub admin: bool,
+}
+
+impl Viewer {
+    pub fn faction(faction: FactionId) -> Self {
+        Self {
+            faction: Some(faction),
+            admin: false,
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Worlds> for Viewer {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        worlds: &Worlds,
+    ) -> Result<Self, Self::Rejection> {
+        let admin = if let Some(header) = parts.headers.get(ADMIN_TOKEN_HEADER) {
+            let admin_token = worlds.default_context().admin_token.as_deref();
+            if !admin_token.map_or(false, |token| {
+                constant_time_eq(header.as_bytes(), token.as_bytes())
+            }) {
+                return Err((StatusCode::UNAUTHORIZED, "invalid admin token"));
+            }
+            true
+        }
+        else {
+            false
+        };
+
+        if let Some(header) = parts.headers.get(FACTION_HEADER) {
+            let faction = header
+                .to_str()
+                .ok()
+                .and_then(|header| header.parse::<Uuid>().ok())
+                .ok_or((StatusCode::BAD_REQUEST, "invalid faction header"))?;
+
+            return Ok(Self {
+                faction: Some(FactionId(faction)),
+                admin,
+            });
+        }
+
+        // browsers can't set headers for websockets, so we also accept the faction
+        // as query parameter.
+        let Query(query) = Query::<ViewerQuery>::try_from_uri(&parts.uri)
+            .map_err(|_| (StatusCode::BAD_REQUEST, "invalid faction query parameter"))?;
+
+        Ok(Self {
+            faction: query.faction,
+            admin,
+        })
+    }
+}
+
+/// Compares the tokens in a time that doesn't depend on where they differ.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Debug, Deserialize)]
+struct ViewerQuery {
+    faction: Option<FactionId>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sensor {
+    pub position: Point3<f32>,
+    pub range: f32,
+}
+
+/// What a [`Viewer`] can see.
+#[derive(Debug)]
+pub enum Visibility {
+    /// The viewer sees everything.
+    Unrestricted,
+
+    /// The viewer has no faction, and only sees what is visible to everyone.
+    Restricted,
+
+    Faction {
+        faction: FactionId,
+        explored: HashSet<StarId>,
+
+        /// Sensors by the chunks their range overlaps.
+        sensors: ChunkIndex<Sensor>,
+    },
+}
+
+impl Visibility {
+    /// Loads what the viewer knows about.
+    ///
+    /// Factions also see what the fleets of factions see, that share their
+    /// sensors with them through a [module](crate::modules), e.g. allies.
+    pub async fn load(
+        tx: &mut Transaction<'_>,
+        viewer: &Viewer,
+        modules: &Modules,
+    ) -> Result<Self, Error> {
+        let Some(faction) = viewer.faction
+        else {
+            return Ok(if viewer.admin {
+                Self::Unrestricted
+            }
+            else {
+                Self::Restricted
+            });
+        };
+
+        let explored = sqlx::query!(
+            "SELECT star_id FROM explored_star WHERE faction_id = $1",
+            faction.0,
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| StarId(row.star_id))
+        .collect();
+
+        let mut factions = modules.sensors_shared_with(tx, faction).await?;
+        factions.push(faction);
+        let sensors = sqlx::query!(
+            r#"
+            SELECT
+                position AS "position: Vec3",
+                sensor_range
+            FROM fleet
+            WHERE faction_id = ANY($1) AND sensor_range > 0
+            "#,
+            &factions.iter().map(|faction| faction.0).collect::<Vec<_>>(),
+        )
+        .fetch_all(&mut ***tx)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let sensor = Sensor {
+                position: row.position.into(),
+                range: row.sensor_range,
+            };
+            (sensor.position, sensor.range, sensor)
+        })
+        .collect();
+
+        Ok(Self::Faction {
+            faction,
+            explored,
+            sensors,
+        })
+    }
+
+    /// Visibility of a faction that only knows what its `sensors` observe,
+    /// e.g. in a [snapshot](crate::replay) of the past.
+    pub fn from_sensors(faction: FactionId, sensors: impl IntoIterator<Item = Sensor>) -> Self {
+        Self::Faction {
+            faction,
+            explored: HashSet::new(),
+            sensors: sensors
+                .into_iter()
+                .map(|sensor| (sensor.position, sensor.range, sensor))
+                .collect(),
+        }
+    }
+
+    /// Returns whether `position` is within sensor range.
+    pub fn is_observed(&self, position: &Point3<f32>) -> bool {
+        match self {
+            Self::Unrestricted => true,
+            Self::Restricted => false,
+            Self::Faction { sensors, .. } => {
+                sensors
+                    .get(position)
+                    .iter()
+                    .any(|sensor| (sensor.position - position).norm() <= sensor.range)
+            }
+        }
+    }
+
+    /// Returns whether the viewer owns something that belongs to `faction`.
+    pub fn owns(&self, faction: Option<FactionId>) -> bool {
+        match self {
+            Self::Unrestricted => true,
+            Self::Restricted => false,
+            Self::Faction {
+                faction: viewer, ..
+            } => faction == Some(*viewer),
+        }
+    }
+
+    pub fn star_visibility(&self, star: StarId, position: &Point3<f32>) -> StarVisibility {
+        match self {
+            Self::Unrestricted => StarVisibility::Observed,
+            Self::Restricted => StarVisibility::Unexplored,
+            Self::Faction { explored, .. } => {
+                if self.is_observed(position) {
+                    StarVisibility::Observed
+                }
+                else if explored.contains(&star) {
+                    StarVisibility::Explored
+                }
+                else {
+                    StarVisibility::Unexplored
+                }
+            }
+        }
+    }
+
+    /// Records that the viewer explored the observed stars, which are given
+    /// with their positions.
+    ///
+    /// Once a star was observed it stays explored, even after it's out of
+    /// sensor range again. Returns the journal entries for the newly explored
+    /// stars, which should be published after the transaction is committed.
+    pub async fn record_explored(
+        &self,
+        tx: &mut Transaction<'_>,
+        stars: &[(StarId, Point3<f32>)],
+    ) -> Result<Vec<JournalEntry>, Error> {
+        let Self::Faction {
+            faction, explored, ..
+        } = self
+        else {
+            return Ok(vec![]);
+        };
+
+        let star_ids = stars
+            .iter()
+            .filter(|(star, position)| !explored.contains(star) && self.is_observed(position))
+            .map(|(star, _)| star.0)
+            .collect::<Vec<_>>();
+        if star_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let discovered = sqlx::query!(
+            r#"
+            INSERT INTO explored_star (faction_id, star_id)
+            SELECT $1, star_id FROM UNNEST($2::UUID[]) AS star_id
+            ON CONFLICT DO NOTHING
+            RETURNING star_id
+            "#,
+            faction.0,
+            &star_ids,
+        )
+        .fetch_all(&mut ***tx)
+        .await?;
+
+        tracing::debug!(faction = %faction.0, num_stars = discovered.len(), "explored stars");
+
+        let mut entries = Vec::with_capacity(discovered.len());
+        for row in discovered {
+            entries.push(
+                journal::append(
+                    tx,
+                    *faction,
+                    JournalEvent::Discovery {
+                        star: StarId(row.star_id),
+                    },
+                )
+                .await?,
+            );
+        }
+
+        Ok(entries)
+    }
+
+    /// Sets the star's visibility, and hides what isn't known about it.
+    ///
+    /// For unexplored stars only what can be observed from afar remains.
+    pub fn filter_star(&self, star: &mut Star) {
+        star.visibility = self.star_visibility(star.id, &star.position);
+
+        if star.visibility == StarVisibility::Unexplored {
+            star.name = None;
+            star.name_generated = false;
+            star.catalog_ids = CatalogIds::default();
+        }
+    }
+}
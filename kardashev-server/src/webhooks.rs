@@ -0,0 +1,192 @@
+//! Webhooks to which a world's events are posted.
+//!
+//! Webhooks are stored with the world's tables and managed through the admin
+//! API. The message is sent in `content` for Discord and in `text` for Slack,
+//! which both ignore the other field. Events are delivered in the background,
+//! and failed deliveries are only logged, so that a broken webhook doesn't
+//! affect the game.
+
+use std::time::Duration;
+
+use kardashev_protocol::{
+    admin::WebhookEvent,
+    model::faction::FactionId,
+};
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    context::{
+        Context,
+        Transaction,
+    },
+    error::Error,
+};
+
+/// Time after which a delivery is aborted.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name under which the messages are posted.
+const USERNAME: &str = "Kardashev";
+
+/// The HTTP client with which webhooks are delivered, shared by all worlds.
+#[derive(Clone, Debug)]
+pub struct Webhooks {
+    client: reqwest::Client,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(TIMEOUT)
+                .build()
+                .expect("failed to build http client"),
+        }
+    }
+}
+
+/// An event, with what's needed to describe it.
+#[derive(Clone, Debug)]
+pub enum Notification {
+    PlayerJoined { faction: String },
+    WarDeclared { aggressor: String, defender: String },
+    ServerStarted { version: String },
+    BackupFailed { name: String, error: String },
+}
+
+impl Notification {
+    pub fn event(&self) -> WebhookEvent {
+        match self {
+            Self::PlayerJoined { .. } => WebhookEvent::PlayerJoined,
+            Self::WarDeclared { .. } => WebhookEvent::WarDeclared,
+            Self::ServerStarted { .. } => WebhookEvent::ServerStarted,
+            Self::BackupFailed { .. } => WebhookEvent::BackupFailed,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::PlayerJoined { faction } => format!("{faction} joined the game."),
+            Self::WarDeclared {
+                aggressor,
+                defender,
+            } => format!("{aggressor} declared war on {defender}."),
+            Self::ServerStarted { version } => format!("The server started (version {version})."),
+            Self::BackupFailed { name, error } => format!("Backup {name} failed: {error}"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    username: &'a str,
+
+    /// The message for Discord.
+    content: &'a str,
+
+    /// The message for Slack.
+    text: &'a str,
+}
+
+impl<'a> Payload<'a> {
+    fn new(message: &'a str) -> Self {
+        Self {
+            username: USERNAME,
+            content: message,
+            text: message,
+        }
+    }
+}
+
+/// Parses the URL of a new webhook, which must be `http` or `https`.
+pub fn parse_url(url: &str) -> Result<Url, Error> {
+    let url = Url::parse(url).map_err(|_| Error::InvalidWebhookUrl)?;
+    if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+        return Err(Error::InvalidWebhookUrl);
+    }
+    Ok(url)
+}
+
+/// Returns the name of a faction, for notifications.
+pub async fn faction_name(tx: &mut Transaction<'_>, faction: FactionId) -> Result<String, Error> {
+    let name = sqlx::query_scalar!("SELECT name FROM faction WHERE faction_id = $1", faction.0,)
+        .fetch_optional(&mut ***tx)
+        .await?;
+    Ok(name.unwrap_or_else(|| faction.0.to_string()))
+}
+
+/// Posts a notification to the world's webhooks that want its event, in the
+/// background.
+pub fn notify(context: &Context, notification: Notification) {
+    let context = context.clone();
+    tokio::spawn(async move {
+        if let Err(error) = deliver(&context, &notification).await {
+            tracing::warn!(world = %context.world.0, ?error, "failed to deliver webhooks");
+        }
+    });
+}
+
+async fn deliver(context: &Context, notification: &Notification) -> Result<(), Error> {
+    let webhooks = {
+        let mut tx = context.transaction().await?;
+        sqlx::query!(
+            "SELECT id, url FROM webhook WHERE $1 = ANY(events)",
+            notification.event().as_str(),
+        )
+        .fetch_all(&mut **tx)
+        .await?
+    };
+
+    let message = notification.message();
+    let payload = Payload::new(&message);
+    for webhook in webhooks {
+        let result = context
+            .webhooks
+            .client
+            .post(&webhook.url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+
+        // the URL isn't logged, because it usually contains a token.
+        match result {
+            Ok(_) => tracing::debug!(id = %webhook.id, "delivered webhook"),
+            Err(error) => {
+                tracing::warn!(id = %webhook.id, error = %error.without_url(), "failed to deliver webhook");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_url,
+        Notification,
+        Payload,
+    };
+
+    #[test]
+    fn payload_works_for_discord_and_slack() {
+        let notification = Notification::WarDeclared {
+            aggressor: "Vogons".to_owned(),
+            defender: "Earth".to_owned(),
+        };
+        let message = notification.message();
+        let payload = serde_json::to_value(Payload::new(&message)).unwrap();
+        assert_eq!(payload["content"], "Vogons declared war on Earth.");
+        assert_eq!(payload["text"], payload["content"]);
+    }
+
+    #[test]
+    fn only_http_urls_are_accepted() {
+        assert!(parse_url("https://discord.com/api/webhooks/1/token").is_ok());
+        assert!(parse_url("http://localhost:8080/hook").is_ok());
+        assert!(parse_url("file:///etc/passwd").is_err());
+        assert!(parse_url("not a url").is_err());
+    }
+}
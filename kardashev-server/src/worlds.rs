@@ -0,0 +1,425 @@
+//! Worlds hosted by the server.
+//!
+//! Each world is a separate galaxy with its own stars, factions, etc. Its
+//! tables are in their own schema, so that all queries work unchanged in any
+//! world: the connections of a world's [`Context`] have the world's schema in
+//! their search path.
+//! The default world's tables are in the public schema, so that existing
+//! databases become the default world.
+//!
+//! Requests select their world with the [`WORLD_HEADER`], or the `world` query
+//! parameter, and every world runs its own background jobs.
+
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{
+    async_trait,
+    extract::{
+        FromRequestParts,
+        Query,
+    },
+    http::request::Parts,
+};
+use kardashev_protocol::{
+    model::world::{
+        World,
+        WorldId,
+    },
+    WORLD_HEADER,
+};
+use serde::Deserialize;
+use sqlx::{
+    PgPool,
+    Postgres,
+};
+use uuid::Uuid;
+
+use crate::{
+    context::{
+        world_pool,
+        Context,
+    },
+    error::Error,
+    modules::Modules,
+    simulation::SimulationConfig,
+    webhooks::{
+        self,
+        Notification,
+    },
+};
+
+/// Configuration of the background jobs, which every world runs.
+#[derive(Clone, Debug)]
+pub struct JobsConfig {
+    pub simulation: SimulationConfig,
+    pub leaderboard_interval: Option<Duration>,
+    pub modules: Modules,
+}
+
+/// The worlds and their contexts.
+#[derive(Clone)]
+pub struct Worlds {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    default: Context,
+    jobs: JobsConfig,
+    contexts: Mutex<HashMap<WorldId, Context>>,
+}
+
+impl Worlds {
+    /// Creates the worlds with the default world's context, and starts its
+    /// jobs. The other worlds are started by [`load`](Self::load).
+    pub fn new(default: Context, jobs: JobsConfig) -> Self {
+        let worlds = Self {
+            inner: Arc::new(Inner {
+                default: default.clone(),
+                jobs,
+                contexts: Mutex::new(HashMap::new()),
+            }),
+        };
+        worlds.register(default);
+        worlds
+    }
+
+    /// The modules of game rules that all worlds run.
+    pub fn modules(&self) -> &Modules {
+        &self.inner.jobs.modules
+    }
+
+    /// How the worlds are simulated.
+    pub fn simulation(&self) -> &SimulationConfig {
+        &self.inner.jobs.simulation
+    }
+
+    /// The default world's context, which holds what all worlds share.
+    pub fn default_context(&self) -> &Context {
+        &self.inner.default
+    }
+
+    /// Adds a world's context and starts its jobs, unless it was already
+    /// added.
+    fn register(&self, context: Context) -> Context {
+        let mut contexts = self.inner.contexts.lock().unwrap();
+        if let Some(context) = contexts.get(&context.world) {
+            return context.clone();
+        }
+
+        tracing::info!(world = %context.world.0, "starting world");
+        spawn_jobs(&context, &self.inner.jobs);
+        webhooks::notify(
+            &context,
+            Notification::ServerStarted {
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+            },
+        );
+        contexts.insert(context.world, context.clone());
+        context
+    }
+
+    fn context_for(&self, world: WorldId, schema_name: String) -> Context {
+        // the default world's schema is the public one, which is in the search path
+        // anyway.
+        let schema = (schema_name != "public").then_some(schema_name.as_str());
+        self.inner.default.for_world(world, schema)
+    }
+
+    /// Starts all worlds in the database.
+    pub async fn load(self) {
+        let result = async {
+            let rows = sqlx::query!("SELECT id, schema_name FROM public.world")
+                .fetch_all(self.inner.default.db())
+                .await?;
+            for row in rows {
+                let world = WorldId(row.id);
+                self.register(self.context_for(world, row.schema_name));
+            }
+            Ok::<(), Error>(())
+        }
+        .await;
+
+        if let Err(error) = result {
+            tracing::error!(?error, "failed to load worlds");
+        }
+    }
+
+    /// Returns the context of a world, or `None` if it doesn't exist.
+    pub async fn get(&self, world: WorldId) -> Result<Option<Context>, Error> {
+        if let Some(context) = self.inner.contexts.lock().unwrap().get(&world) {
+            return Ok(Some(context.clone()));
+        }
+
+        // the world might not be loaded yet.
+        let row = sqlx::query!(
+            "SELECT schema_name FROM public.world WHERE id = $1",
+            world.0,
+        )
+        .fetch_optional(self.inner.default.db())
+        .await?;
+
+        Ok(row.map(|row| self.register(self.context_for(world, row.schema_name))))
+    }
+
+    pub async fn list(&self) -> Result<Vec<World>, Error> {
+        let worlds = sqlx::query!(
+            r#"
+            SELECT id, name, created_at
+            FROM public.world
+            ORDER BY created_at ASC
+            "#
+        )
+        .fetch_all(self.inner.default.db())
+        .await?
+        .into_iter()
+        .map(|row| {
+            World {
+                id: WorldId(row.id),
+                name: row.name,
+                created_at: row.created_at,
+            }
+        })
+        .collect();
+
+        Ok(worlds)
+    }
+
+    /// Creates a new, empty world and starts it.
+    ///
+    /// This creates the world's schema and runs all migrations in it.
+    pub async fn create(&self, name: String) -> Result<World, Error> {
+        let id = WorldId(Uuid::new_v4());
+        let schema_name = format!("world_{}", id.0.simple());
+
+        let mut tx = self.inner.default.db().begin().await?;
+
+        let exists = sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM public.world WHERE name = $1) AS "exists!""#,
+            name,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .exists;
+        if exists {
+            return Err(Error::WorldExists);
+        }
+
+        sqlx::query(&format!(r#"CREATE SCHEMA "{schema_name}""#))
+            .execute(&mut *tx)
+            .await?;
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO public.world (id, name, schema_name)
+            VALUES ($1, $2, $3)
+            RETURNING created_at
+            "#,
+            id.0,
+            name,
+            schema_name,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        // the migrations run on the world's own connections, so that the default
+        // world's connections never see its types.
+        if let Err(error) = migrate_schema(
+            self.inner.default.db(),
+            &schema_name,
+            &self.inner.jobs.modules,
+        )
+        .await
+        {
+            let mut tx = self.inner.default.db().begin().await?;
+            sqlx::query!("DELETE FROM public.world WHERE id = $1", id.0)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!(r#"DROP SCHEMA "{schema_name}" CASCADE"#))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            return Err(error);
+        }
+
+        self.register(self.context_for(id, schema_name));
+
+        Ok(World {
+            id,
+            name,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Runs the migrations for the default world, and then for all other worlds.
+pub async fn migrate(db: &PgPool, modules: &Modules) -> Result<(), Error> {
+    let mut tx = db.begin().await?;
+    run_migrations(&mut tx, modules).await?;
+    tx.commit().await?;
+
+    let rows = sqlx::query!("SELECT schema_name FROM public.world WHERE schema_name <> 'public'")
+        .fetch_all(db)
+        .await?;
+    for row in rows {
+        tracing::info!(schema = %row.schema_name, "migrating world");
+        migrate_schema(db, &row.schema_name, modules).await?;
+    }
+
+    Ok(())
+}
+
+/// Migrations that haven't been run in a world's schema.
+#[derive(Clone, Debug)]
+pub struct PendingMigrations {
+    pub schema_name: String,
+
+    /// Versions and descriptions of the migrations.
+    pub migrations: Vec<(i64, String)>,
+}
+
+/// Finds the core and module migrations that haven't been run yet, for the
+/// default world and all other worlds.
+pub async fn pending_migrations(
+    db: &PgPool,
+    modules: &Modules,
+) -> Result<Vec<PendingMigrations>, Error> {
+    let migrators = std::iter::once(sqlx::migrate!("../migrations"))
+        .chain(modules.migrators())
+        .collect::<Vec<_>>();
+
+    let table_exists = |schema_name: String, table_name: &'static str| {
+        sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM information_schema.tables WHERE table_schema = $1 AND table_name = $2)",
+        )
+        .bind(schema_name)
+        .bind(table_name)
+        .fetch_one(db)
+    };
+
+    // the world table doesn't exist before the first migration.
+    let mut schema_names = vec!["public".to_owned()];
+    if table_exists("public".to_owned(), "world").await? {
+        schema_names.extend(
+            sqlx::query_scalar::<_, String>(
+                "SELECT schema_name FROM public.world WHERE schema_name <> 'public'",
+            )
+            .fetch_all(db)
+            .await?,
+        );
+    }
+
+    let mut pending = vec![];
+    for schema_name in schema_names {
+        let applied = if table_exists(schema_name.clone(), "_sqlx_migrations").await? {
+            sqlx::query_scalar::<_, i64>(&format!(
+                r#"SELECT version FROM "{schema_name}"._sqlx_migrations WHERE success"#
+            ))
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .collect()
+        }
+        else {
+            HashSet::new()
+        };
+
+        let migrations = migrators
+            .iter()
+            .flat_map(|migrator| migrator.iter())
+            .filter(|migration| {
+                !migration.migration_type.is_down_migration()
+                    && !applied.contains(&migration.version)
+            })
+            .map(|migration| (migration.version, migration.description.to_string()))
+            .collect::<Vec<_>>();
+        if !migrations.is_empty() {
+            pending.push(PendingMigrations {
+                schema_name,
+                migrations,
+            });
+        }
+    }
+
+    Ok(pending)
+}
+
+/// Runs the migrations in a world's schema, on a connection of its own.
+async fn migrate_schema(db: &PgPool, schema_name: &str, modules: &Modules) -> Result<(), Error> {
+    let db = world_pool(db, schema_name);
+    let mut tx = db.begin().await?;
+    run_migrations(&mut tx, modules).await?;
+    tx.commit().await?;
+    db.close().await;
+    Ok(())
+}
+
+/// Runs the core migrations, and then the modules'.
+async fn run_migrations(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    modules: &Modules,
+) -> Result<(), Error> {
+    let mut migrator = sqlx::migrate!("../migrations");
+    // the modules' migrations are in the same table.
+    migrator.set_ignore_missing(true);
+    migrator.run(&mut **tx).await?;
+    modules.migrate(tx).await
+}
+
+fn spawn_jobs(context: &Context, jobs: &JobsConfig) {
+    tokio::spawn(crate::names::backfill(context.clone()));
+    tokio::spawn(crate::regions::backfill(context.clone()));
+    tokio::spawn(crate::impostors::backfill(context.clone()));
+    tokio::spawn(crate::backup::run(context.clone()));
+    tokio::spawn(crate::simulation::run(
+        context.clone(),
+        jobs.simulation,
+        jobs.modules.simulation_steps(),
+    ));
+    if let Some(interval) = jobs.leaderboard_interval {
+        tokio::spawn(crate::leaderboard::run(context.clone(), interval));
+    }
+    jobs.modules.spawn_jobs(context);
+}
+
+#[derive(Debug, Deserialize)]
+struct WorldQuery {
+    world: Option<WorldId>,
+}
+
+/// Extracts the context of the world in which a request is made.
+#[async_trait]
+impl FromRequestParts<Worlds> for Context {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, worlds: &Worlds) -> Result<Self, Error> {
+        let world = if let Some(header) = parts.headers.get(WORLD_HEADER) {
+            header
+                .to_str()
+                .ok()
+                .and_then(|header| header.parse::<Uuid>().ok())
+                .map(WorldId)
+                .ok_or(Error::InvalidWorld)?
+        }
+        else {
+            // browsers can't set headers for websockets, so we also accept the world as
+            // query parameter.
+            let Query(query) =
+                Query::<WorldQuery>::try_from_uri(&parts.uri).map_err(|_| Error::InvalidWorld)?;
+            query.world.unwrap_or_default()
+        };
+
+        worlds.get(world).await?.ok_or(Error::UnknownWorld)
+    }
+}
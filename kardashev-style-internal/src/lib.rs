@@ -1,8 +1,13 @@
 mod manifest;
+mod palette;
 mod rename;
+mod theme;
 
 use std::{
-    collections::HashMap,
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
     fmt::Debug,
     io::Write,
     path::{
@@ -22,8 +27,16 @@ use lightningcss::{
 };
 
 use crate::{
-    manifest::StyleMetadata,
+    manifest::{
+        read_style_metadata,
+        StyleMetadata,
+    },
     rename::RenameClassNames,
+    theme::Themes,
+};
+pub use crate::{
+    palette::Palettes,
+    theme::DEFAULT_THEME,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -54,6 +67,14 @@ pub enum Error {
         source: std::io::Error,
         path: PathBuf,
     },
+    #[error("Error while reading output CSS: {path}")]
+    ReadOutput {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("Output CSS has no valid header: {path}")]
+    InvalidOutputHeader { path: PathBuf },
     #[error("File not found: {path}")]
     FileNotFound { path: PathBuf },
     #[error("Crate name could not be determined.")]
@@ -83,24 +104,41 @@ pub enum Error {
         source: toml::de::Error,
         path: PathBuf,
     },
+    #[error("Could not read palette: {path}")]
+    ReadPalette {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("Could not parse palette: {path}\n{source}")]
+    ParsePalette {
+        #[source]
+        source: toml::de::Error,
+        path: PathBuf,
+    },
+    #[error("Invalid palette: {path}: {message}")]
+    InvalidPalette { message: String, path: PathBuf },
+    #[error("Invalid theme: {path}: {message}")]
+    InvalidTheme { message: String, path: PathBuf },
 }
 
 #[derive(Debug)]
 pub struct Output {
     pub class_names: HashMap<String, String>,
+
+    /// Mapping from the names of the variables declared in the `:theme` block
+    /// (without `--`) to their mangled names.
+    pub variables: BTreeMap<String, String>,
+
+    /// Names of the themes, with [`DEFAULT_THEME`] first. This is empty if
+    /// the stylesheet declares no variables.
+    pub themes: Vec<String>,
+
     pub css: String,
     pub css_path: PathBuf,
 }
 
 pub fn prepare_import(input_path: &Path, track: impl FnMut(&Path)) -> Result<Output, Error> {
-    let track_fs = TrackFs {
-        track: Mutex::new(track),
-    };
-    let options = grass::Options::default()
-        .fs(&track_fs)
-        .style(grass::OutputStyle::Expanded)
-        .input_syntax(grass::InputSyntax::Scss);
-
     let manifest_dir = PathBuf::from(
         std::env::var("CARGO_MANIFEST_DIR").map_err(|source| Error::NoManifestDir { source })?,
     );
@@ -122,23 +160,150 @@ pub fn prepare_import(input_path: &Path, track: impl FnMut(&Path)) -> Result<Out
         });
     }
 
-    let mut class_names = HashMap::new();
+    let compiled = compile(&input_path, &crate_name, track)?;
+
+    let output_path = metadata
+        .output
+        .as_deref()
+        .unwrap_or_else(|| Path::new("./target/css/kardashev-ui"));
+    std::fs::create_dir_all(output_path).map_err(|source| {
+        Error::CreateDirectory {
+            source,
+            path: output_path.to_owned(),
+        }
+    })?;
+    let output_path = output_path.join(format!("{crate_name}-{}.scss", file_id(&input_path)));
+
+    let css = write_output(&output_path, &crate_name, &input_path, &compiled)?;
+
+    Ok(Output {
+        class_names: compiled.class_names,
+        variables: compiled.themes.variables.clone(),
+        themes: compiled.themes.names().map(ToOwned::to_owned).collect(),
+        css,
+        css_path: output_path,
+    })
+}
+
+/// Reads the palettes configured in the manifest of the crate in
+/// `manifest_dir`, if any.
+pub fn read_palettes(manifest_dir: &Path) -> Result<Option<Palettes>, Error> {
+    let (package_name, metadata) = read_style_metadata(&manifest_dir.join("Cargo.toml"))?;
+    let Some(palette_path) = metadata.palette
+    else {
+        return Ok(None);
+    };
+    let prefix = metadata
+        .crate_name
+        .unwrap_or_else(|| package_name.replace('-', "_"));
+    Ok(Some(Palettes::read(
+        &manifest_dir.join(palette_path),
+        &prefix,
+    )?))
+}
+
+/// Result of [`regenerate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Regenerated {
+    /// The output was rewritten with the new CSS.
+    Css,
 
+    /// The input now has different class names, variables or themes (or was
+    /// removed), so the code that uses them must be recompiled. The output was
+    /// not changed.
+    ClassNamesChanged,
+}
+
+/// Compiles the input of an existing output file again, without the macro.
+///
+/// This is used when watching for changes to the stylesheets, so that only the
+/// CSS needs to be rebuilt, as long as the class names, variables and themes
+/// stay the same.
+pub fn regenerate(output_path: &Path) -> Result<Regenerated, Error> {
+    let output = std::fs::read_to_string(output_path).map_err(|source| {
+        Error::ReadOutput {
+            source,
+            path: output_path.to_owned(),
+        }
+    })?;
+    let header = OutputHeader::parse(&output).ok_or_else(|| {
+        Error::InvalidOutputHeader {
+            path: output_path.to_owned(),
+        }
+    })?;
+
+    if !header.input_path.exists() {
+        return Ok(Regenerated::ClassNamesChanged);
+    }
+
+    let compiled = compile(&header.input_path, &header.crate_name, |_| {})?;
+    if compiled.class_names != header.class_names
+        || compiled.themes.variables != header.variables
+        || !compiled
+            .themes
+            .names()
+            .eq(header.themes.iter().map(String::as_str))
+    {
+        return Ok(Regenerated::ClassNamesChanged);
+    }
+
+    write_output(
+        output_path,
+        &header.crate_name,
+        &header.input_path,
+        &compiled,
+    )?;
+
+    Ok(Regenerated::Css)
+}
+
+/// Identifies an input file in the mangled class names.
+fn file_id(input_path: &Path) -> String {
     let hash = fasthash::xx::hash64(input_path.as_os_str().as_encoded_bytes());
-    let file_id = bs58::encode(&hash.to_be_bytes()).into_string();
+    bs58::encode(&hash.to_be_bytes()).into_string()
+}
+
+/// A compiled stylesheet.
+#[derive(Debug)]
+struct Compiled {
+    /// Mapping from original to mangled class names.
+    class_names: HashMap<String, String>,
+    themes: Themes,
+    code: String,
+}
+
+/// Compiles the SCSS, extracts its themes, and mangles its class names and
+/// variables.
+fn compile(
+    input_path: &Path,
+    crate_name: &str,
+    track: impl FnMut(&Path),
+) -> Result<Compiled, Error> {
+    let track_fs = TrackFs {
+        track: Mutex::new(track),
+    };
+    let options = grass::Options::default()
+        .fs(&track_fs)
+        .style(grass::OutputStyle::Expanded)
+        .input_syntax(grass::InputSyntax::Scss);
+
+    let mut class_names = HashMap::new();
+
+    let file_id = file_id(input_path);
 
     let mut visitor = RenameClassNames {
         class_names: &mut class_names,
         file_id: &file_id,
-        crate_name: &crate_name,
+        crate_name,
     };
 
-    let css = grass::from_path(&input_path, &options).map_err(|source| {
+    let css = grass::from_path(input_path, &options).map_err(|source| {
         Error::Grass {
             source,
             path: input_path.to_owned(),
         }
     })?;
+    let (themes, css) = Themes::extract(&css, crate_name, &file_id, input_path)?;
 
     let parser_options = ParserOptions::default();
     let mut css = StyleSheet::parse(&css, parser_options).map_err(|source| {
@@ -158,17 +323,21 @@ pub fn prepare_import(input_path: &Path, track: impl FnMut(&Path)) -> Result<Out
         }
     })?;
 
-    let output_path = metadata
-        .output
-        .as_deref()
-        .unwrap_or_else(|| Path::new("./target/css/kardashev-ui"));
-    std::fs::create_dir_all(output_path).map_err(|source| {
-        Error::CreateDirectory {
-            source,
-            path: output_path.to_owned(),
-        }
-    })?;
-    let output_path = output_path.join(format!("{crate_name}-{file_id}.scss"));
+    Ok(Compiled {
+        class_names,
+        themes,
+        code: output.code,
+    })
+}
+
+/// Writes the output file, with a header that [`regenerate`] reads.
+fn write_output(
+    output_path: &Path,
+    crate_name: &str,
+    input_path: &Path,
+    compiled: &Compiled,
+) -> Result<String, Error> {
+    let file_id = file_id(input_path);
 
     let mut output_css = vec![];
     write!(
@@ -183,26 +352,86 @@ pub fn prepare_import(input_path: &Path, track: impl FnMut(&Path)) -> Result<Out
         input_path.display()
     )
     .unwrap();
-    for (original_class_name, mangled_class_name) in &class_names {
+    for (original_class_name, mangled_class_name) in &compiled.class_names {
         writeln!(
             &mut output_css,
             "        {original_class_name} -> {mangled_class_name}"
         )
         .unwrap()
     }
-    writeln!(&mut output_css, "*/\n\n{}\n", output.code).unwrap();
-    std::fs::write(&output_path, &output_css).map_err(|source| {
+    if !compiled.themes.variables.is_empty() {
+        writeln!(&mut output_css, "    Variables:").unwrap();
+        for (variable, mangled_variable) in &compiled.themes.variables {
+            writeln!(&mut output_css, "        {variable} -> {mangled_variable}").unwrap()
+        }
+        let themes = compiled.themes.names().collect::<Vec<_>>();
+        writeln!(&mut output_css, "    Themes: {}", themes.join(", ")).unwrap();
+    }
+    writeln!(&mut output_css, "*/\n\n{}\n", compiled.code).unwrap();
+    std::fs::write(output_path, &output_css).map_err(|source| {
         Error::WriteOutput {
             source,
-            path: output_path.clone(),
+            path: output_path.to_owned(),
         }
     })?;
 
-    Ok(Output {
-        class_names,
-        css: String::from_utf8(output_css).expect("output css contains invalid UTF-8"),
-        css_path: output_path,
-    })
+    Ok(String::from_utf8(output_css).expect("output css contains invalid UTF-8"))
+}
+
+/// The header of an output file.
+#[derive(Debug)]
+struct OutputHeader {
+    crate_name: String,
+    input_path: PathBuf,
+    class_names: HashMap<String, String>,
+    variables: BTreeMap<String, String>,
+    themes: Vec<String>,
+}
+
+impl OutputHeader {
+    fn parse(output: &str) -> Option<Self> {
+        let mut crate_name = None;
+        let mut input_path = None;
+        let mut class_names = HashMap::new();
+        let mut variables = BTreeMap::new();
+        let mut themes = vec![];
+        let mut in_variables = false;
+
+        for line in output.lines() {
+            let line = line.trim();
+            if line == "*/" {
+                break;
+            }
+            else if let Some(value) = line.strip_prefix("Crate: ") {
+                crate_name = Some(value.to_owned());
+            }
+            else if let Some(value) = line.strip_prefix("Input: ") {
+                input_path = Some(PathBuf::from(value));
+            }
+            else if line == "Variables:" {
+                in_variables = true;
+            }
+            else if let Some(value) = line.strip_prefix("Themes: ") {
+                themes = value.split(", ").map(ToOwned::to_owned).collect();
+            }
+            else if let Some((original, mangled)) = line.split_once(" -> ") {
+                if in_variables {
+                    variables.insert(original.to_owned(), mangled.to_owned());
+                }
+                else {
+                    class_names.insert(original.to_owned(), mangled.to_owned());
+                }
+            }
+        }
+
+        Some(Self {
+            crate_name: crate_name?,
+            input_path: input_path?,
+            class_names,
+            variables,
+            themes,
+        })
+    }
 }
 
 struct TrackFs<F> {
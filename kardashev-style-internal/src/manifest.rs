@@ -14,6 +14,7 @@ struct Manifest {
 
 #[derive(Clone, Debug, Deserialize)]
 struct Package {
+    name: String,
     #[serde(default)]
     metadata: Metadata,
 }
@@ -34,6 +35,10 @@ struct KardashevMetadata {
 pub struct StyleMetadata {
     pub output: Option<PathBuf>,
     pub crate_name: Option<String>,
+
+    /// Palette file, relative to the manifest, see
+    /// [`Palettes`](crate::Palettes).
+    pub palette: Option<PathBuf>,
 }
 
 impl Default for StyleMetadata {
@@ -41,24 +46,36 @@ impl Default for StyleMetadata {
         Self {
             output: None,
             crate_name: None,
+            palette: None,
         }
     }
 }
 
 impl StyleMetadata {
     pub fn read(manifest_path: &Path) -> Result<Self, Error> {
-        let toml = std::fs::read_to_string(manifest_path).map_err(|source| {
-            Error::ReadManifest {
-                source,
-                path: manifest_path.to_owned(),
-            }
-        })?;
-        let manifest: Manifest = toml::from_str(&toml).map_err(|source| {
-            Error::ParseManifest {
-                source,
-                path: manifest_path.to_owned(),
-            }
-        })?;
-        Ok(manifest.package.metadata.kardashev.style)
+        Ok(read_package(manifest_path)?.metadata.kardashev.style)
     }
 }
+
+/// Reads the package section of a manifest.
+fn read_package(manifest_path: &Path) -> Result<Package, Error> {
+    let toml = std::fs::read_to_string(manifest_path).map_err(|source| {
+        Error::ReadManifest {
+            source,
+            path: manifest_path.to_owned(),
+        }
+    })?;
+    let manifest: Manifest = toml::from_str(&toml).map_err(|source| {
+        Error::ParseManifest {
+            source,
+            path: manifest_path.to_owned(),
+        }
+    })?;
+    Ok(manifest.package)
+}
+
+/// Reads the package's name and style metadata.
+pub fn read_style_metadata(manifest_path: &Path) -> Result<(String, StyleMetadata), Error> {
+    let package = read_package(manifest_path)?;
+    Ok((package.name, package.metadata.kardashev.style))
+}
@@ -0,0 +1,270 @@
+//! Palettes of semantic colors.
+//!
+//! A palette file defines the colors of tokens like `primary` or `error` for
+//! several palettes:
+//!
+//! ```toml
+//! [palettes.default]
+//! primary = "#1f6404"
+//! error = "#dc3545"
+//!
+//! [palettes.deuteranopia]
+//! error = "#d55e00"
+//! ```
+//!
+//! The `default` palette must define all tokens. Other palettes use the
+//! default colors for tokens they don't define.
+//!
+//! The palettes are emitted as CSS custom properties (e.g.
+//! `--kardashev-primary`), with the other palettes selected by the
+//! `data-palette` attribute on the document element, and as Rust constants.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::Deserialize;
+
+use crate::Error;
+
+/// Name of the palette that defines all tokens.
+const DEFAULT_PALETTE: &str = "default";
+
+#[derive(Debug, Deserialize)]
+struct PaletteFile {
+    palettes: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Palettes {
+    path: PathBuf,
+    prefix: String,
+    tokens: Vec<String>,
+    palettes: Vec<(String, BTreeMap<String, [u8; 3]>)>,
+}
+
+impl Palettes {
+    /// Reads a palette file.
+    ///
+    /// `prefix` is prepended to the names of the CSS custom properties.
+    pub fn read(path: &Path, prefix: &str) -> Result<Self, Error> {
+        let toml = std::fs::read_to_string(path).map_err(|source| {
+            Error::ReadPalette {
+                source,
+                path: path.to_owned(),
+            }
+        })?;
+        let file: PaletteFile = toml::from_str(&toml).map_err(|source| {
+            Error::ParsePalette {
+                source,
+                path: path.to_owned(),
+            }
+        })?;
+
+        let invalid = |message: String| {
+            Error::InvalidPalette {
+                message,
+                path: path.to_owned(),
+            }
+        };
+
+        let tokens = file
+            .palettes
+            .get(DEFAULT_PALETTE)
+            .ok_or_else(|| invalid(format!("no `{DEFAULT_PALETTE}` palette")))?
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut palettes = vec![];
+        for (name, colors) in file.palettes {
+            let colors = colors
+                .into_iter()
+                .map(|(token, color)| {
+                    if !tokens.contains(&token) {
+                        return Err(invalid(format!(
+                            "palette `{name}` defines unknown token `{token}`"
+                        )));
+                    }
+                    let color = parse_hex_color(&color).ok_or_else(|| {
+                        invalid(format!("invalid color for `{name}.{token}`: {color}"))
+                    })?;
+                    Ok((token, color))
+                })
+                .collect::<Result<BTreeMap<_, _>, Error>>()?;
+
+            // the default palette comes first, so that the other palettes override it.
+            if name == DEFAULT_PALETTE {
+                palettes.insert(0, (name, colors));
+            }
+            else {
+                palettes.push((name, colors));
+            }
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            prefix: prefix.to_owned(),
+            tokens,
+            palettes,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// CSS custom properties for all palettes.
+    pub fn to_css(&self) -> String {
+        let mut css = String::new();
+        writeln!(css, "/*\n    Palettes: {}\n*/\n", self.path.display()).unwrap();
+
+        for (name, colors) in &self.palettes {
+            if name == DEFAULT_PALETTE {
+                writeln!(css, ":root {{").unwrap();
+            }
+            else {
+                writeln!(css, ":root[data-palette=\"{name}\"] {{").unwrap();
+            }
+            for (token, [r, g, b]) in colors {
+                writeln!(
+                    css,
+                    "    --{}-{token}: #{r:02x}{g:02x}{b:02x};",
+                    self.prefix
+                )
+                .unwrap();
+            }
+            writeln!(css, "}}\n").unwrap();
+        }
+
+        css
+    }
+
+    /// Rust code defining a `Token` enum, and a `Palette` struct with a
+    /// constant for every palette.
+    ///
+    /// The colors are `palette::Srgb<f32>`, so the `palette` crate must be a
+    /// dependency of the crate the code is included in.
+    pub fn to_rust(&self) -> String {
+        let (_, default_colors) = &self.palettes[0];
+
+        let mut code = String::new();
+
+        writeln!(code, "/// Semantic colors.").unwrap();
+        writeln!(code, "#[allow(dead_code)]").unwrap();
+        writeln!(
+            code,
+            "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]\npub enum Token {{"
+        )
+        .unwrap();
+        for token in &self.tokens {
+            writeln!(code, "    {},", upper_camel_case(token)).unwrap();
+        }
+        writeln!(code, "}}\n").unwrap();
+
+        writeln!(code, "/// Colors of all [`Token`]s.").unwrap();
+        writeln!(
+            code,
+            "#[derive(Clone, Copy, Debug, PartialEq)]\npub struct Palette {{"
+        )
+        .unwrap();
+        for token in &self.tokens {
+            writeln!(code, "    pub {}: palette::Srgb<f32>,", snake_case(token)).unwrap();
+        }
+        writeln!(code, "}}\n").unwrap();
+
+        writeln!(code, "#[allow(dead_code)]\nimpl Palette {{").unwrap();
+        for (name, colors) in &self.palettes {
+            writeln!(
+                code,
+                "    pub const {}: Self = Self {{",
+                constant_case(name)
+            )
+            .unwrap();
+            for token in &self.tokens {
+                let [r, g, b] = colors.get(token).unwrap_or(&default_colors[token]);
+                writeln!(
+                    code,
+                    "        {}: palette::Srgb::new({:?}, {:?}, {:?}),",
+                    snake_case(token),
+                    *r as f32 / 255.0,
+                    *g as f32 / 255.0,
+                    *b as f32 / 255.0,
+                )
+                .unwrap();
+            }
+            writeln!(code, "    }};").unwrap();
+        }
+
+        writeln!(
+            code,
+            "    pub const ALL: &'static [(&'static str, Self)] = &["
+        )
+        .unwrap();
+        for (name, _) in &self.palettes {
+            writeln!(code, "        ({name:?}, Self::{}),", constant_case(name)).unwrap();
+        }
+        writeln!(code, "    ];").unwrap();
+
+        writeln!(
+            code,
+            "    pub fn by_name(name: &str) -> Option<&'static Self> {{\n        Self::ALL.iter().find_map(|(n, palette)| (*n == name).then_some(palette))\n    }}"
+        )
+        .unwrap();
+
+        writeln!(
+            code,
+            "    pub fn get(&self, token: Token) -> palette::Srgb<f32> {{ match token {{"
+        )
+        .unwrap();
+        for token in &self.tokens {
+            writeln!(
+                code,
+                "        Token::{} => self.{},",
+                upper_camel_case(token),
+                snake_case(token)
+            )
+            .unwrap();
+        }
+        writeln!(code, "    }} }}").unwrap();
+
+        writeln!(code, "}}").unwrap();
+
+        code
+    }
+}
+
+/// Parses a color like `#1f6404`.
+fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn snake_case(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn constant_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -0,0 +1,471 @@
+//! Themes, i.e. CSS custom properties with values that are selected at
+//! runtime.
+//!
+//! A stylesheet declares its variables in a `:theme` block, and overrides
+//! them in named variants:
+//!
+//! ```scss
+//! :theme {
+//!     --accent: #1f6404;
+//!     --panel-background: rgba(0, 0, 0, 0.8);
+//! }
+//!
+//! :theme(light) {
+//!     --panel-background: rgba(255, 255, 255, 0.8);
+//! }
+//!
+//! .panel {
+//!     background-color: var(--panel-background);
+//! }
+//! ```
+//!
+//! The variables are mangled like class names, so that stylesheets can't
+//! interfere with each other. The `:theme` block is emitted for `:root`, and
+//! the variants for the `data-theme` attribute on the document element.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::Path,
+};
+
+use crate::Error;
+
+/// Name of the theme declared by the `:theme` block without a name.
+pub const DEFAULT_THEME: &str = "default";
+
+/// The themes of a stylesheet.
+#[derive(Clone, Debug, Default)]
+pub struct Themes {
+    /// Mapping from variable names (without `--`) to mangled variable names
+    /// (with `--`).
+    pub variables: BTreeMap<String, String>,
+
+    /// Themes with their declarations, with the default theme first.
+    themes: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Themes {
+    /// Removes the `:theme` blocks from the compiled CSS, and renames the
+    /// variables they declare in the rest of the CSS.
+    ///
+    /// Returns the themes, and the CSS with the theme variants in front.
+    pub fn extract(
+        css: &str,
+        crate_name: &str,
+        file_id: &str,
+        path: &Path,
+    ) -> Result<(Self, String), Error> {
+        let invalid = |message: String| {
+            Error::InvalidTheme {
+                message,
+                path: path.to_owned(),
+            }
+        };
+
+        let mut rest = String::with_capacity(css.len());
+        let mut themes = Vec::<(String, Vec<(String, String)>)>::new();
+
+        for block in top_level_blocks(css) {
+            let Some((prelude, body)) = block.rule
+            else {
+                rest.push_str(block.text);
+                continue;
+            };
+            let Some(name) = theme_name(prelude)
+            else {
+                rest.push_str(block.text);
+                continue;
+            };
+
+            let mut declarations = vec![];
+            for declaration in split_top_level(body, ';') {
+                let declaration = declaration.trim();
+                if declaration.is_empty() {
+                    continue;
+                }
+                let (variable, value) = declaration.split_once(':').ok_or_else(|| {
+                    invalid(format!(
+                        "invalid declaration in theme `{name}`: {declaration}"
+                    ))
+                })?;
+                let variable = variable.trim().strip_prefix("--").ok_or_else(|| {
+                    invalid(format!(
+                        "theme `{name}` declares `{}`, but themes can only declare variables",
+                        variable.trim()
+                    ))
+                })?;
+                declarations.push((variable.to_owned(), value.trim().to_owned()));
+            }
+
+            if let Some((_, existing)) = themes.iter_mut().find(|(n, _)| *n == name) {
+                existing.extend(declarations);
+            }
+            else if name == DEFAULT_THEME {
+                themes.insert(0, (name, declarations));
+            }
+            else {
+                themes.push((name, declarations));
+            }
+        }
+
+        if themes.is_empty() {
+            return Ok((Self::default(), css.to_owned()));
+        }
+        if themes[0].0 != DEFAULT_THEME {
+            return Err(invalid(format!(
+                "theme `{}` has no `:theme` block that declares its variables",
+                themes[0].0
+            )));
+        }
+
+        let variables = themes[0]
+            .1
+            .iter()
+            .map(|(variable, _)| {
+                (
+                    variable.clone(),
+                    format!("--{crate_name}-{variable}-{file_id}"),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (name, declarations) in &themes[1..] {
+            if let Some((variable, _)) = declarations
+                .iter()
+                .find(|(variable, _)| !variables.contains_key(variable))
+            {
+                return Err(invalid(format!(
+                    "theme `{name}` declares `--{variable}`, which isn't declared in `:theme`"
+                )));
+            }
+        }
+
+        let themes = Self { variables, themes };
+        let css = themes.rename_variables(&format!("{}{rest}", themes.variants_css()));
+
+        Ok((themes, css))
+    }
+
+    /// Names of the themes, with the default theme first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.themes.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// CSS with the variables of every theme, before they're renamed.
+    fn variants_css(&self) -> String {
+        let mut css = String::new();
+        for (name, declarations) in &self.themes {
+            if name == DEFAULT_THEME {
+                writeln!(css, ":root {{").unwrap();
+            }
+            else {
+                writeln!(css, ":root[data-theme=\"{name}\"] {{").unwrap();
+            }
+            for (variable, value) in declarations {
+                writeln!(css, "  --{variable}: {value};").unwrap();
+            }
+            writeln!(css, "}}\n").unwrap();
+        }
+        css
+    }
+
+    /// Replaces the names of the theme's variables, e.g. in `var(--accent)`.
+    ///
+    /// Strings, comments and unquoted URLs are left as they are.
+    fn rename_variables(&self, css: &str) -> String {
+        let mut output = String::with_capacity(css.len());
+        let mut copied = 0;
+        let mut skip_until = 0;
+
+        for (position, c) in Scanner::new(css) {
+            if position < skip_until {
+                continue;
+            }
+
+            match c {
+                '-' if css[position..].starts_with("--") => {
+                    let start = position + 2;
+                    let name_length = css[start..]
+                        .find(|c| !is_ident_char(c))
+                        .unwrap_or(css.len() - start);
+                    skip_until = start + name_length;
+
+                    let preceded_by_ident = is_preceded_by_ident(css, position);
+                    match self.variables.get(&css[start..skip_until]) {
+                        Some(mangled) if !preceded_by_ident => {
+                            output.push_str(&css[copied..position]);
+                            output.push_str(mangled);
+                            copied = skip_until;
+                        }
+                        _ => {}
+                    }
+                }
+                'u' | 'U' if is_url(css, position) => {
+                    // quoted URLs are skipped like any other string, but unquoted ones can contain
+                    // anything except `)`.
+                    let start = position + 4;
+                    let arguments = &css[start..];
+                    if !arguments.trim_start().starts_with(['"', '\'']) {
+                        skip_until = arguments.find(')').map_or(css.len(), |end| start + end);
+                    }
+                }
+                _ => {}
+            }
+        }
+        output.push_str(&css[copied..]);
+
+        output
+    }
+}
+
+/// The name of the theme that a rule with this prelude declares, if it's a
+/// `:theme` block.
+fn theme_name(prelude: &str) -> Option<String> {
+    // comments in front of the block are part of its prelude.
+    let prelude = Scanner::new(prelude).map(|(_, c)| c).collect::<String>();
+    let selector = prelude.trim().strip_prefix(":theme")?;
+    if selector.is_empty() {
+        return Some(DEFAULT_THEME.to_owned());
+    }
+    let name = selector.strip_prefix('(')?.strip_suffix(')')?.trim();
+    (!name.is_empty() && name.chars().all(is_ident_char)).then(|| name.to_owned())
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii()
+}
+
+fn is_preceded_by_ident(css: &str, position: usize) -> bool {
+    css[..position]
+        .chars()
+        .next_back()
+        .is_some_and(is_ident_char)
+}
+
+/// Whether a `url(` function starts at `position`.
+fn is_url(css: &str, position: usize) -> bool {
+    css[position..]
+        .get(..4)
+        .is_some_and(|function| function.eq_ignore_ascii_case("url("))
+        && !is_preceded_by_ident(css, position)
+}
+
+/// A top-level statement of a stylesheet.
+struct Block<'a> {
+    /// The whole text of the statement, including whitespace before it.
+    text: &'a str,
+
+    /// Prelude and body, if it's a rule with a `{}` block.
+    rule: Option<(&'a str, &'a str)>,
+}
+
+/// Splits a stylesheet into its top-level statements.
+fn top_level_blocks(css: &str) -> Vec<Block<'_>> {
+    let mut blocks = vec![];
+    let mut start = 0;
+    let mut open = None;
+    let mut depth = 0usize;
+
+    for (position, c) in Scanner::new(css) {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    open = Some(position);
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    let rule = open
+                        .take()
+                        .map(|open| (&css[start..open], &css[open + 1..position]));
+                    blocks.push(Block {
+                        text: &css[start..=position],
+                        rule,
+                    });
+                    start = position + 1;
+                }
+            }
+            ';' if depth == 0 => {
+                blocks.push(Block {
+                    text: &css[start..=position],
+                    rule: None,
+                });
+                start = position + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if start < css.len() {
+        blocks.push(Block {
+            text: &css[start..],
+            rule: None,
+        });
+    }
+
+    blocks
+}
+
+/// Splits at `separator`, except inside parentheses, strings and comments.
+fn split_top_level(css: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut depth = 0usize;
+
+    for (position, c) in Scanner::new(css) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&css[start..position]);
+                start = position + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&css[start..]);
+
+    parts
+}
+
+/// Iterates over the characters of CSS, skipping strings and comments.
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(css: &'a str) -> Self {
+        Self {
+            chars: css.char_indices().peekable(),
+        }
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (position, c) = self.chars.next()?;
+            match c {
+                '"' | '\'' => {
+                    while let Some((_, next)) = self.chars.next() {
+                        match next {
+                            '\\' => {
+                                self.chars.next();
+                            }
+                            next if next == c => break,
+                            _ => {}
+                        }
+                    }
+                }
+                '/' if matches!(self.chars.peek(), Some((_, '*'))) => {
+                    self.chars.next();
+                    let mut previous = None;
+                    for (_, next) in self.chars.by_ref() {
+                        if previous == Some('*') && next == '/' {
+                            break;
+                        }
+                        previous = Some(next);
+                    }
+                }
+                _ => return Some((position, c)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Themes;
+
+    fn extract(css: &str) -> (Themes, String) {
+        Themes::extract(css, "test", "f00", Path::new("test.scss")).unwrap()
+    }
+
+    #[test]
+    fn it_renames_variables_in_nested_blocks() {
+        let (themes, css) = extract(
+            r#"
+            :theme { --accent: red; }
+            :theme(light) { --accent: blue; }
+            @media (min-width: 100px) {
+                .a { color: var(--accent); }
+            }
+            "#,
+        );
+
+        assert_eq!(themes.names().collect::<Vec<_>>(), ["default", "light"]);
+        assert!(css.contains(":root {\n  --test-accent-f00: red;"));
+        assert!(css.contains(":root[data-theme=\"light\"] {\n  --test-accent-f00: blue;"));
+        assert!(css.contains(".a { color: var(--test-accent-f00); }"));
+        assert!(!css.contains(":theme"));
+    }
+
+    #[test]
+    fn it_ignores_comments() {
+        let (themes, css) = extract(
+            r#"
+            /* :theme { --other: red; } } */
+            :theme { --accent: red; }
+            .a { color: var(--accent); /* var(--accent) */ }
+            "#,
+        );
+
+        assert_eq!(themes.variables.keys().collect::<Vec<_>>(), ["accent"]);
+        assert!(css.contains(".a { color: var(--test-accent-f00); /* var(--accent) */ }"));
+    }
+
+    #[test]
+    fn it_leaves_strings_and_urls_alone() {
+        let (_, css) = extract(
+            r#"
+            :theme { --accent: red; --label: "}"; }
+            .a::before { content: "var(--accent)"; }
+            .b { background: url(--accent.png), url( "--accent.png" ); }
+            "#,
+        );
+
+        assert!(css.contains("--test-label-f00: \"}\";"));
+        assert!(css.contains(r#"content: "var(--accent)";"#));
+        assert!(css.contains(r#"url(--accent.png), url( "--accent.png" );"#));
+    }
+
+    #[test]
+    fn it_keeps_other_at_rules() {
+        let (_, css) = extract(
+            r#"
+            @import url(--accent.css);
+            @charset "--accent";
+            :theme { --accent: red; }
+            @supports (color: var(--accent)) {
+                .a { color: var(--accent); }
+            }
+            "#,
+        );
+
+        assert!(css.contains("@import url(--accent.css);"));
+        assert!(css.contains(r#"@charset "--accent";"#));
+        assert!(css.contains("@supports (color: var(--test-accent-f00))"));
+        assert!(css.contains(".a { color: var(--test-accent-f00); }"));
+    }
+
+    #[test]
+    fn it_only_renames_whole_variable_names() {
+        let (_, css) = extract(
+            r#"
+            :theme { --accent: red; }
+            .a { color: var(--accent-dark); --x: var(--accent); }
+            .accent--accent { color: red; }
+            "#,
+        );
+
+        assert!(css.contains("var(--accent-dark)"));
+        assert!(css.contains("--x: var(--test-accent-f00);"));
+        assert!(css.contains(".accent--accent {"));
+    }
+}
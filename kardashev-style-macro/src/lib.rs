@@ -12,6 +12,7 @@ use proc_macro2::{
     TokenStream,
 };
 use quote::{
+    format_ident,
     quote,
     quote_spanned,
 };
@@ -103,8 +104,47 @@ fn style_impl(item: TokenStream, attributes: TokenStream) -> Result<TokenStream,
         }
     };
 
+    let themes_block = (!output.variables.is_empty()).then(|| {
+        let vis = &item.vis;
+        let vars_ident = format_ident!("{}Vars", ident);
+
+        let mut field_names = vec![];
+        let mut variable_names = vec![];
+        for (variable, mangled_variable) in &output.variables {
+            field_names.push(Ident::new(&variable.to_snek_case(), span));
+            variable_names.push(LitStr::new(mangled_variable, span));
+        }
+        let theme_names = output.themes.iter().map(|theme| LitStr::new(theme, span));
+
+        quote_spanned! {
+            span =>
+            /// Names of the CSS variables declared in the stylesheet's `:theme` block.
+            #[allow(dead_code)]
+            #[derive(Clone, Copy, Debug)]
+            #vis struct #vars_ident {
+                #(
+                    pub #field_names: &'static str,
+                )*
+            }
+
+            #[allow(dead_code)]
+            impl #impl_generics #ident #type_generics #where_clause {
+                const VARS: #vars_ident = #vars_ident {
+                    #(
+                        #field_names: #variable_names,
+                    )*
+                };
+
+                /// Themes that can be selected with the `data-theme` attribute on the
+                /// document element. The first one is the default.
+                const THEMES: &'static [&'static str] = &[#(#theme_names),*];
+            }
+        }
+    });
+
     Ok(quote! {
         #item
         #impl_block
+        #themes_block
     })
 }
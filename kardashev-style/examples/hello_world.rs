@@ -9,5 +9,7 @@ struct StyleOther;
 pub fn main() {
     println!("{}", StyleWorld::myclass);
     println!("{}", StyleWorld::my_other_class);
+    println!("{}", StyleWorld::VARS.highlight);
+    println!("{:?}", StyleWorld::THEMES);
     println!("{}", StyleOther::this_is_renamed);
 }
@@ -0,0 +1,213 @@
+//! Generates the `KeyCode` enum from `src/input/key_codes.tsv`, the
+//! `Palette` constants from the palette file configured in `Cargo.toml`, and
+//! the icon constants from the SVG files in `assets/icons/svg`.
+
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+};
+
+const KEY_CODES_PATH: &str = "src/input/key_codes.tsv";
+
+const ICONS_PATH: &str = "../assets/icons/svg";
+
+const CATEGORIES: &[(&str, &str)] = &[
+    ("letter", "Letter"),
+    ("digit", "Digit"),
+    ("function", "Function"),
+    ("modifier", "Modifier"),
+    ("navigation", "Navigation"),
+    ("editing", "Editing"),
+    ("punctuation", "Punctuation"),
+    ("numpad", "Numpad"),
+    ("media", "Media"),
+    ("browser", "Browser"),
+    ("system", "System"),
+    ("ime", "Ime"),
+];
+
+struct KeyCode<'a> {
+    code: &'a str,
+    name: &'a str,
+    category: &'static str,
+}
+
+fn main() {
+    generate_key_codes();
+    generate_palettes();
+    generate_icons();
+}
+
+fn generate_icons() {
+    println!("cargo:rerun-if-changed={ICONS_PATH}");
+
+    let mut names = std::fs::read_dir(ICONS_PATH)
+        .expect("failed to read icons")
+        .map(|entry| entry.expect("failed to read icons").path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "svg")
+        })
+        .map(|path| {
+            path.file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or_else(|| panic!("{}: invalid icon name", path.display()))
+                .to_owned()
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let mut output = String::new();
+    for name in &names {
+        let constant = name.to_uppercase().replace('-', "_");
+        writeln!(
+            output,
+            "pub const {constant}: IconId = IconId::new({name:?});"
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "#[allow(dead_code)]").unwrap();
+    writeln!(output, "pub const ALL: &[IconId] = &[").unwrap();
+    for name in &names {
+        writeln!(output, "    {},", name.to_uppercase().replace('-', "_")).unwrap();
+    }
+    writeln!(output, "];").unwrap();
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    std::fs::write(out_dir.join("icons.rs"), output).expect("failed to write icons");
+}
+
+fn generate_palettes() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let manifest_dir =
+        PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set"));
+    let palettes = kardashev_style_internal::read_palettes(&manifest_dir)
+        .unwrap_or_else(|error| panic!("{error}"))
+        .expect("no palette configured in Cargo.toml");
+    println!("cargo:rerun-if-changed={}", palettes.path().display());
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    std::fs::write(out_dir.join("palette.rs"), palettes.to_rust())
+        .expect("failed to write palettes");
+}
+
+fn generate_key_codes() {
+    println!("cargo:rerun-if-changed={KEY_CODES_PATH}");
+
+    let data = std::fs::read_to_string(KEY_CODES_PATH).expect("failed to read key codes");
+    let key_codes = data
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            let line_number = index + 1;
+            let columns = line.split('\t').collect::<Vec<_>>();
+            let [code, name, category] = columns[..]
+            else {
+                panic!("{KEY_CODES_PATH}:{line_number}: expected 3 columns separated by tabs");
+            };
+            let category = CATEGORIES
+                .iter()
+                .find_map(|(key, variant)| (*key == category).then_some(*variant))
+                .unwrap_or_else(|| {
+                    panic!("{KEY_CODES_PATH}:{line_number}: unknown category: {category}")
+                });
+            KeyCode {
+                code,
+                name,
+                category,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = String::new();
+
+    writeln!(
+        output,
+        "#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]"
+    )
+    .unwrap();
+    writeln!(output, "pub enum KeyCode {{").unwrap();
+    for key_code in &key_codes {
+        writeln!(output, "    {},", key_code.code).unwrap();
+    }
+    writeln!(output, "}}").unwrap();
+
+    writeln!(output, "impl KeyCode {{").unwrap();
+
+    writeln!(output, "    #[allow(dead_code)]").unwrap();
+    writeln!(output, "    pub const ALL: &'static [Self] = &[").unwrap();
+    for key_code in &key_codes {
+        writeln!(output, "        Self::{},", key_code.code).unwrap();
+    }
+    writeln!(output, "    ];").unwrap();
+
+    writeln!(
+        output,
+        "    fn from_websys(code: &str) -> Option<Self> {{ match code {{"
+    )
+    .unwrap();
+    for key_code in &key_codes {
+        writeln!(
+            output,
+            "        {:?} => Some(Self::{}),",
+            key_code.code, key_code.code
+        )
+        .unwrap();
+    }
+    writeln!(output, "        _ => None, }} }}").unwrap();
+
+    writeln!(
+        output,
+        "    fn to_websys(&self) -> &'static str {{ match self {{"
+    )
+    .unwrap();
+    for key_code in &key_codes {
+        writeln!(
+            output,
+            "        Self::{} => {:?},",
+            key_code.code, key_code.code
+        )
+        .unwrap();
+    }
+    writeln!(output, "    }} }}").unwrap();
+
+    writeln!(output, "    #[allow(dead_code)]").unwrap();
+    writeln!(
+        output,
+        "    pub fn display_name(&self) -> &'static str {{ match self {{"
+    )
+    .unwrap();
+    for key_code in &key_codes {
+        writeln!(
+            output,
+            "        Self::{} => {:?},",
+            key_code.code, key_code.name
+        )
+        .unwrap();
+    }
+    writeln!(output, "    }} }}").unwrap();
+
+    writeln!(output, "    #[allow(dead_code)]").unwrap();
+    writeln!(
+        output,
+        "    pub fn category(&self) -> KeyCategory {{ match self {{"
+    )
+    .unwrap();
+    for key_code in &key_codes {
+        writeln!(
+            output,
+            "        Self::{} => KeyCategory::{},",
+            key_code.code, key_code.category
+        )
+        .unwrap();
+    }
+    writeln!(output, "    }} }}").unwrap();
+
+    writeln!(output, "}}").unwrap();
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    std::fs::write(out_dir.join("key_codes.rs"), output).expect("failed to write key codes");
+}
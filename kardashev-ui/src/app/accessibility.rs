@@ -0,0 +1,258 @@
+//! Accessibility settings.
+//!
+//! The settings are stored in the [`Config`][super::config::Config] and
+//! provided as context to components, and as the [`Accessibility`] resource to
+//! ECS systems.
+//!
+//! The [`ReducedMotion`] and [`ColorVision`] can also be changed in the
+//! settings panel, which is stored in local storage and overrides the config.
+
+use leptos::{
+    create_effect,
+    expect_context,
+    provide_context,
+    Signal,
+    SignalGet,
+    WriteSignal,
+};
+use leptos_use::{
+    storage::use_local_storage,
+    use_media_query,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    app::config::Config,
+    colors::{
+        ActivePalette,
+        Palette,
+    },
+    ecs::server::WorldServer,
+};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+    #[serde(default)]
+    pub reduced_motion: ReducedMotion,
+
+    #[serde(default)]
+    pub color_vision: ColorVision,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReducedMotion {
+    /// Follow the browser's `prefers-reduced-motion` setting.
+    #[default]
+    System,
+    Always,
+    Never,
+}
+
+impl ReducedMotion {
+    pub const ALL: [Self; 3] = [Self::System, Self::Always, Self::Never];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::System => "reduced-motion-system",
+            Self::Always => "reduced-motion-always",
+            Self::Never => "reduced-motion-never",
+        }
+    }
+
+    /// Value of the option in the settings panel.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|reduced_motion| reduced_motion.key() == key)
+    }
+}
+
+/// Which [`Palette`] is used for the UI and the map overlays.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorVision {
+    #[default]
+    Normal,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl ColorVision {
+    pub const ALL: [Self; 4] = [
+        Self::Normal,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Normal => "color-vision-normal",
+            Self::Deuteranopia => "color-vision-deuteranopia",
+            Self::Protanopia => "color-vision-protanopia",
+            Self::Tritanopia => "color-vision-tritanopia",
+        }
+    }
+
+    /// Name of the palette in `src/palette.toml`, which is also the value of
+    /// the `data-palette` attribute.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Normal => "default",
+            Self::Deuteranopia => "deuteranopia",
+            Self::Protanopia => "protanopia",
+            Self::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|color_vision| color_vision.key() == key)
+    }
+
+    pub fn palette(&self) -> &'static Palette {
+        Palette::by_name(self.key()).unwrap_or(&Palette::DEFAULT)
+    }
+
+    /// Label of the color ramp the heatmap uses by default.
+    ///
+    /// The diverging ramps use colors that are hard to tell apart with color
+    /// vision deficiencies, so the perceptually uniform viridis is used
+    /// instead.
+    pub fn heatmap_ramp(&self) -> Option<&'static str> {
+        match self {
+            Self::Normal => None,
+            _ => Some("viridis"),
+        }
+    }
+}
+
+/// Resource containing the effective accessibility settings.
+///
+/// With [`reduced_motion`](Self::reduced_motion), [tweens](crate::tween), and
+/// with them the camera's zoom and fly-to animations, jump to their end, and
+/// variable stars don't pulse. New animations must check it too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Accessibility {
+    pub reduced_motion: bool,
+    pub color_vision: ColorVision,
+}
+
+/// Context holding the effective accessibility settings.
+#[derive(Clone, Copy, Debug)]
+pub struct AccessibilityContext {
+    pub reduced_motion: Signal<bool>,
+
+    /// The reduced motion setting, which
+    /// [`reduced_motion`](Self::reduced_motion) is derived from.
+    pub reduced_motion_setting: Signal<ReducedMotion>,
+    pub set_reduced_motion: WriteSignal<Option<ReducedMotion>>,
+
+    pub color_vision: Signal<ColorVision>,
+    pub set_color_vision: WriteSignal<Option<ColorVision>>,
+}
+
+/// Provides the [`AccessibilityContext`] and keeps the [`Accessibility`]
+/// resource up to date.
+///
+/// If reduced motion is enabled, the `data-reduced-motion` attribute is set on
+/// the document element, which disables CSS transitions and animations. The
+/// `data-palette` attribute selects the CSS custom properties of the palette
+/// for the [`ColorVision`], and the [`ActivePalette`] resource the colors of
+/// the map overlays.
+///
+/// This must be called after the config and the [`WorldServer`] were provided.
+pub fn provide_accessibility() {
+    let Config { accessibility, .. } = expect_context::<Config>();
+    let world = expect_context::<WorldServer>();
+
+    let (stored_reduced_motion, set_reduced_motion, _) =
+        use_local_storage::<Option<ReducedMotion>, codee::string::JsonSerdeCodec>("reduced-motion");
+    let reduced_motion_setting = Signal::derive(move || {
+        stored_reduced_motion
+            .get()
+            .unwrap_or(accessibility.reduced_motion)
+    });
+    let prefers_reduced_motion = use_media_query("(prefers-reduced-motion: reduce)");
+    let reduced_motion = Signal::derive(move || {
+        match reduced_motion_setting.get() {
+            ReducedMotion::System => prefers_reduced_motion.get(),
+            ReducedMotion::Always => true,
+            ReducedMotion::Never => false,
+        }
+    });
+
+    let (stored_color_vision, set_color_vision, _) =
+        use_local_storage::<Option<ColorVision>, codee::string::JsonSerdeCodec>("color-vision");
+    let color_vision = Signal::derive(move || {
+        stored_color_vision
+            .get()
+            .unwrap_or(accessibility.color_vision)
+    });
+
+    create_effect(move |_| {
+        let accessibility = Accessibility {
+            reduced_motion: reduced_motion.get(),
+            color_vision: color_vision.get(),
+        };
+        tracing::debug!(?accessibility, "accessibility settings changed");
+
+        if let Some(document_element) = gloo_utils::document().document_element() {
+            let _ = if accessibility.reduced_motion {
+                document_element.set_attribute("data-reduced-motion", "")
+            }
+            else {
+                document_element.remove_attribute("data-reduced-motion")
+            };
+            let _ = match accessibility.color_vision {
+                ColorVision::Normal => document_element.remove_attribute("data-palette"),
+                color_vision => document_element.set_attribute("data-palette", color_vision.key()),
+            };
+        }
+
+        let _ = world.run(move |system_context| {
+            system_context.resources.insert(accessibility);
+            system_context
+                .resources
+                .insert(ActivePalette(accessibility.color_vision.palette()));
+        });
+    });
+
+    provide_context(AccessibilityContext {
+        reduced_motion,
+        reduced_motion_setting,
+        set_reduced_motion,
+        color_vision,
+        set_color_vision,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ColorVision;
+    use crate::colors::Palette;
+
+    #[test]
+    fn every_color_vision_has_a_palette() {
+        for color_vision in ColorVision::ALL {
+            assert!(
+                Palette::by_name(color_vision.key()).is_some(),
+                "no palette for {color_vision:?} in src/palette.toml"
+            );
+        }
+    }
+}
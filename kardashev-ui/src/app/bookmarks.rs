@@ -0,0 +1,517 @@
+//! Bookmarks of camera views.
+//!
+//! Bookmarks are stored in the `bookmarks` web fs, so they're available
+//! offline. If a faction is configured, they are also synced with the server.
+//! When syncing, the newer version of a bookmark wins. Bookmarks deleted on
+//! another device are restored from the local copy, until they're deleted
+//! there too.
+//!
+//! Bookmarks can be shared by copying them to the clipboard as JSON, and
+//! pasting them on another device, or by dropping an exported JSON file onto
+//! the page.
+//!
+//! Adding and removing bookmarks can be undone, see [`UndoStack`].
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use futures::future::LocalBoxFuture;
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    frames::Galactic,
+    model::{
+        bookmark::{
+            Bookmark,
+            BookmarkId,
+        },
+        star::StarId,
+    },
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    provide_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+    StoredValue,
+};
+use nalgebra::{
+    Isometry3,
+    Point3,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        config::Config,
+        minimap::MinimapTarget,
+        undo::{
+            use_undo_stack,
+            Command,
+        },
+        world_view::fly_map_camera,
+    },
+    ecs::server::WorldServer,
+    graphics::transform::Transform,
+    i18n::use_i18n,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    selection::Selected,
+    t,
+    universe::star::StarEntity,
+    utils::{
+        clipboard,
+        futures::spawn_local,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+#[style(path = "src/app/bookmarks.scss")]
+struct Style;
+
+const WEB_FS_ROOT: &str = "bookmarks";
+const FILE_NAME: &str = "bookmarks.json";
+
+#[derive(Debug, thiserror::Error)]
+#[error("bookmark error")]
+enum Error {
+    WebFs(#[from] web_fs::Error),
+    Json(#[from] serde_json::Error),
+    Client(#[from] kardashev_client::Error),
+    Clipboard(#[from] clipboard::Error),
+}
+
+/// The user's bookmarks, provided as context.
+#[derive(Clone, Copy, Debug)]
+pub struct Bookmarks {
+    bookmarks: RwSignal<Vec<Bookmark>>,
+
+    /// Client to sync with, if a faction is configured.
+    sync: StoredValue<Option<ApiClient>>,
+}
+
+impl Bookmarks {
+    pub fn add(&self, bookmark: Bookmark) {
+        self.bookmarks.update(|bookmarks| {
+            bookmarks.push(bookmark);
+            sort(bookmarks);
+        });
+        self.save();
+    }
+
+    pub fn remove(&self, id: BookmarkId) {
+        self.bookmarks
+            .update(|bookmarks| bookmarks.retain(|bookmark| bookmark.id != id));
+        self.save();
+    }
+
+    /// Adds bookmarks from JSON, which is either a single bookmark or a list.
+    /// Bookmarks that already exist are replaced if the imported one is
+    /// newer.
+    ///
+    /// Returns the number of bookmarks in the JSON.
+    pub fn import(&self, json: &str) -> Result<usize, serde_json::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            One(Bookmark),
+            Many(Vec<Bookmark>),
+        }
+
+        let imported = match serde_json::from_str(json)? {
+            OneOrMany::One(bookmark) => vec![bookmark],
+            OneOrMany::Many(bookmarks) => bookmarks,
+        };
+        let count = imported.len();
+
+        self.bookmarks
+            .update(|bookmarks| *bookmarks = merge(std::mem::take(bookmarks), imported));
+        self.save();
+
+        Ok(count)
+    }
+
+    /// Writes the bookmarks to the web fs, and then pushes them to the server.
+    fn save(&self) {
+        let bookmarks = self.bookmarks.get_untracked();
+        let sync = self.sync.get_value();
+        let notifications = expect_context::<Notifications>();
+
+        spawn_local(async move {
+            let result = async {
+                write_local(&bookmarks).await?;
+                if let Some(api_client) = sync {
+                    api_client.put_bookmarks(bookmarks).await?;
+                }
+                Ok::<(), Error>(())
+            }
+            .await;
+
+            if let Err(error) = result {
+                notify_error(&notifications, "notification-bookmarks-sync-failed", error);
+            }
+        });
+    }
+}
+
+/// Loads the bookmarks from the web fs and syncs them with the server, and
+/// provides them as [`Bookmarks`].
+pub fn provide_bookmarks() {
+    let Config { faction, .. } = expect_context();
+    let api_client = faction.map(|_| expect_context::<ApiClient>());
+    let notifications = expect_context::<Notifications>();
+
+    let bookmarks = Bookmarks {
+        bookmarks: create_rw_signal(vec![]),
+        sync: store_value(api_client.clone()),
+    };
+    provide_context(bookmarks);
+
+    spawn_local(async move {
+        let result = async {
+            let mut local = read_local().await?;
+            sort(&mut local);
+            bookmarks.bookmarks.set(local.clone());
+
+            if let Some(api_client) = api_client {
+                let remote = api_client.get_bookmarks().await?;
+                let merged = merge(local, remote);
+                write_local(&merged).await?;
+                bookmarks.bookmarks.set(merged.clone());
+                api_client.put_bookmarks(merged).await?;
+            }
+
+            Ok::<(), Error>(())
+        }
+        .await;
+
+        if let Err(error) = result {
+            notify_error(&notifications, "notification-bookmarks-sync-failed", error);
+        }
+    });
+}
+
+/// Adds a bookmark, or removes it again when it's undone.
+///
+/// Bookmarks are stored locally first, so the command always succeeds. If
+/// syncing with the server fails, [`Bookmarks`] shows a notification, and the
+/// bookmarks are synced the next time they change.
+#[derive(Debug)]
+struct AddBookmark {
+    bookmarks: Bookmarks,
+    bookmark: Bookmark,
+}
+
+impl Command for AddBookmark {
+    fn error_title(&self) -> &'static str {
+        "notification-bookmarks-sync-failed"
+    }
+
+    fn apply(&self) {
+        self.bookmarks.add(self.bookmark.clone());
+    }
+
+    fn revert(&self) {
+        self.bookmarks.remove(self.bookmark.id);
+    }
+
+    fn send(&self, _undo: bool) -> LocalBoxFuture<'static, Result<(), kardashev_client::Error>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Removes a bookmark, which is the inverse of [`AddBookmark`].
+#[derive(Debug)]
+struct RemoveBookmark(AddBookmark);
+
+impl Command for RemoveBookmark {
+    fn error_title(&self) -> &'static str {
+        self.0.error_title()
+    }
+
+    fn apply(&self) {
+        self.0.revert();
+    }
+
+    fn revert(&self) {
+        self.0.apply();
+    }
+
+    fn send(&self, undo: bool) -> LocalBoxFuture<'static, Result<(), kardashev_client::Error>> {
+        self.0.send(!undo)
+    }
+}
+
+fn notify_error(notifications: &Notifications, title: &str, error: Error) {
+    tracing::error!(?error, title, "bookmark error");
+    let message = match &error {
+        Error::WebFs(error) => error.to_string(),
+        Error::Json(error) => error.to_string(),
+        Error::Client(error) => error.to_string(),
+        Error::Clipboard(error) => error.to_string(),
+    };
+    notifications.notify(Notification::error(title).with_message(message));
+}
+
+async fn open_file() -> Result<web_fs::File, Error> {
+    let web_fs = WebFs::with_named_root(WEB_FS_ROOT).await?;
+    Ok(web_fs
+        .open(FILE_NAME, OpenOptions::new().create(true))
+        .await?)
+}
+
+async fn read_local() -> Result<Vec<Bookmark>, Error> {
+    let data = open_file().await?.read().await?;
+    if data.is_empty() {
+        Ok(vec![])
+    }
+    else {
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+async fn write_local(bookmarks: &[Bookmark]) -> Result<(), Error> {
+    let data = serde_json::to_vec(bookmarks)?;
+    open_file().await?.write(data).await?;
+    Ok(())
+}
+
+/// Merges two sets of bookmarks, keeping the newer version of bookmarks that
+/// are in both.
+fn merge(local: Vec<Bookmark>, remote: Vec<Bookmark>) -> Vec<Bookmark> {
+    let mut merged = HashMap::new();
+    for bookmark in local.into_iter().chain(remote) {
+        match merged.get(&bookmark.id) {
+            Some(Bookmark { updated_at, .. }) if *updated_at >= bookmark.updated_at => {}
+            _ => {
+                merged.insert(bookmark.id, bookmark);
+            }
+        }
+    }
+    let mut merged = merged.into_values().collect::<Vec<_>>();
+    sort(&mut merged);
+    merged
+}
+
+fn sort(bookmarks: &mut Vec<Bookmark>) {
+    bookmarks.sort_by(|a, b| a.name.cmp(&b.name));
+}
+
+/// Lists the bookmarks, and saves the current view as a new one.
+#[component]
+pub fn BookmarkList() -> impl IntoView {
+    let bookmarks = expect_context::<Bookmarks>();
+    let undo_stack = use_undo_stack();
+    let world = store_value(expect_context::<WorldServer>());
+    let i18n = use_i18n();
+    let notifications = store_value(expect_context::<Notifications>());
+    let name = create_rw_signal(String::new());
+
+    let on_save = move |_| {
+        let name = name.get_untracked();
+        let name = if name.trim().is_empty() {
+            i18n.translate("bookmarks-default-name")
+        }
+        else {
+            name
+        };
+
+        let view = world.get_value().run(|system_context| {
+            let camera = system_context
+                .world
+                .query_mut::<&Transform>()
+                .with::<&MinimapTarget>()
+                .into_iter()
+                .next()
+                .map(|(_, transform)| transform.model_matrix.isometry)?;
+            let star = system_context
+                .world
+                .query_mut::<&StarEntity>()
+                .with::<&Selected>()
+                .into_iter()
+                .next()
+                .map(|(_, star)| star.id);
+            Some((camera, star))
+        });
+
+        spawn_local(async move {
+            if let Some((camera, star)) = view.await {
+                undo_stack.execute(AddBookmark {
+                    bookmarks,
+                    bookmark: new_bookmark(name, camera, star),
+                });
+            }
+        });
+    };
+
+    let on_paste = move |_| {
+        let notifications = notifications.get_value();
+        spawn_local(async move {
+            let result = async {
+                let json = clipboard::read_text().await?;
+                Ok::<_, Error>(bookmarks.import(&json)?)
+            }
+            .await;
+
+            match result {
+                Ok(_) => {
+                    notifications.notify(Notification::success("notification-bookmarks-pasted"));
+                }
+                Err(error) => {
+                    notify_error(&notifications, "notification-bookmarks-paste-failed", error);
+                }
+            }
+        });
+    };
+
+    let copy = move |bookmark: &Bookmark| {
+        let notifications = notifications.get_value();
+        let json = serde_json::to_string(bookmark).expect("failed to serialize bookmark");
+        spawn_local(async move {
+            match clipboard::write_text(&json).await {
+                Ok(()) => {
+                    notifications.notify(Notification::success("notification-bookmark-copied"));
+                }
+                Err(error) => {
+                    notify_error(
+                        &notifications,
+                        "notification-bookmark-copy-failed",
+                        error.into(),
+                    );
+                }
+            }
+        });
+    };
+
+    let remove = move |id: BookmarkId| {
+        let bookmark = bookmarks.bookmarks.with_untracked(|bookmarks| {
+            bookmarks.iter().find(|bookmark| bookmark.id == id).cloned()
+        });
+        if let Some(bookmark) = bookmark {
+            undo_stack.execute(RemoveBookmark(AddBookmark {
+                bookmarks,
+                bookmark,
+            }));
+        }
+    };
+
+    let jump_to = move |bookmark: &Bookmark| {
+        let to = Isometry3::from_parts(bookmark.position.into(), bookmark.rotation);
+        let _ = world
+            .get_value()
+            .run(move |system_context| fly_map_camera(system_context, |_| to));
+    };
+
+    view! {
+        <div class=Style::bookmarks>
+            <div class=Style::add>
+                <input
+                    type="text"
+                    class=Style::name_input
+                    placeholder=t!("bookmarks-name")
+                    aria-label=t!("bookmarks-name")
+                    prop:value=move || name.get()
+                    on:input=move |event| name.set(event_target_value(&event))
+                />
+                <button class=Style::button on:click=on_save title=t!("bookmarks-save")>
+                    <Icon icon=icons::BOOKMARK_PLUS />
+                </button>
+                <button class=Style::button on:click=on_paste title=t!("bookmarks-paste")>
+                    <Icon icon=icons::CLIPBOARD_PLUS />
+                </button>
+            </div>
+            <Show
+                when=move || bookmarks.bookmarks.with(|bookmarks| !bookmarks.is_empty())
+                fallback=|| view! { <p class=Style::empty>{t!("bookmarks-empty")}</p> }
+            >
+                <ul class=Style::list>
+                    <For
+                        each=move || bookmarks.bookmarks.get()
+                        key=|bookmark| (bookmark.id, bookmark.updated_at)
+                        children=move |bookmark| {
+                            let id = bookmark.id;
+                            let name = bookmark.name.clone();
+                            let coordinates = galactic_coordinates(&bookmark.position);
+                            let bookmark = store_value(bookmark);
+                            view! {
+                                <li class=Style::entry>
+                                    <button
+                                        class=Style::jump
+                                        on:click=move |_| bookmark.with_value(jump_to)
+                                    >
+                                        {name}
+                                    </button>
+                                    <span
+                                        class=Style::coordinates
+                                        title=t!("bookmarks-galactic-coordinates")
+                                    >
+                                        {coordinates}
+                                    </span>
+                                    <button
+                                        class=Style::button
+                                        on:click=move |_| bookmark.with_value(copy)
+                                        title=t!("bookmarks-copy")
+                                    >
+                                        <Icon icon=icons::CLIPBOARD />
+                                    </button>
+                                    <button
+                                        class=Style::button
+                                        on:click=move |_| remove(id)
+                                        title=t!("bookmarks-remove")
+                                    >
+                                        <Icon icon=icons::TRASH />
+                                    </button>
+                                </li>
+                            }
+                        }
+                    />
+                </ul>
+            </Show>
+        </div>
+    }
+}
+
+/// Formats the galactic longitude and latitude of a position, as seen from the
+/// sun.
+fn galactic_coordinates(position: &Point3<f32>) -> String {
+    let galactic = Galactic::from_game(position);
+    format!(
+        "l {:.1}° b {:.1}°",
+        galactic.longitude.to_degrees(),
+        galactic.latitude.to_degrees()
+    )
+}
+
+fn new_bookmark(name: String, camera: Isometry3<f32>, star: Option<StarId>) -> Bookmark {
+    Bookmark {
+        id: BookmarkId(Uuid::new_v4()),
+        name,
+        position: camera.translation.vector.into(),
+        rotation: camera.rotation,
+        star,
+        updated_at: Utc::now(),
+    }
+}
@@ -0,0 +1,80 @@
+//! Deferred mounting of components that aren't needed to interact with the
+//! map.
+//!
+//! The UI is a single wasm module, which can't be split into chunks that are
+//! fetched on demand. What does delay the map becoming interactive is mounting
+//! everything at once, e.g. panels that fetch data and build large tables. So
+//! these are mounted after the map rendered its first frames, and show a
+//! loading indicator until then.
+
+use std::{
+    cell::Cell,
+    time::Duration,
+};
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    request_animation_frame,
+    set_timeout,
+    view,
+    ChildrenFn,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+};
+
+use crate::t;
+
+#[style(path = "src/app/components/deferred.scss")]
+struct Style;
+
+/// Time after the first animation frame, after which deferred components are
+/// mounted.
+const DEFER_DELAY: Duration = Duration::from_millis(250);
+
+thread_local! {
+    /// Set once the deferred components have been mounted for the first time.
+    /// Components that are created later, e.g. panels that are opened by the
+    /// user, are mounted immediately.
+    static STARTUP_DONE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Mounts its children only after the page became interactive, and shows a
+/// loading indicator until then.
+#[component]
+pub fn Deferred(children: ChildrenFn) -> impl IntoView {
+    let ready = create_rw_signal(STARTUP_DONE.get());
+
+    if !ready.get_untracked() {
+        request_animation_frame(move || {
+            set_timeout(
+                move || {
+                    STARTUP_DONE.set(true);
+                    ready.set(true);
+                },
+                DEFER_DELAY,
+            );
+        });
+    }
+
+    view! {
+        <Show when=move || ready.get() fallback=|| view! { <Loading /> }>
+            {children()}
+        </Show>
+    }
+}
+
+/// Spinner with a label, for content that is still loading.
+#[component]
+pub fn Loading() -> impl IntoView {
+    view! {
+        <div class=Style::loading role="status">
+            <div class=Style::spinner aria-hidden="true"></div>
+            <span>{t!("panel-loading")}</span>
+        </div>
+    }
+}
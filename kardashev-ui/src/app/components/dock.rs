@@ -1,45 +1,137 @@
 use kardashev_style::style;
 use leptos::{
     component,
+    event_target_value,
     view,
     IntoView,
-    Oco,
-};
-use leptos_router::{
-    ToHref,
-    A,
+    Signal,
+    SignalGet,
+    SignalUpdate,
 };
 
-use super::icon::BootstrapIcon;
+use super::icon::{
+    icons,
+    Icon,
+};
+use crate::{
+    app::layout::{
+        use_layout,
+        PanelKind,
+    },
+    audio::music::use_music_settings,
+    i18n::use_i18n,
+    t,
+};
 
 #[style(path = "src/app/components/dock.scss")]
 struct Style;
 
+/// Button that toggles a panel.
 #[component]
-pub fn Item<H: ToHref + 'static>(
-    href: H,
-    #[prop(into)] icon: String,
-    #[prop(into)] label: Oco<'static, str>,
-) -> impl IntoView {
+pub fn Item(kind: PanelKind) -> impl IntoView {
+    let layout = use_layout();
+    let label = use_i18n().message(kind.title());
+
+    let class = move || {
+        if layout.is_open(kind) {
+            format!("{} {}", Style::link, Style::active)
+        }
+        else {
+            Style::link.to_owned()
+        }
+    };
+
     view! {
         <li class=Style::item>
-            <A href={href} active_class="active" class=Style::link>
-                <BootstrapIcon icon=icon alt=label />
-            </A>
+            <button
+                class=class
+                title=label
+                aria-pressed=move || layout.is_open(kind).to_string()
+                on:click=move |_| layout.toggle(kind)
+            >
+                <Icon icon=kind.icon() alt=label />
+            </button>
+        </li>
+    }
+}
+
+/// Button that mutes music, with a volume slider that is shown while it's
+/// hovered.
+#[component]
+pub fn MusicItem() -> impl IntoView {
+    let music = use_music_settings();
+    let muted = move || music.settings.get().muted;
+    let mute_label = t!("dock-music-mute");
+    let unmute_label = t!("dock-music-unmute");
+    let title = move || {
+        if muted() {
+            unmute_label.get()
+        }
+        else {
+            mute_label.get()
+        }
+    };
+    let icon = Signal::derive(move || {
+        if muted() {
+            icons::VOLUME_MUTE
+        }
+        else {
+            icons::VOLUME_UP
+        }
+    });
+
+    view! {
+        <li class=format!("{} {}", Style::item, Style::music)>
+            <button
+                class=Style::link
+                title=title
+                aria-pressed=move || muted().to_string()
+                on:click=move |_| music.set_settings.update(|settings| settings.muted = !settings.muted)
+            >
+                <Icon icon=icon />
+            </button>
+            <input
+                class=Style::volume
+                type="range"
+                min="0"
+                max="1"
+                step="0.05"
+                title=t!("dock-music-volume")
+                aria-label=t!("dock-music-volume")
+                prop:value=move || music.settings.get().volume.to_string()
+                on:input=move |event| {
+                    if let Ok(volume) = event_target_value(&event).parse::<f32>() {
+                        music.set_settings.update(|settings| {
+                            settings.volume = volume;
+                            settings.muted = false;
+                        });
+                    }
+                }
+            />
         </li>
     }
 }
 
 #[component]
 pub fn Dock() -> impl IntoView {
+    let layout = use_layout();
+
     view! {
-        <nav class=Style::dock>
+        <nav class=Style::dock aria-label=t!("dock-label")>
             <ul class=Style::group_top>
-                <Item href="/dashboard" icon="speedometer" label="Dashboard" />
-                <Item href="/map" icon="radar" label="Map" />
+                {PanelKind::ALL.into_iter().map(|kind| view! { <Item kind /> }).collect::<Vec<_>>()}
             </ul>
             <ul class=Style::group_bottom>
-                <Item href="/settings" icon="gear" label="Settings" />
+                <MusicItem />
+                <li class=Style::item>
+                    <button
+                        class=Style::link
+                        title=t!("dock-reset-layout")
+                        on:click=move |_| layout.reset()
+                    >
+                        <Icon icon=icons::LAYOUT_RESET alt=t!("dock-reset-layout") />
+                    </button>
+                </li>
             </ul>
         </nav>
     }
@@ -1,22 +1,170 @@
+use std::sync::Arc;
+
+use kardashev_protocol::{
+    asset_id,
+    assets::{
+        self as dist,
+        AssetId,
+    },
+};
 use kardashev_style::style;
 use leptos::{
     component,
+    create_rw_signal,
+    expect_context,
+    provide_context,
     view,
     IntoView,
     MaybeSignal,
-    Oco,
+    RwSignal,
     SignalGet,
+    SignalSet,
+    SignalWith,
+};
+use url::Url;
+
+use crate::{
+    app::config::Config,
+    assets::server::AssetServer,
+    ecs::server::WorldServer,
+    utils::futures::spawn_local,
 };
 
 #[style(path = "src/app/components/icon.scss")]
 struct Style;
 
+/// The UI's icon set, see `assets/icons/Asset.toml`.
+const ICON_SET: AssetId = asset_id!("c4a0e6f2-5d3b-4b17-9e82-7f1c3a9d0b65");
+
+/// Name of an icon in the UI's icon set. Use the constants in [`icons`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IconId(&'static str);
+
+impl IconId {
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+/// An [`IconId`] for each SVG file in `assets/icons/svg`, generated by the
+/// build script.
+pub mod icons {
+    use super::IconId;
+
+    include!(concat!(env!("OUT_DIR"), "/icons.rs"));
+}
+
+#[derive(Clone, Copy)]
+struct Icons {
+    icon_set: RwSignal<Option<Arc<LoadedIconSet>>>,
+}
+
+#[derive(Debug)]
+struct LoadedIconSet {
+    url: Url,
+    icon_set: dist::IconSet,
+}
+
+/// Looks up the icon set in the asset manifest. [`Icon`]s are empty until it
+/// was found.
+pub fn provide_icons() {
+    let icons = Icons {
+        icon_set: create_rw_signal(None),
+    };
+
+    let Config { urls, .. } = expect_context();
+    let mut asset_url = urls.unwrap_or_default().asset_url;
+    // the trailing slash is important for `Url::join` to work properly
+    if let Ok(mut segments) = asset_url.path_segments_mut() {
+        segments.pop_if_empty().push("");
+    }
+
+    let world = expect_context::<WorldServer>();
+    spawn_local(async move {
+        let asset_server = world
+            .run(|system_context| system_context.resources.get::<AssetServer>().cloned())
+            .await
+            .expect("AssetServer resource missing");
+
+        let icon_set = asset_server
+            .find_map::<dist::IconSet, _>(|icon_set| {
+                (icon_set.id == ICON_SET).then(|| icon_set.clone())
+            })
+            .await
+            .pop();
+        let Some(icon_set) = icon_set
+        else {
+            tracing::warn!(id = %ICON_SET, "icon set not found");
+            return;
+        };
+
+        match asset_url.join(&icon_set.image) {
+            Ok(url) => {
+                icons
+                    .icon_set
+                    .set(Some(Arc::new(LoadedIconSet { url, icon_set })))
+            }
+            Err(error) => tracing::error!(%error, "invalid icon set url"),
+        }
+    });
+
+    provide_context(icons);
+}
+
+/// An icon from the UI's icon set. It's `1em` large and has the color of the
+/// text.
+///
+/// `size` is the size in CSS pixels the icon is expected to be shown at, and
+/// is used to pick the sharpest rasterization of the icon.
 #[component]
-pub fn BootstrapIcon(
-    #[prop(into)] icon: MaybeSignal<String>,
-    #[prop(into, optional)] alt: Option<Oco<'static, str>>,
+pub fn Icon(
+    #[prop(into)] icon: MaybeSignal<IconId>,
+    #[prop(into, optional)] alt: Option<MaybeSignal<String>>,
+    #[prop(default = 16)] size: u32,
 ) -> impl IntoView {
-    view! { <i class={move || format!("bi bi-{}", icon.get())} aria-label=alt></i> }
+    let icons = expect_context::<Icons>();
+
+    // the icon is a mask over the text color, cut out of the sprite sheet.
+    let style = move || {
+        let icon = icon.get();
+        icons.icon_set.with(|loaded| {
+            let LoadedIconSet { url, icon_set } = &**loaded.as_ref()?;
+            let pixel_ratio = gloo_utils::window().device_pixel_ratio();
+            let best_size = icon_set.best_size((size as f64 * pixel_ratio).ceil() as u32)?;
+            let Some(sprite) = icon_set.sprite(icon.name(), best_size)
+            else {
+                tracing::warn!(icon = icon.name(), "icon not in icon set");
+                return None;
+            };
+
+            let em = |pixels: u32| pixels as f32 / best_size as f32;
+            Some(format!(
+                "mask-image: url({url}); mask-size: {}em {}em; mask-position: -{}em -{}em; background-color: currentColor;",
+                em(icon_set.size.w),
+                em(icon_set.size.h),
+                em(sprite.x),
+                em(sprite.y),
+            ))
+        })
+    };
+
+    // icons without alt text are decorative and hidden from assistive technology.
+    let role = alt.is_some().then_some("img");
+    let aria_hidden = alt.is_none().then_some("true");
+    let alt = alt.map(|alt| move || alt.get());
+    view! {
+        <span
+            class=Style::icon
+            style=style
+            role=role
+            aria-label=alt
+            aria-hidden=aria_hidden
+        ></span>
+    }
 }
 
 #[component]
@@ -1,3 +1,7 @@
+pub mod deferred;
 pub mod dock;
 pub mod icon;
+pub mod panel;
+pub mod sparkline;
+pub mod toast;
 pub mod window;
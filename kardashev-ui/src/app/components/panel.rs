@@ -0,0 +1,263 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    ev::{
+        KeyboardEvent,
+        PointerEvent,
+    },
+    event_target,
+    view,
+    For,
+    IntoView,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    SignalWithUntracked,
+    View,
+};
+
+use super::{
+    deferred::Deferred,
+    icon::{
+        icons,
+        Icon,
+    },
+};
+use crate::{
+    app::{
+        bookmarks::BookmarkList,
+        dashboard::Dashboard,
+        diplomacy::Diplomacy,
+        inspector::Inspector,
+        journal::Journal,
+        layout::{
+            use_layout,
+            DockPosition,
+            PanelKind,
+            PanelState,
+        },
+        leaderboard::Leaderboard,
+        orders::{
+            FleetList,
+            OrderQueue,
+        },
+        replay::ReplayPanel,
+        settings::Settings,
+        world_view::WorldView,
+    },
+    i18n::use_i18n,
+    t,
+};
+
+#[style(path = "src/app/components/panel.scss")]
+struct Style;
+
+/// Arranges the open panels according to the layout.
+#[component]
+pub fn Workspace() -> impl IntoView {
+    view! {
+        <div class=Style::workspace>
+            <div class=Style::middle>
+                <DockArea dock=DockPosition::Left />
+                <DockArea dock=DockPosition::Center />
+                <DockArea dock=DockPosition::Right />
+            </div>
+            <DockArea dock=DockPosition::Bottom />
+        </div>
+    }
+}
+
+#[component]
+fn DockArea(dock: DockPosition) -> impl IntoView {
+    let layout = use_layout();
+    let panels = move || {
+        layout.layout().with(|layout| {
+            layout
+                .docked(dock)
+                .map(|panel| panel.kind)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let class = match dock {
+        DockPosition::Left => Style::left,
+        DockPosition::Center => Style::center,
+        DockPosition::Right => Style::right,
+        DockPosition::Bottom => Style::bottom,
+    };
+
+    view! {
+        <div class=format!("{} {class}", Style::dock_area)>
+            <For
+                each=panels
+                key=|kind| *kind
+                children=move |kind| view! { <Panel kind dock /> }
+            />
+        </div>
+    }
+}
+
+#[component]
+fn Panel(kind: PanelKind, dock: DockPosition) -> impl IntoView {
+    let layout = use_layout();
+    let title = use_i18n().message(kind.title());
+
+    let size = move || {
+        layout.layout().with(|layout| {
+            layout
+                .get(kind)
+                .map_or(PanelState::MIN_SIZE, |panel| panel.size)
+        })
+    };
+    let style = move || {
+        match dock {
+            DockPosition::Left | DockPosition::Right => format!("width: {}px;", size()),
+            DockPosition::Bottom => format!("height: {}px;", size()),
+            DockPosition::Center => String::new(),
+        }
+    };
+
+    let dock_buttons = DockPosition::ALL
+        .into_iter()
+        .filter(|other| *other != dock)
+        .map(|other| {
+            let (icon, label) = match other {
+                DockPosition::Left => (icons::LAYOUT_SIDEBAR, t!("panel-dock-left")),
+                DockPosition::Center => (icons::FULLSCREEN, t!("panel-dock-center")),
+                DockPosition::Right => (icons::LAYOUT_SIDEBAR_REVERSE, t!("panel-dock-right")),
+                DockPosition::Bottom => (icons::WINDOW_DOCK, t!("panel-dock-bottom")),
+            };
+            view! {
+                <button class=Style::button title=label on:click=move |_| layout.dock(kind, other)>
+                    <Icon icon=icon alt=label />
+                </button>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    view! {
+        <section class=Style::panel style=style aria-label=title>
+            {(dock != DockPosition::Center).then(|| view! { <ResizeHandle kind dock /> })}
+            <header class=Style::header>
+                <h2 class=Style::title>{title}</h2>
+                {dock_buttons}
+                <button class=Style::button title=t!("panel-close") on:click=move |_| layout.close(kind)>
+                    <Icon icon=icons::X alt=t!("panel-close") />
+                </button>
+            </header>
+            <div class=Style::content>
+                {deferred_panel_content(kind)}
+            </div>
+        </section>
+    }
+}
+
+/// Handle to resize a docked panel.
+///
+/// The handle can be dragged with the mouse, or focused and moved with the
+/// arrow keys.
+#[component]
+fn ResizeHandle(kind: PanelKind, dock: DockPosition) -> impl IntoView {
+    const KEYBOARD_STEP: f64 = 16.0;
+
+    let layout = use_layout();
+    let dragging = create_rw_signal(false);
+
+    let size_untracked = move || {
+        layout.layout().with_untracked(|layout| {
+            layout
+                .get(kind)
+                .map_or(PanelState::MIN_SIZE, |panel| panel.size)
+        })
+    };
+
+    // direction in which the size grows, for movements along the x and y axis.
+    let (grow_x, grow_y) = match dock {
+        DockPosition::Left => (1.0, 0.0),
+        DockPosition::Right => (-1.0, 0.0),
+        DockPosition::Bottom => (0.0, -1.0),
+        DockPosition::Center => (0.0, 0.0),
+    };
+
+    let on_pointer_down = move |event: PointerEvent| {
+        let target = event_target::<web_sys::Element>(&event);
+        let _ = target.set_pointer_capture(event.pointer_id());
+        dragging.set(true);
+    };
+    let on_pointer_up = move |event: PointerEvent| {
+        let target = event_target::<web_sys::Element>(&event);
+        let _ = target.release_pointer_capture(event.pointer_id());
+        dragging.set(false);
+    };
+    let on_pointer_move = move |event: PointerEvent| {
+        if dragging.get_untracked() {
+            let delta =
+                grow_x * f64::from(event.movement_x()) + grow_y * f64::from(event.movement_y());
+            layout.resize(kind, size_untracked() + delta);
+        }
+    };
+    let on_key_down = move |event: KeyboardEvent| {
+        let delta = match event.key().as_str() {
+            "ArrowLeft" => -grow_x,
+            "ArrowRight" => grow_x,
+            "ArrowUp" => -grow_y,
+            "ArrowDown" => grow_y,
+            _ => return,
+        };
+        event.prevent_default();
+        layout.resize(kind, size_untracked() + delta * KEYBOARD_STEP);
+    };
+
+    let (class, orientation) = match dock {
+        DockPosition::Left => (Style::resize_right, "vertical"),
+        DockPosition::Right => (Style::resize_left, "vertical"),
+        _ => (Style::resize_top, "horizontal"),
+    };
+
+    view! {
+        <div
+            class=format!("{} {class}", Style::resize_handle)
+            role="separator"
+            tabindex="0"
+            aria-orientation=orientation
+            aria-label=t!("panel-resize")
+            on:pointerdown=on_pointer_down
+            on:pointerup=on_pointer_up
+            on:pointermove=on_pointer_move
+            on:keydown=on_key_down
+        ></div>
+    }
+}
+
+/// The map is mounted right away, and all other panels once it is interactive.
+fn deferred_panel_content(kind: PanelKind) -> View {
+    if kind == PanelKind::Map {
+        panel_content(kind)
+    }
+    else {
+        view! { <Deferred>{panel_content(kind)}</Deferred> }.into_view()
+    }
+}
+
+fn panel_content(kind: PanelKind) -> View {
+    match kind {
+        PanelKind::Dashboard => view! { <Dashboard /> }.into_view(),
+        PanelKind::Leaderboard => view! { <Leaderboard /> }.into_view(),
+        PanelKind::Diplomacy => view! { <Diplomacy /> }.into_view(),
+        PanelKind::Map => view! { <WorldView /> }.into_view(),
+        PanelKind::Inspector => view! { <Inspector /> }.into_view(),
+        PanelKind::FleetList => view! { <FleetList /> }.into_view(),
+        PanelKind::Orders => view! { <OrderQueue /> }.into_view(),
+        PanelKind::Journal => view! { <Journal /> }.into_view(),
+        PanelKind::Bookmarks => view! { <BookmarkList /> }.into_view(),
+        PanelKind::Replay => view! { <ReplayPanel /> }.into_view(),
+        PanelKind::Settings => view! { <Settings /> }.into_view(),
+        _ => {
+            view! {
+                <p class=Style::placeholder>{t!("panel-placeholder")}</p>
+            }
+            .into_view()
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    view,
+    IntoView,
+    MaybeSignal,
+    SignalWith,
+};
+
+#[style(path = "src/app/components/sparkline.scss")]
+struct Style;
+
+/// Small line chart without axes, e.g. to show a trend in a table.
+///
+/// The values are scaled to fill the chart vertically.
+#[component]
+pub fn Sparkline(
+    #[prop(into)] values: MaybeSignal<Vec<f64>>,
+    #[prop(default = 80.0)] width: f64,
+    #[prop(default = 20.0)] height: f64,
+) -> impl IntoView {
+    let points = move || values.with(|values| points(values, width, height));
+
+    view! {
+        <svg
+            class=Style::sparkline
+            width=width
+            height=height
+            viewBox=format!("0 0 {width} {height}")
+            aria-hidden="true"
+        >
+            <polyline class=Style::line points=points />
+        </svg>
+    }
+}
+
+fn points(values: &[f64], width: f64, height: f64) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = if max > min { max - min } else { 1.0 };
+    let step = if values.len() > 1 {
+        width / (values.len() - 1) as f64
+    }
+    else {
+        0.0
+    };
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let x = i as f64 * step;
+            let y = height - (value - min) / range * height;
+            format!("{x:.1},{y:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
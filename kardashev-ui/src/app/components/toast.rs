@@ -0,0 +1,120 @@
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    on_cleanup,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalUpdate,
+};
+
+use super::icon::{
+    icons,
+    Icon,
+};
+use crate::{
+    i18n::use_i18n,
+    notifications::{
+        Notification,
+        NotificationId,
+        NotificationsReceiver,
+        Severity,
+    },
+    t,
+    utils::{
+        futures::spawn_local,
+        time::sleep,
+    },
+};
+
+#[style(path = "src/app/components/toast.scss")]
+struct Style;
+
+/// Displays notifications received from `receiver` as toasts.
+#[component]
+pub fn Toasts(receiver: NotificationsReceiver) -> impl IntoView {
+    let toasts = create_rw_signal(Vec::<Notification>::new());
+
+    let join_handle = spawn_local(async move {
+        let mut receiver = receiver;
+        while let Some(notification) = receiver.next().await {
+            if let Some(timeout) = notification.timeout {
+                let id = notification.id;
+                spawn_local(async move {
+                    sleep(timeout).await;
+                    dismiss(toasts, id);
+                });
+            }
+
+            toasts.update(|toasts| toasts.push(notification));
+        }
+    });
+    on_cleanup(move || join_handle.abort());
+
+    view! {
+        <div class=Style::toasts role="region" aria-live="polite" aria-label=t!("toast-region-label")>
+            <For
+                each=move || toasts.get()
+                key=|notification| notification.id
+                children=move |notification| view! { <Toast notification toasts /> }
+            />
+        </div>
+    }
+}
+
+/// A single toast.
+///
+/// The title is translated, so it can be a message ID from the catalog.
+#[component]
+fn Toast(notification: Notification, toasts: RwSignal<Vec<Notification>>) -> impl IntoView {
+    let id = notification.id;
+    let i18n = use_i18n();
+    let title = notification.title;
+    let title = move || i18n.translate(&title);
+
+    let (class, icon, role) = match notification.severity {
+        Severity::Info => (Style::info, icons::INFO_CIRCLE, "status"),
+        Severity::Success => (Style::success, icons::CHECK_CIRCLE, "status"),
+        Severity::Warning => (Style::warning, icons::EXCLAMATION_TRIANGLE, "alert"),
+        Severity::Error => (Style::error, icons::X_OCTAGON, "alert"),
+    };
+
+    let actions = notification
+        .actions
+        .into_iter()
+        .map(|action| {
+            let on_click = move |_| {
+                (action.on_click)();
+                dismiss(toasts, id);
+            };
+            view! {
+                <button class=Style::action on:click=on_click>{action.label}</button>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    view! {
+        <div class=format!("{} {class}", Style::toast) role=role>
+            <Icon icon=icon />
+            <div class=Style::content>
+                <div class=Style::title>{title}</div>
+                {notification.message.map(|message| view! { <div class=Style::message>{message}</div> })}
+                <div class=Style::actions>{actions}</div>
+            </div>
+            <button
+                class=Style::dismiss
+                aria-label=t!("toast-dismiss")
+                on:click=move |_| dismiss(toasts, id)
+            >
+                <Icon icon=icons::X />
+            </button>
+        </div>
+    }
+}
+
+fn dismiss(toasts: RwSignal<Vec<Notification>>, id: NotificationId) {
+    toasts.update(|toasts| toasts.retain(|notification| notification.id != id));
+}
@@ -3,6 +3,7 @@ use leptos::{
     component,
     create_effect,
     create_node_ref,
+    ev,
     expect_context,
     html::{
         Canvas,
@@ -10,17 +11,22 @@ use leptos::{
     },
     provide_context,
     store_value,
+    use_context,
     view,
     IntoView,
+    MaybeSignal,
     Signal,
     SignalGet,
     SignalGetUntracked,
 };
 use leptos_use::{
     signal_debounced,
+    use_document,
     use_document_visibility,
     use_element_size_with_options,
     use_element_visibility,
+    use_event_listener,
+    use_window,
     UseElementSizeOptions,
 };
 use web_sys::{
@@ -29,15 +35,31 @@ use web_sys::{
 };
 
 use crate::{
-    app::config::Config,
+    app::{
+        config::Config,
+        startup::{
+            Phase,
+            Startup,
+        },
+    },
     error::Error,
     graphics::{
+        Error as GraphicsError,
         Graphics,
+        InitState,
         Surface,
         SurfaceSize,
         WindowHandle,
     },
-    input::mouse::MouseEvent,
+    input::{
+        mouse::{
+            MouseEvent,
+            PointerLock,
+        },
+        Timestamp,
+        Timestamped,
+    },
+    notifications::Notifications,
     utils::futures::spawn_local_and_handle_error,
 };
 
@@ -50,7 +72,14 @@ pub fn provide_graphics() {
         graphics: config, ..
     } = expect_context::<Config>();
 
-    let graphics = Graphics::new(config);
+    let graphics = Graphics::new(config, use_context::<Notifications>());
+    expect_context::<Startup>().watch(Phase::Graphics, graphics.init_state(), |state| {
+        match state {
+            InitState::Initializing => None,
+            InitState::Initialized => Some(Ok(())),
+            InitState::Failed(error) => Some(Err(error.clone())),
+        }
+    });
     provide_context(graphics);
 }
 
@@ -58,13 +87,25 @@ pub fn provide_graphics() {
 /// This creates a container (div) that can be sized using CSS. The canvas will
 /// atomatically be resized to fill this container.
 ///
+/// The canvas is focusable, and is focused when clicked. The `label` is used
+/// as its accessible name.
+///
+/// `on_load` receives the [`PointerLock`] for the canvas. The pointer lock is
+/// released when the page loses focus. When the graphics are
+/// [restarted](Graphics::restarts), the surface is recreated and `on_load` is
+/// called again, so the render target and render pass have to be replaced.
+///
 /// # TODO
 ///
 /// - Add event handler property
 #[component]
-pub fn Window<OnLoad, OnEvent>(on_load: OnLoad, on_event: OnEvent) -> impl IntoView
+pub fn Window<OnLoad, OnEvent>(
+    on_load: OnLoad,
+    on_event: OnEvent,
+    #[prop(into, optional)] label: Option<MaybeSignal<String>>,
+) -> impl IntoView
 where
-    OnLoad: FnOnce(&Surface) + 'static,
+    OnLoad: FnMut(&Surface, PointerLock) + 'static,
     OnEvent: FnMut(WindowEvent) + 'static,
 {
     let container_node_ref = create_node_ref::<Div>();
@@ -86,19 +127,46 @@ where
 
     let window_handle = WindowHandle::new();
     let surface_handle = store_value(None);
+    let pointer_lock = store_value(None::<PointerLock>);
+    let on_load = store_value(on_load);
 
-    canvas_node_ref.on_load(move |_canvas| {
+    canvas_node_ref.on_load(move |canvas| {
         tracing::debug!("window loaded");
 
+        let canvas_pointer_lock =
+            PointerLock::new(web_sys::HtmlCanvasElement::clone(&canvas).into());
+        pointer_lock.set_value(Some(canvas_pointer_lock.clone()));
+
         spawn_local_and_handle_error(async move {
             let graphics = expect_context::<Graphics>();
-            let surface = graphics
-                .create_surface(window_handle, container_size.get_untracked())
-                .await?;
+            let mut rx_restarts = graphics.restarts();
+
+            loop {
+                rx_restarts.borrow_and_update();
 
-            on_load(&surface);
+                match graphics
+                    .create_surface(window_handle, container_size.get_untracked())
+                    .await
+                {
+                    Ok(surface) => {
+                        let loaded = on_load.try_update_value(|on_load| {
+                            on_load(&surface, canvas_pointer_lock.clone())
+                        });
+                        if loaded.is_none() {
+                            // the window was removed in the meantime.
+                            break;
+                        }
+                        surface_handle.set_value(Some(surface));
+                    }
+                    Err(GraphicsError::Restarted) => {}
+                    Err(error) => return Err(error.into()),
+                }
 
-            surface_handle.set_value(Some(surface));
+                if rx_restarts.changed().await.is_err() {
+                    break;
+                }
+                tracing::info!("graphics restarted. recreating surface");
+            }
 
             Ok::<(), Error>(())
         });
@@ -124,12 +192,42 @@ where
         on_event(WindowEvent::Resize { surface_size })
     });
 
-    let on_mouse_input = move |event: Option<MouseEvent>| {
-        if let Some(event) = event {
-            on_event(WindowEvent::Mouse(event));
+    let on_mouse_input = move |event: &web_sys::Event, mouse_event: Option<MouseEvent>| {
+        if let Some(mouse_event) = mouse_event {
+            on_event(WindowEvent::Mouse(Timestamped::new(
+                mouse_event,
+                Timestamp::from_websys(event),
+            )));
         }
     };
 
+    let is_locked = move || {
+        pointer_lock
+            .with_value(|pointer_lock| pointer_lock.as_ref().map_or(false, PointerLock::is_locked))
+    };
+    let release_pointer_lock = move || {
+        pointer_lock.with_value(|pointer_lock| {
+            if let Some(pointer_lock) = pointer_lock {
+                pointer_lock.release();
+            }
+        })
+    };
+
+    // the event is sent to the document, for whichever element was locked.
+    let was_locked = store_value(false);
+    let _ = use_event_listener(
+        use_document(),
+        ev::Custom::<web_sys::Event>::new("pointerlockchange"),
+        move |event| {
+            let locked = is_locked();
+            if locked != was_locked.get_value() {
+                was_locked.set_value(locked);
+                on_mouse_input(&event, Some(MouseEvent::PointerLockChanged { locked }));
+            }
+        },
+    );
+    let _ = use_event_listener(use_window(), ev::blur, move |_| release_pointer_lock());
+
     let element_visibility = use_element_visibility(container_node_ref);
     let document_visibility = use_document_visibility();
     let is_visible = Signal::derive(move || {
@@ -148,15 +246,36 @@ where
         >
             <canvas
                 node_ref=canvas_node_ref
+                tabindex="0"
+                role="application"
+                aria-label=label.map(|label| move || label.get())
                 width=move || container_size.get().width
                 height=move || container_size.get().height
                 data-raw-handle=window_handle
-                on:mouseup=move |event| on_mouse_input(MouseEvent::from_websys_mouse_up(&event))
-                on:mousedown=move |event| on_mouse_input(MouseEvent::from_websys_mouse_down(&event))
-                on:mousemove=move |event| on_mouse_input(MouseEvent::from_websys_mouse_move(&event))
-                on:mouseenter=move |event| on_mouse_input(MouseEvent::from_websys_mouse_enter(&event))
-                on:mouseleave=move |event| on_mouse_input(MouseEvent::from_websys_mouse_leave(&event))
-                on:wheel=move |event| on_mouse_input(MouseEvent::from_websys_wheel(&event))
+                on:mouseup=move |event| on_mouse_input(&event, MouseEvent::from_websys_mouse_up(&event))
+                on:mousedown=move |event| {
+                    if let Some(canvas) = canvas_node_ref.get_untracked() {
+                        let _ = canvas.focus();
+                    }
+                    on_mouse_input(&event, MouseEvent::from_websys_mouse_down(&event))
+                }
+                on:mousemove=move |event| {
+                    if is_locked() {
+                        on_mouse_input(&event, MouseEvent::from_websys_locked_mouse_move(&event))
+                    }
+                    else {
+                        on_mouse_input(&event, MouseEvent::from_websys_mouse_move(&event))
+                    }
+                }
+                on:mouseenter=move |event| on_mouse_input(&event, MouseEvent::from_websys_mouse_enter(&event))
+                on:mouseleave=move |event| on_mouse_input(&event, MouseEvent::from_websys_mouse_leave(&event))
+                on:wheel=move |event| on_mouse_input(&event, MouseEvent::from_websys_wheel(&event))
+                on:keydown=move |event| {
+                    // most browsers release the lock themselves, without sending the event.
+                    if event.code() == "Escape" {
+                        release_pointer_lock();
+                    }
+                }
                 on:contextmenu=move |event| event.prevent_default()
             ></canvas>
         </div>
@@ -165,7 +284,7 @@ where
 
 #[derive(Clone, Debug)]
 pub enum WindowEvent {
-    Mouse(MouseEvent),
+    Mouse(Timestamped<MouseEvent>),
     Resize { surface_size: SurfaceSize },
     Visibility { visible: bool },
 }
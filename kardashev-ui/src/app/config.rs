@@ -1,4 +1,12 @@
+use kardashev_protocol::{
+    assets::ContentPack,
+    model::{
+        faction::FactionId,
+        world::WorldId,
+    },
+};
 use leptos::{
+    expect_context,
     provide_context,
     SignalGetUntracked,
 };
@@ -9,7 +17,16 @@ use serde::{
 };
 use url::Url;
 
-use crate::graphics;
+use crate::{
+    app::{
+        accessibility::AccessibilityConfig,
+        startup::{
+            Phase,
+            Startup,
+        },
+    },
+    graphics,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Urls {
@@ -36,11 +53,28 @@ pub struct Config {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub urls: Option<Urls>,
+
+    #[serde(default)]
+    pub accessibility: AccessibilityConfig,
+
+    /// Faction the player is playing as. Without one everything is visible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faction: Option<FactionId>,
+
+    /// The server's world to play in. Without one the server's default world
+    /// is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world: Option<WorldId>,
+
+    /// Content packs to load in addition to the ones the server offers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content_packs: Vec<ContentPack>,
 }
 
 pub fn provide_config() {
     let (config, _set_config, _delete_config) =
         use_local_storage::<Config, codee::string::JsonSerdeCodec>("graphics-config");
     let config = config.get_untracked();
-    provide_context(config)
+    provide_context(config);
+    expect_context::<Startup>().finish(Phase::Config);
 }
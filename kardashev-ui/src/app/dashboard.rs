@@ -0,0 +1,178 @@
+//! Overview of the player's empire.
+
+use std::time::Duration;
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::{
+    empire::EmpireSummary,
+    trade::Resource,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_local_resource,
+    expect_context,
+    view,
+    For,
+    IntoView,
+    Show,
+    Signal,
+    SignalGet,
+    SignalWith,
+};
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        config::Config,
+    },
+    t,
+    tween::use_tweened,
+    utils::easing::Easing,
+};
+
+#[style(path = "src/app/dashboard.scss")]
+struct Style;
+
+/// How long the stats take to count up or down to new values.
+const STAT_ANIMATION_DURATION: Duration = Duration::from_millis(600);
+
+/// Shows the [`EmpireSummary`][kardashev_protocol::model::empire::EmpireSummary]
+/// of the player's faction.
+#[component]
+pub fn Dashboard() -> impl IntoView {
+    let Config { faction, .. } = expect_context();
+    let api_client = expect_context::<ApiClient>();
+
+    let summary = create_local_resource(
+        || (),
+        move |_| {
+            let api_client = api_client.clone();
+            async move {
+                faction?;
+                api_client
+                    .get_empire_summary()
+                    .await
+                    .inspect_err(|error| tracing::error!(%error, "failed to load empire summary"))
+                    .ok()
+            }
+        },
+    );
+    let is_loaded = move || summary.with(|summary| summary.as_ref().map_or(false, Option::is_some));
+
+    // the stats count to their new values when the summary is refetched.
+    let stat = move |value: fn(&EmpireSummary) -> u32| {
+        let target = Signal::derive(move || {
+            summary.with(|summary| {
+                summary
+                    .as_ref()
+                    .and_then(Option::as_ref)
+                    .map_or(0.0, |summary| f64::from(value(summary)))
+            })
+        });
+        let tweened = use_tweened(target, STAT_ANIMATION_DURATION, Easing::EaseOut);
+        move || tweened.get().round()
+    };
+    let colonies = stat(|summary| summary.colonies.len() as u32);
+    let fleets = stat(|summary| summary.fleets.total);
+    let fleets_idle = stat(|summary| summary.fleets.idle);
+    let explored_stars = stat(|summary| summary.explored_stars);
+
+    let fallback = move || {
+        if faction.is_none() {
+            view! { <p class=Style::empty>{t!("dashboard-no-faction")}</p> }
+        }
+        else {
+            view! { <p class=Style::empty>{t!("dashboard-unavailable")}</p> }
+        }
+    };
+
+    view! {
+        <div class=Style::dashboard>
+            <button class=Style::button title=t!("dashboard-refresh") on:click=move |_| summary.refetch()>
+                <Icon icon=icons::ARROW_CLOCKWISE alt=t!("dashboard-refresh") />
+            </button>
+            <Show when=is_loaded fallback=fallback>
+                {move || {
+                    summary.get().flatten().map(|summary| {
+                        view! {
+                            <h3 class=Style::name>{summary.name}</h3>
+                            <dl class=Style::stats>
+                                <dt>{t!("dashboard-colonies")}</dt>
+                                <dd>{colonies}</dd>
+                                <dt>{t!("dashboard-fleets")}</dt>
+                                <dd>{fleets}</dd>
+                                <dt>{t!("dashboard-fleets-idle")}</dt>
+                                <dd>{fleets_idle}</dd>
+                                <dt>{t!("dashboard-explored-stars")}</dt>
+                                <dd>{explored_stars}</dd>
+                            </dl>
+                            <Show
+                                when={
+                                    let has_resources = !summary.resources.is_empty();
+                                    move || has_resources
+                                }
+                            >
+                                <h4>{t!("dashboard-resources")}</h4>
+                                <ul class=Style::list>
+                                    {summary.resources.iter().map(|resource| {
+                                        let production = format!("{:.1}", resource.production);
+                                        let stockpiled = format!("{:.0}", resource.stockpiled);
+                                        view! {
+                                            <li class=Style::item>
+                                                <span>{t_resource(resource.resource)}</span>
+                                                <span class=Style::amounts>
+                                                    {t!("dashboard-resource-amounts", production = production, stockpiled = stockpiled)}
+                                                </span>
+                                            </li>
+                                        }
+                                    }).collect::<Vec<_>>()}
+                                </ul>
+                            </Show>
+                            <h4>{t!("dashboard-colonies")}</h4>
+                            <Show
+                                when={
+                                    let is_empty = summary.colonies.is_empty();
+                                    move || !is_empty
+                                }
+                                fallback=|| view! { <p class=Style::empty>{t!("dashboard-no-colonies")}</p> }
+                            >
+                                <ul class=Style::list>
+                                    <For
+                                        each={
+                                            let colonies = summary.colonies.clone();
+                                            move || colonies.clone()
+                                        }
+                                        key=|colony| colony.id
+                                        children=|colony| {
+                                            view! {
+                                                <li class=Style::item>
+                                                    <span>{colony.name}</span>
+                                                    <span class=Style::founded>
+                                                        {colony.founded_at.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string()}
+                                                    </span>
+                                                </li>
+                                            }
+                                        }
+                                    />
+                                </ul>
+                            </Show>
+                        }
+                    })
+                }}
+            </Show>
+        </div>
+    }
+}
+
+/// Translated name of a resource.
+fn t_resource(resource: Resource) -> Signal<String> {
+    match resource {
+        Resource::Energy => t!("resource-energy"),
+        Resource::Metals => t!("resource-metals"),
+        Resource::Volatiles => t!("resource-volatiles"),
+    }
+}
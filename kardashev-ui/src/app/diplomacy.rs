@@ -0,0 +1,378 @@
+//! Relations of the player's faction to the other factions.
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    model::{
+        diplomacy::{
+            Stance,
+            MAX_TREATY_DAYS,
+        },
+        faction::FactionId,
+        leaderboard::LeaderboardEntry,
+    },
+    GetDiplomacyResponse,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+};
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        config::Config,
+    },
+    i18n::use_i18n,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    t,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/diplomacy.scss")]
+struct Style;
+
+/// The player's relation to another faction, as shown in a row of the table.
+#[derive(Clone, Debug)]
+struct FactionRelation {
+    faction: FactionId,
+    name: String,
+    stance: Stance,
+
+    /// Days until the treaty expires, rounded up.
+    remaining_days: Option<u32>,
+
+    /// The stance the other faction offered.
+    offered: Option<Stance>,
+
+    /// The stance the player proposed.
+    proposed: Option<Stance>,
+}
+
+/// Joins the factions of the leaderboard with the player's relations to them.
+fn faction_relations(
+    own: FactionId,
+    entries: Vec<LeaderboardEntry>,
+    diplomacy: &GetDiplomacyResponse,
+) -> Vec<FactionRelation> {
+    let mut relations = entries
+        .into_iter()
+        .filter(|entry| entry.faction != own)
+        .map(|entry| {
+            let relation = diplomacy
+                .relations
+                .iter()
+                .find(|relation| relation.faction == entry.faction);
+            FactionRelation {
+                faction: entry.faction,
+                name: entry.name,
+                stance: relation.map_or(Stance::default(), |relation| relation.stance),
+                remaining_days: relation
+                    .and_then(|relation| relation.treaty.as_ref())
+                    .and_then(|treaty| treaty.remaining_days)
+                    .map(|days| days.max(0.0).ceil() as u32),
+                offered: diplomacy
+                    .proposals
+                    .iter()
+                    .find(|proposal| proposal.from == entry.faction && proposal.to == own)
+                    .map(|proposal| proposal.stance),
+                proposed: diplomacy
+                    .proposals
+                    .iter()
+                    .find(|proposal| proposal.from == own && proposal.to == entry.faction)
+                    .map(|proposal| proposal.stance),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // factions the player deals with come first.
+    relations.sort_by_key(|relation| {
+        (
+            relation.offered.is_none(),
+            relation.stance == Stance::Peace,
+            relation.name.clone(),
+        )
+    });
+    relations
+}
+
+fn stance_title(stance: Stance) -> &'static str {
+    match stance {
+        Stance::War => "diplomacy-stance-war",
+        Stance::Peace => "diplomacy-stance-peace",
+        Stance::Alliance => "diplomacy-stance-alliance",
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    DeclareWar,
+    Propose(Stance),
+    Accept,
+    Decline,
+}
+
+#[component]
+pub fn Diplomacy() -> impl IntoView {
+    let Config { faction, .. } = expect_context();
+    let api_client = expect_context::<ApiClient>();
+    let i18n = use_i18n();
+    let notifications = store_value(expect_context::<Notifications>());
+
+    let relations = create_local_resource(|| (), {
+        let api_client = api_client.clone();
+        move |_| {
+            let api_client = api_client.clone();
+            async move {
+                let own = faction?;
+                let result = async {
+                    let leaderboard = api_client.get_leaderboard().await?;
+                    let diplomacy = api_client.get_diplomacy().await?;
+                    Ok::<_, kardashev_client::Error>(faction_relations(
+                        own,
+                        leaderboard.entries,
+                        &diplomacy,
+                    ))
+                }
+                .await;
+                result
+                    .inspect_err(|error| tracing::error!(%error, "failed to load diplomacy"))
+                    .ok()
+            }
+        }
+    });
+    let is_loaded =
+        move || relations.with(|relations| relations.as_ref().map_or(false, Option::is_some));
+
+    // duration of proposed treaties. treaties without one don't expire.
+    let days = create_rw_signal(None::<u32>);
+
+    let api_client = store_value(api_client);
+    let run = move |faction: FactionId, action: Action| {
+        let api_client = api_client.get_value();
+        let notifications = notifications.get_value();
+        let days = days.get_untracked();
+        spawn_local(async move {
+            let result = match action {
+                Action::DeclareWar => api_client.declare_war(faction).await.map(|_| ()),
+                Action::Propose(stance) => {
+                    api_client
+                        .propose_treaty(faction, stance, days)
+                        .await
+                        .map(|_| ())
+                }
+                Action::Accept => api_client.accept_treaty(faction).await.map(|_| ()),
+                Action::Decline => api_client.delete_proposal(faction).await,
+            };
+            match result {
+                Ok(()) => relations.refetch(),
+                Err(error) => {
+                    tracing::error!(%error, ?action, "diplomatic action failed");
+                    notifications.notify(
+                        Notification::error("notification-diplomacy-failed")
+                            .with_message(error.to_string()),
+                    );
+                }
+            }
+        });
+    };
+
+    let fallback = move || {
+        if faction.is_none() {
+            view! { <p class=Style::empty>{t!("diplomacy-no-faction")}</p> }
+        }
+        else {
+            view! { <p class=Style::empty>{t!("diplomacy-unavailable")}</p> }
+        }
+    };
+
+    view! {
+        <div class=Style::diplomacy>
+            <div class=Style::toolbar>
+                <input
+                    type="number"
+                    class=Style::days
+                    min=1
+                    max=MAX_TREATY_DAYS
+                    placeholder=t!("diplomacy-days-unlimited")
+                    title=t!("diplomacy-days")
+                    aria-label=t!("diplomacy-days")
+                    on:input=move |event| days.set(event_target_value(&event).parse().ok())
+                />
+                <button class=Style::button title=t!("diplomacy-refresh") on:click=move |_| relations.refetch()>
+                    <Icon icon=icons::ARROW_CLOCKWISE alt=t!("diplomacy-refresh") />
+                </button>
+            </div>
+            <Show when=is_loaded fallback=fallback>
+                <table class=Style::table>
+                    <thead>
+                        <tr>
+                            <th>{t!("diplomacy-faction")}</th>
+                            <th>{t!("diplomacy-stance")}</th>
+                            <th>{t!("diplomacy-treaty")}</th>
+                            <th></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        <For
+                            each=move || relations.get().flatten().unwrap_or_default()
+                            key=|relation| {
+                                (
+                                    relation.faction,
+                                    relation.stance,
+                                    relation.remaining_days,
+                                    relation.offered,
+                                    relation.proposed,
+                                )
+                            }
+                            children=move |relation| {
+                                let id = relation.faction;
+                                let stance = relation.stance;
+                                let is_idle = relation.offered.is_none() && relation.proposed.is_none();
+                                view! {
+                                    <tr>
+                                        <td>{relation.name}</td>
+                                        <td class=Style::stance data-stance=format!("{stance:?}").to_lowercase()>
+                                            {i18n.message(stance_title(stance))}
+                                        </td>
+                                        <td>
+                                            {relation.remaining_days.map(|days| t!("diplomacy-treaty-days", days = days))}
+                                        </td>
+                                        <td class=Style::actions>
+                                            {relation.offered.map(|offered| view! {
+                                                <span class=Style::offer>{t!("diplomacy-offered", stance = i18n.translate(stance_title(offered)))}</span>
+                                                <button class=Style::action on:click=move |_| run(id, Action::Accept)>
+                                                    {t!("diplomacy-accept")}
+                                                </button>
+                                                <button class=Style::action on:click=move |_| run(id, Action::Decline)>
+                                                    {t!("diplomacy-decline")}
+                                                </button>
+                                            })}
+                                            {relation.proposed.map(|proposed| view! {
+                                                <span class=Style::offer>{t!("diplomacy-proposed", stance = i18n.translate(stance_title(proposed)))}</span>
+                                                <button class=Style::action on:click=move |_| run(id, Action::Decline)>
+                                                    {t!("diplomacy-withdraw")}
+                                                </button>
+                                            })}
+                                            <Show when=move || is_idle>
+                                                <Show when=move || stance != Stance::War>
+                                                    <button class=Style::action on:click=move |_| run(id, Action::DeclareWar)>
+                                                        {t!("diplomacy-declare-war")}
+                                                    </button>
+                                                </Show>
+                                                <Show when=move || stance == Stance::War>
+                                                    <button class=Style::action on:click=move |_| run(id, Action::Propose(Stance::Peace))>
+                                                        {t!("diplomacy-propose-peace")}
+                                                    </button>
+                                                </Show>
+                                                <Show when=move || stance == Stance::Peace>
+                                                    <button class=Style::action on:click=move |_| run(id, Action::Propose(Stance::Alliance))>
+                                                        {t!("diplomacy-propose-alliance")}
+                                                    </button>
+                                                </Show>
+                                            </Show>
+                                        </td>
+                                    </tr>
+                                }
+                            }
+                        />
+                    </tbody>
+                </table>
+            </Show>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use kardashev_protocol::{
+        model::{
+            diplomacy::{
+                Relation,
+                Stance,
+                Treaty,
+                TreatyProposal,
+            },
+            faction::FactionId,
+            leaderboard::LeaderboardEntry,
+        },
+        uuid::Uuid,
+        GetDiplomacyResponse,
+    };
+
+    use super::faction_relations;
+
+    #[test]
+    fn it_joins_relations_with_factions() {
+        let faction = |i: u128| FactionId(Uuid::from_u128(i));
+        let entry = |i: u128, name: &str| {
+            LeaderboardEntry {
+                faction: faction(i),
+                name: name.to_owned(),
+                power: 0.0,
+                rating: 0.0,
+                history: vec![],
+            }
+        };
+        let diplomacy = GetDiplomacyResponse {
+            relations: vec![Relation {
+                faction: faction(2),
+                stance: Stance::Alliance,
+                since: Utc::now(),
+                treaty: Some(Treaty {
+                    signed_at: Utc::now(),
+                    remaining_days: Some(2.5),
+                    reverts_to: Stance::Peace,
+                }),
+            }],
+            proposals: vec![TreatyProposal {
+                from: faction(3),
+                to: faction(1),
+                stance: Stance::Alliance,
+                days: None,
+                proposed_at: Utc::now(),
+            }],
+        };
+
+        let relations = faction_relations(
+            faction(1),
+            vec![
+                entry(1, "Us"),
+                entry(2, "Ally"),
+                entry(3, "Suitor"),
+                entry(4, "Stranger"),
+            ],
+            &diplomacy,
+        );
+        let names = relations
+            .iter()
+            .map(|relation| relation.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Suitor", "Ally", "Stranger"]);
+
+        assert_eq!(relations[0].offered, Some(Stance::Alliance));
+        assert_eq!(relations[1].stance, Stance::Alliance);
+        assert_eq!(relations[1].remaining_days, Some(3));
+        assert_eq!(relations[2].stance, Stance::Peace);
+        assert_eq!(relations[2].remaining_days, None);
+    }
+}
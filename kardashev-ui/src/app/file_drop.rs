@@ -0,0 +1,215 @@
+//! Importing files by dropping them onto the page.
+//!
+//! What happens with a file depends on its extension:
+//!
+//! - `.json`: Bookmarks, as exported or copied from the bookmark list.
+//! - `.csv`: A star catalog in the format of the HYG database, e.g. for a
+//!   custom galaxy in sandbox mode. It's stored in the `catalogs` web fs.
+//! - `.kardashev`: A saved-game archive. It's stored in the `saves` web fs.
+//!
+//! Progress and errors are reported as notifications.
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    ev,
+    expect_context,
+    view,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalSet,
+};
+use leptos_use::{
+    use_document,
+    use_event_listener,
+};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    app::bookmarks::Bookmarks,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    t,
+    utils::{
+        futures::spawn_local,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+#[style(path = "src/app/file_drop.scss")]
+struct Style;
+
+#[derive(Debug, thiserror::Error)]
+#[error("import error")]
+enum Error {
+    WebFs(#[from] web_fs::Error),
+    Json(#[from] serde_json::Error),
+    #[error("failed to read file")]
+    Read,
+    #[error("unsupported file type: {file_name}")]
+    Unsupported {
+        file_name: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ImportKind {
+    Bookmarks,
+    StarCatalog,
+    SavedGame,
+}
+
+impl ImportKind {
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        let (_, extension) = file_name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Bookmarks),
+            "csv" => Some(Self::StarCatalog),
+            "kardashev" => Some(Self::SavedGame),
+            _ => None,
+        }
+    }
+}
+
+/// Overlay that is shown while files are dragged over the page, and imports
+/// them when they're dropped.
+#[component]
+pub fn FileDrop() -> impl IntoView {
+    let dragging = create_rw_signal(false);
+    let bookmarks = expect_context::<Bookmarks>();
+    let notifications = expect_context::<Notifications>();
+
+    // the drop event is only sent if the default of the drag events is prevented.
+    let _ = use_event_listener(use_document(), ev::dragover, move |event| {
+        if has_files(&event) {
+            event.prevent_default();
+            dragging.set(true);
+        }
+    });
+    let _ = use_event_listener(use_document(), ev::dragleave, move |event| {
+        // leaving the page, not just an element on it.
+        if event.related_target().is_none() {
+            dragging.set(false);
+        }
+    });
+    let _ = use_event_listener(use_document(), ev::drop, move |event| {
+        dragging.set(false);
+        let Some(file_list) = event.data_transfer().and_then(|data| data.files())
+        else {
+            return;
+        };
+        event.prevent_default();
+
+        let files = (0..file_list.length())
+            .filter_map(|i| file_list.item(i))
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return;
+        }
+
+        let notifications = notifications.clone();
+        spawn_local(async move {
+            import_files(files, bookmarks, notifications).await;
+        });
+    });
+
+    view! {
+        <Show when=move || dragging.get()>
+            <div class=Style::overlay aria-hidden="true">
+                <p class=Style::hint>{t!("file-drop-hint")}</p>
+            </div>
+        </Show>
+    }
+}
+
+fn has_files(event: &web_sys::DragEvent) -> bool {
+    event.data_transfer().map_or(false, |data| {
+        data.types()
+            .iter()
+            .any(|ty| ty.as_string().as_deref() == Some("Files"))
+    })
+}
+
+async fn import_files(
+    files: Vec<web_sys::File>,
+    bookmarks: Bookmarks,
+    notifications: Notifications,
+) {
+    let file_names = files.iter().map(|file| file.name()).collect::<Vec<_>>();
+    notifications.notify(
+        Notification::info("notification-import-started").with_message(file_names.join(", ")),
+    );
+
+    let mut imported = 0;
+    for file in files {
+        let file_name = file.name();
+        match import_file(file, bookmarks).await {
+            Ok(kind) => {
+                tracing::info!(%file_name, ?kind, "imported file");
+                imported += 1;
+            }
+            Err(error) => {
+                tracing::error!(%file_name, ?error, "failed to import file");
+                let message = match &error {
+                    Error::WebFs(error) => error.to_string(),
+                    Error::Json(error) => error.to_string(),
+                    error => error.to_string(),
+                };
+                notifications.notify(
+                    Notification::error("notification-import-failed")
+                        .with_message(format!("{file_name}: {message}")),
+                );
+            }
+        }
+    }
+
+    if imported > 0 {
+        notifications.notify(
+            Notification::success("notification-import-succeeded")
+                .with_message(format!("{imported}/{}", file_names.len())),
+        );
+    }
+}
+
+async fn import_file(file: web_sys::File, bookmarks: Bookmarks) -> Result<ImportKind, Error> {
+    let file_name = file.name();
+    let kind = ImportKind::from_file_name(&file_name).ok_or_else(|| {
+        Error::Unsupported {
+            file_name: file_name.clone(),
+        }
+    })?;
+
+    match kind {
+        ImportKind::Bookmarks => {
+            let json = JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|text| text.as_string())
+                .ok_or(Error::Read)?;
+            bookmarks.import(&json)?;
+        }
+        ImportKind::StarCatalog => store(file, "catalogs").await?,
+        ImportKind::SavedGame => store(file, "saves").await?,
+    }
+
+    Ok(kind)
+}
+
+/// Stores the file in the web fs `root`, replacing a file with the same name.
+async fn store(file: web_sys::File, root: &str) -> Result<(), Error> {
+    let web_fs = WebFs::with_named_root(root).await?;
+    let mut web_fs_file = web_fs
+        .open(file.name(), OpenOptions::new().create(true))
+        .await?;
+    let blob = gloo_file::Blob::from(web_sys::Blob::from(file));
+    web_fs_file.write_blob(blob).await?;
+    Ok(())
+}
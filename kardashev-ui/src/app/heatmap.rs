@@ -0,0 +1,625 @@
+//! Heatmap visualization of scalar star properties.
+//!
+//! While a [`HeatmapScalar`] is selected, stars are colored by that value
+//! using a [`ColorRamp`], and regions by the mean value of the stars inside
+//! them. Color ramps are loaded from the asset server. With a color vision
+//! deficiency palette, a ramp that works with it is selected by default.
+
+use std::collections::HashMap;
+
+use hecs::Entity;
+use kardashev_protocol::{
+    assets::{
+        self as dist,
+        AssetId,
+    },
+    model::star::StarVisibility,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    SignalWithUntracked,
+};
+use nalgebra::Point3;
+use palette::{
+    Srgb,
+    WithAlpha,
+};
+use tokio::sync::watch;
+
+use crate::{
+    app::accessibility::AccessibilityContext,
+    assets::{
+        load::{
+            LoadAssetContext,
+            LoadFromAsset,
+        },
+        server::AssetServer,
+        system::AssetTypeRegistry,
+        AssetNotFound,
+        MaybeHasAssetId,
+    },
+    colors::{
+        ActivePalette,
+        Palette,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+    },
+    graphics::{
+        gizmo::Polygon,
+        transform::Transform,
+    },
+    i18n::use_i18n,
+    t,
+    universe::{
+        region::{
+            RegionEntity,
+            FILL_ALPHA as REGION_DEFAULT_FILL_ALPHA,
+        },
+        star::{
+            render,
+            StarProperties,
+        },
+    },
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/heatmap.scss")]
+struct Style;
+
+/// Opacity of the fill of regions colored by the heatmap.
+const REGION_FILL_ALPHA: f32 = 0.2;
+
+/// Number of colors shown in the legend.
+const LEGEND_STEPS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeatmapScalar {
+    Temperature,
+    Luminosity,
+    Mass,
+    AbsoluteMagnitude,
+
+    /// How much the player's faction knows about the star.
+    Activity,
+}
+
+impl HeatmapScalar {
+    pub const ALL: [Self; 5] = [
+        Self::Temperature,
+        Self::Luminosity,
+        Self::Mass,
+        Self::AbsoluteMagnitude,
+        Self::Activity,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Temperature => "heatmap-temperature",
+            Self::Luminosity => "heatmap-luminosity",
+            Self::Mass => "heatmap-mass",
+            Self::AbsoluteMagnitude => "heatmap-absolute-magnitude",
+            Self::Activity => "heatmap-activity",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Temperature => "temperature",
+            Self::Luminosity => "luminosity",
+            Self::Mass => "mass",
+            Self::AbsoluteMagnitude => "absolute-magnitude",
+            Self::Activity => "activity",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scalar| scalar.key() == key)
+    }
+
+    fn value(&self, properties: &StarProperties, star: &render::Star) -> Option<f32> {
+        let value = match self {
+            Self::Temperature => properties.effective_temperature,
+            Self::Luminosity => properties.luminosity,
+            Self::Mass => properties.mass,
+            Self::AbsoluteMagnitude => properties.absolute_magnitude,
+            Self::Activity => {
+                match star.visibility {
+                    StarVisibility::Unexplored => 0.0,
+                    StarVisibility::Explored => 0.5,
+                    StarVisibility::Observed => 1.0,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+
+    /// Whether values span several orders of magnitude and are mapped to the
+    /// ramp logarithmically.
+    fn is_logarithmic(&self) -> bool {
+        matches!(self, Self::Temperature | Self::Luminosity | Self::Mass)
+    }
+
+    /// Range of values that is mapped to the ramp, if it doesn't depend on the
+    /// stars.
+    fn fixed_range(&self) -> Option<(f32, f32)> {
+        match self {
+            Self::Activity => Some((0.0, 1.0)),
+            _ => None,
+        }
+    }
+}
+
+/// A color ramp, loaded from a [`dist::ColorRamp`] asset.
+#[derive(Clone, Debug)]
+pub struct ColorRamp {
+    asset_id: AssetId,
+    label: Option<String>,
+    lut: Vec<[u8; 3]>,
+}
+
+impl ColorRamp {
+    pub fn sample(&self, value: f32) -> Srgb<f32> {
+        let Some(last) = self.lut.len().checked_sub(1)
+        else {
+            return Srgb::new(0.0, 0.0, 0.0);
+        };
+        let index = (value.clamp(0.0, 1.0) * last as f32).round() as usize;
+        Srgb::from(self.lut[index]).into_format()
+    }
+}
+
+impl MaybeHasAssetId for ColorRamp {
+    fn maybe_asset_id(&self) -> Option<AssetId> {
+        Some(self.asset_id)
+    }
+}
+
+impl LoadFromAsset for ColorRamp {
+    type Dist = dist::ColorRamp;
+    type Error = AssetNotFound;
+    type Args = ();
+
+    async fn load<'a, 'b: 'a>(
+        asset_id: AssetId,
+        _args: (),
+        context: &'a mut LoadAssetContext<'b>,
+    ) -> Result<Self, AssetNotFound> {
+        let dist = context
+            .dist_assets
+            .get::<dist::ColorRamp>(asset_id)
+            .ok_or_else(|| AssetNotFound { asset_id })?;
+
+        Ok(Self {
+            asset_id,
+            label: dist.label.clone(),
+            lut: dist.lut.clone(),
+        })
+    }
+}
+
+/// What the heatmap shows, for the legend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeatmapLegend {
+    pub scalar: HeatmapScalar,
+    pub min: f32,
+    pub max: f32,
+
+    /// CSS colors from `min` to `max`.
+    pub colors: Vec<String>,
+}
+
+/// Resource with the state of the heatmap.
+#[derive(Debug)]
+pub struct Heatmap {
+    scalar: Option<HeatmapScalar>,
+    ramp: Option<ColorRamp>,
+
+    /// What was applied to the stars last. Used to only recolor them if
+    /// something changed.
+    applied: Option<Applied>,
+
+    tx_legend: watch::Sender<Option<HeatmapLegend>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Applied {
+    scalar: Option<HeatmapScalar>,
+    ramp: Option<AssetId>,
+    palette: &'static Palette,
+    num_stars: usize,
+    num_regions: usize,
+}
+
+fn heatmap_system(system_context: &mut SystemContext) {
+    let palette = ActivePalette::get(&system_context.resources);
+    let region_fill = palette.region.with_alpha(REGION_DEFAULT_FILL_ALPHA);
+
+    let Some(heatmap) = system_context.resources.get_mut::<Heatmap>()
+    else {
+        return;
+    };
+
+    let applied = Applied {
+        scalar: heatmap.scalar,
+        ramp: heatmap.ramp.as_ref().map(|ramp| ramp.asset_id),
+        palette,
+        num_stars: system_context
+            .world
+            .query_mut::<&render::Star>()
+            .into_iter()
+            .len(),
+        num_regions: system_context
+            .world
+            .query_mut::<&RegionEntity>()
+            .into_iter()
+            .len(),
+    };
+    if heatmap.applied == Some(applied) {
+        return;
+    }
+    heatmap.applied = Some(applied);
+
+    let (Some(scalar), Some(ramp)) = (heatmap.scalar, heatmap.ramp.as_ref())
+    else {
+        for (_, star) in system_context.world.query_mut::<&mut render::Star>() {
+            star.heat = None;
+        }
+        for (_, polygon) in system_context
+            .world
+            .query_mut::<&mut Polygon>()
+            .with::<&RegionEntity>()
+        {
+            polygon.fill = region_fill;
+        }
+        heatmap.tx_legend.send_replace(None);
+        return;
+    };
+
+    let transform_value = |value: f32| {
+        if scalar.is_logarithmic() {
+            (value > 0.0).then(|| value.ln())
+        }
+        else {
+            Some(value)
+        }
+    };
+
+    let values = system_context
+        .world
+        .query_mut::<(&StarProperties, &render::Star, &Transform)>()
+        .into_iter()
+        .filter_map(|(entity, (properties, star, transform))| {
+            let value = transform_value(scalar.value(properties, star)?)?;
+            let position = Point3::from(transform.model_matrix.isometry.translation.vector);
+            Some((entity, value, position))
+        })
+        .collect::<Vec<_>>();
+
+    let (min, max) = scalar
+        .fixed_range()
+        .and_then(|(min, max)| Some((transform_value(min)?, transform_value(max)?)))
+        .unwrap_or_else(|| {
+            values.iter().fold(
+                (f32::INFINITY, f32::NEG_INFINITY),
+                |(min, max), (_, value, _)| (min.min(*value), max.max(*value)),
+            )
+        });
+    let normalize = |value: f32| {
+        if max > min {
+            (value - min) / (max - min)
+        }
+        else {
+            0.5
+        }
+    };
+
+    let normalized = values
+        .iter()
+        .map(|(entity, value, position)| (*entity, (normalize(*value), *position)))
+        .collect::<HashMap<Entity, _>>();
+
+    for (entity, star) in system_context.world.query_mut::<&mut render::Star>() {
+        star.heat = normalized
+            .get(&entity)
+            .map(|(value, _)| ramp.sample(*value).with_alpha(1.0));
+    }
+
+    for (_, polygon) in system_context
+        .world
+        .query_mut::<&mut Polygon>()
+        .with::<&RegionEntity>()
+    {
+        let (sum, count) = normalized
+            .values()
+            .filter(|(_, position)| contains_in_xz(&polygon.vertices, position))
+            .fold((0.0, 0), |(sum, count), (value, _)| {
+                (sum + value, count + 1)
+            });
+        polygon.fill = if count > 0 {
+            ramp.sample(sum / count as f32)
+                .with_alpha(REGION_FILL_ALPHA)
+        }
+        else {
+            region_fill
+        };
+    }
+
+    let untransform_value = |value: f32| {
+        if scalar.is_logarithmic() {
+            value.exp()
+        }
+        else {
+            value
+        }
+    };
+    let legend = (min <= max).then(|| {
+        HeatmapLegend {
+            scalar,
+            min: untransform_value(min),
+            max: untransform_value(max),
+            colors: (0..LEGEND_STEPS)
+                .map(|i| {
+                    let color: Srgb<u8> = ramp
+                        .sample(i as f32 / (LEGEND_STEPS - 1) as f32)
+                        .into_format();
+                    format!("#{color:x}")
+                })
+                .collect(),
+        }
+    });
+    heatmap.tx_legend.send_replace(legend);
+}
+
+/// Whether the convex polygon contains the point, when projected onto the
+/// XZ-plane.
+fn contains_in_xz(vertices: &[Point3<f32>], point: &Point3<f32>) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+
+    let mut sign = 0.0f32;
+    for (a, b) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+        let cross = (b.x - a.x) * (point.z - a.z) - (b.z - a.z) * (point.x - a.x);
+        if cross != 0.0 {
+            if sign != 0.0 && cross.signum() != sign {
+                return false;
+            }
+            sign = cross.signum();
+        }
+    }
+
+    true
+}
+
+/// Dropdowns for the heatmap scalar and color ramp, and the legend.
+#[component]
+pub fn HeatmapControl() -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let i18n = use_i18n();
+    let scalar = create_rw_signal(None::<HeatmapScalar>);
+    let ramp = create_rw_signal(None::<AssetId>);
+    let legend = create_rw_signal(None::<HeatmapLegend>);
+    let AccessibilityContext { color_vision, .. } = expect_context();
+
+    let ramps = create_local_resource(
+        || (),
+        move |_| {
+            async move {
+                let asset_server = world
+                    .get_value()
+                    .run(|system_context| system_context.resources.get::<AssetServer>().cloned())
+                    .await?;
+                let asset_ids = asset_server.find::<dist::ColorRamp>(|_| true).await;
+
+                let mut ramps = vec![];
+                for asset_id in asset_ids {
+                    match asset_server.load::<ColorRamp>(asset_id, ()).await {
+                        Ok(ramp) => ramps.push(ramp),
+                        Err(error) => {
+                            tracing::error!(%asset_id, %error, "failed to load color ramp")
+                        }
+                    }
+                }
+                ramps.sort_by(|a, b| a.label.cmp(&b.label));
+                Some(ramps)
+            }
+        },
+    );
+
+    let join_handle = spawn_local(async move {
+        let mut rx_legend = world
+            .get_value()
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<Heatmap>()
+                    .map(|heatmap| heatmap.tx_legend.subscribe())
+            })
+            .await?;
+
+        loop {
+            legend.set(rx_legend.borrow_and_update().clone());
+            if rx_legend.changed().await.is_err() {
+                break;
+            }
+        }
+
+        Some(())
+    });
+    on_cleanup(move || join_handle.abort());
+
+    let apply = move || {
+        let selected_scalar = scalar.get_untracked();
+        let selected_ramp = ramp.get_untracked().and_then(|asset_id| {
+            ramps.with_untracked(|ramps| {
+                ramps
+                    .as_ref()?
+                    .as_ref()?
+                    .iter()
+                    .find(|ramp| ramp.asset_id == asset_id)
+                    .cloned()
+            })
+        });
+        let _ = world.get_value().run(move |system_context| {
+            if let Some(heatmap) = system_context.resources.get_mut::<Heatmap>() {
+                heatmap.scalar = selected_scalar;
+                heatmap.ramp = selected_ramp;
+            }
+        });
+    };
+
+    // select the ramp for the color vision, or the first one, once they're
+    // loaded. the ramp for the color vision is also selected when it changes.
+    create_effect(move |_| {
+        let preferred = color_vision.get().heatmap_ramp();
+        let selected = ramps.with(|ramps| {
+            let ramps = ramps.as_ref()?.as_ref()?;
+            preferred
+                .and_then(|label| {
+                    ramps
+                        .iter()
+                        .find(|candidate| candidate.label.as_deref() == Some(label))
+                })
+                .or_else(|| ramp.get_untracked().is_none().then(|| ramps.first())?)
+                .map(|candidate| candidate.asset_id)
+        });
+        if selected.is_some() && selected != ramp.get_untracked() {
+            ramp.set(selected);
+            apply();
+        }
+    });
+
+    let on_scalar_change = move |event| {
+        scalar.set(HeatmapScalar::from_key(&event_target_value(&event)));
+        apply();
+    };
+    let on_ramp_change = move |event| {
+        let value = event_target_value(&event);
+        ramp.set(ramps.with_untracked(|ramps| {
+            ramps
+                .as_ref()?
+                .as_ref()?
+                .iter()
+                .find(|ramp| ramp.asset_id.to_string() == value)
+                .map(|ramp| ramp.asset_id)
+        }));
+        apply();
+    };
+
+    let scalar_options = move || {
+        HeatmapScalar::ALL
+            .into_iter()
+            .map(|option| {
+                view! { <option value=option.key()>{i18n.message(option.title())}</option> }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let ramp_options = move || {
+        ramps
+            .get()
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|option| {
+                let asset_id = option.asset_id;
+                let label = option.label.unwrap_or_else(|| asset_id.to_string());
+                view! { <option value=asset_id.to_string()>{label}</option> }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let legend_view = move || {
+        legend.with(|legend| {
+            legend.as_ref().map(|legend| {
+                let gradient = format!(
+                    "background: linear-gradient(to right, {});",
+                    legend.colors.join(", ")
+                );
+                view! {
+                    <div class=Style::legend>
+                        <div class=Style::gradient style=gradient></div>
+                        <div class=Style::range>
+                            <span>{format_value(legend.min)}</span>
+                            <span>{format_value(legend.max)}</span>
+                        </div>
+                    </div>
+                }
+            })
+        })
+    };
+
+    view! {
+        <div class=Style::heatmap>
+            <select aria-label=t!("heatmap-label") on:change=on_scalar_change>
+                <option value="">{t!("heatmap-off")}</option>
+                {scalar_options}
+            </select>
+            <Show when=move || scalar.get().is_some()>
+                <select
+                    aria-label=t!("heatmap-ramp")
+                    prop:value=move || ramp.get().map(|asset_id| asset_id.to_string()).unwrap_or_default()
+                    on:change=on_ramp_change
+                >
+                    {ramp_options}
+                </select>
+                {legend_view}
+            </Show>
+        </div>
+    }
+}
+
+fn format_value(value: f32) -> String {
+    if value != 0.0 && (value.abs() >= 1e4 || value.abs() < 1e-2) {
+        format!("{value:.2e}")
+    }
+    else {
+        format!("{value:.2}")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        if let Some(asset_type_registry) = context.resources.get_mut::<AssetTypeRegistry>() {
+            asset_type_registry.register::<ColorRamp>();
+        }
+        else {
+            tracing::warn!(
+                "resource AssetTypeRegistry is missing. can't register color ramp asset type"
+            );
+        }
+
+        let (tx_legend, _rx) = watch::channel(None);
+        context.resources.insert(Heatmap {
+            scalar: None,
+            ramp: None,
+            applied: None,
+            tx_legend,
+        });
+        context.schedule.add_system(heatmap_system);
+    }
+}
@@ -0,0 +1,210 @@
+//! Reloads the UI when it's rebuilt while the server watches its sources.
+//!
+//! If only the stylesheets changed, the stylesheet is swapped without reloading
+//! the page. If a rebuild fails, the error is shown in an overlay, since the
+//! page still shows the last successful build. The server only streams the
+//! events when watching, so otherwise the event source fails once and is done.
+
+use std::collections::BTreeMap;
+
+use kardashev_protocol::ui::{
+    BuildTarget,
+    Event,
+    EVENTS_PATH,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    expect_context,
+    provide_context,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Show,
+    SignalGet,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+};
+use url::Url;
+use wasm_bindgen::{
+    closure::Closure,
+    JsCast,
+};
+use web_sys::{
+    EventSource,
+    HtmlLinkElement,
+    MessageEvent,
+};
+
+use crate::t;
+
+#[style(path = "src/app/hot_reload.scss")]
+struct Style;
+
+/// The errors of the failed builds.
+#[derive(Clone, Copy, Debug)]
+struct BuildErrors {
+    errors: RwSignal<BTreeMap<BuildTarget, String>>,
+    dismissed: RwSignal<bool>,
+}
+
+pub fn provide_hot_reload() {
+    let build_errors = BuildErrors {
+        errors: create_rw_signal(BTreeMap::new()),
+        dismissed: create_rw_signal(false),
+    };
+    provide_context(build_errors);
+
+    let Some(base_url) = gloo_utils::document()
+        .base_uri()
+        .ok()
+        .flatten()
+        .and_then(|base_url| Url::parse(&base_url).ok())
+    else {
+        return;
+    };
+    let Ok(events_url) = base_url.join(EVENTS_PATH)
+    else {
+        return;
+    };
+
+    let Ok(event_source) = EventSource::new(events_url.as_str())
+    else {
+        tracing::warn!(%events_url, "failed to create event source for UI events");
+        return;
+    };
+
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |message: MessageEvent| {
+        let Some(data) = message.data().as_string()
+        else {
+            return;
+        };
+        match serde_json::from_str::<Event>(&data) {
+            Ok(Event::StyleChanged) => {
+                tracing::info!("stylesheet changed");
+                build_errors.errors.update(|errors| {
+                    errors.remove(&BuildTarget::Ui);
+                });
+                swap_stylesheets(&base_url);
+            }
+            Ok(Event::Rebuilt) => {
+                tracing::info!("UI rebuilt. reloading");
+                let _ = gloo_utils::window().location().reload();
+            }
+            Ok(Event::AssetsRebuilt) => {
+                build_errors.errors.update(|errors| {
+                    errors.remove(&BuildTarget::Assets);
+                });
+            }
+            Ok(Event::BuildFailed { target, message }) => {
+                tracing::error!(?target, %message, "build failed");
+                build_errors.errors.update(|errors| {
+                    errors.insert(target, message);
+                });
+                build_errors.dismissed.set(false);
+            }
+            Err(error) => tracing::warn!(?error, "invalid UI event"),
+        }
+    });
+    event_source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+    // the event source lives as long as the page.
+    on_message.forget();
+}
+
+/// Replaces our own stylesheets with a fresh copy.
+fn swap_stylesheets(base_url: &Url) {
+    let Ok(links) = gloo_utils::document().query_selector_all(r#"link[rel="stylesheet"]"#)
+    else {
+        return;
+    };
+
+    for i in 0..links.length() {
+        let Some(link) = links
+            .item(i)
+            .and_then(|node| node.dyn_into::<HtmlLinkElement>().ok())
+        else {
+            continue;
+        };
+
+        // only our own stylesheets are rebuilt. others, e.g. ones injected by browser
+        // extensions, are left alone.
+        let Ok(mut href) = Url::parse(&link.href())
+        else {
+            continue;
+        };
+        if href.origin() != base_url.origin() {
+            continue;
+        }
+
+        // bust the cache.
+        href.set_query(Some(&format!(
+            "v={}",
+            chrono::Utc::now().timestamp_millis()
+        )));
+
+        let Some(new_link) = link
+            .clone_node()
+            .ok()
+            .and_then(|node| node.dyn_into::<HtmlLinkElement>().ok())
+        else {
+            continue;
+        };
+        // the hash in `index.html` is of the old stylesheet.
+        let _ = new_link.remove_attribute("integrity");
+        new_link.set_href(href.as_str());
+
+        if link.after_with_node_1(&new_link).is_err() {
+            continue;
+        }
+
+        // the old stylesheet is removed once the new one is loaded, so that the page
+        // isn't shown without styles in between.
+        let on_load = Closure::once_into_js(move || link.remove());
+        new_link.set_onload(Some(on_load.unchecked_ref()));
+    }
+}
+
+/// Full-screen overlay with the errors of failed builds.
+#[component]
+pub fn BuildErrorOverlay() -> impl IntoView {
+    let BuildErrors { errors, dismissed } = expect_context();
+    let show = move || !dismissed.get() && errors.with(|errors| !errors.is_empty());
+    let entries = move || {
+        errors.with(|errors| {
+            errors
+                .iter()
+                .map(|(target, message)| (*target, message.clone()))
+                .collect::<Vec<_>>()
+        })
+    };
+
+    view! {
+        <Show when=show>
+            <div class=Style::overlay role="alert">
+                <For
+                    each=entries
+                    key=|(target, message)| (*target, message.clone())
+                    children=move |(target, message)| {
+                        let title = match target {
+                            BuildTarget::Ui => t!("build-error-ui"),
+                            BuildTarget::Assets => t!("build-error-assets"),
+                        };
+                        view! {
+                            <section class=Style::error>
+                                <h2 class=Style::title>{title}</h2>
+                                <pre class=Style::message>{message}</pre>
+                            </section>
+                        }
+                    }
+                />
+                <button class=Style::dismiss on:click=move |_| dismissed.set(true)>
+                    {t!("build-error-dismiss")}
+                </button>
+            </div>
+        </Show>
+    }
+}
@@ -0,0 +1,235 @@
+//! Inspector panel, which lists the selected entities.
+//!
+//! Stars can be renamed here, which can be undone, see
+//! [`UndoStack`](super::undo::UndoStack).
+
+use futures::future::LocalBoxFuture;
+use hecs::Entity;
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::star::StarId;
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+};
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        undo::{
+            use_undo_stack,
+            Command,
+        },
+    },
+    ecs::{
+        server::WorldServer,
+        Label,
+    },
+    selection::use_selection,
+    t,
+    universe::{
+        entity_map::EntityMap,
+        star::StarEntity,
+    },
+};
+
+#[style(path = "src/app/inspector.scss")]
+struct Style;
+
+/// Lists the selected entities.
+#[component]
+pub fn Inspector() -> impl IntoView {
+    let selection = use_selection();
+    let is_empty = move || selection.with(|selection| selection.selected.is_empty());
+
+    view! {
+        <div class=Style::inspector>
+            <Show
+                when=move || !is_empty()
+                fallback=|| view! { <p class=Style::empty>{t!("inspector-nothing-selected")}</p> }
+            >
+                <ul class=Style::list>
+                    <For
+                        each=move || selection.with(|selection| selection.selected.clone())
+                        key=|selected| (selected.entity, selected.label.clone())
+                        children=move |selected| {
+                            view! { <InspectorItem entity=selected.entity label=selected.label /> }
+                        }
+                    />
+                </ul>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn InspectorItem(entity: Entity, label: Option<String>) -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let api_client = store_value(expect_context::<ApiClient>());
+    let undo_stack = use_undo_stack();
+
+    // only stars can be renamed.
+    let star_id = create_local_resource(
+        || (),
+        move |_| {
+            world.get_value().run(move |system_context| {
+                system_context
+                    .world
+                    .get::<&StarEntity>(entity)
+                    .ok()
+                    .map(|star| star.id)
+            })
+        },
+    );
+    let star_id = move || star_id.get().flatten();
+
+    let renaming = create_rw_signal(false);
+    let name = create_rw_signal(label.clone().unwrap_or_default());
+    let previous = store_value(label.clone());
+
+    let rename = move || {
+        renaming.set(false);
+        let Some(star_id) = star_id()
+        else {
+            return;
+        };
+        let new_name = name.get_untracked().trim().to_owned();
+        let new_name = (!new_name.is_empty()).then_some(new_name);
+        if new_name == previous.get_value() {
+            return;
+        }
+
+        undo_stack.execute(RenameStar {
+            world: world.get_value(),
+            api_client: api_client.get_value(),
+            star_id,
+            name: new_name,
+            previous: previous.get_value(),
+        });
+    };
+
+    let label_view = match label {
+        Some(label) => label.into_view(),
+        None => t!("inspector-unnamed").into_view(),
+    };
+
+    view! {
+        <li class=Style::item>
+            <Show
+                when=move || renaming.get()
+                fallback=move || view! { <span class=Style::label>{label_view.clone()}</span> }
+            >
+                <input
+                    type="text"
+                    class=Style::name_input
+                    aria-label=t!("inspector-rename-name")
+                    prop:value=move || name.get()
+                    on:input=move |event| name.set(event_target_value(&event))
+                    on:keydown=move |event| {
+                        match event.key().as_str() {
+                            "Enter" => rename(),
+                            "Escape" => renaming.set(false),
+                            _ => {}
+                        }
+                    }
+                />
+            </Show>
+            <span class=Style::entity>{format!("{entity:?}")}</span>
+            <Show when=move || star_id().is_some()>
+                <button
+                    class=Style::button
+                    title=t!("inspector-rename")
+                    on:click=move |_| {
+                        if renaming.get_untracked() {
+                            rename();
+                        }
+                        else {
+                            renaming.set(true);
+                        }
+                    }
+                >
+                    <Icon icon=icons::PENCIL alt=t!("inspector-rename") />
+                </button>
+            </Show>
+        </li>
+    }
+}
+
+/// Renames a star, or restores its previous name when it's undone.
+#[derive(Clone)]
+struct RenameStar {
+    world: WorldServer,
+    api_client: ApiClient,
+    star_id: StarId,
+    name: Option<String>,
+    previous: Option<String>,
+}
+
+impl RenameStar {
+    /// Shows the name on the map, and wherever the star's [`Label`] is used.
+    fn show(&self, name: Option<String>) {
+        let star_id = self.star_id;
+        let _ = self.world.run(move |system_context| {
+            let Some(entity) = system_context
+                .resources
+                .get::<EntityMap>()
+                .and_then(|entity_map| entity_map.resolve(star_id))
+            else {
+                return;
+            };
+            match name {
+                Some(name) => {
+                    let _ = system_context.world.insert_one(entity, Label::new(name));
+                }
+                None => {
+                    let _ = system_context.world.remove_one::<Label>(entity);
+                }
+            }
+        });
+    }
+}
+
+impl Command for RenameStar {
+    fn error_title(&self) -> &'static str {
+        "notification-rename-star-failed"
+    }
+
+    fn apply(&self) {
+        self.show(self.name.clone());
+    }
+
+    fn revert(&self) {
+        self.show(self.previous.clone());
+    }
+
+    fn send(&self, undo: bool) -> LocalBoxFuture<'static, Result<(), kardashev_client::Error>> {
+        let name = if undo {
+            self.previous.clone()
+        }
+        else {
+            self.name.clone()
+        };
+        let api_client = self.api_client.clone();
+        let star_id = self.star_id;
+
+        Box::pin(async move {
+            api_client.rename_star(star_id, name).await?;
+            Ok(())
+        })
+    }
+}
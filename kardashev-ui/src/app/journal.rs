@@ -0,0 +1,316 @@
+//! The faction's journal, and the session stream that delivers new entries.
+
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    time::Duration,
+};
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    model::{
+        journal::{
+            JournalEntry,
+            JournalEntryId,
+            JournalEvent,
+            JournalEventKind,
+        },
+        star::StarId,
+    },
+    GetJournalQuery,
+    SessionEvent,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    provide_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+};
+use tokio::sync::Notify;
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        startup::{
+            Phase,
+            Startup,
+        },
+    },
+    audio::music::Music,
+    ecs::{
+        server::WorldServer,
+        Label,
+    },
+    error::error_chain,
+    i18n::use_i18n,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    t,
+    universe::star::StarEntity,
+    utils::{
+        futures::spawn_local,
+        time::sleep,
+    },
+};
+
+#[style(path = "src/app/journal.scss")]
+struct Style;
+
+/// Time to wait before reconnecting to the session stream.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Journal entries received over the session stream, newest last.
+#[derive(Clone, Copy, Debug)]
+pub struct LiveJournal {
+    entries: RwSignal<Vec<JournalEntry>>,
+}
+
+/// Connects to the session stream, and provides a [`LiveJournal`] as context.
+///
+/// New journal entries are also shown as notifications. The first connection
+/// finishes the [world phase](Phase::World) of the startup.
+pub fn provide_session() {
+    let api_client = expect_context::<ApiClient>();
+    let notifications = expect_context::<Notifications>();
+    let world = expect_context::<WorldServer>();
+    let startup = expect_context::<Startup>();
+    let live_journal = LiveJournal {
+        entries: create_rw_signal(vec![]),
+    };
+    provide_context(live_journal);
+
+    // retrying skips the reconnect delay.
+    let reconnect = Rc::new(Notify::new());
+    startup.on_retry(Phase::World, {
+        let reconnect = reconnect.clone();
+        move || reconnect.notify_one()
+    });
+
+    spawn_local(async move {
+        loop {
+            match api_client.session().await {
+                Ok(mut session) => {
+                    tracing::debug!("connected to session stream");
+                    startup.finish(Phase::World);
+                    loop {
+                        match session.next().await {
+                            Ok(SessionEvent::Journal { entry }) => {
+                                notifications
+                                    .notify(Notification::info(kind_title(entry.event.kind())));
+                                if entry.event.kind() == JournalEventKind::Battle {
+                                    let _ = world.run(|system_context| {
+                                        if let Some(music) =
+                                            system_context.resources.get_mut::<Music>()
+                                        {
+                                            music.enter_combat();
+                                        }
+                                    });
+                                }
+                                live_journal.entries.update(|entries| entries.push(entry));
+                            }
+                            Err(error) => {
+                                tracing::warn!(%error, "session stream disconnected");
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(%error, "failed to connect to session stream");
+                    startup.fail(Phase::World, error_chain(&error));
+                }
+            }
+
+            tokio::select! {
+                () = sleep(RECONNECT_DELAY) => {}
+                () = reconnect.notified() => {}
+            }
+        }
+    });
+}
+
+/// Message ID for the title of an event kind.
+fn kind_title(kind: JournalEventKind) -> &'static str {
+    match kind {
+        JournalEventKind::Battle => "journal-battle",
+        JournalEventKind::Discovery => "journal-discovery",
+        JournalEventKind::ColonyFounded => "journal-colony-founded",
+    }
+}
+
+fn kind_value(kind: JournalEventKind) -> &'static str {
+    match kind {
+        JournalEventKind::Battle => "battle",
+        JournalEventKind::Discovery => "discovery",
+        JournalEventKind::ColonyFounded => "colony-founded",
+    }
+}
+
+/// Lists the journal entries, newest first.
+///
+/// Entries are loaded page-wise, and entries received over the session stream
+/// are prepended.
+#[component]
+pub fn Journal() -> impl IntoView {
+    let api_client = expect_context::<ApiClient>();
+    let notifications = expect_context::<Notifications>();
+    let live_journal = expect_context::<LiveJournal>();
+    let world = store_value(expect_context::<WorldServer>());
+    let i18n = use_i18n();
+
+    let kind = create_rw_signal(None::<JournalEventKind>);
+    let entries = create_rw_signal(Vec::<JournalEntry>::new());
+    let next = create_rw_signal(None::<JournalEntryId>);
+
+    let load_page = move |before: Option<JournalEntryId>| {
+        let api_client = api_client.clone();
+        let notifications = notifications.clone();
+        let query = GetJournalQuery {
+            before,
+            kind: kind.get_untracked(),
+            limit: None,
+        };
+
+        spawn_local(async move {
+            match api_client.get_journal(&query).await {
+                Ok(page) => {
+                    if before.is_none() {
+                        entries.set(page.entries);
+                    }
+                    else {
+                        entries.update(|entries| entries.extend(page.entries));
+                    }
+                    next.set(page.next);
+                }
+                Err(error) => {
+                    tracing::error!(%error, "failed to load journal");
+                    notifications.notify(
+                        Notification::error("notification-journal-load-failed")
+                            .with_message(error.to_string()),
+                    );
+                }
+            }
+        });
+    };
+
+    let load_page = store_value(load_page);
+
+    // reload when the filter changes.
+    create_effect(move |_| {
+        kind.with(|_| ());
+        load_page.with_value(|load_page| load_page(None));
+    });
+
+    // prepend entries from the session stream.
+    create_effect(move |_| {
+        live_journal.entries.with(|live| {
+            let kind = kind.get_untracked();
+            entries.update(|entries| {
+                let newest = entries.first().map(|entry| entry.id);
+                for entry in live {
+                    if newest.map_or(true, |newest| entry.id > newest)
+                        && kind.map_or(true, |kind| entry.event.kind() == kind)
+                    {
+                        entries.insert(0, entry.clone());
+                    }
+                }
+            });
+        });
+    });
+
+    let star_names = create_local_resource(
+        || (),
+        move |_| {
+            world.get_value().run(|system_context| {
+                system_context
+                    .world
+                    .query_mut::<(&StarEntity, &Label)>()
+                    .into_iter()
+                    .map(|(_, (star, label))| (star.id, label.label.to_string()))
+                    .collect::<HashMap<StarId, String>>()
+            })
+        },
+    );
+    let star_name = move |star: StarId| {
+        star_names
+            .with(|names| names.as_ref().and_then(|names| names.get(&star).cloned()))
+            .unwrap_or_else(|| i18n.translate("journal-unknown-star"))
+    };
+
+    let on_filter = move |event| {
+        let value = event_target_value(&event);
+        kind.set(
+            JournalEventKind::ALL
+                .into_iter()
+                .find(|kind| kind_value(*kind) == value),
+        );
+    };
+
+    view! {
+        <div class=Style::journal>
+            <select class=Style::filter aria-label=t!("journal-filter") on:change=on_filter>
+                <option value="">{t!("journal-filter-all")}</option>
+                {JournalEventKind::ALL
+                    .into_iter()
+                    .map(|kind| view! { <option value=kind_value(kind)>{i18n.message(kind_title(kind))}</option> })
+                    .collect::<Vec<_>>()}
+            </select>
+            <Show
+                when=move || entries.with(|entries| !entries.is_empty())
+                fallback=|| view! { <p class=Style::empty>{t!("journal-empty")}</p> }
+            >
+                <ol class=Style::list>
+                    <For
+                        each=move || entries.get()
+                        key=|entry| entry.id
+                        children=move |entry| {
+                            let detail = match &entry.event {
+                                JournalEvent::Battle { star, .. }
+                                | JournalEvent::Discovery { star }
+                                | JournalEvent::ColonyFounded { star, .. } => star_name(*star),
+                            };
+                            view! {
+                                <li class=Style::entry>
+                                    <span class=Style::kind>{i18n.message(kind_title(entry.event.kind()))}</span>
+                                    <span class=Style::detail>{detail}</span>
+                                    <time class=Style::time datetime=entry.time.to_rfc3339()>
+                                        {entry.time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()}
+                                    </time>
+                                </li>
+                            }
+                        }
+                    />
+                </ol>
+                <Show when=move || next.with(Option::is_some)>
+                    <button
+                        class=Style::more
+                        on:click=move |_| load_page.with_value(|load_page| load_page(next.get_untracked()))
+                    >
+                        <Icon icon=icons::CHEVRON_DOWN />
+                        {t!("journal-load-more")}
+                    </button>
+                </Show>
+            </Show>
+        </div>
+    }
+}
@@ -0,0 +1,56 @@
+//! Grid on the galactic plane.
+
+use nalgebra::Point3;
+
+use crate::{
+    app::layers::{
+        MapLayer,
+        OnLayer,
+    },
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::system::SystemContext,
+    graphics::gizmo::Line,
+};
+
+/// Distance between grid lines in parsecs.
+const SPACING: f32 = 10.0;
+
+/// Number of grid cells from the origin to the edge of the grid.
+const NUM_CELLS: i32 = 10;
+
+const TINT: Tint = Tint {
+    token: Token::Grid,
+    alpha: 0.15,
+    fill_alpha: None,
+};
+
+pub fn spawn_grid(system_context: &mut SystemContext) {
+    let extent = SPACING * (NUM_CELLS as f32);
+    let color = TINT.color(ActivePalette::get(&system_context.resources));
+
+    for i in -NUM_CELLS..=NUM_CELLS {
+        let offset = SPACING * (i as f32);
+        system_context.world.spawn((
+            OnLayer(MapLayer::Grid),
+            Line::new(
+                Point3::new(offset, 0.0, -extent),
+                Point3::new(offset, 0.0, extent),
+                color,
+            ),
+            TINT,
+        ));
+        system_context.world.spawn((
+            OnLayer(MapLayer::Grid),
+            Line::new(
+                Point3::new(-extent, 0.0, offset),
+                Point3::new(extent, 0.0, offset),
+                color,
+            ),
+            TINT,
+        ));
+    }
+}
@@ -0,0 +1,422 @@
+//! Overlay layers of the star map.
+//!
+//! Each [`MapLayer`] is a set of entities marked with [`OnLayer`], usually
+//! gizmos. Layers can be toggled and reordered: hidden layers get the
+//! [`Hidden`] component, and the position of a layer determines the
+//! [`GizmoOrder`] of its entities. Layers shown as HTML, like the region
+//! labels, are always above the gizmos. The layer settings are persisted in
+//! local storage.
+
+mod grid;
+mod ownership;
+mod routes;
+mod trade_routes;
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    expect_context,
+    provide_context,
+    view,
+    For,
+    IntoView,
+    Show,
+    Signal,
+    SignalGet,
+    SignalUpdate,
+    SignalWith,
+    WriteSignal,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    app::components::icon::{
+        icons,
+        Icon,
+        IconId,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+    },
+    graphics::gizmo::{
+        GizmoOrder,
+        Hidden,
+    },
+    i18n::use_i18n,
+    t,
+};
+
+#[style(path = "src/app/layers/layers.scss")]
+struct Style;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MapLayer {
+    Grid,
+    Regions,
+    Ownership,
+    Routes,
+    TradeRoutes,
+    Labels,
+}
+
+impl MapLayer {
+    /// All layers, from the bottom to the top.
+    pub const ALL: [Self; 6] = [
+        Self::Grid,
+        Self::Regions,
+        Self::Ownership,
+        Self::Routes,
+        Self::TradeRoutes,
+        Self::Labels,
+    ];
+
+    pub fn icon(&self) -> IconId {
+        match self {
+            Self::Grid => icons::GRID,
+            Self::Regions => icons::BOUNDING_BOX,
+            Self::Ownership => icons::FLAG,
+            Self::Routes => icons::BEZIER,
+            Self::TradeRoutes => icons::ARROW_LEFT_RIGHT,
+            Self::Labels => icons::FONTS,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Grid => "layer-grid",
+            Self::Regions => "layer-regions",
+            Self::Ownership => "layer-ownership",
+            Self::Routes => "layer-routes",
+            Self::TradeRoutes => "layer-trade-routes",
+            Self::Labels => "layer-labels",
+        }
+    }
+
+    fn visible_by_default(&self) -> bool {
+        !matches!(self, Self::Grid)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerState {
+    pub layer: MapLayer,
+    pub visible: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerSettings {
+    /// The layers, from the bottom to the top.
+    pub layers: Vec<LayerState>,
+}
+
+impl LayerSettings {
+    pub fn is_visible(&self, layer: MapLayer) -> bool {
+        self.layers
+            .iter()
+            .find(|state| state.layer == layer)
+            .map_or_else(|| layer.visible_by_default(), |state| state.visible)
+    }
+
+    /// Position of the layer from the bottom.
+    pub fn order(&self, layer: MapLayer) -> i32 {
+        let index = self
+            .layers
+            .iter()
+            .position(|state| state.layer == layer)
+            .unwrap_or(self.layers.len());
+        index.try_into().unwrap()
+    }
+
+    pub fn toggle(&mut self, layer: MapLayer) {
+        let index = self.index(layer);
+        self.layers[index].visible = !self.layers[index].visible;
+    }
+
+    /// Moves a layer one position up, i.e. on top of the layer above it.
+    pub fn raise(&mut self, layer: MapLayer) {
+        let index = self.index(layer);
+        if index + 1 < self.layers.len() {
+            self.layers.swap(index, index + 1);
+        }
+    }
+
+    /// Moves a layer one position down.
+    pub fn lower(&mut self, layer: MapLayer) {
+        let index = self.index(layer);
+        if index > 0 {
+            self.layers.swap(index, index - 1);
+        }
+    }
+
+    fn index(&mut self, layer: MapLayer) -> usize {
+        match self.layers.iter().position(|state| state.layer == layer) {
+            Some(index) => index,
+            None => {
+                // the settings were persisted by an older version that didn't have this layer.
+                self.layers.push(LayerState {
+                    layer,
+                    visible: layer.visible_by_default(),
+                });
+                self.layers.len() - 1
+            }
+        }
+    }
+}
+
+impl Default for LayerSettings {
+    fn default() -> Self {
+        Self {
+            layers: MapLayer::ALL
+                .into_iter()
+                .map(|layer| {
+                    LayerState {
+                        layer,
+                        visible: layer.visible_by_default(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Marks an entity as part of a layer.
+#[derive(Clone, Copy, Debug)]
+pub struct OnLayer(pub MapLayer);
+
+/// Resource with the current layer settings.
+#[derive(Clone, Debug, Default)]
+pub struct MapLayers {
+    pub settings: LayerSettings,
+}
+
+fn map_layers_system(system_context: &mut SystemContext) {
+    let Some(layers) = system_context.resources.get::<MapLayers>()
+    else {
+        return;
+    };
+
+    let mut query = system_context
+        .world
+        .query::<(&OnLayer, Option<&Hidden>, Option<&GizmoOrder>)>();
+
+    for (entity, (on_layer, hidden, order)) in query.iter() {
+        let visible = layers.settings.is_visible(on_layer.0);
+        if visible && hidden.is_some() {
+            system_context.command_buffer.remove_one::<Hidden>(entity);
+        }
+        else if !visible && hidden.is_none() {
+            system_context.command_buffer.insert_one(entity, Hidden);
+        }
+
+        let expected_order = GizmoOrder(layers.settings.order(on_layer.0));
+        if order != Some(&expected_order) {
+            system_context
+                .command_buffer
+                .insert_one(entity, expected_order);
+        }
+    }
+}
+
+/// Handle to the layer settings, provided as context.
+#[derive(Clone, Copy, Debug)]
+pub struct MapLayersContext {
+    settings: Signal<LayerSettings>,
+    set_settings: WriteSignal<LayerSettings>,
+}
+
+impl MapLayersContext {
+    pub fn is_visible(&self, layer: MapLayer) -> bool {
+        self.settings.with(|settings| settings.is_visible(layer))
+    }
+
+    pub fn toggle(&self, layer: MapLayer) {
+        self.set_settings.update(|settings| settings.toggle(layer));
+    }
+
+    pub fn raise(&self, layer: MapLayer) {
+        self.set_settings.update(|settings| settings.raise(layer));
+    }
+
+    pub fn lower(&self, layer: MapLayer) {
+        self.set_settings.update(|settings| settings.lower(layer));
+    }
+
+    /// The layers from the top to the bottom, in the order they're listed.
+    fn listed(&self) -> Vec<MapLayer> {
+        self.settings.with(|settings| {
+            let mut layers = settings
+                .layers
+                .iter()
+                .map(|state| state.layer)
+                .collect::<Vec<_>>();
+            layers.extend(
+                MapLayer::ALL
+                    .into_iter()
+                    .filter(|layer| !settings.layers.iter().any(|state| state.layer == *layer)),
+            );
+            layers.reverse();
+            layers
+        })
+    }
+}
+
+/// Provides the [`MapLayersContext`] and keeps the [`MapLayers`] resource up to
+/// date.
+///
+/// This must be called after the [`WorldServer`] was provided.
+pub fn provide_map_layers() {
+    let (settings, set_settings, _) =
+        use_local_storage::<LayerSettings, codee::string::JsonSerdeCodec>("map-layers");
+    let world = expect_context::<WorldServer>();
+
+    create_effect(move |_| {
+        let settings = settings.get();
+        let _ = world.run(move |system_context| {
+            system_context.resources.insert(MapLayers { settings });
+        });
+    });
+
+    provide_context(MapLayersContext {
+        settings,
+        set_settings,
+    });
+}
+
+pub fn use_map_layers() -> MapLayersContext {
+    expect_context()
+}
+
+/// Button in the world view that opens the list of layers.
+#[component]
+pub fn LayerControl() -> impl IntoView {
+    let layers = use_map_layers();
+    let open = create_rw_signal(false);
+
+    let toggle_class = move || {
+        if open.get() {
+            format!("{} {}", Style::button, Style::active)
+        }
+        else {
+            Style::button.to_owned()
+        }
+    };
+
+    view! {
+        <div class=Style::layers>
+            <Show when=move || open.get()>
+                <ul class=Style::list>
+                    <For
+                        each=move || layers.listed()
+                        key=|layer| *layer
+                        children=move |layer| view! { <LayerItem layer /> }
+                    />
+                </ul>
+            </Show>
+            <button
+                class=toggle_class
+                title=t!("layers-toggle")
+                aria-expanded=move || open.get().to_string()
+                on:click=move |_| open.update(|open| *open = !*open)
+            >
+                <Icon icon=icons::LAYERS />
+            </button>
+        </div>
+    }
+}
+
+#[component]
+fn LayerItem(layer: MapLayer) -> impl IntoView {
+    let layers = use_map_layers();
+    let label = use_i18n().message(layer.title());
+
+    view! {
+        <li class=Style::item>
+            <label class=Style::label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || layers.is_visible(layer)
+                    on:change=move |_| layers.toggle(layer)
+                />
+                <Icon icon=layer.icon() />
+                {label}
+            </label>
+            <button class=Style::button title=t!("layers-raise") on:click=move |_| layers.raise(layer)>
+                <Icon icon=icons::CHEVRON_UP />
+            </button>
+            <button class=Style::button title=t!("layers-lower") on:click=move |_| layers.lower(layer)>
+                <Icon icon=icons::CHEVRON_DOWN />
+            </button>
+        </li>
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MapLayersPlugin;
+
+impl Plugin for MapLayersPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.resources.insert(MapLayers::default());
+        context.schedule.add_system(map_layers_system);
+        context.startup_schedule.add_system(grid::spawn_grid);
+        context
+            .startup_schedule
+            .add_system(ownership::load_colonies);
+        context.schedule.add_system(ownership::spawn_territories);
+        context.schedule.add_system(routes::update_routes);
+        context
+            .startup_schedule
+            .add_system(trade_routes::load_trade_routes);
+        context
+            .schedule
+            .add_system(trade_routes::spawn_trade_routes);
+        context
+            .schedule
+            .add_system(trade_routes::animate_trade_routes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        LayerSettings,
+        MapLayer,
+    };
+
+    #[test]
+    fn raise_and_lower_swap_neighbours() {
+        let mut settings = LayerSettings::default();
+        assert_eq!(settings.order(MapLayer::Grid), 0);
+
+        settings.raise(MapLayer::Grid);
+        assert_eq!(settings.order(MapLayer::Grid), 1);
+        assert_eq!(settings.order(MapLayer::Regions), 0);
+
+        settings.lower(MapLayer::Regions);
+        assert_eq!(settings.order(MapLayer::Regions), 0);
+
+        settings.raise(MapLayer::Labels);
+        assert_eq!(settings.order(MapLayer::Labels), 5);
+    }
+
+    #[test]
+    fn missing_layers_are_added() {
+        let mut settings = LayerSettings { layers: vec![] };
+        assert!(settings.is_visible(MapLayer::Routes));
+
+        settings.toggle(MapLayer::Routes);
+        assert!(!settings.is_visible(MapLayer::Routes));
+        assert_eq!(settings.order(MapLayer::Routes), 0);
+    }
+}
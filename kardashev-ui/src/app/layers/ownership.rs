@@ -0,0 +1,145 @@
+//! Territory of the player's faction, drawn as a disc around each colonized
+//! star.
+
+use std::{
+    collections::HashMap,
+    f32::consts::TAU,
+};
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::star::StarId;
+use nalgebra::{
+    Point3,
+    Vector3,
+};
+use tokio::sync::oneshot;
+
+use crate::{
+    app::layers::{
+        MapLayer,
+        OnLayer,
+    },
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::system::SystemContext,
+    graphics::{
+        gizmo::Polygon,
+        transform::Transform,
+    },
+    universe::star::StarEntity,
+    utils::futures::spawn_local,
+};
+
+/// Radius of the territory around a colony in parsecs.
+const RADIUS: f32 = 1.0;
+
+const NUM_VERTICES: usize = 24;
+
+const TINT: Tint = Tint {
+    token: Token::Ownership,
+    alpha: 0.5,
+    fill_alpha: Some(0.08),
+};
+
+#[derive(Debug)]
+struct LoadColonies {
+    rx: oneshot::Receiver<Vec<StarId>>,
+}
+
+/// Colonized stars whose territory is drawn once the stars are spawned.
+#[derive(Debug)]
+struct PendingColonies {
+    stars: Vec<StarId>,
+}
+
+pub fn load_colonies(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    // without a faction nobody owns anything.
+    if api_client.faction().is_none() {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    system_context.resources.insert(LoadColonies { rx });
+
+    spawn_local(async move {
+        match api_client.get_empire_summary().await {
+            Ok(summary) => {
+                let stars = summary.colonies.iter().map(|colony| colony.star).collect();
+                let _ = tx.send(stars);
+            }
+            Err(error) => tracing::error!(%error, "failed to load colonies"),
+        }
+    });
+}
+
+pub fn spawn_territories(system_context: &mut SystemContext) {
+    if let Some(load_colonies) = system_context.resources.get_mut::<LoadColonies>() {
+        match load_colonies.rx.try_recv() {
+            Ok(stars) => {
+                system_context.resources.remove::<LoadColonies>();
+                system_context.resources.insert(PendingColonies { stars });
+            }
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                system_context.resources.remove::<LoadColonies>();
+                return;
+            }
+        }
+    }
+
+    let Some(pending) = system_context.resources.get::<PendingColonies>()
+    else {
+        return;
+    };
+
+    let positions = system_context
+        .world
+        .query_mut::<(&StarEntity, &Transform)>()
+        .into_iter()
+        .map(|(_, (star, transform))| {
+            (
+                star.id,
+                Point3::from(transform.model_matrix.isometry.translation.vector),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    if positions.is_empty() {
+        // stars aren't loaded yet.
+        return;
+    }
+
+    let palette = ActivePalette::get(&system_context.resources);
+    let territories = pending
+        .stars
+        .iter()
+        .filter_map(|star| positions.get(star))
+        .map(|center| {
+            Polygon {
+                vertices: (0..NUM_VERTICES)
+                    .map(|i| {
+                        let angle = TAU * (i as f32) / (NUM_VERTICES as f32);
+                        center + RADIUS * Vector3::new(angle.cos(), 0.0, angle.sin())
+                    })
+                    .collect(),
+                fill: TINT.fill(palette),
+                outline: TINT.color(palette),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    system_context.resources.remove::<PendingColonies>();
+    for territory in territories {
+        system_context
+            .world
+            .spawn((OnLayer(MapLayer::Ownership), territory, TINT));
+    }
+}
@@ -0,0 +1,107 @@
+//! Routes of fleets, drawn as lines through the stars in their order queues.
+
+use std::collections::HashMap;
+
+use hecs::Entity;
+use kardashev_protocol::model::star::StarId;
+use nalgebra::Point3;
+
+use crate::{
+    app::layers::{
+        MapLayer,
+        OnLayer,
+    },
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::system::SystemContext,
+    graphics::{
+        gizmo::Line,
+        transform::Transform,
+    },
+    universe::{
+        fleet::{
+            FleetEntity,
+            FleetRoute,
+        },
+        star::StarEntity,
+    },
+};
+
+const TINT: Tint = Tint {
+    token: Token::Route,
+    alpha: 0.4,
+    fill_alpha: None,
+};
+
+/// Lines spawned for a fleet's route.
+#[derive(Debug)]
+struct RouteLines {
+    /// The route the lines were spawned for.
+    stars: Vec<StarId>,
+    lines: Vec<Entity>,
+}
+
+/// Respawns the lines of fleets whose route changed.
+pub fn update_routes(system_context: &mut SystemContext) {
+    let outdated = system_context
+        .world
+        .query_mut::<(&FleetRoute, &Transform, Option<&RouteLines>)>()
+        .with::<&FleetEntity>()
+        .into_iter()
+        .filter(|(_, (route, _, lines))| lines.map_or(true, |lines| lines.stars != route.stars))
+        .map(|(entity, (route, transform, _))| {
+            (
+                entity,
+                route.stars.clone(),
+                Point3::from(transform.model_matrix.isometry.translation.vector),
+            )
+        })
+        .collect::<Vec<_>>();
+    if outdated.is_empty() {
+        return;
+    }
+
+    let positions = system_context
+        .world
+        .query_mut::<(&StarEntity, &Transform)>()
+        .into_iter()
+        .map(|(_, (star, transform))| {
+            (
+                star.id,
+                Point3::from(transform.model_matrix.isometry.translation.vector),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    if positions.is_empty() {
+        // stars aren't loaded yet.
+        return;
+    }
+
+    let palette = ActivePalette::get(&system_context.resources);
+    for (fleet, stars, position) in outdated {
+        if let Ok(old) = system_context.world.remove_one::<RouteLines>(fleet) {
+            for line in old.lines {
+                let _ = system_context.world.despawn(line);
+            }
+        }
+
+        let mut from = position;
+        let mut lines = vec![];
+        // stars the faction doesn't know about are skipped.
+        for to in stars.iter().filter_map(|star| positions.get(star)) {
+            lines.push(system_context.world.spawn((
+                OnLayer(MapLayer::Routes),
+                Line::new(from, *to, TINT.color(palette)),
+                TINT,
+            )));
+            from = *to;
+        }
+
+        let _ = system_context
+            .world
+            .insert_one(fleet, RouteLines { stars, lines });
+    }
+}
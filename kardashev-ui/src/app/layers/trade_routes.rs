@@ -0,0 +1,211 @@
+//! Trade routes of the player's faction, drawn as lines between the colonized
+//! stars, with a pulse that travels along each line in the direction of the
+//! shipments.
+
+use std::collections::HashMap;
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::star::StarId;
+use nalgebra::Point3;
+use tokio::sync::oneshot;
+
+use crate::{
+    app::layers::{
+        MapLayer,
+        OnLayer,
+    },
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::system::SystemContext,
+    graphics::{
+        gizmo::Line,
+        transform::Transform,
+    },
+    universe::star::StarEntity,
+    utils::{
+        futures::spawn_local,
+        time::Instant,
+    },
+};
+
+/// Seconds the pulse takes for a day of travel time.
+const SECONDS_PER_DAY: f32 = 0.5;
+
+/// Shortest time the pulse takes from one end of a route to the other.
+const MIN_PERIOD: f32 = 1.0;
+
+/// Length of the pulse as fraction of the route.
+const PULSE_LENGTH: f32 = 0.15;
+
+const TINT: Tint = Tint {
+    token: Token::Trade,
+    alpha: 0.3,
+    fill_alpha: None,
+};
+
+const PULSE_TINT: Tint = Tint {
+    token: Token::Trade,
+    alpha: 0.9,
+    fill_alpha: None,
+};
+
+#[derive(Clone, Copy, Debug)]
+struct RouteStars {
+    from: StarId,
+    to: StarId,
+    travel_days: f32,
+}
+
+#[derive(Debug)]
+struct LoadTradeRoutes {
+    rx: oneshot::Receiver<Vec<RouteStars>>,
+}
+
+/// Routes that are drawn once the stars are spawned.
+#[derive(Debug)]
+struct PendingTradeRoutes {
+    routes: Vec<RouteStars>,
+}
+
+/// A segment that moves along a trade route.
+#[derive(Clone, Copy, Debug)]
+struct TradePulse {
+    from: Point3<f32>,
+    to: Point3<f32>,
+
+    /// Seconds the pulse takes from one end to the other.
+    period: f32,
+
+    started: Instant,
+}
+
+impl TradePulse {
+    /// The segment at `elapsed` seconds.
+    fn segment(&self, elapsed: f32) -> (Point3<f32>, Point3<f32>) {
+        let start = (elapsed / self.period).fract();
+        let end = (start + PULSE_LENGTH).min(1.0);
+        (
+            self.from + (self.to - self.from) * start,
+            self.from + (self.to - self.from) * end,
+        )
+    }
+}
+
+pub fn load_trade_routes(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    // without a faction there's no trade.
+    if api_client.faction().is_none() {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    system_context.resources.insert(LoadTradeRoutes { rx });
+
+    spawn_local(async move {
+        match api_client.get_trade_routes().await {
+            Ok(response) => {
+                let routes = response
+                    .routes
+                    .iter()
+                    .map(|route| {
+                        RouteStars {
+                            from: route.from_star,
+                            to: route.to_star,
+                            travel_days: route.travel_days,
+                        }
+                    })
+                    .collect();
+                let _ = tx.send(routes);
+            }
+            Err(error) => tracing::error!(%error, "failed to load trade routes"),
+        }
+    });
+}
+
+pub fn spawn_trade_routes(system_context: &mut SystemContext) {
+    if let Some(load_routes) = system_context.resources.get_mut::<LoadTradeRoutes>() {
+        match load_routes.rx.try_recv() {
+            Ok(routes) => {
+                system_context.resources.remove::<LoadTradeRoutes>();
+                system_context
+                    .resources
+                    .insert(PendingTradeRoutes { routes });
+            }
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                system_context.resources.remove::<LoadTradeRoutes>();
+                return;
+            }
+        }
+    }
+
+    let Some(pending) = system_context.resources.get::<PendingTradeRoutes>()
+    else {
+        return;
+    };
+
+    let positions = system_context
+        .world
+        .query_mut::<(&StarEntity, &Transform)>()
+        .into_iter()
+        .map(|(_, (star, transform))| {
+            (
+                star.id,
+                Point3::from(transform.model_matrix.isometry.translation.vector),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    if positions.is_empty() {
+        // stars aren't loaded yet.
+        return;
+    }
+
+    let started = Instant::now();
+    let pulses = pending
+        .routes
+        .iter()
+        .filter_map(|route| {
+            Some(TradePulse {
+                from: *positions.get(&route.from)?,
+                to: *positions.get(&route.to)?,
+                period: (route.travel_days * SECONDS_PER_DAY).max(MIN_PERIOD),
+                started,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    system_context.resources.remove::<PendingTradeRoutes>();
+    let palette = ActivePalette::get(&system_context.resources);
+    for pulse in pulses {
+        system_context.world.spawn((
+            OnLayer(MapLayer::TradeRoutes),
+            Line::new(pulse.from, pulse.to, TINT.color(palette)),
+            TINT,
+        ));
+
+        let (from, to) = pulse.segment(0.0);
+        system_context.world.spawn((
+            OnLayer(MapLayer::TradeRoutes),
+            Line::new(from, to, PULSE_TINT.color(palette)),
+            PULSE_TINT,
+            pulse,
+        ));
+    }
+}
+
+/// Moves the pulses along their routes.
+pub fn animate_trade_routes(system_context: &mut SystemContext) {
+    for (_, (pulse, line)) in system_context.world.query_mut::<(&TradePulse, &mut Line)>() {
+        let (from, to) = pulse.segment(pulse.started.elapsed().as_secs_f32());
+        line.from = from;
+        line.to = to;
+    }
+}
@@ -0,0 +1,245 @@
+//! Panel layout of the UI.
+//!
+//! The UI consists of panels that can be opened, closed, docked to the sides
+//! of the workspace, and resized. The layout is persisted in local storage.
+
+use leptos::{
+    expect_context,
+    provide_context,
+    Signal,
+    SignalUpdate,
+    SignalWith,
+    WriteSignal,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::app::components::icon::{
+    icons,
+    IconId,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PanelKind {
+    Dashboard,
+    Leaderboard,
+    Diplomacy,
+    Map,
+    SystemDetail,
+    FleetList,
+    Console,
+    Inspector,
+    Orders,
+    Journal,
+    Bookmarks,
+    Replay,
+    Settings,
+}
+
+impl PanelKind {
+    pub const ALL: [Self; 13] = [
+        Self::Dashboard,
+        Self::Leaderboard,
+        Self::Diplomacy,
+        Self::Map,
+        Self::SystemDetail,
+        Self::FleetList,
+        Self::Orders,
+        Self::Journal,
+        Self::Bookmarks,
+        Self::Replay,
+        Self::Console,
+        Self::Inspector,
+        Self::Settings,
+    ];
+
+    pub fn icon(&self) -> IconId {
+        match self {
+            Self::Dashboard => icons::SPEEDOMETER,
+            Self::Leaderboard => icons::TROPHY,
+            Self::Diplomacy => icons::FLAG,
+            Self::Map => icons::RADAR,
+            Self::SystemDetail => icons::SUN,
+            Self::FleetList => icons::ROCKET,
+            Self::Console => icons::TERMINAL,
+            Self::Inspector => icons::SEARCH,
+            Self::Orders => icons::LIST_OL,
+            Self::Journal => icons::JOURNAL,
+            Self::Bookmarks => icons::BOOKMARK_STAR,
+            Self::Replay => icons::CLOCK_HISTORY,
+            Self::Settings => icons::GEAR,
+        }
+    }
+
+    /// Message ID of the panel's title.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Dashboard => "panel-dashboard",
+            Self::Leaderboard => "panel-leaderboard",
+            Self::Diplomacy => "panel-diplomacy",
+            Self::Map => "panel-map",
+            Self::SystemDetail => "panel-system-detail",
+            Self::FleetList => "panel-fleet-list",
+            Self::Console => "panel-console",
+            Self::Inspector => "panel-inspector",
+            Self::Orders => "panel-orders",
+            Self::Journal => "panel-journal",
+            Self::Bookmarks => "panel-bookmarks",
+            Self::Replay => "panel-replay",
+            Self::Settings => "panel-settings",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DockPosition {
+    Left,
+    Center,
+    Right,
+    Bottom,
+}
+
+impl DockPosition {
+    pub const ALL: [Self; 4] = [Self::Left, Self::Center, Self::Right, Self::Bottom];
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PanelState {
+    pub kind: PanelKind,
+    pub open: bool,
+    pub dock: DockPosition,
+
+    /// Width (for left and right docks) or height (for the bottom dock) of the
+    /// panel in pixels.
+    pub size: f64,
+}
+
+impl PanelState {
+    pub const MIN_SIZE: f64 = 120.0;
+
+    fn new(kind: PanelKind, open: bool, dock: DockPosition) -> Self {
+        Self {
+            kind,
+            open,
+            dock,
+            size: 320.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub panels: Vec<PanelState>,
+}
+
+impl Layout {
+    pub fn get(&self, kind: PanelKind) -> Option<&PanelState> {
+        self.panels.iter().find(|panel| panel.kind == kind)
+    }
+
+    fn get_mut(&mut self, kind: PanelKind) -> &mut PanelState {
+        let index = match self.panels.iter().position(|panel| panel.kind == kind) {
+            Some(index) => index,
+            None => {
+                // the layout was persisted by an older version that didn't have this panel.
+                self.panels
+                    .push(PanelState::new(kind, false, DockPosition::Right));
+                self.panels.len() - 1
+            }
+        };
+        &mut self.panels[index]
+    }
+
+    /// Returns the open panels at a dock position.
+    pub fn docked(&self, dock: DockPosition) -> impl Iterator<Item = &PanelState> {
+        self.panels
+            .iter()
+            .filter(move |panel| panel.open && panel.dock == dock)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            panels: vec![
+                PanelState::new(PanelKind::Dashboard, false, DockPosition::Left),
+                PanelState::new(PanelKind::Leaderboard, false, DockPosition::Left),
+                PanelState::new(PanelKind::Diplomacy, false, DockPosition::Left),
+                PanelState::new(PanelKind::Map, true, DockPosition::Center),
+                PanelState::new(PanelKind::SystemDetail, false, DockPosition::Right),
+                PanelState::new(PanelKind::FleetList, false, DockPosition::Left),
+                PanelState::new(PanelKind::Console, false, DockPosition::Bottom),
+                PanelState::new(PanelKind::Inspector, false, DockPosition::Right),
+                PanelState::new(PanelKind::Orders, false, DockPosition::Right),
+                PanelState::new(PanelKind::Journal, false, DockPosition::Bottom),
+                PanelState::new(PanelKind::Bookmarks, false, DockPosition::Left),
+                PanelState::new(PanelKind::Replay, false, DockPosition::Bottom),
+                PanelState::new(PanelKind::Settings, false, DockPosition::Right),
+            ],
+        }
+    }
+}
+
+/// Handle to the layout, provided as context.
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutContext {
+    layout: Signal<Layout>,
+    set_layout: WriteSignal<Layout>,
+}
+
+impl LayoutContext {
+    pub fn layout(&self) -> Signal<Layout> {
+        self.layout
+    }
+
+    pub fn is_open(&self, kind: PanelKind) -> bool {
+        self.layout
+            .with(|layout| layout.get(kind).map_or(false, |panel| panel.open))
+    }
+
+    pub fn toggle(&self, kind: PanelKind) {
+        self.set_layout.update(|layout| {
+            let panel = layout.get_mut(kind);
+            panel.open = !panel.open;
+        });
+    }
+
+    pub fn close(&self, kind: PanelKind) {
+        self.set_layout
+            .update(|layout| layout.get_mut(kind).open = false);
+    }
+
+    pub fn dock(&self, kind: PanelKind, dock: DockPosition) {
+        self.set_layout.update(|layout| {
+            let panel = layout.get_mut(kind);
+            panel.dock = dock;
+            panel.open = true;
+        });
+    }
+
+    pub fn resize(&self, kind: PanelKind, size: f64) {
+        self.set_layout.update(|layout| {
+            layout.get_mut(kind).size = size.max(PanelState::MIN_SIZE);
+        });
+    }
+
+    /// Resets the layout to the default.
+    pub fn reset(&self) {
+        self.set_layout.update(|layout| *layout = Layout::default());
+    }
+}
+
+pub fn provide_layout() {
+    let (layout, set_layout, _) =
+        use_local_storage::<Layout, codee::string::JsonSerdeCodec>("layout");
+    provide_context(LayoutContext { layout, set_layout });
+}
+
+pub fn use_layout() -> LayoutContext {
+    expect_context()
+}
@@ -0,0 +1,109 @@
+//! Ranking of the factions by their rating on the Kardashev scale.
+
+use kardashev_client::ApiClient;
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_local_resource,
+    expect_context,
+    view,
+    For,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalWith,
+};
+
+use crate::{
+    app::components::{
+        icon::{
+            icons,
+            Icon,
+        },
+        sparkline::Sparkline,
+    },
+    t,
+};
+
+#[style(path = "src/app/leaderboard.scss")]
+struct Style;
+
+#[component]
+pub fn Leaderboard() -> impl IntoView {
+    let api_client = expect_context::<ApiClient>();
+
+    let leaderboard = create_local_resource(
+        || (),
+        move |_| {
+            let api_client = api_client.clone();
+            async move {
+                api_client
+                    .get_leaderboard()
+                    .await
+                    .inspect_err(|error| tracing::error!(%error, "failed to load leaderboard"))
+                    .ok()
+            }
+        },
+    );
+    let entries = move || {
+        leaderboard
+            .get()
+            .flatten()
+            .map(|leaderboard| leaderboard.entries)
+            .unwrap_or_default()
+    };
+    let is_empty = move || {
+        leaderboard.with(|leaderboard| {
+            leaderboard
+                .as_ref()
+                .and_then(Option::as_ref)
+                .map_or(true, |leaderboard| leaderboard.entries.is_empty())
+        })
+    };
+
+    view! {
+        <div class=Style::leaderboard>
+            <button class=Style::button title=t!("leaderboard-refresh") on:click=move |_| leaderboard.refetch()>
+                <Icon icon=icons::ARROW_CLOCKWISE alt=t!("leaderboard-refresh") />
+            </button>
+            <Show
+                when=move || !is_empty()
+                fallback=|| view! { <p class=Style::empty>{t!("leaderboard-empty")}</p> }
+            >
+                <table class=Style::table>
+                    <thead>
+                        <tr>
+                            <th>{t!("leaderboard-rank")}</th>
+                            <th>{t!("leaderboard-faction")}</th>
+                            <th>{t!("leaderboard-rating")}</th>
+                            <th>{t!("leaderboard-history")}</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        <For
+                            each=move || entries().into_iter().enumerate()
+                            key=|(_, entry)| entry.faction
+                            children=|(index, entry)| {
+                                let history = entry
+                                    .history
+                                    .iter()
+                                    .map(|sample| sample.rating)
+                                    .collect::<Vec<_>>();
+                                view! {
+                                    <tr>
+                                        <td>{index + 1}</td>
+                                        <td>{entry.name}</td>
+                                        <td class=Style::rating title=format!("{:.3e} W", entry.power)>
+                                            {format!("{:.3}", entry.rating)}
+                                        </td>
+                                        <td><Sparkline values=history /></td>
+                                    </tr>
+                                }
+                            }
+                        />
+                    </tbody>
+                </table>
+            </Show>
+        </div>
+    }
+}
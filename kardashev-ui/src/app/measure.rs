@@ -0,0 +1,385 @@
+//! Measurement tool for distances on the star map.
+//!
+//! While the tool is active, clicking on two stars in the world view measures
+//! the distance between them instead of selecting them. Until the second star
+//! is clicked, the measurement follows the star under the mouse. The
+//! measurement is drawn as a [`Line`] gizmo.
+
+use std::time::Duration;
+
+use hecs::Entity;
+use kardashev_protocol::units::{
+    Distance,
+    SECONDS_PER_YEAR,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalSet,
+    SignalWith,
+};
+use nalgebra::{
+    Point2,
+    Point3,
+};
+use tokio::sync::watch;
+
+use crate::{
+    app::components::icon::{
+        icons,
+        Icon,
+    },
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        gizmo::Line,
+        transform::GlobalTransform,
+    },
+    i18n::use_i18n,
+    raycast::Raycaster,
+    selection::SelectionCamera,
+    t,
+    universe::star::StarEntity,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/measure.scss")]
+struct Style;
+
+/// Speeds, as fractions of the speed of light, for which travel times are
+/// shown by default.
+const DEFAULT_SPEEDS: &str = "0.01, 0.1, 0.5";
+
+const LINE_TINT: Tint = Tint {
+    token: Token::Measure,
+    alpha: 0.8,
+    fill_alpha: None,
+};
+
+/// Resource with the state of the measurement tool.
+#[derive(Debug)]
+pub struct MeasureTool {
+    active: bool,
+    start: Option<Entity>,
+    end: Option<Entity>,
+    hover: Option<Entity>,
+    line: Option<Entity>,
+    tx_measurement: watch::Sender<Option<Measurement>>,
+}
+
+impl MeasureTool {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    fn set_active(&mut self, active: bool) {
+        self.active = active;
+        self.start = None;
+        self.end = None;
+        self.hover = None;
+    }
+}
+
+/// A measurement between two stars.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measurement {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub distance: Option<Distance>,
+
+    /// Whether the second star is only hovered, and not clicked yet.
+    pub preview: bool,
+}
+
+/// Input from a world view to the measurement tool.
+#[derive(Clone, Copy, Debug)]
+pub struct MeasureInput {
+    pub camera: SelectionCamera,
+    pub position: Point2<f32>,
+    pub clicked: bool,
+}
+
+impl MeasureInput {
+    pub fn apply(&self, system_context: &mut SystemContext) {
+        let raycaster = system_context
+            .resources
+            .get_mut_or_insert_default::<Raycaster>();
+        let picked = self
+            .camera
+            .pick(system_context.world, raycaster, self.position)
+            .filter(|entity| {
+                system_context
+                    .world
+                    .satisfies::<&StarEntity>(*entity)
+                    .unwrap_or_default()
+            });
+
+        let Some(tool) = system_context.resources.get_mut::<MeasureTool>()
+        else {
+            return;
+        };
+
+        if self.clicked {
+            if let Some(picked) = picked {
+                if tool.start.is_none() || tool.end.is_some() {
+                    tool.start = Some(picked);
+                    tool.end = None;
+                }
+                else {
+                    tool.end = Some(picked);
+                }
+            }
+        }
+        else {
+            tool.hover = picked;
+        }
+    }
+}
+
+fn measure_system(system_context: &mut SystemContext) {
+    let line_color = LINE_TINT.color(ActivePalette::get(&system_context.resources));
+
+    let Some(tool) = system_context.resources.get_mut::<MeasureTool>()
+    else {
+        return;
+    };
+
+    let star = |entity: Option<Entity>| {
+        let entity = entity?;
+        let mut query = system_context
+            .world
+            .query_one::<(&GlobalTransform, Option<&Label>)>(entity)
+            .ok()?;
+        let (transform, label) = query.get()?;
+        Some((
+            transform.model_matrix.transform_point(&Point3::origin()),
+            label.map(|label| label.label.to_string()),
+        ))
+    };
+
+    let start = star(tool.start);
+    let preview = tool.end.is_none();
+    let end = star(tool.end.or(tool.hover)).filter(|_| start.is_some());
+
+    let line = start
+        .as_ref()
+        .zip(end.as_ref())
+        .map(|((from, _), (to, _))| Line::new(*from, *to, line_color));
+
+    let measurement = start.map(|(from_position, from)| {
+        let (to_position, to) = end.unzip();
+        Measurement {
+            from,
+            to: to.flatten(),
+            distance: to_position
+                .map(|to_position| Distance::from_parsecs((to_position - from_position).norm())),
+            preview,
+        }
+    });
+
+    tool.tx_measurement.send_if_modified(|current| {
+        if *current != measurement {
+            *current = measurement;
+            true
+        }
+        else {
+            false
+        }
+    });
+
+    match (line, tool.line) {
+        (Some(line), Some(entity)) => {
+            system_context.command_buffer.insert_one(entity, line);
+        }
+        (Some(line), None) => {
+            let entity = system_context.world.reserve_entity();
+            system_context.command_buffer.insert_one(entity, line);
+            tool.line = Some(entity);
+        }
+        (None, Some(entity)) => {
+            system_context.command_buffer.despawn(entity);
+            tool.line = None;
+        }
+        (None, None) => {}
+    }
+}
+
+/// Button to toggle the measurement tool, and a box showing the measurement.
+#[component]
+pub fn MeasureToolbar() -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let i18n = use_i18n();
+    let active = create_rw_signal(false);
+    let measurement = create_rw_signal(None::<Measurement>);
+    let speeds = create_rw_signal(DEFAULT_SPEEDS.to_owned());
+
+    let join_handle = spawn_local(async move {
+        let mut rx_measurement = world
+            .get_value()
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<MeasureTool>()
+                    .map(|tool| tool.tx_measurement.subscribe())
+            })
+            .await?;
+
+        loop {
+            measurement.set(rx_measurement.borrow_and_update().clone());
+            if rx_measurement.changed().await.is_err() {
+                break;
+            }
+        }
+
+        Some(())
+    });
+    on_cleanup(move || join_handle.abort());
+
+    let set_active = move |value: bool| {
+        active.set(value);
+        let _ = world.get_value().run(move |system_context| {
+            if let Some(tool) = system_context.resources.get_mut::<MeasureTool>() {
+                tool.set_active(value);
+            }
+        });
+    };
+
+    // leaving the tool active would keep the world view from selecting anything.
+    on_cleanup({
+        let world = expect_context::<WorldServer>();
+        move || {
+            let _ = world.run(|system_context| {
+                if let Some(tool) = system_context.resources.get_mut::<MeasureTool>() {
+                    tool.set_active(false);
+                }
+            });
+        }
+    });
+
+    let star_name =
+        move |name: Option<String>| name.unwrap_or_else(|| i18n.translate("measure-unnamed-star"));
+
+    let travel_times = move || {
+        let distance = measurement.with(|measurement| measurement.as_ref()?.distance)?;
+        let rows = speeds.with(|speeds| {
+            speeds
+                .split(',')
+                .filter_map(|speed| speed.trim().parse::<f32>().ok())
+                .filter_map(|speed| Some((speed, distance.travel_time(speed)?)))
+                .map(|(speed, time)| {
+                    view! {
+                        <tr>
+                            <th>{format!("{speed} c")}</th>
+                            <td>{format_travel_time(time)}</td>
+                        </tr>
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+        Some(rows)
+    };
+
+    let toggle_class = move || {
+        if active.get() {
+            format!("{} {}", Style::toggle, Style::active)
+        }
+        else {
+            Style::toggle.to_owned()
+        }
+    };
+
+    view! {
+        <div class=Style::measure>
+            <button
+                class=toggle_class
+                title=t!("measure-toggle")
+                aria-pressed=move || active.get().to_string()
+                on:click=move |_| set_active(!active.get())
+            >
+                <Icon icon=icons::RULERS />
+            </button>
+            <Show when=move || active.get()>
+                <div class=Style::result>
+                    {move || {
+                        match measurement.get() {
+                            None => view! { <p class=Style::hint>{t!("measure-pick-start")}</p> }.into_view(),
+                            Some(Measurement { from, to, distance, preview }) => {
+                                view! {
+                                    <p class=Style::stars>
+                                        {star_name(from)}
+                                        " → "
+                                        {if distance.is_some() { star_name(to) } else { i18n.translate("measure-pick-end") }}
+                                    </p>
+                                    <Show when=move || distance.is_some()>
+                                        <p class=if preview { format!("{} {}", Style::distance, Style::preview) } else { Style::distance.to_owned() }>
+                                            {distance.map(|distance| format!("{:.2} ly / {:.2} pc", distance.light_years(), distance.parsecs()))}
+                                        </p>
+                                    </Show>
+                                }
+                                    .into_view()
+                            }
+                        }
+                    }}
+                    <label class=Style::speeds>
+                        {t!("measure-speeds")}
+                        <input
+                            type="text"
+                            prop:value=move || speeds.get()
+                            on:change=move |event| speeds.set(event_target_value(&event))
+                        />
+                    </label>
+                    <table class=Style::travel_times>{travel_times}</table>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+/// Formats a travel time in days or years, whichever is more readable.
+fn format_travel_time(time: Duration) -> String {
+    let years = time.as_secs_f64() / SECONDS_PER_YEAR;
+    if years < 1.0 {
+        format!("{:.0} d", time.as_secs_f64() / 86_400.0)
+    }
+    else {
+        format!("{years:.1} a")
+    }
+}
+
+pub struct MeasurePlugin;
+
+impl Plugin for MeasurePlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let (tx_measurement, _rx) = watch::channel(None);
+        context.resources.insert(MeasureTool {
+            active: false,
+            start: None,
+            end: None,
+            hover: None,
+            line: None,
+            tx_measurement,
+        });
+        context.schedule.add_system(measure_system);
+    }
+}
@@ -0,0 +1,390 @@
+//! Minimap showing a top-down overview around the map camera.
+//!
+//! The minimap has its own camera that looks down onto the XZ-plane, centered
+//! on the entity marked with [`MinimapTarget`]. It renders with the map's
+//! pipelines into an offscreen texture, which is drawn over the map as a
+//! [`Viewport`]. Clicking on the minimap moves the target to that point.
+
+use std::f32::consts::PI;
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_node_ref,
+    create_rw_signal,
+    expect_context,
+    html::Div,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+};
+use leptos_use::{
+    signal_debounced,
+    use_document_visibility,
+    use_element_bounding,
+    use_element_size_with_options,
+    use_element_visibility,
+    UseElementSizeOptions,
+};
+use nalgebra::{
+    Point2,
+    Point3,
+    Vector3,
+};
+use palette::{
+    Srgb,
+    WithAlpha,
+};
+use tokio::sync::mpsc;
+use web_sys::{
+    ResizeObserverBoxOptions,
+    VisibilityState,
+};
+
+use crate::{
+    app::world_view::CreateWorldViewPipeline,
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        camera::{
+            CameraProjection,
+            ClearColor,
+            DontRender,
+        },
+        hdr::CreateToneMapPass,
+        render_3d::CreateRender3dPass,
+        render_frame::{
+            AttachedRenderPass,
+            CreateRenderPass,
+        },
+        render_layers::RenderLayers,
+        transform::Transform,
+        viewport::{
+            Viewport,
+            ViewportTargets,
+        },
+        SurfaceSize,
+    },
+    input::{
+        mouse::{
+            MouseButton,
+            MouseEvent,
+        },
+        Timestamp,
+        Timestamped,
+    },
+    t,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/minimap.scss")]
+struct Style;
+
+/// Marks the entity that the minimap follows and moves when clicked.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinimapTarget;
+
+/// The minimap, drawn over the frame of the `parent` camera.
+///
+/// The minimap has to be placed inside the element that contains the parent
+/// camera's window. `targets` are created from the parent's surface, and
+/// `create_pipeline` should be the one the parent renders with, so that the
+/// minimap shows the same things as the map.
+#[component]
+pub fn Minimap(
+    #[prop(into)] parent: Signal<Option<hecs::Entity>>,
+    #[prop(into)] targets: Signal<Option<ViewportTargets>>,
+    create_pipeline: CreateWorldViewPipeline,
+) -> impl IntoView {
+    let node_ref = create_node_ref::<Div>();
+    let camera_entity = create_rw_signal(None);
+    let (tx_mouse, rx_mouse) = mpsc::channel(128);
+    let rx_mouse = store_value(Some(rx_mouse));
+
+    let element_size = use_element_size_with_options(
+        node_ref,
+        UseElementSizeOptions::default().box_(ResizeObserverBoxOptions::ContentBox),
+    );
+    let surface_size = signal_debounced(
+        Signal::derive(move || {
+            SurfaceSize {
+                width: (element_size.width.get() as u32).max(1),
+                height: (element_size.height.get() as u32).max(1),
+            }
+        }),
+        500.,
+    );
+
+    // the position of the minimap's content in the parent's frame, which fills
+    // the element containing the minimap.
+    let bounding = use_element_bounding(node_ref);
+    let parent_bounding = use_element_bounding(Signal::derive(move || {
+        node_ref.get().and_then(|element| element.parent_element())
+    }));
+    let position = Signal::derive(move || {
+        let (border_left, border_top) = node_ref.get().map_or((0, 0), |element| {
+            (element.client_left(), element.client_top())
+        });
+        Point2::new(
+            ((bounding.left.get() - parent_bounding.left.get()) as i32 + border_left).max(0) as u32,
+            ((bounding.top.get() - parent_bounding.top.get()) as i32 + border_top).max(0) as u32,
+        )
+    });
+
+    let create_pipeline = store_value(create_pipeline);
+
+    create_effect(move |_| {
+        let (Some(parent), Some(targets)) = (parent.get(), targets.get())
+        else {
+            return;
+        };
+        let surface_size = surface_size.get();
+
+        let render_target = targets.create_render_target(surface_size);
+        let render_pass = AttachedRenderPass::new(
+            CreateToneMapPass {
+                inner: CreateRender3dPass {
+                    create_pipeline: create_pipeline.get_value(),
+                },
+                format: wgpu::TextureFormat::Rgba16Float,
+                bloom: false,
+            }
+            .create_render_pass(&targets.render_pass_context(surface_size)),
+        );
+        let viewport = Viewport {
+            parent,
+            position: position.get_untracked(),
+        };
+
+        let world = expect_context::<WorldServer>();
+
+        // the minimap was resized, or the parent's surface was recreated after
+        // the graphics were restarted.
+        if let Some(entity) = camera_entity.get_untracked() {
+            let _ = world.run(move |system_context| {
+                let _ = system_context
+                    .world
+                    .insert(entity, (render_target, render_pass, viewport));
+                if let Ok(minimap_camera) = system_context
+                    .world
+                    .query_one_mut::<&mut MinimapCamera>(entity)
+                {
+                    minimap_camera.surface_size = surface_size;
+                }
+            });
+            return;
+        }
+        let Some(rx_mouse) = rx_mouse.update_value(Option::take).flatten()
+        else {
+            return;
+        };
+
+        tracing::debug!("spawning minimap camera");
+        let world2 = world.clone();
+        let spawned = world.run(move |system_context| {
+            system_context.world.spawn((
+                Label::new_static("minimap camera"),
+                Transform::default(),
+                CameraProjection::perspective(surface_size.aspect(), PI / 3.0, 0.1, 1000.),
+                ClearColor::new(Srgb::new(0.01, 0.01, 0.03).with_alpha(1.0)),
+                // the minimap doesn't show gizmos, e.g. map overlays.
+                RenderLayers::DEFAULT,
+                MinimapCamera {
+                    mouse_input: rx_mouse,
+                    surface_size,
+                    height: MinimapCamera::DEFAULT_HEIGHT,
+                },
+                render_target,
+                render_pass,
+                viewport,
+            ))
+        });
+        spawn_local(async move {
+            let entity = spawned.await;
+            if camera_entity.try_set(Some(entity)).is_some() {
+                // the minimap was removed in the meantime.
+                let _ = world2.run(move |system_context| {
+                    let _ = system_context.world.despawn(entity);
+                });
+            }
+        });
+    });
+
+    create_effect(move |_| {
+        let position = position.get();
+        if let (Some(entity), Some(parent)) = (camera_entity.get(), parent.get_untracked()) {
+            let world = expect_context::<WorldServer>();
+            let _ = world.run(move |system_context| {
+                let _ = system_context
+                    .world
+                    .insert_one(entity, Viewport { parent, position });
+            });
+        }
+    });
+
+    let element_visibility = use_element_visibility(node_ref);
+    let document_visibility = use_document_visibility();
+    create_effect(move |_| {
+        let visible =
+            element_visibility.get() && document_visibility.get() == VisibilityState::Visible;
+        if let Some(entity) = camera_entity.get() {
+            let world = expect_context::<WorldServer>();
+            let _ = world.run(move |system_context| {
+                if visible {
+                    let _ = system_context.world.remove_one::<DontRender>(entity);
+                }
+                else {
+                    let _ = system_context.world.insert_one(entity, DontRender);
+                }
+            });
+        }
+    });
+
+    on_cleanup(move || {
+        if let Some(entity) = camera_entity.get_untracked() {
+            let world = expect_context::<WorldServer>();
+            let _ = world.run(move |system_context| {
+                let _ = system_context.world.despawn(entity);
+            });
+        }
+    });
+
+    let on_mouse_input = move |event: &web_sys::Event, mouse_event: Option<MouseEvent>| {
+        if let Some(mouse_event) = mouse_event {
+            let _ = tx_mouse.try_send(Timestamped::new(mouse_event, Timestamp::from_websys(event)));
+        }
+    };
+
+    view! {
+        <div
+            node_ref=node_ref
+            class=Style::minimap
+            role="application"
+            aria-label=t!("minimap-label")
+            on:mousedown={
+                let on_mouse_input = on_mouse_input.clone();
+                move |event| on_mouse_input(&event, MouseEvent::from_websys_mouse_down(&event))
+            }
+            on:wheel=move |event| on_mouse_input(&event, MouseEvent::from_websys_wheel(&event))
+        >
+            <div class=Style::crosshair aria-hidden="true"></div>
+        </div>
+    }
+}
+
+#[derive(Debug)]
+struct MinimapCamera {
+    mouse_input: mpsc::Receiver<Timestamped<MouseEvent>>,
+    surface_size: SurfaceSize,
+
+    /// Height of the minimap camera above the target.
+    height: f32,
+}
+
+impl MinimapCamera {
+    const DEFAULT_HEIGHT: f32 = 50.0;
+    const MIN_HEIGHT: f32 = 5.0;
+    const MAX_HEIGHT: f32 = 500.0;
+
+    /// Returns the point on the XZ-plane that is shown at `position` (in
+    /// pixels) in the minimap.
+    fn pick(
+        &self,
+        position: Point2<f32>,
+        transform: &Transform,
+        camera_projection: &CameraProjection,
+    ) -> Option<Point3<f32>> {
+        let (near, far) = camera_projection.screen_to_ray(
+            &transform.model_matrix,
+            &position,
+            self.surface_size,
+        )?;
+
+        let direction = far - near;
+        if direction.y.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = -near.y / direction.y;
+        Some(near + direction * t)
+    }
+}
+
+fn minimap_system(system_context: &mut SystemContext) {
+    let Some((target_entity, target_position)) = system_context
+        .world
+        .query_mut::<&Transform>()
+        .with::<&MinimapTarget>()
+        .into_iter()
+        .next()
+        .map(|(entity, transform)| {
+            (
+                entity,
+                transform.model_matrix.transform_point(&Point3::origin()),
+            )
+        })
+    else {
+        return;
+    };
+
+    let mut navigate_to = None;
+
+    for (_, (minimap_camera, transform, camera_projection)) in
+        system_context
+            .world
+            .query_mut::<(&mut MinimapCamera, &mut Transform, &CameraProjection)>()
+    {
+        while let Ok(Timestamped { event, .. }) = minimap_camera.mouse_input.try_recv() {
+            match event {
+                MouseEvent::ButtonDown {
+                    button: MouseButton::Left,
+                    position,
+                    ..
+                } => {
+                    navigate_to = minimap_camera.pick(position, transform, camera_projection);
+                }
+                MouseEvent::Wheel { delta, .. } => {
+                    minimap_camera.height = (minimap_camera.height * (1.0 + delta.y / 1000.0))
+                        .clamp(MinimapCamera::MIN_HEIGHT, MinimapCamera::MAX_HEIGHT);
+                }
+                _ => {}
+            }
+        }
+
+        let center = Point3::new(target_position.x, 0.0, target_position.z);
+        *transform = Transform::look_at(
+            center + Vector3::y() * minimap_camera.height,
+            center,
+            -Vector3::z(),
+        );
+    }
+
+    if let Some(navigate_to) = navigate_to {
+        tracing::debug!(?navigate_to, "navigating to point on minimap");
+
+        if let Ok(mut transform) = system_context.world.get::<&mut Transform>(target_entity) {
+            transform.model_matrix.isometry.translation.x = navigate_to.x;
+            transform.model_matrix.isometry.translation.z = navigate_to.z;
+        }
+    }
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.schedule.add_system(minimap_system);
+    }
+}
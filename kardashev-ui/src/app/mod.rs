@@ -1,20 +1,59 @@
+mod accessibility;
+mod bookmarks;
 mod components;
 mod config;
+mod dashboard;
+mod diplomacy;
+mod file_drop;
+mod heatmap;
+mod hot_reload;
+mod inspector;
+mod journal;
+mod layers;
+mod layout;
+mod leaderboard;
+mod measure;
+mod minimap;
+mod orders;
+mod profiling;
+mod regions;
+mod replay;
+mod search;
+mod settings;
+mod settings_sync;
+mod star_labels;
+mod startup;
+mod stress;
+mod undo;
 mod world_view;
 
 use core::str;
-use std::f32::consts::PI;
+use std::{
+    collections::HashSet,
+    f32::consts::PI,
+};
 
-use components::window::provide_graphics;
+use components::{
+    dock::Dock,
+    icon::provide_icons,
+    panel::Workspace,
+    toast::Toasts,
+    window::provide_graphics,
+};
 use kardashev_client::ApiClient;
 use kardashev_protocol::asset_id;
 use kardashev_style::style;
 use leptos::{
     component,
+    create_effect,
     expect_context,
     provide_context,
     view,
+    ErrorBoundary,
+    Errors,
     IntoView,
+    RwSignal,
+    SignalWith,
 };
 use leptos_meta::provide_meta_context;
 use leptos_router::Router;
@@ -26,20 +65,69 @@ use palette::Srgb;
 
 use crate::{
     app::{
+        accessibility::{
+            provide_accessibility,
+            Accessibility,
+        },
+        bookmarks::provide_bookmarks,
         config::{
             provide_config,
             Config,
             Urls,
         },
-        world_view::{
-            MapPlugin,
-            WorldView,
+        file_drop::FileDrop,
+        heatmap::HeatmapPlugin,
+        hot_reload::{
+            provide_hot_reload,
+            BuildErrorOverlay,
+        },
+        journal::provide_session,
+        layers::{
+            provide_map_layers,
+            MapLayersPlugin,
         },
+        layout::provide_layout,
+        measure::MeasurePlugin,
+        minimap::MinimapPlugin,
+        regions::RegionOverlayPlugin,
+        settings::provide_settings,
+        settings_sync::provide_settings_sync,
+        star_labels::StarLabelsPlugin,
+        startup::{
+            provide_startup,
+            LoadingScreen,
+            Phase,
+            Startup,
+        },
+        undo::provide_undo_stack,
+        world_view::MapPlugin,
     },
     assets::{
         load::Load,
+        server::{
+            AssetServer,
+            ManifestState,
+        },
         system::AssetsPlugin,
     },
+    audio::{
+        music::{
+            provide_music,
+            MusicPlugin,
+        },
+        resume_audio_on_interaction,
+        AudioPlugin,
+    },
+    collision::{
+        Collider,
+        CollisionLayers,
+        CollisionPlugin,
+    },
+    colors::ColorsPlugin,
+    crash_report::{
+        provide_crash_reporting,
+        report_error,
+    },
     ecs::{
         server::WorldServer,
         system::SystemContext,
@@ -52,6 +140,7 @@ use crate::{
             PointLight,
         },
         material::Material,
+        material_pipeline::MaterialRegistry,
         mesh::{
             shape,
             Mesh,
@@ -59,10 +148,33 @@ use crate::{
             Meshable,
         },
         pbr::PbrMaterial,
+        render_3d::Render3dSettings,
         transform::Transform,
         RenderPlugin,
     },
-    input::InputPlugin,
+    i18n::{
+        provide_i18n,
+        I18nPlugin,
+    },
+    input::{
+        actions::provide_action_map,
+        InputPlugin,
+    },
+    notifications::Notifications,
+    raycast::RaycastPlugin,
+    selection::{
+        Selectable,
+        SelectionPlugin,
+    },
+    t,
+    tween::TweenPlugin,
+    universe::{
+        entity_map::EntityMapPlugin,
+        fleet::FleetPlugin,
+        region::RegionPlugin,
+        star::StarPlugin,
+    },
+    utils::futures::spawn_local,
 };
 
 #[style(path = "src/app/app.scss")]
@@ -74,10 +186,30 @@ pub fn App() -> impl IntoView {
     let urls = Urls::default();
     tracing::info!(?urls, "endpoints");
 
+    provide_startup();
+
+    let (notifications, notifications_receiver) = Notifications::new();
+    provide_context(notifications);
+    provide_action_map();
+
     provide_meta_context();
     provide_config();
     provide_graphics();
     provide_world();
+    provide_icons();
+    resume_audio_on_interaction();
+    provide_undo_stack();
+    provide_session();
+    provide_bookmarks();
+    provide_i18n();
+    provide_crash_reporting();
+    provide_settings();
+    provide_accessibility();
+    provide_layout();
+    provide_music();
+    provide_map_layers();
+    provide_settings_sync();
+    provide_hot_reload();
 
     /*let (log_level, _, _) = use_local_storage::<Option<tracing::Level>, OptionCodec<FromToStringCodec>>("log-level");
     create_effect(move |_| {
@@ -89,39 +221,132 @@ pub fn App() -> impl IntoView {
     view! {
         <Router>
             <div class=Style::app>
-                //<Dock />
+                <Dock />
                 <main class=Style::main>
-                    /*<Routes>
-                        <Route path="/" view=|| view!{ <Redirect path="/dashboard"/> } />
-                        <Route path="/dashboard" view=|| view!{ "TODO: Dashboard" } />
-                        <Route path="/map" view=Map />
-                    </Routes>*/
-                    <WorldView />
+                    <ErrorBoundary fallback=error_fallback>
+                        <Workspace />
+                    </ErrorBoundary>
                 </main>
+                <Toasts receiver=notifications_receiver />
+                <FileDrop />
+                <LoadingScreen />
+                <BuildErrorOverlay />
             </div>
         </Router>
     }
 }
 
+/// Shown instead of the workspace if a view fails, and reports the errors.
+fn error_fallback(errors: RwSignal<Errors>) -> impl IntoView {
+    // the signal still contains the errors that were reported, when another one is
+    // added.
+    create_effect(move |reported: Option<HashSet<String>>| {
+        let mut reported = reported.unwrap_or_default();
+        errors.with(|errors| {
+            for (_, error) in errors.iter() {
+                if reported.insert(error.to_string()) {
+                    report_error(&**error);
+                }
+            }
+        });
+        reported
+    });
+
+    view! {
+        <div class=Style::error_fallback role="alert">
+            {t!("crash-report-view-failed")}
+        </div>
+    }
+}
+
 fn provide_world() {
-    let Config { urls, .. } = expect_context();
+    let Config {
+        graphics,
+        urls,
+        faction,
+        world,
+        content_packs,
+        ..
+    } = expect_context();
     let urls = urls.unwrap_or_default();
     let asset_url = urls.asset_url;
     let api_url = urls.api_url;
-    let api_client = ApiClient::new(api_url);
+    let mut api_client = ApiClient::new(api_url);
+    if let Some(faction) = faction {
+        api_client = api_client.with_faction(faction);
+    }
+    if let Some(world) = world {
+        api_client = api_client.with_world(world);
+    }
     provide_context(api_client.clone());
+    let notifications = expect_context::<Notifications>();
+
+    // custom materials are added here with `with_material`. the world view
+    // draws them in addition to the built-in materials.
+    let materials = MaterialRegistry::default();
 
     tracing::debug!("creating world");
     let world = WorldServer::builder()
         .with_resource(api_client)
-        .with_plugin(AssetsPlugin::from_url(asset_url))
+        .with_resource(Accessibility::default())
+        .with_resource(Render3dSettings {
+            depth_prepass: graphics.depth_prepass,
+        })
+        .with_resource(notifications)
+        .with_plugin(AssetsPlugin::from_url(asset_url.clone()).with_content_packs(content_packs))
         .with_plugin(InputPlugin::default())
-        .with_plugin(RenderPlugin)
+        .with_plugin(RenderPlugin::default().with_materials(materials.clone()))
+        .with_plugin(I18nPlugin)
+        .with_plugin(AudioPlugin)
+        .with_plugin(MusicPlugin::from_url(asset_url))
         .with_plugin(MapPlugin)
+        .with_plugin(MinimapPlugin)
+        .with_plugin(MeasurePlugin)
+        .with_plugin(SelectionPlugin)
+        .with_plugin(RaycastPlugin)
+        .with_plugin(CollisionPlugin::default())
+        .with_plugin(ColorsPlugin)
+        .with_plugin(TweenPlugin::default())
+        .with_plugin(EntityMapPlugin)
+        .with_plugin(StarPlugin)
+        .with_plugin(FleetPlugin)
+        .with_plugin(RegionPlugin)
+        .with_plugin(RegionOverlayPlugin)
+        .with_plugin(StarLabelsPlugin)
+        .with_plugin(MapLayersPlugin)
+        .with_plugin(HeatmapPlugin)
         .with_startup_system(create_world)
         .build();
 
+    watch_asset_manifest(&world);
+
     provide_context(world);
+    provide_context(materials);
+}
+
+/// Reports the download of the asset manifest as the [assets
+/// phase](Phase::Assets) of the startup.
+fn watch_asset_manifest(world: &WorldServer) {
+    let startup = expect_context::<Startup>();
+    let asset_server = world.run(|system_context| {
+        system_context
+            .resources
+            .get::<AssetServer>()
+            .cloned()
+            .expect("missing AssetServer resource")
+    });
+
+    spawn_local(async move {
+        let asset_server = asset_server.await;
+        startup.watch(Phase::Assets, asset_server.manifest_state(), |state| {
+            match state {
+                ManifestState::Loading => None,
+                ManifestState::Loaded => Some(Ok(())),
+                ManifestState::Failed(error) => Some(Err(error.clone())),
+            }
+        });
+        startup.on_retry(Phase::Assets, move || asset_server.retry_manifest());
+    });
 }
 
 fn create_world(system_context: &mut SystemContext) {
@@ -139,6 +364,8 @@ fn create_world(system_context: &mut SystemContext) {
         )),
         Load::<Material<PbrMaterial>>::new(asset_id!("4eef57a3-9df8-4fa1-939f-109c3b02f9f0")),
         Label::new_static("star"),
+        Selectable::new(1.0),
+        Collider::sphere(1.0).with_layers(CollisionLayers::PLANETS, CollisionLayers::ALL),
         PointLight::new(SUN_LIGHT_COLOR),
     ));
 
@@ -151,6 +378,8 @@ fn create_world(system_context: &mut SystemContext) {
         )),
         Load::<Material<PbrMaterial>>::new(asset_id!("d5b74211-70fb-4b4c-9199-c5aa89b90b01")),
         Label::new_static("earth"),
+        Selectable::new(1.0),
+        Collider::sphere(1.0).with_layers(CollisionLayers::PLANETS, CollisionLayers::ALL),
     ));
 
     system_context.resources.insert(AmbientLight {
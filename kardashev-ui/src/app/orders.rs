@@ -0,0 +1,543 @@
+//! Panels for fleets and their order queues.
+//!
+//! Changes to an order queue are shown right away, and can be undone, see
+//! [`UndoStack`](super::undo::UndoStack).
+
+use std::collections::HashMap;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use futures::future::LocalBoxFuture;
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    model::{
+        fleet::{
+            predict_orders,
+            Fleet,
+            FleetId,
+            Order,
+            OrderId,
+            OrderKind,
+            OrderPrediction,
+        },
+        star::StarId,
+    },
+    uuid::Uuid,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+    SignalWithUntracked,
+};
+use nalgebra::Point3;
+
+use crate::{
+    app::{
+        components::icon::{
+            icons,
+            Icon,
+        },
+        undo::{
+            use_undo_stack,
+            Command,
+        },
+    },
+    ecs::{
+        server::WorldServer,
+        Label,
+    },
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    selection::{
+        select,
+        use_selection,
+        SelectionMode,
+    },
+    t,
+    universe::{
+        entity_map::EntityMap,
+        fleet::{
+            FleetEntity,
+            FleetRoute,
+        },
+    },
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/orders.scss")]
+struct Style;
+
+/// Lists the fleets in the world. Clicking on a fleet selects it.
+#[component]
+pub fn FleetList() -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let selection = use_selection();
+
+    let fleets = create_local_resource(
+        || (),
+        move |_| {
+            world.get_value().run(|system_context| {
+                let mut fleets = system_context
+                    .world
+                    .query_mut::<(&FleetEntity, &Label)>()
+                    .into_iter()
+                    .map(|(entity, (_, label))| (entity, label.label.to_string()))
+                    .collect::<Vec<_>>();
+                fleets.sort_by(|(_, a), (_, b)| a.cmp(b));
+                fleets
+            })
+        },
+    );
+
+    let is_selected = move |entity| {
+        selection.with(|selection| {
+            selection
+                .selected
+                .iter()
+                .any(|selected| selected.entity == entity)
+        })
+    };
+
+    let on_click = move |entity, shift: bool| {
+        let mode = if shift {
+            SelectionMode::Toggle
+        }
+        else {
+            SelectionMode::Replace
+        };
+        let _ = world.get_value().run(move |system_context| {
+            select(system_context.world, [entity], mode);
+        });
+    };
+
+    view! {
+        <div class=Style::fleet_list>
+            <button class=Style::button title=t!("fleet-list-refresh") on:click=move |_| fleets.refetch()>
+                <Icon icon=icons::ARROW_CLOCKWISE alt=t!("fleet-list-refresh") />
+            </button>
+            <Show
+                when=move || fleets.with(|fleets| fleets.as_ref().map_or(false, |fleets| !fleets.is_empty()))
+                fallback=|| view! { <p class=Style::empty>{t!("fleet-list-empty")}</p> }
+            >
+                <ul class=Style::list>
+                    <For
+                        each=move || fleets.get().unwrap_or_default()
+                        key=|(entity, _)| *entity
+                        children=move |(entity, name)| {
+                            let class = move || {
+                                if is_selected(entity) {
+                                    format!("{} {}", Style::item, Style::selected)
+                                }
+                                else {
+                                    Style::item.to_owned()
+                                }
+                            };
+                            view! {
+                                <li class=class>
+                                    <button
+                                        class=Style::fleet
+                                        aria-pressed=move || is_selected(entity).to_string()
+                                        on:click=move |event| on_click(entity, event.shift_key())
+                                    >
+                                        {name}
+                                    </button>
+                                </li>
+                            }
+                        }
+                    />
+                </ul>
+            </Show>
+        </div>
+    }
+}
+
+/// Star that can be targeted by orders.
+#[derive(Clone, Debug)]
+struct StarInfo {
+    id: StarId,
+    name: String,
+    position: Point3<f32>,
+}
+
+/// Shows and edits the order queue of the selected fleet.
+///
+/// Arrival times are predicted with [`predict_orders`], the same model the
+/// server uses to validate the queue.
+#[component]
+pub fn OrderQueue() -> impl IntoView {
+    let world = expect_context::<WorldServer>();
+    let api_client = expect_context::<ApiClient>();
+    let notifications = expect_context::<Notifications>();
+    let selection = use_selection();
+
+    let fleet = create_rw_signal(None::<Fleet>);
+
+    // fetch the first selected fleet, whenever the selection changes.
+    create_effect({
+        let api_client = api_client.clone();
+        let notifications = notifications.clone();
+        move |_| {
+            let entities = selection.with(|selection| {
+                selection
+                    .selected
+                    .iter()
+                    .map(|selected| selected.entity)
+                    .collect::<Vec<_>>()
+            });
+            let world = world.clone();
+            let api_client = api_client.clone();
+            let notifications = notifications.clone();
+
+            spawn_local(async move {
+                let fleet_id = world
+                    .run(move |system_context| {
+                        entities.iter().find_map(|entity| {
+                            system_context
+                                .world
+                                .get::<&FleetEntity>(*entity)
+                                .ok()
+                                .map(|fleet| fleet.id)
+                        })
+                    })
+                    .await;
+
+                let Some(fleet_id) = fleet_id
+                else {
+                    fleet.set(None);
+                    return;
+                };
+                if fleet.with_untracked(|fleet| fleet.as_ref().map(|fleet| fleet.id))
+                    == Some(fleet_id)
+                {
+                    return;
+                }
+
+                match api_client.get_fleet(fleet_id).await {
+                    Ok(loaded) => fleet.set(Some(loaded)),
+                    Err(error) => {
+                        tracing::error!(?fleet_id, %error, "failed to load fleet");
+                        notifications.notify(
+                            Notification::error("notification-fleet-load-failed")
+                                .with_message(error.to_string()),
+                        );
+                    }
+                }
+            });
+        }
+    });
+
+    let stars = create_local_resource(|| (), {
+        let api_client = api_client.clone();
+        move |_| {
+            let api_client = api_client.clone();
+            async move {
+                let stars = api_client
+                    .get_stars()
+                    .await
+                    .inspect_err(|error| tracing::error!(%error, "failed to load stars"))
+                    .ok()?;
+                let mut stars = stars
+                    .into_iter()
+                    .filter_map(|star| {
+                        Some(StarInfo {
+                            id: star.id,
+                            name: star.name?,
+                            position: star.position,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                stars.sort_by(|a, b| a.name.cmp(&b.name));
+                Some(stars)
+            }
+        }
+    });
+    let star_lookup = move || {
+        stars
+            .get()
+            .flatten()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|star| (star.id, star))
+            .collect::<HashMap<_, _>>()
+    };
+
+    let predictions = move || -> Option<Vec<OrderPrediction>> {
+        let stars = star_lookup();
+        fleet.with(|fleet| {
+            let fleet = fleet.as_ref()?;
+            predict_orders(
+                fleet.position,
+                fleet.speed,
+                Utc::now(),
+                fleet.orders.iter().map(|order| &order.kind),
+                |star| stars.get(&star).map(|star| star.position),
+            )
+            .ok()
+        })
+    };
+
+    let world = store_value(expect_context::<WorldServer>());
+    let api_client = store_value(api_client);
+    let undo_stack = use_undo_stack();
+    let edit_orders = move |edit: Box<dyn FnOnce(&mut Vec<OrderKind>)>| {
+        let Some((fleet_id, previous)) = fleet.with_untracked(|fleet| {
+            fleet.as_ref().map(|fleet| {
+                (
+                    fleet.id,
+                    fleet
+                        .orders
+                        .iter()
+                        .map(|order| order.kind)
+                        .collect::<Vec<_>>(),
+                )
+            })
+        })
+        else {
+            return;
+        };
+        let mut orders = previous.clone();
+        edit(&mut orders);
+
+        undo_stack.execute(SetOrders {
+            world: world.get_value(),
+            api_client: api_client.get_value(),
+            fleet,
+            fleet_id,
+            orders,
+            previous,
+        });
+    };
+
+    let rows = move || {
+        let predictions = predictions();
+        let stars = star_lookup();
+        fleet.with(|fleet| {
+            let Some(fleet) = fleet
+            else {
+                return vec![];
+            };
+            let num_orders = fleet.orders.len();
+            fleet
+                .orders
+                .iter()
+                .enumerate()
+                .map(|(index, order)| {
+                    let prediction = predictions
+                        .as_ref()
+                        .and_then(|predictions| predictions.get(index).copied());
+                    let star = order.kind.star();
+                    let star_name = stars
+                        .get(&star)
+                        .map(|star| star.name.clone())
+                        .unwrap_or_else(|| star.0.to_string());
+                    let kind = match order.kind {
+                        OrderKind::Move { .. } => t!("order-move"),
+                        OrderKind::Survey { .. } => t!("order-survey"),
+                        OrderKind::Colonize { .. } => t!("order-colonize"),
+                    };
+
+                    view! {
+                        <li class=Style::order>
+                            <span class=Style::order_kind>{kind}</span>
+                            <span class=Style::order_star>{star_name}</span>
+                            <span class=Style::order_eta>
+                                {prediction.map(|prediction| format_time(prediction.arrival))}
+                            </span>
+                            <button
+                                class=Style::button
+                                title=t!("order-move-up")
+                                disabled={index == 0}
+                                on:click=move |_| edit_orders(Box::new(move |orders| orders.swap(index - 1, index)))
+                            >
+                                <Icon icon=icons::ARROW_UP alt=t!("order-move-up") />
+                            </button>
+                            <button
+                                class=Style::button
+                                title=t!("order-move-down")
+                                disabled={index + 1 == num_orders}
+                                on:click=move |_| edit_orders(Box::new(move |orders| orders.swap(index, index + 1)))
+                            >
+                                <Icon icon=icons::ARROW_DOWN alt=t!("order-move-down") />
+                            </button>
+                            <button
+                                class=Style::button
+                                title=t!("order-remove")
+                                on:click=move |_| edit_orders(Box::new(move |orders| { orders.remove(index); }))
+                            >
+                                <Icon icon=icons::X alt=t!("order-remove") />
+                            </button>
+                        </li>
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let new_order_kind = create_rw_signal("move".to_owned());
+    let new_order_star = create_rw_signal(None::<StarId>);
+    let add_order = move |_| {
+        let Some(star) = new_order_star.get_untracked()
+        else {
+            return;
+        };
+        let order = match new_order_kind.get_untracked().as_str() {
+            "survey" => OrderKind::Survey { star },
+            "colonize" => OrderKind::Colonize { star },
+            _ => OrderKind::Move { star },
+        };
+        edit_orders(Box::new(move |orders| orders.push(order)));
+    };
+
+    view! {
+        <div class=Style::order_queue>
+            <Show
+                when=move || fleet.with(|fleet| fleet.is_some())
+                fallback=|| view! { <p class=Style::empty>{t!("order-queue-no-fleet")}</p> }
+            >
+                <h3 class=Style::fleet_name>
+                    {move || fleet.with(|fleet| fleet.as_ref().map(|fleet| fleet.name.clone()))}
+                </h3>
+                <ol class=Style::list>{rows}</ol>
+                <div class=Style::add_order>
+                    <select
+                        aria-label=t!("order-kind")
+                        on:change=move |event| new_order_kind.set(event_target_value(&event))
+                    >
+                        <option value="move">{t!("order-move")}</option>
+                        <option value="survey">{t!("order-survey")}</option>
+                        <option value="colonize">{t!("order-colonize")}</option>
+                    </select>
+                    <select
+                        aria-label=t!("order-star")
+                        on:change=move |event| {
+                            new_order_star.set(event_target_value(&event).parse().ok().map(StarId))
+                        }
+                    >
+                        <option value="">{t!("order-star")}</option>
+                        <For
+                            each=move || stars.get().flatten().unwrap_or_default()
+                            key=|star| star.id
+                            children=|star| view! { <option value=star.id.0.to_string()>{star.name}</option> }
+                        />
+                    </select>
+                    <button class=Style::button on:click=add_order>{t!("order-add")}</button>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+/// Replaces the order queue of a fleet, or restores the previous one when it's
+/// undone.
+#[derive(Clone)]
+struct SetOrders {
+    world: WorldServer,
+    api_client: ApiClient,
+
+    /// The fleet shown in the order queue.
+    fleet: RwSignal<Option<Fleet>>,
+
+    fleet_id: FleetId,
+    orders: Vec<OrderKind>,
+    previous: Vec<OrderKind>,
+}
+
+impl SetOrders {
+    /// Shows the orders in the order queue and as the fleet's route on the map.
+    ///
+    /// The orders don't have IDs until the server responds, so they get the nil
+    /// ID until then.
+    fn show(&self, orders: Vec<Order>) {
+        let fleet_id = self.fleet_id;
+        let route = FleetRoute::from_orders(&orders);
+        let _ = self.world.run(move |system_context| {
+            let entity = system_context
+                .resources
+                .get::<EntityMap>()
+                .and_then(|entity_map| entity_map.resolve(fleet_id));
+            if let Some(entity) = entity {
+                let _ = system_context.world.insert_one(entity, route);
+            }
+        });
+
+        self.fleet.try_update(|fleet| {
+            if let Some(fleet) = fleet.as_mut().filter(|fleet| fleet.id == fleet_id) {
+                fleet.orders = orders;
+            }
+        });
+    }
+
+    fn show_kinds(&self, orders: &[OrderKind]) {
+        self.show(
+            orders
+                .iter()
+                .map(|kind| {
+                    Order {
+                        id: OrderId(Uuid::nil()),
+                        kind: *kind,
+                    }
+                })
+                .collect(),
+        );
+    }
+}
+
+impl Command for SetOrders {
+    fn error_title(&self) -> &'static str {
+        "notification-orders-rejected"
+    }
+
+    fn apply(&self) {
+        self.show_kinds(&self.orders);
+    }
+
+    fn revert(&self) {
+        self.show_kinds(&self.previous);
+    }
+
+    fn send(&self, undo: bool) -> LocalBoxFuture<'static, Result<(), kardashev_client::Error>> {
+        let orders = if undo {
+            self.previous.clone()
+        }
+        else {
+            self.orders.clone()
+        };
+        let this = self.clone();
+
+        Box::pin(async move {
+            let orders = this
+                .api_client
+                .set_fleet_orders(this.fleet_id, orders)
+                .await?;
+            // the server assigns the order IDs.
+            this.show(orders);
+            Ok(())
+        })
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&chrono::Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
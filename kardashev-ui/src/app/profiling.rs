@@ -0,0 +1,220 @@
+//! Overlay showing the render timings of the world view.
+//!
+//! The depth pre-pass can be toggled here, so that the timings with and
+//! without it can be compared. Whether it's enabled initially is set in the
+//! graphics config.
+//!
+//! It also toggles the [span timings](crate::utils::trace), and records traces
+//! that are downloaded when the recording is stopped.
+
+use std::time::Duration;
+
+use hecs::Entity;
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    expect_context,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    StoredValue,
+};
+
+use crate::{
+    app::components::icon::{
+        icons,
+        Icon,
+    },
+    ecs::server::WorldServer,
+    graphics::render_3d::{
+        PassTimings,
+        Render3dSettings,
+        Render3dTimings,
+    },
+    t,
+    utils::{
+        futures::spawn_local,
+        time::interval,
+        trace::{
+            self,
+            ChromeTrace,
+        },
+    },
+};
+
+#[style(path = "src/app/profiling.scss")]
+struct Style;
+
+/// How often the timings are updated.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[component]
+pub fn ProfilingOverlay(camera_entity: StoredValue<Option<Entity>>) -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let active = create_rw_signal(false);
+    let depth_prepass = create_rw_signal(false);
+    let timings = create_rw_signal(Render3dTimings::default());
+    let performance_marks = create_rw_signal(trace::marks_enabled());
+    let recording = create_rw_signal(false);
+
+    let join_handle = spawn_local(async move {
+        let enabled = world
+            .get_value()
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<Render3dSettings>()
+                    .map_or(false, |settings| settings.depth_prepass)
+            })
+            .await;
+        depth_prepass.set(enabled);
+
+        let mut interval = interval(UPDATE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !active.get_untracked() {
+                continue;
+            }
+            let Some(entity) = camera_entity.get_value()
+            else {
+                continue;
+            };
+            if let Ok(camera_timings) = world
+                .get_value()
+                .run_on_entity::<_, _, &Render3dTimings>(entity, |timings| timings.clone())
+                .await
+            {
+                timings.set(camera_timings);
+            }
+        }
+    });
+    on_cleanup(move || join_handle.abort());
+
+    let set_depth_prepass = move |value: bool| {
+        depth_prepass.set(value);
+        let _ = world.get_value().run(move |system_context| {
+            system_context
+                .resources
+                .get_mut_or_insert_default::<Render3dSettings>()
+                .depth_prepass = value;
+        });
+    };
+
+    let set_performance_marks = move |value: bool| {
+        performance_marks.set(value);
+        trace::set_marks_enabled(value);
+    };
+
+    let toggle_recording = move || {
+        if recording.get_untracked() {
+            recording.set(false);
+            if let Some(trace) = trace::stop_recording().filter(|trace| !trace.is_empty()) {
+                spawn_local(async move {
+                    if let Err(error) = trace.download(&ChromeTrace::default_filename()).await {
+                        tracing::error!(?error, "failed to download trace");
+                    }
+                });
+            }
+        }
+        else {
+            recording.set(true);
+            trace::start_recording();
+        }
+    };
+
+    let toggle_class = move || {
+        if active.get() {
+            format!("{} {}", Style::toggle, Style::active)
+        }
+        else {
+            Style::toggle.to_owned()
+        }
+    };
+
+    let record_trace = t!("profiling-record-trace");
+    let stop_trace = t!("profiling-stop-trace");
+    let with_depth_prepass = t!("profiling-with-depth-prepass");
+    let without_depth_prepass = t!("profiling-without-depth-prepass");
+    let row = move |label, timings: Option<PassTimings>| {
+        let frame_time = timings.and_then(|timings| timings.frame_time);
+        view! {
+            <tr>
+                <th>{label}</th>
+                <td>{format_millis(frame_time)}</td>
+                <td>{frame_time.map(|frame_time| format!("{:.0}", 1.0 / frame_time.as_secs_f32())).unwrap_or_else(|| "-".to_owned())}</td>
+                <td>{format_millis(timings.map(|timings| timings.record_time))}</td>
+            </tr>
+        }
+    };
+
+    view! {
+        <div class=Style::profiling>
+            <button
+                class=toggle_class
+                title=t!("profiling-toggle")
+                aria-pressed=move || active.get().to_string()
+                on:click=move |_| active.set(!active.get())
+            >
+                <Icon icon=icons::STOPWATCH />
+            </button>
+            <Show when=move || active.get()>
+                <div class=Style::timings>
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || depth_prepass.get()
+                            on:change=move |_| set_depth_prepass(!depth_prepass.get())
+                        />
+                        {t!("profiling-depth-prepass")}
+                    </label>
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || performance_marks.get()
+                            on:change=move |_| set_performance_marks(!performance_marks.get())
+                        />
+                        {t!("profiling-performance-marks")}
+                    </label>
+                    <button on:click=move |_| toggle_recording()>
+                        {move || {
+                            if recording.get() {
+                                stop_trace.get()
+                            }
+                            else {
+                                record_trace.get()
+                            }
+                        }}
+                    </button>
+                    <table>
+                        <tr>
+                            <th></th>
+                            <th>{t!("profiling-frame-time")}</th>
+                            <th>{t!("profiling-fps")}</th>
+                            <th>{t!("profiling-record-time")}</th>
+                        </tr>
+                        {move || {
+                            let timings = timings.get();
+                            view! {
+                                {row(with_depth_prepass, timings.with_depth_prepass)}
+                                {row(without_depth_prepass, timings.without_depth_prepass)}
+                            }
+                        }}
+                    </table>
+                </div>
+            </Show>
+        </div>
+    }
+}
+
+fn format_millis(duration: Option<Duration>) -> String {
+    duration.map_or_else(
+        || "-".to_owned(),
+        |duration| format!("{:.2} ms", duration.as_secs_f32() * 1000.0),
+    )
+}
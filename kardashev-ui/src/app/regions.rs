@@ -0,0 +1,192 @@
+//! Map overlay showing the regions' outlines and names.
+//!
+//! The outlines are drawn by the renderer, see
+//! [`RegionPlugin`](crate::universe::region::RegionPlugin), and belong to the
+//! [`MapLayer::Regions`] layer. The names are HTML elements placed over the
+//! world view at the regions' projected centers, and are shown with the
+//! [`MapLayer::Labels`] layer.
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    expect_context,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    SignalGet,
+    SignalSet,
+};
+use nalgebra::Point3;
+use tokio::sync::watch;
+
+use crate::{
+    app::{
+        layers::{
+            MapLayer,
+            MapLayers,
+            OnLayer,
+        },
+        minimap::MinimapTarget,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        camera::CameraProjection,
+        transform::Transform,
+    },
+    universe::region::RegionEntity,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/regions.scss")]
+struct Style;
+
+/// Resource with the state of the region overlay.
+#[derive(Debug)]
+pub struct RegionOverlay {
+    tx_labels: watch::Sender<Vec<RegionLabel>>,
+}
+
+/// Name of a region, and where to show it relative to the world view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionLabel {
+    pub name: String,
+
+    /// Position in percent of the world view's size.
+    pub left: f32,
+    pub top: f32,
+}
+
+fn region_overlay_system(system_context: &mut SystemContext) {
+    let untagged = system_context
+        .world
+        .query_mut::<()>()
+        .with::<&RegionEntity>()
+        .without::<&OnLayer>()
+        .into_iter()
+        .map(|(entity, ())| entity)
+        .collect::<Vec<_>>();
+    for entity in untagged {
+        system_context
+            .command_buffer
+            .insert_one(entity, OnLayer(MapLayer::Regions));
+    }
+
+    let visible = system_context
+        .resources
+        .get::<MapLayers>()
+        .map_or(true, |layers| layers.settings.is_visible(MapLayer::Labels));
+
+    let Some(overlay) = system_context.resources.get::<RegionOverlay>()
+    else {
+        return;
+    };
+
+    let mut labels = vec![];
+    if visible {
+        let camera = system_context
+            .world
+            .query_mut::<(&Transform, &CameraProjection)>()
+            .with::<&MinimapTarget>()
+            .into_iter()
+            .next()
+            .map(|(_, (transform, projection))| (transform.model_matrix, *projection));
+
+        if let Some((camera_transform, projection)) = camera {
+            for (_, (transform, label)) in system_context
+                .world
+                .query_mut::<(&Transform, &Label)>()
+                .with::<&RegionEntity>()
+            {
+                let center = transform.model_matrix.transform_point(&Point3::origin());
+                let Some(position) = projection.project_to_normalized(&camera_transform, &center)
+                else {
+                    continue;
+                };
+                if (0.0..=1.0).contains(&position.x) && (0.0..=1.0).contains(&position.y) {
+                    labels.push(RegionLabel {
+                        name: label.label.to_string(),
+                        left: 100.0 * position.x,
+                        top: 100.0 * position.y,
+                    });
+                }
+            }
+        }
+    }
+
+    overlay.tx_labels.send_if_modified(|current| {
+        if *current != labels {
+            *current = labels;
+            true
+        }
+        else {
+            false
+        }
+    });
+}
+
+/// Region names over the world view.
+#[component]
+pub fn RegionLabels() -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let labels = create_rw_signal(Vec::<RegionLabel>::new());
+
+    let join_handle = spawn_local(async move {
+        let mut rx_labels = world
+            .get_value()
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<RegionOverlay>()
+                    .map(|overlay| overlay.tx_labels.subscribe())
+            })
+            .await?;
+
+        loop {
+            labels.set(rx_labels.borrow_and_update().clone());
+            if rx_labels.changed().await.is_err() {
+                break;
+            }
+        }
+
+        Some(())
+    });
+    on_cleanup(move || join_handle.abort());
+
+    view! {
+        <div class=Style::labels aria-hidden="true">
+            {move || {
+                labels
+                    .get()
+                    .into_iter()
+                    .map(|label| {
+                        view! {
+                            <span class=Style::label style=format!("left: {}%; top: {}%;", label.left, label.top)>
+                                {label.name}
+                            </span>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+        </div>
+    }
+}
+
+pub struct RegionOverlayPlugin;
+
+impl Plugin for RegionOverlayPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let (tx_labels, _rx) = watch::channel(vec![]);
+        context.resources.insert(RegionOverlay { tx_labels });
+        context.schedule.add_system(region_overlay_system);
+    }
+}
@@ -0,0 +1,322 @@
+//! Replay of the world's history.
+//!
+//! The panel lists the snapshots the server recorded, and plays them back over
+//! a [`Replay`](kardashev_client::Replay) stream. While a replay runs, the
+//! fleets in the world are the ones of the current snapshot. When it's stopped,
+//! or the panel is closed, the live fleets are loaded again.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_client::ApiClient;
+use kardashev_protocol::replay::{
+    ReplayControl,
+    ReplayEvent,
+    ReplayQuery,
+    WorldSnapshot,
+    DEFAULT_SPEED,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_local_resource,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    Show,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    app::components::icon::{
+        icons,
+        Icon,
+    },
+    ecs::server::WorldServer,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    t,
+    universe::fleet::{
+        load_fleets,
+        replace_fleets,
+    },
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/replay.scss")]
+struct Style;
+
+/// Snapshots per second that can be picked.
+const SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 5.0, 10.0];
+
+/// The snapshot that is shown.
+#[derive(Clone, Debug, PartialEq)]
+struct Frame {
+    epoch: u64,
+    taken_at: DateTime<Utc>,
+    num_fleets: usize,
+    num_colonies: usize,
+}
+
+#[component]
+pub fn ReplayPanel() -> impl IntoView {
+    let api_client = expect_context::<ApiClient>();
+    let world = expect_context::<WorldServer>();
+    let notifications = expect_context::<Notifications>();
+
+    let snapshots = create_local_resource(|| (), {
+        let api_client = api_client.clone();
+        move |_| {
+            let api_client = api_client.clone();
+            async move {
+                api_client
+                    .get_snapshots()
+                    .await
+                    .inspect_err(|error| tracing::error!(%error, "failed to load snapshots"))
+                    .unwrap_or_default()
+            }
+        }
+    });
+    let first_epoch = move || {
+        snapshots.with(|snapshots| {
+            snapshots
+                .as_ref()
+                .and_then(|snapshots| snapshots.first())
+                .map(|snapshot| snapshot.epoch)
+        })
+    };
+    let last_epoch = move || {
+        snapshots.with(|snapshots| {
+            snapshots
+                .as_ref()
+                .and_then(|snapshots| snapshots.last())
+                .map(|snapshot| snapshot.epoch)
+        })
+    };
+
+    // the sender to the running replay, if there is one.
+    let controls = store_value(None::<mpsc::UnboundedSender<ReplayControl>>);
+    let active = create_rw_signal(false);
+    let playing = create_rw_signal(false);
+    let speed = create_rw_signal(DEFAULT_SPEED);
+    let frame = create_rw_signal(None::<Frame>);
+
+    let start = move |from: Option<u64>| {
+        let (tx_controls, mut rx_controls) = mpsc::unbounded_channel();
+        controls.set_value(Some(tx_controls));
+        active.set(true);
+        playing.set(true);
+
+        let api_client = api_client.clone();
+        let world = world.clone();
+        let notifications = notifications.clone();
+        let query = ReplayQuery {
+            from,
+            speed: Some(speed.get_untracked()),
+        };
+        spawn_local(async move {
+            match api_client.replay(&query).await {
+                Ok(mut replay) => {
+                    loop {
+                        tokio::select! {
+                            control = rx_controls.recv() => {
+                                let Some(control) = control
+                                else {
+                                    break;
+                                };
+                                if let Err(error) = replay.send(control).await {
+                                    tracing::warn!(%error, "replay disconnected");
+                                    break;
+                                }
+                            }
+                            event = replay.next() => {
+                                match event {
+                                    Ok(ReplayEvent::Snapshot { snapshot }) => {
+                                        frame.set(Some(show_snapshot(&world, snapshot)));
+                                    }
+                                    Ok(ReplayEvent::End) => playing.set(false),
+                                    Err(error) => {
+                                        tracing::warn!(%error, "replay disconnected");
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Err(error) => {
+                    tracing::error!(%error, "failed to start replay");
+                    notifications.notify(
+                        Notification::error("notification-replay-failed")
+                            .with_message(error.to_string()),
+                    );
+                }
+            }
+
+            // back to the live fleets.
+            let _ = world.run(load_fleets);
+            active.set(false);
+            playing.set(false);
+            frame.set(None);
+        });
+    };
+
+    let send = move |control: ReplayControl| {
+        controls.with_value(|controls| {
+            if let Some(controls) = controls {
+                let _ = controls.send(control);
+            }
+        });
+    };
+
+    // dropping the sender ends the replay.
+    let stop = move || controls.set_value(None);
+    on_cleanup(stop);
+
+    let toggle_playing = {
+        let start = start.clone();
+        move |_| {
+            if !active.get_untracked() {
+                start(None);
+            }
+            else if playing.get_untracked() {
+                send(ReplayControl::Pause);
+                playing.set(false);
+            }
+            else {
+                send(ReplayControl::Play);
+                playing.set(true);
+            }
+        }
+    };
+
+    let seek = move |event| {
+        let Ok(epoch) = event_target_value(&event).parse::<u64>()
+        else {
+            return;
+        };
+        if active.get_untracked() {
+            send(ReplayControl::Seek { epoch });
+        }
+        else {
+            start(Some(epoch));
+        }
+    };
+
+    let set_speed = move |event| {
+        let Ok(new_speed) = event_target_value(&event).parse::<f32>()
+        else {
+            return;
+        };
+        speed.set(new_speed);
+        send(ReplayControl::SetSpeed { speed: new_speed });
+    };
+
+    let play_icon = Signal::derive(move || {
+        if playing.get() {
+            icons::PAUSE
+        }
+        else {
+            icons::PLAY
+        }
+    });
+    let play_title = move || {
+        if playing.get() {
+            t!("replay-pause").get()
+        }
+        else {
+            t!("replay-play").get()
+        }
+    };
+
+    view! {
+        <div class=Style::replay>
+            <Show
+                when=move || first_epoch().is_some()
+                fallback=|| view! { <p class=Style::empty>{t!("replay-empty")}</p> }
+            >
+                <div class=Style::controls>
+                    <button class=Style::button title=play_title on:click=toggle_playing.clone()>
+                        <Icon icon=play_icon alt=Signal::derive(play_title) />
+                    </button>
+                    <input
+                        class=Style::timeline
+                        type="range"
+                        min=first_epoch
+                        max=last_epoch
+                        prop:value=move || {
+                            frame
+                                .with(|frame| frame.as_ref().map(|frame| frame.epoch))
+                                .or_else(first_epoch)
+                                .unwrap_or_default()
+                        }
+                        on:change=seek.clone()
+                    />
+                    <select class=Style::speed title=t!("replay-speed") on:change=set_speed>
+                        {SPEEDS
+                            .iter()
+                            .map(|option| {
+                                view! {
+                                    <option value=option.to_string() selected=move || speed.get() == *option>
+                                        {format!("{option}×")}
+                                    </option>
+                                }
+                            })
+                            .collect::<Vec<_>>()}
+                    </select>
+                    <Show when=move || active.get()>
+                        <button class=Style::button on:click=move |_| stop()>
+                            {t!("replay-live")}
+                        </button>
+                    </Show>
+                </div>
+                {move || {
+                    frame
+                        .get()
+                        .map(|frame| {
+                            let time = frame
+                                .taken_at
+                                .with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string();
+                            view! {
+                                <p class=Style::status>
+                                    {t!(
+                                        "replay-status",
+                                        epoch = frame.epoch,
+                                        time = time,
+                                        fleets = frame.num_fleets,
+                                        colonies = frame.num_colonies,
+                                    )}
+                                </p>
+                            }
+                        })
+                }}
+            </Show>
+        </div>
+    }
+}
+
+/// Replaces the fleets in the world with the snapshot's.
+fn show_snapshot(world: &WorldServer, snapshot: WorldSnapshot) -> Frame {
+    let frame = Frame {
+        epoch: snapshot.epoch,
+        taken_at: snapshot.taken_at,
+        num_fleets: snapshot.fleets.len(),
+        num_colonies: snapshot.colonies.len(),
+    };
+    let _ = world.run(move |system_context| replace_fleets(system_context, snapshot.fleets));
+    frame
+}
@@ -0,0 +1,202 @@
+//! Search box for stars, which flies the map camera to the chosen star and
+//! selects it once the camera arrived.
+
+use std::time::Duration;
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::star::{
+    StarId,
+    StarSearchResult,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    Show,
+    SignalGet,
+    SignalSet,
+    SignalWith,
+};
+use nalgebra::{
+    Isometry3,
+    Point3,
+    Vector3,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    app::world_view::fly_map_camera,
+    ecs::{
+        server::WorldServer,
+        system::SystemContext,
+    },
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    selection::{
+        select,
+        SelectionMode,
+    },
+    t,
+    tween::TweenEvents,
+    universe::entity_map::EntityMap,
+    utils::{
+        futures::spawn_local,
+        time::sleep,
+    },
+};
+
+#[style(path = "src/app/search.scss")]
+struct Style;
+
+/// Time to wait after the last keystroke before searching.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Maximum number of results shown.
+const LIMIT: u32 = 10;
+
+/// Distance from the star at which the camera stops.
+const FLY_TO_DISTANCE: f32 = 2.0;
+
+#[component]
+pub fn StarSearch() -> impl IntoView {
+    let api_client = expect_context::<ApiClient>();
+    let notifications = expect_context::<Notifications>();
+    let world = store_value(expect_context::<WorldServer>());
+
+    let query = create_rw_signal(String::new());
+    let results = create_rw_signal(Vec::<StarSearchResult>::new());
+    // incremented with every keystroke, so that only the latest search is shown.
+    let generation = store_value(0u64);
+
+    let on_input = move |event| {
+        let q = event_target_value(&event);
+        query.set(q.clone());
+        generation.update_value(|generation| *generation += 1);
+        let current = generation.get_value();
+
+        let api_client = api_client.clone();
+        let notifications = notifications.clone();
+        spawn_local(async move {
+            sleep(DEBOUNCE).await;
+            if generation.try_get_value() != Some(current) {
+                return;
+            }
+
+            if q.trim().is_empty() {
+                results.set(vec![]);
+                return;
+            }
+
+            match api_client.search_stars(&q, Some(LIMIT)).await {
+                Ok(found) => {
+                    if generation.try_get_value() == Some(current) {
+                        results.set(found);
+                    }
+                }
+                Err(error) => {
+                    tracing::error!(%error, "star search failed");
+                    notifications.notify(
+                        Notification::error("notification-star-search-failed")
+                            .with_message(error.to_string()),
+                    );
+                }
+            }
+        });
+    };
+
+    let fly_to = move |star_id: StarId, star: Point3<f32>| {
+        query.set(String::new());
+        results.set(vec![]);
+        let world = world.get_value();
+        spawn_local(async move {
+            let Some((mut events, tween_id)) = world
+                .run(move |system_context| {
+                    let events = system_context.resources.get::<TweenEvents>()?.subscribe();
+                    let tween_id = fly_map_camera(system_context, |camera| {
+                        // stop in front of the star, keeping the camera's orientation. the camera
+                        // looks along its negative Z axis.
+                        let backward = camera.rotation * Vector3::z();
+                        Isometry3::from_parts(
+                            (star + backward * FLY_TO_DISTANCE).into(),
+                            camera.rotation,
+                        )
+                    })?;
+                    Some((events, tween_id))
+                })
+                .await
+            else {
+                return;
+            };
+
+            // if the camera is sent somewhere else before it arrived, the star isn't
+            // selected.
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.id == tween_id => {
+                        if event.completed {
+                            let _ = world
+                                .run(move |system_context| select_star(system_context, star_id));
+                        }
+                        break;
+                    }
+                    Err(RecvError::Closed) => break,
+                    _ => {}
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class=Style::search role="search">
+            <input
+                type="search"
+                class=Style::input
+                placeholder=t!("star-search-placeholder")
+                aria-label=t!("star-search-label")
+                prop:value=move || query.get()
+                on:input=on_input
+            />
+            <Show when=move || results.with(|results| !results.is_empty())>
+                <ul class=Style::results>
+                    <For
+                        each=move || results.get()
+                        key=|result| result.id
+                        children=move |result| {
+                            let designation = result.catalog_ids.designation();
+                            let title = result.name.clone().or_else(|| designation.clone()).unwrap_or_else(|| result.id.0.to_string());
+                            let subtitle = result.name.is_some().then_some(designation).flatten();
+                            let id = result.id;
+                            let position = result.position;
+                            view! {
+                                <li>
+                                    <button class=Style::result on:click=move |_| fly_to(id, position)>
+                                        <span class=Style::name>{title}</span>
+                                        <span class=Style::designation>{subtitle}</span>
+                                    </button>
+                                </li>
+                            }
+                        }
+                    />
+                </ul>
+            </Show>
+        </div>
+    }
+}
+
+fn select_star(system_context: &mut SystemContext, star_id: StarId) {
+    let entity = system_context
+        .resources
+        .get::<EntityMap>()
+        .and_then(|entity_map| entity_map.resolve(star_id));
+    if let Some(entity) = entity {
+        select(system_context.world, [entity], SelectionMode::Replace);
+    }
+}
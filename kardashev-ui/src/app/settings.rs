@@ -0,0 +1,303 @@
+//! Settings panel.
+//!
+//! Shows the graphics capabilities, i.e. which rendering features were
+//! disabled because the GPU doesn't support them, and why. It also has the
+//! color vision setting, and the opt-ins for sending crash reports and the
+//! capabilities to the server.
+
+use std::sync::Arc;
+
+use kardashev_client::ApiClient;
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_rw_signal,
+    event_target_value,
+    expect_context,
+    provide_context,
+    view,
+    IntoView,
+    RwSignal,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    WriteSignal,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    app::accessibility::{
+        AccessibilityContext,
+        ColorVision,
+        ReducedMotion,
+    },
+    crash_report::{
+        CrashReportSettings,
+        CrashReportSettingsContext,
+    },
+    ecs::server::WorldServer,
+    graphics::{
+        capabilities::{
+            Capabilities,
+            RenderFeature,
+        },
+        Graphics,
+    },
+    i18n::use_i18n,
+    t,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/settings.scss")]
+struct Style;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityReportSettings {
+    /// Send the graphics capabilities to the server.
+    #[serde(default)]
+    pub send_report: bool,
+}
+
+#[derive(Clone, Copy)]
+struct SettingsContext {
+    capabilities: RwSignal<Option<Arc<Capabilities>>>,
+    report_settings: Signal<CapabilityReportSettings>,
+    set_report_settings: WriteSignal<CapabilityReportSettings>,
+}
+
+/// Inserts the graphics [`Capabilities`] as a resource into the world, and
+/// sends them to the server if the player opted in.
+///
+/// This must be called after the [`Graphics`], [`WorldServer`] and
+/// [`ApiClient`] were provided.
+pub fn provide_settings() {
+    let graphics = expect_context::<Graphics>();
+    let world = expect_context::<WorldServer>();
+    let api_client = expect_context::<ApiClient>();
+
+    let capabilities = create_rw_signal(None);
+    let (report_settings, set_report_settings, _) = use_local_storage::<
+        CapabilityReportSettings,
+        codee::string::JsonSerdeCodec,
+    >("capability-report-settings");
+
+    let mut rx_capabilities = graphics.capabilities();
+    spawn_local(async move {
+        loop {
+            let current = rx_capabilities.borrow_and_update().clone();
+            capabilities.set(current);
+            if rx_capabilities.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    create_effect(move |_| {
+        if let Some(capabilities) = capabilities.get() {
+            let capabilities = Capabilities::clone(&capabilities);
+            let _ = world.run(move |system_context| {
+                system_context.resources.insert(capabilities);
+            });
+        }
+    });
+
+    // every adapter is only reported once per session.
+    create_effect(move |reported: Option<Vec<String>>| {
+        let mut reported = reported.unwrap_or_default();
+        if !report_settings.get().send_report {
+            return reported;
+        }
+
+        capabilities.with(|capabilities| {
+            let Some(capabilities) = capabilities
+            else {
+                return;
+            };
+            let key = format!(
+                "{:?} {}",
+                capabilities.backend_type, capabilities.adapter.name
+            );
+            if reported.contains(&key) {
+                return;
+            }
+            reported.push(key);
+
+            let report = capabilities.to_report(
+                env!("CARGO_PKG_VERSION").to_owned(),
+                gloo_utils::window().navigator().user_agent().ok(),
+            );
+            let api_client = api_client.clone();
+            spawn_local(async move {
+                match api_client.report_client_capabilities(&report).await {
+                    Ok(()) => tracing::info!("sent graphics capabilities"),
+                    Err(error) => tracing::warn!(?error, "failed to send graphics capabilities"),
+                }
+            });
+        });
+
+        reported
+    });
+
+    provide_context(SettingsContext {
+        capabilities,
+        report_settings,
+        set_report_settings,
+    });
+}
+
+#[component]
+pub fn Settings() -> impl IntoView {
+    let SettingsContext {
+        capabilities,
+        report_settings,
+        set_report_settings,
+    } = expect_context();
+    let CrashReportSettingsContext {
+        settings: crash_report_settings,
+        set_settings: set_crash_report_settings,
+    } = expect_context();
+    let AccessibilityContext {
+        reduced_motion_setting,
+        set_reduced_motion,
+        color_vision,
+        set_color_vision,
+        ..
+    } = expect_context();
+    let i18n = use_i18n();
+
+    let reduced_motion_options = move || {
+        ReducedMotion::ALL
+            .into_iter()
+            .map(|option| {
+                view! { <option value=option.key()>{i18n.message(option.title())}</option> }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let color_vision_options = move || {
+        ColorVision::ALL
+            .into_iter()
+            .map(|option| {
+                view! { <option value=option.key()>{i18n.message(option.title())}</option> }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let capabilities_view = move || {
+        let Some(capabilities) = capabilities.get()
+        else {
+            return view! {
+                <p class=Style::empty>{t!("settings-graphics-not-initialized")}</p>
+            }
+            .into_view();
+        };
+
+        let features = RenderFeature::ALL
+            .into_iter()
+            .map(|feature| {
+                let status = match capabilities.disabled_reason(feature) {
+                    None => {
+                        view! { <td class=Style::enabled>{t!("settings-graphics-enabled")}</td> }
+                    }
+                    Some(reason) => {
+                        view! {
+                            <td class=Style::disabled title=reason.to_string()>
+                                {t!("settings-graphics-disabled")}
+                                " ("
+                                {reason.to_string()}
+                                ")"
+                            </td>
+                        }
+                    }
+                };
+                view! {
+                    <tr>
+                        <th>{move || i18n.translate(feature.title())}</th>
+                        {status}
+                    </tr>
+                }
+            })
+            .collect::<Vec<_>>();
+
+        view! {
+            <table class=Style::capabilities>
+                <tr>
+                    <th>{t!("settings-graphics-backend")}</th>
+                    <td>{format!("{:?}", capabilities.backend_type)}</td>
+                </tr>
+                <tr>
+                    <th>{t!("settings-graphics-adapter")}</th>
+                    <td>{capabilities.adapter.name.clone()}</td>
+                </tr>
+                {features}
+            </table>
+        }
+        .into_view()
+    };
+
+    view! {
+        <div class=Style::settings>
+            <h3>{t!("settings-graphics")}</h3>
+            {capabilities_view}
+            <h3>{t!("settings-accessibility")}</h3>
+            <label>
+                {t!("settings-reduced-motion")}
+                " "
+                <select
+                    prop:value=move || reduced_motion_setting.get().key()
+                    on:change=move |event| {
+                        set_reduced_motion.set(ReducedMotion::from_key(&event_target_value(&event)))
+                    }
+                >
+                    {reduced_motion_options}
+                </select>
+            </label>
+            <label>
+                {t!("settings-color-vision")}
+                " "
+                <select
+                    prop:value=move || color_vision.get().key()
+                    on:change=move |event| {
+                        set_color_vision.set(ColorVision::from_key(&event_target_value(&event)))
+                    }
+                >
+                    {color_vision_options}
+                </select>
+            </label>
+            <h3>{t!("settings-reports")}</h3>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || crash_report_settings.get().send_reports
+                    on:change=move |_| {
+                        set_crash_report_settings
+                            .set(CrashReportSettings {
+                                send_reports: !crash_report_settings.get_untracked().send_reports,
+                            })
+                    }
+                />
+                {t!("settings-send-crash-reports")}
+            </label>
+            <label>
+                <input
+                    type="checkbox"
+                    prop:checked=move || report_settings.get().send_report
+                    on:change=move |_| {
+                        set_report_settings
+                            .set(CapabilityReportSettings {
+                                send_report: !report_settings.get_untracked().send_report,
+                            })
+                    }
+                />
+                {t!("settings-send-capabilities")}
+            </label>
+        </div>
+    }
+}
@@ -0,0 +1,271 @@
+//! Syncs the UI settings with the server, so that they follow the player
+//! across browsers.
+//!
+//! The synced settings are the local storage values under [`SYNCED_KEYS`],
+//! which are stored on the server as one JSON object. Settings that belong to
+//! the device, like the graphics config and the report opt-ins, are not
+//! synced.
+//!
+//! Each browser remembers the version of the settings it synced last, and the
+//! settings themselves as the base for merging. A setting that wasn't changed
+//! locally since then takes the server's value, otherwise the local value
+//! wins. The server only stores settings that are based on its latest
+//! version, so that changes from another browser are merged instead of
+//! overwritten.
+
+use kardashev_client::ApiClient;
+use leptos::{
+    create_effect,
+    expect_context,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+    SignalWithUntracked,
+    WriteSignal,
+};
+use leptos_use::{
+    signal_debounced,
+    storage::use_local_storage,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    app::config::Config,
+    input::actions::KEY_BINDINGS_KEY,
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    utils::futures::spawn_local,
+};
+
+/// Local storage keys of the synced settings.
+const SYNCED_KEYS: [&str; 7] = [
+    "layout",
+    "map-layers",
+    "music-settings",
+    "reduced-motion",
+    "color-vision",
+    "locale",
+    KEY_BINDINGS_KEY,
+];
+
+/// Local storage key of the [`SyncState`].
+const SYNC_STATE_KEY: &str = "settings-sync";
+
+/// How often the settings are merged and pushed again, if another browser
+/// changes them at the same time.
+const MAX_ATTEMPTS: usize = 3;
+
+/// Local changes are pushed once the settings didn't change for this many
+/// milliseconds.
+const PUSH_DELAY: f64 = 2000.;
+
+type Settings = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct SyncState {
+    /// Version of the settings on the server when they were last synced.
+    version: Option<u64>,
+
+    /// The settings when they were last synced.
+    base: Settings,
+}
+
+#[derive(Clone, Copy)]
+struct LocalSetting {
+    key: &'static str,
+    value: Signal<Option<serde_json::Value>>,
+    set_value: WriteSignal<Option<serde_json::Value>>,
+}
+
+/// Syncs the settings with the server, if a faction is configured.
+///
+/// The settings are pulled once on startup, and pushed when they change
+/// locally.
+pub fn provide_settings_sync() {
+    let Config { faction, .. } = expect_context();
+    if faction.is_none() {
+        return;
+    }
+    let api_client = expect_context::<ApiClient>();
+    let notifications = expect_context::<Notifications>();
+
+    let local = SYNCED_KEYS.map(|key| {
+        let (value, set_value, _) =
+            use_local_storage::<Option<serde_json::Value>, codee::string::JsonSerdeCodec>(key);
+        LocalSetting {
+            key,
+            value,
+            set_value,
+        }
+    });
+    let (sync_state, set_sync_state, _) =
+        use_local_storage::<SyncState, codee::string::JsonSerdeCodec>(SYNC_STATE_KEY);
+
+    // syncs run one after another. changes during a sync are pushed after it.
+    let (tx_sync, mut rx_sync) = mpsc::unbounded_channel();
+    spawn_local(async move {
+        while rx_sync.recv().await.is_some() {
+            while rx_sync.try_recv().is_ok() {}
+
+            if let Err(error) = sync(&api_client, &local, sync_state, set_sync_state).await {
+                tracing::error!(?error, "settings sync failed");
+                notifications.notify(
+                    Notification::error("notification-settings-sync-failed")
+                        .with_message(error.to_string()),
+                );
+            }
+        }
+    });
+
+    let settings = signal_debounced(Signal::derive(move || read_local(&local)), PUSH_DELAY);
+    create_effect(move |synced: Option<()>| {
+        // settings that were just applied by a sync don't need to be pushed.
+        let changed = settings
+            .with(|settings| sync_state.with_untracked(|sync_state| sync_state.base != *settings));
+        if synced.is_none() || changed {
+            let _ = tx_sync.send(());
+        }
+    });
+}
+
+/// Merges the local settings with the server's, applies them locally, and
+/// pushes them if they differ from the server's.
+async fn sync(
+    api_client: &ApiClient,
+    local: &[LocalSetting],
+    sync_state: Signal<SyncState>,
+    set_sync_state: WriteSignal<SyncState>,
+) -> Result<(), kardashev_client::Error> {
+    let mut remote = api_client.get_settings().await?;
+
+    for _ in 0..MAX_ATTEMPTS {
+        let local_settings = read_local(local);
+        let (base_version, settings) = match &remote {
+            Some(remote) => {
+                let remote_settings = match &remote.settings {
+                    serde_json::Value::Object(settings) => settings.clone(),
+                    _ => Settings::new(),
+                };
+                let merged = sync_state.with_untracked(|sync_state| {
+                    merge(&sync_state.base, &local_settings, &remote_settings)
+                });
+                apply_local(local, &merged);
+
+                if merged == remote_settings {
+                    set_sync_state.set(SyncState {
+                        version: Some(remote.version),
+                        base: merged,
+                    });
+                    return Ok(());
+                }
+                (Some(remote.version), merged)
+            }
+            None => (None, local_settings),
+        };
+
+        let response = api_client
+            .put_settings(base_version, settings.clone().into())
+            .await?;
+        if response.conflict {
+            tracing::debug!(
+                version = response.settings.version,
+                "settings were changed by another client"
+            );
+            remote = Some(response.settings);
+            continue;
+        }
+
+        set_sync_state.set(SyncState {
+            version: Some(response.settings.version),
+            base: settings,
+        });
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "settings keep being changed by another client. pushing them with the next change."
+    );
+    Ok(())
+}
+
+fn read_local(local: &[LocalSetting]) -> Settings {
+    local
+        .iter()
+        .filter_map(|setting| Some((setting.key.to_owned(), setting.value.get()?)))
+        .collect()
+}
+
+fn apply_local(local: &[LocalSetting], settings: &Settings) {
+    for setting in local {
+        if let Some(value) = settings.get(setting.key) {
+            if setting.value.get_untracked().as_ref() != Some(value) {
+                setting.set_value.set(Some(value.clone()));
+            }
+        }
+    }
+}
+
+/// Three-way merge of the settings. Settings that weren't changed locally
+/// since `base` take the remote value, and otherwise the local value wins.
+fn merge(base: &Settings, local: &Settings, remote: &Settings) -> Settings {
+    let mut merged = remote.clone();
+    for (key, value) in local {
+        if base.get(key) != Some(value) {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{
+        merge,
+        Settings,
+    };
+
+    fn settings(value: serde_json::Value) -> Settings {
+        let serde_json::Value::Object(settings) = value
+        else {
+            panic!("settings must be an object");
+        };
+        settings
+    }
+
+    #[test]
+    fn local_changes_win_over_remote_ones() {
+        let base = settings(json!({ "locale": "en", "color-vision": null, "layout": 1 }));
+        let local = settings(json!({ "locale": "de", "color-vision": null, "layout": 1 }));
+        let remote = settings(json!({ "locale": "fr", "color-vision": "tritanopia", "layout": 1 }));
+
+        assert_eq!(
+            merge(&base, &local, &remote),
+            settings(json!({ "locale": "de", "color-vision": "tritanopia", "layout": 1 }))
+        );
+    }
+
+    #[test]
+    fn settings_from_both_sides_are_kept() {
+        let base = Settings::new();
+        let local = settings(json!({ "key-bindings": { "Alt+KeyU": "undo" } }));
+        let remote = settings(json!({ "music-settings": { "volume": 0.5 } }));
+
+        assert_eq!(
+            merge(&base, &local, &remote),
+            settings(json!({
+                "key-bindings": { "Alt+KeyU": "undo" },
+                "music-settings": { "volume": 0.5 },
+            }))
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Star names over the world view.
+//!
+//! Labeling every visible star would be unreadable, and slow with thousands of
+//! stars, so [`star_label_layout_system`] picks which names to show: Stars are
+//! ranked by their apparent magnitude as seen from the camera, and the
+//! magnitude limit rises as the camera zooms in. The labels are then placed
+//! greedily, the most important first, skipping any that would overlap one
+//! that was already placed.
+//!
+//! The labels are HTML elements from a pool of [`MAX_LABELS`] slots. A star
+//! keeps its slot while its label is shown, so the elements are only moved
+//! instead of being recreated. Like the region names, the labels belong to the
+//! [`MapLayer::Labels`] layer.
+
+use std::collections::HashMap;
+
+use kardashev_protocol::{
+    model::star::StarId,
+    stellar::apparent_magnitude,
+};
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_effect,
+    create_node_ref,
+    create_rw_signal,
+    expect_context,
+    html::Div,
+    on_cleanup,
+    store_value,
+    view,
+    IntoView,
+    RwSignal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalWith,
+};
+use leptos_use::use_element_size;
+use nalgebra::{
+    Point2,
+    Point3,
+};
+use tokio::sync::watch;
+
+use crate::{
+    app::{
+        layers::{
+            MapLayer,
+            MapLayers,
+        },
+        minimap::MinimapTarget,
+        world_view::WorldViewCameraController,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        camera::{
+            CameraProjection,
+            Projection,
+        },
+        transform::Transform,
+        SurfaceSize,
+    },
+    selection::Selected,
+    universe::star::{
+        StarEntity,
+        StarProperties,
+    },
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/star_labels.scss")]
+struct Style;
+
+/// How many labels are shown at most.
+pub const MAX_LABELS: usize = 64;
+
+/// Faintest apparent magnitude that is labeled at the default zoom.
+const MAGNITUDE_LIMIT: f32 = 2.0;
+
+/// How much the magnitude limit rises each time the field of view is halved.
+const MAGNITUDE_LIMIT_PER_ZOOM: f32 = 1.5;
+
+/// Labels that are already shown are ranked as if they were this much
+/// brighter, so that labels don't flicker between stars of similar
+/// brightness.
+const SHOWN_BONUS: f32 = 0.5;
+
+/// Estimated size of a label in pixels, see `star_labels.scss`.
+const CHAR_WIDTH: f32 = 7.0;
+const LABEL_HEIGHT: f32 = 14.0;
+
+/// Labels are placed this many pixels to the right of their star.
+const LABEL_OFFSET: f32 = 8.0;
+
+/// Minimum distance between labels in pixels.
+const LABEL_PADDING: f32 = 2.0;
+
+/// Resource with the state of the star labels.
+#[derive(Debug)]
+pub struct StarLabelLayout {
+    /// Size of the world view in pixels, set by the [`StarLabels`]
+    /// component.
+    viewport: Option<SurfaceSize>,
+
+    /// Which star is shown in which slot.
+    slots: HashMap<StarId, usize>,
+
+    tx_labels: watch::Sender<Vec<Option<StarLabel>>>,
+}
+
+/// Name of a star, and where to show it in the world view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarLabel {
+    pub name: String,
+
+    /// Position of the label's top-left corner in pixels.
+    pub left: f32,
+    pub top: f32,
+
+    pub selected: bool,
+}
+
+/// A star that might be labeled.
+#[derive(Clone, Debug)]
+struct Candidate {
+    id: StarId,
+    name: String,
+    position: Point2<f32>,
+    rank: f32,
+    selected: bool,
+}
+
+/// Screen-space rectangle in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Rect {
+    min: Point2<f32>,
+    max: Point2<f32>,
+}
+
+impl Rect {
+    fn for_label(position: Point2<f32>, name: &str) -> Self {
+        let min = Point2::new(position.x + LABEL_OFFSET, position.y - 0.5 * LABEL_HEIGHT);
+        let width = CHAR_WIDTH * name.chars().count() as f32;
+        Self {
+            min,
+            max: Point2::new(min.x + width, min.y + LABEL_HEIGHT),
+        }
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.min.x < other.max.x + LABEL_PADDING
+            && other.min.x < self.max.x + LABEL_PADDING
+            && self.min.y < other.max.y + LABEL_PADDING
+            && other.min.y < self.max.y + LABEL_PADDING
+    }
+}
+
+/// Faintest apparent magnitude that is labeled with this projection.
+fn magnitude_limit(projection: &CameraProjection) -> f32 {
+    match projection.projection {
+        Projection::Perspective { fovy } => {
+            let zoom = WorldViewCameraController::DEFAULT_FOVY / fovy;
+            MAGNITUDE_LIMIT + MAGNITUDE_LIMIT_PER_ZOOM * zoom.max(1.0).log2()
+        }
+        Projection::Orthographic { .. } => MAGNITUDE_LIMIT,
+    }
+}
+
+/// Greedily picks labels that don't overlap, the lowest rank first.
+///
+/// Selected stars are always labeled first.
+fn place_labels(mut candidates: Vec<Candidate>, max_labels: usize) -> Vec<Candidate> {
+    candidates.sort_by(|a, b| b.selected.cmp(&a.selected).then(a.rank.total_cmp(&b.rank)));
+
+    let mut placed = Vec::with_capacity(max_labels);
+    let mut rects = Vec::<Rect>::with_capacity(max_labels);
+    for candidate in candidates {
+        if placed.len() == max_labels {
+            break;
+        }
+        let rect = Rect::for_label(candidate.position, &candidate.name);
+        if rects.iter().any(|placed| placed.overlaps(&rect)) {
+            continue;
+        }
+        rects.push(rect);
+        placed.push(candidate);
+    }
+
+    placed
+}
+
+fn star_label_layout_system(system_context: &mut SystemContext) {
+    let visible = system_context
+        .resources
+        .get::<MapLayers>()
+        .map_or(true, |layers| layers.settings.is_visible(MapLayer::Labels));
+
+    let Some(layout) = system_context.resources.get_mut::<StarLabelLayout>()
+    else {
+        return;
+    };
+
+    let camera = system_context
+        .world
+        .query_mut::<(&Transform, &CameraProjection)>()
+        .with::<&MinimapTarget>()
+        .into_iter()
+        .next()
+        .map(|(_, (transform, projection))| (transform.model_matrix, *projection));
+
+    let mut placed = vec![];
+    if let (true, Some(viewport), Some((camera_transform, projection))) = (
+        visible,
+        layout
+            .viewport
+            .filter(|viewport| viewport.width > 0 && viewport.height > 0),
+        camera,
+    ) {
+        let camera_position = camera_transform.transform_point(&Point3::origin());
+        let limit = magnitude_limit(&projection);
+
+        let mut candidates = vec![];
+        for (_, (star, properties, transform, label, selected)) in
+            system_context.world.query_mut::<(
+                &StarEntity,
+                &StarProperties,
+                &Transform,
+                &Label,
+                Option<&Selected>,
+            )>()
+        {
+            let position = transform.model_matrix.transform_point(&Point3::origin());
+            let mut rank = apparent_magnitude(
+                properties.absolute_magnitude,
+                (position - camera_position).norm(),
+            );
+            if layout.slots.contains_key(&star.id) {
+                rank -= SHOWN_BONUS;
+            }
+            let selected = selected.is_some();
+            if rank > limit && !selected {
+                continue;
+            }
+
+            let Some(normalized) = projection.project_to_normalized(&camera_transform, &position)
+            else {
+                continue;
+            };
+            if !(0.0..=1.0).contains(&normalized.x) || !(0.0..=1.0).contains(&normalized.y) {
+                continue;
+            }
+
+            candidates.push(Candidate {
+                id: star.id,
+                name: label.label.to_string(),
+                position: Point2::new(
+                    normalized.x * viewport.width as f32,
+                    normalized.y * viewport.height as f32,
+                ),
+                rank,
+                selected,
+            });
+        }
+
+        placed = place_labels(candidates, MAX_LABELS);
+    }
+
+    // stars that are still shown keep their slots, the others are assigned the
+    // free ones.
+    let mut slots = HashMap::with_capacity(placed.len());
+    let mut labels = vec![None; MAX_LABELS];
+    let mut new = vec![];
+    for candidate in placed {
+        match layout.slots.get(&candidate.id) {
+            Some(slot) => {
+                slots.insert(candidate.id, *slot);
+                labels[*slot] = Some(candidate);
+            }
+            None => new.push(candidate),
+        }
+    }
+    let mut free = (0..MAX_LABELS).filter(|slot| labels[*slot].is_none());
+    for candidate in new {
+        let slot = free.next().expect("more labels than slots");
+        slots.insert(candidate.id, slot);
+        labels[slot] = Some(candidate);
+    }
+    layout.slots = slots;
+
+    let labels = labels
+        .into_iter()
+        .map(|candidate| {
+            candidate.map(|candidate| {
+                StarLabel {
+                    name: candidate.name,
+                    left: candidate.position.x + LABEL_OFFSET,
+                    top: candidate.position.y - 0.5 * LABEL_HEIGHT,
+                    selected: candidate.selected,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    layout.tx_labels.send_if_modified(|current| {
+        if *current != labels {
+            *current = labels;
+            true
+        }
+        else {
+            false
+        }
+    });
+}
+
+/// Star names over the world view.
+#[component]
+pub fn StarLabels() -> impl IntoView {
+    let world = store_value(expect_context::<WorldServer>());
+    let container_node_ref = create_node_ref::<Div>();
+    let container_size = use_element_size(container_node_ref);
+
+    // one signal per slot, so that only the labels that changed are updated.
+    let slots = (0..MAX_LABELS)
+        .map(|_| create_rw_signal(None::<StarLabel>))
+        .collect::<Vec<_>>();
+    let slots = store_value(slots);
+
+    create_effect(move |_| {
+        let viewport = SurfaceSize {
+            width: container_size.width.get() as u32,
+            height: container_size.height.get() as u32,
+        };
+        let _ = world.get_value().run(move |system_context| {
+            if let Some(layout) = system_context.resources.get_mut::<StarLabelLayout>() {
+                layout.viewport = Some(viewport);
+            }
+        });
+    });
+
+    let join_handle = spawn_local(async move {
+        let mut rx_labels = world
+            .get_value()
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<StarLabelLayout>()
+                    .map(|layout| layout.tx_labels.subscribe())
+            })
+            .await?;
+
+        loop {
+            let labels = rx_labels.borrow_and_update().clone();
+            slots.with_value(|slots: &Vec<RwSignal<Option<StarLabel>>>| {
+                for (slot, label) in slots.iter().zip(labels) {
+                    if slot.get_untracked() != label {
+                        slot.set(label);
+                    }
+                }
+            });
+            if rx_labels.changed().await.is_err() {
+                break;
+            }
+        }
+
+        Some(())
+    });
+    on_cleanup(move || join_handle.abort());
+
+    let slot_views = slots
+        .get_value()
+        .into_iter()
+        .map(|slot| {
+            let class = move || {
+                slot.with(|label| {
+                    match label {
+                        Some(StarLabel { selected: true, .. }) => {
+                            format!("{} {}", Style::label, Style::selected)
+                        }
+                        Some(_) => Style::label.to_owned(),
+                        None => format!("{} {}", Style::label, Style::hidden),
+                    }
+                })
+            };
+            let style = move || {
+                slot.with(|label| {
+                    label.as_ref().map_or_else(String::new, |label| {
+                        format!("transform: translate({}px, {}px);", label.left, label.top)
+                    })
+                })
+            };
+            let name = move || slot.with(|label| label.as_ref().map(|label| label.name.clone()));
+            view! { <span class=class style=style>{name}</span> }
+        })
+        .collect::<Vec<_>>();
+
+    view! {
+        <div class=Style::labels node_ref=container_node_ref aria-hidden="true">
+            {slot_views}
+        </div>
+    }
+}
+
+pub struct StarLabelsPlugin;
+
+impl Plugin for StarLabelsPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let (tx_labels, _rx) = watch::channel(vec![None; MAX_LABELS]);
+        context.resources.insert(StarLabelLayout {
+            viewport: None,
+            slots: HashMap::new(),
+            tx_labels,
+        });
+        context.schedule.add_system(star_label_layout_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::model::star::StarId;
+    use nalgebra::Point2;
+    use uuid::Uuid;
+
+    use super::{
+        place_labels,
+        Candidate,
+    };
+
+    fn star_id(id: u128) -> StarId {
+        StarId(Uuid::from_u128(id))
+    }
+
+    fn candidate(id: u128, x: f32, y: f32, rank: f32) -> Candidate {
+        Candidate {
+            id: star_id(id),
+            name: "Sirius".to_owned(),
+            position: Point2::new(x, y),
+            rank,
+            selected: false,
+        }
+    }
+
+    #[test]
+    fn it_skips_overlapping_labels() {
+        let placed = place_labels(
+            vec![
+                candidate(1, 100.0, 100.0, 1.0),
+                candidate(2, 110.0, 102.0, -1.0),
+                candidate(3, 100.0, 200.0, 0.0),
+            ],
+            10,
+        );
+        let ids = placed.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![star_id(2), star_id(3)]);
+    }
+
+    #[test]
+    fn it_places_selected_labels_first() {
+        let mut selected = candidate(1, 100.0, 100.0, 5.0);
+        selected.selected = true;
+        let placed = place_labels(vec![candidate(2, 105.0, 100.0, -1.0), selected], 1);
+        assert_eq!(placed[0].id, star_id(1));
+    }
+}
@@ -0,0 +1,299 @@
+//! Startup phases, and the loading screen that is shown until they're done.
+//!
+//! The loading screen from `index.html` is only shown while the wasm module is
+//! fetched. Once the app is mounted, [`LoadingScreen`] shows the progress of
+//! the remaining phases on top of the workspace, which is already rendered
+//! underneath, so that its windows can create their surfaces.
+//!
+//! If a phase fails, the loading screen shows the error and offers to retry
+//! the phase. Phases that can't be retried on their own reload the page
+//! instead. Translations are assets themselves, so the loading screen falls
+//! back to English until they're loaded.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    rc::Rc,
+};
+
+use kardashev_style::style;
+use leptos::{
+    component,
+    create_rw_signal,
+    expect_context,
+    provide_context,
+    store_value,
+    view,
+    For,
+    IntoView,
+    RwSignal,
+    Show,
+    SignalUpdate,
+    SignalWith,
+    StoredValue,
+};
+use tokio::sync::watch;
+
+use crate::{
+    app::components::icon::KardashevIcon,
+    i18n::use_i18n,
+    utils::futures::spawn_local,
+};
+
+#[style(path = "src/app/startup.scss")]
+struct Style;
+
+/// A phase of the startup, in the order in which they're started.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    /// Fetching and instantiating the wasm module. This is done when the app
+    /// runs.
+    Wasm,
+
+    /// Loading the config from local storage.
+    Config,
+
+    /// Initializing the graphics backend.
+    Graphics,
+
+    /// Downloading the asset manifest.
+    Assets,
+
+    /// Connecting to the world's session stream.
+    World,
+}
+
+impl Phase {
+    pub const ALL: [Self; 5] = [
+        Self::Wasm,
+        Self::Config,
+        Self::Graphics,
+        Self::Assets,
+        Self::World,
+    ];
+
+    /// Message ID and English fallback of the phase's name.
+    fn message(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Wasm => ("startup-phase-wasm", "Starting"),
+            Self::Config => ("startup-phase-config", "Loading settings"),
+            Self::Graphics => ("startup-phase-graphics", "Initializing graphics"),
+            Self::Assets => ("startup-phase-assets", "Downloading assets"),
+            Self::World => ("startup-phase-world", "Connecting to the world"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PhaseState {
+    Pending,
+    Done,
+
+    /// The phase failed with this error.
+    Failed(String),
+}
+
+/// Progress of the startup.
+///
+/// This is provided as context by [`provide_startup`].
+#[derive(Clone, Copy)]
+pub struct Startup {
+    phases: RwSignal<BTreeMap<Phase, PhaseState>>,
+    retries: StoredValue<BTreeMap<Phase, Rc<dyn Fn()>>>,
+}
+
+impl Startup {
+    pub fn finish(&self, phase: Phase) {
+        tracing::debug!(?phase, "startup phase finished");
+        self.phases.update(|phases| {
+            phases.insert(phase, PhaseState::Done);
+        });
+    }
+
+    /// Marks a phase as failed. Phases that already finished are left as they
+    /// are, e.g. when the session stream disconnects later on.
+    pub fn fail(&self, phase: Phase, error: impl ToString) {
+        let error = error.to_string();
+        self.phases.update(|phases| {
+            let state = phases.entry(phase).or_insert(PhaseState::Pending);
+            if *state != PhaseState::Done {
+                tracing::error!(?phase, %error, "startup phase failed");
+                *state = PhaseState::Failed(error);
+            }
+        });
+    }
+
+    /// Sets how a failed phase is retried. Without this, retrying reloads the
+    /// page.
+    pub fn on_retry(&self, phase: Phase, retry: impl Fn() + 'static) {
+        self.retries.update_value(|retries| {
+            retries.insert(phase, Rc::new(retry));
+        });
+    }
+
+    fn retry(&self, phase: Phase) {
+        tracing::debug!(?phase, "retrying startup phase");
+        self.phases.update(|phases| {
+            phases.insert(phase, PhaseState::Pending);
+        });
+        if let Some(retry) = self
+            .retries
+            .with_value(|retries| retries.get(&phase).cloned())
+        {
+            retry();
+        }
+        else {
+            let _ = gloo_utils::window().location().reload();
+        }
+    }
+
+    /// Finishes or fails a phase when the value in `rx` changes, until the
+    /// phase is done.
+    ///
+    /// `state` maps the value to `None` while the phase is pending.
+    pub fn watch<T: 'static>(
+        &self,
+        phase: Phase,
+        mut rx: watch::Receiver<T>,
+        state: impl Fn(&T) -> Option<Result<(), String>> + 'static,
+    ) {
+        let startup = *self;
+        spawn_local(async move {
+            loop {
+                let result = state(&rx.borrow_and_update());
+                match result {
+                    Some(Ok(())) => {
+                        startup.finish(phase);
+                        break;
+                    }
+                    Some(Err(error)) => startup.fail(phase, error),
+                    None => {}
+                }
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Fraction of the phases that are done, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        self.phases.with(|phases| {
+            let done = phases
+                .values()
+                .filter(|state| **state == PhaseState::Done)
+                .count();
+            done as f32 / phases.len() as f32
+        })
+    }
+
+    /// Whether all phases are done.
+    pub fn is_done(&self) -> bool {
+        self.phases
+            .with(|phases| phases.values().all(|state| *state == PhaseState::Done))
+    }
+}
+
+impl Debug for Startup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Startup")
+            .field("phases", &self.phases)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Provides the [`Startup`] as context. This must be called first, so that
+/// the other `provide_*` functions can report their phases.
+pub fn provide_startup() {
+    let phases = Phase::ALL
+        .into_iter()
+        .map(|phase| (phase, PhaseState::Pending))
+        .collect();
+    let startup = Startup {
+        phases: create_rw_signal(phases),
+        retries: store_value(BTreeMap::new()),
+    };
+    provide_context(startup);
+
+    // we're running, so the wasm module was instantiated.
+    startup.finish(Phase::Wasm);
+}
+
+/// Full-screen splash with the progress of the startup. It's removed once all
+/// phases are done.
+#[component]
+pub fn LoadingScreen() -> impl IntoView {
+    let startup = expect_context::<Startup>();
+    let i18n = use_i18n();
+
+    let phases = move || {
+        startup.phases.with(|phases| {
+            phases
+                .iter()
+                .map(|(phase, state)| (*phase, state.clone()))
+                .collect::<Vec<_>>()
+        })
+    };
+    let percent = move || (startup.progress() * 100.0).round();
+
+    view! {
+        <Show when=move || !startup.is_done()>
+            <div class=Style::loading_screen role="status" aria-live="polite">
+                <div class=Style::splash>
+                    <KardashevIcon />
+                </div>
+                <div
+                    class=Style::progress
+                    role="progressbar"
+                    aria-valuemin="0"
+                    aria-valuemax="100"
+                    aria-valuenow=percent
+                >
+                    <div
+                        class=Style::progress_bar
+                        style=move || format!("width: {}%;", percent())
+                    ></div>
+                </div>
+                <ul class=Style::phases>
+                    <For
+                        each=phases
+                        key=|(phase, state)| (*phase, state.clone())
+                        children=move |(phase, state)| {
+                            let (key, fallback) = phase.message();
+                            let name = i18n.message_or(key, fallback);
+                            match state {
+                                PhaseState::Pending => {
+                                    view! {
+                                        <li class=Style::pending>{name}</li>
+                                    }
+                                        .into_view()
+                                }
+                                PhaseState::Done => {
+                                    view! {
+                                        <li class=Style::done>{name}</li>
+                                    }
+                                        .into_view()
+                                }
+                                PhaseState::Failed(error) => {
+                                    view! {
+                                        <li class=Style::failed role="alert">
+                                            {name}
+                                            <pre class=Style::error>{error}</pre>
+                                            <button
+                                                class=Style::retry
+                                                on:click=move |_| startup.retry(phase)
+                                            >
+                                                {i18n.message_or("startup-retry", "Retry")}
+                                            </button>
+                                        </li>
+                                    }
+                                        .into_view()
+                                }
+                            }
+                        }
+                    />
+                </ul>
+            </div>
+        </Show>
+    }
+}
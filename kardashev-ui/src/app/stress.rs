@@ -0,0 +1,294 @@
+//! Stress test scenes, to validate rendering optimizations like instancing,
+//! culling and batching on real browsers.
+//!
+//! A stress test is started from the developer console with
+//! `kardashev.stress_test({ meshes: 10000, lights: 32, particles: 5000 })`.
+//! It spawns the scene around the origin, records the timings of every frame
+//! for a fixed duration, and then despawns the scene again. The frame
+//! statistics are written as JSON into the `stress-tests` web fs.
+
+use std::{
+    f32::consts::PI,
+    time::Duration,
+};
+
+use hecs::Entity;
+use kardashev_protocol::{
+    asset_id,
+    assets::AssetId,
+};
+use nalgebra::{
+    Point3,
+    Vector3,
+};
+use palette::{
+    Hsv,
+    IntoColor,
+    Srgb,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    ecs::server::WorldServer,
+    graphics::{
+        blinn_phong::BlinnPhongMaterial,
+        builtin::{
+            self,
+            BuiltinAssets,
+        },
+        light::PointLight,
+        material::Material,
+        pbr::PbrMaterial,
+        render_3d::{
+            FrameSample,
+            FrameSamples,
+        },
+        transform::Transform,
+    },
+    utils::{
+        futures::spawn_local_and_handle_error,
+        time::sleep,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+/// Material shared by all meshes, so that they're drawn instanced.
+const MESH_MATERIAL: AssetId = asset_id!("8d9e1700-7d19-4efb-af04-5c178ac35476");
+
+/// Transparent material shared by all particles.
+const PARTICLE_MATERIAL: AssetId = asset_id!("dcde2829-b0e5-4d4d-b2e8-58d673543efc");
+
+/// Distance between neighbouring meshes.
+const MESH_SPACING: f32 = 3.0;
+
+/// Arguments of the `stress_test` console command.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StressScene {
+    /// Number of opaque meshes, which are laid out in a cube.
+    pub meshes: usize,
+
+    /// Number of point lights, on a ring around the meshes.
+    pub lights: usize,
+
+    /// Number of small transparent particles, on a shell around the meshes.
+    pub particles: usize,
+
+    /// How long frames are recorded, in seconds.
+    pub duration: f32,
+}
+
+impl Default for StressScene {
+    fn default() -> Self {
+        Self {
+            meshes: 1000,
+            lights: 8,
+            particles: 1000,
+            duration: 10.0,
+        }
+    }
+}
+
+impl StressScene {
+    fn spawn(&self, world: &mut hecs::World, builtin: &BuiltinAssets) -> Vec<Entity> {
+        let mut entities = Vec::with_capacity(self.meshes + self.lights + self.particles);
+
+        // meshes in a cube centered on the origin
+        let side = (self.meshes as f32).cbrt().ceil().max(1.0) as usize;
+        let extent = side as f32 * MESH_SPACING;
+        let mesh_material = Material::new(BlinnPhongMaterial {
+            diffuse_color: Some(Srgb::new(0.8, 0.8, 0.8)),
+            ..Default::default()
+        })
+        .with_asset_id(MESH_MATERIAL)
+        .with_label("stress test mesh");
+        for i in 0..self.meshes {
+            let grid = Vector3::new(
+                (i % side) as f32,
+                ((i / side) % side) as f32,
+                (i / (side * side)) as f32,
+            );
+            let position = grid * MESH_SPACING - Vector3::repeat(0.5 * (extent - MESH_SPACING));
+            entities.push(world.spawn((
+                Transform::from_position(position.into()),
+                builtin.unit_sphere.clone(),
+                mesh_material.clone(),
+                builtin::default_material::<PbrMaterial>(),
+            )));
+        }
+
+        // lights on a ring around the cube, with evenly spaced hues
+        for i in 0..self.lights {
+            let t = i as f32 / self.lights as f32;
+            let angle = 2.0 * PI * t;
+            let position = Point3::new(angle.cos(), 0.0, angle.sin()) * extent;
+            let color: Srgb<f32> = Hsv::new(360.0 * t, 0.5, 1.0).into_color();
+            entities.push(world.spawn((
+                Transform::from_position(position),
+                PointLight::new(color).with_range(extent),
+            )));
+        }
+
+        // particles on a fibonacci sphere around the cube
+        let particle_material = Material::new(BlinnPhongMaterial {
+            diffuse_color: Some(Srgb::new(0.6, 0.8, 1.0)),
+            emissive_color: Some(Srgb::new(0.3, 0.4, 0.5)),
+            dissolve: Some(0.5),
+            ..Default::default()
+        })
+        .with_asset_id(PARTICLE_MATERIAL)
+        .with_label("stress test particle");
+        let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+        for i in 0..self.particles {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / self.particles as f32;
+            let radius = (1.0 - y * y).sqrt();
+            let angle = golden_angle * i as f32;
+            let direction = Vector3::new(radius * angle.cos(), y, radius * angle.sin());
+            entities.push(world.spawn((
+                Transform::from_position((direction * extent).into()).with_scaling(0.2),
+                builtin.unit_sphere.clone(),
+                particle_material.clone(),
+                builtin::default_material::<PbrMaterial>(),
+            )));
+        }
+
+        entities
+    }
+}
+
+/// Statistics of the frames recorded during a stress test. Times are in
+/// milliseconds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StressTestReport {
+    pub scene: StressScene,
+    pub user_agent: Option<String>,
+    pub frames: usize,
+    pub fps: f32,
+    pub frame_time: Percentiles,
+    pub record_time: Percentiles,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub mean: f32,
+    pub min: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl Percentiles {
+    fn new(samples: impl IntoIterator<Item = Duration>) -> Self {
+        let mut samples = samples
+            .into_iter()
+            .map(|duration| duration.as_secs_f32() * 1000.0)
+            .collect::<Vec<_>>();
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f32::total_cmp);
+
+        let percentile = |p: f32| {
+            let index = (p * (samples.len() - 1) as f32).round() as usize;
+            samples[index]
+        };
+
+        Self {
+            mean: samples.iter().sum::<f32>() / samples.len() as f32,
+            min: samples[0],
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+impl StressTestReport {
+    fn new(scene: StressScene, samples: &[FrameSample]) -> Self {
+        let frame_time = Percentiles::new(samples.iter().map(|sample| sample.frame_time));
+        let fps = if frame_time.mean > 0.0 {
+            1000.0 / frame_time.mean
+        }
+        else {
+            0.0
+        };
+
+        Self {
+            scene,
+            user_agent: gloo_utils::window().navigator().user_agent().ok(),
+            frames: samples.len(),
+            fps,
+            frame_time,
+            record_time: Percentiles::new(samples.iter().map(|sample| sample.record_time)),
+        }
+    }
+}
+
+/// Spawns the scene, records the frames rendered by the camera, and writes the
+/// report into the `stress-tests` web fs.
+pub fn run_stress_test(world: WorldServer, camera_entity: Entity, scene: StressScene) {
+    spawn_local_and_handle_error(async move {
+        let web_fs = WebFs::with_named_root("stress-tests").await?;
+        tracing::info!(?scene, "starting stress test");
+
+        let entities = world
+            .run({
+                let scene = scene.clone();
+                move |system_context| {
+                    let builtin = system_context
+                        .resources
+                        .get::<BuiltinAssets>()
+                        .cloned()
+                        .unwrap_or_default();
+                    let entities = scene.spawn(system_context.world, &builtin);
+                    let _ = system_context
+                        .world
+                        .insert_one(camera_entity, FrameSamples::default());
+                    entities
+                }
+            })
+            .await;
+
+        sleep(Duration::from_secs_f32(scene.duration)).await;
+
+        let samples = world
+            .run(move |system_context| {
+                for entity in entities {
+                    let _ = system_context.world.despawn(entity);
+                }
+                system_context
+                    .world
+                    .remove_one::<FrameSamples>(camera_entity)
+                    .map(|samples| samples.samples)
+                    .unwrap_or_default()
+            })
+            .await;
+
+        let report = StressTestReport::new(scene, &samples);
+        tracing::info!(
+            frames = report.frames,
+            fps = report.fps,
+            p99 = report.frame_time.p99,
+            "stress test finished"
+        );
+
+        let path = format!(
+            "stress-test-{}.json",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+        );
+        let mut file = web_fs.open(&path, OpenOptions::new().create(true)).await?;
+        file.write(serde_json::to_vec_pretty(&report)?).await?;
+        tracing::info!(%path, "wrote stress test report");
+
+        Ok::<(), web_fs::Error>(())
+    });
+}
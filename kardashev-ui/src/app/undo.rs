@@ -0,0 +1,159 @@
+//! Undo and redo of changes made through the UI.
+//!
+//! Changes are made by executing a [`Command`] on the [`UndoStack`]. Commands
+//! are applied locally right away, and then sent to the server. If the server
+//! rejects a command, the local change is rolled back, and the command is
+//! removed from the stack.
+//!
+//! Ctrl+Z and Ctrl+Shift+Z (or Ctrl+Y) undo and redo the last command, see
+//! [`ActionMap`](crate::input::actions::ActionMap).
+
+use std::rc::Rc;
+
+use futures::future::LocalBoxFuture;
+use leptos::{
+    create_rw_signal,
+    expect_context,
+    provide_context,
+    store_value,
+    RwSignal,
+    SignalUpdate,
+    SignalWith,
+    StoredValue,
+};
+
+use crate::{
+    input::actions::{
+        use_action,
+        Action,
+    },
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    utils::futures::spawn_local,
+};
+
+/// Maximum number of commands that can be undone.
+const MAX_COMMANDS: usize = 100;
+
+/// A change that can be undone.
+pub trait Command: 'static {
+    /// Message ID of the notification that is shown if the server rejects the
+    /// command.
+    fn error_title(&self) -> &'static str;
+
+    /// Applies the change locally.
+    fn apply(&self);
+
+    /// Reverts the local change.
+    fn revert(&self);
+
+    /// Sends the change to the server, or its reversal if `undo` is set.
+    fn send(&self, undo: bool) -> LocalBoxFuture<'static, Result<(), kardashev_client::Error>>;
+}
+
+/// Commands that can be undone and redone, provided as context.
+#[derive(Clone, Copy)]
+pub struct UndoStack {
+    undo: RwSignal<Vec<Rc<dyn Command>>>,
+    redo: RwSignal<Vec<Rc<dyn Command>>>,
+    notifications: StoredValue<Notifications>,
+}
+
+impl UndoStack {
+    /// Applies a command, and sends it to the server.
+    pub fn execute(&self, command: impl Command) {
+        let command: Rc<dyn Command> = Rc::new(command);
+        command.apply();
+        self.undo.update(|undo| {
+            undo.push(command.clone());
+            if undo.len() > MAX_COMMANDS {
+                undo.remove(0);
+            }
+        });
+        self.redo.update(Vec::clear);
+        self.send(command, false);
+    }
+
+    pub fn undo(&self) {
+        let Some(command) = self.undo.try_update(Vec::pop).flatten()
+        else {
+            return;
+        };
+        command.revert();
+        self.redo.update(|redo| redo.push(command.clone()));
+        self.send(command, true);
+    }
+
+    pub fn redo(&self) {
+        let Some(command) = self.redo.try_update(Vec::pop).flatten()
+        else {
+            return;
+        };
+        command.apply();
+        self.undo.update(|undo| undo.push(command.clone()));
+        self.send(command, false);
+    }
+
+    #[allow(dead_code)]
+    pub fn can_undo(&self) -> bool {
+        self.undo.with(|undo| !undo.is_empty())
+    }
+
+    #[allow(dead_code)]
+    pub fn can_redo(&self) -> bool {
+        self.redo.with(|redo| !redo.is_empty())
+    }
+
+    fn send(&self, command: Rc<dyn Command>, undo: bool) {
+        let stack = *self;
+        spawn_local(async move {
+            let Err(error) = command.send(undo).await
+            else {
+                return;
+            };
+            tracing::error!(%error, undo, "command was rejected");
+
+            // roll back, and forget the command, since it can't be undone or redone
+            // anymore.
+            if undo {
+                command.apply();
+            }
+            else {
+                command.revert();
+            }
+            stack.undo.update(|commands| {
+                commands.retain(|other| !Rc::ptr_eq(other, &command));
+            });
+            stack.redo.update(|commands| {
+                commands.retain(|other| !Rc::ptr_eq(other, &command));
+            });
+
+            stack.notifications.with_value(|notifications| {
+                notifications.notify(
+                    Notification::error(command.error_title()).with_message(error.to_string()),
+                );
+            });
+        });
+    }
+}
+
+/// Provides the [`UndoStack`], and handles the undo and redo actions.
+///
+/// This must be called after the [`Notifications`] were provided.
+pub fn provide_undo_stack() {
+    let stack = UndoStack {
+        undo: create_rw_signal(vec![]),
+        redo: create_rw_signal(vec![]),
+        notifications: store_value(expect_context::<Notifications>()),
+    };
+    provide_context(stack);
+
+    use_action(Action::Undo, move || stack.undo());
+    use_action(Action::Redo, move || stack.redo());
+}
+
+pub fn use_undo_stack() -> UndoStack {
+    expect_context()
+}
@@ -1,31 +1,70 @@
-use std::f32::consts::PI;
+use std::{
+    f32::consts::PI,
+    time::Duration,
+};
 
+use kardashev_client::ApiClient;
 use kardashev_style::style;
 use leptos::{
     component,
+    create_rw_signal,
     expect_context,
     on_cleanup,
     store_value,
     view,
     IntoView,
+    Show,
+    SignalGet,
+    SignalSet,
 };
 use nalgebra::{
+    Isometry3,
+    Point2,
     Point3,
     Similarity3,
     Translation3,
     UnitQuaternion,
+    Vector2,
     Vector3,
 };
 use palette::WithAlpha;
+use serde::Deserialize;
 use tokio::sync::{
     mpsc,
     watch,
 };
 
 use crate::{
-    app::components::window::{
-        Window,
-        WindowEvent,
+    app::{
+        components::window::{
+            Window,
+            WindowEvent,
+        },
+        heatmap::HeatmapControl,
+        layers::LayerControl,
+        measure::{
+            MeasureInput,
+            MeasureTool,
+            MeasureToolbar,
+        },
+        minimap::{
+            Minimap,
+            MinimapTarget,
+        },
+        profiling::ProfilingOverlay,
+        regions::RegionLabels,
+        search::StarSearch,
+        star_labels::StarLabels,
+        stress::{
+            run_stress_test,
+            StressScene,
+        },
+    },
+    audio::spatial::AudioListener,
+    collision::{
+        Collider,
+        CollisionLayers,
+        PushOut,
     },
     ecs::{
         plugin::{
@@ -37,20 +76,30 @@ use crate::{
         Label,
     },
     graphics::{
-        blinn_phong::{
-            BlinnPhongRenderPipeline,
-            CreateBlinnPhongRenderPipeline,
-        },
+        blinn_phong::BlinnPhongMaterial,
         camera::{
             CameraProjection,
             ClearColor,
             DontRender,
+            Projection,
             RenderTarget,
         },
+        gizmo::{
+            CreateGizmoPipeline,
+            GizmoPipeline,
+        },
         hdr::CreateToneMapPass,
-        pbr::{
-            CreatePbrRenderPipeline,
-            PbrRenderPipeline,
+        material_pipeline::{
+            CreateMaterialPipeline,
+            CreateMaterialPipelines,
+            MaterialPipeline,
+            MaterialPipelines,
+            MaterialRegistry,
+        },
+        pbr::PbrMaterial,
+        recorder::{
+            FrameRecorder,
+            RecorderError,
         },
         render_3d::{
             CreateRender3dPass,
@@ -58,28 +107,78 @@ use crate::{
             CreateRender3dPipelineContext,
             Render3dPipeline,
             Render3dPipelineContext,
+            Render3dPrepareContext,
+            Render3dTimings,
         },
         render_frame::{
             AttachedRenderPass,
             CreateRenderPass,
         },
+        screenshot::{
+            Screenshot,
+            TakeScreenshot,
+        },
         transform::{
             Parent,
             Transform,
         },
+        viewport::{
+            CreateViewportsPass,
+            ViewportTargets,
+        },
         Surface,
+        SurfaceSize,
     },
     input::{
         keyboard::{
             KeyCode,
-            KeyboardEvent,
+            KeyModifiers,
             KeyboardInput,
         },
         mouse::{
             MouseButton,
             MouseEvent,
+            PointerLock,
         },
+        InputEvent,
         InputState,
+        Timestamped,
+    },
+    raycast::{
+        place_surface_marker,
+        Ray,
+    },
+    selection::{
+        SelectionArea,
+        SelectionCamera,
+        SelectionMode,
+        SelectionRequest,
+    },
+    t,
+    tween::{
+        fields,
+        start_tween,
+        Tween,
+        TweenId,
+    },
+    universe::star::{
+        impostor::{
+            CreateRenderImpostorPipeline,
+            RenderImpostorPipeline,
+            StarLodViewer,
+        },
+        render::{
+            CreateRenderStarPipeline,
+            RenderStarPipeline,
+        },
+    },
+    utils::{
+        console,
+        futures::{
+            spawn_local,
+            spawn_local_and_handle_error,
+        },
+        web_fs::WebFs,
     },
 };
 
@@ -89,35 +188,76 @@ struct Style;
 #[component]
 pub fn WorldView() -> impl IntoView {
     let camera_entity = store_value(None);
+    let parent_camera = create_rw_signal(None);
+    let viewport_targets = create_rw_signal(None);
     let (tx_mouse, rx_mouse) = mpsc::channel(128);
     let (tx_pipeline_switch, rx_pipeline_switch) = watch::channel(WhichPipeline::BlinnPhong);
+    let (tx_marquee, rx_marquee) = watch::channel(None);
+    let marquee = create_rw_signal(None);
+    let materials = expect_context::<MaterialRegistry>();
+    let create_pipeline = CreateWorldViewPipeline {
+        switch: rx_pipeline_switch,
+        materials,
+    };
+    let minimap_pipeline = create_pipeline.clone();
+    let mut camera_inputs = Some((rx_mouse, tx_pipeline_switch, tx_marquee));
 
-    let on_load = move |surface: &Surface| {
-        tracing::debug!("spawning camera for window");
-
+    let on_load = move |surface: &Surface, pointer_lock: PointerLock| {
         let surface_size = surface.size();
-        let aspect = (surface_size.width as f32) / (surface_size.height as f32);
+        let aspect = surface_size.aspect();
 
         let render_target = RenderTarget::from_surface(surface);
         let render_pass = AttachedRenderPass::new(
-            CreateToneMapPass {
-                inner: CreateRender3dPass {
-                    create_pipeline: CreateWorldViewPipeline {
-                        switch: rx_pipeline_switch,
+            CreateViewportsPass {
+                inner: CreateToneMapPass {
+                    inner: CreateRender3dPass {
+                        create_pipeline: create_pipeline.clone(),
                     },
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    bloom: true,
                 },
-                format: wgpu::TextureFormat::Rgba16Float,
             }
             .create_render_pass_from_surface(&surface),
         );
+        viewport_targets.set(Some(ViewportTargets::from_surface(surface)));
 
         let world = expect_context::<WorldServer>();
+
+        // the surface was recreated after the graphics were restarted.
+        if let Some(entity) = camera_entity.get_value() {
+            tracing::debug!("replacing render target of camera");
+            let _ = world.run(move |system_context| {
+                let _ = system_context
+                    .world
+                    .insert(entity, (render_target, render_pass));
+            });
+            return;
+        }
+        let Some((rx_mouse, tx_pipeline_switch, tx_marquee)) = camera_inputs.take()
+        else {
+            return;
+        };
+
+        tracing::debug!("spawning camera for window");
+        let world2 = world.clone();
         let _ = world.run(move |system_context| {
             let entity = system_context.world.spawn((
                 Label::new_static("map camera"),
+                MinimapTarget,
+                StarLodViewer,
+                AudioListener,
                 Transform::look_at(Point3::new(0., 0., 5.), Point3::origin(), Vector3::y()),
-                CameraProjection::new(aspect, PI / 3.0, 0.1, 100.),
+                CameraProjection::perspective(
+                    aspect,
+                    WorldViewCameraController::DEFAULT_FOVY,
+                    0.1,
+                    100.,
+                ),
                 ClearColor::new(palette::named::BLACK.into_format().with_alpha(1.0)),
+                // larger than the near plane, so that planets aren't clipped by it.
+                Collider::sphere(WorldViewCameraController::COLLIDER_RADIUS)
+                    .with_layers(CollisionLayers::CAMERA, CollisionLayers::PLANETS),
+                PushOut,
                 WorldViewCameraController {
                     mouse_input: rx_mouse,
                     keyboard_input: system_context
@@ -127,10 +267,16 @@ pub fn WorldView() -> impl IntoView {
                         .clone(),
                     state: Default::default(),
                     z_mouse: 10.0,
+                    fovy: WorldViewCameraController::DEFAULT_FOVY,
                     switch_pipeline: tx_pipeline_switch,
+                    surface_size,
+                    press: None,
+                    marquee: tx_marquee,
+                    pointer_lock,
                 },
                 render_target,
                 render_pass,
+                Render3dTimings::default(),
             ));
 
             let _light = system_context.world.spawn((
@@ -142,6 +288,9 @@ pub fn WorldView() -> impl IntoView {
             ));
 
             camera_entity.set_value(Some(entity));
+            parent_camera.set(Some(entity));
+
+            register_console_commands(world2, entity);
         });
     };
 
@@ -153,13 +302,12 @@ pub fn WorldView() -> impl IntoView {
             WindowEvent::Resize { surface_size } => {
                 if let Some(camera_entity) = camera_entity.get_value() {
                     let world = expect_context::<WorldServer>();
-                    let aspect = (surface_size.width as f32) / (surface_size.height as f32);
                     let _ = world.run(move |system_context| {
-                        let mut camera = system_context
+                        let controller = system_context
                             .world
-                            .get::<&mut CameraProjection>(camera_entity)
+                            .query_one_mut::<&mut WorldViewCameraController>(camera_entity)
                             .unwrap();
-                        camera.projection_matrix.set_aspect(aspect);
+                        controller.surface_size = surface_size;
                     });
                 }
             }
@@ -183,6 +331,8 @@ pub fn WorldView() -> impl IntoView {
     };
 
     on_cleanup(move || {
+        unregister_console_commands();
+
         camera_entity.update_value(|camera_entity| {
             if let Some(camera_entity) = *camera_entity {
                 let world = expect_context::<WorldServer>();
@@ -194,16 +344,60 @@ pub fn WorldView() -> impl IntoView {
         });
     });
 
+    let join_handle = spawn_local(async move {
+        let mut rx_marquee = rx_marquee;
+        loop {
+            marquee.set(*rx_marquee.borrow_and_update());
+            if rx_marquee.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+    on_cleanup(move || join_handle.abort());
+
+    let marquee_style = move || {
+        marquee.get().and_then(|area| {
+            match area {
+                SelectionArea::Rect { min, max } => {
+                    Some(format!(
+                        "left: {}px; top: {}px; width: {}px; height: {}px;",
+                        min.x,
+                        min.y,
+                        max.x - min.x,
+                        max.y - min.y
+                    ))
+                }
+                SelectionArea::Point(_) => None,
+            }
+        })
+    };
+
     view! {
         <div class=Style::window>
-            <Window on_load on_event />
+            <Window on_load on_event label=t!("world-view-label") />
+            <Show when=move || marquee_style().is_some()>
+                <div class=Style::marquee style=marquee_style></div>
+            </Show>
+            <RegionLabels />
+            <StarLabels />
+            <LayerControl />
+            <HeatmapControl />
+            <StarSearch />
+            <MeasureToolbar />
+            <ProfilingOverlay camera_entity />
+            <Minimap
+                parent=parent_camera
+                targets=viewport_targets
+                create_pipeline=minimap_pipeline
+            />
         </div>
     }
 }
 
 #[derive(Clone, Debug)]
-struct CreateWorldViewPipeline {
+pub(super) struct CreateWorldViewPipeline {
     switch: watch::Receiver<WhichPipeline>,
+    materials: MaterialRegistry,
 }
 
 impl CreateRender3dPipeline for CreateWorldViewPipeline {
@@ -212,8 +406,15 @@ impl CreateRender3dPipeline for CreateWorldViewPipeline {
     fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> WorldViewPipeline {
         WorldViewPipeline {
             switch: self.switch,
-            pbr: CreatePbrRenderPipeline.create_pipeline(context),
-            blinn_phong: CreateBlinnPhongRenderPipeline.create_pipeline(context),
+            pbr: CreateMaterialPipeline::default().create_pipeline(context),
+            blinn_phong: CreateMaterialPipeline::default().create_pipeline(context),
+            materials: CreateMaterialPipelines {
+                materials: self.materials,
+            }
+            .create_pipeline(context),
+            stars: CreateRenderStarPipeline.create_pipeline(context),
+            impostors: CreateRenderImpostorPipeline.create_pipeline(context),
+            gizmos: CreateGizmoPipeline.create_pipeline(context),
         }
     }
 }
@@ -234,13 +435,21 @@ impl WhichPipeline {
 }
 
 #[derive(Debug)]
-struct WorldViewPipeline {
+pub(super) struct WorldViewPipeline {
     switch: watch::Receiver<WhichPipeline>,
-    pbr: PbrRenderPipeline,
-    blinn_phong: BlinnPhongRenderPipeline,
+    pbr: MaterialPipeline<PbrMaterial>,
+    blinn_phong: MaterialPipeline<BlinnPhongMaterial>,
+    materials: MaterialPipelines,
+    stars: RenderStarPipeline,
+    impostors: RenderImpostorPipeline,
+    gizmos: GizmoPipeline,
 }
 
 impl Render3dPipeline for WorldViewPipeline {
+    fn prepare(&mut self, prepare_context: &mut Render3dPrepareContext) {
+        self.stars.prepare(prepare_context);
+    }
+
     fn render(&mut self, pipeline_context: &mut Render3dPipelineContext) {
         match *self.switch.borrow() {
             WhichPipeline::Pbr => {
@@ -250,92 +459,479 @@ impl Render3dPipeline for WorldViewPipeline {
                 self.blinn_phong.render(pipeline_context);
             }
         }
+        self.materials.render(pipeline_context);
+        self.stars.render(pipeline_context);
+        self.impostors.render(pipeline_context);
+        self.gizmos.render(pipeline_context);
+    }
+
+    fn render_depth(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        match *self.switch.borrow() {
+            WhichPipeline::Pbr => {
+                self.pbr.render_depth(pipeline_context);
+            }
+            WhichPipeline::BlinnPhong => {
+                self.blinn_phong.render_depth(pipeline_context);
+            }
+        }
+        self.materials.render_depth(pipeline_context);
+    }
+
+    fn render_transparent(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        match *self.switch.borrow() {
+            WhichPipeline::Pbr => {
+                self.pbr.render_transparent(pipeline_context);
+            }
+            WhichPipeline::BlinnPhong => {
+                self.blinn_phong.render_transparent(pipeline_context);
+            }
+        }
+        self.materials.render_transparent(pipeline_context);
     }
 }
 
 #[derive(Debug)]
-struct WorldViewCameraController {
-    mouse_input: mpsc::Receiver<MouseEvent>,
+pub(super) struct WorldViewCameraController {
+    mouse_input: mpsc::Receiver<Timestamped<MouseEvent>>,
     keyboard_input: KeyboardInput,
     state: InputState,
     z_mouse: f32,
+
+    /// Field of view the camera is zooming to.
+    fovy: f32,
+
     switch_pipeline: watch::Sender<WhichPipeline>,
+    surface_size: SurfaceSize,
+    press: Option<Press>,
+    marquee: watch::Sender<Option<SelectionArea>>,
+
+    /// Clicking the middle mouse button toggles free-look, which locks the
+    /// pointer and rotates the camera with the mouse.
+    pointer_lock: PointerLock,
+}
+
+impl WorldViewCameraController {
+    pub(super) const DEFAULT_FOVY: f32 = PI / 3.0;
+
+    const COLLIDER_RADIUS: f32 = 0.2;
+
+    /// Range of the field of view that can be zoomed to with alt and the mouse
+    /// wheel.
+    const MIN_FOVY: f32 = PI / 36.0;
+    const MAX_FOVY: f32 = PI / 2.0;
+
+    const ZOOM_DURATION: Duration = Duration::from_millis(300);
+}
+
+/// Left mouse button press in a world view.
+///
+/// Releasing the button without dragging selects the entity under the mouse.
+/// Dragging with shift held selects all entities within the marquee, otherwise
+/// it moves the camera.
+#[derive(Clone, Copy, Debug)]
+struct Press {
+    position: Point2<f32>,
+    modifiers: KeyModifiers,
+    dragged: bool,
+}
+
+impl Press {
+    /// Distance in pixels the mouse has to move for a press to become a drag.
+    const DRAG_THRESHOLD: f32 = 4.0;
+}
+
+/// Arguments of the `screenshot` console command.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+struct ScreenshotArgs {
+    target: ScreenshotTarget,
+}
+
+/// Where screenshots go.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ScreenshotTarget {
+    /// Downloaded by the browser.
+    #[default]
+    Download,
+
+    /// Stored in the `screenshots` web fs.
+    WebFs,
+}
+
+/// Takes a screenshot of the next frame rendered by the camera, and downloads
+/// or stores it.
+fn request_screenshot(
+    command_buffer: &mut hecs::CommandBuffer,
+    camera_entity: hecs::Entity,
+    target: ScreenshotTarget,
+) {
+    tracing::info!(?target, "taking screenshot");
+
+    let (take_screenshot, rx_screenshot) = TakeScreenshot::new();
+    command_buffer.insert_one(camera_entity, take_screenshot);
+
+    spawn_local_and_handle_error(async move {
+        let screenshot = rx_screenshot.receive().await?;
+        let filename = Screenshot::default_filename();
+        match target {
+            ScreenshotTarget::Download => screenshot.download(&filename).await,
+            ScreenshotTarget::WebFs => {
+                let web_fs = WebFs::with_named_root("screenshots").await?;
+                screenshot.save(&web_fs, &filename).await
+            }
+        }
+    });
+}
+
+fn register_console_commands(world: WorldServer, camera_entity: hecs::Entity) {
+    console::register_command_with_args("screenshot", {
+        let world = world.clone();
+        move |args: ScreenshotArgs| {
+            let _ = world.run(move |system_context| {
+                request_screenshot(system_context.command_buffer, camera_entity, args.target);
+            });
+        }
+    });
+
+    console::register_command("start_recording", {
+        let world = world.clone();
+        move || {
+            start_recording(world.clone(), camera_entity);
+        }
+    });
+
+    console::register_command("stop_recording", {
+        let world = world.clone();
+        move || {
+            let _ = world.run(move |system_context| {
+                tracing::info!("stopping recording");
+                system_context
+                    .command_buffer
+                    .remove_one::<FrameRecorder>(camera_entity);
+            });
+        }
+    });
+
+    console::register_command_with_args("stress_test", move |scene: StressScene| {
+        run_stress_test(world.clone(), camera_entity, scene);
+    });
+}
+
+fn unregister_console_commands() {
+    console::unregister_command("screenshot");
+    console::unregister_command("start_recording");
+    console::unregister_command("stop_recording");
+    console::unregister_command("stress_test");
+}
+
+/// Starts recording a timelapse of the camera's frames into the `recordings`
+/// web fs.
+fn start_recording(world: WorldServer, camera_entity: hecs::Entity) {
+    spawn_local_and_handle_error(async move {
+        let api_client = world
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<ApiClient>()
+                    .expect("no api client")
+                    .clone()
+            })
+            .await;
+        let web_fs = WebFs::with_named_root("recordings").await?;
+        let name = format!(
+            "timelapse-{}",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+        );
+        tracing::info!(%name, "starting recording");
+
+        let frame_recorder = FrameRecorder::new(web_fs, name)
+            .with_server(api_client)
+            .with_every_nth_frame(60);
+        let _ = world.run(move |system_context| {
+            system_context
+                .command_buffer
+                .insert_one(camera_entity, frame_recorder);
+        });
+
+        Ok::<(), RecorderError>(())
+    });
+}
+
+/// Rotates the camera by the angle the mouse moved at the distance `z_mouse`.
+fn rotate_camera(
+    camera_transform: &mut Transform,
+    camera_projection: &CameraProjection,
+    surface_size: SurfaceSize,
+    z_mouse: f32,
+    delta: Vector2<f32>,
+) {
+    let world_delta = pan_delta(camera_projection, surface_size, z_mouse, delta);
+    let yaw = (world_delta.x / z_mouse).asin();
+    let pitch = (world_delta.y / z_mouse).asin();
+
+    camera_transform.model_matrix.isometry.rotation *=
+        UnitQuaternion::from_axis_angle(&-Vector3::y_axis(), yaw)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), pitch);
+}
+
+/// Distance in world space by which the camera is moved, if the mouse moved by
+/// `delta` pixels at the distance `z_mouse`.
+fn pan_delta(
+    camera_projection: &CameraProjection,
+    surface_size: SurfaceSize,
+    z_mouse: f32,
+    delta: Vector2<f32>,
+) -> Vector2<f32> {
+    // the camera moves in the opposite direction, so that the world follows the
+    // mouse.
+    Vector2::new(-delta.x, delta.y) * camera_projection.pixel_size_at(z_mouse, surface_size)
 }
 
 fn world_view_camera_controller_system(system_context: &mut SystemContext) {
+    let measuring = system_context
+        .resources
+        .get::<MeasureTool>()
+        .map_or(false, MeasureTool::is_active);
+
     let query = system_context.world.query_mut::<(
         &mut WorldViewCameraController,
         &mut Transform,
         &CameraProjection,
     )>();
 
-    for (_entity, (controller, camera_transform, camera_projection)) in query {
-        loop {
-            match controller.mouse_input.try_recv() {
-                Ok(event) => {
-                    controller.state.mouse.push(&event);
-
-                    match event {
-                        MouseEvent::Move { delta, .. } => {
-                            if controller.state.mouse.buttons.is_down(MouseButton::Left) {
-                                let world_delta =
-                                    camera_projection.projection_matrix.unproject_point(
-                                        &Point3::new(delta.x, -delta.y, controller.z_mouse),
-                                    );
-                                camera_transform.model_matrix *= Translation3::from(Vector3::new(
-                                    world_delta.x,
-                                    world_delta.y,
-                                    0.0,
-                                ));
-                            }
+    let mut selection_requests = vec![];
+    let mut measure_inputs = vec![];
+    let mut marker_rays = vec![];
+    let mut zoom_tweens = vec![];
 
-                            if controller.state.mouse.buttons.is_down(MouseButton::Right) {
-                                let world_delta =
-                                    camera_projection.projection_matrix.unproject_point(
-                                        &Point3::new(delta.x, -delta.y, controller.z_mouse),
-                                    );
-                                let yaw = (world_delta.x / controller.z_mouse).asin();
-                                let pitch = (world_delta.y / controller.z_mouse).asin();
-
-                                camera_transform.model_matrix.isometry.rotation *=
-                                    UnitQuaternion::from_axis_angle(&-Vector3::y_axis(), yaw)
-                                        * UnitQuaternion::from_axis_angle(
-                                            &Vector3::x_axis(),
-                                            pitch,
-                                        );
+    for (entity, (controller, camera_transform, camera_projection)) in query {
+        while let Ok(event) = controller.mouse_input.try_recv() {
+            controller.state.push(event.map(InputEvent::Mouse));
+        }
+        while let Some(event) = controller.keyboard_input.try_next() {
+            controller.state.push(event.map(InputEvent::Keyboard));
+        }
+        controller.state.begin_tick();
+
+        while let Some(Timestamped { event, .. }) = controller.state.next_event() {
+            let InputEvent::Mouse(event) = event
+            else {
+                continue;
+            };
+
+            match event {
+                // in free-look, the cursor is hidden and its position is stale.
+                MouseEvent::ButtonDown {
+                    button: MouseButton::Left,
+                    position,
+                    modifiers,
+                } if !controller.state.mouse.pointer_locked => {
+                    controller.press = Some(Press {
+                        position,
+                        modifiers,
+                        dragged: false,
+                    });
+                }
+                MouseEvent::ButtonUp {
+                    button: MouseButton::Left,
+                    position,
+                    ..
+                } => {
+                    if let Some(press) = controller.press.take() {
+                        controller.marquee.send_replace(None);
+
+                        let shift = press.modifiers.contains(KeyModifiers::SHIFT);
+                        let camera = SelectionCamera {
+                            transform: camera_transform.model_matrix,
+                            projection: *camera_projection,
+                            surface_size: controller.surface_size,
+                        };
+                        let area_and_mode = if measuring && !press.dragged {
+                            measure_inputs.push(MeasureInput {
+                                camera,
+                                position,
+                                clicked: true,
+                            });
+                            None
+                        }
+                        else if !press.dragged && press.modifiers.contains(KeyModifiers::ALT) {
+                            marker_rays.extend(Ray::from_screen(&camera, position));
+                            None
+                        }
+                        else if !press.dragged {
+                            let mode = if shift {
+                                SelectionMode::Toggle
                             }
+                            else {
+                                SelectionMode::Replace
+                            };
+                            Some((SelectionArea::Point(position), mode))
+                        }
+                        else if shift {
+                            Some((
+                                SelectionArea::rect(press.position, position),
+                                SelectionMode::Add,
+                            ))
                         }
-                        MouseEvent::Wheel { delta, .. } => {
-                            camera_transform.model_matrix *=
-                                Translation3::from(Vector3::new(0.0, 0.0, delta.y / 1000.0));
+                        else {
+                            None
+                        };
+
+                        if let Some((area, mode)) = area_and_mode {
+                            selection_requests.push(SelectionRequest { camera, area, mode });
                         }
-                        _ => {}
                     }
                 }
-                Err(_) => break,
-            }
-        }
+                MouseEvent::Leave => {
+                    controller.press = None;
+                    controller.marquee.send_replace(None);
+                }
+                MouseEvent::Move { position, delta } => {
+                    if measuring {
+                        measure_inputs.push(MeasureInput {
+                            camera: SelectionCamera {
+                                transform: camera_transform.model_matrix,
+                                projection: *camera_projection,
+                                surface_size: controller.surface_size,
+                            },
+                            position,
+                            clicked: false,
+                        });
+                    }
 
-        loop {
-            match controller.keyboard_input.try_next() {
-                Some(event) => {
-                    match event {
-                        KeyboardEvent::KeyDown {
-                            code: KeyCode::F9,
-                            repeat: false,
-                            ..
-                        } => {
-                            controller
-                                .switch_pipeline
-                                .send_modify(|which| which.toggle());
+                    let mut marquee = None;
+                    if let Some(press) = &mut controller.press {
+                        if (position - press.position).norm() > Press::DRAG_THRESHOLD {
+                            press.dragged = true;
+                        }
+                        if press.modifiers.contains(KeyModifiers::SHIFT) {
+                            marquee = Some(SelectionArea::rect(press.position, position));
                         }
-                        _ => {}
                     }
+
+                    if marquee.is_some() {
+                        controller.marquee.send_replace(marquee);
+                    }
+                    else if controller.state.mouse.buttons.is_down(MouseButton::Left) {
+                        let world_delta = pan_delta(
+                            camera_projection,
+                            controller.surface_size,
+                            controller.z_mouse,
+                            delta,
+                        );
+                        camera_transform.model_matrix *=
+                            Translation3::from(Vector3::new(world_delta.x, world_delta.y, 0.0));
+                    }
+
+                    if controller.state.mouse.buttons.is_down(MouseButton::Right) {
+                        rotate_camera(
+                            camera_transform,
+                            camera_projection,
+                            controller.surface_size,
+                            controller.z_mouse,
+                            delta,
+                        );
+                    }
+                }
+                MouseEvent::LockedMove { delta } => {
+                    rotate_camera(
+                        camera_transform,
+                        camera_projection,
+                        controller.surface_size,
+                        controller.z_mouse,
+                        delta,
+                    );
+                }
+                // with alt the wheel zooms by changing the field of view.
+                MouseEvent::Wheel { delta, .. }
+                    if controller.state.keyboard.is_pressed(KeyCode::AltLeft) =>
+                {
+                    // zooming continues from the last target, so that scrolling fast zooms further.
+                    controller.fovy = (controller.fovy * (delta.y / 1000.0).exp()).clamp(
+                        WorldViewCameraController::MIN_FOVY,
+                        WorldViewCameraController::MAX_FOVY,
+                    );
+                    zoom_tweens.push((
+                        entity,
+                        Tween::new(
+                            fields::projection,
+                            Projection::Perspective {
+                                fovy: controller.fovy,
+                            },
+                            WorldViewCameraController::ZOOM_DURATION,
+                        ),
+                    ));
                 }
-                None => break,
+                MouseEvent::Wheel { delta, .. } => {
+                    camera_transform.model_matrix *=
+                        Translation3::from(Vector3::new(0.0, 0.0, delta.y / 1000.0));
+                }
+                _ => {}
+            }
+        }
+
+        if controller.state.mouse.just_pressed(MouseButton::Middle) {
+            if controller.state.mouse.pointer_locked {
+                controller.pointer_lock.release();
+            }
+            else {
+                controller.pointer_lock.request();
             }
         }
+        if controller.state.keyboard.just_pressed(KeyCode::F9) {
+            controller
+                .switch_pipeline
+                .send_modify(|which| which.toggle());
+        }
+        if controller.state.keyboard.just_pressed(KeyCode::F2) {
+            request_screenshot(
+                system_context.command_buffer,
+                entity,
+                ScreenshotTarget::Download,
+            );
+        }
     }
+
+    for selection_request in selection_requests {
+        selection_request.apply(system_context);
+    }
+
+    for ray in marker_rays {
+        place_surface_marker(system_context, &ray);
+    }
+
+    for measure_input in measure_inputs {
+        measure_input.apply(system_context);
+    }
+
+    for (entity, tween) in zoom_tweens {
+        start_tween(system_context, entity, tween);
+    }
+}
+
+/// How long it takes the map camera to fly to another view.
+const FLY_TO_DURATION: Duration = Duration::from_millis(800);
+
+/// Flies the map camera to the view returned by `to`, which is passed the
+/// camera's current view.
+///
+/// Returns the ID of the camera's tween, if there is a map camera.
+pub fn fly_map_camera(
+    system_context: &mut SystemContext,
+    to: impl FnOnce(&Isometry3<f32>) -> Isometry3<f32>,
+) -> Option<TweenId> {
+    let camera = system_context
+        .world
+        .query_mut::<&Transform>()
+        .with::<&MinimapTarget>()
+        .into_iter()
+        .next()
+        .map(|(entity, transform)| (entity, transform.model_matrix.isometry));
+
+    let (entity, from) = camera?;
+    let tween = Tween::new(fields::isometry, to(&from), FLY_TO_DURATION);
+    Some(start_tween(system_context, entity, tween))
 }
 
 pub struct MapPlugin;
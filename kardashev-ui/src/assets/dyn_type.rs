@@ -1,5 +1,9 @@
 use std::{
-    any::type_name,
+    any::{
+        type_name,
+        TypeId,
+    },
+    collections::HashSet,
     fmt::Debug,
     future::Future,
     marker::PhantomData,
@@ -10,14 +14,20 @@ use std::{
 use kardashev_protocol::assets::AssetId;
 use tokio::sync::oneshot;
 
-use crate::assets::{
-    load::{
-        Load,
-        LoadAssetContext,
-        LoadAssetState,
-        LoadFromAsset,
+use crate::{
+    assets::{
+        load::{
+            Load,
+            LoadAssetContext,
+            LoadAssetState,
+            LoadFromAsset,
+        },
+        server::AssetServer,
+    },
+    notifications::{
+        Notification,
+        Notifications,
     },
-    server::AssetServer,
 };
 
 #[derive(Clone, Copy)]
@@ -54,6 +64,13 @@ pub(super) trait DynAssetTypeTrait {
     fn loader_system<'w>(
         &self,
         asset_server: &AssetServer,
+        notifications: Option<&Notifications>,
+        world: &'w mut hecs::World,
+        command_buffer: &'w mut hecs::CommandBuffer,
+    );
+    fn reload<'w>(
+        &self,
+        asset_ids: &HashSet<AssetId>,
         world: &'w mut hecs::World,
         command_buffer: &'w mut hecs::CommandBuffer,
     );
@@ -71,6 +88,7 @@ impl<A: LoadFromAsset> DynAssetTypeTrait for DynAssetTypeImpl<A> {
     fn loader_system<'w>(
         &self,
         asset_server: &AssetServer,
+        notifications: Option<&Notifications>,
         world: &'w mut hecs::World,
         command_buffer: &'w mut hecs::CommandBuffer,
     ) {
@@ -95,6 +113,18 @@ impl<A: LoadFromAsset> DynAssetTypeTrait for DynAssetTypeImpl<A> {
                                 }
                                 Err(error) => {
                                     tracing::error!(asset_id = %load.asset_id, ?error, "failed to load asset");
+                                    if let Some(notifications) = notifications {
+                                        notifications.notify(
+                                            Notification::error("notification-asset-load-failed")
+                                                .with_message(format!(
+                                                    "{}: {error}",
+                                                    load.asset_id
+                                                )),
+                                        );
+                                    }
+                                    if let Some(fallback) = A::fallback() {
+                                        command_buffer.insert_one(entity, fallback);
+                                    }
                                 }
                             }
 
@@ -111,6 +141,27 @@ impl<A: LoadFromAsset> DynAssetTypeTrait for DynAssetTypeImpl<A> {
             }
         }
     }
+
+    fn reload<'w>(
+        &self,
+        asset_ids: &HashSet<AssetId>,
+        world: &'w mut hecs::World,
+        command_buffer: &'w mut hecs::CommandBuffer,
+    ) {
+        for (entity, asset) in world.query_mut::<&A>() {
+            let Some(asset_id) = asset
+                .maybe_asset_id()
+                .filter(|asset_id| asset_ids.contains(asset_id))
+            else {
+                continue;
+            };
+            if let Some(args) = asset.reload_args() {
+                tracing::debug!(%asset_id, asset_type = type_name::<A>(), "reloading asset");
+                command_buffer.remove_one::<A>(entity);
+                command_buffer.insert_one(entity, Load::<A>::with_args(asset_id, args));
+            }
+        }
+    }
 }
 
 impl<A> Clone for DynAssetTypeImpl<A> {
@@ -142,6 +193,14 @@ impl DynAssetLoadRequest {
         self.inner.asset_id()
     }
 
+    /// Identifies the loaded asset by its type and ID.
+    ///
+    /// Requests with the same key load the same data, but they might differ in
+    /// their arguments.
+    pub fn key(&self) -> (TypeId, AssetId) {
+        (self.inner.asset_type_id(), self.inner.asset_id())
+    }
+
     pub async fn load<'a>(self, context: &'a mut LoadAssetContext<'a>) {
         self.inner.load(context).await;
     }
@@ -155,6 +214,7 @@ impl Debug for DynAssetLoadRequest {
 
 trait DynAssetLoadRequestTrait {
     fn asset_type_name(&self) -> &'static str;
+    fn asset_type_id(&self) -> TypeId;
     fn asset_id(&self) -> AssetId;
 
     fn load<'a>(
@@ -174,6 +234,10 @@ impl<A: LoadFromAsset> DynAssetLoadRequestTrait for DynAssetLoadRequestImpl<A> {
         type_name::<A>()
     }
 
+    fn asset_type_id(&self) -> TypeId {
+        TypeId::of::<A>()
+    }
+
     fn asset_id(&self) -> AssetId {
         self.asset_id
     }
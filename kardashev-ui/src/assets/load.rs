@@ -27,6 +27,10 @@ use crate::{
 
 /// Trait for assets that can be loaded from the asset API.
 ///
+/// Concurrent loads of the same asset are run one after the other, so that
+/// loaders can share downloaded and decoded data through the
+/// [`LoadAssetContext::cache`].
+///
 /// See also [`GpuAsset`][`crate::rendering::loading::GpuAsset`].
 pub trait LoadFromAsset: MaybeHasAssetId + Sized + Send + Sync + 'static {
     type Dist;
@@ -38,6 +42,21 @@ pub trait LoadFromAsset: MaybeHasAssetId + Sized + Send + Sync + 'static {
         args: Self::Args,
         context: &'a mut LoadAssetContext<'b>,
     ) -> impl Future<Output = Result<Self, Self::Error>> + 'a;
+
+    /// Returns an asset to attach instead, if loading failed.
+    ///
+    /// By default nothing is attached.
+    fn fallback() -> Option<Self> {
+        None
+    }
+
+    /// Returns the arguments to load the asset again with, when it changed on
+    /// the asset server.
+    ///
+    /// By default assets are not reloaded.
+    fn reload_args(&self) -> Option<Self::Args> {
+        None
+    }
 }
 
 /// An asset in the process of being loaded.
@@ -1,7 +1,7 @@
 mod dyn_type;
 pub mod image;
 pub mod load;
-mod server;
+pub mod server;
 pub mod store;
 pub mod system;
 
@@ -1,3 +1,13 @@
+use std::{
+    any::TypeId,
+    collections::{
+        HashSet,
+        VecDeque,
+    },
+    fmt::Debug,
+    sync::Arc,
+};
+
 use kardashev_client::{
     AssetClient,
     Events,
@@ -5,10 +15,15 @@ use kardashev_client::{
 use kardashev_protocol::assets::{
     self as dist,
     AssetId,
+    ContentPack,
+    HasAssetId,
+    ManifestError,
 };
 use tokio::sync::{
+    broadcast,
     mpsc,
     oneshot,
+    watch,
 };
 
 use crate::{
@@ -25,6 +40,11 @@ use crate::{
         store::AssetStore,
         Error,
     },
+    error::error_chain,
+    notifications::{
+        Notification,
+        Notifications,
+    },
     utils::{
         any_cache::AnyArcCache,
         futures::spawn_local_and_handle_error,
@@ -35,13 +55,56 @@ use crate::{
 #[derive(Clone, Debug)]
 pub struct AssetServer {
     tx_command: mpsc::UnboundedSender<Command>,
+    rx_manifest_state: watch::Receiver<ManifestState>,
+    tx_retry: mpsc::UnboundedSender<()>,
+    tx_reload: broadcast::Sender<Arc<HashSet<AssetId>>>,
 }
 
 impl AssetServer {
-    pub fn new(client: AssetClient) -> Self {
+    /// Creates the asset server, which loads the manifest, and the manifests of
+    /// the server's content packs and `content_packs`.
+    pub fn new(
+        client: AssetClient,
+        content_packs: Vec<ContentPack>,
+        notifications: Option<Notifications>,
+    ) -> Self {
         let (tx_command, rx_command) = mpsc::unbounded_channel();
-        Reactor::spawn(client, rx_command);
-        AssetServer { tx_command }
+        let (tx_manifest_state, rx_manifest_state) = watch::channel(ManifestState::Loading);
+        let (tx_retry, rx_retry) = mpsc::unbounded_channel();
+        let (tx_reload, _) = broadcast::channel(16);
+        Reactor::spawn(
+            client,
+            content_packs,
+            notifications,
+            rx_command,
+            tx_manifest_state,
+            rx_retry,
+            tx_reload.clone(),
+        );
+        AssetServer {
+            tx_command,
+            rx_manifest_state,
+            tx_retry,
+            tx_reload,
+        }
+    }
+
+    /// Returns a receiver for whether the asset manifest was loaded.
+    ///
+    /// Load requests are queued until it is.
+    pub fn manifest_state(&self) -> watch::Receiver<ManifestState> {
+        self.rx_manifest_state.clone()
+    }
+
+    /// Downloads the asset manifest again, if it failed.
+    pub fn retry_manifest(&self) {
+        let _ = self.tx_retry.send(());
+    }
+
+    /// Returns a receiver for the IDs of assets that changed on the asset
+    /// server and should be reloaded.
+    pub fn reloads(&self) -> broadcast::Receiver<Arc<HashSet<AssetId>>> {
+        self.tx_reload.subscribe()
     }
 
     pub(super) fn send_command(&self, command: Command) {
@@ -71,6 +134,35 @@ impl AssetServer {
         }
     }
 
+    /// Returns the IDs of all assets of type `A` in the manifest that match
+    /// `filter`.
+    pub async fn find<A: dist::Asset>(
+        &self,
+        filter: impl Fn(&A) -> bool + Send + 'static,
+    ) -> Vec<AssetId> {
+        self.find_map(move |asset: &A| filter(asset).then(|| asset.asset_id()))
+            .await
+    }
+
+    /// Maps all assets of type `A` in the manifest with `filter_map`, and
+    /// returns the values for which it returned `Some`.
+    pub async fn find_map<A: dist::Asset, R: Send + 'static>(
+        &self,
+        filter_map: impl Fn(&A) -> Option<R> + Send + 'static,
+    ) -> Vec<R> {
+        let (tx, rx) = oneshot::channel();
+        self.send_command(Command::Find {
+            find: Box::new(move |assets| {
+                let values = assets
+                    .iter::<A>()
+                    .filter_map(|asset| filter_map(asset))
+                    .collect();
+                let _ = tx.send(values);
+            }),
+        });
+        rx.await.expect("asset server died")
+    }
+
     pub fn register_asset_type<A: LoadFromAsset>(&self) {
         self.send_command(Command::RegisterAssetType {
             asset_type: DynAssetType::new::<A>(),
@@ -78,6 +170,16 @@ impl AssetServer {
     }
 }
 
+/// Whether the asset manifest was loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestState {
+    Loading,
+    Loaded,
+
+    /// Loading the manifest failed. The error is formatted with its sources.
+    Failed(String),
+}
+
 #[derive(Debug)]
 struct Reactor {
     client: AssetClient,
@@ -85,19 +187,38 @@ struct Reactor {
     assets: dist::Assets,
     cache: AnyArcCache<AssetId>,
     rx_command: mpsc::UnboundedReceiver<Command>,
+    notifications: Option<Notifications>,
+    tx_reload: broadcast::Sender<Arc<HashSet<AssetId>>>,
+
+    load_queue: LoadQueue,
 }
 
 impl Reactor {
-    fn spawn(client: AssetClient, rx_command: mpsc::UnboundedReceiver<Command>) {
+    fn spawn(
+        client: AssetClient,
+        content_packs: Vec<ContentPack>,
+        notifications: Option<Notifications>,
+        rx_command: mpsc::UnboundedReceiver<Command>,
+        tx_manifest_state: watch::Sender<ManifestState>,
+        mut rx_retry: mpsc::UnboundedReceiver<()>,
+        tx_reload: broadcast::Sender<Arc<HashSet<AssetId>>>,
+    ) {
         spawn_local_and_handle_error(async move {
-            let manifest = client.get_manifest().await?;
-
-            let mut dist_asset_types = dist::AssetTypes::default();
-            dist_asset_types.with_builtin();
-            let assets = manifest.assets.parse(&dist_asset_types)?;
-            for ty in assets.unrecognized_types() {
-                tracing::warn!("unrecognized asset type: {ty:?}");
-            }
+            let assets = loop {
+                tx_manifest_state.send_replace(ManifestState::Loading);
+                match load_manifest(&client, &content_packs, notifications.as_ref()).await {
+                    Ok(assets) => break assets,
+                    Err(error) => {
+                        let error = error_chain(&error);
+                        tracing::error!(%error, "failed to load asset manifest");
+                        tx_manifest_state.send_replace(ManifestState::Failed(error));
+                        if rx_retry.recv().await.is_none() {
+                            return Ok(());
+                        }
+                    }
+                }
+            };
+            tx_manifest_state.send_replace(ManifestState::Loaded);
 
             let asset_store = AssetStore::new().await?;
 
@@ -107,6 +228,9 @@ impl Reactor {
                 assets,
                 cache: AnyArcCache::default(),
                 rx_command,
+                notifications,
+                tx_reload,
+                load_queue: LoadQueue::default(),
             };
 
             reactor.run().await
@@ -133,13 +257,35 @@ impl Reactor {
         }
 
         loop {
+            // pending requests are queued before the next load starts, so that they can be
+            // coalesced.
+            while let Ok(command) = self.rx_command.try_recv() {
+                handle_command(&self.assets, &mut self.load_queue, command);
+            }
+            if let Some(load_request) = self.load_queue.queued.pop_front() {
+                self.load(load_request).await;
+                continue;
+            }
+
             tokio::select! {
                 command_opt = self.rx_command.recv() => {
                     let Some(command) = command_opt else { break; };
-                    self.handle_command(command).await?;
+                    handle_command(&self.assets, &mut self.load_queue, command);
                 }
                 event_result = next_event(&mut events) => {
-                    self.handle_event(event_result?).await?;
+                    match event_result {
+                        Ok(event) => self.handle_event(event).await?,
+                        Err(error) => {
+                            tracing::error!(?error, "lost connection to asset events");
+                            if let Some(notifications) = &self.notifications {
+                                notifications.notify(
+                                    Notification::warning("notification-asset-events-disconnected")
+                                        .with_message(error.to_string()),
+                                );
+                            }
+                            events = None;
+                        }
+                    }
                 }
             }
         }
@@ -147,43 +293,246 @@ impl Reactor {
         Ok(())
     }
 
-    async fn handle_command(&mut self, command: Command) -> Result<(), Error> {
-        match command {
-            Command::Load { load_request } => {
-                tracing::debug!(asset_id = %load_request.asset_id(), asset_type = load_request.asset_type_name(), "loading asset");
-                let asset_store = self.asset_store.lock().await;
-                let mut loader = LoadAssetContext {
-                    dist_assets: &self.assets,
-                    client: &self.client,
-                    asset_store: &asset_store,
-                    cache: &mut self.cache,
-                };
-                load_request.load(&mut loader).await;
-            }
-            Command::RegisterAssetType { asset_type } => {
-                let _ = asset_type;
-                // todo
+    /// Loads an asset, and queues the requests that arrive in the meantime.
+    async fn load(&mut self, load_request: DynAssetLoadRequest) {
+        tracing::debug!(asset_id = %load_request.asset_id(), asset_type = load_request.asset_type_name(), "loading asset");
+        self.load_queue.in_flight = Some(load_request.key());
+
+        let asset_store = self.asset_store.lock().await;
+        let mut loader = LoadAssetContext {
+            dist_assets: &self.assets,
+            client: &self.client,
+            asset_store: &asset_store,
+            cache: &mut self.cache,
+        };
+        let mut load = std::pin::pin!(load_request.load(&mut loader));
+
+        loop {
+            tokio::select! {
+                () = &mut load => break,
+                Some(command) = self.rx_command.recv() => {
+                    handle_command(&self.assets, &mut self.load_queue, command);
+                }
             }
         }
 
-        Ok(())
+        self.load_queue.in_flight = None;
     }
 
     async fn handle_event(&mut self, event: dist::Event) -> Result<(), Error> {
         match event {
-            dist::Event::Changed { asset_ids } => {
-                tracing::debug!(?asset_ids, "assets changed");
-                // todo: the specified asset was changed and can be reloaded
+            dist::Event::Rebuilt { diff } => {
+                let mut asset_ids = diff.asset_ids().collect::<HashSet<_>>();
+                tracing::debug!(?asset_ids, "assets rebuilt");
+
+                let mut dist_asset_types = dist::AssetTypes::default();
+                dist_asset_types.with_builtin();
+                if let Err(error) = diff.apply(&mut self.assets, &dist_asset_types) {
+                    tracing::error!(?error, "failed to apply asset diff");
+                    return Ok(());
+                }
+
+                // materials hold on to their textures, so they're reloaded too.
+                asset_ids.extend(
+                    self.assets
+                        .iter::<dist::Material>()
+                        .filter(|material| {
+                            material
+                                .textures()
+                                .any(|texture| asset_ids.contains(&texture))
+                        })
+                        .map(|material| material.id)
+                        .collect::<Vec<_>>(),
+                );
+
+                for asset_id in &asset_ids {
+                    self.cache.remove(asset_id);
+                }
+                // nobody might be listening.
+                let _ = self.tx_reload.send(Arc::new(asset_ids));
+            }
+            dist::Event::Lagged => {
+                tracing::warn!("missed asset events, some assets might be outdated");
             }
-            dist::Event::Lagged => {}
         }
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
+fn handle_command(assets: &dist::Assets, load_queue: &mut LoadQueue, command: Command) {
+    match command {
+        Command::Load { load_request } => {
+            load_queue.enqueue(load_request);
+        }
+        Command::Find { find } => {
+            find(assets);
+        }
+        Command::RegisterAssetType { asset_type } => {
+            let _ = asset_type;
+            // todo
+        }
+    }
+}
+
+/// Load requests waiting for the current load to finish.
+///
+/// Assets are loaded one after another. Requests for an asset that is already
+/// queued or loading are coalesced, i.e. queued right after it. Loaders then
+/// find the downloaded and decoded data in the cache, so that it's only
+/// loaded once, e.g. when many entities use the same material.
+#[derive(Debug, Default)]
+struct LoadQueue {
+    queued: VecDeque<DynAssetLoadRequest>,
+
+    /// Type and ID of the asset that is being loaded.
+    in_flight: Option<(TypeId, AssetId)>,
+}
+
+impl LoadQueue {
+    fn enqueue(&mut self, load_request: DynAssetLoadRequest) {
+        let key = load_request.key();
+
+        let position = self
+            .queued
+            .iter()
+            .rposition(|queued| queued.key() == key)
+            .map(|position| position + 1)
+            .or_else(|| (self.in_flight == Some(key)).then_some(0));
+
+        if let Some(position) = position {
+            tracing::trace!(asset_id = %key.1, asset_type = load_request.asset_type_name(), "coalesced asset load request");
+            self.queued.insert(position, load_request);
+        }
+        else {
+            self.queued.push_back(load_request);
+        }
+    }
+}
+
+/// Downloads and parses the asset manifest, and merges the assets of the
+/// content packs into it.
+///
+/// Content packs that fail to load are skipped.
+async fn load_manifest(
+    client: &AssetClient,
+    content_packs: &[ContentPack],
+    notifications: Option<&Notifications>,
+) -> Result<dist::Assets, Error> {
+    let manifest = client.get_manifest().await.inspect_err(|error| {
+        // the assets were built by a newer version, so this client is outdated.
+        if let kardashev_client::Error::Manifest(error @ ManifestError::Unsupported { .. }) = error
+        {
+            if let Some(notifications) = notifications {
+                notifications.notify(
+                    Notification::error("notification-asset-manifest-unsupported")
+                        .with_message(error.to_string()),
+                );
+            }
+        }
+    })?;
+    if manifest.version > dist::MANIFEST_VERSION {
+        tracing::info!(
+            version = manifest.version,
+            supported = dist::MANIFEST_VERSION,
+            "asset manifest is newer than the client, ignoring added fields"
+        );
+    }
+
+    let mut dist_asset_types = dist::AssetTypes::default();
+    dist_asset_types.with_builtin();
+    let mut assets = manifest.assets.parse(&dist_asset_types)?;
+
+    // the server's content packs are loaded first, so that they win over the
+    // configured ones.
+    let mut all_content_packs = client
+        .get_content_packs()
+        .await
+        .map(|content_packs| content_packs.packs)
+        .unwrap_or_else(|error| {
+            tracing::warn!(?error, "failed to get the server's content packs");
+            vec![]
+        });
+    all_content_packs.extend(content_packs.iter().cloned());
+
+    for content_pack in &all_content_packs {
+        match load_content_pack(client, content_pack, &dist_asset_types).await {
+            Ok(content_pack_assets) => {
+                tracing::info!(name = %content_pack.name, url = %content_pack.url, "loaded content pack");
+                assets.merge(content_pack_assets);
+            }
+            Err(error) => {
+                let error = error_chain(&error);
+                tracing::error!(name = %content_pack.name, %error, "failed to load content pack");
+                if let Some(notifications) = notifications {
+                    notifications.notify(
+                        Notification::warning("notification-content-pack-failed")
+                            .with_message(format!("{}: {error}", content_pack.name)),
+                    );
+                }
+            }
+        }
+    }
+
+    for ty in assets.unrecognized_types() {
+        tracing::warn!("unrecognized asset type: {ty:?}");
+    }
+
+    Ok(assets)
+}
+
+/// Downloads and parses the manifest of a content pack, and moves its assets
+/// into the pack's namespace.
+async fn load_content_pack(
+    client: &AssetClient,
+    content_pack: &ContentPack,
+    dist_asset_types: &dist::AssetTypes,
+) -> Result<dist::Assets, Error> {
+    let client = client.for_content_pack(content_pack)?;
+    let manifest = client.get_manifest().await?;
+    let mut assets = manifest
+        .assets
+        .namespaced(&content_pack.name)
+        .parse(dist_asset_types)?;
+
+    // files are downloaded relative to the game's asset URL, so the pack's
+    // files need absolute URLs.
+    let asset_url = client.asset_url();
+    assets.map_files(|file| {
+        if let Ok(url) = asset_url.join(file) {
+            *file = url.into();
+        }
+    });
+
+    Ok(assets)
+}
+
 pub(super) enum Command {
-    Load { load_request: DynAssetLoadRequest },
-    RegisterAssetType { asset_type: DynAssetType },
+    Load {
+        load_request: DynAssetLoadRequest,
+    },
+    Find {
+        find: Box<dyn FnOnce(&dist::Assets) + Send>,
+    },
+    RegisterAssetType {
+        asset_type: DynAssetType,
+    },
+}
+
+impl Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load { load_request } => {
+                f.debug_struct("Load")
+                    .field("load_request", load_request)
+                    .finish()
+            }
+            Self::Find { .. } => f.debug_struct("Find").finish_non_exhaustive(),
+            Self::RegisterAssetType { asset_type } => {
+                f.debug_struct("RegisterAssetType")
+                    .field("asset_type", asset_type)
+                    .finish()
+            }
+        }
+    }
 }
@@ -13,6 +13,7 @@ use serde::{
 use crate::{
     assets::Error,
     utils::web_fs::{
+        self,
         file_lock::FileLockWriteGuard,
         File,
         OpenOptions,
@@ -50,6 +51,23 @@ pub struct AssetStoreGuard {
     _guard: FileLockWriteGuard,
 }
 
+impl AssetStoreGuard {
+    /// Opens the file in which a downloaded file is cached, and creates it if
+    /// it doesn't exist yet.
+    ///
+    /// Files of content packs are absolute URLs, which are flattened into a
+    /// file name.
+    pub async fn open_cached(&self, file: &str) -> Result<File, web_fs::Error> {
+        let file_name = file.replace(
+            |c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')),
+            "_",
+        );
+        self.web_fs
+            .open(file_name, OpenOptions::new().create(true))
+            .await
+    }
+}
+
 impl Deref for AssetStoreGuard {
     type Target = WebFs;
 
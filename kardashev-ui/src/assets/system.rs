@@ -1,6 +1,15 @@
-use std::fmt::Debug;
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    sync::Arc,
+};
 
 use kardashev_client::AssetClient;
+use kardashev_protocol::assets::{
+    AssetId,
+    ContentPack,
+};
+use tokio::sync::broadcast;
 use url::Url;
 
 use crate::{
@@ -20,13 +29,17 @@ use crate::{
             SystemContext,
         },
     },
+    notifications::Notifications,
 };
 
 /// [`System`] that queries [`Load<A>`s](Load), loads them, and attaches the
 /// loaded asset.
+///
+/// Assets that changed on the asset server are detached and loaded again.
 #[derive(Default)]
 pub struct AssetLoaderSystem {
     command_buffer: hecs::CommandBuffer,
+    rx_reload: Option<broadcast::Receiver<Arc<HashSet<AssetId>>>>,
 }
 
 impl System for AssetLoaderSystem {
@@ -43,6 +56,28 @@ impl System for AssetLoaderSystem {
             .get::<AssetServer>()
             .expect("AssetServer resource missing");
 
+        let notifications = system_context.resources.get::<Notifications>();
+
+        let rx_reload = self.rx_reload.get_or_insert_with(|| asset_server.reloads());
+        loop {
+            let asset_ids = match rx_reload.try_recv() {
+                Ok(asset_ids) => asset_ids,
+                Err(broadcast::error::TryRecvError::Lagged(num_skipped)) => {
+                    tracing::warn!(num_skipped, "missed asset reloads");
+                    continue;
+                }
+                Err(_) => break,
+            };
+            for asset_type in &asset_type_registry.asset_types {
+                asset_type.reload(
+                    &asset_ids,
+                    &mut system_context.world,
+                    &mut self.command_buffer,
+                );
+            }
+        }
+        self.command_buffer.run_on(&mut system_context.world);
+
         for asset_type in &asset_type_registry.asset_types {
             tracing::trace!(
                 asset_type = asset_type.asset_type_name(),
@@ -50,6 +85,7 @@ impl System for AssetLoaderSystem {
             );
             asset_type.loader_system(
                 asset_server,
+                notifications,
                 &mut system_context.world,
                 &mut self.command_buffer,
             );
@@ -100,6 +136,7 @@ impl AssetTypeRegistry {
 #[derive(Debug)]
 pub struct AssetsPlugin {
     client: AssetClient,
+    content_packs: Vec<ContentPack>,
 }
 
 impl AssetsPlugin {
@@ -108,13 +145,26 @@ impl AssetsPlugin {
     }
 
     pub fn from_client(client: AssetClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            content_packs: vec![],
+        }
+    }
+
+    /// Loads these content packs in addition to the ones the server offers.
+    pub fn with_content_packs(mut self, content_packs: Vec<ContentPack>) -> Self {
+        self.content_packs = content_packs;
+        self
     }
 }
 
 impl Plugin for AssetsPlugin {
     fn register(self, context: RegisterPluginContext) {
-        let asset_server = AssetServer::new(self.client.clone());
+        let asset_server = AssetServer::new(
+            self.client.clone(),
+            self.content_packs,
+            context.resources.get::<Notifications>().cloned(),
+        );
 
         context.resources.insert(asset_server.clone());
         context
@@ -0,0 +1,146 @@
+//! Audio output using the Web Audio API.
+//!
+//! Sounds are assets that are loaded like any other asset, e.g. by attaching
+//! a [`Load<Sound>`](crate::assets::load::Load) to an entity. A
+//! [`SpatialSound`](spatial::SpatialSound) on the same entity plays the sound
+//! at the position of the entity, relative to the
+//! [`AudioListener`](spatial::AudioListener). Music is played by
+//! [`Music`](music::Music).
+//!
+//! Browsers only allow audio to start after the user interacted with the
+//! page, so the output starts suspended and is resumed by
+//! [`resume_audio_on_interaction`].
+
+pub mod music;
+pub mod sound;
+pub mod spatial;
+
+use leptos::{
+    ev,
+    expect_context,
+};
+use leptos_use::{
+    use_document,
+    use_event_listener,
+};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::js_sys;
+use web_sys::{
+    AudioContext,
+    AudioContextState,
+    AudioNode,
+    GainNode,
+};
+
+use crate::{
+    assets::system::AssetTypeRegistry,
+    audio::{
+        sound::Sound,
+        spatial::spatial_audio_system,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error("audio error")]
+pub enum Error {
+    #[error("web audio error: {message}")]
+    WebAudio { message: String },
+}
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        // usually a `DOMException`, which has a message.
+        let message = js_sys::Reflect::get(&value, &"message".into())
+            .ok()
+            .and_then(|message| message.as_string())
+            .unwrap_or_else(|| format!("{value:?}"));
+        Self::WebAudio { message }
+    }
+}
+
+/// Resource for playing audio.
+///
+/// All sounds are played through a master gain node, which then goes to the
+/// speakers.
+#[derive(Debug)]
+pub struct AudioOutput {
+    context: AudioContext,
+    master: GainNode,
+}
+
+impl AudioOutput {
+    pub fn new() -> Result<Self, Error> {
+        let context = AudioContext::new()?;
+        let master = context.create_gain()?;
+        master.connect_with_audio_node(&context.destination())?;
+        Ok(Self { context, master })
+    }
+
+    pub fn context(&self) -> &AudioContext {
+        &self.context
+    }
+
+    /// The node that sounds connect to.
+    pub fn input(&self) -> &AudioNode {
+        &self.master
+    }
+
+    /// Resumes the output if it's suspended.
+    ///
+    /// This only has an effect if the user interacted with the page before.
+    pub fn resume(&self) {
+        if self.context.state() == AudioContextState::Suspended {
+            if let Err(error) = self.context.resume() {
+                tracing::warn!(error = %Error::from(error), "failed to resume audio output");
+            }
+        }
+    }
+}
+
+/// Registers the [`Sound`] asset type, inserts the [`AudioOutput`] and plays
+/// [`SpatialSound`](spatial::SpatialSound)s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        if let Some(asset_type_registry) = context.resources.get_mut::<AssetTypeRegistry>() {
+            asset_type_registry.register::<Sound>();
+        }
+        else {
+            tracing::warn!(
+                "resource AssetTypeRegistry is missing. can't register sound asset type"
+            );
+        }
+
+        match AudioOutput::new() {
+            Ok(output) => context.resources.insert(output),
+            Err(error) => tracing::warn!(%error, "audio output not available"),
+        }
+
+        context.schedule.add_system(spatial_audio_system);
+    }
+}
+
+/// Resumes the [`AudioOutput`] whenever the user clicks or presses a key.
+pub fn resume_audio_on_interaction() {
+    let world = expect_context::<WorldServer>();
+    let resume = move || {
+        let _ = world.run(|system_context| {
+            if let Some(output) = system_context.resources.get::<AudioOutput>() {
+                output.resume();
+            }
+        });
+    };
+
+    let resume2 = resume.clone();
+    let _ = use_event_listener(use_document(), ev::pointerdown, move |_| resume());
+    let _ = use_event_listener(use_document(), ev::keydown, move |_| resume2());
+}
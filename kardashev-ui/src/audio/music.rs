@@ -0,0 +1,528 @@
+//! Background music and ambience.
+//!
+//! Music tracks are [sound assets](kardashev_protocol::assets::Sound) that
+//! belong to a playlist. The [`Music`] resource plays the playlist for the
+//! current [`MusicContext`], and crossfades to the next track when a track
+//! ends or the context changes.
+//!
+//! Tracks are long, so they aren't decoded into memory like other sounds, but
+//! streamed by an audio element. The next track of the playlist is preloaded
+//! while the current one plays.
+//!
+//! The volume and whether music is muted are persisted in local storage, see
+//! [`provide_music`].
+
+use std::{
+    collections::HashMap,
+    time::Duration,
+};
+
+use kardashev_protocol::assets::{
+    self as dist,
+    AssetId,
+};
+use leptos::{
+    create_effect,
+    expect_context,
+    provide_context,
+    Signal,
+    SignalGet,
+    WriteSignal,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::oneshot;
+use url::Url;
+use web_sys::{
+    AudioContext,
+    AudioContextState,
+    GainNode,
+    HtmlAudioElement,
+    MediaElementAudioSourceNode,
+};
+
+use crate::{
+    app::layout::{
+        use_layout,
+        PanelKind,
+    },
+    assets::server::AssetServer,
+    audio::{
+        AudioOutput,
+        Error,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+    },
+    utils::{
+        futures::spawn_local,
+        time::Instant,
+    },
+};
+
+/// How long it takes to crossfade from one track to the next.
+const CROSSFADE_DURATION: Duration = Duration::from_secs(4);
+
+/// How long combat music plays after a battle.
+const COMBAT_DURATION: Duration = Duration::from_secs(120);
+
+/// What's going on in the game, which determines the playlist.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MusicContext {
+    /// Looking at the galaxy map.
+    #[default]
+    Map,
+
+    /// Looking at a star system.
+    System,
+
+    /// A battle happened recently.
+    Combat,
+}
+
+impl MusicContext {
+    /// Name of the playlist that is played in this context.
+    pub fn playlist(&self) -> &'static str {
+        match self {
+            Self::Map => "map",
+            Self::System => "system",
+            Self::Combat => "combat",
+        }
+    }
+
+    fn from_playlist(playlist: &str) -> Option<Self> {
+        [Self::Map, Self::System, Self::Combat]
+            .into_iter()
+            .find(|context| context.playlist() == playlist)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MusicSettings {
+    /// Volume from `0.0` to `1.0`.
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for MusicSettings {
+    fn default() -> Self {
+        Self {
+            volume: 0.5,
+            muted: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct TrackSource {
+    asset_id: AssetId,
+    url: Url,
+}
+
+#[derive(Debug, Default)]
+struct Playlist {
+    tracks: Vec<TrackSource>,
+    position: usize,
+}
+
+impl Playlist {
+    /// Advances to the next track and returns it.
+    fn next(&mut self) -> Option<&TrackSource> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+        self.position = (self.position + 1) % self.tracks.len();
+        self.tracks.get(self.position)
+    }
+
+    /// The track after the current one.
+    fn peek(&self) -> Option<&TrackSource> {
+        if self.tracks.is_empty() {
+            return None;
+        }
+        self.tracks.get((self.position + 1) % self.tracks.len())
+    }
+}
+
+/// A track that is streamed by an audio element:
+///
+/// ```plain
+/// element -> source -> gain -> music bus
+/// ```
+#[derive(Debug)]
+struct Track {
+    asset_id: AssetId,
+    context: MusicContext,
+    element: HtmlAudioElement,
+    source: MediaElementAudioSourceNode,
+    gain: GainNode,
+}
+
+impl Track {
+    fn new(
+        audio_context: &AudioContext,
+        bus: &GainNode,
+        track_source: &TrackSource,
+        context: MusicContext,
+    ) -> Result<Self, Error> {
+        let element = HtmlAudioElement::new_with_src(track_source.url.as_str())?;
+        element.set_cross_origin(Some("anonymous"));
+        element.set_preload("auto");
+
+        let source = audio_context.create_media_element_source(&element)?;
+        let gain = audio_context.create_gain()?;
+        gain.gain().set_value(0.0);
+        source.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(bus)?;
+
+        Ok(Self {
+            asset_id: track_source.asset_id,
+            context,
+            element,
+            source,
+            gain,
+        })
+    }
+
+    fn ramp_to(&self, audio_context: &AudioContext, value: f32, duration: Duration) {
+        let now = audio_context.current_time();
+        let gain = self.gain.gain();
+        let _ = gain.cancel_scheduled_values(now);
+        let _ = gain.set_value_at_time(gain.value(), now);
+        let _ = gain.linear_ramp_to_value_at_time(value, now + duration.as_secs_f64());
+    }
+
+    fn play(&self) {
+        // fails if the browser doesn't allow playback yet. we try again on the next
+        // update.
+        let _ = self.element.play();
+    }
+
+    /// Whether the track is close enough to its end to crossfade to the next
+    /// one.
+    fn is_ending(&self) -> bool {
+        let duration = self.element.duration();
+        self.element.ended()
+            || (duration.is_finite()
+                && duration - self.element.current_time() <= CROSSFADE_DURATION.as_secs_f64())
+    }
+}
+
+impl Drop for Track {
+    fn drop(&mut self) {
+        let _ = self.element.pause();
+        // stops the download
+        self.element.set_src("");
+        let _ = self.gain.disconnect();
+        let _ = self.source.disconnect();
+    }
+}
+
+/// Resource that plays music.
+#[derive(Debug)]
+pub struct Music {
+    asset_url: Url,
+    audio_context: AudioContext,
+    bus: GainNode,
+    settings: MusicSettings,
+    view_context: MusicContext,
+    combat_until: Option<Instant>,
+    playlists: HashMap<MusicContext, Playlist>,
+    rx_playlists: Option<oneshot::Receiver<HashMap<MusicContext, Playlist>>>,
+    current: Option<Track>,
+    next: Option<Track>,
+    fading_out: Vec<(Track, Instant)>,
+}
+
+impl Music {
+    fn new(asset_url: Url, output: &AudioOutput) -> Result<Self, Error> {
+        let audio_context = output.context().clone();
+        let bus = audio_context.create_gain()?;
+        bus.connect_with_audio_node(output.input())?;
+
+        let music = Self {
+            asset_url,
+            audio_context,
+            bus,
+            settings: MusicSettings::default(),
+            view_context: MusicContext::default(),
+            combat_until: None,
+            playlists: HashMap::new(),
+            rx_playlists: None,
+            current: None,
+            next: None,
+            fading_out: vec![],
+        };
+        music.apply_settings();
+        Ok(music)
+    }
+
+    pub fn set_settings(&mut self, settings: MusicSettings) {
+        self.settings = settings;
+        self.apply_settings();
+    }
+
+    /// Sets whether the player looks at the map or a star system.
+    pub fn set_view_context(&mut self, context: MusicContext) {
+        self.view_context = context;
+    }
+
+    /// Plays combat music for a while.
+    pub fn enter_combat(&mut self) {
+        self.combat_until = Some(Instant::now() + COMBAT_DURATION);
+    }
+
+    /// The context that music is currently played for.
+    pub fn context(&self) -> MusicContext {
+        if self.combat_until.is_some() {
+            MusicContext::Combat
+        }
+        else {
+            self.view_context
+        }
+    }
+
+    fn apply_settings(&self) {
+        let volume = if self.settings.muted {
+            0.0
+        }
+        else {
+            self.settings.volume.clamp(0.0, 1.0)
+        };
+        self.bus.gain().set_value(volume);
+    }
+
+    fn update(&mut self, now: Instant) {
+        if let Some(rx_playlists) = &mut self.rx_playlists {
+            match rx_playlists.try_recv() {
+                Ok(playlists) => {
+                    self.playlists = playlists;
+                    self.rx_playlists = None;
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.rx_playlists = None,
+            }
+        }
+
+        if self.combat_until.map_or(false, |until| until <= now) {
+            self.combat_until = None;
+        }
+
+        self.fading_out.retain(|(_, until)| *until > now);
+
+        if self.settings.muted {
+            // don't stream music that can't be heard.
+            if let Some(current) = &self.current {
+                let _ = current.element.pause();
+            }
+            return;
+        }
+
+        // until the user interacted with the page, nothing can be played.
+        if self.audio_context.state() != AudioContextState::Running {
+            return;
+        }
+
+        let context = self.context();
+        let switch = self.current.as_ref().map_or(true, |current| {
+            current.context != context || current.is_ending()
+        });
+        if switch {
+            self.crossfade(context, now);
+        }
+
+        if let Some(current) = &self.current {
+            if current.element.paused() {
+                current.play();
+            }
+        }
+    }
+
+    /// Fades out the current track and fades in the next track for `context`.
+    fn crossfade(&mut self, context: MusicContext, now: Instant) {
+        if let Some(current) = self.current.take() {
+            current.ramp_to(&self.audio_context, 0.0, CROSSFADE_DURATION);
+            self.fading_out.push((current, now + CROSSFADE_DURATION));
+        }
+
+        let Some(playlist) = self.playlists.get_mut(&context)
+        else {
+            self.next = None;
+            return;
+        };
+        let Some(track_source) = playlist.next().cloned()
+        else {
+            return;
+        };
+
+        // use the preloaded track, if it's the right one.
+        let track = match self.next.take() {
+            Some(next) if next.context == context && next.asset_id == track_source.asset_id => {
+                Ok(next)
+            }
+            _ => Track::new(&self.audio_context, &self.bus, &track_source, context),
+        };
+
+        match track {
+            Ok(track) => {
+                tracing::debug!(asset_id = %track.asset_id, ?context, "playing music track");
+                track.ramp_to(&self.audio_context, 1.0, CROSSFADE_DURATION);
+                track.play();
+                self.current = Some(track);
+            }
+            Err(error) => tracing::error!(%error, "failed to play music track"),
+        }
+
+        self.next = playlist
+            .peek()
+            .filter(|next| next.asset_id != track_source.asset_id)
+            .and_then(|next| {
+                Track::new(&self.audio_context, &self.bus, next, context)
+                    .inspect_err(|error| tracing::error!(%error, "failed to preload music track"))
+                    .ok()
+            });
+    }
+}
+
+fn load_playlists(system_context: &mut SystemContext) {
+    let Some(asset_server) = system_context.resources.get::<AssetServer>().cloned()
+    else {
+        tracing::warn!("resource AssetServer is missing. can't load playlists");
+        return;
+    };
+    let Some(music) = system_context.resources.get_mut::<Music>()
+    else {
+        return;
+    };
+
+    let (tx, rx) = oneshot::channel();
+    music.rx_playlists = Some(rx);
+    let asset_url = music.asset_url.clone();
+
+    spawn_local(async move {
+        let tracks = asset_server
+            .find_map::<dist::Sound, _>(|sound| {
+                let context = MusicContext::from_playlist(sound.playlist.as_deref()?)?;
+                Some((context, sound.id, sound.audio.clone()))
+            })
+            .await;
+
+        let mut playlists = HashMap::<MusicContext, Playlist>::new();
+        for (context, asset_id, audio) in tracks {
+            match asset_url.join(&audio) {
+                Ok(url) => {
+                    playlists
+                        .entry(context)
+                        .or_default()
+                        .tracks
+                        .push(TrackSource { asset_id, url })
+                }
+                Err(error) => tracing::error!(%asset_id, %error, "invalid music track url"),
+            }
+        }
+        tracing::debug!(num_playlists = playlists.len(), "loaded music playlists");
+
+        let _ = tx.send(playlists);
+    });
+}
+
+fn music_system(system_context: &mut SystemContext) {
+    if let Some(music) = system_context.resources.get_mut::<Music>() {
+        music.update(Instant::now());
+    }
+}
+
+/// Inserts the [`Music`] resource. This needs the [`AudioOutput`] from the
+/// [`AudioPlugin`](super::AudioPlugin).
+#[derive(Debug)]
+pub struct MusicPlugin {
+    asset_url: Url,
+}
+
+impl MusicPlugin {
+    pub fn from_url(asset_url: Url) -> Self {
+        Self { asset_url }
+    }
+}
+
+impl Plugin for MusicPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let Some(output) = context.resources.get::<AudioOutput>()
+        else {
+            tracing::warn!("resource AudioOutput is missing. music is disabled");
+            return;
+        };
+
+        match Music::new(self.asset_url, output) {
+            Ok(music) => context.resources.insert(music),
+            Err(error) => {
+                tracing::warn!(%error, "music not available");
+                return;
+            }
+        }
+
+        context.startup_schedule.add_system(load_playlists);
+        context.schedule.add_system(music_system);
+    }
+}
+
+/// Persisted [`MusicSettings`].
+#[derive(Clone, Copy, Debug)]
+pub struct MusicSettingsContext {
+    pub settings: Signal<MusicSettings>,
+    pub set_settings: WriteSignal<MusicSettings>,
+}
+
+/// Provides the [`MusicSettingsContext`] and keeps the [`Music`] resource up
+/// to date with it and with the layout.
+///
+/// This must be called after the [`WorldServer`] and layout were provided.
+pub fn provide_music() {
+    let (settings, set_settings, _) =
+        use_local_storage::<MusicSettings, codee::string::JsonSerdeCodec>("music-settings");
+    let world = expect_context::<WorldServer>();
+    let layout = use_layout();
+
+    create_effect({
+        let world = world.clone();
+        move |_| {
+            let settings = settings.get();
+            let _ = world.run(move |system_context| {
+                if let Some(music) = system_context.resources.get_mut::<Music>() {
+                    music.set_settings(settings);
+                }
+            });
+        }
+    });
+
+    create_effect(move |_| {
+        let view_context = if layout.is_open(PanelKind::SystemDetail) {
+            MusicContext::System
+        }
+        else {
+            MusicContext::Map
+        };
+        let _ = world.run(move |system_context| {
+            if let Some(music) = system_context.resources.get_mut::<Music>() {
+                music.set_view_context(view_context);
+            }
+        });
+    });
+
+    provide_context(MusicSettingsContext {
+        settings,
+        set_settings,
+    });
+}
+
+pub fn use_music_settings() -> MusicSettingsContext {
+    expect_context()
+}
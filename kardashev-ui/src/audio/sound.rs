@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use kardashev_client::DownloadError;
+use kardashev_protocol::assets::{
+    self as dist,
+    AssetId,
+};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{
+    js_sys,
+    JsFuture,
+};
+use web_sys::{
+    AudioBuffer,
+    OfflineAudioContext,
+};
+
+use crate::{
+    assets::{
+        load::{
+            LoadAssetContext,
+            LoadFromAsset,
+        },
+        AssetNotFound,
+        MaybeHasAssetId,
+    },
+    audio::{
+        AudioOutput,
+        Error,
+    },
+    utils::thread_local_cell::ThreadLocalCell,
+};
+
+/// Sample rate to which sounds are resampled when they're decoded.
+const DECODE_SAMPLE_RATE: f32 = 48000.0;
+
+/// A decoded sound.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    asset_id: Option<AssetId>,
+    buffer: Arc<ThreadLocalCell<AudioBuffer>>,
+}
+
+impl Sound {
+    /// Creates a mono sound from samples, e.g. ones that were synthesized.
+    pub fn from_samples(
+        output: &AudioOutput,
+        samples: &[f32],
+        sample_rate: f32,
+    ) -> Result<Self, Error> {
+        let buffer = output
+            .context()
+            .create_buffer(1, samples.len() as u32, sample_rate)?;
+        buffer.copy_to_channel(samples, 0)?;
+        Ok(Self {
+            asset_id: None,
+            buffer: Arc::new(ThreadLocalCell::new(buffer)),
+        })
+    }
+
+    pub fn buffer(&self) -> &AudioBuffer {
+        self.buffer.get()
+    }
+}
+
+impl MaybeHasAssetId for Sound {
+    fn maybe_asset_id(&self) -> Option<AssetId> {
+        self.asset_id
+    }
+}
+
+impl LoadFromAsset for Sound {
+    type Dist = dist::Sound;
+    type Error = SoundError;
+    type Args = ();
+
+    async fn load<'a, 'b: 'a>(
+        asset_id: AssetId,
+        _args: (),
+        context: &'a mut LoadAssetContext<'b>,
+    ) -> Result<Self, SoundError> {
+        let dist = context
+            .dist_assets
+            .get::<dist::Sound>(asset_id)
+            .ok_or_else(|| AssetNotFound { asset_id })?;
+
+        let client = context.client;
+        let buffer = context
+            .cache
+            .get_or_try_insert_async(asset_id, || {
+                async move {
+                    let data = client.download_file(&dist.audio).await?.bytes().await?;
+                    let buffer = decode(&data).await?;
+                    Ok::<_, SoundError>(Arc::new(ThreadLocalCell::new(buffer)))
+                }
+            })
+            .await?;
+
+        tracing::debug!(%asset_id, "sound loaded");
+
+        Ok(Self {
+            asset_id: Some(asset_id),
+            buffer,
+        })
+    }
+}
+
+/// Decodes an audio file.
+///
+/// The loader has no access to the [`AudioOutput`](super::AudioOutput), so
+/// this uses an offline context. The decoded buffer can be played by any
+/// context.
+async fn decode(data: &[u8]) -> Result<AudioBuffer, Error> {
+    let context = OfflineAudioContext::new_with_number_of_channels_and_length_and_sample_rate(
+        2,
+        1,
+        DECODE_SAMPLE_RATE,
+    )?;
+    let array_buffer = js_sys::Uint8Array::from(data).buffer();
+    let buffer = JsFuture::from(context.decode_audio_data(&array_buffer)?).await?;
+    Ok(buffer.unchecked_into())
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("sound load error")]
+pub enum SoundError {
+    AssetNotFound(#[from] AssetNotFound),
+    Download(#[from] DownloadError),
+    Audio(#[from] Error),
+}
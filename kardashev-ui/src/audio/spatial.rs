@@ -0,0 +1,242 @@
+//! Sounds that are positioned in space.
+//!
+//! The position of a [`SpatialSound`] is computed relative to the entity with
+//! the [`AudioListener`], which is usually the camera. The listener itself
+//! always stays at the origin of the audio context, looking down the negative
+//! Z axis. This way the audio context never sees the large coordinates of the
+//! world.
+
+use nalgebra::Point3;
+use web_sys::{
+    AudioBufferSourceNode,
+    DistanceModelType,
+    GainNode,
+    PannerNode,
+    PanningModelType,
+};
+
+use crate::{
+    audio::{
+        sound::Sound,
+        AudioOutput,
+        Error,
+    },
+    ecs::system::SystemContext,
+    graphics::transform::GlobalTransform,
+    utils::thread_local_cell::ThreadLocalCell,
+};
+
+/// Marks the entity from whose point of view spatial sounds are heard.
+///
+/// If there are multiple listeners, one of them is used. If there is none,
+/// spatial sounds are muted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AudioListener;
+
+/// How the volume of a sound decreases with its distance to the listener.
+///
+/// See the [Web Audio specification][1] for the formulas.
+///
+/// [1]: https://webaudio.github.io/web-audio-api/#enumdef-distancemodeltype
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceModel {
+    Linear,
+    #[default]
+    Inverse,
+    Exponential,
+}
+
+impl From<DistanceModel> for DistanceModelType {
+    fn from(value: DistanceModel) -> Self {
+        match value {
+            DistanceModel::Linear => Self::Linear,
+            DistanceModel::Inverse => Self::Inverse,
+            DistanceModel::Exponential => Self::Exponential,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Attenuation {
+    pub distance_model: DistanceModel,
+
+    /// Distance up to which the sound is played at full volume.
+    pub ref_distance: f32,
+
+    /// Distance after which the volume doesn't decrease any further. For the
+    /// linear model the sound is silent from this distance on.
+    pub max_distance: f32,
+
+    /// How fast the volume decreases.
+    pub rolloff_factor: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            distance_model: DistanceModel::default(),
+            ref_distance: 1.0,
+            max_distance: 10000.0,
+            rolloff_factor: 1.0,
+        }
+    }
+}
+
+/// Plays the [`Sound`] of its entity at the entity's position.
+///
+/// Playback starts as soon as the sound is loaded, and stops when the
+/// component is removed. Changes to the attenuation and whether the sound is
+/// looped only take effect when playback starts, but the gain can be changed
+/// at any time.
+#[derive(Debug)]
+pub struct SpatialSound {
+    pub gain: f32,
+    pub looped: bool,
+    pub attenuation: Attenuation,
+    playback: Playback,
+}
+
+impl Default for SpatialSound {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            looped: true,
+            attenuation: Attenuation::default(),
+            playback: Playback::Pending,
+        }
+    }
+}
+
+impl SpatialSound {
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Plays the sound only once, instead of looping it.
+    #[allow(dead_code)]
+    pub fn once(mut self) -> Self {
+        self.looped = false;
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+}
+
+#[derive(Debug)]
+enum Playback {
+    Pending,
+    Playing(ThreadLocalCell<Voice>),
+    Failed,
+}
+
+/// Audio nodes of a playing [`SpatialSound`]:
+///
+/// ```plain
+/// source -> gain -> panner -> output
+/// ```
+#[derive(Debug)]
+struct Voice {
+    source: AudioBufferSourceNode,
+    gain: GainNode,
+    panner: PannerNode,
+}
+
+impl Voice {
+    fn start(
+        output: &AudioOutput,
+        sound: &Sound,
+        spatial_sound: &SpatialSound,
+    ) -> Result<Self, Error> {
+        let context = output.context();
+
+        let source = context.create_buffer_source()?;
+        source.set_buffer(Some(sound.buffer()));
+        source.set_loop(spatial_sound.looped);
+
+        let gain = context.create_gain()?;
+        gain.gain().set_value(spatial_sound.gain);
+
+        let panner = context.create_panner()?;
+        panner.set_panning_model(PanningModelType::Hrtf);
+        let attenuation = &spatial_sound.attenuation;
+        panner.set_distance_model(attenuation.distance_model.into());
+        panner.set_ref_distance(attenuation.ref_distance.into());
+        panner.set_max_distance(attenuation.max_distance.into());
+        panner.set_rolloff_factor(attenuation.rolloff_factor.into());
+
+        source.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&panner)?;
+        panner.connect_with_audio_node(output.input())?;
+        source.start()?;
+
+        Ok(Self {
+            source,
+            gain,
+            panner,
+        })
+    }
+
+    fn set_position(&self, position: &Point3<f32>) {
+        self.panner.position_x().set_value(position.x);
+        self.panner.position_y().set_value(position.y);
+        self.panner.position_z().set_value(position.z);
+    }
+}
+
+impl Drop for Voice {
+    fn drop(&mut self) {
+        let _ = self.source.stop();
+        let _ = self.panner.disconnect();
+    }
+}
+
+pub fn spatial_audio_system(system_context: &mut SystemContext) {
+    let Some(output) = system_context.resources.get::<AudioOutput>()
+    else {
+        return;
+    };
+
+    let listener = system_context
+        .world
+        .query_mut::<&GlobalTransform>()
+        .with::<&AudioListener>()
+        .into_iter()
+        .next()
+        .map(|(_, transform)| transform.model_matrix);
+
+    for (entity, (transform, sound, spatial_sound)) in
+        system_context
+            .world
+            .query_mut::<(&GlobalTransform, &Sound, &mut SpatialSound)>()
+    {
+        if let Playback::Pending = spatial_sound.playback {
+            spatial_sound.playback = match Voice::start(output, sound, spatial_sound) {
+                Ok(voice) => Playback::Playing(ThreadLocalCell::new(voice)),
+                Err(error) => {
+                    tracing::error!(?entity, %error, "failed to play sound");
+                    Playback::Failed
+                }
+            };
+        }
+
+        let Playback::Playing(voice) = &spatial_sound.playback
+        else {
+            continue;
+        };
+        let voice = voice.get();
+
+        if let Some(listener) = &listener {
+            let position = Point3::from(transform.model_matrix.isometry.translation.vector);
+            voice.set_position(&listener.inverse_transform_point(&position));
+            voice.gain.gain().set_value(spatial_sound.gain);
+        }
+        else {
+            voice.gain.gain().set_value(0.0);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Collisions between entities with [`Collider`]s.
+//!
+//! Every tick, the [`collision_system`] tests the colliders at their
+//! [`GlobalTransform`]s with the [shared collision
+//! detection](kardashev_protocol::collision), and records which entities
+//! started or stopped touching in [`CollisionEvents`]. Since the world server
+//! ticks at a fixed rate, this runs in fixed time steps, like the server's
+//! simulation.
+//!
+//! Entities with [`PushOut`], like the map camera, are moved out of the
+//! colliders they overlap, so that the camera can't clip into planets.
+
+use std::collections::HashMap;
+
+use hecs::Entity;
+use kardashev_protocol::collision::{
+    detect_collisions,
+    Body,
+};
+pub use kardashev_protocol::collision::{
+    CollisionLayers,
+    Contact,
+    Shape,
+};
+use nalgebra::{
+    Point3,
+    Translation3,
+    Vector3,
+};
+
+use crate::{
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+    },
+    graphics::transform::{
+        GlobalTransform,
+        Transform,
+    },
+};
+
+/// Edge length of the cells of the broadphase grid.
+const DEFAULT_CELL_SIZE: f32 = 4.0;
+
+#[derive(Clone, Debug)]
+pub struct Collider {
+    pub shape: Shape,
+    pub layers: CollisionLayers,
+}
+
+impl Collider {
+    pub fn sphere(radius: f32) -> Self {
+        Self::new(Shape::Sphere { radius })
+    }
+
+    pub fn aabb(half_extents: Vector3<f32>) -> Self {
+        Self::new(Shape::Aabb { half_extents })
+    }
+
+    pub fn convex(points: Vec<Point3<f32>>) -> Self {
+        Self::new(Shape::Convex { points })
+    }
+
+    fn new(shape: Shape) -> Self {
+        Self {
+            shape,
+            layers: CollisionLayers::default(),
+        }
+    }
+
+    pub fn with_layers(mut self, memberships: u32, filter: u32) -> Self {
+        self.layers = CollisionLayers::new(memberships, filter);
+        self
+    }
+}
+
+/// Moves the entity out of the colliders it overlaps.
+///
+/// Only works for entities without a parent, since it changes their
+/// [`Transform`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PushOut;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionEvent {
+    Started {
+        a: Entity,
+        b: Entity,
+        contact: Contact,
+    },
+    Stopped {
+        a: Entity,
+        b: Entity,
+    },
+}
+
+/// Collisions of the last tick.
+#[derive(Debug, Default)]
+pub struct CollisionEvents {
+    events: Vec<CollisionEvent>,
+
+    /// Contacts from `a` to `b` of the entities that are touching.
+    touching: HashMap<(Entity, Entity), Contact>,
+}
+
+impl CollisionEvents {
+    /// Entities that started or stopped touching in the last tick.
+    pub fn events(&self) -> &[CollisionEvent] {
+        &self.events
+    }
+
+    /// All entities that are touching, e.g. to check whether a ship is docked.
+    pub fn touching(&self) -> impl Iterator<Item = (Entity, Entity, &Contact)> {
+        self.touching
+            .iter()
+            .map(|((a, b), contact)| (*a, *b, contact))
+    }
+
+    pub fn contact(&self, a: Entity, b: Entity) -> Option<Contact> {
+        if let Some(contact) = self.touching.get(&(a, b)) {
+            Some(*contact)
+        }
+        else {
+            self.touching.get(&(b, a)).map(|contact| {
+                Contact {
+                    normal: -contact.normal,
+                    depth: contact.depth,
+                }
+            })
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CollisionConfig {
+    /// Edge length of the cells of the broadphase grid. Should be about the
+    /// size of the common colliders.
+    pub cell_size: f32,
+}
+
+impl Default for CollisionConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+}
+
+pub fn collision_system(system_context: &mut SystemContext) {
+    let cell_size = system_context
+        .resources
+        .get::<CollisionConfig>()
+        .map_or(DEFAULT_CELL_SIZE, |config| config.cell_size);
+
+    let touching = {
+        let mut query = system_context
+            .world
+            .query::<(&Collider, &GlobalTransform)>();
+        let bodies = query
+            .iter()
+            .map(|(entity, (collider, transform))| {
+                Body {
+                    key: entity,
+                    shape: &collider.shape,
+                    pose: transform.model_matrix,
+                    layers: collider.layers,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        detect_collisions(&bodies, cell_size)
+            .into_iter()
+            .map(|collision| ((collision.a, collision.b), collision.contact))
+            .collect::<HashMap<_, _>>()
+    };
+
+    let collision_events = system_context
+        .resources
+        .get_mut_or_insert_default::<CollisionEvents>();
+    collision_events.events.clear();
+    for (&(a, b), contact) in &touching {
+        if collision_events.contact(a, b).is_none() {
+            collision_events.events.push(CollisionEvent::Started {
+                a,
+                b,
+                contact: *contact,
+            });
+        }
+    }
+    for &(a, b) in collision_events.touching.keys() {
+        if !touching.contains_key(&(a, b)) && !touching.contains_key(&(b, a)) {
+            collision_events
+                .events
+                .push(CollisionEvent::Stopped { a, b });
+        }
+    }
+    collision_events.touching = touching;
+
+    // the deepest contact is resolved, so that overlapping colliders don't push
+    // the entity out twice.
+    let mut push_outs = HashMap::<Entity, Vector3<f32>>::new();
+    for (&(a, b), contact) in &collision_events.touching {
+        for (entity, offset) in [
+            (a, -contact.normal.into_inner() * contact.depth),
+            (b, contact.normal.into_inner() * contact.depth),
+        ] {
+            if system_context
+                .world
+                .satisfies::<&PushOut>(entity)
+                .unwrap_or(false)
+            {
+                let push_out = push_outs.entry(entity).or_default();
+                if offset.norm_squared() > push_out.norm_squared() {
+                    *push_out = offset;
+                }
+            }
+        }
+    }
+    for (entity, offset) in push_outs {
+        if let Ok(transform) = system_context.world.query_one_mut::<&mut Transform>(entity) {
+            transform
+                .model_matrix
+                .append_translation_mut(&Translation3::from(offset));
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CollisionPlugin {
+    config: CollisionConfig,
+}
+
+impl CollisionPlugin {
+    pub fn with_cell_size(mut self, cell_size: f32) -> Self {
+        self.config.cell_size = cell_size;
+        self
+    }
+}
+
+impl Plugin for CollisionPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.resources.insert(self.config);
+        context.resources.insert(CollisionEvents::default());
+        context.schedule.add_system(collision_system);
+    }
+}
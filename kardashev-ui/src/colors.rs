@@ -0,0 +1,114 @@
+//! Semantic colors.
+//!
+//! The colors are defined in `src/palette.toml`. The style pipeline generates
+//! CSS custom properties from it, which the stylesheets use through the
+//! variables in `prelude.scss`, and the [`Palette`] constants in this module.
+//! Besides the default palette, there are palettes for color vision
+//! deficiencies, which are selected in the
+//! [accessibility settings](crate::app::accessibility).
+//!
+//! Gizmos that are colored by a [`Tint`] are recolored when the
+//! [`ActivePalette`] changes.
+
+use palette::{
+    Srgba,
+    WithAlpha,
+};
+
+use crate::{
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        resource::Resources,
+        system::SystemContext,
+    },
+    graphics::gizmo::{
+        Line,
+        Polygon,
+    },
+};
+
+include!(concat!(env!("OUT_DIR"), "/palette.rs"));
+
+/// Resource with the palette in use.
+#[derive(Clone, Copy, Debug)]
+pub struct ActivePalette(pub &'static Palette);
+
+impl ActivePalette {
+    pub fn get(resources: &Resources) -> &'static Palette {
+        resources
+            .get::<ActivePalette>()
+            .copied()
+            .unwrap_or_default()
+            .0
+    }
+}
+
+impl Default for ActivePalette {
+    fn default() -> Self {
+        Self(&Palette::DEFAULT)
+    }
+}
+
+/// Colors a [`Line`] or the outline of a [`Polygon`] with a palette token.
+#[derive(Clone, Copy, Debug)]
+pub struct Tint {
+    pub token: Token,
+    pub alpha: f32,
+
+    /// Opacity of the fill of a polygon. Without it, the fill is transparent
+    /// and the tint leaves it alone, e.g. because the heatmap colors it.
+    pub fill_alpha: Option<f32>,
+}
+
+impl Tint {
+    pub fn new(token: Token, alpha: f32) -> Self {
+        Self {
+            token,
+            alpha,
+            fill_alpha: None,
+        }
+    }
+
+    pub fn with_fill(mut self, alpha: f32) -> Self {
+        self.fill_alpha = Some(alpha);
+        self
+    }
+
+    pub fn color(&self, palette: &Palette) -> Srgba<f32> {
+        palette.get(self.token).with_alpha(self.alpha)
+    }
+
+    pub fn fill(&self, palette: &Palette) -> Srgba<f32> {
+        palette
+            .get(self.token)
+            .with_alpha(self.fill_alpha.unwrap_or(0.0))
+    }
+}
+
+fn tint_system(system_context: &mut SystemContext) {
+    let palette = ActivePalette::get(&system_context.resources);
+
+    for (_, (tint, line)) in system_context.world.query_mut::<(&Tint, &mut Line)>() {
+        line.color = tint.color(palette);
+    }
+
+    for (_, (tint, polygon)) in system_context.world.query_mut::<(&Tint, &mut Polygon)>() {
+        polygon.outline = tint.color(palette);
+        if tint.fill_alpha.is_some() {
+            polygon.fill = tint.fill(palette);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ColorsPlugin;
+
+impl Plugin for ColorsPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.resources.insert(ActivePalette::default());
+        context.schedule.add_system(tint_system);
+    }
+}
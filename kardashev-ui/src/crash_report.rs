@@ -0,0 +1,390 @@
+//! Crash and error reports.
+//!
+//! Panics and unhandled errors are captured together with the most recent log
+//! events (breadcrumbs), and stored in the web fs under `crash-reports`. If
+//! the player opted in, they're also sent to the server, where they show up on
+//! the admin dashboard. Otherwise a notification asks whether to send them.
+//!
+//! A panic leaves the wasm module in a broken state, so the panic hook can't
+//! run any async code. It only writes the report to local storage, and the
+//! report is moved to the web fs the next time the app starts.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write,
+};
+
+use chrono::Utc;
+use kardashev_client::ApiClient;
+use kardashev_protocol::{
+    Breadcrumb,
+    ClientErrorKind,
+    ClientErrorReport,
+};
+use leptos::{
+    create_effect,
+    expect_context,
+    provide_context,
+    Signal,
+    SignalGetUntracked,
+    SignalSet,
+    WriteSignal,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::{
+    field::{
+        Field,
+        Visit,
+    },
+    Event,
+    Level,
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::Context,
+    Layer,
+};
+
+use crate::{
+    error::error_chain,
+    i18n::{
+        use_i18n,
+        I18n,
+    },
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    utils::{
+        futures::spawn_local,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+/// How many log events are attached to a report.
+const MAX_BREADCRUMBS: usize = 50;
+
+/// How many reports are kept in local storage until they're moved to the web
+/// fs.
+const MAX_PENDING: usize = 20;
+
+const PENDING_KEY: &str = "crash-reports-pending";
+const WEB_FS_ROOT: &str = "crash-reports";
+const NOTIFICATION_TITLE: &str = "notification-crash-report-saved";
+
+#[derive(Debug, thiserror::Error)]
+#[error("crash report error")]
+enum Error {
+    WebFs(#[from] web_fs::Error),
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReportSettings {
+    /// Send reports to the server without asking.
+    #[serde(default)]
+    pub send_reports: bool,
+}
+
+/// The crash report settings, provided as context.
+#[derive(Clone, Copy)]
+pub struct CrashReportSettingsContext {
+    pub settings: Signal<CrashReportSettings>,
+    pub set_settings: WriteSignal<CrashReportSettings>,
+}
+
+thread_local! {
+    static BREADCRUMBS: RefCell<VecDeque<Breadcrumb>> = RefCell::new(VecDeque::with_capacity(MAX_BREADCRUMBS));
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+}
+
+/// Records the most recent events with level `INFO` or higher.
+pub struct BreadcrumbLayer;
+
+impl<S: Subscriber> Layer<S> for BreadcrumbLayer {
+    fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > Level::INFO {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let breadcrumb = Breadcrumb {
+            time: Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_owned(),
+            message: visitor.message,
+        };
+
+        // an event logged while the breadcrumbs are borrowed (i.e. by the panic hook)
+        // is dropped.
+        let _ = BREADCRUMBS.try_with(|breadcrumbs| {
+            if let Ok(mut breadcrumbs) = breadcrumbs.try_borrow_mut() {
+                if breadcrumbs.len() == MAX_BREADCRUMBS {
+                    breadcrumbs.pop_front();
+                }
+                breadcrumbs.push_back(breadcrumb);
+            }
+        });
+    }
+}
+
+/// Formats an event's message followed by its fields.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        }
+        else {
+            let _ = write!(self.message, "{}={value:?}", field.name());
+        }
+    }
+}
+
+/// Sets a panic hook that logs the panic to the console and records a crash
+/// report.
+pub fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+
+        let message = if let Some(message) = info.payload().downcast_ref::<&str>() {
+            (*message).to_owned()
+        }
+        else if let Some(message) = info.payload().downcast_ref::<String>() {
+            message.clone()
+        }
+        else {
+            "panic".to_owned()
+        };
+
+        store_pending(new_report(
+            ClientErrorKind::Panic,
+            message,
+            info.location().map(ToString::to_string),
+        ));
+    }));
+}
+
+/// Records a crash report for an error that wasn't handled otherwise.
+///
+/// The report contains the error and its sources.
+pub fn report_error(error: &dyn std::error::Error) {
+    store_pending(new_report(ClientErrorKind::Error, error_chain(error), None));
+    spawn_local(flush_pending());
+}
+
+fn new_report(
+    kind: ClientErrorKind,
+    message: String,
+    location: Option<String>,
+) -> ClientErrorReport {
+    let breadcrumbs = BREADCRUMBS
+        .try_with(|breadcrumbs| {
+            breadcrumbs
+                .try_borrow()
+                .map(|breadcrumbs| breadcrumbs.iter().cloned().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default();
+
+    ClientErrorReport {
+        kind,
+        message,
+        location,
+        time: Utc::now(),
+        client_version: env!("CARGO_PKG_VERSION").to_owned(),
+        user_agent: gloo_utils::window().navigator().user_agent().ok(),
+        breadcrumbs,
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    gloo_utils::window().local_storage().ok().flatten()
+}
+
+/// Appends a report to the pending reports in local storage.
+///
+/// This is synchronous, so it can be used from the panic hook.
+fn store_pending(report: ClientErrorReport) {
+    let Some(storage) = local_storage()
+    else {
+        return;
+    };
+
+    let mut pending = take_pending(&storage);
+    if pending.len() == MAX_PENDING {
+        pending.remove(0);
+    }
+    pending.push(report);
+
+    if let Ok(json) = serde_json::to_string(&pending) {
+        let _ = storage.set_item(PENDING_KEY, &json);
+    }
+}
+
+fn take_pending(storage: &web_sys::Storage) -> Vec<ClientErrorReport> {
+    let pending = storage
+        .get_item(PENDING_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let _ = storage.remove_item(PENDING_KEY);
+    pending
+}
+
+/// What's needed to send reports, set up by [`provide_crash_reporting`].
+#[derive(Clone)]
+struct Reporter {
+    api_client: ApiClient,
+    notifications: Notifications,
+    i18n: I18n,
+    settings: Signal<CrashReportSettings>,
+    set_settings: WriteSignal<CrashReportSettings>,
+}
+
+/// Loads the crash report settings and handles reports that were recorded
+/// before, e.g. from a panic in the last session.
+///
+/// This must be called after the [`ApiClient`], [`Notifications`] and
+/// [`I18n`] were provided.
+pub fn provide_crash_reporting() {
+    let (settings, set_settings, _) = use_local_storage::<
+        CrashReportSettings,
+        codee::string::JsonSerdeCodec,
+    >("crash-report-settings");
+
+    let reporter = Reporter {
+        api_client: expect_context(),
+        notifications: expect_context(),
+        i18n: use_i18n(),
+        settings,
+        set_settings,
+    };
+    let i18n = reporter.i18n;
+    REPORTER.with_borrow_mut(|r| *r = Some(reporter));
+    provide_context(CrashReportSettingsContext {
+        settings,
+        set_settings,
+    });
+
+    // wait until the catalog is loaded, so that the notification is translated.
+    create_effect(move |flushed: Option<bool>| {
+        if flushed == Some(true) {
+            return true;
+        }
+        let loaded = i18n.translate(NOTIFICATION_TITLE) != NOTIFICATION_TITLE;
+        if loaded {
+            spawn_local(flush_pending());
+        }
+        loaded
+    });
+}
+
+/// Moves the pending reports to the web fs, and sends them or asks whether to
+/// send them.
+async fn flush_pending() {
+    let Some(reporter) = REPORTER.with_borrow(Clone::clone)
+    else {
+        return;
+    };
+    let Some(storage) = local_storage()
+    else {
+        return;
+    };
+
+    let pending = take_pending(&storage);
+    if pending.is_empty() {
+        return;
+    }
+
+    if let Err(error) = save(&pending).await {
+        tracing::warn!(?error, "failed to save crash reports");
+    }
+
+    if reporter.settings.get_untracked().send_reports {
+        send(reporter.api_client, pending).await;
+        return;
+    }
+
+    let i18n = reporter.i18n;
+    let send_now = pending.clone();
+    reporter.notifications.notify(
+        Notification::warning(NOTIFICATION_TITLE)
+            .with_message(i18n.translate_with_args(
+                "notification-crash-report-saved-message",
+                &[("count", pending.len().to_string())],
+            ))
+            .with_action(i18n.translate("crash-report-send"), move || {
+                send_later(send_now.clone(), false);
+            })
+            .with_action(i18n.translate("crash-report-always-send"), move || {
+                send_later(pending.clone(), true);
+            }),
+    );
+}
+
+/// Sends reports from a notification action, which must be `Send`.
+fn send_later(reports: Vec<ClientErrorReport>, always: bool) {
+    let Some(reporter) = REPORTER.with_borrow(Clone::clone)
+    else {
+        return;
+    };
+    if always {
+        reporter
+            .set_settings
+            .set(CrashReportSettings { send_reports: true });
+    }
+    spawn_local(send(reporter.api_client, reports));
+}
+
+async fn send(api_client: ApiClient, reports: Vec<ClientErrorReport>) {
+    for report in &reports {
+        // errors are not reported, as that might create another report.
+        if let Err(error) = api_client.report_client_error(report).await {
+            tracing::warn!(?error, "failed to send crash report");
+            return;
+        }
+    }
+    tracing::info!(count = reports.len(), "sent crash reports");
+}
+
+async fn save(reports: &[ClientErrorReport]) -> Result<(), Error> {
+    let web_fs = WebFs::with_named_root(WEB_FS_ROOT).await?;
+    for (i, report) in reports.iter().enumerate() {
+        let kind = match report.kind {
+            ClientErrorKind::Panic => "panic",
+            ClientErrorKind::Error => "error",
+        };
+        let file_name = format!(
+            "{}-{i}-{kind}.json",
+            report.time.format("%Y-%m-%d-%H%M%S%3f")
+        );
+        let data = serde_json::to_vec_pretty(report)?;
+        web_fs
+            .open(&file_name, OpenOptions::new().create(true))
+            .await?
+            .write(data)
+            .await?;
+    }
+    Ok(())
+}
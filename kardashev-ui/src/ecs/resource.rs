@@ -45,6 +45,12 @@ impl Resources {
             .map(|resource| resource.downcast_mut().unwrap())
     }
 
+    pub fn remove<R: 'static>(&mut self) -> Option<R> {
+        self.resources
+            .remove(&TypeId::of::<R>())
+            .map(|resource| *resource.downcast().unwrap())
+    }
+
     pub fn try_get_mut<R: 'static>(&mut self) -> Result<&mut R, ResourceNotFound> {
         self.get_mut().ok_or_else(|| {
             ResourceNotFound {
@@ -27,6 +27,7 @@ impl System for Schedule {
 
     fn poll_system(&mut self, system_context: &mut SystemContext<'_>) -> Result<(), Self::Error> {
         for system in &mut self.systems {
+            let _span = tracing::debug_span!("system", label = system.label()).entered();
             system.poll_system(system_context).map_err(|error| {
                 Error::System {
                     system: system.label(),
@@ -273,6 +273,7 @@ impl Reactor {
                     }
                 }
                 _ = self.tick.tick() => {
+                    let _span = tracing::debug_span!("tick", tick = system_context.tick.0).entered();
                     self.schedule.poll_system(&mut system_context)?;
                     system_context.apply_buffered();
                 }
@@ -1,3 +1,5 @@
+use std::fmt::Write;
+
 #[derive(Debug, thiserror::Error)]
 #[error("app error")]
 pub enum Error {
@@ -5,3 +7,14 @@ pub enum Error {
     Client(#[from] kardashev_client::Error),
     Schedule(#[from] crate::ecs::Error),
 }
+
+/// Formats an error with its sources, e.g. `asset loader error: http error`.
+pub fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        let _ = write!(message, ": {error}");
+        source = error.source();
+    }
+    message
+}
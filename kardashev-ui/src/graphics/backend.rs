@@ -16,6 +16,15 @@ use serde::{
 
 use crate::{
     graphics::{
+        capabilities::{
+            Capabilities,
+            OPTIONAL_FEATURES,
+        },
+        mipmap::MipmapGenerators,
+        watchdog::{
+            Failure,
+            FailureReporter,
+        },
         Config,
         Error,
     },
@@ -56,14 +65,19 @@ pub struct Backend {
     pub adapter: Arc<wgpu::Adapter>,
     pub device: Arc<wgpu::Device>,
     pub queue: Arc<wgpu::Queue>,
+    pub capabilities: Arc<Capabilities>,
+    pub failures: FailureReporter,
+    pub mipmap_generators: MipmapGenerators,
 }
 
 impl Backend {
     pub(super) async fn new(
         instance: Arc<wgpu::Instance>,
+        backend_type: BackendType,
         config: &Config,
         compatible_surface: Option<&wgpu::Surface<'static>>,
         required_limits: wgpu::Limits,
+        failures: FailureReporter,
     ) -> Result<Self, Error> {
         tracing::debug!("creating render adapter");
         let adapter = instance
@@ -79,7 +93,7 @@ impl Backend {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: Default::default(),
+                    required_features: adapter.features() & OPTIONAL_FEATURES,
                     required_limits,
                     memory_hints: config.memory_hints.as_wgpu(),
                 },
@@ -92,8 +106,22 @@ impl Backend {
             panic!("uncaptured wgpu error: {error}");
         }));
 
+        let device_lost = failures.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            tracing::error!(?reason, message, "device lost");
+            match reason {
+                // the device was dropped on purpose, e.g. by the watchdog.
+                wgpu::DeviceLostReason::Destroyed
+                | wgpu::DeviceLostReason::Dropped
+                | wgpu::DeviceLostReason::ReplacedCallback => {}
+                _ => device_lost.report(Failure::DeviceLost { message }),
+            }
+        });
+
         tracing::debug!("device features: {:#?}", device.features());
 
+        let capabilities = Capabilities::detect(backend_type, &adapter, &device);
+
         static IDS: AtomicUsize = AtomicUsize::new(1);
         let id = BackendId(NonZeroUsize::new(IDS.fetch_add(1, Ordering::Relaxed)).unwrap());
 
@@ -103,6 +131,9 @@ impl Backend {
             adapter: Arc::new(adapter),
             device: Arc::new(device),
             queue: Arc::new(queue),
+            capabilities: Arc::new(capabilities),
+            failures,
+            mipmap_generators: MipmapGenerators::default(),
         })
     }
 }
@@ -5,7 +5,6 @@ use bytemuck::{
 use kardashev_protocol::assets::{
     self as dist,
     AssetId,
-    Vertex,
 };
 use palette::Srgb;
 
@@ -18,30 +17,27 @@ use crate::{
         AssetNotFound,
     },
     graphics::{
-        draw_batch::DrawBatcher,
         material::{
             get_fallback,
             BindGroupBuilder,
+            BlendMode,
             GpuMaterial,
             MaterialError,
             PipelineMaterial,
         },
-        render_3d::{
-            CreateRender3dPipeline,
-            CreateRender3dPipelineContext,
-            MeshMaterialPair,
-            MeshMaterialPairKey,
-            Render3dPipeline,
-            Render3dPipelineContext,
+        material_pipeline::{
+            MaterialDescriptor,
+            MaterialShader,
         },
         texture::{
             Texture,
             TextureError,
         },
+        transform::GlobalTransform,
         utils::{
+            BindGroupLayoutBuilder,
             GpuResourceCache,
             HasVertexBufferLayout,
-            MaterialBindGroupLayoutBuilder,
             Srgb32Ext,
         },
     },
@@ -50,127 +46,6 @@ use crate::{
 #[include_wgsl_oil::include_wgsl_oil("blinn_phong.wgsl")]
 mod shader {}
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct CreateBlinnPhongRenderPipeline;
-
-impl CreateRender3dPipeline for CreateBlinnPhongRenderPipeline {
-    type Pipeline = BlinnPhongRenderPipeline;
-
-    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
-        let shader = context
-            .backend
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("blinn_phong.wgsl"),
-                source: wgpu::ShaderSource::Wgsl(shader::SOURCE.into()),
-            });
-
-        let mut material_bind_group_layout_builder = MaterialBindGroupLayoutBuilder::default();
-        for _ in 0..7 {
-            material_bind_group_layout_builder.push_view_and_sampler();
-        }
-
-        let material_bind_group_layout = material_bind_group_layout_builder.build(
-            &context.backend.device,
-            Some("blinn-phong material bind group"),
-        );
-
-        let pipeline_layout =
-            context
-                .backend
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("blinn-phong pipeline layout"),
-                    bind_group_layouts: &[
-                        &material_bind_group_layout,
-                        &context.camera_bind_group_layout,
-                        &context.light_bind_group_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-
-        let pipeline =
-            context
-                .backend
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("blinn-phong pipeline"),
-                    layout: Some(&pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: "vs_main",
-                        buffers: &[Vertex::layout(), Instance::layout()],
-                        compilation_options: Default::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: context.surface_format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: Default::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        unclipped_depth: false,
-                        conservative: false,
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: context.depth_texture_format,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState::default(),
-                    }),
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                    cache: None,
-                });
-
-        BlinnPhongRenderPipeline {
-            pipeline,
-            material_bind_group_layout,
-            draw_batcher: DrawBatcher::new(context.backend),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct BlinnPhongRenderPipeline {
-    pipeline: wgpu::RenderPipeline,
-    material_bind_group_layout: wgpu::BindGroupLayout,
-    draw_batcher: DrawBatcher<MeshMaterialPairKey, MeshMaterialPair<BlinnPhongMaterial>, Instance>,
-}
-
-impl Render3dPipeline for BlinnPhongRenderPipeline {
-    fn render(&mut self, pipeline_context: &mut Render3dPipelineContext) {
-        pipeline_context.render_pass.set_pipeline(&self.pipeline);
-        pipeline_context.bind_camera_uniform(1);
-        pipeline_context.bind_light_uniform(2);
-        pipeline_context.batch_meshes_with_material::<BlinnPhongMaterial, Instance>(
-            &mut self.draw_batcher,
-            &self.material_bind_group_layout,
-            |transform, material| {
-                Instance {
-                    model_transform: transform.as_homogeneous_matrix_array(),
-                    material: MaterialInstanceData::from_material(material),
-                }
-            },
-        );
-        pipeline_context.draw_batched_meshes_with_materials(&mut self.draw_batcher, 1, 0, 0);
-    }
-}
-
 #[derive(Clone, Debug, Default)]
 pub struct BlinnPhongMaterial {
     pub ambient_texture: Option<Texture>,
@@ -186,9 +61,44 @@ pub struct BlinnPhongMaterial {
     pub dissolve: Option<f32>,
     pub emissive_texture: Option<Texture>,
     pub emissive_color: Option<Srgb<f32>>,
+
+    /// Scales the emissive color that is written to the bloom target.
+    pub bloom: Option<f32>,
 }
 
 impl PipelineMaterial for BlinnPhongMaterial {
+    type Instance = Instance;
+
+    fn descriptor() -> MaterialDescriptor {
+        MaterialDescriptor {
+            label: "blinn-phong",
+            shader: MaterialShader {
+                label: "blinn_phong.wgsl",
+                source: shader::SOURCE,
+            },
+            emissive: true,
+            bind_group_layout: (0..7).fold(BindGroupLayoutBuilder::default(), |builder, _| {
+                builder.with_view_and_sampler(wgpu::ShaderStages::FRAGMENT)
+            }),
+        }
+    }
+
+    fn instance(&self, transform: &GlobalTransform) -> Instance {
+        Instance {
+            model_transform: transform.as_homogeneous_matrix_array(),
+            material: MaterialInstanceData::from_material(self),
+        }
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        if self.dissolve.map_or(false, |dissolve| dissolve > 0.0) {
+            BlendMode::AlphaBlend
+        }
+        else {
+            BlendMode::Opaque
+        }
+    }
+
     async fn load_from_server<'a, 'b: 'a>(
         asset_id: AssetId,
         mut context: &'a mut LoadAssetContext<'b>,
@@ -240,6 +150,7 @@ impl PipelineMaterial for BlinnPhongMaterial {
             dissolve: dist.dissolve,
             emissive_texture,
             emissive_color: dist.emissive_color,
+            bloom: dist.bloom,
         })
     }
 
@@ -336,6 +247,7 @@ pub struct MaterialInstanceData {
     pub emissive_color: [f32; 3],
     pub shininess: f32,
     pub dissolve: f32,
+    pub bloom: f32,
 }
 
 impl MaterialInstanceData {
@@ -348,6 +260,7 @@ impl MaterialInstanceData {
             emissive_color: material.emissive_color.unwrap_or(WHITE).as_array3(),
             shininess: material.shininess.unwrap_or(64.0),
             dissolve: material.dissolve.unwrap_or(0.0),
+            bloom: material.bloom.unwrap_or(0.0),
         }
     }
 }
@@ -422,6 +335,12 @@ impl HasVertexBufferLayout for Instance {
                     shader_location: 14,
                     format: wgpu::VertexFormat::Float32,
                 },
+                // material bloom
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 30]>() as wgpu::BufferAddress,
+                    shader_location: 15,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
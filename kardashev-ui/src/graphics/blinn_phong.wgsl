@@ -1,11 +1,15 @@
 #import camera.wgsl::Camera;
-#import light.wgsl::Lights;
+#import light.wgsl::{Lights, light_cluster, cluster_light, light_attenuation};
 
 @group(1) @binding(0)
 var<uniform> camera: Camera;
 
 @group(2) @binding(0)
 var<uniform> light: Lights;
+@group(2) @binding(1)
+var point_lights: texture_2d<f32>;
+@group(2) @binding(2)
+var light_clusters: texture_2d<u32>;
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
@@ -26,24 +30,29 @@ struct InstanceInput {
     @location(12) material_emissive_color: vec3<f32>,
     @location(13) material_shininess: f32,
     @location(14) material_dissolve: f32,
+    @location(15) material_bloom: f32,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-    @location(1) tangent_position: vec3<f32>,
-    @location(2) tangent_view_position: vec3<f32>,
-    @location(3) tangent_light_position: vec3<f32>,
-    @location(4) material_ambient_color: vec3<f32>,
-    @location(5) material_diffuse_color: vec3<f32>,
-    @location(6) material_specular_color: vec3<f32>,
-    @location(7) material_emissive_color: vec3<f32>,
-    @location(8) material_shininess: f32,
-    @location(9) material_dissolve: f32,
+    @location(1) world_position: vec3<f32>,
+    @location(2) world_tangent: vec3<f32>,
+    @location(3) world_bitangent: vec3<f32>,
+    @location(4) world_normal: vec3<f32>,
+    @location(5) material_ambient_color: vec3<f32>,
+    @location(6) material_diffuse_color: vec3<f32>,
+    @location(7) material_specular_color: vec3<f32>,
+    @location(8) material_emissive_color: vec3<f32>,
+    @location(9) material_shininess: f32,
+    @location(10) material_dissolve: f32,
+    @location(11) material_bloom: f32,
 }
 
 struct FragmentOutput {
     @location(0) color: vec4<f32>,
+    // emissive light that blooms
+    @location(1) emissive: vec4<f32>,
 }
 
 @group(0) @binding(0)
@@ -100,22 +109,15 @@ fn vs_main(
     //let world_tangent = vertex.tangent;
     //let world_bitangent = vertex.bitangent;
 
-    let tangent_matrix = transpose(mat3x3<f32>(
-        world_tangent,
-        world_bitangent,
-        world_normal,
-    ));
-
     out.clip_position = camera.view_projection * world_position;
     out.tex_coords = vertex.tex_coords;
-        
-    out.tangent_position = tangent_matrix * world_position.xyz;
-    out.tangent_view_position = tangent_matrix * camera.view_position.xyz;
-    // fixme
-    //for (var i: u32 = 0; i < light.num_point_lights; i++) {
-    //    out.tangent_light_position[i] = tangent_matrix * light.point_lights[i].position;
-    //}
-    out.tangent_light_position = tangent_matrix * light.point_lights[0].position.xyz;
+
+    // the lights are transformed into tangent space per fragment, since there are too many to pass
+    // them from the vertex shader.
+    out.world_position = world_position.xyz;
+    out.world_tangent = world_tangent;
+    out.world_bitangent = world_bitangent;
+    out.world_normal = world_normal;
     
     out.material_ambient_color = instance.material_ambient_color;
     out.material_diffuse_color = instance.material_diffuse_color;
@@ -123,6 +125,7 @@ fn vs_main(
     out.material_emissive_color = instance.material_emissive_color;
     out.material_shininess = instance.material_shininess;
     out.material_dissolve = instance.material_dissolve;
+    out.material_bloom = instance.material_bloom;
     return out;
 }
 
@@ -134,8 +137,13 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let tangent_normal = textureSample(material_normal_texture_view, material_normal_sampler, in.tex_coords).xyz * 2.0 - 1.0;
     //let tangent_normal = vec3f(0.0, 0.0, 1.0);
 
-    //let view_direction = normalize(camera.view_position - in.world_position);
-    let view_direction = normalize(in.tangent_view_position - in.tangent_position);
+    let tangent_matrix = transpose(mat3x3<f32>(
+        normalize(in.world_tangent),
+        normalize(in.world_bitangent),
+        normalize(in.world_normal),
+    ));
+
+    let view_direction = tangent_matrix * normalize(camera.view_position - in.world_position);
     
     let ambient_texture_color = textureSample(material_ambient_texture_view, material_ambient_sampler, in.tex_coords).xyz;
     let ambient_color = light.ambient_light * ambient_texture_color * in.material_ambient_color;
@@ -152,24 +160,38 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     let texture_shininess = textureSample(material_shininess_texture_view, material_shininess_sampler, in.tex_coords).x;
     let shininess = texture_shininess * in.material_shininess;
 
-    // spot lights
-    for (var i: u32 = 0; i < light.num_point_lights; i++) {
-        let light_direction = normalize(in.tangent_light_position - in.tangent_position);
+    // point lights of the fragment's cluster
+    let cluster = light_cluster(light, light_clusters, in.clip_position.xy, in.world_position);
+    for (var i: u32 = 0; i < cluster.num_lights; i++) {
+        let point_light = cluster_light(point_lights, light_clusters, cluster, i);
+        let to_light = point_light.position - in.world_position;
+        let distance = length(to_light);
+        let attenuation = light_attenuation(point_light, distance);
+        if attenuation <= 0.0 {
+            continue;
+        }
+        let light_direction = tangent_matrix * (to_light / distance);
         
         let reflect_direction = reflect(-light_direction, tangent_normal);
         //let half_direction = normalize(view_direction + light_direction);
         
         let diffuse_strength = max(dot(tangent_normal, light_direction), 0.0);
-        diffuse_color += light.point_lights[i].color * diffuse_strength;
+        diffuse_color += point_light.color * diffuse_strength * attenuation;
 
         let specular_strength = pow(max(dot(view_direction, reflect_direction), 0.0), shininess);
         //let specular_strength = pow(max(dot(tangent_normal, half_direction), 0.0), shininess);
-        specular_color += light.point_lights[i].color * specular_strength;
+        specular_color += point_light.color * specular_strength * attenuation;
     }
     diffuse_color *= diffuse_texture_color * in.material_diffuse_color;
     specular_color *= specular_texture_color * in.material_specular_color;
     
-    out.color = vec4f(ambient_color + emissive_color + diffuse_color + specular_color, 1.0);
+    // dissolve is the transparency of the material. it's only blended if the material's dissolve is
+    // greater than 0.
+    let dissolve_texture_value = textureSample(material_dissolve_texture_view, material_dissolve_sampler, in.tex_coords).x;
+    let alpha = 1.0 - dissolve_texture_value * in.material_dissolve;
+
+    out.color = vec4f(ambient_color + emissive_color + diffuse_color + specular_color, alpha);
+    out.emissive = vec4f(emissive_color * in.material_bloom, alpha);
 
     return out;
 }
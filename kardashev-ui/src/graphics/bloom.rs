@@ -0,0 +1,359 @@
+//! Bloom of emissive light.
+//!
+//! Render passes that are created with an
+//! [`emissive_format`](super::render_frame::CreateRenderPassContext::emissive_format)
+//! write the light that should bloom into a separate emissive target, e.g.
+//! bright stars and materials with a `bloom` factor. [`Bloom`] blurs it by
+//! downsampling it into a mip chain and upsampling it again. The
+//! [`ToneMapPass`](super::hdr::ToneMapPass) then adds the blurred light to the
+//! image.
+
+use crate::graphics::{
+    backend::Backend,
+    mipmap::mip_level_count,
+    SurfaceSize,
+};
+
+/// Maximum number of mip levels of the blur. More levels make the bloom
+/// wider.
+const MAX_MIP_LEVELS: u32 = 6;
+
+#[derive(Debug)]
+pub struct Bloom {
+    format: wgpu::TextureFormat,
+    pipeline: BloomPipeline,
+    textures: BloomTextures,
+}
+
+impl Bloom {
+    pub fn new(backend: &Backend, size: SurfaceSize, format: wgpu::TextureFormat) -> Self {
+        let pipeline = BloomPipeline::new(backend, format);
+        let textures = BloomTextures::new(backend, size, format, &pipeline);
+
+        Self {
+            format,
+            pipeline,
+            textures,
+        }
+    }
+
+    /// The target for the emissive light, with the size of the render target.
+    pub fn emissive_view(&self) -> &wgpu::TextureView {
+        &self.textures.emissive_view
+    }
+
+    /// The blurred emissive light, at half the size of the render target.
+    pub fn output_view(&self) -> &wgpu::TextureView {
+        &self.textures.mip_views[0]
+    }
+
+    /// Resizes the textures, if the render target was resized. Returns whether
+    /// they were resized, in which case the [`output_view`](Self::output_view)
+    /// changed.
+    pub fn resize_if_needed(&mut self, backend: &Backend, size: SurfaceSize) -> bool {
+        if SurfaceSize::from_texture(&self.textures.emissive) == size {
+            return false;
+        }
+
+        tracing::debug!(?size, "resizing bloom textures");
+        self.textures = BloomTextures::new(backend, size, self.format, &self.pipeline);
+        true
+    }
+
+    /// Blurs the emissive light. This must be recorded after the emissive
+    /// light was rendered.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder) {
+        let textures = &self.textures;
+
+        for (target, bind_group) in textures
+            .mip_views
+            .iter()
+            .zip(&textures.downsample_bind_groups)
+        {
+            self.pipeline.draw(
+                encoder,
+                "bloom downsample render pass",
+                &self.pipeline.downsample,
+                target,
+                bind_group,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            );
+        }
+
+        for (target, bind_group) in textures
+            .mip_views
+            .iter()
+            .zip(&textures.upsample_bind_groups)
+            .rev()
+        {
+            self.pipeline.draw(
+                encoder,
+                "bloom upsample render pass",
+                &self.pipeline.upsample,
+                target,
+                bind_group,
+                wgpu::LoadOp::Load,
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BloomTextures {
+    emissive: wgpu::Texture,
+    emissive_view: wgpu::TextureView,
+    mip_views: Vec<wgpu::TextureView>,
+
+    /// The first reads the emissive target, the others read the previous mip
+    /// level.
+    downsample_bind_groups: Vec<wgpu::BindGroup>,
+
+    /// Each reads the next mip level.
+    upsample_bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl BloomTextures {
+    fn new(
+        backend: &Backend,
+        size: SurfaceSize,
+        format: wgpu::TextureFormat,
+        pipeline: &BloomPipeline,
+    ) -> Self {
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT;
+
+        let emissive = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom emissive texture"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+        let emissive_view = emissive.create_view(&Default::default());
+
+        let width = (size.width / 2).max(1);
+        let height = (size.height / 2).max(1);
+        let mip_level_count = mip_level_count(width, height).min(MAX_MIP_LEVELS);
+        let mips = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("bloom mip texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+        let mip_views = (0..mip_level_count)
+            .map(|mip_level| {
+                mips.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("bloom mip view"),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let downsample_bind_groups = std::iter::once(&emissive_view)
+            .chain(&mip_views[..mip_views.len() - 1])
+            .map(|source| pipeline.create_bind_group(backend, source))
+            .collect();
+        let upsample_bind_groups = mip_views[1..]
+            .iter()
+            .map(|source| pipeline.create_bind_group(backend, source))
+            .collect();
+
+        Self {
+            emissive,
+            emissive_view,
+            mip_views,
+            downsample_bind_groups,
+            upsample_bind_groups,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BloomPipeline {
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+}
+
+impl BloomPipeline {
+    fn new(backend: &Backend, format: wgpu::TextureFormat) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("bloom.wgsl"));
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("bloom bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("bloom pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let create_pipeline = |entry_point: &str, blend: wgpu::BlendState| {
+            backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(&format!("bloom {entry_point} pipeline")),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        compilation_options: Default::default(),
+                        buffers: &[],
+                    },
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point,
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: Some(blend),
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                        compilation_options: Default::default(),
+                    }),
+                    multiview: None,
+                    cache: None,
+                })
+        };
+
+        let downsample = create_pipeline("downsample", wgpu::BlendState::REPLACE);
+
+        // the upsampled light is added to the light of the mip level.
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let upsample = create_pipeline(
+            "upsample",
+            wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            },
+        );
+
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("bloom sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            bind_group_layout,
+            sampler,
+            downsample,
+            upsample,
+        }
+    }
+
+    fn create_bind_group(&self, backend: &Backend, source: &wgpu::TextureView) -> wgpu::BindGroup {
+        backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bloom bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            })
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        pipeline: &wgpu::RenderPipeline,
+        target: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,57 @@
+// Blurs the emissive light by downsampling it into a mip chain, and then
+// upsampling it back, see `bloom.rs`.
+
+struct VertexOutput {
+    @location(0) uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vi: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    // Generate a triangle that covers the whole screen
+    out.uv = vec2<f32>(
+        f32((vi << 1u) & 2u),
+        f32(vi & 2u),
+    );
+    out.clip_position = vec4<f32>(out.uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv.y = 1.0 - out.uv.y;
+    return out;
+}
+
+@group(0)
+@binding(0)
+var source_image: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var source_sampler: sampler;
+
+// averages 4x4 source texels with 4 bilinear samples.
+@fragment
+fn downsample(vs: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_image));
+    var color = textureSample(source_image, source_sampler, vs.uv + vec2<f32>(-1.0, -1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(1.0, -1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(-1.0, 1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(1.0, 1.0) * texel);
+    return color * 0.25;
+}
+
+// 3x3 tent filter. this is added to the downsampled image of the target mip.
+@fragment
+fn upsample(vs: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_image));
+    var color = textureSample(source_image, source_sampler, vs.uv) * 4.0;
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(-1.0, 0.0) * texel) * 2.0;
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(1.0, 0.0) * texel) * 2.0;
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(0.0, -1.0) * texel) * 2.0;
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(0.0, 1.0) * texel) * 2.0;
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(-1.0, -1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(1.0, -1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(-1.0, 1.0) * texel);
+    color += textureSample(source_image, source_sampler, vs.uv + vec2<f32>(1.0, 1.0) * texel);
+    return color / 16.0;
+}
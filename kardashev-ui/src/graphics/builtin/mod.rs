@@ -0,0 +1,102 @@
+//! Built-in assets that are baked into the binary.
+//!
+//! These are available before any network I/O has completed, and are used in
+//! place of assets that are still loading or failed to load.
+
+use image::RgbaImage;
+use kardashev_protocol::{
+    asset_id,
+    assets::AssetId,
+};
+
+use crate::graphics::{
+    material::{
+        Material,
+        PipelineMaterial,
+    },
+    material_pipeline::MaterialShader,
+    mesh::{
+        shape,
+        Mesh,
+        MeshBuilder,
+        Meshable,
+    },
+    texture::Texture,
+};
+
+pub const MISSING_TEXTURE: AssetId = asset_id!("5a1f3b7e-4c0a-4d59-9a0e-6f1e8e2b7c41");
+pub const DEFAULT_MATERIAL: AssetId = asset_id!("0c7d1b55-8a0f-4f6a-b0b5-3a6d2d9c1e02");
+pub const UNIT_CUBE: AssetId = asset_id!("b3c8e6a2-1d4f-4e7b-8c39-5f0a2e9d4b13");
+pub const UNIT_SPHERE: AssetId = asset_id!("e9a4f2c1-7b3d-4a58-9e61-2c8b0d5f3a24");
+
+/// 8x8 magenta/black checkerboard, as raw RGBA8 pixels.
+static MISSING_TEXTURE_DATA: &[u8] = include_bytes!("missing_texture.rgba");
+const MISSING_TEXTURE_SIZE: u32 = 8;
+
+pub fn missing_texture_image() -> RgbaImage {
+    RgbaImage::from_raw(
+        MISSING_TEXTURE_SIZE,
+        MISSING_TEXTURE_SIZE,
+        MISSING_TEXTURE_DATA.to_vec(),
+    )
+    .expect("invalid built-in texture data")
+}
+
+pub fn missing_texture() -> Texture {
+    Texture::from(missing_texture_image())
+        .with_asset_id(MISSING_TEXTURE)
+        .with_label("missing texture")
+}
+
+pub fn default_material<C: PipelineMaterial>() -> Material<C> {
+    Material::new(C::default())
+        .with_asset_id(DEFAULT_MATERIAL)
+        .with_label("default material")
+}
+
+#[include_wgsl_oil::include_wgsl_oil("../error.wgsl")]
+mod error_shader {}
+
+/// Flat magenta shader that is drawn instead of a material whose shader
+/// doesn't match its bind group layout.
+///
+/// It only reads the model transform from the instance, so it works with any
+/// [`PipelineMaterial::Instance`] that starts with it.
+pub const ERROR_SHADER: MaterialShader = MaterialShader {
+    label: "error.wgsl",
+    source: error_shader::SOURCE,
+};
+
+pub fn unit_cube() -> Mesh {
+    Mesh::from(shape::Cuboid::default().mesh().build())
+        .with_asset_id(UNIT_CUBE)
+        .with_label("unit cube")
+}
+
+pub fn unit_sphere() -> Mesh {
+    Mesh::from(shape::Sphere::default().mesh().build())
+        .with_asset_id(UNIT_SPHERE)
+        .with_label("unit sphere")
+}
+
+/// Resource holding the built-in assets.
+///
+/// These can be cloned onto entities. Clones share their CPU data, and since
+/// they have fixed asset IDs, their GPU resources are shared via the
+/// [`GpuResourceCache`][super::utils::GpuResourceCache].
+#[derive(Clone, Debug)]
+pub struct BuiltinAssets {
+    pub missing_texture: Texture,
+    pub unit_cube: Mesh,
+    pub unit_sphere: Mesh,
+}
+
+impl Default for BuiltinAssets {
+    fn default() -> Self {
+        Self {
+            missing_texture: missing_texture(),
+            unit_cube: unit_cube(),
+            unit_sphere: unit_sphere(),
+        }
+    }
+}
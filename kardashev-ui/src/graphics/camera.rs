@@ -3,7 +3,13 @@ use std::{
     sync::Arc,
 };
 
-use nalgebra::Perspective3;
+use nalgebra::{
+    Matrix4,
+    Point2,
+    Point3,
+    RowVector4,
+    Similarity3,
+};
 use palette::{
     Srgba,
     WithAlpha,
@@ -13,21 +19,234 @@ use crate::{
     graphics::{
         backend::Backend,
         Surface,
+        SurfaceSize,
     },
     utils::thread_local_cell::ThreadLocalCell,
 };
 
-#[derive(Debug)]
+/// Kind of projection of a [`CameraProjection`], with the parameters that
+/// determine how much of the world is visible.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians.
+        fovy: f32,
+    },
+    Orthographic {
+        /// Height of the visible area in world units.
+        height: f32,
+    },
+}
+
+impl Projection {
+    /// Interpolates between two projections.
+    ///
+    /// Projections of different kinds can't be interpolated, so this switches
+    /// to `to` once `t` reaches `1.0`.
+    pub fn lerp(&self, to: &Self, t: f32) -> Self {
+        match (self, to) {
+            (Self::Perspective { fovy: from }, Self::Perspective { fovy: to }) => {
+                Self::Perspective {
+                    fovy: from + (to - from) * t,
+                }
+            }
+            (Self::Orthographic { height: from }, Self::Orthographic { height: to }) => {
+                Self::Orthographic {
+                    height: from + (to - from) * t,
+                }
+            }
+            _ if t >= 1.0 => *to,
+            _ => *self,
+        }
+    }
+}
+
+/// Projection of a camera.
+///
+/// The [`aspect`](Self::aspect) of cameras that render to a [`RenderTarget`] is
+/// updated from the size of the render target.
+#[derive(Clone, Copy, Debug)]
 pub struct CameraProjection {
-    pub projection_matrix: Perspective3<f32>,
+    pub projection: Projection,
+
+    /// Ratio of the width to the height of the render target.
+    pub aspect: f32,
+
+    /// Distance of the near clipping plane.
+    pub z_near: f32,
+
+    /// Distance of the far clipping plane.
+    ///
+    /// With reverse-Z the depth precision barely depends on the ratio between
+    /// the near and far plane, so the near plane can be close to the camera
+    /// and the far plane very far away.
+    pub z_far: f32,
 }
 
 impl CameraProjection {
-    pub fn new(aspect: f32, fovy: f32, z_near: f32, z_far: f32) -> Self {
+    pub fn perspective(aspect: f32, fovy: f32, z_near: f32, z_far: f32) -> Self {
         Self {
-            projection_matrix: Perspective3::new(aspect, fovy, z_near, z_far),
+            projection: Projection::Perspective { fovy },
+            aspect,
+            z_near,
+            z_far,
         }
     }
+
+    #[allow(dead_code)]
+    pub fn orthographic(aspect: f32, height: f32, z_near: f32, z_far: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic { height },
+            aspect,
+            z_near,
+            z_far,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_clip_planes(mut self, z_near: f32, z_far: f32) -> Self {
+        self.z_near = z_near;
+        self.z_far = z_far;
+        self
+    }
+
+    /// Height of the visible area at `distance` from the camera, in world
+    /// units.
+    pub fn view_height_at(&self, distance: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective { fovy } => 2.0 * distance * (0.5 * fovy).tan(),
+            Projection::Orthographic { height } => height,
+        }
+    }
+
+    /// Size of a pixel on a surface at `distance` from the camera, in world
+    /// units.
+    pub fn pixel_size_at(&self, distance: f32, surface_size: SurfaceSize) -> f32 {
+        self.view_height_at(distance) / (surface_size.height as f32)
+    }
+
+    /// Projection matrix that is used for rendering.
+    ///
+    /// Points on the near plane get a depth of `1.0` and points on the far
+    /// plane a depth of `0.0` (reverse-Z). Together with a floating-point depth
+    /// buffer the precision is then roughly the same relative to the distance
+    /// everywhere, instead of being concentrated at the near plane. This
+    /// avoids z-fighting in scenes that span from ship to astronomical scales.
+    ///
+    /// Render pipelines must therefore compare depths with
+    /// [`DepthTexture::COMPARE`](super::render_3d::DepthTexture::COMPARE), and
+    /// depth textures are cleared to
+    /// [`DepthTexture::CLEAR`](super::render_3d::DepthTexture::CLEAR).
+    pub fn render_matrix(&self) -> Matrix4<f32> {
+        let depth_range = self.z_far - self.z_near;
+        match self.projection {
+            Projection::Perspective { fovy } => {
+                let f = 1.0 / (0.5 * fovy).tan();
+                Matrix4::from_rows(&[
+                    RowVector4::new(f / self.aspect, 0.0, 0.0, 0.0),
+                    RowVector4::new(0.0, f, 0.0, 0.0),
+                    RowVector4::new(
+                        0.0,
+                        0.0,
+                        self.z_near / depth_range,
+                        self.z_near * self.z_far / depth_range,
+                    ),
+                    RowVector4::new(0.0, 0.0, -1.0, 0.0),
+                ])
+            }
+            Projection::Orthographic { height } => {
+                Matrix4::from_rows(&[
+                    RowVector4::new(2.0 / (height * self.aspect), 0.0, 0.0, 0.0),
+                    RowVector4::new(0.0, 2.0 / height, 0.0, 0.0),
+                    RowVector4::new(0.0, 0.0, 1.0 / depth_range, self.z_far / depth_range),
+                    RowVector4::new(0.0, 0.0, 0.0, 1.0),
+                ])
+            }
+        }
+    }
+
+    /// Projects a point in world space to normalized device coordinates, i.e.
+    /// `x` and `y` from `-1.0` to `1.0` with `y` pointing up, and the depth in
+    /// `z`.
+    ///
+    /// Returns `None` if the point is behind the camera.
+    pub fn world_to_ndc(
+        &self,
+        camera_transform: &Similarity3<f32>,
+        point: &Point3<f32>,
+    ) -> Option<Point3<f32>> {
+        let view_point = camera_transform.inverse_transform_point(point);
+        if view_point.z >= 0.0 {
+            return None;
+        }
+        Point3::from_homogeneous(self.render_matrix() * view_point.to_homogeneous())
+    }
+
+    /// Unprojects a point in normalized device coordinates to world space.
+    ///
+    /// A depth of `1.0` is on the near plane, and `0.0` on the far plane.
+    pub fn ndc_to_world(
+        &self,
+        camera_transform: &Similarity3<f32>,
+        ndc: &Point3<f32>,
+    ) -> Option<Point3<f32>> {
+        let inverse = self.render_matrix().try_inverse()?;
+        let view_point = Point3::from_homogeneous(inverse * ndc.to_homogeneous())?;
+        Some(camera_transform.transform_point(&view_point))
+    }
+
+    /// Converts pixel coordinates on a surface to normalized device
+    /// coordinates.
+    pub fn screen_to_ndc(position: &Point2<f32>, surface_size: SurfaceSize) -> Point2<f32> {
+        Point2::new(
+            2.0 * position.x / (surface_size.width as f32) - 1.0,
+            1.0 - 2.0 * position.y / (surface_size.height as f32),
+        )
+    }
+
+    /// Projects a point in world space to pixel coordinates on a surface.
+    ///
+    /// Returns `None` if the point is behind the camera.
+    pub fn project_to_screen(
+        &self,
+        camera_transform: &Similarity3<f32>,
+        point: &Point3<f32>,
+        surface_size: SurfaceSize,
+    ) -> Option<Point2<f32>> {
+        let normalized = self.project_to_normalized(camera_transform, point)?;
+        Some(Point2::new(
+            normalized.x * (surface_size.width as f32),
+            normalized.y * (surface_size.height as f32),
+        ))
+    }
+
+    /// Projects a point in world space to coordinates relative to the
+    /// surface, with `(0, 0)` in the top-left and `(1, 1)` in the
+    /// bottom-right corner.
+    ///
+    /// Returns `None` if the point is behind the camera.
+    pub fn project_to_normalized(
+        &self,
+        camera_transform: &Similarity3<f32>,
+        point: &Point3<f32>,
+    ) -> Option<Point2<f32>> {
+        let ndc = self.world_to_ndc(camera_transform, point)?;
+        Some(Point2::new((ndc.x + 1.0) * 0.5, (1.0 - ndc.y) * 0.5))
+    }
+
+    /// Unprojects pixel coordinates on a surface to the ray from the near
+    /// plane to the far plane, in world space.
+    pub fn screen_to_ray(
+        &self,
+        camera_transform: &Similarity3<f32>,
+        position: &Point2<f32>,
+        surface_size: SurfaceSize,
+    ) -> Option<(Point3<f32>, Point3<f32>)> {
+        let ndc = Self::screen_to_ndc(position, surface_size);
+        let near = self.ndc_to_world(camera_transform, &Point3::new(ndc.x, ndc.y, 1.0))?;
+        let far = self.ndc_to_world(camera_transform, &Point3::new(ndc.x, ndc.y, 0.0))?;
+        Some((near, far))
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -85,3 +304,51 @@ pub(super) enum RenderTargetInner {
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct DontRender;
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::{
+        Point3,
+        Similarity3,
+    };
+
+    use super::CameraProjection;
+    use crate::graphics::SurfaceSize;
+
+    fn depth(camera: &CameraProjection, distance: f32) -> f32 {
+        let clip = camera.render_matrix() * Point3::new(0.0, 0.0, -distance).to_homogeneous();
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn render_matrix_maps_to_reverse_z() {
+        let camera = CameraProjection::perspective(1.0, PI / 3.0, 0.1, 1000.0);
+        assert!((depth(&camera, 0.1) - 1.0).abs() < 1e-6);
+        assert!(depth(&camera, 1000.0).abs() < 1e-6);
+        assert!(depth(&camera, 1.0) > depth(&camera, 2.0));
+    }
+
+    #[test]
+    fn screen_ray_unprojects_projected_point() {
+        let camera = CameraProjection::perspective(1.5, PI / 3.0, 0.1, 1000.0);
+        let camera_transform = Similarity3::identity();
+        let surface_size = SurfaceSize {
+            width: 1200,
+            height: 800,
+        };
+        let point = Point3::new(1.0, -2.0, -10.0);
+
+        let position = camera
+            .project_to_screen(&camera_transform, &point, surface_size)
+            .unwrap();
+        let (near, far) = camera
+            .screen_to_ray(&camera_transform, &position, surface_size)
+            .unwrap();
+
+        let direction = (far - near).normalize();
+        let closest = near + direction * (point - near).dot(&direction);
+        assert!((closest - point).norm() < 1e-3);
+    }
+}
@@ -0,0 +1,342 @@
+//! What the GPU supports.
+//!
+//! The [`Capabilities`] are detected when a
+//! [`Backend`](super::backend::Backend) is created. Render passes check them
+//! and disable optional [`RenderFeature`]s that aren't supported, instead of
+//! failing. E.g. HDR rendering falls back to an 8 bit format when 16 bit float
+//! textures can't be rendered to, which is the case on some WebGL
+//! implementations.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+};
+
+use kardashev_protocol::ClientCapabilityReport;
+
+use crate::graphics::backend::BackendType;
+
+/// Features the device is requested with, if the adapter supports them.
+pub const OPTIONAL_FEATURES: wgpu::Features =
+    wgpu::Features::TIMESTAMP_QUERY.union(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER);
+
+/// Format of HDR render targets, if it's supported.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Format that is used instead of [`HDR_FORMAT`] if it isn't supported.
+pub const FALLBACK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Formats whose support is included in the report.
+const REPORTED_FORMATS: [wgpu::TextureFormat; 6] = [
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba32Float,
+    wgpu::TextureFormat::Depth24Plus,
+    wgpu::TextureFormat::Depth32Float,
+];
+
+/// Optional rendering features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderFeature {
+    Hdr,
+    ComputeShaders,
+    TimestampQueries,
+    GpuCulling,
+    GpuLightClustering,
+}
+
+impl RenderFeature {
+    pub const ALL: [Self; 5] = [
+        Self::Hdr,
+        Self::ComputeShaders,
+        Self::TimestampQueries,
+        Self::GpuCulling,
+        Self::GpuLightClustering,
+    ];
+
+    /// Message ID of the feature's name.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Hdr => "render-feature-hdr",
+            Self::ComputeShaders => "render-feature-compute-shaders",
+            Self::TimestampQueries => "render-feature-timestamp-queries",
+            Self::GpuCulling => "render-feature-gpu-culling",
+            Self::GpuLightClustering => "render-feature-gpu-light-clustering",
+        }
+    }
+}
+
+impl Display for RenderFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Hdr => "hdr",
+            Self::ComputeShaders => "compute-shaders",
+            Self::TimestampQueries => "timestamp-queries",
+            Self::GpuCulling => "gpu-culling",
+            Self::GpuLightClustering => "gpu-light-clustering",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Why a [`RenderFeature`] is disabled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisabledReason {
+    MissingFeature(wgpu::Features),
+    MissingDownlevelFlag(wgpu::DownlevelFlags),
+    InsufficientLimit {
+        limit: &'static str,
+        required: u32,
+    },
+    UnsupportedFormat {
+        format: wgpu::TextureFormat,
+        usage: &'static str,
+    },
+}
+
+impl Display for DisabledReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingFeature(features) => write!(f, "missing feature {features:?}"),
+            Self::MissingDownlevelFlag(flags) => write!(f, "missing capability {flags:?}"),
+            Self::InsufficientLimit { limit, required } => {
+                write!(f, "{limit} is less than {required}")
+            }
+            Self::UnsupportedFormat { format, usage } => {
+                write!(f, "{format:?} can't be used as {usage}")
+            }
+        }
+    }
+}
+
+/// Support of a texture format.
+#[derive(Clone, Copy, Debug)]
+pub struct FormatSupport {
+    pub format: wgpu::TextureFormat,
+    pub features: wgpu::TextureFormatFeatures,
+}
+
+impl FormatSupport {
+    pub fn renderable(&self) -> bool {
+        self.features
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+    }
+
+    fn check_renderable(&self) -> Result<(), DisabledReason> {
+        if self.renderable() {
+            Ok(())
+        }
+        else {
+            Err(DisabledReason::UnsupportedFormat {
+                format: self.format,
+                usage: "render target",
+            })
+        }
+    }
+
+    fn check_flags(
+        &self,
+        flags: wgpu::TextureFormatFeatureFlags,
+        usage: &'static str,
+    ) -> Result<(), DisabledReason> {
+        if self.features.flags.contains(flags) {
+            Ok(())
+        }
+        else {
+            Err(DisabledReason::UnsupportedFormat {
+                format: self.format,
+                usage,
+            })
+        }
+    }
+}
+
+/// Capabilities of an adapter and the device that was requested from it.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    pub backend_type: BackendType,
+    pub adapter: wgpu::AdapterInfo,
+    pub limits: wgpu::Limits,
+    pub features: wgpu::Features,
+    pub downlevel_flags: wgpu::DownlevelFlags,
+    pub formats: Vec<FormatSupport>,
+    pub disabled: BTreeMap<RenderFeature, DisabledReason>,
+}
+
+impl Capabilities {
+    pub fn detect(
+        backend_type: BackendType,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+    ) -> Self {
+        let formats = REPORTED_FORMATS
+            .iter()
+            .map(|format| {
+                FormatSupport {
+                    format: *format,
+                    features: adapter.get_texture_format_features(*format),
+                }
+            })
+            .collect();
+
+        let mut capabilities = Self {
+            backend_type,
+            adapter: adapter.get_info(),
+            limits: device.limits(),
+            features: device.features(),
+            downlevel_flags: adapter.get_downlevel_capabilities().flags,
+            formats,
+            disabled: BTreeMap::new(),
+        };
+
+        for feature in RenderFeature::ALL {
+            if let Err(reason) = capabilities.check(feature) {
+                tracing::info!(%feature, %reason, "render feature disabled");
+                capabilities.disabled.insert(feature, reason);
+            }
+        }
+
+        capabilities
+    }
+
+    fn check(&self, feature: RenderFeature) -> Result<(), DisabledReason> {
+        match feature {
+            RenderFeature::Hdr => {
+                let hdr = self.format(HDR_FORMAT);
+                hdr.check_renderable()?;
+                hdr.check_flags(
+                    wgpu::TextureFormatFeatureFlags::FILTERABLE
+                        | wgpu::TextureFormatFeatureFlags::BLENDABLE,
+                    "filterable and blendable render target",
+                )
+            }
+            RenderFeature::ComputeShaders => {
+                self.check_downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            }
+            RenderFeature::TimestampQueries => {
+                if self.features.contains(wgpu::Features::TIMESTAMP_QUERY) {
+                    Ok(())
+                }
+                else {
+                    Err(DisabledReason::MissingFeature(
+                        wgpu::Features::TIMESTAMP_QUERY,
+                    ))
+                }
+            }
+            RenderFeature::GpuCulling => {
+                self.check_downlevel_flags(
+                    wgpu::DownlevelFlags::COMPUTE_SHADERS
+                        | wgpu::DownlevelFlags::INDIRECT_EXECUTION,
+                )?;
+                // input, output and the draw arguments.
+                if self.limits.max_storage_buffers_per_shader_stage < 3 {
+                    return Err(DisabledReason::InsufficientLimit {
+                        limit: "max_storage_buffers_per_shader_stage",
+                        required: 3,
+                    });
+                }
+                Ok(())
+            }
+            RenderFeature::GpuLightClustering => {
+                self.check_downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)?;
+                // the clusters, which are then copied into a texture.
+                if self.limits.max_storage_buffers_per_shader_stage < 1 {
+                    return Err(DisabledReason::InsufficientLimit {
+                        limit: "max_storage_buffers_per_shader_stage",
+                        required: 1,
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check_downlevel_flags(&self, flags: wgpu::DownlevelFlags) -> Result<(), DisabledReason> {
+        if self.downlevel_flags.contains(flags) {
+            Ok(())
+        }
+        else {
+            Err(DisabledReason::MissingDownlevelFlag(flags))
+        }
+    }
+
+    fn format(&self, format: wgpu::TextureFormat) -> FormatSupport {
+        self.formats
+            .iter()
+            .find(|support| support.format == format)
+            .copied()
+            .expect("format is not in REPORTED_FORMATS")
+    }
+
+    pub fn is_enabled(&self, feature: RenderFeature) -> bool {
+        !self.disabled.contains_key(&feature)
+    }
+
+    /// Returns the reason why a feature is disabled, or `None` if it's
+    /// enabled.
+    pub fn disabled_reason(&self, feature: RenderFeature) -> Option<&DisabledReason> {
+        self.disabled.get(&feature)
+    }
+
+    /// Format for HDR render targets.
+    ///
+    /// This is [`FALLBACK_FORMAT`] if [`RenderFeature::Hdr`] is disabled.
+    pub fn hdr_format(&self) -> wgpu::TextureFormat {
+        if self.is_enabled(RenderFeature::Hdr) {
+            HDR_FORMAT
+        }
+        else {
+            FALLBACK_FORMAT
+        }
+    }
+
+    /// Creates a report that can be sent to the server.
+    pub fn to_report(
+        &self,
+        client_version: String,
+        user_agent: Option<String>,
+    ) -> ClientCapabilityReport {
+        let limits = serde_json::to_value(&self.limits)
+            .and_then(serde_json::from_value)
+            .unwrap_or_default();
+
+        let texture_formats = self
+            .formats
+            .iter()
+            .map(|support| {
+                let mut supported = vec![];
+                if support.renderable() {
+                    supported.push("render-attachment".to_owned());
+                }
+                for (name, _) in support.features.flags.iter_names() {
+                    supported.push(name.to_lowercase().replace('_', "-"));
+                }
+                (format!("{:?}", support.format), supported)
+            })
+            .collect();
+
+        ClientCapabilityReport {
+            client_version,
+            user_agent,
+            backend: format!("{:?}", self.backend_type),
+            adapter: self.adapter.name.clone(),
+            driver: format!("{} {}", self.adapter.driver, self.adapter.driver_info)
+                .trim()
+                .to_owned(),
+            features: self
+                .features
+                .iter_names()
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+            limits,
+            texture_formats,
+            disabled: self
+                .disabled
+                .iter()
+                .map(|(feature, reason)| (feature.to_string(), reason.to_string()))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! Frustum culling of instances.
+//!
+//! [`GpuCulling`] culls instances in a compute shader, and writes the visible
+//! ones to a vertex buffer, together with the arguments for
+//! [`draw_indexed_indirect`](wgpu::RenderPass::draw_indexed_indirect). The CPU
+//! then doesn't need to know how many instances are visible, so large instanced
+//! scenes like the star billboards only cost an upload per frame.
+//!
+//! This requires [`RenderFeature::GpuCulling`]. Without it, e.g. on WebGL,
+//! pipelines cull on the CPU with a [`Frustum`] instead.
+//!
+//! Instances must start with their model matrix (`[f32; 16]`, column-major).
+//! Their bounding sphere is centered at the matrix' translation, and its radius
+//! is the pipeline's bounding radius scaled by the largest scaling of the
+//! matrix.
+//!
+//! Entities with a [`Mesh`](crate::graphics::mesh::Mesh) are culled on the CPU
+//! when they're batched, by the world-space [`Aabb`] of their mesh. Entities
+//! that must always be drawn, like skyboxes, opt out with [`NoFrustumCulling`].
+//!
+//! [`RenderFeature::GpuCulling`]: crate::graphics::capabilities::RenderFeature::GpuCulling
+
+use std::marker::PhantomData;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use nalgebra::{
+    Matrix4,
+    Point3,
+    Vector3,
+    Vector4,
+};
+
+use crate::graphics::backend::Backend;
+
+/// Instances per workgroup, see `culling.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Planes of a camera's view frustum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top plane, and the two depth planes. Points inside
+    /// the frustum have a positive distance to every plane.
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix, with depth in
+    /// `0.0..=1.0`.
+    pub fn from_view_projection(view_projection: &Matrix4<f32>) -> Self {
+        let row = |i| view_projection.row(i).transpose();
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| plane / plane.xyz().norm());
+        Self { planes }
+    }
+
+    pub fn contains_sphere(&self, center: &Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.xyz().dot(&center.coords) + plane.w >= -radius)
+    }
+
+    /// Returns whether an instance with this model matrix is at least partly
+    /// inside the frustum, see the [module documentation](self).
+    pub fn contains_instance(&self, model_matrix: &Matrix4<f32>, bounding_radius: f32) -> bool {
+        let center = Point3::from(model_matrix.fixed_view::<3, 1>(0, 3).into_owned());
+        let scaling = (0..3)
+            .map(|i| model_matrix.fixed_view::<3, 1>(0, i).norm())
+            .fold(0.0, f32::max);
+        self.contains_sphere(&center, bounding_radius * scaling)
+    }
+
+    pub fn contains_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner that is the farthest along the plane's normal.
+            let corner = Vector3::from_fn(|i, _| {
+                if plane[i] >= 0.0 {
+                    aabb.max[i]
+                }
+                else {
+                    aabb.min[i]
+                }
+            });
+            plane.xyz().dot(&corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    /// The smallest box containing all `points`, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb, point| {
+            Some(aabb.map_or(
+                Self {
+                    min: point,
+                    max: point,
+                },
+                |aabb: Self| {
+                    Self {
+                        min: aabb.min.inf(&point),
+                        max: aabb.max.sup(&point),
+                    }
+                },
+            ))
+        })
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    /// The box around this box transformed by `model_matrix`, e.g. to get the
+    /// world-space box of a mesh.
+    pub fn transform(&self, model_matrix: &Matrix4<f32>) -> Self {
+        let center = model_matrix.transform_point(&self.center());
+        let half_extents = model_matrix.fixed_view::<3, 3>(0, 0).abs() * self.half_extents();
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+}
+
+/// Marks an entity that is drawn even if it's outside of the view frustum.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoFrustumCulling;
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct CullingParams {
+    planes: [[f32; 4]; 6],
+    instance_count: u32,
+
+    /// Size of an instance in 4 byte words.
+    instance_stride: u32,
+
+    bounding_radius: f32,
+    _padding: u32,
+}
+
+/// Arguments of an indexed indirect draw call, as they're laid out in the
+/// indirect buffer.
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct DrawIndexedIndirectArgs {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+/// Culls instances of type `I` in a compute shader, see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct GpuCulling<I> {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    draw_args_buffer: wgpu::Buffer,
+    buffers: Option<CullingBuffers>,
+    index_count: u32,
+    bounding_radius: f32,
+    _instance: PhantomData<I>,
+}
+
+/// Input and output buffers, which are recreated when they're too small.
+#[derive(Debug)]
+struct CullingBuffers {
+    capacity: usize,
+    input: wgpu::Buffer,
+    output: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl<I: Pod> GpuCulling<I> {
+    /// Creates the culling pass for a mesh with `index_count` indices, whose
+    /// vertices are within `bounding_radius` of its origin.
+    pub fn new(backend: &Backend, index_count: u32, bounding_radius: f32) -> Self {
+        assert!(
+            std::mem::size_of::<I>() >= std::mem::size_of::<[f32; 16]>()
+                && std::mem::size_of::<I>() % 4 == 0,
+            "instances must start with a model matrix and be a multiple of 4 bytes"
+        );
+
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("./culling.wgsl"));
+
+        let storage = |binding, read_only| {
+            wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }
+        };
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("GpuCulling bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        storage(1, true),
+                        storage(2, false),
+                        storage(3, false),
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("GpuCulling pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("GpuCulling pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "cull",
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        let params_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GpuCulling params"),
+            size: std::mem::size_of::<CullingParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let draw_args_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GpuCulling draw args"),
+            size: std::mem::size_of::<DrawIndexedIndirectArgs>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            draw_args_buffer,
+            buffers: None,
+            index_count,
+            bounding_radius,
+            _instance: PhantomData,
+        }
+    }
+
+    /// Uploads the instances and records the culling pass.
+    ///
+    /// This must be called outside of a render pass, e.g. in
+    /// [`Render3dPipeline::prepare`](crate::graphics::render_3d::Render3dPipeline::prepare).
+    pub fn cull(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        frustum: &Frustum,
+        instances: &[I],
+    ) {
+        // the instance count is reset before the command buffer runs, and incremented
+        // by the shader for every visible instance.
+        backend.queue.write_buffer(
+            &self.draw_args_buffer,
+            0,
+            bytemuck::bytes_of(&DrawIndexedIndirectArgs {
+                index_count: self.index_count,
+                instance_count: 0,
+                first_index: 0,
+                base_vertex: 0,
+                first_instance: 0,
+            }),
+        );
+
+        if instances.is_empty() {
+            return;
+        }
+
+        self.reserve(backend, instances.len());
+        let buffers = self.buffers.as_ref().unwrap();
+        backend
+            .queue
+            .write_buffer(&buffers.input, 0, bytemuck::cast_slice(instances));
+
+        let params = CullingParams {
+            planes: frustum.planes.map(Into::into),
+            instance_count: instances.len() as u32,
+            instance_stride: (std::mem::size_of::<I>() / 4) as u32,
+            bounding_radius: self.bounding_radius,
+            _padding: 0,
+        };
+        backend
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("GpuCulling compute pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &buffers.bind_group, &[]);
+        compute_pass.dispatch_workgroups((instances.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Draws the visible instances. The caller sets the pipeline, bind groups
+    /// and index buffer.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, instance_buffer_slot: u32) {
+        if let Some(buffers) = &self.buffers {
+            render_pass.set_vertex_buffer(instance_buffer_slot, buffers.output.slice(..));
+            render_pass.draw_indexed_indirect(&self.draw_args_buffer, 0);
+        }
+    }
+
+    /// Recreates the input and output buffers if they can't hold `len`
+    /// instances.
+    fn reserve(&mut self, backend: &Backend, len: usize) {
+        if self
+            .buffers
+            .as_ref()
+            .map_or(true, |buffers| buffers.capacity < len)
+        {
+            let capacity = len.next_power_of_two();
+            tracing::debug!(capacity, "resizing culling buffers");
+            let size = (capacity * std::mem::size_of::<I>()) as wgpu::BufferAddress;
+
+            let input = backend.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("GpuCulling input"),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let output = backend.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("GpuCulling output"),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            });
+            let bind_group = backend
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("GpuCulling bind group"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.params_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: input.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: output.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: self.draw_args_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+            self.buffers = Some(CullingBuffers {
+                capacity,
+                input,
+                output,
+                bind_group,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::{
+        Matrix4,
+        Point3,
+        Vector3,
+    };
+
+    use super::{
+        Aabb,
+        Frustum,
+    };
+    use crate::graphics::camera::CameraProjection;
+
+    fn frustum() -> Frustum {
+        // camera at the origin looking down -z.
+        let projection = CameraProjection::perspective(1.0, PI / 2.0, 0.1, 100.0);
+        Frustum::from_view_projection(&projection.render_matrix())
+    }
+
+    #[test]
+    fn it_culls_spheres_outside_the_frustum() {
+        let frustum = frustum();
+        assert!(frustum.contains_sphere(&Point3::new(0.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.contains_sphere(&Point3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.contains_sphere(&Point3::new(50.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.contains_sphere(&Point3::new(0.0, 0.0, -200.0), 1.0));
+    }
+
+    #[test]
+    fn it_keeps_spheres_that_intersect_the_frustum() {
+        let frustum = frustum();
+        // the right plane is at x = 10 for z = -10.
+        assert!(frustum.contains_sphere(&Point3::new(10.5, 0.0, -10.0), 1.0));
+        let model_matrix =
+            Matrix4::new_translation(&Vector3::new(12.0, 0.0, -10.0)) * Matrix4::new_scaling(3.0);
+        assert!(frustum.contains_instance(&model_matrix, 1.0));
+        assert!(!frustum.contains_instance(&model_matrix, 0.1));
+    }
+
+    #[test]
+    fn it_culls_boxes_outside_the_frustum() {
+        let frustum = frustum();
+        let unit =
+            Aabb::from_points([Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)]).unwrap();
+
+        let at = |x: f32, z: f32, scaling: f32| {
+            unit.transform(
+                &(Matrix4::new_translation(&Vector3::new(x, 0.0, z))
+                    * Matrix4::new_scaling(scaling)),
+            )
+        };
+        assert!(frustum.contains_aabb(&at(0.0, -10.0, 1.0)));
+        assert!(!frustum.contains_aabb(&at(0.0, 10.0, 1.0)));
+        assert!(!frustum.contains_aabb(&at(14.0, -10.0, 1.0)));
+        assert!(frustum.contains_aabb(&at(14.0, -10.0, 3.0)));
+
+        let rotated = unit.transform(&Matrix4::from_euler_angles(0.0, PI / 4.0, 0.0));
+        assert!((rotated.max.x - 2.0f32.sqrt()).abs() < 1e-5);
+        assert!((rotated.max.y - 1.0).abs() < 1e-5);
+    }
+}
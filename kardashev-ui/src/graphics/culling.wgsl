@@ -0,0 +1,71 @@
+// Frustum culling of instances, see `culling.rs`.
+//
+// Instances are copied as words, so this works for any instance type that
+// starts with a model matrix.
+
+struct CullingParams {
+    planes: array<vec4<f32>, 6>,
+    instance_count: u32,
+    instance_stride: u32,
+    bounding_radius: f32,
+};
+
+struct DrawIndexedIndirectArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> params: CullingParams;
+
+@group(0)
+@binding(1)
+var<storage, read> input: array<u32>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> output: array<u32>;
+
+@group(0)
+@binding(3)
+var<storage, read_write> draw_args: DrawIndexedIndirectArgs;
+
+fn column(offset: u32, i: u32) -> vec3<f32> {
+    let base = offset + i * 4u;
+    return vec3<f32>(
+        bitcast<f32>(input[base]),
+        bitcast<f32>(input[base + 1u]),
+        bitcast<f32>(input[base + 2u]),
+    );
+}
+
+@compute
+@workgroup_size(64)
+fn cull(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.instance_count {
+        return;
+    }
+
+    let offset = index * params.instance_stride;
+    let center = column(offset, 3u);
+    let scaling = max(length(column(offset, 0u)), max(length(column(offset, 1u)), length(column(offset, 2u))));
+    let radius = params.bounding_radius * scaling;
+
+    for (var i = 0u; i < 6u; i++) {
+        let plane = params.planes[i];
+        if dot(plane.xyz, center) + plane.w < -radius {
+            return;
+        }
+    }
+
+    let slot = atomicAdd(&draw_args.instance_count, 1u);
+    let output_offset = slot * params.instance_stride;
+    for (var i = 0u; i < params.instance_stride; i++) {
+        output[output_offset + i] = input[offset + i];
+    }
+}
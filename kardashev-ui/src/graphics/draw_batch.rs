@@ -49,6 +49,8 @@ impl<K: Eq + Hash, V, I> DrawBatcher<K, V, I> {
 
 impl<K, V, I: Pod> DrawBatcher<K, V, I> {
     pub fn prepare(&mut self, backend: &Backend) -> Option<PreparedBatch<V>> {
+        self.items.clear();
+
         // create instance list
         for (_, mut entry) in self.entries.drain() {
             let start_index = self.instance_buffer.len() as u32;
@@ -71,15 +73,100 @@ impl<K, V, I: Pod> DrawBatcher<K, V, I> {
 
         if self.items.len() > 0 {
             self.instance_buffer.upload_and_clear(backend);
+        }
+        self.prepared()
+    }
+}
 
-            Some(PreparedBatch {
+impl<K, V, I> DrawBatcher<K, V, I> {
+    /// Returns the batch that was prepared last, e.g. to draw it again in
+    /// another pass.
+    pub fn prepared(&self) -> Option<PreparedBatch<V>> {
+        (self.items.len() > 0).then(|| {
+            PreparedBatch {
                 instance_buffer: self.instance_buffer.slice(..),
-                batch_items: self.items.drain(..),
-            })
+                batch_items: self.items.iter(),
+            }
+        })
+    }
+}
+
+/// Queue for transparent objects, which have to be drawn back-to-front.
+///
+/// Unlike the [`DrawBatcher`], this doesn't batch instances, since objects
+/// in between them might have to be drawn first. Instead each object is
+/// drawn on its own, sorted by their distance to the camera.
+#[derive(Debug)]
+pub struct TransparentQueue<V, I> {
+    instance_buffer: InstanceBuffer<I>,
+    entries: Vec<TransparentEntry<V, I>>,
+    items: Vec<BatchItem<V>>,
+}
+
+#[derive(Debug)]
+struct TransparentEntry<V, I> {
+    distance: f32,
+    value: V,
+    instance: I,
+}
+
+impl<V, I> TransparentQueue<V, I> {
+    const INITIAL_BUFFER_SIZE: usize = 128;
+
+    pub fn new(backend: &Backend) -> Self {
+        Self {
+            instance_buffer: InstanceBuffer::new(backend, Self::INITIAL_BUFFER_SIZE),
+            entries: Vec::with_capacity(Self::INITIAL_BUFFER_SIZE),
+            items: vec![],
         }
-        else {
-            None
+    }
+
+    /// Queues an object. `distance` is its distance to the camera, or
+    /// anything that is ordered the same way, e.g. the squared distance.
+    pub fn push(&mut self, distance: f32, value: V, instance: I) {
+        self.entries.push(TransparentEntry {
+            distance,
+            value,
+            instance,
+        });
+    }
+}
+
+impl<V, I: Pod> TransparentQueue<V, I> {
+    /// Sorts the queued objects back-to-front and uploads their instances.
+    ///
+    /// Each item of the returned batch is a single object.
+    pub fn prepare(&mut self, backend: &Backend) -> Option<PreparedBatch<V>> {
+        self.items.clear();
+        self.entries
+            .sort_by(|a, b| b.distance.total_cmp(&a.distance));
+
+        for (index, entry) in self.entries.drain(..).enumerate() {
+            let index = index as u32;
+            self.instance_buffer.push(entry.instance);
+            self.items.push(BatchItem {
+                range: index..index + 1,
+                value: entry.value,
+            });
+        }
+
+        if self.items.len() > 0 {
+            self.instance_buffer.upload_and_clear(backend);
         }
+        self.prepared()
+    }
+}
+
+impl<V, I> TransparentQueue<V, I> {
+    /// Returns the batch that was prepared last, e.g. to draw it again in
+    /// another pass.
+    pub fn prepared(&self) -> Option<PreparedBatch<V>> {
+        (self.items.len() > 0).then(|| {
+            PreparedBatch {
+                instance_buffer: self.instance_buffer.slice(..),
+                batch_items: self.items.iter(),
+            }
+        })
     }
 }
 
@@ -97,11 +184,11 @@ pub struct BatchItem<V> {
 #[derive(Debug)]
 pub struct PreparedBatch<'a, V> {
     pub instance_buffer: wgpu::BufferSlice<'a>,
-    batch_items: std::vec::Drain<'a, BatchItem<V>>,
+    batch_items: std::slice::Iter<'a, BatchItem<V>>,
 }
 
 impl<'a, V> Iterator for PreparedBatch<'a, V> {
-    type Item = BatchItem<V>;
+    type Item = &'a BatchItem<V>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.batch_items.next()
@@ -0,0 +1,46 @@
+#import camera.wgsl::Camera;
+#import render_3d.wgsl::{VertexInput, vs_main_inner};
+
+// Drawn instead of a material whose shader is broken. It only uses the camera
+// bind group and the model transform at the start of the instance, so it works
+// with any material's bind group and instance layout.
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+struct InstanceInput {
+    @location(5) model_transform_a: vec4<f32>,
+    @location(6) model_transform_b: vec4<f32>,
+    @location(7) model_transform_c: vec4<f32>,
+    @location(8) model_transform_d: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_normal: vec3<f32>,
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_transform = mat4x4<f32>(
+        instance.model_transform_a,
+        instance.model_transform_b,
+        instance.model_transform_c,
+        instance.model_transform_d,
+    );
+    let inner = vs_main_inner(model, model_transform, camera);
+    var out: VertexOutput;
+    out.clip_position = inner.clip_position;
+    out.world_normal = inner.world_normal;
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // shade a little, so that the shape is still visible.
+    let shade = 0.6 + 0.4 * abs(input.world_normal.y);
+    return vec4<f32>(shade, 0.0, shade, 1.0);
+}
@@ -0,0 +1,351 @@
+//! Gizmos are simple shapes drawn on top of the scene, e.g. to visualize
+//! measurements.
+//!
+//! Gizmos are positioned in world space, and ignore the transform of the
+//! entity they're attached to. Gizmos on entities with [`Hidden`] are not
+//! drawn, nor are gizmos on [layers](RenderLayers) the camera doesn't see.
+//! Gizmos without [`RenderLayers`] are on [`RenderLayers::GIZMOS`].
+//! Gizmos with a lower [`GizmoOrder`] are drawn first, i.e. below the others.
+
+use std::ops::Range;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use nalgebra::Point3;
+use palette::Srgba;
+
+use crate::graphics::{
+    render_3d::{
+        CreateRender3dPipeline,
+        CreateRender3dPipelineContext,
+        Render3dPipeline,
+        Render3dPipelineContext,
+    },
+    render_layers::RenderLayers,
+    utils::{
+        HasVertexBufferLayout,
+        InstanceBuffer,
+    },
+};
+
+/// A line between two points.
+#[derive(Clone, Copy, Debug)]
+pub struct Line {
+    pub from: Point3<f32>,
+    pub to: Point3<f32>,
+    pub color: Srgba<f32>,
+}
+
+impl Line {
+    pub fn new(from: Point3<f32>, to: Point3<f32>, color: Srgba<f32>) -> Self {
+        Self { from, to, color }
+    }
+}
+
+/// A filled, convex polygon with an outline.
+#[derive(Clone, Debug)]
+pub struct Polygon {
+    pub vertices: Vec<Point3<f32>>,
+    pub fill: Srgba<f32>,
+    pub outline: Srgba<f32>,
+}
+
+/// Hides the gizmos of an entity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Hidden;
+
+/// Order in which gizmos are drawn. Entities without it are drawn with order
+/// 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GizmoOrder(pub i32);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CreateGizmoPipeline;
+
+impl CreateRender3dPipeline for CreateGizmoPipeline {
+    type Pipeline = GizmoPipeline;
+
+    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
+        let shader = context
+            .backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("gizmo.wgsl"));
+
+        let pipeline_layout =
+            context
+                .backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("GizmoPipeline pipeline layout"),
+                    bind_group_layouts: &[&context.camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let create_pipeline = |label: &str,
+                               entry_point: &str,
+                               layout: wgpu::VertexBufferLayout<'static>,
+                               topology: wgpu::PrimitiveTopology| {
+            context
+                .backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point,
+                        buffers: &[layout],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &context.color_targets(wgpu::BlendState::ALPHA_BLENDING, false),
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    // gizmos are drawn on top of everything, so they don't write depth.
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: context.depth_texture_format,
+                        depth_write_enabled: false,
+                        depth_compare: wgpu::CompareFunction::Always,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+        };
+
+        let line_pipeline = create_pipeline(
+            "GizmoPipeline line pipeline",
+            "vs_line",
+            LineInstance::layout(),
+            wgpu::PrimitiveTopology::LineList,
+        );
+        let fill_pipeline = create_pipeline(
+            "GizmoPipeline fill pipeline",
+            "vs_fill",
+            FillVertex::layout(),
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        GizmoPipeline {
+            line_pipeline,
+            fill_pipeline,
+            line_buffer: InstanceBuffer::new(context.backend, 16),
+            fill_buffer: InstanceBuffer::new(context.backend, 64),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GizmoPipeline {
+    line_pipeline: wgpu::RenderPipeline,
+    fill_pipeline: wgpu::RenderPipeline,
+    line_buffer: InstanceBuffer<LineInstance>,
+
+    /// Vertices of the triangles that fill polygons. These are not instanced.
+    fill_buffer: InstanceBuffer<FillVertex>,
+}
+
+impl Render3dPipeline for GizmoPipeline {
+    fn render(&mut self, context: &mut Render3dPipelineContext) {
+        let mut query = context
+            .world
+            .query::<(
+                Option<&Line>,
+                Option<&Polygon>,
+                Option<&GizmoOrder>,
+                Option<&RenderLayers>,
+            )>()
+            .without::<&Hidden>();
+
+        let mut gizmos = query
+            .iter()
+            .filter(|(_entity, (line, polygon, _order, layers))| {
+                (line.is_some() || polygon.is_some())
+                    && context.camera_layers.sees_or(*layers, RenderLayers::GIZMOS)
+            })
+            .map(|(_entity, (line, polygon, order, _layers))| {
+                (order.copied().unwrap_or_default(), line, polygon)
+            })
+            .collect::<Vec<_>>();
+        gizmos.sort_by_key(|(order, _line, _polygon)| *order);
+
+        let mut batches: Vec<Batch> = vec![];
+        let mut current_order = None;
+
+        for (order, line, polygon) in gizmos {
+            if current_order != Some(order) {
+                current_order = Some(order);
+                let fills = self.fill_buffer.len().try_into().unwrap();
+                let lines = self.line_buffer.len().try_into().unwrap();
+                batches.push(Batch {
+                    fills: fills..fills,
+                    lines: lines..lines,
+                });
+            }
+
+            if let Some(line) = line {
+                self.line_buffer
+                    .push(LineInstance::new(line.from, line.to, line.color));
+            }
+
+            if let Some(polygon) = polygon {
+                let vertices = &polygon.vertices;
+                let edges = vertices.iter().zip(vertices.iter().cycle().skip(1));
+                for (from, to) in edges {
+                    self.line_buffer
+                        .push(LineInstance::new(*from, *to, polygon.outline));
+                }
+
+                // triangle fan around the first vertex.
+                if let Some((first, rest)) = vertices.split_first() {
+                    for pair in rest.windows(2) {
+                        for vertex in [*first, pair[0], pair[1]] {
+                            self.fill_buffer.push(FillVertex {
+                                position: vertex.into(),
+                                color: color_array(polygon.fill),
+                            });
+                        }
+                    }
+                }
+            }
+
+            let batch = batches.last_mut().expect("batch was pushed above");
+            batch.fills.end = self.fill_buffer.len().try_into().unwrap();
+            batch.lines.end = self.line_buffer.len().try_into().unwrap();
+        }
+
+        if batches.is_empty() {
+            return;
+        }
+
+        self.fill_buffer.upload_and_clear(&context.backend);
+        self.line_buffer.upload_and_clear(&context.backend);
+
+        // each batch fills its polygons first, so that their outlines are drawn on top.
+        for batch in batches {
+            if !batch.fills.is_empty() {
+                context.render_pass.set_pipeline(&self.fill_pipeline);
+                context
+                    .render_pass
+                    .set_bind_group(0, &context.camera_bind_group, &[]);
+                context
+                    .render_pass
+                    .set_vertex_buffer(0, self.fill_buffer.slice(..));
+                context.render_pass.draw(batch.fills, 0..1);
+            }
+
+            if !batch.lines.is_empty() {
+                context.render_pass.set_pipeline(&self.line_pipeline);
+                context
+                    .render_pass
+                    .set_bind_group(0, &context.camera_bind_group, &[]);
+                context
+                    .render_pass
+                    .set_vertex_buffer(0, self.line_buffer.slice(..));
+                context.render_pass.draw(0..2, batch.lines);
+            }
+        }
+    }
+}
+
+/// Fill vertices and line instances of gizmos with the same [`GizmoOrder`].
+#[derive(Debug)]
+struct Batch {
+    fills: Range<u32>,
+    lines: Range<u32>,
+}
+
+fn color_array(color: Srgba<f32>) -> [f32; 4] {
+    [color.red, color.green, color.blue, color.alpha]
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct LineInstance {
+    start: [f32; 3],
+    end: [f32; 3],
+    color: [f32; 4],
+}
+
+impl LineInstance {
+    fn new(start: Point3<f32>, end: Point3<f32>, color: Srgba<f32>) -> Self {
+        Self {
+            start: start.into(),
+            end: end.into(),
+            color: color_array(color),
+        }
+    }
+}
+
+impl HasVertexBufferLayout for LineInstance {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct FillVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+impl HasVertexBufferLayout for FillVertex {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
@@ -0,0 +1,55 @@
+struct CameraUniform {
+    view_projection: mat4x4f,
+    view_position: vec3f,
+    time: f32,
+    aspect: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct LineInput {
+    @location(0) start: vec3f,
+    @location(1) end: vec3f,
+    @location(2) color: vec4f,
+}
+
+struct FillInput {
+    @location(0) position: vec3f,
+    @location(1) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_line(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: LineInput,
+) -> VertexOutput {
+    // every line is drawn from 2 vertices: the 1st at its start, the 2nd at its end.
+    var position = instance.start;
+    if vertex_index == 1u {
+        position = instance.end;
+    }
+
+    var output: VertexOutput;
+    output.clip_position = camera.view_projection * vec4f(position, 1.0);
+    output.color = instance.color;
+    return output;
+}
+
+@vertex
+fn vs_fill(vertex: FillInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = camera.view_projection * vec4f(vertex.position, 1.0);
+    output.color = vertex.color;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4f {
+    return input.color;
+}
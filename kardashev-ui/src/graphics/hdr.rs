@@ -2,6 +2,8 @@ use wgpu::SamplerBindingType;
 
 use crate::graphics::{
     backend::Backend,
+    bloom::Bloom,
+    capabilities::HDR_FORMAT,
     render_frame::{
         CreateRenderPass,
         CreateRenderPassContext,
@@ -15,30 +17,50 @@ use crate::graphics::{
 pub struct CreateToneMapPass<P: CreateRenderPass> {
     pub inner: P,
     pub format: wgpu::TextureFormat,
+
+    /// Whether the inner pass writes emissive light, which is bloomed and
+    /// added to the image before it's tone mapped. See [`Bloom`].
+    pub bloom: bool,
 }
 
 impl<P: CreateRenderPass> CreateRenderPass for CreateToneMapPass<P> {
     type RenderPass = ToneMapPass<P::RenderPass>;
 
     fn create_render_pass(self, context: &CreateRenderPassContext) -> Self::RenderPass {
+        let format = if self.format == HDR_FORMAT {
+            context.backend.capabilities.hdr_format()
+        }
+        else {
+            self.format
+        };
+        if format != self.format {
+            tracing::info!(requested = ?self.format, ?format, "HDR is disabled, using fallback format");
+        }
+
         let inner = self.inner.create_render_pass(&CreateRenderPassContext {
             backend: context.backend,
             surface_size: context.surface_size,
-            surface_format: self.format,
+            surface_format: format,
+            emissive_format: self.bloom.then_some(format),
         });
 
         let tone_mapping = ToneMapPipeline::new(context.backend, context.surface_format);
-        let staging = StagingTexture::new(
+        let staging = StagingTexture::new(context.backend, context.surface_size, format);
+        let bloom = self
+            .bloom
+            .then(|| Bloom::new(context.backend, context.surface_size, format));
+        let bind_group = tone_mapping.create_bind_group(
             context.backend,
-            context.surface_size,
-            self.format,
-            &tone_mapping.bind_group_layout,
+            &staging.view,
+            bloom.as_ref().map(Bloom::output_view),
         );
 
         ToneMapPass {
             inner,
             staging,
+            bloom,
             tone_mapping,
+            bind_group,
         }
     }
 }
@@ -47,27 +69,42 @@ impl<P: CreateRenderPass> CreateRenderPass for CreateToneMapPass<P> {
 pub struct ToneMapPass<P> {
     inner: P,
     staging: StagingTexture,
+    bloom: Option<Bloom>,
     tone_mapping: ToneMapPipeline,
+    bind_group: wgpu::BindGroup,
 }
 
 impl<P: RenderPass> RenderPass for ToneMapPass<P> {
     fn render(&mut self, context: &mut RenderPassContext) {
-        self.staging.resize_if_needed(
-            context.backend,
-            context.target_size,
-            &self.tone_mapping.bind_group_layout,
-        );
+        let mut resized = self
+            .staging
+            .resize_if_needed(context.backend, context.target_size);
+        if let Some(bloom) = &mut self.bloom {
+            resized |= bloom.resize_if_needed(context.backend, context.target_size);
+        }
+        if resized {
+            self.bind_group = self.tone_mapping.create_bind_group(
+                context.backend,
+                &self.staging.view,
+                self.bloom.as_ref().map(Bloom::output_view),
+            );
+        }
 
         self.inner.render(&mut RenderPassContext {
             backend: context.backend,
             encoder: context.encoder,
             target_view: &self.staging.view,
+            emissive_view: self.bloom.as_ref().map(Bloom::emissive_view),
             target_size: context.target_size,
             render_target_entity: context.render_target_entity,
             world: context.world,
             resources: context.resources,
         });
 
+        if let Some(bloom) = &self.bloom {
+            bloom.render(context.encoder);
+        }
+
         let mut render_pass = context
             .encoder
             .begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -86,7 +123,7 @@ impl<P: RenderPass> RenderPass for ToneMapPass<P> {
             });
 
         render_pass.set_pipeline(&self.tone_mapping.pipeline);
-        render_pass.set_bind_group(0, &self.staging.bind_group, &[]);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
         render_pass.draw(0..3, 0..1);
     }
 }
@@ -95,56 +132,28 @@ impl<P: RenderPass> RenderPass for ToneMapPass<P> {
 struct StagingTexture {
     texture: wgpu::Texture,
     view: wgpu::TextureView,
-    sampler: wgpu::Sampler,
     format: wgpu::TextureFormat,
-    bind_group: wgpu::BindGroup,
 }
 
 impl StagingTexture {
-    fn new(
-        backend: &Backend,
-        size: SurfaceSize,
-        format: wgpu::TextureFormat,
-        bind_group_layout: &wgpu::BindGroupLayout,
-    ) -> Self {
+    fn new(backend: &Backend, size: SurfaceSize, format: wgpu::TextureFormat) -> Self {
         let (texture, view) = create_staging_texture(backend, size, format);
-        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("hdr staging sampler"),
-            ..Default::default()
-        });
-        let bind_group = create_staging_bind_group(backend, &view, &sampler, bind_group_layout);
-
         Self {
             texture,
             view,
-            sampler,
             format,
-            bind_group,
         }
     }
 
-    fn resize(
-        &mut self,
-        backend: &Backend,
-        size: SurfaceSize,
-        bind_group_layout: &wgpu::BindGroupLayout,
-    ) {
-        let (texture, view) = create_staging_texture(backend, size, self.format);
-        self.texture = texture;
-        self.view = view;
-        self.bind_group =
-            create_staging_bind_group(backend, &self.view, &self.sampler, bind_group_layout);
-    }
-
-    fn resize_if_needed(
-        &mut self,
-        backend: &Backend,
-        size: SurfaceSize,
-        bind_group_layout: &wgpu::BindGroupLayout,
-    ) {
+    /// Returns whether the texture was resized.
+    fn resize_if_needed(&mut self, backend: &Backend, size: SurfaceSize) -> bool {
         if SurfaceSize::from_texture(&self.texture) != size {
             tracing::debug!(?size, "resizing staging texture");
-            self.resize(backend, size, bind_group_layout);
+            (self.texture, self.view) = create_staging_texture(backend, size, self.format);
+            true
+        }
+        else {
+            false
         }
     }
 }
@@ -174,34 +183,14 @@ fn create_staging_texture(
     (texture, view)
 }
 
-fn create_staging_bind_group(
-    backend: &Backend,
-    view: &wgpu::TextureView,
-    sampler: &wgpu::Sampler,
-    bind_group_layout: &wgpu::BindGroupLayout,
-) -> wgpu::BindGroup {
-    backend
-        .device
-        .create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("hdr staging bind group"),
-            layout: bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        })
-}
-
 #[derive(Debug)]
 struct ToneMapPipeline {
     bind_group_layout: wgpu::BindGroupLayout,
     pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+
+    /// Black texture that is bound instead of the bloom, if it's disabled.
+    no_bloom_view: wgpu::TextureView,
 }
 
 impl ToneMapPipeline {
@@ -232,6 +221,16 @@ impl ToneMapPipeline {
                             ty: wgpu::BindingType::Sampler(SamplerBindingType::Filtering),
                             count: None,
                         },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -284,9 +283,71 @@ impl ToneMapPipeline {
                 cache: None,
             });
 
+        // the bloom has half the size of the image, so it's sampled linearly.
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("hdr staging sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // textures are initialized with zeros
+        let no_bloom_view = backend
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("hdr no bloom texture"),
+                size: wgpu::Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+
         Self {
             bind_group_layout,
             pipeline,
+            sampler,
+            no_bloom_view,
         }
     }
+
+    fn create_bind_group(
+        &self,
+        backend: &Backend,
+        view: &wgpu::TextureView,
+        bloom_view: Option<&wgpu::TextureView>,
+    ) -> wgpu::BindGroup {
+        backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("hdr staging bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            bloom_view.unwrap_or(&self.no_bloom_view),
+                        ),
+                    },
+                ],
+            })
+    }
 }
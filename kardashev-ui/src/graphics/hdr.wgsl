@@ -47,9 +47,18 @@ var hdr_image: texture_2d<f32>;
 @binding(1)
 var hdr_sampler: sampler;
 
+// blurred emissive light, see `bloom.rs`. this is black if bloom is disabled.
+@group(0)
+@binding(2)
+var bloom_image: texture_2d<f32>;
+
+// the bloom is the sum of all mip levels of the blur.
+const BLOOM_INTENSITY: f32 = 0.2;
+
 @fragment
 fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
     let hdr = textureSample(hdr_image, hdr_sampler, vs.uv);
-    let sdr = aces_tone_map(hdr.rgb);
+    let bloom = textureSample(bloom_image, hdr_sampler, vs.uv).rgb;
+    let sdr = aces_tone_map(hdr.rgb + bloom * BLOOM_INTENSITY);
     return vec4(sdr, hdr.a);
 }
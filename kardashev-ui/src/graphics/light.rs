@@ -1,5 +1,76 @@
+//! Lights, and the clustered light culling for point lights.
+//!
+//! The view frustum is divided into a grid of clusters: [`CLUSTERS_X`] by
+//! [`CLUSTERS_Y`] tiles on the screen, and [`CLUSTERS_Z`] depth slices, which
+//! are spaced exponentially, so that clusters near the camera are small. Every
+//! frame, each point light is assigned to the clusters that its range
+//! overlaps. Shaders then find the cluster of a fragment and only shade it
+//! with the lights of that cluster (see `light.wgsl`), instead of with all
+//! lights in the scene.
+//!
+//! The lights and the clusters' light lists are stored in textures, since
+//! WebGL doesn't support storage buffers. Lights are assigned to clusters in
+//! a compute shader, which writes them to a storage buffer that is then
+//! copied into the cluster texture. This requires
+//! [`RenderFeature::GpuLightClustering`]. Without it, e.g. on WebGL, they're
+//! assigned on the CPU by [`ClusterGrid`] instead.
+//!
+//! [`RenderFeature::GpuLightClustering`]: super::capabilities::RenderFeature::GpuLightClustering
+
+use std::ops::RangeInclusive;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use nalgebra::{
+    Matrix4,
+    Point3,
+    Similarity3,
+    Vector3,
+};
 use palette::Srgb;
 
+use crate::graphics::{
+    backend::Backend,
+    camera::CameraProjection,
+    capabilities::RenderFeature,
+    transform::GlobalTransform,
+    utils::{
+        BindGroupLayoutBuilder,
+        Srgb32Ext,
+    },
+    SurfaceSize,
+};
+
+/// Number of tiles along the width of the screen.
+pub const CLUSTERS_X: u32 = 16;
+
+/// Number of tiles along the height of the screen.
+pub const CLUSTERS_Y: u32 = 9;
+
+/// Number of depth slices.
+pub const CLUSTERS_Z: u32 = 24;
+
+/// Lights a cluster can hold. Further lights that overlap a cluster are
+/// ignored for it.
+pub const MAX_LIGHTS_PER_CLUSTER: u32 = 31;
+
+/// Point lights that are shaded per frame. If there are more, the ones
+/// closest to the camera are used.
+pub const MAX_POINT_LIGHTS: usize = 1024;
+
+/// Texels per cluster in the cluster texture: the number of lights, followed
+/// by their indices.
+const CLUSTER_STRIDE: u32 = MAX_LIGHTS_PER_CLUSTER + 1;
+
+/// Size of the cluster texture. A row holds the clusters of one row of tiles
+/// in one depth slice.
+const CLUSTER_TEXTURE_SIZE: (u32, u32) = (CLUSTERS_X * CLUSTER_STRIDE, CLUSTERS_Y * CLUSTERS_Z);
+
+/// Lights per workgroup, see `light_clustering.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
 #[derive(Clone, Copy, Debug)]
 pub struct AmbientLight {
     pub color: Srgb<f32>,
@@ -8,10 +79,658 @@ pub struct AmbientLight {
 #[derive(Clone, Copy, Debug)]
 pub struct PointLight {
     pub color: Srgb<f32>,
+
+    /// Distance at which the light fades out completely. Lights with a
+    /// smaller range are assigned to fewer clusters, so they're cheaper to
+    /// shade.
+    pub range: f32,
 }
 
 impl PointLight {
+    /// Range of lights that reach everything.
+    pub const UNLIMITED_RANGE: f32 = f32::MAX;
+
     pub fn new(color: Srgb<f32>) -> Self {
-        Self { color }
+        Self {
+            color,
+            range: Self::UNLIMITED_RANGE,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+}
+
+/// Maps points to the clusters of a camera's view.
+#[derive(Clone, Copy, Debug)]
+pub struct ClusterGrid {
+    camera_transform: Similarity3<f32>,
+    target_size: SurfaceSize,
+
+    /// Transforms from world to view space.
+    view: Matrix4<f32>,
+    projection: Matrix4<f32>,
+    z_near: f32,
+    z_far: f32,
+
+    /// Depth slices per logarithmic unit of depth.
+    z_scale: f32,
+}
+
+impl ClusterGrid {
+    pub fn new(
+        camera_projection: &CameraProjection,
+        camera_transform: &Similarity3<f32>,
+        target_size: SurfaceSize,
+    ) -> Self {
+        let z_near = camera_projection.z_near;
+        let z_far = camera_projection.z_far;
+        Self {
+            camera_transform: *camera_transform,
+            target_size,
+            view: camera_transform.inverse().to_homogeneous(),
+            projection: camera_projection.render_matrix(),
+            z_near,
+            z_far,
+            z_scale: CLUSTERS_Z as f32 / (z_far / z_near).ln(),
+        }
+    }
+
+    /// Depth slice of a distance along the view direction.
+    fn slice(&self, depth: f32) -> u32 {
+        let slice = (depth.max(self.z_near) / self.z_near).ln() * self.z_scale;
+        (slice as u32).min(CLUSTERS_Z - 1)
+    }
+
+    /// Returns the tiles and depth slices that a light at `position` with
+    /// `range` overlaps, or `None` if it's outside of the view.
+    ///
+    /// This is conservative, i.e. it might include clusters that the light
+    /// doesn't reach. `light_clustering.wgsl` does the same on the GPU.
+    pub fn clusters(&self, position: &Point3<f32>, range: f32) -> Option<[RangeInclusive<u32>; 3]> {
+        let center = self.view.transform_point(position);
+        let depth = -center.z;
+        if depth + range < self.z_near || depth - range > self.z_far {
+            return None;
+        }
+
+        let mut tiles = [0..=CLUSTERS_X - 1, 0..=CLUSTERS_Y - 1];
+
+        // if the light reaches the camera, its projection covers the whole screen.
+        if depth - range > self.z_near {
+            let mut ndc_min = [f32::INFINITY; 2];
+            let mut ndc_max = [f32::NEG_INFINITY; 2];
+            for i in 0..8 {
+                let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+                let corner = center + Vector3::new(sign(1), sign(2), sign(4)) * range;
+                let ndc = self.projection.transform_point(&corner);
+                for axis in 0..2 {
+                    ndc_min[axis] = ndc_min[axis].min(ndc[axis]);
+                    ndc_max[axis] = ndc_max[axis].max(ndc[axis]);
+                }
+            }
+            if ndc_max[0] < -1.0 || ndc_max[1] < -1.0 || ndc_min[0] > 1.0 || ndc_min[1] > 1.0 {
+                return None;
+            }
+
+            let tile =
+                |ndc: f32, count: u32| ((0.5 * ndc * count as f32).max(0.0) as u32).min(count - 1);
+            // tiles are counted from the top of the screen.
+            tiles = [
+                tile(ndc_min[0] + 1.0, CLUSTERS_X)..=tile(ndc_max[0] + 1.0, CLUSTERS_X),
+                tile(1.0 - ndc_max[1], CLUSTERS_Y)..=tile(1.0 - ndc_min[1], CLUSTERS_Y),
+            ];
+        }
+
+        let [x, y] = tiles;
+        Some([x, y, self.slice(depth - range)..=self.slice(depth + range)])
+    }
+
+    /// Assigns lights to clusters on the CPU, and writes the light lists in
+    /// the layout of the cluster texture.
+    fn assign(&self, lights: &[PointLightUniform], clusters: &mut [u32]) {
+        clusters.fill(0);
+        for (index, light) in lights.iter().enumerate() {
+            let Some([xs, ys, zs]) = self.clusters(&light.position.into(), light.range)
+            else {
+                continue;
+            };
+            for z in zs {
+                for y in ys.clone() {
+                    for x in xs.clone() {
+                        let offset =
+                            (((y + z * CLUSTERS_Y) * CLUSTERS_X + x) * CLUSTER_STRIDE) as usize;
+                        let count = clusters[offset];
+                        if count < MAX_LIGHTS_PER_CLUSTER {
+                            clusters[offset + 1 + count as usize] = index as u32;
+                            clusters[offset] = count + 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lights of a [`Render3dPass`](super::render_3d::Render3dPass), and their
+/// bind group.
+#[derive(Debug)]
+pub struct Lights {
+    uniform_buffer: wgpu::Buffer,
+    point_lights_texture: wgpu::Texture,
+    clusters_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    point_lights: Vec<PointLightUniform>,
+    clustering: Clustering,
+}
+
+#[derive(Debug)]
+enum Clustering {
+    Cpu { clusters: Vec<u32> },
+    Gpu(GpuLightClustering),
+}
+
+impl Lights {
+    pub fn new(backend: &Backend, bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniform_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("light buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+            size: std::mem::size_of::<LightUniform>() as wgpu::BufferAddress,
+        });
+
+        let point_lights_texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("point lights"),
+            size: wgpu::Extent3d {
+                width: 2,
+                height: MAX_POINT_LIGHTS as u32,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let clusters_texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("light clusters"),
+            size: wgpu::Extent3d {
+                width: CLUSTER_TEXTURE_SIZE.0,
+                height: CLUSTER_TEXTURE_SIZE.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("light bind group"),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &point_lights_texture.create_view(&Default::default()),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            &clusters_texture.create_view(&Default::default()),
+                        ),
+                    },
+                ],
+            });
+
+        let clustering = if backend
+            .capabilities
+            .is_enabled(RenderFeature::GpuLightClustering)
+        {
+            Clustering::Gpu(GpuLightClustering::new(backend, &point_lights_texture))
+        }
+        else {
+            Clustering::Cpu {
+                clusters: vec![0; (CLUSTER_TEXTURE_SIZE.0 * CLUSTER_TEXTURE_SIZE.1) as usize],
+            }
+        };
+
+        Self {
+            uniform_buffer,
+            point_lights_texture,
+            clusters_texture,
+            bind_group,
+            point_lights: Vec::with_capacity(MAX_POINT_LIGHTS),
+            clustering,
+        }
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Uploads the lights and assigns them to the clusters of `grid`.
+    ///
+    /// This must be called outside of a render pass, since it might record a
+    /// compute pass.
+    pub fn update<'a>(
+        &mut self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        grid: &ClusterGrid,
+        ambient_light: Option<&AmbientLight>,
+        point_lights: impl IntoIterator<Item = (&'a GlobalTransform, &'a PointLight)>,
+    ) {
+        let camera_position = grid.camera_transform.transform_point(&Point3::origin());
+
+        self.point_lights.clear();
+        self.point_lights
+            .extend(point_lights.into_iter().map(|(transform, point_light)| {
+                PointLightUniform {
+                    position: transform
+                        .model_matrix
+                        .transform_point(&Point3::origin())
+                        .into(),
+                    range: point_light.range,
+                    color: point_light.color.as_array3(),
+                    _padding: 0,
+                }
+            }));
+        if self.point_lights.len() > MAX_POINT_LIGHTS {
+            let distance = |light: &PointLightUniform| {
+                (Point3::from(light.position) - camera_position).norm_squared()
+            };
+            self.point_lights
+                .select_nth_unstable_by(MAX_POINT_LIGHTS, |a, b| {
+                    distance(a).total_cmp(&distance(b))
+                });
+            self.point_lights.truncate(MAX_POINT_LIGHTS);
+        }
+
+        let uniform = LightUniform {
+            ambient_light: ambient_light.map_or([0.0; 3], |light| light.color.as_array3()),
+            num_point_lights: self.point_lights.len() as u32,
+            view_position: camera_position.into(),
+            z_near: grid.z_near,
+            view_direction: grid
+                .camera_transform
+                .transform_vector(&-Vector3::z())
+                .normalize()
+                .into(),
+            z_scale: grid.z_scale,
+            target_size: [
+                grid.target_size.width as f32,
+                grid.target_size.height as f32,
+            ],
+            _padding: [0; 2],
+        };
+        backend
+            .queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        if !self.point_lights.is_empty() {
+            backend.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.point_lights_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&self.point_lights),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(std::mem::size_of::<PointLightUniform>() as u32),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: 2,
+                    height: self.point_lights.len() as u32,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        match &mut self.clustering {
+            Clustering::Cpu { clusters } => {
+                grid.assign(&self.point_lights, clusters);
+                backend.queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &self.clusters_texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    bytemuck::cast_slice(clusters),
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * CLUSTER_TEXTURE_SIZE.0),
+                        rows_per_image: None,
+                    },
+                    wgpu::Extent3d {
+                        width: CLUSTER_TEXTURE_SIZE.0,
+                        height: CLUSTER_TEXTURE_SIZE.1,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+            Clustering::Gpu(clustering) => {
+                clustering.assign(
+                    backend,
+                    encoder,
+                    grid,
+                    self.point_lights.len() as u32,
+                    &self.clusters_texture,
+                );
+            }
+        }
+    }
+}
+
+/// Layout of the light bind group: the [`LightUniform`], the point lights and
+/// the clusters.
+pub fn bind_group_layout_builder() -> BindGroupLayoutBuilder {
+    BindGroupLayoutBuilder::default()
+        .with_uniform_buffer(wgpu::ShaderStages::VERTEX_FRAGMENT)
+        .with_entry(
+            wgpu::ShaderStages::FRAGMENT,
+            wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+        )
+        .with_entry(
+            wgpu::ShaderStages::FRAGMENT,
+            wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Uint,
+            },
+        )
+}
+
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+struct LightUniform {
+    ambient_light: [f32; 3],
+    num_point_lights: u32,
+    view_position: [f32; 3],
+    z_near: f32,
+    view_direction: [f32; 3],
+    z_scale: f32,
+    target_size: [f32; 2],
+    _padding: [u32; 2],
+}
+
+/// A point light as it's stored in the point light texture, i.e. two texels
+/// per light.
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+#[repr(C)]
+struct PointLightUniform {
+    position: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    _padding: u32,
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct ClusteringParams {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    z_near: f32,
+    z_far: f32,
+    z_scale: f32,
+    num_lights: u32,
+}
+
+/// Assigns lights to clusters in a compute shader, see the
+/// [module documentation](self).
+#[derive(Debug)]
+struct GpuLightClustering {
+    pipeline: wgpu::ComputePipeline,
+    params_buffer: wgpu::Buffer,
+    clusters_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GpuLightClustering {
+    fn new(backend: &Backend, point_lights_texture: &wgpu::Texture) -> Self {
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("./light_clustering.wgsl"));
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("GpuLightClustering bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("GpuLightClustering pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("GpuLightClustering pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "assign",
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        let params_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GpuLightClustering params"),
+            size: std::mem::size_of::<ClusteringParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let clusters_buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GpuLightClustering clusters"),
+            size: (4 * CLUSTER_TEXTURE_SIZE.0 * CLUSTER_TEXTURE_SIZE.1) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("GpuLightClustering bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &point_lights_texture.create_view(&Default::default()),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: clusters_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+        Self {
+            pipeline,
+            params_buffer,
+            clusters_buffer,
+            bind_group,
+        }
+    }
+
+    /// Records the compute pass that assigns the lights, and the copy of the
+    /// clusters into the cluster texture.
+    fn assign(
+        &self,
+        backend: &Backend,
+        encoder: &mut wgpu::CommandEncoder,
+        grid: &ClusterGrid,
+        num_lights: u32,
+        clusters_texture: &wgpu::Texture,
+    ) {
+        let params = ClusteringParams {
+            view: grid.view.into(),
+            projection: grid.projection.into(),
+            z_near: grid.z_near,
+            z_far: grid.z_far,
+            z_scale: grid.z_scale,
+            num_lights,
+        };
+        backend
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        encoder.clear_buffer(&self.clusters_buffer, 0, None);
+
+        if num_lights > 0 {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("GpuLightClustering compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.dispatch_workgroups(num_lights.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        encoder.copy_buffer_to_texture(
+            wgpu::ImageCopyBuffer {
+                buffer: &self.clusters_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * CLUSTER_TEXTURE_SIZE.0),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::ImageCopyTexture {
+                texture: clusters_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d {
+                width: CLUSTER_TEXTURE_SIZE.0,
+                height: CLUSTER_TEXTURE_SIZE.1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::{
+        Point3,
+        Similarity3,
+    };
+
+    use super::{
+        ClusterGrid,
+        CLUSTERS_X,
+        CLUSTERS_Y,
+        CLUSTERS_Z,
+    };
+    use crate::graphics::{
+        camera::CameraProjection,
+        light::PointLight,
+        SurfaceSize,
+    };
+
+    fn grid() -> ClusterGrid {
+        // camera at the origin looking down -z.
+        let projection = CameraProjection::perspective(16.0 / 9.0, PI / 2.0, 0.1, 100.0);
+        let target_size = SurfaceSize {
+            width: 1600,
+            height: 900,
+        };
+        ClusterGrid::new(&projection, &Similarity3::identity(), target_size)
+    }
+
+    #[test]
+    fn it_assigns_small_lights_to_few_clusters() {
+        let [x, y, z] = grid().clusters(&Point3::new(0.0, 0.0, -10.0), 0.5).unwrap();
+        assert!(x.contains(&(CLUSTERS_X / 2)) && x.end() - x.start() <= 1);
+        assert!(y.contains(&(CLUSTERS_Y / 2)) && y.end() - y.start() <= 1);
+        assert!(z.end() - z.start() <= 1);
+    }
+
+    #[test]
+    fn it_skips_lights_outside_the_view() {
+        let grid = grid();
+        assert!(grid.clusters(&Point3::new(0.0, 0.0, 10.0), 1.0).is_none());
+        assert!(grid.clusters(&Point3::new(50.0, 0.0, -10.0), 1.0).is_none());
+        assert!(grid.clusters(&Point3::new(0.0, 0.0, -200.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn it_assigns_unlimited_lights_to_all_clusters() {
+        let [x, y, z] = grid()
+            .clusters(&Point3::new(0.0, 0.0, 10.0), PointLight::UNLIMITED_RANGE)
+            .unwrap();
+        assert_eq!(x, 0..=CLUSTERS_X - 1);
+        assert_eq!(y, 0..=CLUSTERS_Y - 1);
+        assert_eq!(z, 0..=CLUSTERS_Z - 1);
     }
 }
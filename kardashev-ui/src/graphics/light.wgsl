@@ -1,12 +1,74 @@
-const MAX_SPOT_LIGHTS: u32 = 16;
+// Lights and clustered light culling, see `light.rs`.
+//
+// Shaders bind the `Lights` uniform, the point light texture and the cluster
+// texture, find the fragment's cluster with `light_cluster`, and then shade it
+// with the lights returned by `cluster_light`.
+
+const CLUSTERS_X: u32 = 16u;
+const CLUSTERS_Y: u32 = 9u;
+const CLUSTERS_Z: u32 = 24u;
+const CLUSTER_STRIDE: u32 = 32u;
 
 struct Lights {
     ambient_light: vec3<f32>,
     num_point_lights: u32,
-    point_lights: array<SpotLight, MAX_SPOT_LIGHTS>,
+    view_position: vec3<f32>,
+    z_near: f32,
+    view_direction: vec3<f32>,
+    z_scale: f32,
+    target_size: vec2<f32>,
 };
 
-struct SpotLight {
-    position: vec3f,
-    color: vec3f,
+struct PointLight {
+    position: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+}
+
+struct Cluster {
+    // texel with the number of lights, followed by their indices.
+    texel: vec2<u32>,
+    num_lights: u32,
+}
+
+fn light_cluster(
+    lights: Lights,
+    clusters: texture_2d<u32>,
+    frag_coord: vec2<f32>,
+    world_position: vec3<f32>,
+) -> Cluster {
+    let grid = vec2<f32>(f32(CLUSTERS_X), f32(CLUSTERS_Y));
+    let tile = vec2<u32>(clamp(frag_coord / lights.target_size * grid, vec2<f32>(0.0), grid - 1.0));
+
+    let depth = dot(world_position - lights.view_position, lights.view_direction);
+    let slice = u32(clamp(
+        log(max(depth, lights.z_near) / lights.z_near) * lights.z_scale,
+        0.0,
+        f32(CLUSTERS_Z - 1u),
+    ));
+
+    var cluster: Cluster;
+    cluster.texel = vec2<u32>(tile.x * CLUSTER_STRIDE, tile.y + slice * CLUSTERS_Y);
+    cluster.num_lights = min(textureLoad(clusters, cluster.texel, 0).x, CLUSTER_STRIDE - 1u);
+    return cluster;
+}
+
+fn cluster_light(
+    point_lights: texture_2d<f32>,
+    clusters: texture_2d<u32>,
+    cluster: Cluster,
+    i: u32,
+) -> PointLight {
+    let index = textureLoad(clusters, cluster.texel + vec2<u32>(i + 1u, 0u), 0).x;
+    let position_and_range = textureLoad(point_lights, vec2<u32>(0u, index), 0);
+    let color = textureLoad(point_lights, vec2<u32>(1u, index), 0);
+    return PointLight(position_and_range.xyz, position_and_range.w, color.xyz);
+}
+
+// Fades the light out towards its range, so that it doesn't end abruptly at a
+// cluster's border.
+fn light_attenuation(light: PointLight, distance: f32) -> f32 {
+    let x = distance / light.range;
+    let window = saturate(1.0 - x * x * x * x);
+    return window * window;
 }
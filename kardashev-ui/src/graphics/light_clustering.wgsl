@@ -0,0 +1,97 @@
+// Assigns point lights to clusters, see `light.rs`.
+//
+// This is the same as `ClusterGrid::clusters`, with one invocation per light.
+// The clusters are laid out like the cluster texture, into which they're
+// copied afterwards.
+
+const CLUSTERS_X: u32 = 16u;
+const CLUSTERS_Y: u32 = 9u;
+const CLUSTERS_Z: u32 = 24u;
+const CLUSTER_STRIDE: u32 = 32u;
+
+struct ClusteringParams {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    z_near: f32,
+    z_far: f32,
+    z_scale: f32,
+    num_lights: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> params: ClusteringParams;
+
+@group(0)
+@binding(1)
+var point_lights: texture_2d<f32>;
+
+@group(0)
+@binding(2)
+var<storage, read_write> clusters: array<atomic<u32>>;
+
+fn slice(depth: f32) -> u32 {
+    return u32(clamp(
+        log(max(depth, params.z_near) / params.z_near) * params.z_scale,
+        0.0,
+        f32(CLUSTERS_Z - 1u),
+    ));
+}
+
+@compute
+@workgroup_size(64)
+fn assign(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.num_lights {
+        return;
+    }
+
+    let light = textureLoad(point_lights, vec2<u32>(0u, index), 0);
+    let center = (params.view * vec4<f32>(light.xyz, 1.0)).xyz;
+    let range = light.w;
+    let depth = -center.z;
+    if depth + range < params.z_near || depth - range > params.z_far {
+        return;
+    }
+
+    let grid = vec2<f32>(f32(CLUSTERS_X), f32(CLUSTERS_Y));
+    var tile_min = vec2<u32>(0u);
+    var tile_max = vec2<u32>(CLUSTERS_X - 1u, CLUSTERS_Y - 1u);
+
+    // if the light reaches the camera, its projection covers the whole screen.
+    if depth - range > params.z_near {
+        var ndc_min = vec2<f32>(3.4e38);
+        var ndc_max = vec2<f32>(-3.4e38);
+        for (var i = 0u; i < 8u; i++) {
+            let sign = vec3<f32>(
+                select(-1.0, 1.0, (i & 1u) != 0u),
+                select(-1.0, 1.0, (i & 2u) != 0u),
+                select(-1.0, 1.0, (i & 4u) != 0u),
+            );
+            let clip = params.projection * vec4<f32>(center + sign * range, 1.0);
+            let ndc = clip.xy / clip.w;
+            ndc_min = min(ndc_min, ndc);
+            ndc_max = max(ndc_max, ndc);
+        }
+        if any(ndc_max < vec2<f32>(-1.0)) || any(ndc_min > vec2<f32>(1.0)) {
+            return;
+        }
+
+        // tiles are counted from the top of the screen.
+        tile_min = vec2<u32>(clamp(vec2<f32>(ndc_min.x + 1.0, 1.0 - ndc_max.y) * 0.5 * grid, vec2<f32>(0.0), grid - 1.0));
+        tile_max = vec2<u32>(clamp(vec2<f32>(ndc_max.x + 1.0, 1.0 - ndc_min.y) * 0.5 * grid, vec2<f32>(0.0), grid - 1.0));
+    }
+
+    let slice_max = slice(depth + range);
+    for (var z = slice(depth - range); z <= slice_max; z++) {
+        for (var y = tile_min.y; y <= tile_max.y; y++) {
+            for (var x = tile_min.x; x <= tile_max.x; x++) {
+                let offset = ((y + z * CLUSTERS_Y) * CLUSTERS_X + x) * CLUSTER_STRIDE;
+                let slot = atomicAdd(&clusters[offset], 1u);
+                if slot < CLUSTER_STRIDE - 1u {
+                    atomicStore(&clusters[offset + 1u + slot], index);
+                }
+            }
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use std::{
+    fmt::{
+        Debug,
+        Display,
+    },
     future::Future,
     marker::PhantomData,
     sync::Arc,
 };
 
 use arrayvec::ArrayVec;
+use bytemuck::Pod;
 use kardashev_protocol::{
     asset_id,
     assets::{
@@ -32,11 +37,17 @@ use crate::{
             Backend,
             PerBackend,
         },
+        builtin,
+        material_pipeline::MaterialDescriptor,
         texture::{
             GpuTexture,
             TextureError,
         },
-        utils::GpuResourceCache,
+        transform::GlobalTransform,
+        utils::{
+            GpuResourceCache,
+            HasVertexBufferLayout,
+        },
     },
     utils::thread_local_cell::ThreadLocalCell,
 };
@@ -49,6 +60,27 @@ pub struct Material<C> {
     pub gpu: PerBackend<Arc<ThreadLocalCell<GpuMaterial<C>>>>,
 }
 
+impl<C> Material<C> {
+    pub fn new(cpu: C) -> Self {
+        Self {
+            asset_id: None,
+            label: None,
+            cpu,
+            gpu: PerBackend::default(),
+        }
+    }
+
+    pub fn with_asset_id(mut self, asset_id: AssetId) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Display) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+}
+
 impl<C: PipelineMaterial> Material<C> {
     pub fn gpu(
         &mut self,
@@ -108,11 +140,42 @@ impl<C: PipelineMaterial> LoadFromAsset for Material<C> {
             gpu: PerBackend::default(),
         })
     }
+
+    fn fallback() -> Option<Self> {
+        Some(builtin::default_material())
+    }
+
+    fn reload_args(&self) -> Option<()> {
+        Some(())
+    }
 }
 
+/// A kind of material, which is rendered with its own pipeline.
+///
+/// The [`Default`] value is used as a fallback while the actual material is
+/// loading, or if it failed to load. It should render with the built-in
+/// fallback textures.
+///
+/// Materials other than the built-in ones are registered with a
+/// [`MaterialRegistry`](super::material_pipeline::MaterialRegistry).
 // todo: rename. would like to call it `Material`, but we also have the struct
 // `Material`
-pub trait PipelineMaterial: Send + Sync + Sized + 'static {
+pub trait PipelineMaterial: Default + Send + Sync + Sized + 'static {
+    /// Per-instance data, which is passed to the shader in a vertex buffer.
+    type Instance: Pod + Debug + HasVertexBufferLayout;
+
+    fn descriptor() -> MaterialDescriptor;
+
+    fn instance(&self, transform: &GlobalTransform) -> Self::Instance;
+
+    /// How the material is blended with what is behind it.
+    ///
+    /// Transparent materials are drawn after all opaque ones, sorted
+    /// back-to-front.
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Opaque
+    }
+
     fn load_from_server<'a, 'b: 'a>(
         asset_id: AssetId,
         context: &'a mut LoadAssetContext<'b>,
@@ -127,6 +190,42 @@ pub trait PipelineMaterial: Send + Sync + Sized + 'static {
     ) -> Result<GpuMaterial<Self>, MaterialError>;
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+
+    /// Blended using the alpha of the fragment shader's output, e.g. for
+    /// atmospheres.
+    AlphaBlend,
+
+    /// Added to what is behind it, e.g. for glows.
+    Additive,
+}
+
+impl BlendMode {
+    pub fn is_transparent(&self) -> bool {
+        !matches!(self, Self::Opaque)
+    }
+
+    pub fn as_wgpu(&self) -> wgpu::BlendState {
+        match self {
+            Self::Opaque => wgpu::BlendState::REPLACE,
+            Self::AlphaBlend => wgpu::BlendState::ALPHA_BLENDING,
+            Self::Additive => {
+                wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::SrcAlpha,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GpuMaterial<M> {
     pub bind_group: wgpu::BindGroup,
@@ -178,24 +277,29 @@ impl<'a, 'b: 'a, const N: usize> BindGroupBuilder<'a, 'b, N> {
         }
     }
 
+    /// Pushes a texture view and sampler to the bind group.
+    ///
+    /// If the texture has its own sampler, that is used. Otherwise
+    /// `fallback_sampler` is used.
     pub fn push(
         &mut self,
         texture: &'a mut Option<Texture>,
         fallback_texture: &'a wgpu::TextureView,
-        //sampler: Option<&'a wgpu::Sampler>,
         fallback_sampler: &'a wgpu::Sampler,
     ) -> Result<(), MaterialError> {
         let index = self.entries.len() as u32;
 
-        let texture = texture
+        let (texture, sampler) = texture
             .as_mut()
             .map(|texture| texture.gpu(self.backend, self.cache))
             .transpose()?
-            .map(|texture| &texture.get().view)
-            .unwrap_or_else(|| fallback_texture);
+            .map(|texture| {
+                let texture = texture.get();
+                (&texture.view, texture.sampler.as_ref())
+            })
+            .unwrap_or_else(|| (fallback_texture, None));
 
-        //let sampler = sampler.unwrap_or(fallback_sampler);
-        let sampler = fallback_sampler;
+        let sampler = sampler.unwrap_or(fallback_sampler);
 
         self.entries.push(wgpu::BindGroupEntry {
             binding: index,
@@ -226,7 +330,7 @@ pub enum MaterialError {
 pub struct Fallback {
     pub white: Arc<GpuTexture>,
     pub black: Arc<GpuTexture>,
-    pub pink: Arc<GpuTexture>,
+    pub missing: Arc<GpuTexture>,
     pub normal: Arc<GpuTexture>,
     pub sampler: Arc<wgpu::Sampler>,
 }
@@ -249,9 +353,11 @@ pub fn get_fallback(
                 wgpu::TextureFormat::Rgba8UnormSrgb,
                 backend,
             ));
-            let pink = Arc::new(GpuTexture::color1x1(
-                palette::named::PINK.into_format().with_alpha(1.0),
+            let missing = Arc::new(GpuTexture::from_rgba_image(
+                &builtin::missing_texture_image(),
                 wgpu::TextureFormat::Rgba8UnormSrgb,
+                Some("missing texture"),
+                false,
                 backend,
             ));
             let normal = Arc::new(GpuTexture::color1x1(
@@ -277,7 +383,7 @@ pub fn get_fallback(
             Arc::new(ThreadLocalCell::new(Fallback {
                 white,
                 black,
-                pink,
+                missing,
                 normal,
                 sampler,
             }))
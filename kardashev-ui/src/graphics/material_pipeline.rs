@@ -0,0 +1,428 @@
+//! Render pipelines for meshes with materials.
+//!
+//! Every [`PipelineMaterial`] describes its shader and bind group layout with
+//! a [`MaterialDescriptor`], and [`CreateMaterialPipeline`] creates a render
+//! pipeline from that. Materials other than the built-in
+//! [`BlinnPhongMaterial`](super::blinn_phong::BlinnPhongMaterial) and
+//! [`PbrMaterial`](super::pbr::PbrMaterial) are added to a
+//! [`MaterialRegistry`], which is passed to the
+//! [`RenderPlugin`](super::RenderPlugin) and to the render passes that should
+//! draw them.
+
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+};
+
+use kardashev_protocol::assets::Vertex;
+
+use crate::{
+    assets::system::AssetTypeRegistry,
+    graphics::{
+        builtin,
+        draw_batch::{
+            DrawBatcher,
+            TransparentQueue,
+        },
+        material::{
+            BlendMode,
+            Material,
+            PipelineMaterial,
+        },
+        render_3d::{
+            CreateRender3dPipeline,
+            CreateRender3dPipelineContext,
+            DepthTexture,
+            MeshMaterialPair,
+            MeshMaterialPairKey,
+            Render3dPipeline,
+            Render3dPipelineContext,
+            TransparentMeshMaterialPair,
+        },
+        utils::{
+            BindGroupLayoutBuilder,
+            HasVertexBufferLayout,
+        },
+    },
+};
+
+/// Describes how a material is rendered.
+///
+/// The shader must have the entry points `vs_main` and `fs_main`. It gets the
+/// vertices in vertex buffer 0 and the
+/// [instances](PipelineMaterial::Instance) in vertex buffer 1. The material's
+/// bind group is group 0, followed by the camera and light bind groups.
+///
+/// If the shader doesn't match the bind group layouts, the material is drawn
+/// with [`builtin::ERROR_SHADER`] instead.
+#[derive(Clone, Debug)]
+pub struct MaterialDescriptor {
+    pub label: &'static str,
+    pub shader: MaterialShader,
+
+    /// Whether the shader writes emissive light that blooms to `@location(1)`.
+    pub emissive: bool,
+
+    pub bind_group_layout: BindGroupLayoutBuilder,
+}
+
+/// WGSL shader of a material, e.g. from `include_wgsl_oil`.
+#[derive(Clone, Copy, Debug)]
+pub struct MaterialShader {
+    pub label: &'static str,
+    pub source: &'static str,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CreateMaterialPipeline<M> {
+    _material: PhantomData<fn() -> M>,
+}
+
+impl<M> Default for CreateMaterialPipeline<M> {
+    fn default() -> Self {
+        Self {
+            _material: PhantomData,
+        }
+    }
+}
+
+impl<M: PipelineMaterial> CreateRender3dPipeline for CreateMaterialPipeline<M> {
+    type Pipeline = MaterialPipeline<M>;
+
+    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
+        let descriptor = M::descriptor();
+
+        // draw the material with the error shader, instead of failing to create the
+        // pipeline.
+        let (material_shader, emissive) = match context.check_shader_bindings(
+            descriptor.shader.label,
+            descriptor.shader.source,
+            &descriptor.bind_group_layout,
+        ) {
+            Ok(()) => (descriptor.shader, descriptor.emissive),
+            Err(error) => {
+                tracing::error!(material = descriptor.label, "{error}");
+                (builtin::ERROR_SHADER, false)
+            }
+        };
+
+        let shader = context
+            .backend
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(material_shader.label),
+                source: wgpu::ShaderSource::Wgsl(material_shader.source.into()),
+            });
+
+        let material_bind_group_layout = descriptor.bind_group_layout.build(
+            &context.backend.device,
+            Some(&format!("{} material bind group", descriptor.label)),
+        );
+
+        let pipeline_layout =
+            context
+                .backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(&format!("{} pipeline layout", descriptor.label)),
+                    bind_group_layouts: &[
+                        &material_bind_group_layout,
+                        &context.camera_bind_group_layout,
+                        &context.light_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+
+        let create_pipeline = |blend_mode: BlendMode| {
+            context
+                .backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(&format!("{} pipeline ({blend_mode:?})", descriptor.label)),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::layout(), M::Instance::layout()],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &context.color_targets(blend_mode.as_wgpu(), emissive),
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: context.depth_texture_format,
+                        depth_write_enabled: !blend_mode.is_transparent(),
+                        // fragments that were written by the depth pre-pass have the same depth.
+                        depth_compare: DepthTexture::COMPARE_EQUAL,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+        };
+
+        let depth_pipeline =
+            context
+                .backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(&format!("{} depth pipeline", descriptor.label)),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[Vertex::layout(), M::Instance::layout()],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: None,
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: Some(wgpu::Face::Back),
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: context.depth_texture_format,
+                        depth_write_enabled: true,
+                        depth_compare: DepthTexture::COMPARE,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                });
+
+        MaterialPipeline {
+            depth_pipeline,
+            opaque_pipeline: create_pipeline(BlendMode::Opaque),
+            alpha_blend_pipeline: create_pipeline(BlendMode::AlphaBlend),
+            additive_pipeline: create_pipeline(BlendMode::Additive),
+            material_bind_group_layout,
+            draw_batcher: DrawBatcher::new(context.backend),
+            transparent_queue: TransparentQueue::new(context.backend),
+            fallback_material: builtin::default_material(),
+            batched_in_depth_prepass: false,
+        }
+    }
+}
+
+/// Draws all entities with a [`Mesh`](super::mesh::Mesh) and a
+/// [`Material<M>`].
+///
+/// Entities with a transparent [blend mode](PipelineMaterial::blend_mode) are
+/// drawn in [`render_transparent`](Render3dPipeline::render_transparent).
+#[derive(Debug)]
+pub struct MaterialPipeline<M: PipelineMaterial> {
+    depth_pipeline: wgpu::RenderPipeline,
+    opaque_pipeline: wgpu::RenderPipeline,
+    alpha_blend_pipeline: wgpu::RenderPipeline,
+    additive_pipeline: wgpu::RenderPipeline,
+    material_bind_group_layout: wgpu::BindGroupLayout,
+    draw_batcher: DrawBatcher<MeshMaterialPairKey, MeshMaterialPair<M>, M::Instance>,
+    transparent_queue: TransparentQueue<TransparentMeshMaterialPair<M>, M::Instance>,
+    fallback_material: Material<M>,
+
+    /// Whether the meshes for this frame were already batched in the depth
+    /// pre-pass.
+    batched_in_depth_prepass: bool,
+}
+
+impl<M: PipelineMaterial> MaterialPipeline<M> {
+    fn batch(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        pipeline_context.batch_meshes_with_material::<M, M::Instance>(
+            &mut self.draw_batcher,
+            &mut self.transparent_queue,
+            &self.material_bind_group_layout,
+            &mut self.fallback_material,
+            |transform, material| material.instance(transform),
+        );
+    }
+}
+
+impl<M: PipelineMaterial> Render3dPipeline for MaterialPipeline<M> {
+    fn render(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        pipeline_context
+            .render_pass
+            .set_pipeline(&self.opaque_pipeline);
+        pipeline_context.bind_camera_uniform(1);
+        pipeline_context.bind_light_uniform(2);
+        if std::mem::take(&mut self.batched_in_depth_prepass) {
+            pipeline_context.draw_prepared_meshes_with_materials(&self.draw_batcher, 1, 0, 0);
+        }
+        else {
+            self.batch(pipeline_context);
+            pipeline_context.draw_batched_meshes_with_materials(&mut self.draw_batcher, 1, 0, 0);
+        }
+    }
+
+    fn render_depth(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        pipeline_context
+            .render_pass
+            .set_pipeline(&self.depth_pipeline);
+        pipeline_context.bind_camera_uniform(1);
+        pipeline_context.bind_light_uniform(2);
+        self.batch(pipeline_context);
+        pipeline_context.draw_batched_meshes_with_materials(&mut self.draw_batcher, 1, 0, 0);
+        self.batched_in_depth_prepass = true;
+    }
+
+    fn render_transparent(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        pipeline_context.bind_camera_uniform(1);
+        pipeline_context.bind_light_uniform(2);
+
+        // borrow the pipelines separately from the queue.
+        let Self {
+            opaque_pipeline,
+            alpha_blend_pipeline,
+            additive_pipeline,
+            transparent_queue,
+            ..
+        } = self;
+        pipeline_context.draw_transparent_meshes_with_materials(
+            transparent_queue,
+            1,
+            0,
+            0,
+            |blend_mode| {
+                match blend_mode {
+                    BlendMode::Opaque => &*opaque_pipeline,
+                    BlendMode::AlphaBlend => &*alpha_blend_pipeline,
+                    BlendMode::Additive => &*additive_pipeline,
+                }
+            },
+        );
+    }
+}
+
+/// Custom materials, i.e. materials other than the built-in ones.
+///
+/// # Example
+///
+/// ```ignore
+/// let materials = MaterialRegistry::default().with_material::<HologramMaterial>();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MaterialRegistry {
+    materials: Vec<RegisteredMaterial>,
+}
+
+#[derive(Clone, Copy)]
+struct RegisteredMaterial {
+    type_name: &'static str,
+    register_asset_type: fn(&mut AssetTypeRegistry),
+    create_pipeline: fn(&CreateRender3dPipelineContext) -> Box<dyn Render3dPipeline>,
+}
+
+impl Debug for RegisteredMaterial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegisteredMaterial")
+            .field("type_name", &self.type_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MaterialRegistry {
+    #[allow(dead_code)]
+    pub fn with_material<M: PipelineMaterial>(mut self) -> Self {
+        self.materials.push(RegisteredMaterial {
+            type_name: std::any::type_name::<M>(),
+            register_asset_type: |asset_type_registry| {
+                asset_type_registry.register::<Material<M>>();
+            },
+            create_pipeline: |context| {
+                Box::new(CreateMaterialPipeline::<M>::default().create_pipeline(context))
+            },
+        });
+        self
+    }
+
+    /// Registers [`Material<M>`] as asset type for all materials, so that they
+    /// can be loaded.
+    pub fn register_asset_types(&self, asset_type_registry: &mut AssetTypeRegistry) {
+        for material in &self.materials {
+            (material.register_asset_type)(asset_type_registry);
+        }
+    }
+}
+
+/// Creates the pipelines for all materials in a [`MaterialRegistry`].
+#[derive(Clone, Debug, Default)]
+pub struct CreateMaterialPipelines {
+    pub materials: MaterialRegistry,
+}
+
+impl CreateRender3dPipeline for CreateMaterialPipelines {
+    type Pipeline = MaterialPipelines;
+
+    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
+        MaterialPipelines {
+            pipelines: self
+                .materials
+                .materials
+                .iter()
+                .map(|material| {
+                    tracing::debug!(material = material.type_name, "creating material pipeline");
+                    (material.type_name, (material.create_pipeline)(context))
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct MaterialPipelines {
+    pipelines: Vec<(&'static str, Box<dyn Render3dPipeline>)>,
+}
+
+impl Debug for MaterialPipelines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.pipelines.iter().map(|(type_name, _)| type_name))
+            .finish()
+    }
+}
+
+impl Render3dPipeline for MaterialPipelines {
+    fn render(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        for (_, pipeline) in &mut self.pipelines {
+            pipeline.render(pipeline_context);
+        }
+    }
+
+    fn render_depth(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        for (_, pipeline) in &mut self.pipelines {
+            pipeline.render_depth(pipeline_context);
+        }
+    }
+
+    fn render_transparent(&mut self, pipeline_context: &mut Render3dPipelineContext) {
+        for (_, pipeline) in &mut self.pipelines {
+            pipeline.render_transparent(pipeline_context);
+        }
+    }
+}
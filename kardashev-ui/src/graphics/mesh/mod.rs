@@ -20,6 +20,7 @@ pub use kardashev_protocol::assets::{
     PrimitiveTopology,
     Vertex,
 };
+use nalgebra::Point3;
 use wgpu::util::DeviceExt;
 
 use crate::{
@@ -40,14 +41,13 @@ use crate::{
             Backend,
             PerBackend,
         },
+        builtin,
+        culling::Aabb,
         utils::GpuResourceCache,
     },
     utils::{
         thread_local_cell::ThreadLocalCell,
-        web_fs::{
-            self,
-            OpenOptions,
-        },
+        web_fs,
     },
 };
 
@@ -56,6 +56,7 @@ pub struct Mesh {
     asset_id: Option<AssetId>,
     label: Option<String>,
     cpu: Option<Arc<CpuMesh>>,
+    aabb: Option<Aabb>,
     gpu: PerBackend<Arc<ThreadLocalCell<GpuMesh>>>,
 }
 
@@ -64,6 +65,18 @@ impl Mesh {
         self.cpu.as_deref()
     }
 
+    /// The mesh data, which is shared by clones of this mesh and by meshes
+    /// that were loaded from the same asset.
+    pub fn cpu_shared(&self) -> Option<&Arc<CpuMesh>> {
+        self.cpu.as_ref()
+    }
+
+    /// Bounding box of the vertices in model space, or `None` if the mesh has
+    /// no vertices.
+    pub fn aabb(&self) -> Option<&Aabb> {
+        self.aabb.as_ref()
+    }
+
     pub fn gpu(
         &mut self,
         backend: &Backend,
@@ -106,6 +119,7 @@ impl From<CpuMesh> for Mesh {
         Mesh {
             asset_id: None,
             label: None,
+            aabb: mesh_aabb(&mesh),
             cpu: Some(Arc::new(mesh)),
             gpu: PerBackend::default(),
         }
@@ -143,10 +157,27 @@ impl LoadFromAsset for Mesh {
         Ok(Self {
             asset_id: Some(asset_id),
             label: dist.label.clone(),
+            aabb: mesh_aabb(&cpu),
             cpu: Some(cpu),
             gpu: PerBackend::default(),
         })
     }
+
+    fn fallback() -> Option<Self> {
+        Some(builtin::unit_cube())
+    }
+
+    fn reload_args(&self) -> Option<()> {
+        Some(())
+    }
+}
+
+fn mesh_aabb(mesh: &CpuMesh) -> Option<Aabb> {
+    Aabb::from_points(
+        mesh.vertices
+            .iter()
+            .map(|vertex| Point3::from(vertex.position)),
+    )
 }
 
 async fn load_mesh_from_server<'a, 'b: 'a>(
@@ -154,9 +185,7 @@ async fn load_mesh_from_server<'a, 'b: 'a>(
     asset_store: &AssetStoreGuard,
     client: &AssetClient,
 ) -> Result<Arc<CpuMesh>, MeshError> {
-    let mut file = asset_store
-        .open(&dist.mesh, OpenOptions::new().create(true))
-        .await?;
+    let mut file = asset_store.open_cached(&dist.mesh).await?;
 
     let mut data = None;
 
@@ -1,18 +1,30 @@
 pub mod backend;
 pub mod blinn_phong;
+pub mod bloom;
+pub mod builtin;
 pub mod camera;
+pub mod capabilities;
+pub mod culling;
 pub mod draw_batch;
+pub mod gizmo;
 pub mod hdr;
 pub mod light;
 pub mod material;
+pub mod material_pipeline;
 pub mod mesh;
+pub mod mipmap;
 pub mod model;
 pub mod pbr;
+pub mod recorder;
 pub mod render_3d;
 pub mod render_frame;
+pub mod render_layers;
+pub mod screenshot;
 pub mod texture;
 pub mod transform;
 pub mod utils;
+pub mod viewport;
+pub mod watchdog;
 
 use std::{
     fmt::Debug,
@@ -49,14 +61,22 @@ use crate::{
             BackendType,
         },
         blinn_phong::BlinnPhongMaterial,
+        builtin::BuiltinAssets,
+        capabilities::Capabilities,
         material::Material,
+        material_pipeline::MaterialRegistry,
         mesh::Mesh,
         pbr::PbrMaterial,
         render_frame::rendering_system,
         texture::Texture,
         transform::local_to_global_transform_system,
         utils::GpuResourceCache,
+        watchdog::{
+            FailureReporter,
+            Watchdog,
+        },
     },
+    notifications::Notifications,
     utils::{
         futures::spawn_local_and_handle_error,
         thread_local_cell::ThreadLocalError,
@@ -76,6 +96,9 @@ pub enum Error {
 
     #[error("failed to create surface")]
     CreateSurface(#[source] ThreadLocalError<wgpu::CreateSurfaceError>),
+
+    #[error("graphics were restarted")]
+    Restarted,
 }
 
 impl From<wgpu::RequestDeviceError> for Error {
@@ -95,6 +118,11 @@ pub struct Config {
     pub backend_type: SelectBackendType,
     pub power_preference: wgpu::PowerPreference,
     pub memory_hints: MemoryHints,
+
+    /// Enables the depth pre-pass of 3D render passes, see
+    /// [`Render3dSettings`](render_3d::Render3dSettings).
+    #[serde(default)]
+    pub depth_prepass: bool,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -123,24 +151,81 @@ impl MemoryHints {
     }
 }
 
+/// Whether the graphics were initialized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InitState {
+    Initializing,
+    Initialized,
+
+    /// No backend could be initialized. The error is formatted with its
+    /// sources.
+    Failed(String),
+}
+
 #[derive(Clone, Debug)]
 pub struct Graphics {
     tx_command: mpsc::Sender<Command>,
+    rx_capabilities: watch::Receiver<Option<Arc<Capabilities>>>,
+    rx_restarts: watch::Receiver<u32>,
+    rx_init_state: watch::Receiver<InitState>,
 }
 
 impl Graphics {
-    pub fn new(config: Config) -> Self {
+    /// The reactor is supervised by a [watchdog](watchdog), which sends a
+    /// notification when it restarts it.
+    pub fn new(config: Config, notifications: Option<Notifications>) -> Self {
         tracing::debug!(?config, "initializing graphics");
 
         let (tx_command, rx_command) = mpsc::channel(16);
 
-        spawn_local_and_handle_error(async move {
-            let reactor = Reactor::new(config, rx_command).await?;
-            reactor.run().await;
-            Ok::<(), Error>(())
-        });
+        let (tx_capabilities, rx_capabilities) = watch::channel(None);
+        let (tx_restarts, rx_restarts) = watch::channel(0);
+        let (tx_init_state, rx_init_state) = watch::channel(InitState::Initializing);
+
+        spawn_local_and_handle_error(
+            Watchdog {
+                config,
+                tx_command: tx_command.downgrade(),
+                rx_command,
+                tx_capabilities,
+                tx_restarts,
+                tx_init_state,
+                notifications,
+            }
+            .run(),
+        );
+
+        Self {
+            tx_command,
+            rx_capabilities,
+            rx_restarts,
+            rx_init_state,
+        }
+    }
+
+    /// Returns a receiver for whether the graphics were initialized.
+    ///
+    /// With WebGL this doesn't wait for a backend, since it's only created with
+    /// the first surface.
+    pub fn init_state(&self) -> watch::Receiver<InitState> {
+        self.rx_init_state.clone()
+    }
 
-        Self { tx_command }
+    /// Returns a receiver for the number of times the reactor was restarted.
+    ///
+    /// Surfaces that were created before a restart don't present anymore, and
+    /// have to be recreated.
+    pub fn restarts(&self) -> watch::Receiver<u32> {
+        self.rx_restarts.clone()
+    }
+
+    /// Returns a receiver for the capabilities of the most recently created
+    /// backend.
+    ///
+    /// With WebGL every surface has its own backend, but they all use the same
+    /// adapter.
+    pub fn capabilities(&self) -> watch::Receiver<Option<Arc<Capabilities>>> {
+        self.rx_capabilities.clone()
     }
 
     async fn send_command(&self, command: Command) {
@@ -164,11 +249,12 @@ impl Graphics {
         })
         .await;
 
+        // the reactor drops the sender if it's restarted in the meantime.
         let CreateSurfaceResponse {
             backend,
             surface,
             surface_configuration,
-        } = rx_result.await.unwrap()?;
+        } = rx_result.await.map_err(|_| Error::Restarted)??;
 
         Ok(Surface {
             backend,
@@ -183,11 +269,16 @@ struct Reactor {
     config: Config,
     backend_type: BackendType,
     shared_backend: Option<Backend>,
-    rx_command: mpsc::Receiver<Command>,
+    tx_capabilities: watch::Sender<Option<Arc<Capabilities>>>,
+    failures: FailureReporter,
 }
 
 impl Reactor {
-    async fn new(config: Config, rx_command: mpsc::Receiver<Command>) -> Result<Self, Error> {
+    async fn new(
+        config: Config,
+        tx_capabilities: watch::Sender<Option<Arc<Capabilities>>>,
+        failures: FailureReporter,
+    ) -> Result<Self, Error> {
         let (backend_type, shared_backend) = match config.backend_type {
             SelectBackendType::AutoDetect => {
                 tracing::debug!("trying WEBGPU");
@@ -196,7 +287,15 @@ impl Reactor {
                     ..Default::default()
                 });
 
-                match Backend::new(Arc::new(instance), &config, None, wgpu::Limits::default()).await
+                match Backend::new(
+                    Arc::new(instance),
+                    BackendType::WebGpu,
+                    &config,
+                    None,
+                    wgpu::Limits::default(),
+                    failures.clone(),
+                )
+                .await
                 {
                     Ok(shared_backend) => (BackendType::WebGpu, Some(shared_backend)),
                     Err(error) => {
@@ -214,23 +313,34 @@ impl Reactor {
                     backends: backend_type.as_wgpu(),
                     ..Default::default()
                 });
-                let shared_backend =
-                    Backend::new(Arc::new(instance), &config, None, wgpu::Limits::default())
-                        .await?;
+                let shared_backend = Backend::new(
+                    Arc::new(instance),
+                    backend_type,
+                    &config,
+                    None,
+                    wgpu::Limits::default(),
+                    failures.clone(),
+                )
+                .await?;
                 (backend_type, Some(shared_backend))
             }
         };
 
+        if let Some(shared_backend) = &shared_backend {
+            tx_capabilities.send_replace(Some(shared_backend.capabilities.clone()));
+        }
+
         Ok(Self {
             config,
             backend_type,
             shared_backend,
-            rx_command,
+            tx_capabilities,
+            failures,
         })
     }
 
-    async fn run(mut self) {
-        while let Some(command) = self.rx_command.recv().await {
+    async fn run(&self, rx_command: &mut mpsc::Receiver<Command>) {
+        while let Some(command) = rx_command.recv().await {
             match command {
                 Command::CreateSurface {
                     window_handle,
@@ -240,6 +350,9 @@ impl Reactor {
                     let result = self.create_surface(window_handle, surface_size).await;
                     let _ = tx_result.send(result);
                 }
+                Command::Ping { tx_pong } => {
+                    let _ = tx_pong.send(());
+                }
             }
         }
     }
@@ -273,12 +386,17 @@ impl Reactor {
 
             let backend = Backend::new(
                 instance,
+                self.backend_type,
                 &self.config,
                 Some(&surface),
                 wgpu::Limits::downlevel_webgl2_defaults(),
+                self.failures.clone(),
             )
             .await?;
 
+            self.tx_capabilities
+                .send_replace(Some(backend.capabilities.clone()));
+
             (surface, backend)
         };
 
@@ -298,8 +416,12 @@ impl Reactor {
 
         tracing::debug!("selected surface format: {surface_format:?}");
 
+        // we need COPY_SRC to take screenshots, but not all backends support it.
+        let surface_usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_capabilities.usages & wgpu::TextureUsages::COPY_SRC);
+
         let surface_configuration = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: surface_usage,
             format: surface_format,
             width: surface_size.width,
             height: surface_size.height,
@@ -326,6 +448,8 @@ enum Command {
         surface_size: SurfaceSize,
         tx_result: oneshot::Sender<Result<CreateSurfaceResponse, Error>>,
     },
+    /// Sent by the [watchdog](watchdog) to check that the reactor responds.
+    Ping { tx_pong: oneshot::Sender<()> },
 }
 
 #[derive(Debug)]
@@ -410,6 +534,11 @@ impl SurfaceSize {
             height: texture.height(),
         }
     }
+
+    /// Ratio of the width to the height.
+    pub fn aspect(&self) -> f32 {
+        (self.width as f32) / (self.height as f32)
+    }
 }
 
 #[derive(Debug)]
@@ -447,8 +576,22 @@ impl SurfaceVisibilityListener {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct RenderPlugin;
+#[derive(Clone, Debug, Default)]
+pub struct RenderPlugin {
+    materials: MaterialRegistry,
+}
+
+impl RenderPlugin {
+    /// Registers the asset types of custom materials.
+    ///
+    /// The same registry has to be passed to the render passes that draw
+    /// them, e.g. with
+    /// [`CreateMaterialPipelines`](material_pipeline::CreateMaterialPipelines).
+    pub fn with_materials(mut self, materials: MaterialRegistry) -> Self {
+        self.materials = materials;
+        self
+    }
+}
 
 impl Plugin for RenderPlugin {
     fn register(self, context: RegisterPluginContext) {
@@ -458,12 +601,14 @@ impl Plugin for RenderPlugin {
                 .register::<Mesh>()
                 .register::<Material<BlinnPhongMaterial>>()
                 .register::<Material<PbrMaterial>>();
+            self.materials.register_asset_types(asset_type_registry);
         }
         else {
             tracing::warn!("resource AssetTypeRegistry is missing. can't register asset types for rendering system");
         }
 
         context.resources.insert(GpuResourceCache::default());
+        context.resources.insert(BuiltinAssets::default());
         context
             .schedule
             .add_system(local_to_global_transform_system);
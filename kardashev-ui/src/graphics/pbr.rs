@@ -5,7 +5,6 @@ use bytemuck::{
 use kardashev_protocol::assets::{
     self as dist,
     AssetId,
-    Vertex,
 };
 
 use crate::{
@@ -17,7 +16,6 @@ use crate::{
         AssetNotFound,
     },
     graphics::{
-        draw_batch::DrawBatcher,
         material::{
             get_fallback,
             BindGroupBuilder,
@@ -25,22 +23,19 @@ use crate::{
             MaterialError,
             PipelineMaterial,
         },
-        render_3d::{
-            CreateRender3dPipeline,
-            CreateRender3dPipelineContext,
-            MeshMaterialPair,
-            MeshMaterialPairKey,
-            Render3dPipeline,
-            Render3dPipelineContext,
+        material_pipeline::{
+            MaterialDescriptor,
+            MaterialShader,
         },
         texture::{
             Texture,
             TextureError,
         },
+        transform::GlobalTransform,
         utils::{
+            BindGroupLayoutBuilder,
             GpuResourceCache,
             HasVertexBufferLayout,
-            MaterialBindGroupLayoutBuilder,
         },
     },
 };
@@ -48,124 +43,6 @@ use crate::{
 #[include_wgsl_oil::include_wgsl_oil("pbr.wgsl")]
 mod shader {}
 
-#[derive(Clone, Copy, Debug, Default)]
-pub struct CreatePbrRenderPipeline;
-
-impl CreateRender3dPipeline for CreatePbrRenderPipeline {
-    type Pipeline = PbrRenderPipeline;
-
-    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
-        let shader = context
-            .backend
-            .device
-            .create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("pbr.wgsl"),
-                source: wgpu::ShaderSource::Wgsl(shader::SOURCE.into()),
-            });
-
-        let mut material_bind_group_layout_builder = MaterialBindGroupLayoutBuilder::default();
-        for _ in 0..4 {
-            material_bind_group_layout_builder.push_view_and_sampler();
-        }
-
-        let material_bind_group_layout = material_bind_group_layout_builder
-            .build(&context.backend.device, Some("pbr material bind group"));
-
-        let pipeline_layout =
-            context
-                .backend
-                .device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("pbr pipeline layout"),
-                    bind_group_layouts: &[
-                        &material_bind_group_layout,
-                        &context.camera_bind_group_layout,
-                        &context.light_bind_group_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-
-        let pipeline =
-            context
-                .backend
-                .device
-                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                    label: Some("pbr pipeline"),
-                    layout: Some(&pipeline_layout),
-                    vertex: wgpu::VertexState {
-                        module: &shader,
-                        entry_point: "vs_main",
-                        buffers: &[Vertex::layout(), Instance::layout()],
-                        compilation_options: Default::default(),
-                    },
-                    fragment: Some(wgpu::FragmentState {
-                        module: &shader,
-                        entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: context.surface_format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
-                        compilation_options: Default::default(),
-                    }),
-                    primitive: wgpu::PrimitiveState {
-                        topology: wgpu::PrimitiveTopology::TriangleList,
-                        strip_index_format: None,
-                        front_face: wgpu::FrontFace::Ccw,
-                        cull_mode: Some(wgpu::Face::Back),
-                        polygon_mode: wgpu::PolygonMode::Fill,
-                        unclipped_depth: false,
-                        conservative: false,
-                    },
-                    depth_stencil: Some(wgpu::DepthStencilState {
-                        format: context.depth_texture_format,
-                        depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
-                        stencil: wgpu::StencilState::default(),
-                        bias: wgpu::DepthBiasState::default(),
-                    }),
-                    multisample: wgpu::MultisampleState {
-                        count: 1,
-                        mask: !0,
-                        alpha_to_coverage_enabled: false,
-                    },
-                    multiview: None,
-                    cache: None,
-                });
-
-        PbrRenderPipeline {
-            pipeline,
-            material_bind_group_layout,
-            draw_batcher: DrawBatcher::new(context.backend),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct PbrRenderPipeline {
-    pipeline: wgpu::RenderPipeline,
-    material_bind_group_layout: wgpu::BindGroupLayout,
-    draw_batcher: DrawBatcher<MeshMaterialPairKey, MeshMaterialPair<PbrMaterial>, Instance>,
-}
-
-impl Render3dPipeline for PbrRenderPipeline {
-    fn render(&mut self, pipeline_context: &mut Render3dPipelineContext) {
-        pipeline_context.render_pass.set_pipeline(&self.pipeline);
-        pipeline_context.bind_camera_uniform(1);
-        pipeline_context.bind_light_uniform(2);
-        pipeline_context.batch_meshes_with_material::<PbrMaterial, Instance>(
-            &mut self.draw_batcher,
-            &self.material_bind_group_layout,
-            |transform, _| {
-                Instance {
-                    model_transform: transform.as_homogeneous_matrix_array(),
-                }
-            },
-        );
-        pipeline_context.draw_batched_meshes_with_materials(&mut self.draw_batcher, 1, 0, 0);
-    }
-}
-
 #[derive(Clone, Debug, Default)]
 pub struct PbrMaterial {
     pub albedo: Option<Texture>,
@@ -175,6 +52,28 @@ pub struct PbrMaterial {
 }
 
 impl PipelineMaterial for PbrMaterial {
+    type Instance = Instance;
+
+    fn descriptor() -> MaterialDescriptor {
+        MaterialDescriptor {
+            label: "pbr",
+            shader: MaterialShader {
+                label: "pbr.wgsl",
+                source: shader::SOURCE,
+            },
+            emissive: false,
+            bind_group_layout: (0..4).fold(BindGroupLayoutBuilder::default(), |builder, _| {
+                builder.with_view_and_sampler(wgpu::ShaderStages::FRAGMENT)
+            }),
+        }
+    }
+
+    fn instance(&self, transform: &GlobalTransform) -> Instance {
+        Instance {
+            model_transform: transform.as_homogeneous_matrix_array(),
+        }
+    }
+
     async fn load_from_server<'a, 'b: 'a>(
         asset_id: AssetId,
         mut context: &'a mut LoadAssetContext<'b>,
@@ -226,7 +125,7 @@ impl PipelineMaterial for PbrMaterial {
         let fallback = fallback.get();
 
         let mut bind_group_builder = BindGroupBuilder::<8>::new(backend, cache);
-        bind_group_builder.push(&mut self.albedo, &fallback.pink.view, &fallback.sampler)?;
+        bind_group_builder.push(&mut self.albedo, &fallback.missing.view, &fallback.sampler)?;
         bind_group_builder.push(&mut self.normal, &fallback.black.view, &fallback.sampler)?;
         bind_group_builder.push(&mut self.metalness, &fallback.black.view, &fallback.sampler)?;
         bind_group_builder.push(&mut self.roughness, &fallback.black.view, &fallback.sampler)?;
@@ -0,0 +1,18 @@
+// Encodes RGBA pixels as PNG, so that the main thread doesn't block on it.
+//
+// Receives `{ width, height, pixels }`, with the pixels as an `ArrayBuffer`,
+// and responds with `{ png }` or `{ error }`.
+
+self.onmessage = async (event) => {
+    const { width, height, pixels } = event.data;
+    try {
+        const canvas = new OffscreenCanvas(width, height);
+        const context = canvas.getContext("2d");
+        context.putImageData(new ImageData(new Uint8ClampedArray(pixels), width, height), 0, 0);
+        const png = await canvas.convertToBlob({ type: "image/png" });
+        self.postMessage({ png });
+    }
+    catch (error) {
+        self.postMessage({ error: String(error) });
+    }
+};
@@ -0,0 +1,246 @@
+use std::{
+    ops::ControlFlow,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use kardashev_client::ApiClient;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::sync::watch;
+
+use crate::{
+    graphics::{
+        backend::Backend,
+        screenshot::{
+            start_capture,
+            ScreenshotError,
+        },
+    },
+    utils::{
+        futures::{
+            spawn_local,
+            spawn_local_and_handle_error,
+            throttled_loop,
+        },
+        thread_local_cell::ThreadLocalCell,
+        time::ServerClock,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error("frame recorder error")]
+pub enum RecorderError {
+    Screenshot(#[from] ScreenshotError),
+    WebFs(#[from] web_fs::Error),
+}
+
+/// How often the server's status is fetched while recording.
+const SERVER_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Component that records every `n`th frame rendered to the entity's
+/// [`RenderTarget`][super::camera::RenderTarget] into a [`WebFs`].
+///
+/// Frames are stored as PNG files named `{name}-{frame:06}.png`. Each file
+/// has [`FrameMetadata`] attached under the key `"frame"`, so the frames can
+/// be matched to the simulation when they're assembled into a timelapse.
+#[derive(Debug)]
+pub struct FrameRecorder {
+    web_fs: ThreadLocalCell<WebFs>,
+    name: String,
+    server: Option<watch::Receiver<ServerState>>,
+    every_nth_frame: u64,
+    max_pending: usize,
+    frames_rendered: u64,
+    frames_recorded: u64,
+    pending: Arc<AtomicUsize>,
+}
+
+impl FrameRecorder {
+    pub fn new(web_fs: WebFs, name: impl Into<String>) -> Self {
+        Self {
+            web_fs: ThreadLocalCell::new(web_fs),
+            name: name.into(),
+            server: None,
+            every_nth_frame: 1,
+            max_pending: 4,
+            frames_rendered: 0,
+            frames_recorded: 0,
+            pending: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Record the server's time and simulation epoch with the frames.
+    ///
+    /// They're fetched from the server's status every few seconds while the
+    /// recorder exists. If that fails, the frames are recorded with what was
+    /// fetched before.
+    pub fn with_server(mut self, api_client: ApiClient) -> Self {
+        let (tx, rx) = watch::channel(ServerState::default());
+        spawn_local(sync_server(api_client, tx));
+        self.server = Some(rx);
+        self
+    }
+
+    /// Only record every `n`th frame.
+    pub fn with_every_nth_frame(mut self, n: u64) -> Self {
+        self.every_nth_frame = n.max(1);
+        self
+    }
+
+    /// Maximum number of frames that are being encoded and stored at the same
+    /// time. If this is exceeded, frames are dropped.
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    pub fn frames_recorded(&self) -> u64 {
+        self.frames_recorded
+    }
+
+    pub(super) fn capture(&mut self, backend: &Backend, texture: &wgpu::Texture) {
+        let frame_rendered = self.frames_rendered;
+        self.frames_rendered += 1;
+
+        if frame_rendered % self.every_nth_frame != 0 {
+            return;
+        }
+
+        if self.pending.load(Ordering::Relaxed) >= self.max_pending {
+            tracing::warn!(name = %self.name, "frame recorder can't keep up. dropping frame");
+            return;
+        }
+
+        let capture = match start_capture(backend, texture) {
+            Ok(capture) => capture,
+            Err(error) => {
+                tracing::error!(?error, "failed to capture frame");
+                return;
+            }
+        };
+
+        let server = self
+            .server
+            .as_ref()
+            .map(|server| *server.borrow())
+            .unwrap_or_default();
+        let metadata = FrameMetadata {
+            frame: self.frames_recorded,
+            epoch: server.epoch,
+            server_time: server.clock.map(|clock| clock.now()),
+            recorded_at: Utc::now(),
+        };
+        self.frames_recorded += 1;
+
+        let path = format!("{}-{:06}.png", self.name, metadata.frame);
+        let web_fs = self.web_fs.get().clone();
+        let pending = PendingGuard::new(self.pending.clone());
+
+        spawn_local_and_handle_error(async move {
+            let _pending = pending;
+
+            let screenshot = capture.finish().await?;
+            let png = screenshot.to_png().await?;
+
+            let mut file = web_fs.open(&path, OpenOptions::new().create(true)).await?;
+            file.meta_data_mut().insert("frame", &metadata)?;
+            file.write_blob(png).await?;
+
+            tracing::debug!(%path, frame = metadata.frame, "recorded frame");
+
+            Ok::<(), RecorderError>(())
+        });
+    }
+}
+
+/// Metadata stored with every recorded frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    /// Index of the recorded frame, starting at 0.
+    pub frame: u64,
+
+    /// The simulation epoch that the server had run last, when its status
+    /// was fetched before the frame was rendered. `None` if it wasn't fetched
+    /// yet, or the world hasn't been simulated yet.
+    pub epoch: Option<i64>,
+
+    /// The server's time at which the frame was rendered, estimated from the
+    /// browser's clock. `None` if the server's clock wasn't synced yet.
+    pub server_time: Option<DateTime<Utc>>,
+
+    /// The browser's time at which the frame was rendered.
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// What the recorder knows about the server.
+#[derive(Clone, Copy, Debug, Default)]
+struct ServerState {
+    clock: Option<ServerClock>,
+    epoch: Option<i64>,
+}
+
+/// Fetches the server's status until the recorder is dropped.
+async fn sync_server(api_client: ApiClient, tx: watch::Sender<ServerState>) {
+    let api_client = &api_client;
+    let tx = &tx;
+
+    throttled_loop(SERVER_SYNC_INTERVAL, move || {
+        async move {
+            if tx.is_closed() {
+                return ControlFlow::Break(());
+            }
+
+            let sent_at = Utc::now();
+            match api_client.status().await {
+                Ok(status) => {
+                    let clock = ServerClock::from_status(&status, sent_at, Utc::now());
+                    tx.send_replace(ServerState {
+                        clock,
+                        epoch: status.epoch,
+                    });
+                }
+                Err(error) => {
+                    tracing::warn!(?error, "failed to fetch the server's status for recording");
+                }
+            }
+
+            ControlFlow::Continue(())
+        }
+    })
+    .await
+}
+
+struct PendingGuard {
+    pending: Arc<AtomicUsize>,
+}
+
+impl PendingGuard {
+    fn new(pending: Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::Relaxed);
+        Self { pending }
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
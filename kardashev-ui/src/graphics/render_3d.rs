@@ -8,21 +8,33 @@ use bytemuck::{
     Zeroable,
 };
 use nalgebra::Point3;
-use palette::Srgb;
 
 use crate::{
+    assets::load::Load,
     ecs::resource::Resources,
     graphics::{
         camera::{
             CameraProjection,
             ClearColor,
         },
-        draw_batch::DrawBatcher,
+        culling::{
+            Frustum,
+            NoFrustumCulling,
+        },
+        draw_batch::{
+            DrawBatcher,
+            PreparedBatch,
+            TransparentQueue,
+        },
         light::{
+            self,
             AmbientLight,
+            ClusterGrid,
+            Lights,
             PointLight,
         },
         material::{
+            BlendMode,
             GpuMaterial,
             GpuMaterialId,
             Material,
@@ -39,11 +51,14 @@ use crate::{
             RenderPass,
             RenderPassContext,
         },
+        render_layers::RenderLayers,
         transform::GlobalTransform,
         utils::{
+            check_shader_bindings,
             wgpu_buffer_size,
+            BindGroupLayoutBuilder,
             GpuResourceCache,
-            Srgb32Ext,
+            ShaderBindingError,
             Srgba64Ext,
         },
         Backend,
@@ -77,23 +92,8 @@ impl<P: CreateRender3dPipeline> CreateRenderPass for CreateRender3dPass<P> {
                 size: wgpu_buffer_size::<CameraUniform>(),
             });
 
-        let camera_bind_group_layout =
-            context
-                .backend
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("camera_bind_group_layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }],
-                });
+        let camera_bind_group_layout = camera_bind_group_layout_builder()
+            .build(&context.backend.device, Some("camera_bind_group_layout"));
 
         let camera_bind_group =
             context
@@ -108,52 +108,17 @@ impl<P: CreateRender3dPipeline> CreateRenderPass for CreateRender3dPass<P> {
                     label: Some("camera_bind_group"),
                 });
 
-        let light_buffer = context
-            .backend
-            .device
-            .create_buffer(&wgpu::BufferDescriptor {
-                label: Some("light buffer"),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-                size: wgpu_buffer_size::<LightUniform>(),
-            });
+        let light_bind_group_layout = light::bind_group_layout_builder()
+            .build(&context.backend.device, Some("light bind group layout"));
 
-        let light_bind_group_layout =
-            context
-                .backend
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("light bind group layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }],
-                });
-
-        let light_bind_group =
-            context
-                .backend
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &light_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: light_buffer.as_entire_binding(),
-                    }],
-                    label: None,
-                });
+        let lights = Lights::new(context.backend, &light_bind_group_layout);
 
         let pipeline = self
             .create_pipeline
             .create_pipeline(&CreateRender3dPipelineContext {
                 backend: context.backend,
                 surface_format: context.surface_format,
+                emissive_format: context.emissive_format,
                 depth_texture_format: DepthTexture::FORMAT,
                 camera_bind_group_layout: &camera_bind_group_layout,
                 light_bind_group_layout: &light_bind_group_layout,
@@ -167,11 +132,11 @@ impl<P: CreateRender3dPipeline> CreateRenderPass for CreateRender3dPass<P> {
             pipeline,
             camera_buffer,
             camera_bind_group,
-            light_buffer,
-            light_bind_group,
+            lights,
             depth_texture,
             creation_time,
             fps,
+            last_frame: None,
         }
     }
 }
@@ -181,11 +146,11 @@ pub struct Render3dPass<P> {
     pipeline: P,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
-    light_buffer: wgpu::Buffer,
-    light_bind_group: wgpu::BindGroup,
+    lights: Lights,
     depth_texture: DepthTexture,
     creation_time: Instant,
     fps: TicksPerSecond,
+    last_frame: Option<Instant>,
 }
 
 impl<P: Render3dPipeline> RenderPass for Render3dPass<P> {
@@ -195,44 +160,35 @@ impl<P: Render3dPipeline> RenderPass for Render3dPass<P> {
 
         let mut query_camera = context
             .world
-            .query_one::<(Option<&ClearColor>, &GlobalTransform, &CameraProjection)>(
-                context.render_target_entity,
-            )
+            .query_one::<(
+                Option<&ClearColor>,
+                &GlobalTransform,
+                &CameraProjection,
+                Option<&RenderLayers>,
+            )>(context.render_target_entity)
             .expect("render target entity doesn't exist");
 
-        if let Some((clear_color, camera_transform, camera_projection)) = query_camera.get() {
-            let mut render_pass = context
-                .encoder
-                .begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render3d render pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: context.target_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: clear_color
-                                .map(|c| wgpu::LoadOp::Clear(c.clear_color.into_format().as_wgpu()))
-                                .unwrap_or(wgpu::LoadOp::Load),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    })],
-                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: &self.depth_texture.texture_view,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
-                            store: wgpu::StoreOp::Store,
-                        }),
-                        stencil_ops: None,
-                    }),
-                    occlusion_query_set: None,
-                    timestamp_writes: None,
-                });
+        if let Some((clear_color, camera_transform, camera_projection, camera_layers)) =
+            query_camera.get()
+        {
+            let camera_layers = RenderLayers::of_camera(camera_layers);
 
             // update timing information
             let now = Instant::now();
             self.fps.push(now);
-
-            // update camera uniform
-            let camera_uniform = CameraUniform::from_camera(camera_projection, camera_transform)
+            let frame_time = self
+                .last_frame
+                .replace(now)
+                .map(|last_frame| now.duration_since(last_frame));
+
+            // update camera uniform. the aspect of the camera is only updated after the
+            // frame was rendered, but the target might have been resized since
+            // the last frame.
+            let camera_projection = CameraProjection {
+                aspect: context.target_size.aspect(),
+                ..*camera_projection
+            };
+            let camera_uniform = CameraUniform::from_camera(&camera_projection, camera_transform)
                 .with_time(now.duration_since(self.creation_time).as_secs_f32());
             context.backend.queue.write_buffer(
                 &self.camera_buffer,
@@ -240,34 +196,164 @@ impl<P: Render3dPipeline> RenderPass for Render3dPass<P> {
                 bytemuck::bytes_of(&camera_uniform),
             );
 
-            // update lights uniform
-            let mut light_uniform = LightUniform::default();
-            if let Some(ambient_light) = context.resources.get::<AmbientLight>() {
-                light_uniform.set_ambient_color(ambient_light.color);
-            }
+            // update lights and assign them to clusters
             let mut query_lights = context.world.query::<(&GlobalTransform, &PointLight)>();
-            for (_, (transform, point_light)) in query_lights.iter() {
-                if !light_uniform.add_point_light(
-                    transform.model_matrix.transform_point(&Point3::origin()),
-                    point_light.color,
-                ) {
-                    break;
-                }
-            }
-            context.backend.queue.write_buffer(
-                &self.light_buffer,
-                0,
-                bytemuck::bytes_of(&light_uniform),
+            self.lights.update(
+                context.backend,
+                context.encoder,
+                &ClusterGrid::new(
+                    &camera_projection,
+                    &camera_transform.model_matrix,
+                    context.target_size,
+                ),
+                context.resources.get::<AmbientLight>(),
+                query_lights.iter().map(|(_, components)| components),
+            );
+
+            let camera_position = camera_transform
+                .model_matrix
+                .transform_point(&Point3::origin());
+            let depth_prepass = context
+                .resources
+                .get::<Render3dSettings>()
+                .map_or(false, |settings| settings.depth_prepass);
+
+            let frustum = Frustum::from_view_projection(
+                &(camera_projection.render_matrix()
+                    * camera_transform.model_matrix.inverse().to_homogeneous()),
             );
 
-            self.pipeline.render(&mut Render3dPipelineContext {
-                backend: &mut context.backend,
-                render_pass: &mut render_pass,
-                camera_bind_group: &self.camera_bind_group,
-                light_bind_group: &self.light_bind_group,
+            // compute work, like GPU culling, has to be recorded before the render passes.
+            self.pipeline.prepare(&mut Render3dPrepareContext {
+                backend: context.backend,
+                encoder: context.encoder,
+                frustum,
+                camera_position,
+                camera_layers,
                 world: context.world,
                 resources: context.resources,
             });
+
+            if depth_prepass {
+                let mut render_pass =
+                    context
+                        .encoder
+                        .begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: Some("Render3d depth pre-pass"),
+                            color_attachments: &[],
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachment {
+                                    view: &self.depth_texture.texture_view,
+                                    depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(DepthTexture::CLEAR),
+                                        store: wgpu::StoreOp::Store,
+                                    }),
+                                    stencil_ops: None,
+                                },
+                            ),
+                            occlusion_query_set: None,
+                            timestamp_writes: None,
+                        });
+
+                self.pipeline.render_depth(&mut Render3dPipelineContext {
+                    backend: &mut context.backend,
+                    render_pass: &mut render_pass,
+                    camera_bind_group: &self.camera_bind_group,
+                    light_bind_group: self.lights.bind_group(),
+                    frustum,
+                    camera_position,
+                    camera_layers,
+                    world: context.world,
+                    resources: context.resources,
+                });
+            }
+
+            {
+                let mut color_attachments = vec![Some(wgpu::RenderPassColorAttachment {
+                    view: context.target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: clear_color
+                            .map(|c| wgpu::LoadOp::Clear(c.clear_color.into_format().as_wgpu()))
+                            .unwrap_or(wgpu::LoadOp::Load),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })];
+                if let Some(view) = context.emissive_view {
+                    // the emissive light is always cleared, since it's only bloomed once.
+                    color_attachments.push(Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    }));
+                }
+
+                let mut render_pass =
+                    context
+                        .encoder
+                        .begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: Some("Render3d render pass"),
+                            color_attachments: &color_attachments,
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachment {
+                                    view: &self.depth_texture.texture_view,
+                                    depth_ops: Some(wgpu::Operations {
+                                        // keep the depth from the pre-pass
+                                        load: if depth_prepass {
+                                            wgpu::LoadOp::Load
+                                        }
+                                        else {
+                                            wgpu::LoadOp::Clear(DepthTexture::CLEAR)
+                                        },
+                                        store: wgpu::StoreOp::Store,
+                                    }),
+                                    stencil_ops: None,
+                                },
+                            ),
+                            occlusion_query_set: None,
+                            timestamp_writes: None,
+                        });
+
+                let mut pipeline_context = Render3dPipelineContext {
+                    backend: &mut context.backend,
+                    render_pass: &mut render_pass,
+                    camera_bind_group: &self.camera_bind_group,
+                    light_bind_group: self.lights.bind_group(),
+                    frustum,
+                    camera_position,
+                    camera_layers,
+                    world: context.world,
+                    resources: context.resources,
+                };
+                self.pipeline.render(&mut pipeline_context);
+                self.pipeline.render_transparent(&mut pipeline_context);
+            }
+
+            if let Ok(mut timings) = context
+                .world
+                .get::<&mut Render3dTimings>(context.render_target_entity)
+            {
+                timings.push(
+                    depth_prepass,
+                    frame_time,
+                    Instant::now().duration_since(now),
+                );
+            }
+
+            if let Ok(mut samples) = context
+                .world
+                .get::<&mut FrameSamples>(context.render_target_entity)
+            {
+                if let Some(frame_time) = frame_time {
+                    samples.push(FrameSample {
+                        frame_time,
+                        record_time: Instant::now().duration_since(now),
+                    });
+                }
+            }
         }
         else {
             tracing::warn!("entity with RenderTarget component is missing other camera components");
@@ -282,24 +368,231 @@ pub trait CreateRender3dPipeline {
 }
 
 pub trait Render3dPipeline {
+    /// Prepares the frame, before any render pass begins.
+    ///
+    /// Pipelines can upload their instances here and record compute passes,
+    /// e.g. for [`GpuCulling`](super::culling::GpuCulling).
+    fn prepare(&mut self, context: &mut Render3dPrepareContext) {
+        let _ = context;
+    }
+
     fn render(&mut self, context: &mut Render3dPipelineContext);
+
+    /// Renders only the depth of opaque objects, in the depth pre-pass.
+    ///
+    /// If the pre-pass is enabled, this is called before
+    /// [`render`](Self::render), with a render pass that only has a depth
+    /// attachment. Opaque pipelines that draw in the pre-pass must use a depth
+    /// comparison that passes for equal depths, i.e.
+    /// [`DepthTexture::COMPARE_EQUAL`].
+    fn render_depth(&mut self, context: &mut Render3dPipelineContext) {
+        let _ = context;
+    }
+
+    /// Renders transparent objects. This is called after
+    /// [`render`](Self::render) was called, so that all opaque objects have
+    /// been drawn.
+    ///
+    /// Transparent objects should be drawn back-to-front and without writing
+    /// to the depth buffer.
+    fn render_transparent(&mut self, context: &mut Render3dPipelineContext) {
+        let _ = context;
+    }
+}
+
+/// Settings for all [`Render3dPass`]es.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Render3dSettings {
+    /// Render the depth of opaque objects before shading them, so that only
+    /// the closest fragments are shaded. This helps with scenes with a lot of
+    /// overdraw.
+    pub depth_prepass: bool,
+}
+
+/// Timings of the [`Render3dPass`] that renders to this entity's render
+/// target, separately with and without the depth pre-pass, so they can be
+/// compared.
+///
+/// WebGL doesn't support timestamp queries, so the timings are measured on the
+/// CPU. The frame time only reflects the cost of rendering, if the GPU is the
+/// bottleneck.
+#[derive(Clone, Debug, Default)]
+pub struct Render3dTimings {
+    pub with_depth_prepass: Option<PassTimings>,
+    pub without_depth_prepass: Option<PassTimings>,
+}
+
+impl Render3dTimings {
+    /// Weight of a new measurement in the moving averages.
+    const SMOOTHING: f32 = 0.05;
+
+    fn push(&mut self, depth_prepass: bool, frame_time: Option<Duration>, record_time: Duration) {
+        let timings = if depth_prepass {
+            &mut self.with_depth_prepass
+        }
+        else {
+            &mut self.without_depth_prepass
+        };
+
+        let average = |average: Duration, value: Duration| {
+            average.mul_f32(1.0 - Self::SMOOTHING) + value.mul_f32(Self::SMOOTHING)
+        };
+
+        match timings {
+            Some(timings) => {
+                if let Some(frame_time) = frame_time {
+                    timings.frame_time =
+                        Some(timings.frame_time.map_or(frame_time, |average_frame_time| {
+                            average(average_frame_time, frame_time)
+                        }));
+                }
+                timings.record_time = average(timings.record_time, record_time);
+            }
+            None => {
+                *timings = Some(PassTimings {
+                    frame_time,
+                    record_time,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PassTimings {
+    /// Moving average of the time between frames.
+    pub frame_time: Option<Duration>,
+
+    /// Moving average of the time it took to record the render passes.
+    pub record_time: Duration,
+}
+
+/// Unsmoothed timings of every frame rendered to this entity's render target,
+/// e.g. for stress tests.
+#[derive(Clone, Debug, Default)]
+pub struct FrameSamples {
+    pub samples: Vec<FrameSample>,
+}
+
+impl FrameSamples {
+    fn push(&mut self, sample: FrameSample) {
+        self.samples.push(sample);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSample {
+    /// Time since the previous frame.
+    pub frame_time: Duration,
+
+    /// Time it took to record the render passes.
+    pub record_time: Duration,
 }
 
 #[derive(Debug)]
 pub struct CreateRender3dPipelineContext<'a> {
     pub backend: &'a Backend,
     pub surface_format: wgpu::TextureFormat,
+
+    /// Format of the emissive target, if the pass has one. Use
+    /// [`color_targets`](Self::color_targets) to create matching color
+    /// targets.
+    pub emissive_format: Option<wgpu::TextureFormat>,
+
     pub depth_texture_format: wgpu::TextureFormat,
     pub camera_bind_group_layout: &'a wgpu::BindGroupLayout,
     pub light_bind_group_layout: &'a wgpu::BindGroupLayout,
 }
 
+impl<'a> CreateRender3dPipelineContext<'a> {
+    /// Color targets of a pipeline's fragment state: the surface, and the
+    /// emissive target if the pass has one.
+    ///
+    /// If `emissive` is set, the shader must write the emissive light that
+    /// blooms to `@location(1)`. Otherwise the emissive target isn't written
+    /// to.
+    pub fn color_targets(
+        &self,
+        blend: wgpu::BlendState,
+        emissive: bool,
+    ) -> Vec<Option<wgpu::ColorTargetState>> {
+        let mut targets = vec![Some(wgpu::ColorTargetState {
+            format: self.surface_format,
+            blend: Some(blend),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+        if let Some(format) = self.emissive_format {
+            targets.push(Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: if emissive {
+                    wgpu::ColorWrites::ALL
+                }
+                else {
+                    wgpu::ColorWrites::empty()
+                },
+            }));
+        }
+        targets
+    }
+
+    /// Checks the bindings of a 3D pipeline's shader, which uses the material
+    /// bind group as group 0, and the camera and light bind groups as groups
+    /// 1 and 2.
+    pub fn check_shader_bindings(
+        &self,
+        label: &str,
+        source: &str,
+        material_bind_group_layout: &BindGroupLayoutBuilder,
+    ) -> Result<(), ShaderBindingError> {
+        check_shader_bindings(
+            label,
+            source,
+            &[
+                material_bind_group_layout,
+                &camera_bind_group_layout_builder(),
+                &light::bind_group_layout_builder(),
+            ],
+        )
+    }
+}
+
+fn camera_bind_group_layout_builder() -> BindGroupLayoutBuilder {
+    BindGroupLayoutBuilder::default().with_uniform_buffer(wgpu::ShaderStages::VERTEX_FRAGMENT)
+}
+
+// todo: impl Debug
+pub struct Render3dPrepareContext<'a> {
+    pub backend: &'a Backend,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+
+    /// View frustum of the camera, for culling.
+    pub frustum: Frustum,
+
+    pub camera_position: Point3<f32>,
+
+    /// Layers the camera sees. Entities on other layers must not be drawn.
+    pub camera_layers: RenderLayers,
+
+    pub world: &'a hecs::World,
+    pub resources: &'a mut Resources,
+}
+
 // todo: impl Debug
 pub struct Render3dPipelineContext<'a> {
     pub backend: &'a Backend,
     pub render_pass: &'a mut wgpu::RenderPass<'a>,
     pub camera_bind_group: &'a wgpu::BindGroup,
     pub light_bind_group: &'a wgpu::BindGroup,
+
+    /// View frustum of the camera, for culling.
+    pub frustum: Frustum,
+
+    pub camera_position: Point3<f32>,
+
+    /// Layers the camera sees. Entities on other layers must not be drawn.
+    pub camera_layers: RenderLayers,
+
     pub world: &'a hecs::World,
     pub resources: &'a mut Resources,
 }
@@ -315,26 +608,61 @@ impl<'a> Render3dPipelineContext<'a> {
             .set_bind_group(bind_group_index, &self.light_bind_group, &[]);
     }
 
+    /// Batches all entities with a [`Mesh`] and a [`Material<M>`].
+    ///
+    /// Entities whose material is still loading are drawn with
+    /// `fallback_material`. Entities with a transparent material are put into
+    /// `transparent_queue` instead. Entities outside of the view frustum are
+    /// skipped, unless they have the [`NoFrustumCulling`] component.
     pub fn batch_meshes_with_material<M: PipelineMaterial, I: Pod>(
         &mut self,
         draw_batcher: &mut DrawBatcher<MeshMaterialPairKey, MeshMaterialPair<M>, I>,
+        transparent_queue: &mut TransparentQueue<TransparentMeshMaterialPair<M>, I>,
         material_bind_group_layout: &wgpu::BindGroupLayout,
+        fallback_material: &mut Material<M>,
         make_instance: impl Fn(&GlobalTransform, &M) -> I,
     ) {
         tracing::trace!("batching");
 
-        let mut render_entities = self
-            .world
-            .query::<(&GlobalTransform, &mut Mesh, &mut Material<M>)>();
+        let mut render_entities = self.world.query::<(
+            &GlobalTransform,
+            &mut Mesh,
+            Option<&mut Material<M>>,
+            Option<&Load<Material<M>>>,
+            Option<&RenderLayers>,
+            Option<&NoFrustumCulling>,
+        )>();
 
         let gpu_resource_cache = self
             .resources
             .get_mut_or_insert_default::<GpuResourceCache>();
 
-        for (_entity, (transform, mesh, material)) in render_entities.iter() {
+        for (_entity, (transform, mesh, material, loading, layers, no_culling)) in
+            render_entities.iter()
+        {
+            if !self.camera_layers.sees(layers) {
+                continue;
+            }
+
+            if no_culling.is_none() {
+                if let Some(aabb) = mesh.aabb() {
+                    let aabb = aabb.transform(&transform.model_matrix.to_homogeneous());
+                    if !self.frustum.contains_aabb(&aabb) {
+                        continue;
+                    }
+                }
+            }
+
             // todo: handle errors
 
+            let material = match (material, loading) {
+                (Some(material), _) => material,
+                (None, Some(_)) => &mut *fallback_material,
+                (None, None) => continue,
+            };
+
             let instance = make_instance(transform, &material.cpu);
+            let blend_mode = material.cpu.blend_mode();
 
             let Ok(mesh_gpu) = mesh.gpu(&self.backend, gpu_resource_cache)
             else {
@@ -350,6 +678,20 @@ impl<'a> Render3dPipelineContext<'a> {
                 continue;
             };
 
+            if blend_mode.is_transparent() {
+                let position = transform.model_matrix.transform_point(&Point3::origin());
+                transparent_queue.push(
+                    (position - self.camera_position).norm_squared(),
+                    TransparentMeshMaterialPair {
+                        mesh: mesh_gpu.clone(),
+                        material: material_gpu.clone(),
+                        blend_mode,
+                    },
+                    instance,
+                );
+                continue;
+            }
+
             draw_batcher.push(
                 MeshMaterialPairKey {
                     mesh: mesh_gpu.get().id(),
@@ -373,7 +715,40 @@ impl<'a> Render3dPipelineContext<'a> {
         vertex_buffer_slot: u32,
         material_bind_group_index: u32,
     ) {
-        if let Some(prepared_batch) = draw_batcher.prepare(self.backend) {
+        let prepared_batch = draw_batcher.prepare(self.backend);
+        self.draw_prepared_batch(
+            prepared_batch,
+            instance_buffer_slot,
+            vertex_buffer_slot,
+            material_bind_group_index,
+        );
+    }
+
+    /// Draws the batch that was prepared last again, e.g. the batch of the
+    /// depth pre-pass in the main pass.
+    pub fn draw_prepared_meshes_with_materials<M: PipelineMaterial, I: Pod>(
+        &mut self,
+        draw_batcher: &DrawBatcher<MeshMaterialPairKey, MeshMaterialPair<M>, I>,
+        instance_buffer_slot: u32,
+        vertex_buffer_slot: u32,
+        material_bind_group_index: u32,
+    ) {
+        self.draw_prepared_batch(
+            draw_batcher.prepared(),
+            instance_buffer_slot,
+            vertex_buffer_slot,
+            material_bind_group_index,
+        );
+    }
+
+    fn draw_prepared_batch<M>(
+        &mut self,
+        prepared_batch: Option<PreparedBatch<MeshMaterialPair<M>>>,
+        instance_buffer_slot: u32,
+        vertex_buffer_slot: u32,
+        material_bind_group_index: u32,
+    ) {
+        if let Some(prepared_batch) = prepared_batch {
             self.render_pass
                 .set_vertex_buffer(instance_buffer_slot, prepared_batch.instance_buffer);
 
@@ -390,8 +765,58 @@ impl<'a> Render3dPipelineContext<'a> {
                     &material.bind_group,
                     &[],
                 );
+                self.render_pass.draw_indexed(
+                    0..mesh.num_indices as u32,
+                    0,
+                    batch_item.range.clone(),
+                );
+            }
+        }
+    }
+
+    /// Draws the transparent objects queued by
+    /// [`batch_meshes_with_material`](Self::batch_meshes_with_material),
+    /// back-to-front.
+    ///
+    /// `pipeline` returns the render pipeline for a blend mode. It's only set
+    /// when the blend mode changes.
+    pub fn draw_transparent_meshes_with_materials<'p, M: PipelineMaterial, I: Pod>(
+        &mut self,
+        transparent_queue: &mut TransparentQueue<TransparentMeshMaterialPair<M>, I>,
+        instance_buffer_slot: u32,
+        vertex_buffer_slot: u32,
+        material_bind_group_index: u32,
+        pipeline: impl Fn(BlendMode) -> &'p wgpu::RenderPipeline,
+    ) {
+        if let Some(prepared_batch) = transparent_queue.prepare(self.backend) {
+            self.render_pass
+                .set_vertex_buffer(instance_buffer_slot, prepared_batch.instance_buffer);
+
+            let mut current_blend_mode = None;
+            for batch_item in prepared_batch {
+                let blend_mode = batch_item.value.blend_mode;
+                if current_blend_mode != Some(blend_mode) {
+                    self.render_pass.set_pipeline(pipeline(blend_mode));
+                    current_blend_mode = Some(blend_mode);
+                }
+
+                let mesh = batch_item.value.mesh.get();
+                let material = batch_item.value.material.get();
+
+                self.render_pass
+                    .set_vertex_buffer(vertex_buffer_slot, mesh.vertex_buffer.slice(..));
                 self.render_pass
-                    .draw_indexed(0..mesh.num_indices as u32, 0, batch_item.range);
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                self.render_pass.set_bind_group(
+                    material_bind_group_index,
+                    &material.bind_group,
+                    &[],
+                );
+                self.render_pass.draw_indexed(
+                    0..mesh.num_indices as u32,
+                    0,
+                    batch_item.range.clone(),
+                );
             }
         }
     }
@@ -406,6 +831,16 @@ pub struct DepthTexture {
 impl DepthTexture {
     pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
+    /// Depth of the far plane, to which the depth texture is cleared. We use
+    /// reverse-Z, see [`CameraProjection::render_matrix`].
+    pub const CLEAR: f32 = 0.0;
+
+    /// Depth comparison for pipelines that test against the depth texture.
+    pub const COMPARE: wgpu::CompareFunction = wgpu::CompareFunction::Greater;
+
+    /// Like [`COMPARE`](Self::COMPARE), but also passes for equal depths.
+    pub const COMPARE_EQUAL: wgpu::CompareFunction = wgpu::CompareFunction::GreaterEqual;
+
     pub fn new(backend: &Backend, surface_size: SurfaceSize) -> Self {
         let size = wgpu::Extent3d {
             width: surface_size.width,
@@ -456,7 +891,7 @@ pub struct CameraUniform {
 impl CameraUniform {
     fn from_camera(camera: &CameraProjection, transform: &GlobalTransform) -> Self {
         Self {
-            view_projection: (camera.projection_matrix.as_matrix()
+            view_projection: (camera.render_matrix()
                 * transform.model_matrix.inverse().to_homogeneous())
             .as_slice()
             .try_into()
@@ -470,7 +905,7 @@ impl CameraUniform {
                 .try_into()
                 .unwrap(),
             _padding1: Default::default(),
-            aspect: camera.projection_matrix.aspect(),
+            aspect: camera.aspect,
             time: 0.0,
             _padding2: Default::default(),
         }
@@ -482,48 +917,6 @@ impl CameraUniform {
     }
 }
 
-pub const MAX_POINT_LIGHTS: usize = 16;
-
-#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
-#[repr(C)]
-struct LightUniform {
-    pub ambient_light: [f32; 3],
-    pub num_point_lights: u32,
-    pub point_lights: [PointLightUniform; MAX_POINT_LIGHTS],
-}
-
-impl LightUniform {
-    pub fn set_ambient_color(&mut self, color: Srgb<f32>) {
-        self.ambient_light = color.as_array3();
-    }
-
-    pub fn add_point_light(&mut self, position: Point3<f32>, color: Srgb<f32>) -> bool {
-        let index: usize = self.num_point_lights.try_into().unwrap();
-        if index < MAX_POINT_LIGHTS {
-            self.point_lights[index] = PointLightUniform {
-                position: position.coords.as_slice().try_into().unwrap(),
-                _padding0: 0,
-                color: color.as_array3(),
-                _padding1: 0,
-            };
-            self.num_point_lights += 1;
-            true
-        }
-        else {
-            false
-        }
-    }
-}
-
-#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
-#[repr(C)]
-struct PointLightUniform {
-    pub position: [f32; 3],
-    _padding0: u32,
-    pub color: [f32; 3],
-    _padding1: u32,
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MeshMaterialPairKey {
     pub mesh: GpuMeshId,
@@ -535,3 +928,10 @@ pub struct MeshMaterialPair<M> {
     pub mesh: Arc<ThreadLocalCell<GpuMesh>>,
     pub material: Arc<ThreadLocalCell<GpuMaterial<M>>>,
 }
+
+#[derive(Clone, Debug)]
+pub struct TransparentMeshMaterialPair<M> {
+    pub mesh: Arc<ThreadLocalCell<GpuMesh>>,
+    pub material: Arc<ThreadLocalCell<GpuMaterial<M>>>,
+    pub blend_mode: BlendMode,
+}
@@ -8,10 +8,14 @@ use crate::{
     },
     graphics::{
         camera::{
+            CameraProjection,
             DontRender,
             RenderTarget,
             RenderTargetInner,
         },
+        recorder::FrameRecorder,
+        screenshot::TakeScreenshot,
+        watchdog::Failure,
         Backend,
         Surface,
         SurfaceSize,
@@ -20,17 +24,54 @@ use crate::{
 };
 
 pub fn rendering_system(system_context: &mut SystemContext) {
+    let mut target_sizes = vec![];
+
     let mut render_targets = system_context
         .world
-        .query::<(&RenderTarget, &mut AttachedRenderPass, Option<&Label>)>()
+        .query::<(
+            &RenderTarget,
+            &mut AttachedRenderPass,
+            Option<&mut TakeScreenshot>,
+            Option<&mut FrameRecorder>,
+            Option<&Label>,
+        )>()
         .without::<&DontRender>();
 
-    for (render_target_entity, (render_target, render_pass, label)) in render_targets.iter() {
+    for (
+        render_target_entity,
+        (render_target, render_pass, take_screenshot, frame_recorder, label),
+    ) in render_targets.iter()
+    {
+        let capture_screenshot = |backend: &Backend, texture: &wgpu::Texture| {
+            if let Some(take_screenshot) = take_screenshot {
+                take_screenshot.capture(backend, texture);
+                system_context
+                    .command_buffer
+                    .remove_one::<TakeScreenshot>(render_target_entity);
+            }
+            if let Some(frame_recorder) = frame_recorder {
+                frame_recorder.capture(backend, texture);
+            }
+        };
+
         match render_target.inner.get() {
             RenderTargetInner::Surface { backend, surface } => {
-                let surface_texture = surface
-                    .get_current_texture()
-                    .expect("could not get target texture");
+                let surface_texture = match surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    Err(wgpu::SurfaceError::Timeout | wgpu::SurfaceError::Outdated) => {
+                        tracing::debug!(?label, "surface texture not available. skipping frame");
+                        continue;
+                    }
+                    Err(error) => {
+                        tracing::error!(?label, %error, "could not get surface texture");
+                        backend.failures.report(Failure::SurfaceLost { error });
+                        continue;
+                    }
+                };
+                target_sizes.push((
+                    render_target_entity,
+                    SurfaceSize::from_texture(&surface_texture.texture),
+                ));
                 render_to_texture(
                     backend,
                     render_pass,
@@ -40,9 +81,11 @@ pub fn rendering_system(system_context: &mut SystemContext) {
                     &mut system_context.resources,
                     label,
                 );
+                capture_screenshot(backend, &surface_texture.texture);
                 surface_texture.present();
             }
             RenderTargetInner::Texture { backend, texture } => {
+                target_sizes.push((render_target_entity, SurfaceSize::from_texture(texture)));
                 render_to_texture(
                     backend,
                     render_pass,
@@ -52,9 +95,22 @@ pub fn rendering_system(system_context: &mut SystemContext) {
                     &mut system_context.resources,
                     label,
                 );
+                capture_screenshot(backend, texture);
             }
         };
     }
+    drop(render_targets);
+
+    // the render passes use the size of the render target for this frame, but
+    // e.g. picking needs the aspect ratio too.
+    for (entity, target_size) in target_sizes {
+        if let Ok(camera_projection) = system_context
+            .world
+            .query_one_mut::<&mut CameraProjection>(entity)
+        {
+            camera_projection.aspect = target_size.aspect();
+        }
+    }
 }
 
 fn render_to_texture(
@@ -80,6 +136,7 @@ fn render_to_texture(
         backend: &backend,
         encoder: &mut encoder,
         target_view: &target_view,
+        emissive_view: None,
         target_size,
         render_target_entity,
         world,
@@ -104,6 +161,10 @@ pub struct CreateRenderPassContext<'a> {
     pub backend: &'a Backend,
     pub surface_size: SurfaceSize,
     pub surface_format: wgpu::TextureFormat,
+
+    /// Format of the emissive target, if the render pass should write
+    /// emissive light for the bloom into [`RenderPassContext::emissive_view`].
+    pub emissive_format: Option<wgpu::TextureFormat>,
 }
 
 impl<'a> CreateRenderPassContext<'a> {
@@ -112,6 +173,7 @@ impl<'a> CreateRenderPassContext<'a> {
             backend: &surface.backend,
             surface_size: surface.size(),
             surface_format: surface.format(),
+            emissive_format: None,
         }
     }
 }
@@ -125,6 +187,12 @@ pub struct RenderPassContext<'a> {
     pub backend: &'a Backend,
     pub encoder: &'a mut wgpu::CommandEncoder,
     pub target_view: &'a wgpu::TextureView,
+
+    /// Emissive target, if the render pass was created with an
+    /// [`emissive_format`](CreateRenderPassContext::emissive_format). It has
+    /// the same size as the target.
+    pub emissive_view: Option<&'a wgpu::TextureView>,
+
     pub target_size: SurfaceSize,
     pub render_target_entity: hecs::Entity,
     pub world: &'a hecs::World,
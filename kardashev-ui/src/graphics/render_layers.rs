@@ -0,0 +1,78 @@
+//! Render layers, with which cameras only see some entities.
+//!
+//! Entities are on the layers of their [`RenderLayers`], or on
+//! [`RenderLayers::DEFAULT`] if they don't have one. Gizmos without
+//! [`RenderLayers`] are on [`RenderLayers::GIZMOS`] instead, so that e.g. the
+//! minimap doesn't draw map overlays. Cameras see the layers of their
+//! [`RenderLayers`], or all layers if they don't have one. An entity is only
+//! batched for a camera if they share a layer.
+
+/// Bitmask of up to 32 layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderLayers(u32);
+
+impl RenderLayers {
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// Layer of entities without [`RenderLayers`].
+    pub const DEFAULT: Self = Self::layer(0);
+
+    /// Layer of [gizmos](super::gizmo) without [`RenderLayers`], e.g. map
+    /// overlays and measurements.
+    pub const GIZMOS: Self = Self::layer(1);
+
+    /// A single layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is not less than 32.
+    pub const fn layer(layer: u32) -> Self {
+        assert!(layer < 32, "there are only 32 render layers");
+        Self(1 << layer)
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// The layers a camera sees.
+    pub fn of_camera(camera_layers: Option<&RenderLayers>) -> Self {
+        camera_layers.copied().unwrap_or(Self::ALL)
+    }
+
+    /// Whether an entity on `entity_layers` is visible to a camera that sees
+    /// these layers.
+    pub fn sees(&self, entity_layers: Option<&RenderLayers>) -> bool {
+        self.sees_or(entity_layers, Self::DEFAULT)
+    }
+
+    /// Like [`sees`](Self::sees), but entities without layers are on
+    /// `default`.
+    pub fn sees_or(&self, entity_layers: Option<&RenderLayers>, default: Self) -> bool {
+        entity_layers.copied().unwrap_or(default).intersects(*self)
+    }
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderLayers;
+
+    #[test]
+    fn cameras_see_entities_on_shared_layers() {
+        let camera = RenderLayers::of_camera(Some(&RenderLayers::DEFAULT));
+        assert!(camera.sees(None));
+        assert!(!camera.sees(Some(&RenderLayers::GIZMOS)));
+        assert!(!camera.sees_or(None, RenderLayers::GIZMOS));
+        assert!(camera.sees_or(Some(&RenderLayers::DEFAULT), RenderLayers::GIZMOS));
+
+        let camera = RenderLayers::of_camera(None);
+        assert!(camera.sees(Some(&RenderLayers::layer(31))));
+        assert!(camera.sees_or(None, RenderLayers::GIZMOS));
+    }
+}
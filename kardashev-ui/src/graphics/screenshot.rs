@@ -0,0 +1,294 @@
+use std::time::Duration;
+
+use gloo_file::{
+    Blob,
+    ObjectUrl,
+};
+use image::RgbaImage;
+use tokio::sync::oneshot;
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::js_sys::{
+    Array,
+    Object,
+    Reflect,
+    Uint8Array,
+};
+use web_sys::HtmlAnchorElement;
+
+use crate::{
+    graphics::backend::Backend,
+    utils::{
+        futures::{
+            spawn_local,
+            WorkerError,
+            WorkerPool,
+        },
+        time::sleep,
+        web_fs::{
+            self,
+            OpenOptions,
+            WebFs,
+        },
+    },
+};
+
+thread_local! {
+    /// Workers that encode PNGs.
+    static PNG_WORKERS: WorkerPool = WorkerPool::new(include_str!("png_worker.js"));
+}
+
+fn png_workers() -> WorkerPool {
+    PNG_WORKERS.with(Clone::clone)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScreenshotError {
+    #[error("render target can't be copied from")]
+    NotCopyable,
+
+    #[error("unsupported texture format: {0:?}")]
+    UnsupportedFormat(wgpu::TextureFormat),
+
+    #[error("failed to map buffer")]
+    MapBuffer(#[from] wgpu::BufferAsyncError),
+
+    #[error("render target was removed before the screenshot was taken")]
+    Cancelled,
+
+    #[error("failed to encode PNG: {message}")]
+    Encode { message: String },
+
+    #[error("failed to save screenshot")]
+    WebFs(#[from] web_fs::Error),
+
+    #[error("PNG worker failed")]
+    Worker(#[from] WorkerError),
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for ScreenshotError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// Component that requests a screenshot of the next frame rendered to the
+/// entity's [`RenderTarget`][super::camera::RenderTarget].
+///
+/// The screenshot is taken from the final image, i.e. after tone mapping. The
+/// component is removed after the screenshot was taken.
+#[derive(Debug)]
+pub struct TakeScreenshot {
+    tx: Option<oneshot::Sender<Result<Screenshot, ScreenshotError>>>,
+}
+
+impl TakeScreenshot {
+    pub fn new() -> (Self, ScreenshotReceiver) {
+        let (tx, rx) = oneshot::channel();
+        (Self { tx: Some(tx) }, ScreenshotReceiver { rx })
+    }
+
+    pub(super) fn capture(&mut self, backend: &Backend, texture: &wgpu::Texture) {
+        let Some(tx) = self.tx.take()
+        else {
+            return;
+        };
+
+        match start_capture(backend, texture) {
+            Ok(capture) => {
+                spawn_local(async move {
+                    let _ = tx.send(capture.finish().await);
+                });
+            }
+            Err(error) => {
+                let _ = tx.send(Err(error));
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScreenshotReceiver {
+    rx: oneshot::Receiver<Result<Screenshot, ScreenshotError>>,
+}
+
+impl ScreenshotReceiver {
+    pub async fn receive(self) -> Result<Screenshot, ScreenshotError> {
+        self.rx.await.unwrap_or(Err(ScreenshotError::Cancelled))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    pub image: RgbaImage,
+}
+
+impl Screenshot {
+    /// Encodes the screenshot as PNG.
+    ///
+    /// The encoding is done in a web worker, so it doesn't block the main
+    /// thread. Only copying the pixels to the worker does.
+    pub async fn to_png(&self) -> Result<Blob, ScreenshotError> {
+        let (width, height) = self.image.dimensions();
+
+        // the pixels are copied into a javascript buffer once, which is then moved to
+        // the worker.
+        let pixels = Uint8Array::from(self.image.as_raw().as_slice()).buffer();
+        let message = Object::new();
+        Reflect::set(&message, &"width".into(), &width.into())?;
+        Reflect::set(&message, &"height".into(), &height.into())?;
+        Reflect::set(&message, &"pixels".into(), &pixels)?;
+        let response = png_workers().run(&message, &Array::of1(&pixels)).await?;
+
+        let png = Reflect::get(&response, &"png".into())?;
+        if png.is_undefined() {
+            let message = Reflect::get(&response, &"error".into())?
+                .as_string()
+                .unwrap_or_default();
+            return Err(ScreenshotError::Encode { message });
+        }
+
+        Ok(png.unchecked_into::<web_sys::Blob>().into())
+    }
+
+    /// Encodes the screenshot as PNG and triggers a download in the browser.
+    pub async fn download(&self, filename: &str) -> Result<(), ScreenshotError> {
+        let png = self.to_png().await?;
+        let url = ObjectUrl::from(png);
+
+        let anchor: HtmlAnchorElement =
+            gloo_utils::document().create_element("a")?.unchecked_into();
+        anchor.set_href(&url);
+        anchor.set_download(filename);
+        anchor.click();
+
+        // give the browser some time to start the download before revoking the URL.
+        sleep(Duration::from_secs(1)).await;
+
+        Ok(())
+    }
+
+    /// Encodes the screenshot as PNG and stores it at `path` in `web_fs`.
+    pub async fn save(&self, web_fs: &WebFs, path: &str) -> Result<(), ScreenshotError> {
+        let png = self.to_png().await?;
+        let mut file = web_fs.open(path, OpenOptions::new().create(true)).await?;
+        file.write_blob(png).await?;
+        Ok(())
+    }
+
+    /// Returns a filename for the screenshot, containing the current time.
+    pub fn default_filename() -> String {
+        format!(
+            "kardashev-{}.png",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+        )
+    }
+}
+
+pub(super) struct PendingCapture {
+    buffer: wgpu::Buffer,
+    rx_mapped: oneshot::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    swap_red_blue: bool,
+}
+
+pub(super) fn start_capture(
+    backend: &Backend,
+    texture: &wgpu::Texture,
+) -> Result<PendingCapture, ScreenshotError> {
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        return Err(ScreenshotError::NotCopyable);
+    }
+
+    let swap_red_blue = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        format => return Err(ScreenshotError::UnsupportedFormat(format)),
+    };
+
+    let width = texture.width();
+    let height = texture.height();
+    let unpadded_bytes_per_row = 4 * width;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+
+    let buffer = backend.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("screenshot buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = backend
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("screenshot encoder"),
+        });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    backend.queue.submit([encoder.finish()]);
+
+    let (tx_mapped, rx_mapped) = oneshot::channel();
+    buffer
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx_mapped.send(result);
+        });
+
+    Ok(PendingCapture {
+        buffer,
+        rx_mapped,
+        width,
+        height,
+        padded_bytes_per_row,
+        swap_red_blue,
+    })
+}
+
+impl PendingCapture {
+    pub(super) async fn finish(self) -> Result<Screenshot, ScreenshotError> {
+        self.rx_mapped
+            .await
+            .map_err(|_| ScreenshotError::Cancelled)??;
+
+        let unpadded_bytes_per_row = (4 * self.width) as usize;
+        let mut data = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            for row in mapped.chunks(self.padded_bytes_per_row as usize) {
+                data.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.swap_red_blue {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        let image = RgbaImage::from_raw(self.width, self.height, data)
+            .expect("screenshot buffer has wrong size");
+
+        Ok(Screenshot { image })
+    }
+}
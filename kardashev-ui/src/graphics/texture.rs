@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    fmt::Display,
+    sync::Arc,
+};
 
 use gloo_file::Blob;
 use image::RgbaImage;
@@ -30,17 +33,18 @@ use crate::{
     },
     graphics::{
         backend::PerBackend,
+        builtin,
+        mipmap::mip_level_count,
         utils::{
             GpuResourceCache,
+            TextureEdgeModeExt,
+            TextureFilterExt,
             TextureFormatExt,
         },
     },
     utils::{
         thread_local_cell::ThreadLocalCell,
-        web_fs::{
-            self,
-            OpenOptions,
-        },
+        web_fs,
     },
 };
 
@@ -85,6 +89,16 @@ impl Texture {
             }
         })
     }
+
+    pub fn with_asset_id(mut self, asset_id: AssetId) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    pub fn with_label(mut self, label: impl Display) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
 }
 
 impl From<RgbaImage> for Texture {
@@ -95,6 +109,8 @@ impl From<RgbaImage> for Texture {
             cpu: Some(Arc::new(CpuTexture {
                 image,
                 format: dist::TextureFormat::Rgba8UnormSrgb,
+                sampler: None,
+                mipmaps: false,
             })),
             gpu: PerBackend::default(),
         }
@@ -144,6 +160,14 @@ impl LoadFromAsset for Texture {
             gpu: PerBackend::default(),
         })
     }
+
+    fn fallback() -> Option<Self> {
+        Some(builtin::missing_texture())
+    }
+
+    fn reload_args(&self) -> Option<()> {
+        Some(())
+    }
 }
 
 pub(super) async fn load_texture_from_server(
@@ -151,9 +175,7 @@ pub(super) async fn load_texture_from_server(
     asset_store: &AssetStoreGuard,
     client: &AssetClient,
 ) -> Result<Arc<CpuTexture>, TextureError> {
-    let mut file = asset_store
-        .open(&dist.image, &OpenOptions::new().create(true))
-        .await?;
+    let mut file = asset_store.open_cached(&dist.image).await?;
 
     let mut data = None;
 
@@ -189,6 +211,8 @@ pub(super) async fn load_texture_from_server(
     Ok::<_, TextureError>(Arc::new(CpuTexture {
         image,
         format: dist.format,
+        sampler: Some(SamplerSettings::from_dist(dist)),
+        mipmaps: dist.mipmaps,
     }))
 }
 
@@ -197,40 +221,131 @@ fn load_texture_to_gpu(
     label: Option<&str>,
     backend: &Backend,
 ) -> Result<GpuTexture, TextureError> {
-    let image = &texture.image;
-
-    let image_size = image.dimensions();
-    let texture_size = wgpu::Extent3d {
-        width: image_size.0,
-        height: image_size.1,
-        depth_or_array_layers: 1,
-    };
-
-    let texture = backend.device.create_texture_with_data(
-        &backend.queue,
-        &wgpu::TextureDescriptor {
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: texture.format.as_wgpu(),
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            label,
-            view_formats: &[],
-        },
-        wgpu::util::TextureDataOrder::default(),
-        image.as_raw(),
+    let mut gpu = GpuTexture::from_rgba_image(
+        &texture.image,
+        texture.format.as_wgpu(),
+        label,
+        texture.mipmaps,
+        backend,
     );
 
-    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    if let Some(sampler) = &texture.sampler {
+        gpu.sampler = Some(backend.device.create_sampler(&sampler.descriptor(
+            label,
+            gpu.texture.mip_level_count(),
+            backend.device.features(),
+        )));
+    }
 
-    Ok(GpuTexture { texture, view })
+    Ok(gpu)
 }
 
 #[derive(Clone, Debug)]
 pub struct CpuTexture {
     image: RgbaImage,
     format: dist::TextureFormat,
+    sampler: Option<SamplerSettings>,
+    mipmaps: bool,
+}
+
+/// Sampler settings for a texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SamplerSettings {
+    pub u_edge_mode: wgpu::AddressMode,
+    pub v_edge_mode: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            u_edge_mode: wgpu::AddressMode::ClampToEdge,
+            v_edge_mode: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            anisotropy: 1,
+        }
+    }
+}
+
+impl SamplerSettings {
+    pub fn from_dist(dist: &dist::Texture) -> Self {
+        let default = Self::default();
+        Self {
+            u_edge_mode: dist
+                .u_edge_mode
+                .map_or(default.u_edge_mode, |mode| mode.as_wgpu()),
+            v_edge_mode: dist
+                .v_edge_mode
+                .map_or(default.v_edge_mode, |mode| mode.as_wgpu()),
+            mag_filter: dist
+                .mag_filter
+                .map_or(default.mag_filter, |filter| filter.as_wgpu()),
+            min_filter: dist
+                .min_filter
+                .map_or(default.min_filter, |filter| filter.as_wgpu()),
+            mipmap_filter: dist
+                .mipmap_filter
+                .map_or(default.mipmap_filter, |filter| filter.as_wgpu()),
+            anisotropy: dist.anisotropy.unwrap_or(default.anisotropy),
+        }
+    }
+
+    /// Creates the descriptor for a sampler on a device with `features`.
+    ///
+    /// Textures are clamped to a transparent border, if the device supports it,
+    /// and to their edge otherwise.
+    pub fn descriptor<'a>(
+        &self,
+        label: Option<&'a str>,
+        mip_level_count: u32,
+        features: wgpu::Features,
+    ) -> wgpu::SamplerDescriptor<'a> {
+        // anisotropic filtering is only valid if all filters are linear.
+        let all_linear = self.mag_filter == wgpu::FilterMode::Linear
+            && self.min_filter == wgpu::FilterMode::Linear
+            && self.mipmap_filter == wgpu::FilterMode::Linear;
+        let anisotropy_clamp = if all_linear {
+            self.anisotropy.clamp(1, 16)
+        }
+        else {
+            1
+        };
+
+        let clamp_to_border = features.contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER);
+        let address_mode = |mode| {
+            match mode {
+                wgpu::AddressMode::ClampToBorder if !clamp_to_border => {
+                    wgpu::AddressMode::ClampToEdge
+                }
+                mode => mode,
+            }
+        };
+        let address_mode_u = address_mode(self.u_edge_mode);
+        let address_mode_v = address_mode(self.v_edge_mode);
+        let border_color = (address_mode_u == wgpu::AddressMode::ClampToBorder
+            || address_mode_v == wgpu::AddressMode::ClampToBorder)
+            .then_some(wgpu::SamplerBorderColor::TransparentBlack);
+
+        wgpu::SamplerDescriptor {
+            label,
+            address_mode_u,
+            address_mode_v,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: mip_level_count as f32,
+            compare: None,
+            anisotropy_clamp,
+            border_color,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -247,6 +362,9 @@ pub enum TextureError {
 pub struct GpuTexture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
+    /// Sampler for this texture. If this is `None`, the material's default
+    /// sampler is used.
+    pub sampler: Option<wgpu::Sampler>,
 }
 
 impl GpuTexture {
@@ -257,6 +375,84 @@ impl GpuTexture {
         }
     }
 
+    pub fn from_rgba_image(
+        image: &RgbaImage,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        mipmaps: bool,
+        backend: &Backend,
+    ) -> Self {
+        let image_size = image.dimensions();
+        let texture_size = wgpu::Extent3d {
+            width: image_size.0,
+            height: image_size.1,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = if mipmaps {
+            let texture = backend.device.create_texture(&wgpu::TextureDescriptor {
+                size: texture_size,
+                mip_level_count: mip_level_count(image_size.0, image_size.1),
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                label,
+                view_formats: &[],
+            });
+
+            backend.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image.as_raw(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * image_size.0),
+                    rows_per_image: Some(image_size.1),
+                },
+                texture_size,
+            );
+
+            backend
+                .mipmap_generators
+                .get(backend, format)
+                .generate(backend, &texture);
+
+            texture
+        }
+        else {
+            backend.device.create_texture_with_data(
+                &backend.queue,
+                &wgpu::TextureDescriptor {
+                    size: texture_size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    label,
+                    view_formats: &[],
+                },
+                wgpu::util::TextureDataOrder::default(),
+                image.as_raw(),
+            )
+        };
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        GpuTexture {
+            texture,
+            view,
+            sampler: None,
+        }
+    }
+
     pub fn color1x1<C: palette::stimulus::IntoStimulus<u8>>(
         color: Srgba<C>,
         format: wgpu::TextureFormat,
@@ -290,7 +486,11 @@ impl GpuTexture {
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        GpuTexture { texture, view }
+        GpuTexture {
+            texture,
+            view,
+            sampler: None,
+        }
     }
 }
 
@@ -7,6 +7,8 @@ use std::{
 use bytemuck::Pod;
 use kardashev_protocol::assets::{
     AssetId,
+    TextureEdgeMode,
+    TextureFilter,
     TextureFormat,
     Vertex,
 };
@@ -281,6 +283,11 @@ impl<T> InstanceBuffer<T> {
     pub fn is_empty(&self) -> bool {
         self.staging.is_empty()
     }
+
+    /// Instances that haven't been uploaded yet.
+    pub fn staged(&self) -> &[T] {
+        &self.staging
+    }
 }
 
 impl<T: Pod> InstanceBuffer<T> {
@@ -294,34 +301,64 @@ impl<T: Pod> InstanceBuffer<T> {
     }
 }
 
+/// Builds a bind group layout from a typed description of its entries.
+///
+/// The bindings are numbered in the order the entries are added. The entries
+/// can be checked against the shader with [`ShaderBindings`].
 #[derive(Clone, Debug, Default)]
-pub struct MaterialBindGroupLayoutBuilder {
+pub struct BindGroupLayoutBuilder {
     entries: Vec<wgpu::BindGroupLayoutEntry>,
 }
 
-impl MaterialBindGroupLayoutBuilder {
-    pub fn push_view_and_sampler(&mut self) {
-        let index = self.entries.len() as u32;
-
+impl BindGroupLayoutBuilder {
+    pub fn with_entry(mut self, visibility: wgpu::ShaderStages, ty: wgpu::BindingType) -> Self {
         self.entries.push(wgpu::BindGroupLayoutEntry {
-            binding: index,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Texture {
+            binding: self.entries.len() as u32,
+            visibility,
+            ty,
+            count: None,
+        });
+        self
+    }
+
+    pub fn with_uniform_buffer(self, visibility: wgpu::ShaderStages) -> Self {
+        self.with_entry(
+            visibility,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        )
+    }
+
+    /// Adds a filterable 2D texture.
+    pub fn with_texture(self, visibility: wgpu::ShaderStages) -> Self {
+        self.with_entry(
+            visibility,
+            wgpu::BindingType::Texture {
                 multisampled: false,
                 view_dimension: wgpu::TextureViewDimension::D2,
                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
             },
-            count: None,
-        });
+        )
+    }
 
-        self.entries.push(wgpu::BindGroupLayoutEntry {
-            binding: index + 1,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            // This should match the filterable field of the
-            // corresponding Texture entry above.
-            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            count: None,
-        });
+    pub fn with_sampler(self, visibility: wgpu::ShaderStages) -> Self {
+        self.with_entry(
+            visibility,
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        )
+    }
+
+    /// Adds a filterable 2D texture, followed by its sampler, e.g. for a
+    /// material.
+    pub fn with_view_and_sampler(self, visibility: wgpu::ShaderStages) -> Self {
+        self.with_texture(visibility).with_sampler(visibility)
+    }
+
+    pub fn entries(&self) -> &[wgpu::BindGroupLayoutEntry] {
+        &self.entries
     }
 
     pub fn build(&self, device: &wgpu::Device, label: Option<&str>) -> wgpu::BindGroupLayout {
@@ -332,6 +369,286 @@ impl MaterialBindGroupLayoutBuilder {
     }
 }
 
+/// The resource bindings of a WGSL shader, found by reflection with naga.
+///
+/// This is used to check that the bind group layouts of a pipeline match
+/// what the shader expects. wgpu also validates this, but its errors don't
+/// tell which binding is wrong.
+#[derive(Clone, Debug)]
+pub struct ShaderBindings {
+    label: String,
+    bindings: Vec<ShaderBinding>,
+}
+
+#[derive(Clone, Debug)]
+struct ShaderBinding {
+    name: String,
+    group: u32,
+    binding: u32,
+    ty: ShaderBindingType,
+
+    /// Stages of the entry points that use the binding.
+    stages: wgpu::ShaderStages,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ShaderBindingType {
+    Uniform,
+    Storage {
+        read_only: bool,
+    },
+    Texture {
+        view_dimension: wgpu::TextureViewDimension,
+        sample_kind: Option<naga::ScalarKind>,
+        multisampled: bool,
+    },
+    StorageTexture {
+        view_dimension: wgpu::TextureViewDimension,
+    },
+    Sampler {
+        comparison: bool,
+    },
+    Other,
+}
+
+impl ShaderBindings {
+    pub fn reflect(label: impl Into<String>, source: &str) -> Result<Self, ShaderBindingError> {
+        let label = label.into();
+
+        let module = naga::front::wgsl::parse_str(source).map_err(|error| {
+            ShaderBindingError::Parse {
+                shader: label.clone(),
+                message: error.emit_to_string(source),
+            }
+        })?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|error| {
+            ShaderBindingError::Validate {
+                shader: label.clone(),
+                message: error.emit_to_string(source),
+            }
+        })?;
+
+        let bindings = module
+            .global_variables
+            .iter()
+            .filter_map(|(handle, global)| {
+                let resource_binding = global.binding.as_ref()?;
+
+                let mut stages = wgpu::ShaderStages::NONE;
+                for (index, entry_point) in module.entry_points.iter().enumerate() {
+                    if !info.get_entry_point(index)[handle].is_empty() {
+                        stages |= match entry_point.stage {
+                            naga::ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                            naga::ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                            naga::ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                        };
+                    }
+                }
+
+                Some(ShaderBinding {
+                    name: global.name.clone().unwrap_or_default(),
+                    group: resource_binding.group,
+                    binding: resource_binding.binding,
+                    ty: ShaderBindingType::from_naga(global.space, &module.types[global.ty].inner),
+                    stages,
+                })
+            })
+            .collect();
+
+        Ok(Self { label, bindings })
+    }
+
+    /// Checks that the bindings match the bind group layouts, which are
+    /// given in the order of their groups.
+    ///
+    /// Entries of the layouts that the shader doesn't use are fine.
+    pub fn check(&self, groups: &[&BindGroupLayoutBuilder]) -> Result<(), ShaderBindingError> {
+        for binding in &self.bindings {
+            let error = |problem| {
+                ShaderBindingError::Mismatch {
+                    shader: self.label.clone(),
+                    name: binding.name.clone(),
+                    group: binding.group,
+                    binding: binding.binding,
+                    problem,
+                }
+            };
+
+            let entry = groups
+                .get(binding.group as usize)
+                .and_then(|group| {
+                    group
+                        .entries()
+                        .iter()
+                        .find(|entry| entry.binding == binding.binding)
+                })
+                .ok_or_else(|| error("missing in the bind group layout".to_owned()))?;
+
+            if !binding.ty.matches(&entry.ty) {
+                return Err(error(format!(
+                    "shader expects {:?}, but the layout has {:?}",
+                    binding.ty, entry.ty
+                )));
+            }
+
+            if !entry.visibility.contains(binding.stages) {
+                return Err(error(format!(
+                    "used in {:?}, but only visible in {:?}",
+                    binding.stages, entry.visibility
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ShaderBindingType {
+    fn from_naga(space: naga::AddressSpace, ty: &naga::TypeInner) -> Self {
+        match (space, ty) {
+            (naga::AddressSpace::Uniform, _) => Self::Uniform,
+            (naga::AddressSpace::Storage { access }, _) => {
+                Self::Storage {
+                    read_only: !access.contains(naga::StorageAccess::STORE),
+                }
+            }
+            (
+                naga::AddressSpace::Handle,
+                naga::TypeInner::Image {
+                    dim,
+                    arrayed,
+                    class,
+                },
+            ) => {
+                let view_dimension = match (dim, arrayed) {
+                    (naga::ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+                    (naga::ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+                    (naga::ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+                    (naga::ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+                    (naga::ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+                    (naga::ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+                };
+                match class {
+                    naga::ImageClass::Sampled { kind, multi } => {
+                        Self::Texture {
+                            view_dimension,
+                            sample_kind: Some(*kind),
+                            multisampled: *multi,
+                        }
+                    }
+                    naga::ImageClass::Depth { multi } => {
+                        Self::Texture {
+                            view_dimension,
+                            sample_kind: None,
+                            multisampled: *multi,
+                        }
+                    }
+                    naga::ImageClass::Storage { .. } => Self::StorageTexture { view_dimension },
+                }
+            }
+            (naga::AddressSpace::Handle, naga::TypeInner::Sampler { comparison }) => {
+                Self::Sampler {
+                    comparison: *comparison,
+                }
+            }
+            _ => Self::Other,
+        }
+    }
+
+    fn matches(&self, ty: &wgpu::BindingType) -> bool {
+        match (self, ty) {
+            (
+                Self::Uniform,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    ..
+                },
+            ) => true,
+            (
+                Self::Storage { read_only },
+                wgpu::BindingType::Buffer {
+                    ty:
+                        wgpu::BufferBindingType::Storage {
+                            read_only: layout_read_only,
+                        },
+                    ..
+                },
+            ) => read_only == layout_read_only,
+            (
+                Self::Texture {
+                    view_dimension,
+                    sample_kind,
+                    multisampled,
+                },
+                wgpu::BindingType::Texture {
+                    sample_type,
+                    view_dimension: layout_view_dimension,
+                    multisampled: layout_multisampled,
+                },
+            ) => {
+                let sample_type_matches = match (sample_kind, sample_type) {
+                    (Some(naga::ScalarKind::Float), wgpu::TextureSampleType::Float { .. }) => true,
+                    (Some(naga::ScalarKind::Sint), wgpu::TextureSampleType::Sint) => true,
+                    (Some(naga::ScalarKind::Uint), wgpu::TextureSampleType::Uint) => true,
+                    (None, wgpu::TextureSampleType::Depth) => true,
+                    _ => false,
+                };
+                sample_type_matches
+                    && view_dimension == layout_view_dimension
+                    && multisampled == layout_multisampled
+            }
+            (
+                Self::StorageTexture { view_dimension },
+                wgpu::BindingType::StorageTexture {
+                    view_dimension: layout_view_dimension,
+                    ..
+                },
+            ) => view_dimension == layout_view_dimension,
+            (Self::Sampler { comparison }, wgpu::BindingType::Sampler(sampler_type)) => {
+                *comparison == (*sampler_type == wgpu::SamplerBindingType::Comparison)
+            }
+            // e.g. acceleration structures, which we don't use.
+            (Self::Other, _) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Checks the bindings of a pipeline's shader against its bind group layouts.
+///
+/// This should be done before creating the pipeline, since that would fail
+/// with a less helpful error.
+pub fn check_shader_bindings(
+    label: &str,
+    source: &str,
+    groups: &[&BindGroupLayoutBuilder],
+) -> Result<(), ShaderBindingError> {
+    ShaderBindings::reflect(label, source).and_then(|bindings| bindings.check(groups))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShaderBindingError {
+    #[error("failed to parse shader {shader}:\n{message}")]
+    Parse { shader: String, message: String },
+
+    #[error("invalid shader {shader}:\n{message}")]
+    Validate { shader: String, message: String },
+
+    #[error("binding `{name}` (group {group}, binding {binding}) of shader {shader}: {problem}")]
+    Mismatch {
+        shader: String,
+        name: String,
+        group: u32,
+        binding: u32,
+        problem: String,
+    },
+}
+
 pub trait TextureFormatExt {
     fn as_wgpu(&self) -> wgpu::TextureFormat;
 }
@@ -344,3 +661,101 @@ impl TextureFormatExt for TextureFormat {
         }
     }
 }
+
+pub trait TextureEdgeModeExt {
+    fn as_wgpu(&self) -> wgpu::AddressMode;
+}
+
+impl TextureEdgeModeExt for TextureEdgeMode {
+    fn as_wgpu(&self) -> wgpu::AddressMode {
+        match self {
+            TextureEdgeMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            TextureEdgeMode::Repeat => wgpu::AddressMode::Repeat,
+            TextureEdgeMode::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+            TextureEdgeMode::ClampToBorder => wgpu::AddressMode::ClampToBorder,
+        }
+    }
+}
+
+pub trait TextureFilterExt {
+    fn as_wgpu(&self) -> wgpu::FilterMode;
+}
+
+impl TextureFilterExt for TextureFilter {
+    fn as_wgpu(&self) -> wgpu::FilterMode {
+        match self {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BindGroupLayoutBuilder,
+        ShaderBindingError,
+        ShaderBindings,
+    };
+
+    const SOURCE: &str = r#"
+        @group(0) @binding(0)
+        var<uniform> color: vec4<f32>;
+        @group(0) @binding(1)
+        var color_texture: texture_2d<f32>;
+        @group(0) @binding(2)
+        var color_sampler: sampler;
+
+        @vertex
+        fn vs_main() -> @builtin(position) vec4<f32> {
+            return vec4<f32>(0.0);
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            return color * textureSample(color_texture, color_sampler, vec2<f32>(0.0));
+        }
+    "#;
+
+    #[test]
+    fn matching_layout_passes() {
+        let layout = BindGroupLayoutBuilder::default()
+            .with_uniform_buffer(wgpu::ShaderStages::FRAGMENT)
+            .with_view_and_sampler(wgpu::ShaderStages::FRAGMENT);
+        ShaderBindings::reflect("test.wgsl", SOURCE)
+            .unwrap()
+            .check(&[&layout])
+            .unwrap();
+    }
+
+    #[test]
+    fn mismatched_layout_names_the_binding() {
+        let layout = BindGroupLayoutBuilder::default()
+            .with_uniform_buffer(wgpu::ShaderStages::FRAGMENT)
+            .with_sampler(wgpu::ShaderStages::FRAGMENT)
+            .with_texture(wgpu::ShaderStages::FRAGMENT);
+        let error = ShaderBindings::reflect("test.wgsl", SOURCE)
+            .unwrap()
+            .check(&[&layout])
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ShaderBindingError::Mismatch { name, binding: 1, .. } if name == "color_texture"
+        ));
+    }
+
+    #[test]
+    fn binding_invisible_to_its_stage_is_an_error() {
+        let layout = BindGroupLayoutBuilder::default()
+            .with_uniform_buffer(wgpu::ShaderStages::VERTEX)
+            .with_view_and_sampler(wgpu::ShaderStages::FRAGMENT);
+        let error = ShaderBindings::reflect("test.wgsl", SOURCE)
+            .unwrap()
+            .check(&[&layout])
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ShaderBindingError::Mismatch { binding: 0, .. }
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+//! Cameras that are rendered into a rectangle of another camera's frame.
+//!
+//! A camera with a [`Viewport`] renders into an offscreen texture, like any
+//! camera with a texture [`RenderTarget`]. The [`ViewportsPass`] of its parent
+//! camera then draws that texture over the parent's frame. This is how the
+//! minimap is shown on top of the map, without a second canvas and surface.
+//!
+//! The texture is drawn as it was rendered last, so if the viewport camera is
+//! rendered after its parent, it lags a frame behind.
+
+use std::sync::Arc;
+
+use nalgebra::Point2;
+
+use crate::graphics::{
+    backend::Backend,
+    camera::{
+        RenderTarget,
+        RenderTargetInner,
+    },
+    render_frame::{
+        CreateRenderPass,
+        CreateRenderPassContext,
+        RenderPass,
+        RenderPassContext,
+    },
+    Surface,
+    SurfaceSize,
+};
+
+/// Component that draws a camera's frame over the frame of its parent camera.
+///
+/// The camera's [`RenderTarget`] must have been created with
+/// [`ViewportTargets::create_render_target`].
+#[derive(Clone, Copy, Debug)]
+pub struct Viewport {
+    pub parent: hecs::Entity,
+
+    /// Top-left corner of the viewport in the parent's frame, in pixels.
+    pub position: Point2<u32>,
+}
+
+/// Creates the render targets and passes of viewports, for a parent camera
+/// that renders to a surface.
+#[derive(Clone, Debug)]
+pub struct ViewportTargets {
+    backend: Backend,
+    format: wgpu::TextureFormat,
+}
+
+impl ViewportTargets {
+    pub fn from_surface(surface: &Surface) -> Self {
+        Self {
+            backend: surface.backend.clone(),
+            format: surface.format(),
+        }
+    }
+
+    /// Creates the offscreen texture a viewport camera renders into.
+    ///
+    /// It has the same format as the parent's frame, so that it can be drawn
+    /// into it as it is.
+    pub fn create_render_target(&self, size: SurfaceSize) -> RenderTarget {
+        let texture = self
+            .backend
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("viewport texture"),
+                size: wgpu::Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+        RenderTarget::from_texture(self.backend.clone(), Arc::new(texture))
+    }
+
+    /// Context for creating the render pass of a viewport camera with the
+    /// given size.
+    pub fn render_pass_context(&self, size: SurfaceSize) -> CreateRenderPassContext<'_> {
+        CreateRenderPassContext {
+            backend: &self.backend,
+            surface_size: size,
+            surface_format: self.format,
+            emissive_format: None,
+        }
+    }
+}
+
+/// Renders the inner pass, and then draws the [`Viewport`]s of the camera
+/// over it.
+#[derive(Clone, Copy, Debug)]
+pub struct CreateViewportsPass<P> {
+    pub inner: P,
+}
+
+impl<P: CreateRenderPass> CreateRenderPass for CreateViewportsPass<P> {
+    type RenderPass = ViewportsPass<P::RenderPass>;
+
+    fn create_render_pass(self, context: &CreateRenderPassContext) -> Self::RenderPass {
+        ViewportsPass {
+            inner: self.inner.create_render_pass(context),
+            pipeline: ViewportPipeline::new(context.backend, context.surface_format),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ViewportsPass<P> {
+    inner: P,
+    pipeline: ViewportPipeline,
+}
+
+impl<P: RenderPass> RenderPass for ViewportsPass<P> {
+    fn render(&mut self, context: &mut RenderPassContext) {
+        self.inner.render(context);
+
+        let mut viewports = context.world.query::<(&Viewport, &RenderTarget)>();
+        for (_, (viewport, render_target)) in viewports.iter() {
+            if viewport.parent != context.render_target_entity {
+                continue;
+            }
+            let RenderTargetInner::Texture { texture, .. } = render_target.inner.get()
+            else {
+                continue;
+            };
+
+            // viewports must lie within the target. they can stick out while the
+            // window is resized, and are skipped until then.
+            let size = SurfaceSize::from_texture(texture);
+            if viewport.position.x + size.width > context.target_size.width
+                || viewport.position.y + size.height > context.target_size.height
+            {
+                continue;
+            }
+
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self.pipeline.create_bind_group(context.backend, &view);
+
+            let mut render_pass = context
+                .encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("viewport render pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: context.target_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+            render_pass.set_viewport(
+                viewport.position.x as f32,
+                viewport.position.y as f32,
+                size.width as f32,
+                size.height as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_pipeline(&self.pipeline.pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// Draws a texture over the whole viewport.
+#[derive(Debug)]
+struct ViewportPipeline {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+}
+
+impl ViewportPipeline {
+    fn new(backend: &Backend, format: wgpu::TextureFormat) -> Self {
+        // the mipmap shader just samples the source texture over the whole target.
+        let shader = backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("mipmap.wgsl"));
+
+        let bind_group_layout =
+            backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("viewport bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_layout =
+            backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("viewport pipeline layout"),
+                    bind_group_layouts: &[&bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline = backend
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("viewport pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: Default::default(),
+                }),
+                multiview: None,
+                cache: None,
+            });
+
+        let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("viewport sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+            sampler,
+        }
+    }
+
+    fn create_bind_group(&self, backend: &Backend, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        backend
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("viewport bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            })
+    }
+}
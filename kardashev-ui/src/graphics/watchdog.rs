@@ -0,0 +1,184 @@
+//! Watchdog that restarts the graphics reactor when it hangs.
+//!
+//! The reactor is restarted if it doesn't answer pings, if a device is lost
+//! (e.g. when the browser drops the WebGL context), or if a surface can't
+//! present frames anymore. It's torn down and reinitialized with the last
+//! config, and [`Graphics::restarts`](super::Graphics::restarts) notifies the
+//! windows, so that they recreate their surfaces.
+
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::sync::{
+    mpsc,
+    oneshot,
+    watch,
+};
+
+use crate::{
+    error::error_chain,
+    graphics::{
+        capabilities::Capabilities,
+        Command,
+        Config,
+        Error,
+        InitState,
+        Reactor,
+    },
+    notifications::{
+        Notification,
+        Notifications,
+    },
+    utils::time::{
+        interval,
+        sleep,
+    },
+};
+
+/// Interval in which the reactor is pinged.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time after which the reactor is considered hung, if it didn't answer a
+/// ping.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time to wait before reinitializing, so that a reactor that keeps failing
+/// isn't restarted in a tight loop.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Why the reactor is restarted.
+#[derive(Clone, Debug)]
+pub enum Failure {
+    Unresponsive,
+    DeviceLost { message: String },
+    SurfaceLost { error: wgpu::SurfaceError },
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unresponsive => write!(f, "graphics reactor is not responding"),
+            Self::DeviceLost { message } => write!(f, "device lost: {message}"),
+            Self::SurfaceLost { error } => write!(f, "surface lost: {error}"),
+        }
+    }
+}
+
+/// Handle with which [backends](super::backend::Backend) report failures.
+///
+/// Failures of backends that were created before the last restart are
+/// ignored.
+#[derive(Clone, Debug)]
+pub struct FailureReporter {
+    tx_failure: mpsc::UnboundedSender<(u32, Failure)>,
+    generation: u32,
+}
+
+impl FailureReporter {
+    pub fn report(&self, failure: Failure) {
+        let _ = self.tx_failure.send((self.generation, failure));
+    }
+}
+
+/// Runs the reactor, and restarts it when it fails.
+pub(super) struct Watchdog {
+    pub config: Config,
+    pub tx_command: mpsc::WeakSender<Command>,
+    pub rx_command: mpsc::Receiver<Command>,
+    pub tx_capabilities: watch::Sender<Option<Arc<Capabilities>>>,
+    pub tx_restarts: watch::Sender<u32>,
+    pub tx_init_state: watch::Sender<InitState>,
+    pub notifications: Option<Notifications>,
+}
+
+impl Watchdog {
+    pub async fn run(mut self) -> Result<(), Error> {
+        let (tx_failure, mut rx_failure) = mpsc::unbounded_channel();
+        let mut generation = 0;
+
+        loop {
+            let failures = FailureReporter {
+                tx_failure: tx_failure.clone(),
+                generation,
+            };
+            let reactor =
+                match Reactor::new(self.config.clone(), self.tx_capabilities.clone(), failures)
+                    .await
+                {
+                    Ok(reactor) => reactor,
+                    Err(error) => {
+                        self.tx_init_state
+                            .send_replace(InitState::Failed(error_chain(&error)));
+                        return Err(error);
+                    }
+                };
+            self.tx_init_state.send_replace(InitState::Initialized);
+            if generation > 0 {
+                self.tx_restarts.send_replace(generation);
+            }
+
+            let failure = tokio::select! {
+                () = reactor.run(&mut self.rx_command) => {
+                    // all handles to the graphics were dropped.
+                    return Ok(());
+                }
+                failure = next_failure(&mut rx_failure, generation) => failure,
+                failure = ping(&self.tx_command) => failure,
+            };
+
+            tracing::error!(%failure, generation, "restarting graphics reactor");
+            if let Some(notifications) = &self.notifications {
+                notifications.notify(
+                    Notification::warning("notification-graphics-restarted")
+                        .with_message(failure.to_string()),
+                );
+            }
+
+            drop(reactor);
+            sleep(RESTART_DELAY).await;
+            generation += 1;
+        }
+    }
+}
+
+async fn next_failure(
+    rx_failure: &mut mpsc::UnboundedReceiver<(u32, Failure)>,
+    generation: u32,
+) -> Failure {
+    while let Some((failure_generation, failure)) = rx_failure.recv().await {
+        if failure_generation == generation {
+            return failure;
+        }
+    }
+    std::future::pending().await
+}
+
+/// Pings the reactor, and returns when it doesn't answer in time.
+async fn ping(tx_command: &mpsc::WeakSender<Command>) -> Failure {
+    let mut interval = interval(PING_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let Some(tx_command) = tx_command.upgrade()
+        else {
+            // the reactor stops once the command channel is closed.
+            return std::future::pending().await;
+        };
+
+        let (tx_pong, rx_pong) = oneshot::channel();
+        let pong = async move {
+            // the channel might be full if the reactor hangs.
+            tx_command.send(Command::Ping { tx_pong }).await.ok()?;
+            rx_pong.await.ok()
+        };
+
+        tokio::select! {
+            _ = pong => {}
+            _ = sleep(PING_TIMEOUT) => return Failure::Unresponsive,
+        }
+    }
+}
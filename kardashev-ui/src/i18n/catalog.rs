@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use kardashev_client::DownloadError;
+use kardashev_protocol::assets::{
+    self as dist,
+    AssetId,
+};
+
+use crate::{
+    assets::{
+        load::{
+            LoadAssetContext,
+            LoadFromAsset,
+        },
+        AssetNotFound,
+        MaybeHasAssetId,
+    },
+    i18n::Locale,
+};
+
+/// Translated messages for one locale.
+#[derive(Clone, Debug)]
+pub struct Catalog {
+    asset_id: Option<AssetId>,
+    locale: Locale,
+    messages: Arc<dist::CatalogMessages>,
+}
+
+impl Catalog {
+    pub fn new(locale: Locale) -> Self {
+        Self {
+            asset_id: None,
+            locale,
+            messages: Default::default(),
+        }
+    }
+
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages
+            .messages
+            .get(key)
+            .map(|message| message.as_str())
+    }
+
+    #[cfg(test)]
+    pub(super) fn from_messages<'a>(
+        locale: Locale,
+        messages: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        Self {
+            asset_id: None,
+            locale,
+            messages: Arc::new(dist::CatalogMessages {
+                messages: messages
+                    .into_iter()
+                    .map(|(key, message)| (key.to_owned(), message.to_owned()))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Adds all messages from `other` to this catalog, replacing existing
+    /// messages with the same key.
+    pub fn merge(&mut self, other: &Catalog) {
+        let messages = Arc::make_mut(&mut self.messages);
+        messages.messages.extend(
+            other
+                .messages
+                .messages
+                .iter()
+                .map(|(key, message)| (key.clone(), message.clone())),
+        );
+    }
+}
+
+impl MaybeHasAssetId for Catalog {
+    fn maybe_asset_id(&self) -> Option<AssetId> {
+        self.asset_id
+    }
+}
+
+impl LoadFromAsset for Catalog {
+    type Dist = dist::Catalog;
+    type Error = CatalogError;
+    type Args = ();
+
+    async fn load<'a, 'b: 'a>(
+        asset_id: AssetId,
+        _args: (),
+        context: &'a mut LoadAssetContext<'b>,
+    ) -> Result<Self, CatalogError> {
+        let dist = context
+            .dist_assets
+            .get::<dist::Catalog>(asset_id)
+            .ok_or_else(|| AssetNotFound { asset_id })?;
+
+        let client = context.client;
+        let messages = context
+            .cache
+            .get_or_try_insert_async(asset_id, || {
+                async move {
+                    let data = client.download_file(&dist.messages).await?.bytes().await?;
+                    let messages: dist::CatalogMessages = serde_json::from_slice(&data)?;
+                    Ok::<_, CatalogError>(Arc::new(messages))
+                }
+            })
+            .await?;
+
+        tracing::debug!(%asset_id, locale = %dist.locale, "catalog loaded");
+
+        Ok(Self {
+            asset_id: Some(asset_id),
+            locale: Locale::new(dist.locale.clone()),
+            messages,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("catalog load error")]
+pub enum CatalogError {
+    AssetNotFound(#[from] AssetNotFound),
+    Download(#[from] DownloadError),
+    Json(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Catalog;
+    use crate::i18n::Locale;
+
+    #[test]
+    fn merged_messages_replace_existing_ones() {
+        let mut catalog = Catalog::from_messages(
+            Locale::DEFAULT,
+            [("greeting", "Hello"), ("farewell", "Bye")],
+        );
+        catalog.merge(&Catalog::from_messages(
+            Locale::DEFAULT,
+            [("greeting", "Hi"), ("thanks", "Thanks")],
+        ));
+
+        assert_eq!(catalog.get("greeting"), Some("Hi"));
+        assert_eq!(catalog.get("farewell"), Some("Bye"));
+        assert_eq!(catalog.get("thanks"), Some("Thanks"));
+        assert_eq!(catalog.get("missing"), None);
+    }
+}
@@ -0,0 +1,439 @@
+//! Localization of UI strings.
+//!
+//! Translations are stored in [`Catalog`]s, which are loaded from the asset
+//! server. Components can use the [`t!`][crate::t] macro to get a reactive
+//! signal for a translated message, which updates when the locale is changed
+//! with [`I18n::set_locale`].
+
+mod catalog;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+};
+
+use leptos::{
+    create_effect,
+    create_rw_signal,
+    expect_context,
+    provide_context,
+    RwSignal,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    SignalUpdate,
+    SignalWith,
+};
+use leptos_use::storage::use_local_storage;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+pub use self::catalog::{
+    Catalog,
+    CatalogError,
+};
+use crate::{
+    assets::{
+        server::AssetServer,
+        system::AssetTypeRegistry,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+    },
+    utils::futures::spawn_local_and_handle_error,
+};
+
+/// A locale, identified by its language tag, e.g. `en` or `de-AT`.
+///
+/// This is also a resource, holding the locale currently selected in the UI.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Locale {
+    tag: Cow<'static, str>,
+}
+
+impl Locale {
+    /// The locale that is used if a message is missing in the selected
+    /// locale.
+    pub const DEFAULT: Self = Self::new_static("en");
+
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: Cow::Owned(tag.into()),
+        }
+    }
+
+    pub const fn new_static(tag: &'static str) -> Self {
+        Self {
+            tag: Cow::Borrowed(tag),
+        }
+    }
+
+    /// Returns the locale preferred by the browser.
+    pub fn from_browser() -> Option<Self> {
+        gloo_utils::window().navigator().language().map(Self::new)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.tag
+    }
+
+    /// Returns the locale without region, e.g. `de` for `de-AT`.
+    pub fn language(&self) -> Option<Self> {
+        let (language, _region) = self.tag.split_once('-')?;
+        Some(Self::new(language))
+    }
+
+    /// Returns the locales in which messages are looked up, in order: the
+    /// locale itself, its language and [`Locale::DEFAULT`].
+    pub fn fallbacks(&self) -> Vec<Locale> {
+        let mut locales = vec![self.clone()];
+        locales.extend(self.language());
+        if !locales.contains(&Self::DEFAULT) {
+            locales.push(Self::DEFAULT);
+        }
+        locales
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tag)
+    }
+}
+
+/// Handle to the translations, provided as context.
+#[derive(Clone, Copy, Debug)]
+pub struct I18n {
+    locale: RwSignal<Locale>,
+    catalogs: RwSignal<HashMap<Locale, Catalog>>,
+}
+
+impl I18n {
+    pub fn locale(&self) -> Signal<Locale> {
+        self.locale.into()
+    }
+
+    pub fn set_locale(&self, locale: Locale) {
+        tracing::info!(%locale, "switching locale");
+        self.locale.set(locale);
+    }
+
+    /// Translates a message.
+    ///
+    /// If the message is missing in the selected locale, the message from
+    /// [`Locale::DEFAULT`] is used. If that is missing too, the key is
+    /// returned.
+    ///
+    /// This tracks the selected locale and the loaded catalogs.
+    pub fn translate(&self, key: &str) -> String {
+        self.translate_with_args(key, &[])
+    }
+
+    /// Translates a message and replaces its placeholders (`{ $name }`) with
+    /// the given arguments.
+    pub fn translate_with_args(&self, key: &str, args: &[(&str, String)]) -> String {
+        self.try_translate(key, args).unwrap_or_else(|| {
+            tracing::trace!(key, locale = %self.locale.get(), "missing translation");
+            key.to_owned()
+        })
+    }
+
+    fn try_translate(&self, key: &str, args: &[(&str, String)]) -> Option<String> {
+        let locale = self.locale.get();
+
+        self.catalogs.with(|catalogs| {
+            find_message(catalogs, &locale, key).map(|message| format_message(message, args))
+        })
+    }
+
+    /// Returns a signal for a translated message.
+    pub fn message(&self, key: &'static str) -> Signal<String> {
+        let i18n = *self;
+        Signal::derive(move || i18n.translate(key))
+    }
+
+    /// Returns a signal for a translated message, which is `fallback` until a
+    /// catalog containing the message is loaded.
+    ///
+    /// This is for the UI that is shown before the catalogs are loaded, e.g.
+    /// the loading screen.
+    pub fn message_or(&self, key: &'static str, fallback: &'static str) -> Signal<String> {
+        let i18n = *self;
+        Signal::derive(move || {
+            i18n.try_translate(key, &[])
+                .unwrap_or_else(|| fallback.to_owned())
+        })
+    }
+}
+
+/// Provides [`I18n`] as context.
+///
+/// The selected locale is persisted in local storage. Catalogs for the
+/// selected locale are loaded on demand.
+///
+/// This must be called after the [`WorldServer`] was provided.
+pub fn provide_i18n() {
+    let world = expect_context::<WorldServer>();
+
+    let (stored_locale, set_stored_locale, _) =
+        use_local_storage::<Option<Locale>, codee::string::JsonSerdeCodec>("locale");
+    let locale = stored_locale
+        .get_untracked()
+        .or_else(Locale::from_browser)
+        .unwrap_or_default();
+
+    let i18n = I18n {
+        locale: create_rw_signal(locale),
+        catalogs: create_rw_signal(HashMap::new()),
+    };
+
+    create_effect(move |_| {
+        let locale = i18n.locale.get();
+        set_stored_locale.set(Some(locale.clone()));
+
+        let _ = world.run({
+            let locale = locale.clone();
+            move |system_context| {
+                system_context.resources.insert(locale);
+            }
+        });
+
+        for locale in locale.fallbacks() {
+            if i18n
+                .catalogs
+                .with_untracked(|catalogs| catalogs.contains_key(&locale))
+            {
+                continue;
+            }
+
+            // insert an empty catalog, so we only load it once.
+            i18n.catalogs.update(|catalogs| {
+                catalogs.insert(locale.clone(), Catalog::new(locale.clone()));
+            });
+
+            let world = world.clone();
+            spawn_local_and_handle_error(async move {
+                let catalog = load_catalog(&world, locale.clone()).await?;
+                i18n.catalogs.update(|catalogs| {
+                    catalogs.insert(locale, catalog);
+                });
+                Ok::<(), CatalogError>(())
+            });
+        }
+    });
+
+    provide_context(i18n);
+}
+
+pub fn use_i18n() -> I18n {
+    expect_context()
+}
+
+/// Loads and merges all catalogs for a locale.
+async fn load_catalog(world: &WorldServer, locale: Locale) -> Result<Catalog, CatalogError> {
+    let asset_server = world
+        .run(|system_context| system_context.resources.get::<AssetServer>().cloned())
+        .await
+        .expect("AssetServer resource missing");
+
+    let asset_ids = {
+        let locale = locale.clone();
+        asset_server
+            .find::<kardashev_protocol::assets::Catalog>(move |catalog| {
+                catalog.locale == locale.as_str()
+            })
+            .await
+    };
+    if asset_ids.is_empty() {
+        tracing::warn!(%locale, "no catalogs for locale");
+    }
+
+    let mut merged = Catalog::new(locale);
+    for asset_id in asset_ids {
+        let catalog = asset_server.load::<Catalog>(asset_id, ()).await?;
+        merged.merge(&catalog);
+    }
+
+    Ok(merged)
+}
+
+/// Looks up a message in the catalogs of the locale's
+/// [fallbacks](Locale::fallbacks).
+fn find_message<'a>(
+    catalogs: &'a HashMap<Locale, Catalog>,
+    locale: &Locale,
+    key: &str,
+) -> Option<&'a str> {
+    locale
+        .fallbacks()
+        .iter()
+        .find_map(|locale| catalogs.get(locale).and_then(|catalog| catalog.get(key)))
+}
+
+/// Replaces placeholders of the form `{ $name }` in a message.
+///
+/// Placeholders without a matching argument are left as-is.
+fn format_message(message: &str, args: &[(&str, String)]) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}')
+        else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + end + 1];
+        let name = placeholder[1..placeholder.len() - 1]
+            .trim()
+            .strip_prefix('$');
+
+        match name.and_then(|name| args.iter().find(|(arg, _)| *arg == name)) {
+            Some((_, value)) => output.push_str(value),
+            None => output.push_str(placeholder),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Returns a [`Signal`] with the translation of a message.
+///
+/// Arguments can be passed to replace placeholders in the message. These are
+/// evaluated inside the signal, so they can be reactive.
+///
+/// ```ignore
+/// view! {
+///     <p>{t!("greeting")}</p>
+///     <p>{t!("star-count", count = count.get())}</p>
+/// }
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::use_i18n().message($key)
+    };
+    ($key:literal, $($name:ident = $value:expr),+ $(,)?) => {{
+        let i18n = $crate::i18n::use_i18n();
+        ::leptos::Signal::derive(move || {
+            i18n.translate_with_args(
+                $key,
+                &[$((::std::stringify!($name), ::std::string::ToString::to_string(&$value))),+],
+            )
+        })
+    }};
+}
+
+/// Registers the [`Catalog`] asset type and inserts the [`Locale`] resource.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct I18nPlugin;
+
+impl Plugin for I18nPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        if let Some(asset_type_registry) = context.resources.get_mut::<AssetTypeRegistry>() {
+            asset_type_registry.register::<Catalog>();
+        }
+        else {
+            tracing::warn!(
+                "resource AssetTypeRegistry is missing. can't register catalog asset type"
+            );
+        }
+
+        context.resources.insert(Locale::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        find_message,
+        format_message,
+        Catalog,
+        Locale,
+    };
+
+    fn catalogs() -> HashMap<Locale, Catalog> {
+        [
+            Catalog::from_messages(
+                Locale::new("en"),
+                [("greeting", "Hello"), ("farewell", "Goodbye")],
+            ),
+            Catalog::from_messages(Locale::new("de"), [("greeting", "Hallo")]),
+            Catalog::from_messages(Locale::new("de-AT"), [("greeting", "Servus")]),
+        ]
+        .into_iter()
+        .map(|catalog| (catalog.locale().clone(), catalog))
+        .collect()
+    }
+
+    #[test]
+    fn fallbacks_go_from_region_to_language_to_default() {
+        assert_eq!(
+            Locale::new("de-AT").fallbacks(),
+            [Locale::new("de-AT"), Locale::new("de"), Locale::DEFAULT]
+        );
+        assert_eq!(Locale::new("en").fallbacks(), [Locale::DEFAULT]);
+    }
+
+    #[test]
+    fn messages_are_looked_up_in_the_most_specific_locale() {
+        let catalogs = catalogs();
+        assert_eq!(
+            find_message(&catalogs, &Locale::new("de-AT"), "greeting"),
+            Some("Servus")
+        );
+        assert_eq!(
+            find_message(&catalogs, &Locale::new("de-DE"), "greeting"),
+            Some("Hallo")
+        );
+    }
+
+    #[test]
+    fn missing_messages_fall_back_to_the_default_locale() {
+        let catalogs = catalogs();
+        assert_eq!(
+            find_message(&catalogs, &Locale::new("de-AT"), "farewell"),
+            Some("Goodbye")
+        );
+        assert_eq!(
+            find_message(&catalogs, &Locale::new("fr"), "greeting"),
+            Some("Hello")
+        );
+        assert_eq!(find_message(&catalogs, &Locale::new("de"), "missing"), None);
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_arguments() {
+        assert_eq!(
+            format_message(
+                "{ $count } stars near { $star }",
+                &[("count", "3".to_owned()), ("star", "Sol".to_owned())]
+            ),
+            "3 stars near Sol"
+        );
+        assert_eq!(
+            format_message("{ $missing } and {unclosed", &[]),
+            "{ $missing } and {unclosed"
+        );
+    }
+}
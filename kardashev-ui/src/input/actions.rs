@@ -0,0 +1,272 @@
+//! Actions that are triggered by key chords, e.g. undo with Ctrl+Z.
+//!
+//! Unlike the [`InputState`](super::InputState), which is updated once per
+//! tick for the camera controllers, actions are handled by the UI as soon as
+//! the keys are pressed. Key presses in text fields are left to the browser,
+//! so that Ctrl+Z there still undoes typing.
+//!
+//! The player can rebind actions in local storage under `key-bindings`, as an
+//! object from key chords to actions, e.g. `{"Ctrl+KeyU": "undo"}`. These
+//! override the default bindings, and are synced with the other settings.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+};
+
+use leptos::{
+    create_memo,
+    provide_context,
+    use_context,
+    MaybeSignal,
+    Memo,
+    SignalWith,
+    SignalWithUntracked,
+};
+use leptos_use::{
+    storage::use_local_storage,
+    use_event_listener,
+    use_window,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use wasm_bindgen::JsCast;
+
+use crate::input::keyboard::{
+    KeyCode,
+    KeyModifiers,
+};
+
+/// Local storage key of the player's key bindings.
+pub const KEY_BINDINGS_KEY: &str = "key-bindings";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Undo,
+    Redo,
+}
+
+/// A key that is pressed together with modifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub const fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+}
+
+/// Names of the modifiers, in the order they're displayed.
+const MODIFIER_NAMES: [(KeyModifiers, &str); 4] = [
+    (KeyModifiers::CTRL, "Ctrl"),
+    (KeyModifiers::ALT, "Alt"),
+    (KeyModifiers::SHIFT, "Shift"),
+    (KeyModifiers::META, "Meta"),
+];
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid key chord: {0}")]
+pub struct KeyChordParseError(String);
+
+/// Parses chords like `Ctrl+Shift+KeyZ`, i.e. modifiers and a key code
+/// separated by `+`.
+impl FromStr for KeyChord {
+    type Err = KeyChordParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || KeyChordParseError(s.to_owned());
+
+        let mut parts = s.split('+').map(str::trim);
+        let code = parts.next_back().ok_or_else(error)?;
+        let code = code.parse().map_err(|_| error())?;
+
+        let mut modifiers = KeyModifiers::empty();
+        for part in parts {
+            let (modifier, _) = MODIFIER_NAMES
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(part))
+                .ok_or_else(error)?;
+            modifiers |= *modifier;
+        }
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", self.code)
+    }
+}
+
+/// Maps key chords to [`Action`]s, provided as context.
+///
+/// The default bindings use Ctrl, and Cmd on macOS.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActionMap {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let mut action_map = Self {
+            bindings: HashMap::new(),
+        };
+        for modifier in [KeyModifiers::CTRL, KeyModifiers::META] {
+            action_map = action_map
+                .with_binding(KeyChord::new(KeyCode::KeyZ, modifier), Action::Undo)
+                .with_binding(
+                    KeyChord::new(KeyCode::KeyZ, modifier | KeyModifiers::SHIFT),
+                    Action::Redo,
+                )
+                .with_binding(KeyChord::new(KeyCode::KeyY, modifier), Action::Redo);
+        }
+        action_map
+    }
+}
+
+impl ActionMap {
+    pub fn with_binding(mut self, chord: KeyChord, action: Action) -> Self {
+        self.bindings.insert(chord, action);
+        self
+    }
+
+    /// Adds bindings from key chords in their string form. Invalid chords are
+    /// skipped.
+    pub fn with_bindings(mut self, bindings: &HashMap<String, Action>) -> Self {
+        for (chord, action) in bindings {
+            match chord.parse() {
+                Ok(chord) => self = self.with_binding(chord, *action),
+                Err(error) => tracing::warn!(%error, "ignoring key binding"),
+            }
+        }
+        self
+    }
+
+    pub fn action(&self, chord: &KeyChord) -> Option<Action> {
+        self.bindings.get(chord).copied()
+    }
+}
+
+/// Provides the [`ActionMap`] with the default bindings and the player's key
+/// bindings, which are updated when they change.
+pub fn provide_action_map() {
+    let (key_bindings, _, _) = use_local_storage::<
+        HashMap<String, Action>,
+        codee::string::JsonSerdeCodec,
+    >(KEY_BINDINGS_KEY);
+    let action_map = create_memo(move |_| {
+        key_bindings.with(|key_bindings| ActionMap::default().with_bindings(key_bindings))
+    });
+    provide_context(action_map);
+}
+
+/// Calls `handler` whenever a key chord bound to `action` is pressed.
+///
+/// The [`ActionMap`] is taken from the context, or the default one is used.
+pub fn use_action(action: Action, handler: impl Fn() + 'static) {
+    let action_map = use_context::<Memo<ActionMap>>()
+        .map(MaybeSignal::from)
+        .unwrap_or_else(|| MaybeSignal::Static(ActionMap::default()));
+
+    let _ = use_event_listener(use_window(), leptos::ev::keydown, move |event| {
+        if event.repeat() || is_text_field(event.target()) {
+            return;
+        }
+        let Ok(code) = event.code().parse::<KeyCode>()
+        else {
+            return;
+        };
+        let chord = KeyChord::new(code, KeyModifiers::from_websys(&event));
+        if action_map.with_untracked(|action_map| action_map.action(&chord)) == Some(action) {
+            event.prevent_default();
+            handler();
+        }
+    });
+}
+
+fn is_text_field(target: Option<web_sys::EventTarget>) -> bool {
+    target
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        .map_or(false, |element| {
+            matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        Action,
+        ActionMap,
+        KeyChord,
+    };
+    use crate::input::keyboard::{
+        KeyCode,
+        KeyModifiers,
+    };
+
+    #[test]
+    fn shift_turns_undo_into_redo() {
+        let action_map = ActionMap::default();
+        assert_eq!(
+            action_map.action(&KeyChord::new(KeyCode::KeyZ, KeyModifiers::CTRL)),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            action_map.action(&KeyChord::new(
+                KeyCode::KeyZ,
+                KeyModifiers::CTRL | KeyModifiers::SHIFT
+            )),
+            Some(Action::Redo)
+        );
+        assert_eq!(
+            action_map.action(&KeyChord::new(KeyCode::KeyZ, KeyModifiers::empty())),
+            None
+        );
+    }
+
+    #[test]
+    fn key_chords_round_trip() {
+        let chord = "Ctrl+Shift+KeyZ".parse::<KeyChord>().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::new(KeyCode::KeyZ, KeyModifiers::CTRL | KeyModifiers::SHIFT)
+        );
+        assert_eq!(chord.to_string(), "Ctrl+Shift+KeyZ");
+        assert_eq!("shift+ctrl+KeyZ".parse::<KeyChord>().unwrap(), chord);
+        assert!("Hyper+KeyZ".parse::<KeyChord>().is_err());
+        assert!("Ctrl+".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn key_bindings_override_defaults() {
+        let bindings = HashMap::from([
+            ("Ctrl+KeyY".to_owned(), Action::Undo),
+            ("Alt+KeyU".to_owned(), Action::Undo),
+            ("Ctrl+NotAKey".to_owned(), Action::Redo),
+        ]);
+        let action_map = ActionMap::default().with_bindings(&bindings);
+        assert_eq!(
+            action_map.action(&KeyChord::new(KeyCode::KeyY, KeyModifiers::CTRL)),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            action_map.action(&KeyChord::new(KeyCode::KeyU, KeyModifiers::ALT)),
+            Some(Action::Undo)
+        );
+    }
+}
@@ -11,9 +11,14 @@ use leptos_use::{
 use linear_map::set::LinearSet;
 use tokio::sync::broadcast;
 
+use crate::input::{
+    Timestamp,
+    Timestamped,
+};
+
 #[derive(Debug)]
 pub struct KeyboardInput {
-    rx: broadcast::Receiver<KeyboardEvent>,
+    rx: broadcast::Receiver<Timestamped<KeyboardEvent>>,
 }
 
 impl Clone for KeyboardInput {
@@ -30,27 +35,33 @@ impl KeyboardInput {
         let tx_down = tx_up.clone();
 
         let _ = use_event_listener(use_window(), leptos::ev::keyup, move |event| {
-            if let Some(event) = KeyboardEvent::from_websys_key_up(&event) {
-                tracing::trace!(?event, "keyboard event");
-                let _ = tx_up.send(event);
+            if let Some(keyboard_event) = KeyboardEvent::from_websys_key_up(&event) {
+                tracing::trace!(?keyboard_event, "keyboard event");
+                let _ = tx_up.send(Timestamped::new(
+                    keyboard_event,
+                    Timestamp::from_websys(&event),
+                ));
             }
         });
 
         let _ = use_event_listener(use_window(), leptos::ev::keydown, move |event| {
-            if let Some(event) = KeyboardEvent::from_websys_key_down(&event) {
-                tracing::trace!(?event, "keyboard event");
-                let _ = tx_down.send(event);
+            if let Some(keyboard_event) = KeyboardEvent::from_websys_key_down(&event) {
+                tracing::trace!(?keyboard_event, "keyboard event");
+                let _ = tx_down.send(Timestamped::new(
+                    keyboard_event,
+                    Timestamp::from_websys(&event),
+                ));
             }
         });
 
         KeyboardInput { rx }
     }
 
-    pub async fn next(&mut self) -> KeyboardEvent {
+    pub async fn next(&mut self) -> Timestamped<KeyboardEvent> {
         self.rx.recv().await.unwrap()
     }
 
-    pub fn try_next(&mut self) -> Option<KeyboardEvent> {
+    pub fn try_next(&mut self) -> Option<Timestamped<KeyboardEvent>> {
         self.rx.try_recv().ok()
     }
 }
@@ -85,211 +96,75 @@ impl KeyboardEvent {
     }
 }
 
+/// Keyboard part of the [`InputState`](super::InputState).
 #[derive(Clone, Debug, Default)]
 pub struct KeyboardInputState {
     pub keys_pressed: LinearSet<KeyCode>,
+    keys_just_pressed: LinearSet<KeyCode>,
+    keys_just_released: LinearSet<KeyCode>,
 }
 
 impl KeyboardInputState {
-    pub fn push(&mut self, event: &KeyboardEvent) {
+    pub(super) fn apply(&mut self, event: &KeyboardEvent) {
         match event {
             KeyboardEvent::KeyUp { code, .. } => {
-                self.keys_pressed.remove(code);
+                if self.keys_pressed.remove(code) {
+                    self.keys_just_released.insert(*code);
+                }
             }
             KeyboardEvent::KeyDown { code, .. } => {
-                self.keys_pressed.insert(*code);
+                // repeated key downs are not edges.
+                if self.keys_pressed.insert(*code) {
+                    self.keys_just_pressed.insert(*code);
+                }
             }
         }
     }
-}
 
-macro_rules! key_codes {
-    {$($key:ident,)*} => {
-        #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-        pub enum KeyCode {
-            $($key,)*
-        }
+    pub(super) fn clear_edges(&mut self) {
+        self.keys_just_pressed.clear();
+        self.keys_just_released.clear();
+    }
 
-        impl KeyCode {
-            fn from_websys(code: &str) -> Option<Self> {
-                match code {
-                    $(
-                        stringify!($key) => Some(Self::$key),
-                    )*
-                    _ => None,
-                }
-            }
+    #[allow(dead_code)]
+    pub fn is_pressed(&self, code: KeyCode) -> bool {
+        self.keys_pressed.contains(&code)
+    }
 
-            fn to_websys(&self) -> &'static str {
-                match self {
-                    $(
-                        Self::$key => stringify!($key),
-                    )*
-                }
-            }
-        }
-    };
+    /// Whether the key was pressed in the current tick.
+    pub fn just_pressed(&self, code: KeyCode) -> bool {
+        self.keys_just_pressed.contains(&code)
+    }
+
+    /// Whether the key was released in the current tick.
+    #[allow(dead_code)]
+    pub fn just_released(&self, code: KeyCode) -> bool {
+        self.keys_just_released.contains(&code)
+    }
 }
 
-key_codes! {
-    Again,
-    AltLeft,
-    AltRight,
-    ArrowDown,
-    ArrowLeft,
-    ArrowRight,
-    ArrowUp,
-    Backquote,
-    Backslash,
-    Backspace,
-    BracketLeft,
-    BracketRight,
-    BrowserBack,
-    BrowserFavorites,
-    BrowserForward,
-    BrowserHome,
-    BrowserRefresh,
-    BrowserSearch,
-    BrowserStop,
-    CapsLock,
-    Comma,
-    ContextMenu,
-    ControlLeft,
-    ControlRight,
-    Convert,
-    Copy,
-    Cut,
-    Delete,
-    Digit0,
-    Digit1,
-    Digit2,
-    Digit3,
-    Digit4,
-    Digit5,
-    Digit6,
-    Digit7,
-    Digit8,
-    Digit9,
-    Eject,
-    End,
-    Enter,
-    Equal,
-    Escape,
-    F1,
-    F10,
-    F11,
-    F12,
-    F13,
-    F14,
-    F15,
-    F16,
-    F17,
-    F18,
-    F19,
-    F2,
-    F20,
-    F21,
-    F22,
-    F23,
-    F24,
-    F3,
-    F4,
-    F5,
-    F6,
-    F7,
-    F8,
-    F9,
-    Find,
-    Help,
-    Home,
-    Insert,
-    IntlBackslash,
-    IntlRo,
-    IntlYen,
-    KanaMode,
-    KeyA,
-    KeyB,
-    KeyC,
-    KeyD,
-    KeyE,
-    KeyF,
-    KeyG,
-    KeyH,
-    KeyI,
-    KeyJ,
-    KeyK,
-    KeyL,
-    KeyM,
-    KeyN,
-    KeyO,
-    KeyP,
-    KeyQ,
-    KeyR,
-    KeyS,
-    KeyT,
-    KeyU,
-    KeyV,
-    KeyW,
-    KeyX,
-    KeyY,
-    KeyZ,
-    Lang1,
-    Lang2,
-    LaunchApp1,
-    LaunchApp2,
-    LaunchMail,
-    MediaPlayPause,
-    MediaSelect,
-    MediaStop,
-    MediaTrackNext,
-    MediaTrackPrevious,
-    MetaLeft,
-    MetaRight,
-    Minus,
-    NonConvert,
-    NumLock,
-    Numpad0,
-    Numpad1,
-    Numpad2,
-    Numpad3,
-    Numpad4,
-    Numpad5,
-    Numpad6,
-    Numpad7,
-    Numpad8,
-    Numpad9,
-    NumpadAdd,
-    NumpadComma,
-    NumpadDecimal,
-    NumpadDivide,
-    NumpadEnter,
-    NumpadEqual,
-    NumpadMultiply,
-    NumpadSubtract,
-    Open,
-    PageDown,
-    PageUp,
-    Paste,
-    Pause,
-    Period,
-    PrintScreen,
-    Props,
-    Quote,
-    ScrollLock,
-    Select,
-    Semicolon,
-    ShiftLeft,
-    ShiftRight,
-    Slash,
-    Space,
-    Tab,
-    Undo,
-    VolumeDown,
-    VolumeMute,
-    VolumeUp,
-    WakeUp,
+/// Category of a key, e.g. for grouping keys when listing key bindings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KeyCategory {
+    Letter,
+    Digit,
+    Function,
+    Modifier,
+    Navigation,
+    Editing,
+    Punctuation,
+    Numpad,
+    Media,
+    Browser,
+    System,
+    /// Keys for input method editors, e.g. for Japanese.
+    Ime,
 }
 
+// The `KeyCode` enum, with the keys' display names and categories. It's
+// generated by `build.rs` from `key_codes.tsv`, to which new keys are added.
+include!(concat!(env!("OUT_DIR"), "/key_codes.rs"));
+
 #[derive(Debug, thiserror::Error)]
 #[error("Invalid keycode: {0}")]
 pub struct KeyCodeParseError(String);
@@ -309,7 +184,7 @@ impl Display for KeyCode {
 }
 
 bitflags! {
-    #[derive(Copy, Clone, Debug, Default)]
+    #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
     pub struct KeyModifiers: u8 {
         const ALT   = 0b00000010;
         const CTRL  = 0b00000001;
@@ -335,4 +210,21 @@ impl KeyModifiers {
         }
         mods
     }
+
+    pub fn from_websys_mouse(event: &web_sys::MouseEvent) -> Self {
+        let mut mods = KeyModifiers::default();
+        if event.alt_key() {
+            mods |= KeyModifiers::ALT;
+        }
+        if event.ctrl_key() {
+            mods |= KeyModifiers::CTRL;
+        }
+        if event.meta_key() {
+            mods |= KeyModifiers::META;
+        }
+        if event.shift_key() {
+            mods |= KeyModifiers::SHIFT;
+        }
+        mods
+    }
 }
@@ -1,6 +1,9 @@
+pub mod actions;
 pub mod keyboard;
 pub mod mouse;
 
+use std::collections::VecDeque;
+
 use self::{
     keyboard::{
         KeyboardEvent,
@@ -25,18 +28,90 @@ pub enum InputEvent {
     Keyboard(KeyboardEvent),
 }
 
+/// Time at which the browser dispatched an input event, in milliseconds since
+/// the page was loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Timestamp(f64);
+
+impl Timestamp {
+    pub fn from_websys(event: &web_sys::Event) -> Self {
+        Self(event.time_stamp())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Timestamped<E> {
+    pub event: E,
+    pub timestamp: Timestamp,
+}
+
+impl<E> Timestamped<E> {
+    pub fn new(event: E, timestamp: Timestamp) -> Self {
+        Self { event, timestamp }
+    }
+
+    pub fn map<F>(self, f: impl FnOnce(E) -> F) -> Timestamped<F> {
+        Timestamped {
+            event: f(self.event),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+/// Input state that is updated once per simulation tick.
+///
+/// Events are [pushed](Self::push) as they arrive, and only take effect in
+/// the next tick, which is started with [`begin_tick`](Self::begin_tick).
+/// Then the events are consumed in the order in which they happened with
+/// [`next_event`](Self::next_event), which also updates the state. This way
+/// the state is the same, no matter how the events were spread over the
+/// frames.
+///
+/// The `just_pressed` and `just_released` edges are those of the current
+/// tick. A key that was pressed and released within one tick is both.
 #[derive(Clone, Debug, Default)]
 pub struct InputState {
     pub keyboard: KeyboardInputState,
     pub mouse: MouseInputState,
+
+    /// Events that arrived since the current tick began.
+    pending: Vec<Timestamped<InputEvent>>,
+
+    /// Events of the current tick that haven't been consumed yet.
+    current: VecDeque<Timestamped<InputEvent>>,
 }
 
 impl InputState {
-    pub fn push(&mut self, event: &InputEvent) {
-        match event {
-            InputEvent::Keyboard(event) => self.keyboard.push(event),
-            InputEvent::Mouse(event) => self.mouse.push(event),
+    /// Buffers an event until the next tick.
+    pub fn push(&mut self, event: Timestamped<InputEvent>) {
+        self.pending.push(event);
+    }
+
+    /// Starts a new tick with the events that arrived since the last one.
+    ///
+    /// Events of the last tick that weren't consumed are applied first.
+    pub fn begin_tick(&mut self) {
+        while self.next_event().is_some() {}
+
+        self.keyboard.clear_edges();
+        self.mouse.clear_edges();
+
+        // mouse and keyboard events come through different channels. the sort is
+        // stable, so events with the same timestamp stay in the order they arrived.
+        self.pending
+            .sort_by(|a, b| a.timestamp.0.total_cmp(&b.timestamp.0));
+        self.current.extend(self.pending.drain(..));
+    }
+
+    /// Applies the next event of the current tick to the state and returns
+    /// it.
+    pub fn next_event(&mut self) -> Option<Timestamped<InputEvent>> {
+        let event = self.current.pop_front()?;
+        match &event.event {
+            InputEvent::Keyboard(keyboard_event) => self.keyboard.apply(keyboard_event),
+            InputEvent::Mouse(mouse_event) => self.mouse.apply(mouse_event),
         }
+        Some(event)
     }
 }
 
@@ -58,3 +133,88 @@ impl Plugin for InputPlugin {
         context.resources.insert(self.keyboard_input);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        keyboard::{
+            KeyCode,
+            KeyModifiers,
+            KeyboardEvent,
+        },
+        InputEvent,
+        InputState,
+        Timestamp,
+        Timestamped,
+    };
+
+    fn key_down(code: KeyCode, timestamp: f64) -> Timestamped<InputEvent> {
+        Timestamped::new(
+            InputEvent::Keyboard(KeyboardEvent::KeyDown {
+                code,
+                repeat: false,
+                modifiers: KeyModifiers::empty(),
+            }),
+            Timestamp(timestamp),
+        )
+    }
+
+    fn key_up(code: KeyCode, timestamp: f64) -> Timestamped<InputEvent> {
+        Timestamped::new(
+            InputEvent::Keyboard(KeyboardEvent::KeyUp {
+                code,
+                modifiers: KeyModifiers::empty(),
+            }),
+            Timestamp(timestamp),
+        )
+    }
+
+    fn run_tick(state: &mut InputState) -> Vec<Timestamp> {
+        state.begin_tick();
+        std::iter::from_fn(|| state.next_event())
+            .map(|event| event.timestamp)
+            .collect()
+    }
+
+    #[test]
+    fn events_take_effect_in_the_next_tick() {
+        let mut state = InputState::default();
+        state.push(key_down(KeyCode::KeyW, 1.0));
+        assert!(!state.keyboard.is_pressed(KeyCode::KeyW));
+
+        run_tick(&mut state);
+        assert!(state.keyboard.is_pressed(KeyCode::KeyW));
+        assert!(state.keyboard.just_pressed(KeyCode::KeyW));
+
+        run_tick(&mut state);
+        assert!(state.keyboard.is_pressed(KeyCode::KeyW));
+        assert!(!state.keyboard.just_pressed(KeyCode::KeyW));
+    }
+
+    #[test]
+    fn press_and_release_within_a_tick_are_both_edges() {
+        let mut state = InputState::default();
+        state.push(key_down(KeyCode::Space, 1.0));
+        state.push(key_up(KeyCode::Space, 2.0));
+
+        run_tick(&mut state);
+        assert!(!state.keyboard.is_pressed(KeyCode::Space));
+        assert!(state.keyboard.just_pressed(KeyCode::Space));
+        assert!(state.keyboard.just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn events_are_consumed_in_timestamp_order() {
+        let mut state = InputState::default();
+        state.push(key_up(KeyCode::KeyA, 3.0));
+        state.push(key_down(KeyCode::KeyA, 1.0));
+        state.push(key_down(KeyCode::KeyB, 2.0));
+
+        assert_eq!(
+            run_tick(&mut state),
+            [Timestamp(1.0), Timestamp(2.0), Timestamp(3.0)]
+        );
+        assert!(!state.keyboard.is_pressed(KeyCode::KeyA));
+        assert!(state.keyboard.is_pressed(KeyCode::KeyB));
+    }
+}
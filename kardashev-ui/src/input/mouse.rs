@@ -6,20 +6,33 @@ use nalgebra::{
     Vector3,
 };
 
+use crate::input::keyboard::KeyModifiers;
+
 #[derive(Clone, Debug)]
 pub enum MouseEvent {
     ButtonUp {
         button: MouseButton,
         position: Point2<f32>,
+        modifiers: KeyModifiers,
     },
     ButtonDown {
         button: MouseButton,
         position: Point2<f32>,
+        modifiers: KeyModifiers,
     },
     Move {
         position: Point2<f32>,
         delta: Vector2<f32>,
     },
+    /// The mouse moved while the pointer is [locked](PointerLock). The cursor
+    /// doesn't move then, so there is only the relative motion.
+    LockedMove {
+        delta: Vector2<f32>,
+    },
+    /// The pointer was locked or released, see [`PointerLock`].
+    PointerLockChanged {
+        locked: bool,
+    },
     Enter,
     Leave,
     Wheel {
@@ -33,6 +46,7 @@ impl MouseEvent {
         Some(Self::ButtonUp {
             button: MouseButton::from_websys(event.button())?,
             position: mouse_position_from_websys(event),
+            modifiers: KeyModifiers::from_websys_mouse(event),
         })
     }
 
@@ -40,6 +54,7 @@ impl MouseEvent {
         Some(Self::ButtonDown {
             button: MouseButton::from_websys(event.button())?,
             position: mouse_position_from_websys(event),
+            modifiers: KeyModifiers::from_websys_mouse(event),
         })
     }
 
@@ -50,6 +65,12 @@ impl MouseEvent {
         })
     }
 
+    pub(crate) fn from_websys_locked_mouse_move(event: &web_sys::MouseEvent) -> Option<Self> {
+        Some(Self::LockedMove {
+            delta: mouse_delta_from_websys(event),
+        })
+    }
+
     pub(crate) fn from_websys_mouse_enter(_event: &web_sys::MouseEvent) -> Option<Self> {
         Some(Self::Enter)
     }
@@ -128,25 +149,39 @@ impl WheelDeltaMode {
     }
 }
 
+/// Mouse part of the [`InputState`](super::InputState).
 #[derive(Clone, Debug, Default)]
 pub struct MouseInputState {
     pub buttons: MouseButtonState,
     pub position: Option<Point2<f32>>,
     pub absolute_scroll: Vector3<f32>,
+    pub pointer_locked: bool,
+    buttons_just_pressed: MouseButtonState,
+    buttons_just_released: MouseButtonState,
 }
 
 impl MouseInputState {
-    pub fn push(&mut self, event: &MouseEvent) {
+    pub(super) fn apply(&mut self, event: &MouseEvent) {
         match event {
             MouseEvent::ButtonUp { button, .. } => {
+                if self.buttons.is_down(*button) {
+                    self.buttons_just_released.set_down(*button);
+                }
                 self.buttons.set_up(*button);
             }
             MouseEvent::ButtonDown { button, .. } => {
+                if !self.buttons.is_down(*button) {
+                    self.buttons_just_pressed.set_down(*button);
+                }
                 self.buttons.set_down(*button);
             }
             MouseEvent::Move { position, .. } => {
                 self.position = Some(*position);
             }
+            MouseEvent::LockedMove { .. } => {}
+            MouseEvent::PointerLockChanged { locked } => {
+                self.pointer_locked = *locked;
+            }
             MouseEvent::Enter => {}
             MouseEvent::Leave => {
                 self.position = None;
@@ -156,6 +191,61 @@ impl MouseInputState {
             }
         }
     }
+
+    pub(super) fn clear_edges(&mut self) {
+        self.buttons_just_pressed = Default::default();
+        self.buttons_just_released = Default::default();
+    }
+
+    /// Whether the button was pressed in the current tick.
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_just_pressed.is_down(button)
+    }
+
+    /// Whether the button was released in the current tick.
+    #[allow(dead_code)]
+    pub fn just_released(&self, button: MouseButton) -> bool {
+        self.buttons_just_released.is_down(button)
+    }
+}
+
+/// Pointer lock on an element, e.g. for a free-look camera.
+///
+/// While the pointer is locked, the cursor is hidden and can't hit the screen
+/// edges, and the element receives [`MouseEvent::LockedMove`] with the
+/// relative motion of the mouse. The browser releases the lock when Escape is
+/// pressed, and the [`Window`](crate::app::components::window::Window)
+/// releases it when the page loses focus.
+#[derive(Clone, Debug)]
+pub struct PointerLock {
+    element: web_sys::Element,
+}
+
+impl PointerLock {
+    pub fn new(element: web_sys::Element) -> Self {
+        Self { element }
+    }
+
+    /// Requests the pointer lock.
+    ///
+    /// Browsers only grant it shortly after user input, e.g. a click. Whether
+    /// it was granted is reported with [`MouseEvent::PointerLockChanged`].
+    pub fn request(&self) {
+        self.element.request_pointer_lock();
+    }
+
+    /// Releases the pointer lock, if the element holds it.
+    pub fn release(&self) {
+        if self.is_locked() {
+            gloo_utils::document().exit_pointer_lock();
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        gloo_utils::document()
+            .pointer_lock_element()
+            .map_or(false, |element| element == self.element)
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -2,37 +2,74 @@
 
 pub mod app;
 pub mod assets;
+pub mod audio;
+pub mod collision;
+pub mod colors;
+pub mod crash_report;
 pub mod ecs;
 pub mod error;
 pub mod graphics;
+pub mod i18n;
 pub mod input;
+pub mod notifications;
+pub mod raycast;
+pub mod selection;
+pub mod tween;
 pub mod universe;
 pub mod utils;
 
 use tracing::Level;
-use tracing_wasm::WASMLayerConfigBuilder;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_wasm::{
+    WASMLayer,
+    WASMLayerConfigBuilder,
+};
 use wasm_bindgen::JsCast;
 
-use crate::app::App;
+use crate::{
+    app::App,
+    crash_report::BreadcrumbLayer,
+    utils::trace::PerformanceLayer,
+};
 
 fn main() {
-    tracing_wasm::set_as_global_default_with_config(
-        WASMLayerConfigBuilder::new()
-            .set_max_level(Level::DEBUG)
-            .build(),
-    );
-    console_error_panic_hook::set_once();
+    init_tracing();
+    crash_report::set_panic_hook();
 
     tracing::info!("starting app");
 
-    let root = web_sys::window()
+    let document = web_sys::window()
         .expect("no window")
         .document()
-        .expect("no document")
+        .expect("no document");
+
+    let root = document
         .get_element_by_id("root")
         .expect("no root element")
         .dyn_into()
         .unwrap();
 
     leptos::mount_to(root, App);
+
+    // the loading screen from `index.html`, shown while the wasm module was
+    // fetched.
+    if let Some(loading) = document.get_element_by_id("loading") {
+        loading.remove();
+    }
+}
+
+/// Sets up logging to the browser console, and the layers that record
+/// breadcrumbs for crash reports and span timings.
+fn init_tracing() {
+    let subscriber = tracing_subscriber::registry()
+        .with(WASMLayer::new(
+            WASMLayerConfigBuilder::new()
+                .set_max_level(Level::DEBUG)
+                // the `PerformanceLayer` does this, with better names.
+                .set_report_logs_in_timings(false)
+                .build(),
+        ))
+        .with(BreadcrumbLayer)
+        .with(PerformanceLayer);
+    tracing::subscriber::set_global_default(subscriber).expect("failed to set tracing subscriber");
 }
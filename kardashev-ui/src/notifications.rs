@@ -0,0 +1,170 @@
+//! In-game notifications.
+//!
+//! Notifications can be sent from anywhere with a [`Notifications`] handle,
+//! which is also available as a resource to ECS systems. They are displayed
+//! as toasts by the [`Toasts`][crate::app::components::toast::Toasts]
+//! component.
+
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Default time after which a notification with this severity is
+    /// dismissed.
+    ///
+    /// Errors are not dismissed automatically.
+    pub fn default_timeout(&self) -> Option<Duration> {
+        match self {
+            Self::Info | Self::Success => Some(Duration::from_secs(5)),
+            Self::Warning => Some(Duration::from_secs(10)),
+            Self::Error => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NotificationId(u64);
+
+impl NotificationId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// A notification.
+///
+/// The title is translated when it's displayed, so it can be a message ID from
+/// the [catalog][crate::i18n::Catalog].
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub id: NotificationId,
+    pub severity: Severity,
+    pub title: String,
+    pub message: Option<String>,
+    pub timeout: Option<Duration>,
+    pub actions: Vec<NotificationAction>,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, title: impl Into<String>) -> Self {
+        Self {
+            id: NotificationId::next(),
+            severity,
+            title: title.into(),
+            message: None,
+            timeout: severity.default_timeout(),
+            actions: vec![],
+        }
+    }
+
+    pub fn info(title: impl Into<String>) -> Self {
+        Self::new(Severity::Info, title)
+    }
+
+    pub fn success(title: impl Into<String>) -> Self {
+        Self::new(Severity::Success, title)
+    }
+
+    pub fn warning(title: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, title)
+    }
+
+    pub fn error(title: impl Into<String>) -> Self {
+        Self::new(Severity::Error, title)
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Sets the time after which the notification is dismissed. With `None`
+    /// the notification stays until it's dismissed by the user.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adds a button to the notification. Clicking it runs `on_click` and
+    /// dismisses the notification.
+    pub fn with_action(
+        mut self,
+        label: impl Into<String>,
+        on_click: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        self.actions.push(NotificationAction {
+            label: label.into(),
+            on_click: Arc::new(on_click),
+        });
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationAction {
+    pub label: String,
+    pub on_click: Arc<dyn Fn() + Send + Sync>,
+}
+
+impl Debug for NotificationAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NotificationAction")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle to send notifications.
+///
+/// This is inserted as a resource, and provided as context.
+#[derive(Clone, Debug)]
+pub struct Notifications {
+    tx: mpsc::UnboundedSender<Notification>,
+}
+
+impl Notifications {
+    pub fn new() -> (Self, NotificationsReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, NotificationsReceiver { rx })
+    }
+
+    pub fn notify(&self, notification: Notification) -> NotificationId {
+        let id = notification.id;
+        tracing::debug!(?id, severity = ?notification.severity, title = %notification.title, "notification");
+        if self.tx.send(notification).is_err() {
+            tracing::warn!(?id, "notification receiver dropped");
+        }
+        id
+    }
+}
+
+#[derive(Debug)]
+pub struct NotificationsReceiver {
+    rx: mpsc::UnboundedReceiver<Notification>,
+}
+
+impl NotificationsReceiver {
+    pub async fn next(&mut self) -> Option<Notification> {
+        self.rx.recv().await
+    }
+}
@@ -0,0 +1,552 @@
+//! Raycasts against the triangles of meshes.
+//!
+//! Picking entities by their [`Selectable`] radius only tells how far the
+//! cursor is from an entity's origin. For large meshes, like planets, that's
+//! not precise enough: Clicks on their edge miss them, and there's no way to
+//! tell where on the surface the cursor is. The [`Raycaster`] intersects rays
+//! with the triangles of [`Mesh`]es instead. It builds a [`Bvh`] for a mesh on
+//! the first raycast that reaches it, and caches it per asset.
+//!
+//! Alt-clicking a mesh in the world view places a [`SurfaceMarker`] where the
+//! ray hit it.
+//!
+//! [`Selectable`]: crate::selection::Selectable
+
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    ops::Range,
+    sync::{
+        Arc,
+        Weak,
+    },
+};
+
+use hecs::Entity;
+use kardashev_protocol::assets::{
+    AssetId,
+    WindingOrder,
+};
+use nalgebra::{
+    Point2,
+    Point3,
+    Similarity3,
+    Translation3,
+    Unit,
+    UnitQuaternion,
+    Vector3,
+};
+
+use crate::{
+    assets::MaybeHasAssetId,
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+    },
+    graphics::{
+        gizmo::Line,
+        mesh::{
+            CpuMesh,
+            Mesh,
+            PrimitiveTopology,
+        },
+        transform::{
+            GlobalTransform,
+            Parent,
+            Transform,
+        },
+    },
+    selection::SelectionCamera,
+};
+
+/// Triangles in a leaf of a [`Bvh`].
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Length of the line that shows a [`SurfaceMarker`], in world units.
+const MARKER_LENGTH: f32 = 0.25;
+
+const MARKER_TINT: Tint = Tint {
+    token: Token::EmphasisLight,
+    alpha: 1.0,
+    fill_alpha: None,
+};
+
+/// A ray, starting at `origin`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+
+    /// Distances along the ray are multiples of this vector, so it's usually
+    /// normalized.
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self { origin, direction }
+    }
+
+    /// Ray from the near plane of the camera through `point` on the screen.
+    pub fn from_screen(camera: &SelectionCamera, point: Point2<f32>) -> Option<Self> {
+        let (near, far) =
+            camera
+                .projection
+                .screen_to_ray(&camera.transform, &point, camera.surface_size)?;
+        Some(Self::new(near, (far - near).try_normalize(f32::EPSILON)?))
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// The ray in the local space of a model. Distances along it are the same
+    /// as along this ray.
+    fn to_local(&self, model_matrix: &Similarity3<f32>) -> Self {
+        Self {
+            origin: model_matrix.inverse_transform_point(&self.origin),
+            direction: model_matrix.inverse_transform_vector(&self.direction),
+        }
+    }
+}
+
+/// Where a ray hit a mesh, in the mesh's local space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeshHit {
+    pub distance: f32,
+
+    /// Surface normal, interpolated from the vertex normals.
+    pub normal: Unit<Vector3<f32>>,
+}
+
+/// Where a ray hit an entity's mesh, in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub position: Point3<f32>,
+    pub normal: Unit<Vector3<f32>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: Point3<f32>,
+    max: Point3<f32>,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Self {
+            min: Point3::from([f32::INFINITY; 3]),
+            max: Point3::from([f32::NEG_INFINITY; 3]),
+        }
+    }
+
+    fn grow(&mut self, point: &Point3<f32>) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    /// Returns whether the ray enters the box before `max_distance`.
+    fn intersects(&self, ray: &Ray, max_distance: f32) -> bool {
+        let mut near = 0.0f32;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // `max` and `min` ignore the NaNs of rays parallel to a face.
+            near = near.max(t0);
+            far = far.min(t1);
+            if far < near {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Triangle {
+    /// Counter-clockwise.
+    vertices: [Point3<f32>; 3],
+    normals: [Vector3<f32>; 3],
+}
+
+impl Triangle {
+    fn centroid(&self) -> Point3<f32> {
+        Point3::from(
+            (self.vertices[0].coords + self.vertices[1].coords + self.vertices[2].coords) / 3.0,
+        )
+    }
+
+    /// Intersects the triangle with the ray (Möller-Trumbore), from both
+    /// sides.
+    fn intersect(&self, ray: &Ray, max_distance: f32) -> Option<MeshHit> {
+        let [v0, v1, v2] = self.vertices;
+        let edge1 = v1 - v0;
+        let edge2 = v2 - v0;
+
+        let p = ray.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant == 0.0 {
+            // the ray is parallel to the triangle.
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+
+        let s = ray.origin - v0;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = ray.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inverse;
+        if !(0.0..=max_distance).contains(&distance) {
+            return None;
+        }
+
+        let normal = self.normals[0] * (1.0 - u - v) + self.normals[1] * u + self.normals[2] * v;
+        let normal = Unit::try_new(normal, f32::EPSILON)
+            .or_else(|| Unit::try_new(edge1.cross(&edge2), 0.0))?;
+
+        Some(MeshHit { distance, normal })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    bounds: Aabb,
+    triangles: Range<usize>,
+    children: Option<[usize; 2]>,
+}
+
+/// Bounding volume hierarchy over the triangles of a mesh.
+#[derive(Clone, Debug)]
+pub struct Bvh {
+    /// The root is the first node.
+    nodes: Vec<Node>,
+    triangles: Vec<Triangle>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy by splitting the triangles at the median of the
+    /// longest axis of their bounds.
+    ///
+    /// Meshes that aren't triangle lists have no triangles that rays could
+    /// hit.
+    pub fn new(mesh: &CpuMesh) -> Self {
+        let mut triangles = vec![];
+        if mesh.primitive_topology == PrimitiveTopology::TriangleList {
+            for indices in mesh.indices.chunks_exact(3) {
+                let mut vertices = [indices[0], indices[1], indices[2]]
+                    .map(|index| mesh.vertices[usize::from(index)]);
+                if mesh.winding_order == WindingOrder::Clockwise {
+                    vertices.swap(1, 2);
+                }
+                triangles.push(Triangle {
+                    vertices: vertices.map(|vertex| Point3::from(vertex.position)),
+                    normals: vertices.map(|vertex| Vector3::from(vertex.normal)),
+                });
+            }
+        }
+
+        let mut nodes = vec![];
+        if !triangles.is_empty() {
+            build_node(&mut nodes, &mut triangles, 0);
+        }
+
+        Self { nodes, triangles }
+    }
+
+    /// Returns the closest hit of the ray within `max_distance`.
+    pub fn cast(&self, ray: &Ray, max_distance: f32) -> Option<MeshHit> {
+        let mut closest: Option<MeshHit> = None;
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        }
+        else {
+            vec![0]
+        };
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let max_distance = closest.map_or(max_distance, |hit| hit.distance);
+            if !node.bounds.intersects(ray, max_distance) {
+                continue;
+            }
+
+            if let Some(children) = node.children {
+                stack.extend(children);
+            }
+            else {
+                for triangle in &self.triangles[node.triangles.clone()] {
+                    let max_distance = closest.map_or(max_distance, |hit| hit.distance);
+                    if let Some(hit) = triangle.intersect(ray, max_distance) {
+                        closest = Some(hit);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// Builds the node for `triangles`, which start at `offset` in the BVH's
+/// triangles, and returns its index.
+fn build_node(nodes: &mut Vec<Node>, triangles: &mut [Triangle], offset: usize) -> usize {
+    let mut bounds = Aabb::empty();
+    for triangle in triangles.iter() {
+        for vertex in &triangle.vertices {
+            bounds.grow(vertex);
+        }
+    }
+
+    let index = nodes.len();
+    nodes.push(Node {
+        bounds,
+        triangles: offset..offset + triangles.len(),
+        children: None,
+    });
+
+    if triangles.len() > MAX_LEAF_SIZE {
+        let axis = (bounds.max - bounds.min).imax();
+        let middle = triangles.len() / 2;
+        triangles.select_nth_unstable_by(middle, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+        let (left, right) = triangles.split_at_mut(middle);
+        let left = build_node(nodes, left, offset);
+        let right = build_node(nodes, right, offset + middle);
+        nodes[index].children = Some([left, right]);
+    }
+
+    index
+}
+
+/// Resource that casts rays against the meshes in the world.
+#[derive(Debug, Default)]
+pub struct Raycaster {
+    /// The mesh data is kept to notice when an asset is reloaded.
+    bvhs: HashMap<AssetId, (Weak<CpuMesh>, Arc<Bvh>)>,
+}
+
+impl Raycaster {
+    /// Returns the closest hit of the ray on the meshes of entities for which
+    /// `filter` returns `true`.
+    pub fn cast(
+        &mut self,
+        world: &hecs::World,
+        ray: &Ray,
+        mut filter: impl FnMut(Entity) -> bool,
+    ) -> Option<RaycastHit> {
+        let mut closest: Option<RaycastHit> = None;
+
+        let mut query = world.query::<(&Mesh, &GlobalTransform)>();
+        for (entity, (mesh, transform)) in query.iter() {
+            if !filter(entity) {
+                continue;
+            }
+            let Some(bvh) = self.bvh(mesh)
+            else {
+                continue;
+            };
+
+            let max_distance = closest.map_or(f32::INFINITY, |hit| hit.distance);
+            let Some(hit) = bvh.cast(&ray.to_local(&transform.model_matrix), max_distance)
+            else {
+                continue;
+            };
+
+            closest = Some(RaycastHit {
+                entity,
+                distance: hit.distance,
+                position: ray.at(hit.distance),
+                normal: transform.model_matrix.isometry.rotation * hit.normal,
+            });
+        }
+
+        closest
+    }
+
+    fn bvh(&mut self, mesh: &Mesh) -> Option<Arc<Bvh>> {
+        let cpu = mesh.cpu_shared()?;
+
+        let Some(asset_id) = mesh.maybe_asset_id()
+        else {
+            // meshes that aren't assets are built in code, and usually small.
+            return Some(Arc::new(Bvh::new(cpu)));
+        };
+
+        if let Some((cached_mesh, bvh)) = self.bvhs.get(&asset_id) {
+            if cached_mesh.as_ptr() == Arc::as_ptr(cpu) {
+                return Some(bvh.clone());
+            }
+        }
+
+        let bvh = Arc::new(Bvh::new(cpu));
+        self.bvhs
+            .insert(asset_id, (Arc::downgrade(cpu), bvh.clone()));
+        Some(bvh)
+    }
+}
+
+/// A marker on the surface of a mesh. It's a child of the entity with the
+/// mesh, and shown as a line along the surface normal.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SurfaceMarker;
+
+/// Places a [`SurfaceMarker`] where the ray hits a mesh. If it doesn't hit
+/// anything, all markers are removed.
+pub fn place_surface_marker(system_context: &mut SystemContext, ray: &Ray) {
+    let hit = system_context
+        .resources
+        .get_mut_or_insert_default::<Raycaster>()
+        .cast(system_context.world, ray, |_| true);
+
+    let Some(hit) = hit
+    else {
+        let markers = system_context
+            .world
+            .query_mut::<()>()
+            .with::<&SurfaceMarker>()
+            .into_iter()
+            .map(|(entity, ())| entity)
+            .collect::<Vec<_>>();
+        for entity in markers {
+            system_context.command_buffer.despawn(entity);
+        }
+        return;
+    };
+
+    let Ok(parent) = system_context
+        .world
+        .get::<&GlobalTransform>(hit.entity)
+        .map(|transform| transform.model_matrix)
+    else {
+        return;
+    };
+
+    // the marker's Y axis points along the normal.
+    let rotation = UnitQuaternion::rotation_between(&Vector3::y(), &hit.normal.into_inner())
+        .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI));
+    let global = Similarity3::from_parts(Translation3::from(hit.position.coords), rotation, 1.0);
+
+    let color = MARKER_TINT.color(ActivePalette::get(&system_context.resources));
+    system_context.command_buffer.spawn((
+        Transform {
+            model_matrix: global * parent.inverse(),
+        },
+        Parent { entity: hit.entity },
+        SurfaceMarker,
+        MARKER_TINT,
+        Line::new(
+            hit.position,
+            hit.position + hit.normal.into_inner() * MARKER_LENGTH,
+            color,
+        ),
+    ));
+}
+
+/// Moves the lines of the markers with the meshes they're on, and removes
+/// markers whose mesh is gone.
+fn surface_marker_system(system_context: &mut SystemContext) {
+    let mut query = system_context
+        .world
+        .query::<(&Parent, Option<&GlobalTransform>, &mut Line)>()
+        .with::<&SurfaceMarker>();
+
+    for (entity, (parent, transform, line)) in query.iter() {
+        if !system_context.world.contains(parent.entity) {
+            system_context.command_buffer.despawn(entity);
+            continue;
+        }
+        if let Some(transform) = transform {
+            line.from = transform.model_matrix.transform_point(&Point3::origin());
+            line.to =
+                line.from + transform.model_matrix.transform_vector(&Vector3::y()) * MARKER_LENGTH;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RaycastPlugin;
+
+impl Plugin for RaycastPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.resources.insert(Raycaster::default());
+        context.schedule.add_system(surface_marker_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{
+        Point3,
+        Vector3,
+    };
+
+    use super::{
+        Bvh,
+        Ray,
+    };
+    use crate::graphics::mesh::{
+        shape,
+        MeshBuilder,
+        Meshable,
+    };
+
+    #[test]
+    fn rays_hit_the_surface_of_a_sphere() {
+        let bvh = Bvh::new(&shape::Sphere::new(2.0).mesh().build());
+
+        let hit = bvh
+            .cast(
+                &Ray::new(Point3::new(0.0, 0.0, 10.0), -Vector3::z()),
+                f32::INFINITY,
+            )
+            .unwrap();
+        assert!((hit.distance - 8.0).abs() < 0.05, "{}", hit.distance);
+        assert!(hit.normal.dot(&Vector3::z()) > 0.99);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 10.0), -Vector3::z());
+        assert!(bvh.cast(&ray, 5.0).is_none());
+
+        let ray = Ray::new(Point3::new(0.0, 2.5, 10.0), -Vector3::z());
+        assert!(bvh.cast(&ray, f32::INFINITY).is_none());
+    }
+
+    #[test]
+    fn rays_hit_the_closest_triangle() {
+        let bvh = Bvh::new(&shape::Sphere::new(1.0).mesh().build());
+
+        for direction in [Vector3::x(), -Vector3::y(), Vector3::new(1.0, 1.0, -1.0)] {
+            let direction = direction.normalize();
+            let hit = bvh
+                .cast(
+                    &Ray::new(Point3::origin() - direction * 5.0, direction),
+                    f32::INFINITY,
+                )
+                .unwrap();
+            assert!((hit.distance - 4.0).abs() < 0.05, "{}", hit.distance);
+            assert!(hit.normal.dot(&direction) < -0.99);
+        }
+    }
+}
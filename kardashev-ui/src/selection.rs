@@ -0,0 +1,339 @@
+//! Selection of entities.
+//!
+//! Entities with a [`Selectable`] component can be selected by clicking on
+//! them in a world view, or by dragging a marquee around them. Selected
+//! entities have a [`Selected`] component. Whenever the set of selected
+//! entities changes, a [`SelectionChanged`] event is published, to which UI
+//! components can subscribe with [`use_selection`].
+
+use hecs::Entity;
+use leptos::{
+    create_rw_signal,
+    expect_context,
+    on_cleanup,
+    ReadSignal,
+    SignalSet,
+};
+use nalgebra::{
+    Point2,
+    Point3,
+    Similarity3,
+};
+use tokio::sync::watch;
+
+use crate::{
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        server::WorldServer,
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        camera::CameraProjection,
+        transform::GlobalTransform,
+        SurfaceSize,
+    },
+    raycast::{
+        Ray,
+        Raycaster,
+    },
+    utils::futures::spawn_local,
+};
+
+/// Marks an entity as selectable.
+#[derive(Clone, Copy, Debug)]
+pub struct Selectable {
+    /// Radius around the entity's origin that can be clicked to select it.
+    pub radius: f32,
+}
+
+impl Selectable {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+/// Marks an entity as selected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Selected;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Replace the current selection.
+    Replace,
+
+    /// Add to the current selection.
+    Add,
+
+    /// Toggle entities in the current selection.
+    Toggle,
+}
+
+/// Area on the screen in which to select entities, in pixel coordinates.
+#[derive(Clone, Copy, Debug)]
+pub enum SelectionArea {
+    Point(Point2<f32>),
+    Rect { min: Point2<f32>, max: Point2<f32> },
+}
+
+impl SelectionArea {
+    /// Creates a rectangular area from two opposing corners.
+    pub fn rect(a: Point2<f32>, b: Point2<f32>) -> Self {
+        Self::Rect {
+            min: a.inf(&b),
+            max: a.sup(&b),
+        }
+    }
+}
+
+/// Camera through which entities are selected.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectionCamera {
+    pub transform: Similarity3<f32>,
+    pub projection: CameraProjection,
+    pub surface_size: SurfaceSize,
+}
+
+impl SelectionCamera {
+    /// Returns the selectable entity at `point` on the screen, if any.
+    ///
+    /// If there are multiple entities, the one closest to the camera is
+    /// returned.
+    pub fn pick(
+        &self,
+        world: &hecs::World,
+        raycaster: &mut Raycaster,
+        point: Point2<f32>,
+    ) -> Option<Entity> {
+        SelectionRequest {
+            camera: *self,
+            area: SelectionArea::Point(point),
+            mode: SelectionMode::Replace,
+        }
+        .pick(world, raycaster)
+        .into_iter()
+        .next()
+    }
+}
+
+/// Request to change the selection.
+#[derive(Clone, Copy, Debug)]
+pub struct SelectionRequest {
+    pub camera: SelectionCamera,
+    pub area: SelectionArea,
+    pub mode: SelectionMode,
+}
+
+impl SelectionRequest {
+    /// Minimum distance in pixels from an entity at which a click will still
+    /// select it.
+    const PICK_TOLERANCE: f32 = 8.0;
+
+    /// Returns the entities in the selection area.
+    ///
+    /// For a point only the entity closest to the camera is returned. Entities
+    /// with a mesh are also picked where the cursor is over one of its
+    /// triangles.
+    fn pick(&self, world: &hecs::World, raycaster: &mut Raycaster) -> Vec<Entity> {
+        let camera_position = self.camera.transform.transform_point(&Point3::origin());
+
+        let mut query = world.query::<(&Selectable, &GlobalTransform)>();
+        let candidates = query
+            .iter()
+            .filter_map(|(entity, (selectable, transform))| {
+                let position = transform.model_matrix.transform_point(&Point3::origin());
+                let screen_position = self.camera.projection.project_to_screen(
+                    &self.camera.transform,
+                    &position,
+                    self.camera.surface_size,
+                )?;
+                let distance = (position - camera_position).norm();
+                Some((entity, selectable, screen_position, distance))
+            });
+
+        match self.area {
+            SelectionArea::Point(point) => {
+                let closest = candidates
+                    .filter(|(_, selectable, screen_position, distance)| {
+                        let radius = selectable.radius
+                            / self
+                                .camera
+                                .projection
+                                .pixel_size_at(*distance, self.camera.surface_size);
+                        (screen_position - point).norm() <= radius.max(Self::PICK_TOLERANCE)
+                    })
+                    .map(|(entity, _, _, distance)| (entity, distance))
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                let hit = Ray::from_screen(&self.camera, point)
+                    .and_then(|ray| {
+                        raycaster.cast(world, &ray, |entity| {
+                            world.satisfies::<&Selectable>(entity).unwrap_or_default()
+                        })
+                    })
+                    .map(|hit| (hit.entity, (hit.position - camera_position).norm()));
+
+                closest
+                    .into_iter()
+                    .chain(hit)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(entity, _)| entity)
+                    .into_iter()
+                    .collect()
+            }
+            SelectionArea::Rect { min, max } => {
+                candidates
+                    .filter(|(_, _, screen_position, _)| {
+                        screen_position.x >= min.x
+                            && screen_position.x <= max.x
+                            && screen_position.y >= min.y
+                            && screen_position.y <= max.y
+                    })
+                    .map(|(entity, ..)| entity)
+                    .collect()
+            }
+        }
+    }
+
+    /// Applies the selection request to the world.
+    pub fn apply(&self, system_context: &mut SystemContext) {
+        let raycaster = system_context
+            .resources
+            .get_mut_or_insert_default::<Raycaster>();
+        let picked = self.pick(system_context.world, raycaster);
+        select(system_context.world, picked, self.mode);
+    }
+}
+
+/// Changes the selection to include `entities`.
+///
+/// This can be used to select entities without picking them on the screen,
+/// e.g. from a list.
+pub fn select(
+    world: &mut hecs::World,
+    entities: impl IntoIterator<Item = Entity>,
+    mode: SelectionMode,
+) {
+    if mode == SelectionMode::Replace {
+        let selected = world
+            .query_mut::<()>()
+            .with::<&Selected>()
+            .into_iter()
+            .map(|(entity, ())| entity)
+            .collect::<Vec<_>>();
+        for entity in selected {
+            let _ = world.remove_one::<Selected>(entity);
+        }
+    }
+
+    for entity in entities {
+        if mode == SelectionMode::Toggle && world.satisfies::<&Selected>(entity).unwrap_or_default()
+        {
+            let _ = world.remove_one::<Selected>(entity);
+        }
+        else {
+            let _ = world.insert_one(entity, Selected);
+        }
+    }
+}
+
+/// Event that is published when the selection changes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelectionChanged {
+    pub selected: Vec<SelectedEntity>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectedEntity {
+    pub entity: Entity,
+    pub label: Option<String>,
+}
+
+/// Resource used to publish [`SelectionChanged`] events.
+#[derive(Debug)]
+pub struct SelectionEvents {
+    tx: watch::Sender<SelectionChanged>,
+}
+
+impl SelectionEvents {
+    pub fn subscribe(&self) -> watch::Receiver<SelectionChanged> {
+        self.tx.subscribe()
+    }
+}
+
+fn selection_changed_system(system_context: &mut SystemContext) {
+    let Some(events) = system_context.resources.get::<SelectionEvents>()
+    else {
+        return;
+    };
+
+    let mut selected = system_context
+        .world
+        .query_mut::<Option<&Label>>()
+        .with::<&Selected>()
+        .into_iter()
+        .map(|(entity, label)| {
+            SelectedEntity {
+                entity,
+                label: label.map(|label| label.label.to_string()),
+            }
+        })
+        .collect::<Vec<_>>();
+    selected.sort_by_key(|selected| selected.entity);
+
+    events.tx.send_if_modified(|event| {
+        if event.selected != selected {
+            tracing::debug!(num_selected = selected.len(), "selection changed");
+            event.selected = selected;
+            true
+        }
+        else {
+            false
+        }
+    });
+}
+
+/// Returns a signal with the current selection.
+///
+/// This must be called after the [`WorldServer`] was provided.
+pub fn use_selection() -> ReadSignal<SelectionChanged> {
+    let selection = create_rw_signal(SelectionChanged::default());
+    let world = expect_context::<WorldServer>();
+
+    let join_handle = spawn_local(async move {
+        let mut events = world
+            .run(|system_context| {
+                system_context
+                    .resources
+                    .get::<SelectionEvents>()
+                    .map(|events| events.subscribe())
+            })
+            .await?;
+
+        loop {
+            selection.set(events.borrow_and_update().clone());
+            if events.changed().await.is_err() {
+                break;
+            }
+        }
+
+        Some(())
+    });
+    on_cleanup(move || join_handle.abort());
+
+    selection.read_only()
+}
+
+#[derive(Debug, Default)]
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let (tx, _rx) = watch::channel(SelectionChanged::default());
+        context.resources.insert(SelectionEvents { tx });
+        context.schedule.add_system(selection_changed_system);
+    }
+}
@@ -0,0 +1,569 @@
+//! Animating values over time.
+//!
+//! Components are animated by inserting a [`Tween`] for the component into
+//! its entity with [`start_tween`]. A tween consists of steps that are played
+//! one after another. Each step animates a field of the component from the
+//! value it has when the step starts to a target value. When all steps are
+//! done, the tween is removed and a [`TweenFinished`] event is published, to
+//! which e.g. UI components can subscribe.
+//!
+//! Values in the UI can be animated with [`use_tweened`].
+//!
+//! With reduced motion, see [`Accessibility`], tweens jump to their end
+//! instead of animating.
+
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    time::Duration,
+};
+
+use hecs::{
+    Component,
+    Entity,
+};
+use leptos::{
+    create_effect,
+    create_rw_signal,
+    request_animation_frame,
+    store_value,
+    use_context,
+    RwSignal,
+    Signal,
+    SignalGet,
+    SignalGetUntracked,
+    SignalSet,
+    StoredValue,
+};
+use nalgebra::{
+    Isometry3,
+    Point3,
+    Vector3,
+};
+use palette::{
+    Mix,
+    Srgba,
+};
+use tokio::sync::broadcast;
+
+use crate::{
+    app::accessibility::{
+        Accessibility,
+        AccessibilityContext,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+    },
+    graphics::{
+        camera::{
+            CameraProjection,
+            ClearColor,
+            Projection,
+        },
+        transform::Transform,
+    },
+    utils::{
+        easing::Easing,
+        time::Instant,
+    },
+};
+
+/// Values that can be interpolated.
+pub trait Lerp: Clone + Send + Sync + 'static {
+    /// Interpolates between `self` at `t = 0` and `to` at `t = 1`.
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * f64::from(t)
+    }
+}
+
+impl Lerp for Point3<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Point3::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Vector3<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Vector3::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Isometry3<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self.lerp_slerp(to, t)
+    }
+}
+
+impl Lerp for Srgba<f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        // mixing in linear space, so that the brightness changes evenly.
+        Srgba::from_linear(self.into_linear().mix(to.into_linear(), t))
+    }
+}
+
+impl Lerp for Projection {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        Projection::lerp(self, to, t)
+    }
+}
+
+/// Identifies a [`Tween`] in [`TweenFinished`] events.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TweenId(u64);
+
+impl TweenId {
+    fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Animates fields of the component `C` of the entity it's attached to.
+///
+/// Tweens are only played for components for which the [`TweenPlugin`]
+/// registered a system.
+pub struct Tween<C> {
+    id: TweenId,
+    steps: VecDeque<TweenStep<C>>,
+
+    /// When the current step started. This is set in the first tick of the
+    /// step.
+    step_started: Option<Instant>,
+}
+
+impl<C: Component> Tween<C> {
+    /// Creates a tween that animates the field returned by `field` to `to`.
+    pub fn new<T: Lerp>(field: fn(&mut C) -> &mut T, to: T, duration: Duration) -> Self {
+        Self {
+            id: TweenId::new(),
+            steps: VecDeque::new(),
+            step_started: None,
+        }
+        .then(field, to, duration)
+    }
+
+    /// Adds a step that animates the field returned by `field` to `to`, after
+    /// the previous steps are done.
+    pub fn then<T: Lerp>(mut self, field: fn(&mut C) -> &mut T, to: T, duration: Duration) -> Self {
+        self.steps.push_back(TweenStep {
+            animation: Box::new(FieldAnimation {
+                field,
+                from: None,
+                to,
+            }),
+            duration,
+            easing: Easing::default(),
+        });
+        self
+    }
+
+    /// Sets the easing of the last step.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        if let Some(step) = self.steps.back_mut() {
+            step.easing = easing;
+        }
+        self
+    }
+
+    pub fn id(&self) -> TweenId {
+        self.id
+    }
+
+    /// Applies all steps at once, as if their time had passed.
+    ///
+    /// Returns `true` like [`update`](Self::update) does when all steps are
+    /// done.
+    fn skip(&mut self, component: &mut C, now: Instant) -> bool {
+        for step in &mut self.steps {
+            step.duration = Duration::ZERO;
+        }
+        self.update(component, now)
+    }
+
+    /// Advances the tween and applies it to the component.
+    ///
+    /// Returns `true` if all steps are done.
+    fn update(&mut self, component: &mut C, now: Instant) -> bool {
+        while let Some(step) = self.steps.front_mut() {
+            let started = *self.step_started.get_or_insert_with(|| {
+                step.animation.begin(component);
+                now
+            });
+
+            let t = if step.duration.is_zero() {
+                1.0
+            }
+            else {
+                (now.duration_since(started).as_secs_f32() / step.duration.as_secs_f32()).min(1.0)
+            };
+            step.animation.apply(component, step.easing.apply(t));
+
+            if t < 1.0 {
+                return false;
+            }
+
+            // the next step starts where this one ended, so that chained steps don't drift.
+            let duration = step.duration;
+            self.steps.pop_front();
+            self.step_started = Some(started + duration);
+            if let Some(next) = self.steps.front_mut() {
+                next.animation.begin(component);
+            }
+        }
+
+        true
+    }
+}
+
+impl<C> Debug for Tween<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tween")
+            .field("id", &self.id)
+            .field("num_steps", &self.steps.len())
+            .field("step_started", &self.step_started)
+            .finish()
+    }
+}
+
+struct TweenStep<C> {
+    animation: Box<dyn Animation<C>>,
+    duration: Duration,
+    easing: Easing,
+}
+
+trait Animation<C>: Send + Sync {
+    /// Called when the step starts, with the component's current value.
+    fn begin(&mut self, component: &mut C);
+
+    fn apply(&self, component: &mut C, t: f32);
+}
+
+struct FieldAnimation<C, T> {
+    field: fn(&mut C) -> &mut T,
+    from: Option<T>,
+    to: T,
+}
+
+impl<C, T: Lerp> Animation<C> for FieldAnimation<C, T> {
+    fn begin(&mut self, component: &mut C) {
+        self.from = Some((self.field)(component).clone());
+    }
+
+    fn apply(&self, component: &mut C, t: f32) {
+        let from = self
+            .from
+            .as_ref()
+            .expect("animation applied before it began");
+        *(self.field)(component) = from.lerp(&self.to, t);
+    }
+}
+
+/// Event that is published when a [`Tween`] is done or was replaced by
+/// another one.
+#[derive(Clone, Copy, Debug)]
+pub struct TweenFinished {
+    pub entity: Entity,
+    pub id: TweenId,
+
+    /// `false` if the tween was replaced before it was done.
+    pub completed: bool,
+}
+
+/// Resource used to publish [`TweenFinished`] events.
+#[derive(Debug)]
+pub struct TweenEvents {
+    tx: broadcast::Sender<TweenFinished>,
+}
+
+impl TweenEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<TweenFinished> {
+        self.tx.subscribe()
+    }
+
+    fn publish(&self, event: TweenFinished) {
+        // no one might be listening.
+        let _ = self.tx.send(event);
+    }
+}
+
+/// Starts animating the component `C` of `entity`.
+///
+/// A tween for the same component that is still running is replaced.
+pub fn start_tween<C: Component>(
+    system_context: &mut SystemContext,
+    entity: Entity,
+    tween: Tween<C>,
+) -> TweenId {
+    let id = tween.id;
+    if let Ok(replaced) = system_context.world.remove_one::<Tween<C>>(entity) {
+        if let Some(events) = system_context.resources.get::<TweenEvents>() {
+            events.publish(TweenFinished {
+                entity,
+                id: replaced.id,
+                completed: false,
+            });
+        }
+    }
+    let _ = system_context.world.insert_one(entity, tween);
+    id
+}
+
+fn tween_system<C: Component>(system_context: &mut SystemContext) {
+    let now = Instant::now();
+    let reduced_motion = system_context
+        .resources
+        .get::<Accessibility>()
+        .map_or(false, |accessibility| accessibility.reduced_motion);
+    let mut finished = vec![];
+
+    for (entity, (tween, component)) in system_context.world.query_mut::<(&mut Tween<C>, &mut C)>()
+    {
+        let done = if reduced_motion {
+            tween.skip(component, now)
+        }
+        else {
+            tween.update(component, now)
+        };
+        if done {
+            finished.push((entity, tween.id));
+        }
+    }
+
+    let events = system_context.resources.get::<TweenEvents>();
+    for (entity, id) in finished {
+        let _ = system_context.world.remove_one::<Tween<C>>(entity);
+        if let Some(events) = events {
+            events.publish(TweenFinished {
+                entity,
+                id,
+                completed: true,
+            });
+        }
+    }
+}
+
+/// Plays [`Tween`]s for transforms, camera projections and clear colors.
+/// Tweens for other components can be added with
+/// [`with_component`](Self::with_component).
+#[derive(Debug)]
+pub struct TweenPlugin {
+    systems: Vec<fn(&mut SystemContext)>,
+}
+
+impl TweenPlugin {
+    #[allow(dead_code)]
+    pub fn with_component<C: Component>(mut self) -> Self {
+        self.systems.push(tween_system::<C>);
+        self
+    }
+}
+
+impl Default for TweenPlugin {
+    fn default() -> Self {
+        Self {
+            systems: vec![
+                tween_system::<Transform>,
+                tween_system::<CameraProjection>,
+                tween_system::<ClearColor>,
+            ],
+        }
+    }
+}
+
+impl Plugin for TweenPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        let (tx, _rx) = broadcast::channel(16);
+        context.resources.insert(TweenEvents { tx });
+        for system in self.systems {
+            context.schedule.add_system(system);
+        }
+    }
+}
+
+/// Returns a signal that follows `target`, but animates changes of it.
+///
+/// With reduced motion, the signal follows `target` without animation.
+pub fn use_tweened<T: Lerp + PartialEq>(
+    target: impl Into<Signal<T>>,
+    duration: Duration,
+    easing: Easing,
+) -> Signal<T> {
+    let target = target.into();
+    let value = create_rw_signal(target.get_untracked());
+    let animation = store_value(None::<UiAnimation<T>>);
+    let reduced_motion =
+        use_context::<AccessibilityContext>().map(|accessibility| accessibility.reduced_motion);
+
+    create_effect(move |_| {
+        let to = target.get();
+        if value.get_untracked() == to {
+            return;
+        }
+        if reduced_motion.map_or(false, |reduced_motion| reduced_motion.get_untracked()) {
+            // a running animation ends at the next frame, with the new value.
+            animation.update_value(|animation| {
+                if let Some(animation) = animation {
+                    animation.from = to.clone();
+                    animation.to = to.clone();
+                }
+            });
+            value.set(to);
+            return;
+        }
+        let running = animation.with_value(Option::is_some);
+        animation.set_value(Some(UiAnimation {
+            from: value.get_untracked(),
+            to,
+            started: Instant::now(),
+        }));
+        if !running {
+            request_animation_frame(move || animate_frame(value, animation, duration, easing));
+        }
+    });
+
+    value.into()
+}
+
+struct UiAnimation<T> {
+    from: T,
+    to: T,
+    started: Instant,
+}
+
+fn animate_frame<T: Lerp>(
+    value: RwSignal<T>,
+    animation: StoredValue<Option<UiAnimation<T>>>,
+    duration: Duration,
+    easing: Easing,
+) {
+    let Some(Some((next, done))) = animation.try_with_value(|animation| {
+        animation.as_ref().map(|animation| {
+            let t = if duration.is_zero() {
+                1.0
+            }
+            else {
+                (animation.started.elapsed().as_secs_f32() / duration.as_secs_f32()).min(1.0)
+            };
+            (
+                animation.from.lerp(&animation.to, easing.apply(t)),
+                t >= 1.0,
+            )
+        })
+    })
+    else {
+        // the signal was disposed, or the animation is done.
+        return;
+    };
+
+    value.set(next);
+    if done {
+        animation.set_value(None);
+    }
+    else {
+        request_animation_frame(move || animate_frame(value, animation, duration, easing));
+    }
+}
+
+/// Field accessors for tweens, e.g. `Tween::new(fields::isometry, ...)`.
+pub mod fields {
+    use nalgebra::Isometry3;
+    use palette::Srgba;
+
+    use crate::graphics::{
+        camera::{
+            CameraProjection,
+            ClearColor,
+            Projection,
+        },
+        transform::Transform,
+    };
+
+    pub fn isometry(transform: &mut Transform) -> &mut Isometry3<f32> {
+        &mut transform.model_matrix.isometry
+    }
+
+    pub fn projection(camera_projection: &mut CameraProjection) -> &mut Projection {
+        &mut camera_projection.projection
+    }
+
+    #[allow(dead_code)]
+    pub fn clear_color(clear_color: &mut ClearColor) -> &mut Srgba<f32> {
+        &mut clear_color.clear_color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Tween;
+    use crate::utils::{
+        easing::Easing,
+        time::Instant,
+    };
+
+    #[derive(Debug, Default)]
+    struct Value {
+        x: f32,
+        y: f32,
+    }
+
+    fn x(value: &mut Value) -> &mut f32 {
+        &mut value.x
+    }
+
+    fn y(value: &mut Value) -> &mut f32 {
+        &mut value.y
+    }
+
+    #[test]
+    fn chained_steps_are_played_one_after_another() {
+        let second = Duration::from_secs(1);
+        let mut tween = Tween::new(x, 1.0, second)
+            .with_easing(Easing::Linear)
+            .then(y, 2.0, second)
+            .with_easing(Easing::Linear);
+        let mut value = Value::default();
+        let start = Instant::now();
+
+        assert!(!tween.update(&mut value, start));
+        assert!(!tween.update(&mut value, start + second / 2));
+        assert_eq!((value.x, value.y), (0.5, 0.0));
+
+        assert!(!tween.update(&mut value, start + second * 3 / 2));
+        assert_eq!((value.x, value.y), (1.0, 1.0));
+
+        assert!(tween.update(&mut value, start + second * 3));
+        assert_eq!((value.x, value.y), (1.0, 2.0));
+    }
+
+    #[test]
+    fn skipped_tweens_end_at_once() {
+        let second = Duration::from_secs(1);
+        let mut tween = Tween::new(x, 1.0, second).then(y, 2.0, second);
+        let mut value = Value::default();
+        let start = Instant::now();
+
+        assert!(!tween.update(&mut value, start));
+        assert!(tween.skip(&mut value, start + second / 2));
+        assert_eq!((value.x, value.y), (1.0, 2.0));
+    }
+}
@@ -0,0 +1,159 @@
+//! Mapping of server entities to the entities they're spawned as.
+//!
+//! Server entities are identified by their [`NetworkId`]. When they're loaded
+//! again, e.g. after the connection to the server was lost, they're spawned
+//! through the [`EntityMap`], which updates the entities that already exist
+//! instead of spawning duplicates. Entity handles that other components hold
+//! therefore stay valid.
+
+use std::collections::HashMap;
+
+use hecs::{
+    DynamicBundle,
+    Entity,
+};
+use kardashev_protocol::model::network::NetworkId;
+
+use crate::ecs::{
+    plugin::{
+        Plugin,
+        RegisterPluginContext,
+    },
+    system::SystemContext,
+};
+
+/// Resource that maps [`NetworkId`]s to entities and back.
+#[derive(Debug, Default)]
+pub struct EntityMap {
+    entities: HashMap<NetworkId, Entity>,
+    network_ids: HashMap<Entity, NetworkId>,
+}
+
+impl EntityMap {
+    /// Returns the entity the server entity is spawned as.
+    pub fn resolve(&self, network_id: impl Into<NetworkId>) -> Option<Entity> {
+        self.entities.get(&network_id.into()).copied()
+    }
+
+    /// Returns the network ID of an entity, or `None` if it's not a server
+    /// entity.
+    #[allow(dead_code)]
+    pub fn network_id(&self, entity: Entity) -> Option<NetworkId> {
+        self.network_ids.get(&entity).copied()
+    }
+
+    pub fn insert(&mut self, network_id: impl Into<NetworkId>, entity: Entity) {
+        let network_id = network_id.into();
+        if let Some(old) = self.entities.insert(network_id, entity) {
+            self.network_ids.remove(&old);
+        }
+        self.network_ids.insert(entity, network_id);
+    }
+
+    pub fn remove(&mut self, network_id: impl Into<NetworkId>) -> Option<Entity> {
+        let entity = self.entities.remove(&network_id.into())?;
+        self.network_ids.remove(&entity);
+        Some(entity)
+    }
+
+    /// Spawns the server entity with the components, or inserts them into the
+    /// entity it's already spawned as.
+    pub fn spawn_or_update(
+        &mut self,
+        world: &mut hecs::World,
+        network_id: impl Into<NetworkId>,
+        components: impl DynamicBundle,
+    ) -> Entity {
+        let network_id = network_id.into();
+        // the entity might have been despawned, but not pruned from the map yet.
+        if let Some(entity) = self
+            .resolve(network_id)
+            .filter(|entity| world.contains(*entity))
+        {
+            world
+                .insert(entity, components)
+                .expect("entity doesn't exist");
+            entity
+        }
+        else {
+            let entity = world.spawn(components);
+            self.insert(network_id, entity);
+            entity
+        }
+    }
+
+    /// Despawns the server entities for which `keep` returns `false`, e.g.
+    /// the ones that are missing from a reloaded snapshot.
+    pub fn retain(&mut self, world: &mut hecs::World, mut keep: impl FnMut(&NetworkId) -> bool) {
+        let network_ids = &mut self.network_ids;
+        self.entities.retain(|network_id, entity| {
+            if keep(network_id) {
+                true
+            }
+            else {
+                let _ = world.despawn(*entity);
+                network_ids.remove(entity);
+                false
+            }
+        });
+    }
+}
+
+/// Removes entities that were despawned from the [`EntityMap`].
+fn prune_entity_map(system_context: &mut SystemContext) {
+    let Some(entity_map) = system_context.resources.get_mut::<EntityMap>()
+    else {
+        return;
+    };
+    let world = &system_context.world;
+    entity_map
+        .entities
+        .retain(|_, entity| world.contains(*entity));
+    entity_map
+        .network_ids
+        .retain(|entity, _| world.contains(*entity));
+}
+
+#[derive(Debug, Default)]
+pub struct EntityMapPlugin;
+
+impl Plugin for EntityMapPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.resources.insert(EntityMap::default());
+        context.schedule.add_system(prune_entity_map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kardashev_protocol::model::{
+        fleet::FleetId,
+        network::NetworkId,
+        star::StarId,
+    };
+    use uuid::Uuid;
+
+    use super::EntityMap;
+
+    #[test]
+    fn it_updates_entities_that_were_already_spawned() {
+        let mut world = hecs::World::new();
+        let mut entity_map = EntityMap::default();
+        let star = StarId(Uuid::from_u128(1));
+        let fleet = FleetId(Uuid::from_u128(1));
+
+        let entity = entity_map.spawn_or_update(&mut world, star, (1u32,));
+        let fleet_entity = entity_map.spawn_or_update(&mut world, fleet, (3u32,));
+        assert_eq!(
+            entity_map.spawn_or_update(&mut world, star, (2u32,)),
+            entity
+        );
+        assert_eq!(*world.get::<&u32>(entity).unwrap(), 2);
+        assert_eq!(entity_map.network_id(entity), Some(NetworkId::Star(star)));
+
+        entity_map.retain(&mut world, |id| matches!(id, NetworkId::Fleet(_)));
+        assert!(!world.contains(entity));
+        assert_eq!(entity_map.resolve(star), None);
+        assert_eq!(entity_map.resolve(fleet), Some(fleet_entity));
+    }
+}
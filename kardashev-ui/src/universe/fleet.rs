@@ -0,0 +1,241 @@
+//! Fleets in the world.
+//!
+//! Fleets are loaded from the server at startup, and spawned through the
+//! [`EntityMap`] as selectable entities with a [`FleetEntity`] component. Their
+//! order queues are edited through the API, see
+//! [`OrderQueue`][crate::app::orders::OrderQueue]. A
+//! [replay](crate::app::replay) replaces them with the fleets of its
+//! snapshots, until the live fleets are [reloaded](load_fleets).
+//!
+//! Fleets with orders are on the move, and can be heard by their engine hum.
+//! The hum is synthesized, so that it doesn't need a sound asset.
+
+use std::{
+    collections::HashSet,
+    f32::consts::TAU,
+};
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::{
+    fleet::{
+        Fleet,
+        FleetId,
+        Order,
+    },
+    network::NetworkId,
+    star::StarId,
+};
+
+use crate::{
+    audio::{
+        self,
+        sound::Sound,
+        spatial::{
+            Attenuation,
+            SpatialSound,
+        },
+        AudioOutput,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+        Label,
+    },
+    graphics::transform::Transform,
+    selection::Selectable,
+    universe::entity_map::EntityMap,
+    utils::futures::{
+        spawn_task,
+        Task,
+    },
+};
+
+/// Sample rate of the engine hum.
+const HUM_SAMPLE_RATE: f32 = 24000.0;
+
+/// Length of the engine hum in seconds. All its frequencies are multiples of
+/// its inverse, so that it loops seamlessly.
+const HUM_DURATION: f32 = 2.0;
+
+const HUM_GAIN: f32 = 0.2;
+
+/// Marks an entity as a fleet.
+#[derive(Clone, Copy, Debug)]
+pub struct FleetEntity {
+    pub id: FleetId,
+}
+
+/// Stars a fleet will visit, in the order of its order queue.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FleetRoute {
+    pub stars: Vec<StarId>,
+}
+
+impl FleetRoute {
+    pub fn from_orders<'a>(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        Self {
+            stars: orders.into_iter().map(|order| order.kind.star()).collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LoadFleets {
+    task: Task<Vec<Fleet>>,
+}
+
+/// Loads the fleets from the server, and replaces the spawned fleets with them.
+pub fn load_fleets(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    let task = spawn_task(async move {
+        let fleets = api_client.get_fleets().await?;
+        tracing::debug!(num_fleets = fleets.len(), "loaded fleets");
+        Ok::<_, kardashev_client::Error>(fleets)
+    });
+    system_context.resources.insert(LoadFleets { task });
+}
+
+fn spawn_fleets(system_context: &mut SystemContext) {
+    let Some(load_fleets) = system_context.resources.get_mut::<LoadFleets>()
+    else {
+        return;
+    };
+
+    let Some(result) = load_fleets.task.try_take()
+    else {
+        return;
+    };
+    system_context.resources.remove::<LoadFleets>();
+    let Ok(fleets) = result
+    else {
+        return;
+    };
+
+    replace_fleets(system_context, fleets);
+}
+
+/// Spawns the fleets, or updates them if they're already spawned, and
+/// despawns all other fleets.
+pub fn replace_fleets(system_context: &mut SystemContext, fleets: Vec<Fleet>) {
+    let entity_map = system_context
+        .resources
+        .get_mut_or_insert_default::<EntityMap>();
+
+    // fleets that are gone, e.g. from the server, are despawned.
+    let fleet_ids = fleets
+        .iter()
+        .map(|fleet| NetworkId::from(fleet.id))
+        .collect::<HashSet<_>>();
+    entity_map.retain(system_context.world, |network_id| {
+        !matches!(network_id, NetworkId::Fleet(_)) || fleet_ids.contains(network_id)
+    });
+
+    for fleet in fleets {
+        entity_map.spawn_or_update(
+            system_context.world,
+            fleet.id,
+            (
+                FleetEntity { id: fleet.id },
+                FleetRoute::from_orders(&fleet.orders),
+                Label::new(fleet.name),
+                Transform::from_position(fleet.position),
+                Selectable::new(0.1),
+            ),
+        );
+    }
+}
+
+/// The sound of fleets' engines, which is shared by all fleets.
+///
+/// This is `None` if it couldn't be synthesized.
+#[derive(Debug)]
+struct EngineHum {
+    sound: Option<Sound>,
+}
+
+/// Plays the engine hum of fleets with orders, and stops it when they have
+/// none left.
+fn engine_hum_system(system_context: &mut SystemContext) {
+    if system_context.resources.get::<EngineHum>().is_none() {
+        let Some(output) = system_context.resources.get::<AudioOutput>()
+        else {
+            return;
+        };
+        let sound = synthesize_engine_hum(output)
+            .inspect_err(|error| tracing::warn!(%error, "failed to synthesize engine hum"))
+            .ok();
+        system_context.resources.insert(EngineHum { sound });
+    }
+    let Some(hum) = system_context
+        .resources
+        .get::<EngineHum>()
+        .and_then(|hum| hum.sound.as_ref())
+    else {
+        return;
+    };
+
+    for (entity, (route, spatial_sound)) in system_context
+        .world
+        .query_mut::<(&FleetRoute, Option<&SpatialSound>)>()
+        .with::<&FleetEntity>()
+    {
+        match (route.stars.is_empty(), spatial_sound.is_some()) {
+            (false, false) => {
+                system_context.command_buffer.insert(
+                    entity,
+                    (
+                        hum.clone(),
+                        SpatialSound::default()
+                            .with_gain(HUM_GAIN)
+                            .with_attenuation(Attenuation {
+                                ref_distance: 0.5,
+                                max_distance: 50.0,
+                                ..Default::default()
+                            }),
+                    ),
+                );
+            }
+            (true, true) => {
+                system_context
+                    .command_buffer
+                    .remove::<(Sound, SpatialSound)>(entity);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A low hum with a slow wobble.
+fn synthesize_engine_hum(output: &AudioOutput) -> Result<Sound, audio::Error> {
+    let length = (HUM_SAMPLE_RATE * HUM_DURATION) as usize;
+    let samples = (0..length)
+        .map(|i| {
+            let t = i as f32 / HUM_SAMPLE_RATE;
+            let wobble = 0.8 + 0.2 * (TAU * 2.0 * t).sin();
+            let hum = 0.6 * (TAU * 55.0 * t).sin()
+                + 0.3 * (TAU * 110.0 * t).sin()
+                + 0.1 * (TAU * 165.0 * t).sin();
+            wobble * hum
+        })
+        .collect::<Vec<_>>();
+    Sound::from_samples(output, &samples, HUM_SAMPLE_RATE)
+}
+
+#[derive(Debug, Default)]
+pub struct FleetPlugin;
+
+impl Plugin for FleetPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.startup_schedule.add_system(load_fleets);
+        context.schedule.add_system(spawn_fleets);
+        context.schedule.add_system(engine_hum_system);
+    }
+}
@@ -1 +1,4 @@
+pub mod entity_map;
+pub mod fleet;
+pub mod region;
 pub mod star;
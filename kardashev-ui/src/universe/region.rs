@@ -0,0 +1,119 @@
+//! Regions of high star density.
+//!
+//! Regions are computed by the server and loaded at startup. Their outlines
+//! are drawn as [`Polygon`] gizmos.
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::region::{
+    Region,
+    RegionId,
+};
+use nalgebra::Point3;
+use palette::WithAlpha;
+
+use crate::{
+    colors::{
+        ActivePalette,
+        Tint,
+        Token,
+    },
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+        Label,
+    },
+    graphics::{
+        gizmo::Polygon,
+        transform::Transform,
+    },
+    utils::futures::{
+        spawn_task,
+        Task,
+    },
+};
+
+/// Opacity of the fill of regions, if the heatmap doesn't color them.
+pub const FILL_ALPHA: f32 = 0.06;
+
+/// Tints the outline. The fill is owned by the [heatmap][crate::app::heatmap].
+const TINT: Tint = Tint {
+    token: Token::Region,
+    alpha: 0.4,
+    fill_alpha: None,
+};
+
+/// Marks an entity as a region.
+#[derive(Clone, Copy, Debug)]
+pub struct RegionEntity {
+    pub id: RegionId,
+}
+
+#[derive(Debug)]
+struct LoadRegions {
+    task: Task<Vec<Region>>,
+}
+
+fn load_regions(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    let task = spawn_task(async move {
+        let regions = api_client.get_regions().await?;
+        tracing::debug!(num_regions = regions.len(), "loaded regions");
+        Ok::<_, kardashev_client::Error>(regions)
+    });
+    system_context.resources.insert(LoadRegions { task });
+}
+
+fn spawn_regions(system_context: &mut SystemContext) {
+    let Some(load_regions) = system_context.resources.get_mut::<LoadRegions>()
+    else {
+        return;
+    };
+
+    let Some(result) = load_regions.task.try_take()
+    else {
+        return;
+    };
+    system_context.resources.remove::<LoadRegions>();
+    let Ok(regions) = result
+    else {
+        return;
+    };
+
+    let palette = ActivePalette::get(&system_context.resources);
+    for region in regions {
+        let y = region.center.y;
+        system_context.world.spawn((
+            RegionEntity { id: region.id },
+            Label::new(region.name),
+            Transform::from_position(region.center),
+            Polygon {
+                vertices: region
+                    .hull
+                    .iter()
+                    .map(|point| Point3::new(point.x, y, point.y))
+                    .collect(),
+                fill: palette.region.with_alpha(FILL_ALPHA),
+                outline: TINT.color(palette),
+            },
+            TINT,
+        ));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RegionPlugin;
+
+impl Plugin for RegionPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.startup_schedule.add_system(load_regions);
+        context.schedule.add_system(spawn_regions);
+    }
+}
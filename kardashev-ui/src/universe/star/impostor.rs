@@ -0,0 +1,334 @@
+//! Impostors for distant stars.
+//!
+//! The server precomputes a small point cloud for every chunk of space (see
+//! [`StarChunk`]). Chunks that are far away from the [`StarLodViewer`] are
+//! drawn as these point clouds instead of individual star billboards. In
+//! between [`FADE_START`] and [`FADE_END`] both are blended, so that stars
+//! don't pop in and out.
+
+use std::collections::HashMap;
+
+use bytemuck::{
+    Pod,
+    Zeroable,
+};
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::{
+    chunk::{
+        ChunkCoords,
+        CHUNK_SIZE,
+    },
+    impostor::StarChunk,
+};
+use nalgebra::Point3;
+use palette::Srgb;
+
+use super::render::Star;
+use crate::{
+    ecs::system::SystemContext,
+    graphics::{
+        render_3d::{
+            CreateRender3dPipeline,
+            CreateRender3dPipelineContext,
+            DepthTexture,
+            Render3dPipeline,
+            Render3dPipelineContext,
+        },
+        transform::GlobalTransform,
+        utils::{
+            HasVertexBufferLayout,
+            InstanceBuffer,
+            Srgb32Ext,
+        },
+    },
+    utils::futures::{
+        spawn_task,
+        Task,
+    },
+};
+
+/// Distance from the viewer to a chunk's center, at which the chunk starts to
+/// fade into its impostor.
+pub const FADE_START: f32 = 2.0 * CHUNK_SIZE;
+
+/// Distance from the viewer to a chunk's center, beyond which only the
+/// impostor is drawn.
+pub const FADE_END: f32 = 3.0 * CHUNK_SIZE;
+
+/// Marks the camera from which distances to chunks are measured.
+#[derive(Clone, Copy, Debug)]
+pub struct StarLodViewer;
+
+/// How far a chunk has faded into its impostor, from `0.0` (only individual
+/// stars) to `1.0` (only the impostor).
+pub fn impostor_fade(distance: f32) -> f32 {
+    let t = ((distance - FADE_START) / (FADE_END - FADE_START)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[derive(Debug)]
+struct LoadedChunk {
+    chunk: StarChunk,
+    fade: f32,
+}
+
+/// The loaded chunks and their current fades.
+#[derive(Debug, Default)]
+pub struct StarChunks {
+    chunks: HashMap<ChunkCoords, LoadedChunk>,
+}
+
+impl StarChunks {
+    /// Returns the fade of the chunk containing `position`.
+    ///
+    /// Positions outside of any known chunk are never faded.
+    pub fn fade_at(&self, position: &Point3<f32>) -> f32 {
+        self.chunks
+            .get(&ChunkCoords::containing(position))
+            .map_or(0.0, |loaded| loaded.fade)
+    }
+}
+
+#[derive(Debug)]
+struct LoadStarChunks {
+    task: Task<Vec<StarChunk>>,
+}
+
+pub(super) fn load_star_chunks(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    let task = spawn_task(async move {
+        let chunks = api_client.get_star_chunks().await?;
+        tracing::debug!(num_chunks = chunks.len(), "loaded star chunks");
+        Ok::<_, kardashev_client::Error>(chunks)
+    });
+    system_context.resources.insert(LoadStarChunks { task });
+}
+
+pub(super) fn receive_star_chunks(system_context: &mut SystemContext) {
+    let Some(load_chunks) = system_context.resources.get_mut::<LoadStarChunks>()
+    else {
+        return;
+    };
+
+    let Some(result) = load_chunks.task.try_take()
+    else {
+        return;
+    };
+    system_context.resources.remove::<LoadStarChunks>();
+    let Ok(chunks) = result
+    else {
+        return;
+    };
+
+    system_context.resources.insert(StarChunks {
+        chunks: chunks
+            .into_iter()
+            .map(|chunk| (chunk.coords, LoadedChunk { chunk, fade: 0.0 }))
+            .collect(),
+    });
+}
+
+/// Updates the chunks' fades from the viewer's position and passes them on to
+/// the stars.
+pub(super) fn star_lod_system(system_context: &mut SystemContext) {
+    let Some(star_chunks) = system_context.resources.get_mut::<StarChunks>()
+    else {
+        return;
+    };
+
+    let Some(viewer) = system_context
+        .world
+        .query_mut::<&GlobalTransform>()
+        .with::<&StarLodViewer>()
+        .into_iter()
+        .next()
+        .map(|(_, transform)| transform.model_matrix.transform_point(&Point3::origin()))
+    else {
+        return;
+    };
+
+    for loaded in star_chunks.chunks.values_mut() {
+        loaded.fade = impostor_fade(nalgebra::distance(&viewer, &loaded.chunk.coords.center()));
+    }
+
+    for (_, (transform, star)) in system_context
+        .world
+        .query_mut::<(&GlobalTransform, &mut Star)>()
+    {
+        let position = transform.model_matrix.transform_point(&Point3::origin());
+        star.impostor_fade = star_chunks.fade_at(&position);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CreateRenderImpostorPipeline;
+
+impl CreateRender3dPipeline for CreateRenderImpostorPipeline {
+    type Pipeline = RenderImpostorPipeline;
+
+    fn create_pipeline(self, context: &CreateRender3dPipelineContext) -> Self::Pipeline {
+        let shader = context
+            .backend
+            .device
+            .create_shader_module(wgpu::include_wgsl!("./impostor.wgsl"));
+
+        let pipeline_layout =
+            context
+                .backend
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("RenderImpostorPipeline pipeline layout"),
+                    bind_group_layouts: &[&context.camera_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+
+        let pipeline =
+            context
+                .backend
+                .device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("RenderImpostorPipeline pipeline"),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[Instance::layout()],
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        // impostor points add up, like the light of the stars they stand in for.
+                        // they're too faint to bloom.
+                        targets: &context.color_targets(
+                            wgpu::BlendState {
+                                color: wgpu::BlendComponent {
+                                    src_factor: wgpu::BlendFactor::One,
+                                    dst_factor: wgpu::BlendFactor::One,
+                                    operation: wgpu::BlendOperation::Add,
+                                },
+                                alpha: wgpu::BlendComponent::OVER,
+                            },
+                            false,
+                        ),
+                        compilation_options: Default::default(),
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::PointList,
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: context.depth_texture_format,
+                        depth_write_enabled: false,
+                        depth_compare: DepthTexture::COMPARE,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState {
+                        count: 1,
+                        mask: !0,
+                        alpha_to_coverage_enabled: false,
+                    },
+                    multiview: None,
+                    cache: None,
+                });
+
+        RenderImpostorPipeline {
+            pipeline,
+            instance_buffer: InstanceBuffer::new(context.backend, 1024),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderImpostorPipeline {
+    pipeline: wgpu::RenderPipeline,
+    instance_buffer: InstanceBuffer<Instance>,
+}
+
+impl Render3dPipeline for RenderImpostorPipeline {
+    fn render(&mut self, context: &mut Render3dPipelineContext) {
+        let Some(star_chunks) = context.resources.get::<StarChunks>()
+        else {
+            return;
+        };
+
+        for loaded in star_chunks.chunks.values() {
+            if loaded.fade <= 0.0 {
+                continue;
+            }
+
+            for point in &loaded.chunk.points {
+                // a point standing in for many stars is brighter, but not by as much as their
+                // combined luminosity would suggest, or the brightest chunks would outshine
+                // everything else.
+                let brightness = loaded.fade * (0.2 + 0.1 * point.luminosity.max(0.0).ln_1p());
+                self.instance_buffer.push(Instance {
+                    position: point.position.into(),
+                    brightness,
+                    color: Srgb::from_linear(point.color).as_array4(),
+                });
+            }
+        }
+
+        let num_instances = self.instance_buffer.len().try_into().unwrap();
+        if num_instances > 0 {
+            tracing::trace!(num_instances, "drawing impostors");
+
+            self.instance_buffer.upload_and_clear(&context.backend);
+
+            context.render_pass.set_pipeline(&self.pipeline);
+            context
+                .render_pass
+                .set_bind_group(0, &context.camera_bind_group, &[]);
+            context
+                .render_pass
+                .set_vertex_buffer(0, self.instance_buffer.slice(..));
+            context.render_pass.draw(0..1, 0..num_instances);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+#[repr(C)]
+struct Instance {
+    position: [f32; 3],
+    brightness: f32,
+    color: [f32; 4],
+}
+
+impl HasVertexBufferLayout for Instance {
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
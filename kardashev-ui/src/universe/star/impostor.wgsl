@@ -0,0 +1,36 @@
+struct CameraUniform {
+    view_projection: mat4x4f,
+    view_position: vec3f,
+    time: f32,
+    aspect: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct InstanceInput {
+    @location(0) position: vec3f,
+    @location(1) brightness: f32,
+    @location(2) color: vec4f,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: InstanceInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * vec4f(instance.position, 1.0);
+    out.color = vec4f(instance.color.rgb * instance.brightness, instance.color.a);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
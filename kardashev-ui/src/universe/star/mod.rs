@@ -1 +1,270 @@
+//! Stars in the world.
+//!
+//! Stars are loaded from the server at startup, and spawned through the
+//! [`EntityMap`], so that stars that are loaded again keep their entities.
+//! Afterwards they're kept in sync with the server's [`StarEvent`]s. The
+//! server tells us how much the player's faction knows about each star, and
+//! stars that are unexplored are rendered dimmed, see [`render::Star`]. Their
+//! brightness otherwise follows their apparent magnitude from the camera, and
+//! variable stars flicker. Distant stars are replaced by [impostors][impostor].
+
+pub mod impostor;
 pub mod render;
+
+use std::{
+    collections::HashSet,
+    time::Duration,
+};
+
+use kardashev_client::ApiClient;
+use kardashev_protocol::model::{
+    network::NetworkId,
+    star::{
+        Star,
+        StarEvent,
+        StarId,
+    },
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    ecs::{
+        plugin::{
+            Plugin,
+            RegisterPluginContext,
+        },
+        system::SystemContext,
+        Label,
+    },
+    graphics::transform::Transform,
+    selection::Selectable,
+    universe::entity_map::EntityMap,
+    utils::{
+        futures::{
+            spawn_local,
+            spawn_task,
+            Task,
+        },
+        time::sleep,
+    },
+};
+
+/// How long to wait before reconnecting to the star events.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Marks an entity as a star.
+#[derive(Clone, Copy, Debug)]
+pub struct StarEntity {
+    pub id: StarId,
+}
+
+/// Physical properties of a star.
+#[derive(Clone, Copy, Debug)]
+pub struct StarProperties {
+    /// Effective temperature in Kelvin.
+    pub effective_temperature: f32,
+
+    /// Luminosity relative to the sun.
+    pub luminosity: f32,
+
+    /// Mass relative to the sun.
+    pub mass: f32,
+
+    pub absolute_magnitude: f32,
+}
+
+#[derive(Debug)]
+struct LoadStars {
+    task: Task<Vec<Star>>,
+}
+
+fn load_stars(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    let task = spawn_task(async move {
+        let stars = api_client.get_stars().await?;
+        tracing::debug!(num_stars = stars.len(), "loaded stars");
+        Ok::<_, kardashev_client::Error>(stars)
+    });
+    system_context.resources.insert(LoadStars { task });
+}
+
+#[derive(Debug)]
+struct StarEvents {
+    rx: mpsc::UnboundedReceiver<StarEvent>,
+}
+
+/// Subscribes to the changes to the stars.
+///
+/// If the connection is lost, this reconnects, and reloads the stars, since
+/// changes were missed in the meantime.
+fn subscribe_star_events(system_context: &mut SystemContext) {
+    let api_client = system_context
+        .resources
+        .get::<ApiClient>()
+        .expect("no api client")
+        .clone();
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    system_context.resources.insert(StarEvents { rx });
+
+    spawn_local(async move {
+        let mut missed_events = false;
+        loop {
+            match api_client.star_events().await {
+                Ok(mut star_events) => {
+                    if std::mem::take(&mut missed_events) && tx.send(StarEvent::Reload).is_err() {
+                        return;
+                    }
+                    loop {
+                        match star_events.next().await {
+                            Ok(event) => {
+                                if tx.send(event).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(error) => {
+                                tracing::warn!(%error, "star events disconnected");
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(error) => tracing::warn!(%error, "failed to subscribe to star events"),
+            }
+            missed_events = true;
+            sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+fn receive_star_events(system_context: &mut SystemContext) {
+    let Some(star_events) = system_context.resources.get_mut::<StarEvents>()
+    else {
+        return;
+    };
+    let mut events = vec![];
+    while let Ok(event) = star_events.rx.try_recv() {
+        events.push(event);
+    }
+
+    for event in events {
+        match event {
+            StarEvent::Inserted { star } | StarEvent::Updated { star } => {
+                let entity_map = system_context
+                    .resources
+                    .get_mut_or_insert_default::<EntityMap>();
+                spawn_star(system_context.world, entity_map, star);
+            }
+            StarEvent::Deleted { id } => {
+                let entity_map = system_context
+                    .resources
+                    .get_mut_or_insert_default::<EntityMap>();
+                if let Some(entity) = entity_map.remove(id) {
+                    let _ = system_context.world.despawn(entity);
+                }
+            }
+            // replaces a load that is still running.
+            StarEvent::Reload => load_stars(system_context),
+        }
+    }
+}
+
+fn spawn_stars(system_context: &mut SystemContext) {
+    let Some(load_stars) = system_context.resources.get_mut::<LoadStars>()
+    else {
+        return;
+    };
+
+    let Some(result) = load_stars.task.try_take()
+    else {
+        return;
+    };
+    system_context.resources.remove::<LoadStars>();
+    let Ok(stars) = result
+    else {
+        return;
+    };
+
+    let entity_map = system_context
+        .resources
+        .get_mut_or_insert_default::<EntityMap>();
+
+    // stars that are gone from the server are despawned.
+    let star_ids = stars
+        .iter()
+        .map(|star| NetworkId::from(star.id))
+        .collect::<HashSet<_>>();
+    entity_map.retain(system_context.world, |network_id| {
+        !matches!(network_id, NetworkId::Star(_)) || star_ids.contains(network_id)
+    });
+
+    for star in stars {
+        spawn_star(system_context.world, entity_map, star);
+    }
+}
+
+/// Spawns a star, or updates it if it's already spawned.
+fn spawn_star(world: &mut hecs::World, entity_map: &mut EntityMap, star: Star) {
+    // keep what the client derived itself.
+    let (heat, impostor_fade) = entity_map
+        .resolve(star.id)
+        .and_then(|entity| world.get::<&render::Star>(entity).ok())
+        .map_or((None, 0.0), |render_star| {
+            (render_star.heat, render_star.impostor_fade)
+        });
+
+    let entity = entity_map.spawn_or_update(
+        world,
+        star.id,
+        (
+            StarEntity { id: star.id },
+            render::Star {
+                effective_temperature: star.effective_temperature,
+                visibility: star.visibility,
+                absolute_magnitude: star.absolute_magnitude,
+                variability: star
+                    .variability
+                    .map(|amplitude| render::Variability::new(star.id, amplitude)),
+                heat,
+                impostor_fade,
+            },
+            StarProperties {
+                effective_temperature: star.effective_temperature,
+                luminosity: star.luminousity,
+                mass: star.mass,
+                absolute_magnitude: star.absolute_magnitude,
+            },
+            Transform::from_position(star.position).with_scaling(0.05),
+            Selectable::new(0.05),
+        ),
+    );
+
+    if let Some(name) = star.name {
+        let _ = world.insert_one(entity, Label::new(name));
+    }
+    else {
+        let _ = world.remove_one::<Label>(entity);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StarPlugin;
+
+impl Plugin for StarPlugin {
+    fn register(self, context: RegisterPluginContext) {
+        context.startup_schedule.add_system(load_stars);
+        context.startup_schedule.add_system(subscribe_star_events);
+        context
+            .startup_schedule
+            .add_system(impostor::load_star_chunks);
+        context.schedule.add_system(spawn_stars);
+        context.schedule.add_system(receive_star_events);
+        context.schedule.add_system(impostor::receive_star_chunks);
+        context.schedule.add_system(impostor::star_lod_system);
+    }
+}
@@ -2,28 +2,132 @@ use bytemuck::{
     Pod,
     Zeroable,
 };
-use palette::Srgba;
-
-use crate::graphics::{
-    render_3d::{
-        CreateRender3dPipeline,
-        CreateRender3dPipelineContext,
-        Render3dPipeline,
-        Render3dPipelineContext,
+use kardashev_protocol::{
+    model::star::{
+        StarId,
+        StarVisibility,
     },
-    transform::GlobalTransform,
-    utils::{
-        HasVertexBufferLayout,
-        InstanceBuffer,
-        Srgb32Ext,
+    stellar::{
+        teff_lut,
+        teff_lut_coordinate,
+        TEFF_LUT_SIZE,
+    },
+};
+use palette::{
+    Srgb,
+    Srgba,
+};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    app::accessibility::Accessibility,
+    graphics::{
+        backend::Backend,
+        capabilities::RenderFeature,
+        culling::GpuCulling,
+        render_3d::{
+            CreateRender3dPipeline,
+            CreateRender3dPipelineContext,
+            DepthTexture,
+            Render3dPipeline,
+            Render3dPipelineContext,
+            Render3dPrepareContext,
+        },
+        render_layers::RenderLayers,
+        transform::GlobalTransform,
+        utils::{
+            HasVertexBufferLayout,
+            InstanceBuffer,
+            Srgb32Ext,
+        },
     },
 };
 
 #[derive(Debug)]
 pub struct Star {
-    pub color: Srgba<f32>,
+    /// Effective temperature in Kelvin, from which the shader looks up the
+    /// star's color.
+    pub effective_temperature: f32,
+
+    pub visibility: StarVisibility,
+
+    /// Absolute magnitude, from which the shader computes the star's apparent
+    /// brightness at the camera's distance.
+    pub absolute_magnitude: f32,
+
+    pub variability: Option<Variability>,
+
+    /// Color assigned by the [heatmap][crate::app::heatmap], which replaces
+    /// the star's own color.
+    pub heat: Option<Srgba<f32>>,
+
+    /// How far the star has faded into the impostor of its chunk, see
+    /// [`impostor`][super::impostor]. Fading stars shrink, and fully faded
+    /// ones aren't drawn at all.
+    pub impostor_fade: f32,
 }
 
+impl Star {
+    /// Color with which the star's color from the lookup texture is
+    /// multiplied, or the heatmap color, which replaces it.
+    ///
+    /// Stars that are not currently observed are dimmed, and unexplored ones
+    /// even more so. Heatmap colors are not dimmed.
+    fn render_color(&self) -> Srgba<f32> {
+        if let Some(heat) = self.heat {
+            return heat;
+        }
+
+        let brightness = match self.visibility {
+            StarVisibility::Observed => 1.0,
+            StarVisibility::Explored => 0.6,
+            StarVisibility::Unexplored => 0.25,
+        };
+        Srgba::new(brightness, brightness, brightness, 1.0)
+    }
+
+    /// Coordinate in the lookup texture, or a negative value for heatmap
+    /// colors.
+    fn teff_coordinate(&self) -> f32 {
+        if self.heat.is_some() {
+            -1.0
+        }
+        else {
+            teff_lut_coordinate(self.effective_temperature)
+        }
+    }
+}
+
+/// Brightness variation of a variable star.
+#[derive(Clone, Copy, Debug)]
+pub struct Variability {
+    /// Peak-to-peak amplitude in magnitudes.
+    pub amplitude: f32,
+
+    /// Phase in radians, so that variable stars don't all pulse in unison.
+    pub phase: f32,
+}
+
+impl Variability {
+    /// Variability with a phase derived from the star's ID, so that it stays
+    /// the same when the star is loaded again.
+    pub fn new(star_id: StarId, amplitude: f32) -> Self {
+        let phase = (star_id.0.as_u128() as u16) as f32 / (u16::MAX as f32 + 1.0);
+        Self {
+            amplitude,
+            phase: phase * std::f32::consts::TAU,
+        }
+    }
+}
+
+/// Radius of the star billboards' bounding sphere, in model space. The quads
+/// span from `-1.0` to `1.0`.
+const BOUNDING_RADIUS: f32 = std::f32::consts::SQRT_2;
+
+/// Indices of the billboard quad for indirect draws. The shader generates the
+/// vertices from their index.
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 3, 4, 5];
+
 #[derive(Clone, Copy, Debug, Default)]
 pub struct CreateRenderStarPipeline;
 
@@ -36,13 +140,44 @@ impl CreateRender3dPipeline for CreateRenderStarPipeline {
             .device
             .create_shader_module(wgpu::include_wgsl!("./shader.wgsl"));
 
+        let teff_lut_bind_group_layout =
+            context
+                .backend
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("RenderStarPipeline teff lut bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Texture {
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                multisampled: false,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::VERTEX,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                });
+        let teff_lut_bind_group =
+            create_teff_lut_bind_group(context.backend, &teff_lut_bind_group_layout);
+
         let pipeline_layout =
             context
                 .backend
                 .device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("RenderStarPipeline pipeline layout"),
-                    bind_group_layouts: &[&context.camera_bind_group_layout],
+                    bind_group_layouts: &[
+                        &context.camera_bind_group_layout,
+                        &teff_lut_bind_group_layout,
+                    ],
                     push_constant_ranges: &[],
                 });
 
@@ -62,11 +197,7 @@ impl CreateRender3dPipeline for CreateRenderStarPipeline {
                     fragment: Some(wgpu::FragmentState {
                         module: &shader,
                         entry_point: "fs_main",
-                        targets: &[Some(wgpu::ColorTargetState {
-                            format: context.surface_format,
-                            blend: Some(wgpu::BlendState::REPLACE),
-                            write_mask: wgpu::ColorWrites::ALL,
-                        })],
+                        targets: &context.color_targets(wgpu::BlendState::REPLACE, true),
                         compilation_options: Default::default(),
                     }),
                     primitive: wgpu::PrimitiveState {
@@ -81,7 +212,7 @@ impl CreateRender3dPipeline for CreateRenderStarPipeline {
                     depth_stencil: Some(wgpu::DepthStencilState {
                         format: context.depth_texture_format,
                         depth_write_enabled: true,
-                        depth_compare: wgpu::CompareFunction::Less,
+                        depth_compare: DepthTexture::COMPARE,
                         stencil: wgpu::StencilState::default(),
                         bias: wgpu::DepthBiasState::default(),
                     }),
@@ -94,44 +225,194 @@ impl CreateRender3dPipeline for CreateRenderStarPipeline {
                     cache: None,
                 });
 
+        // stars are culled on the GPU if possible, and on the CPU otherwise.
+        let gpu_culling = context
+            .backend
+            .capabilities
+            .is_enabled(RenderFeature::GpuCulling)
+            .then(|| {
+                let index_buffer =
+                    context
+                        .backend
+                        .device
+                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some("RenderStarPipeline quad indices"),
+                            contents: bytemuck::cast_slice(&QUAD_INDICES),
+                            usage: wgpu::BufferUsages::INDEX,
+                        });
+                (
+                    GpuCulling::new(context.backend, QUAD_INDICES.len() as u32, BOUNDING_RADIUS),
+                    index_buffer,
+                )
+            });
+
         RenderStarPipeline {
             pipeline,
+            teff_lut_bind_group,
             instance_buffer: InstanceBuffer::new(context.backend, 128),
+            gpu_culling,
         }
     }
 }
 
+/// Creates the lookup texture of star colors by effective temperature.
+///
+/// WebGL doesn't support 1D textures, so this is a 2D texture with a single
+/// row. The colors are stored sRGB-encoded, like the colors of the instances.
+fn create_teff_lut_bind_group(
+    backend: &Backend,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::BindGroup {
+    let data = teff_lut()
+        .into_iter()
+        .flat_map(|color| {
+            let color: Srgb<u8> = Srgb::from_linear(color);
+            [color.red, color.green, color.blue, 255]
+        })
+        .collect::<Vec<u8>>();
+
+    let texture = backend.device.create_texture_with_data(
+        &backend.queue,
+        &wgpu::TextureDescriptor {
+            label: Some("star teff lut"),
+            size: wgpu::Extent3d {
+                width: TEFF_LUT_SIZE as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::default(),
+        &data,
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = backend.device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("star teff lut sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    backend
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("star teff lut bind group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        })
+}
+
 #[derive(Debug)]
 pub struct RenderStarPipeline {
     pipeline: wgpu::RenderPipeline,
+    teff_lut_bind_group: wgpu::BindGroup,
     instance_buffer: InstanceBuffer<Instance>,
+    gpu_culling: Option<(GpuCulling<Instance>, wgpu::Buffer)>,
 }
 
 impl Render3dPipeline for RenderStarPipeline {
-    fn render(&mut self, context: &mut Render3dPipelineContext) {
-        let mut query = context.world.query::<(&GlobalTransform, &Star)>();
+    fn prepare(&mut self, context: &mut Render3dPrepareContext) {
+        self.instance_buffer.clear();
+
+        // with reduced motion, variable stars are shown at their mean brightness.
+        let animate_variability = !context
+            .resources
+            .get::<Accessibility>()
+            .map_or(false, |accessibility| accessibility.reduced_motion);
+
+        let mut query = context
+            .world
+            .query::<(&GlobalTransform, &Star, Option<&RenderLayers>)>();
+
+        for (_entity, (transform, star, layers)) in query.iter() {
+            if star.impostor_fade >= 1.0 || !context.camera_layers.sees(layers) {
+                continue;
+            }
+
+            let model_matrix = transform
+                .model_matrix
+                .append_scaling(1.0 - star.impostor_fade)
+                .to_homogeneous();
+            if self.gpu_culling.is_none()
+                && !context
+                    .frustum
+                    .contains_instance(&model_matrix, BOUNDING_RADIUS)
+            {
+                continue;
+            }
 
-        for (_entity, (transform, star)) in query.iter() {
             self.instance_buffer.push(Instance {
-                model_transform: transform.as_homogeneous_matrix_array(),
-                color: star.color.as_array4(),
+                model_transform: model_matrix
+                    .as_slice()
+                    .try_into()
+                    .expect("convert model matrix to array"),
+                color: star.render_color().as_array4(),
+                teff_coordinate: star.teff_coordinate(),
+                absolute_magnitude: star.absolute_magnitude,
+                variability: star
+                    .variability
+                    .filter(|_| animate_variability)
+                    .map_or([0.0; 2], |variability| {
+                        [variability.amplitude, variability.phase]
+                    }),
             });
         }
 
-        let num_instances = self.instance_buffer.len().try_into().unwrap();
-        if num_instances > 0 {
-            tracing::trace!(num_instances, "drawing stars");
+        if let Some((gpu_culling, _)) = &mut self.gpu_culling {
+            gpu_culling.cull(
+                context.backend,
+                context.encoder,
+                &context.frustum,
+                self.instance_buffer.staged(),
+            );
+            self.instance_buffer.clear();
+        }
+        else {
+            self.instance_buffer.upload(context.backend);
+        }
+    }
 
-            self.instance_buffer.upload_and_clear(&context.backend);
+    fn render(&mut self, context: &mut Render3dPipelineContext) {
+        context.render_pass.set_pipeline(&self.pipeline);
+        context
+            .render_pass
+            .set_bind_group(0, &context.camera_bind_group, &[]);
+        context
+            .render_pass
+            .set_bind_group(1, &self.teff_lut_bind_group, &[]);
 
-            context.render_pass.set_pipeline(&self.pipeline);
-            context
-                .render_pass
-                .set_bind_group(0, &context.camera_bind_group, &[]);
+        if let Some((gpu_culling, index_buffer)) = &self.gpu_culling {
             context
                 .render_pass
-                .set_vertex_buffer(0, self.instance_buffer.slice(..));
-            context.render_pass.draw(0..6, 0..num_instances);
+                .set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            gpu_culling.draw(context.render_pass, 0);
+        }
+        else {
+            let num_instances = self.instance_buffer.len().try_into().unwrap();
+            if num_instances > 0 {
+                tracing::trace!(num_instances, "drawing stars");
+                context
+                    .render_pass
+                    .set_vertex_buffer(0, self.instance_buffer.slice(..));
+                context.render_pass.draw(0..6, 0..num_instances);
+            }
+            self.instance_buffer.clear();
         }
     }
 }
@@ -141,6 +422,9 @@ impl Render3dPipeline for RenderStarPipeline {
 struct Instance {
     model_transform: [f32; 16],
     color: [f32; 4],
+    teff_coordinate: f32,
+    absolute_magnitude: f32,
+    variability: [f32; 2],
 }
 
 impl HasVertexBufferLayout for Instance {
@@ -174,6 +458,21 @@ impl HasVertexBufferLayout for Instance {
                     shader_location: 4,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 21]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 22]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
             ],
         }
     }
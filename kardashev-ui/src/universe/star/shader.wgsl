@@ -8,22 +8,64 @@ struct CameraUniform {
 @group(0) @binding(0)
 var<uniform> camera: CameraUniform;
 
+// star colors by effective temperature, see `kardashev_protocol::stellar::teff_lut`.
+@group(1) @binding(0)
+var teff_lut: texture_2d<f32>;
+@group(1) @binding(1)
+var teff_lut_sampler: sampler;
+
 struct InstanceInput {
     @location(0) model_transform_0: vec4f,
     @location(1) model_transform_1: vec4f,
     @location(2) model_transform_2: vec4f,
     @location(3) model_transform_3: vec4f,
     @location(4) star_color: vec4f,
+    // negative if `star_color` is a heatmap color, which replaces the star's color.
+    @location(5) teff_coordinate: f32,
+    @location(6) absolute_magnitude: f32,
+    // peak-to-peak amplitude in magnitudes, and phase in radians. the amplitude is 0 for stars that
+    // aren't variable.
+    @location(7) variability: vec2f,
+}
+
+// stars at least this bright are drawn at full brightness.
+const REFERENCE_MAGNITUDE: f32 = -1.0;
+// fainter stars are still drawn this bright, so that they don't vanish.
+const MIN_BRIGHTNESS: f32 = 0.08;
+// the response to flux is compressed, so that the ~20 magnitudes from the brightest to the
+// faintest stars remain distinguishable.
+const BRIGHTNESS_EXPONENT: f32 = 0.3;
+// stars brighter than this bloom, proportionally to how much brighter they are.
+const BLOOM_THRESHOLD: f32 = 0.5;
+// angular frequency of the variability animation, in radians per second.
+const VARIABILITY_FREQUENCY: f32 = 1.5;
+
+// apparent magnitude at a distance in parsecs, see `kardashev_protocol::stellar::apparent_magnitude`.
+fn apparent_magnitude(absolute_magnitude: f32, distance: f32) -> f32 {
+    // log10(x) = log2(x) * log10(2)
+    return absolute_magnitude + 5.0 * log2(max(distance, 0.01)) * 0.30103 - 5.0;
+}
+
+// compressed flux relative to the reference magnitude. this is greater than 1 for stars brighter
+// than the reference magnitude.
+fn apparent_luminosity(instance: InstanceInput, position: vec3f) -> f32 {
+    var magnitude = apparent_magnitude(instance.absolute_magnitude, distance(position, camera.view_position));
+    magnitude += 0.5 * instance.variability.x * sin(camera.time * VARIABILITY_FREQUENCY + instance.variability.y);
+    let flux = pow(10.0, -0.4 * (magnitude - REFERENCE_MAGNITUDE));
+    return pow(flux, BRIGHTNESS_EXPONENT);
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4f,
     @location(0) position: vec2f,
     @location(1) color: vec4f,
+    @location(2) emissive: vec3f,
 }
 
 struct FragmentOutput {
     @location(0) color: vec4f,
+    // HDR emissive light that blooms
+    @location(1) emissive: vec4f,
     //@builtin(frag_depth) depth: f32,
 }
 
@@ -75,7 +117,18 @@ fn vs_main(
     let vertex_position = vertices[vertex_index];
     out.clip_position = translation + vec4f(vertex_position.x * scale_x, vertex_position.y * scale_y, 0.0, 1.0);
     out.position = vertex_position;
-    out.color = instance.star_color;
+    if instance.teff_coordinate < 0.0 {
+        // heatmap colors don't bloom
+        out.color = instance.star_color;
+        out.emissive = vec3f(0.0);
+    }
+    else {
+        let teff_color = textureSampleLevel(teff_lut, teff_lut_sampler, vec2f(instance.teff_coordinate, 0.5), 0.0);
+        let color = teff_color.rgb * instance.star_color.rgb;
+        let luminosity = apparent_luminosity(instance, model_transform[3].xyz);
+        out.color = vec4f(color * clamp(luminosity, MIN_BRIGHTNESS, 1.0), instance.star_color.a);
+        out.emissive = color * max(luminosity - BLOOM_THRESHOLD, 0.0);
+    }
     //out.normal = normalize((model_transform * vec4f(0.0, 0.0, 1.0, 0.0)).xyz);
 
     return out;
@@ -89,6 +142,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
 
     var out: FragmentOutput;
     out.color = in.color;
+    out.emissive = vec4f(in.emissive, 1.0);
     //out.color = vec4f(1.0, 1.0, 1.0, 1.0);
 
     return out;
@@ -26,6 +26,11 @@ impl<K: Eq + Hash> AnyArcCache<K> {
         self.cache.retain(|_, weak| weak.strong_count() > 0);
     }
 
+    /// Removes the values of all types for `key`.
+    pub fn remove(&mut self, key: &K) {
+        self.cache.retain(|(other, _), _| other != key);
+    }
+
     pub fn get<T>(&self, key: K) -> Option<Arc<T>>
     where
         T: Send + Sync + 'static,
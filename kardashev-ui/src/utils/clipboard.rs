@@ -0,0 +1,42 @@
+//! Reading and writing text from and to the system clipboard.
+//!
+//! The browser only allows this in secure contexts and in response to user
+//! input. Reading may also ask the user for permission.
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{
+    js_sys,
+    JsFuture,
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error("clipboard error")]
+pub enum Error {
+    #[error("clipboard access failed: {message}")]
+    Access { message: String },
+    #[error("clipboard doesn't contain text")]
+    NotText,
+}
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        // usually a `DOMException`, which has a message, e.g. if permission was denied.
+        let message = js_sys::Reflect::get(&value, &"message".into())
+            .ok()
+            .and_then(|message| message.as_string())
+            .unwrap_or_else(|| format!("{value:?}"));
+        Self::Access { message }
+    }
+}
+
+pub async fn write_text(text: &str) -> Result<(), Error> {
+    let clipboard = gloo_utils::window().navigator().clipboard();
+    JsFuture::from(clipboard.write_text(text)).await?;
+    Ok(())
+}
+
+pub async fn read_text() -> Result<String, Error> {
+    let clipboard = gloo_utils::window().navigator().clipboard();
+    let text = JsFuture::from(clipboard.read_text()).await?;
+    text.as_string().ok_or(Error::NotText)
+}
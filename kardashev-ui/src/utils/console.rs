@@ -0,0 +1,74 @@
+//! Commands that can be called from the browser's developer console.
+//!
+//! Commands are registered as functions on the global `kardashev` object, e.g.
+//! a command named `screenshot` can be called with `kardashev.screenshot()`.
+//! Commands with arguments take them as an object, e.g.
+//! `kardashev.stress_test({ meshes: 10000 })`.
+
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{
+    closure::Closure,
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::js_sys::{
+    Object,
+    Reflect,
+};
+
+const NAMESPACE: &str = "kardashev";
+
+pub fn register_command(name: &str, command: impl Fn() + 'static) {
+    let command = Closure::<dyn Fn()>::new(command).into_js_value();
+    set_command(name, &command);
+    tracing::debug!("registered console command: {NAMESPACE}.{name}()");
+}
+
+/// Registers a command that takes its arguments as an object. If the command is
+/// called without arguments, they're deserialized from an empty object, so
+/// that arguments with `#[serde(default)]` can be omitted.
+pub fn register_command_with_args<A: DeserializeOwned>(name: &str, command: impl Fn(A) + 'static) {
+    let command_name = name.to_owned();
+    let command = Closure::<dyn Fn(JsValue)>::new(move |args: JsValue| {
+        let args = if args.is_undefined() {
+            Object::new().into()
+        }
+        else {
+            args
+        };
+        match serde_wasm_bindgen::from_value(args) {
+            Ok(args) => command(args),
+            Err(error) => {
+                tracing::error!(%error, "invalid arguments for console command {command_name}");
+            }
+        }
+    })
+    .into_js_value();
+    set_command(name, &command);
+    tracing::debug!("registered console command: {NAMESPACE}.{name}({{ ... }})");
+}
+
+fn set_command(name: &str, command: &JsValue) {
+    let window = gloo_utils::window();
+
+    let namespace = Reflect::get(&window, &NAMESPACE.into())
+        .ok()
+        .filter(|namespace| namespace.is_object())
+        .unwrap_or_else(|| {
+            let namespace: JsValue = Object::new().into();
+            Reflect::set(&window, &NAMESPACE.into(), &namespace)
+                .expect("failed to set console namespace");
+            namespace
+        });
+
+    Reflect::set(&namespace, &name.into(), command).expect("failed to set console command");
+}
+
+pub fn unregister_command(name: &str) {
+    let window = gloo_utils::window();
+    if let Ok(namespace) = Reflect::get(&window, &NAMESPACE.into()) {
+        if namespace.is_object() {
+            let _ = Reflect::delete_property(namespace.unchecked_ref(), &name.into());
+        }
+    }
+}
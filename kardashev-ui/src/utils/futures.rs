@@ -1,26 +1,68 @@
+//! Spawning tasks, on the browser's event loop or, natively, on tokio.
+//!
+//! Systems use these instead of the runtime's functions, so that they run
+//! unchanged in the browser and in native tests and tools. Native tasks are
+//! spawned on the current [`LocalSet`](tokio::task::LocalSet), so with a
+//! current-thread runtime and paused time they run deterministically.
+//!
+//! Blocking work is moved off the main thread with a [`WorkerPool`].
+
 use std::{
+    cell::RefCell,
     future::Future,
+    ops::ControlFlow,
     pin::Pin,
+    rc::Rc,
     task::{
         Context,
         Poll,
     },
+    time::Duration,
 };
 
 use futures::{
     pin_mut,
     FutureExt,
 };
+use gloo_file::{
+    Blob,
+    ObjectUrl,
+};
 use tokio::sync::{
     oneshot,
     watch,
 };
+use wasm_bindgen::{
+    closure::Closure,
+    JsCast,
+    JsValue,
+};
+use wasm_bindgen_futures::js_sys::{
+    Array,
+    Reflect,
+};
+use web_sys::{
+    MessageEvent,
+    Worker,
+};
+
+use crate::utils::time::sleep;
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_detached(fut: impl Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(fut);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_detached(fut: impl Future<Output = ()> + 'static) {
+    tokio::task::spawn_local(fut);
+}
 
 pub fn spawn_local<F: Future<Output = T> + 'static, T: 'static>(fut: F) -> JoinHandle<T> {
     let (tx_result, rx_result) = oneshot::channel();
     let (tx_cancel, rx_cancel) = watch::channel(false);
 
-    wasm_bindgen_futures::spawn_local(async move {
+    spawn_detached(async move {
         pin_mut!(fut);
         let mut rx_cancel = Some(rx_cancel);
 
@@ -61,6 +103,8 @@ pub enum JoinError {
     Cancelled,
     #[error("task panicked")]
     Panic,
+    #[error("task failed")]
+    Failed,
 }
 
 #[derive(Debug)]
@@ -116,14 +160,231 @@ pub fn spawn_local_and_handle_error<
 ) {
     spawn_local(fut.map(|result| {
         if let Err(error) = result {
-            let mut error: &dyn std::error::Error = &error;
+            handle_error(&error);
+        }
+    }));
+}
+
+/// Logs an error of a task, and reports it.
+fn handle_error(error: &dyn std::error::Error) {
+    let mut source = error;
+
+    tracing::error!(error = %source);
+
+    while let Some(next) = source.source() {
+        tracing::error!(source = %next);
+        source = next;
+    }
+
+    crate::crash_report::report_error(error);
+}
+
+/// Spawns a task whose output is picked up by a system, once it's finished.
+///
+/// Errors are handled like by [`spawn_local_and_handle_error`].
+pub fn spawn_task<F: Future<Output = Result<T, E>> + 'static, T: 'static, E: std::error::Error>(
+    fut: F,
+) -> Task<T> {
+    let join_handle = spawn_local(fut.map(|result| {
+        result.map_err(|error| {
+            handle_error(&error);
+            JoinError::Failed
+        })
+    }));
+
+    Task {
+        join_handle: Some(join_handle),
+    }
+}
+
+/// A task spawned with [`spawn_task`], usually stored in a resource.
+///
+/// The task is aborted when this is dropped, e.g. when the resource is
+/// removed.
+#[derive(Debug)]
+pub struct Task<T> {
+    join_handle: Option<JoinHandle<Result<T, JoinError>>>,
+}
+
+impl<T> Task<T> {
+    /// Returns the task's output if it has finished, without waiting for it.
+    ///
+    /// After it returned the output, this always returns `None`.
+    pub fn try_take(&mut self) -> Option<Result<T, JoinError>> {
+        let result = self.join_handle.as_mut()?.now_or_never()?;
+        self.join_handle = None;
+        Some(result.and_then(|result| result))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.join_handle
+            .as_ref()
+            .map_or(true, |join_handle| join_handle.is_finished())
+    }
+}
+
+impl<T> Drop for Task<T> {
+    fn drop(&mut self) {
+        if let Some(join_handle) = &self.join_handle {
+            join_handle.abort();
+        }
+    }
+}
+
+/// Web workers running the same script, for work that would block the main
+/// thread, like [`tokio::task::spawn_blocking`] does natively.
+///
+/// The UI's wasm build has no threads, and web workers can't share its memory,
+/// so the work can't be a closure. Instead the script gets its input in a
+/// message, and responds with one message. Workers are reused once they've
+/// responded.
+#[derive(Clone)]
+pub struct WorkerPool {
+    inner: Rc<WorkerPoolInner>,
+}
+
+struct WorkerPoolInner {
+    script: ObjectUrl,
+    idle: RefCell<Vec<Worker>>,
+}
+
+impl WorkerPool {
+    /// Creates a pool of workers running `script`, usually included with
+    /// [`include_str!`].
+    pub fn new(script: &str) -> Self {
+        let script = ObjectUrl::from(Blob::new_with_options(script, Some("text/javascript")));
+        Self {
+            inner: Rc::new(WorkerPoolInner {
+                script,
+                idle: RefCell::new(vec![]),
+            }),
+        }
+    }
 
-            tracing::error!(%error);
+    /// Posts `message` to an idle worker, or a new one, and waits for its
+    /// response.
+    ///
+    /// The objects in `transfer`, e.g. `ArrayBuffer`s, are moved to the worker
+    /// instead of being copied.
+    pub async fn run(&self, message: &JsValue, transfer: &Array) -> Result<JsValue, WorkerError> {
+        let idle = self.inner.idle.borrow_mut().pop();
+        let worker = match idle {
+            Some(worker) => worker,
+            None => Worker::new(&self.inner.script)?,
+        };
+        let busy = BusyWorker {
+            worker: Some(worker),
+            pool: &self.inner,
+        };
 
-            while let Some(source) = error.source() {
-                tracing::error!(%source);
-                error = source;
+        let (tx, rx) = oneshot::channel();
+        let tx = Rc::new(RefCell::new(Some(tx)));
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new({
+            let tx = tx.clone();
+            move |event: MessageEvent| {
+                if let Some(tx) = tx.borrow_mut().take() {
+                    let _ = tx.send(Ok(event.data()));
+                }
+            }
+        });
+        let on_error = Closure::<dyn FnMut(JsValue)>::new(move |event: JsValue| {
+            if let Some(tx) = tx.borrow_mut().take() {
+                let message = Reflect::get(&event, &"message".into())
+                    .ok()
+                    .and_then(|message| message.as_string())
+                    .unwrap_or_else(|| "failed to load worker".to_owned());
+                let _ = tx.send(Err(WorkerError::Failed { message }));
             }
+        });
+
+        let worker = busy.worker();
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        worker.post_message_with_transfer(message, transfer)?;
+
+        let response = rx.await.unwrap_or_else(|_| {
+            Err(WorkerError::Failed {
+                message: "worker stopped".to_owned(),
+            })
+        })?;
+
+        // only workers that responded are reused. the others are terminated when
+        // `busy` is dropped.
+        busy.release();
+
+        Ok(response)
+    }
+}
+
+impl std::fmt::Debug for WorkerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkerPool")
+            .field("script", &&*self.inner.script)
+            .field("idle", &self.inner.idle.borrow().len())
+            .finish()
+    }
+}
+
+/// A worker that is running a message, and is terminated if it's not released
+/// back to its pool, e.g. because the future waiting for it was dropped.
+struct BusyWorker<'a> {
+    worker: Option<Worker>,
+    pool: &'a WorkerPoolInner,
+}
+
+impl BusyWorker<'_> {
+    fn worker(&self) -> &Worker {
+        self.worker.as_ref().unwrap()
+    }
+
+    fn release(mut self) {
+        let worker = self.worker.take().unwrap();
+        worker.set_onmessage(None);
+        worker.set_onerror(None);
+        self.pool.idle.borrow_mut().push(worker);
+    }
+}
+
+impl Drop for BusyWorker<'_> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.terminate();
         }
-    }));
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkerError {
+    #[error("worker failed: {message}")]
+    Failed { message: String },
+
+    #[error("javascript error: {message}")]
+    Js { message: String },
+}
+
+impl From<JsValue> for WorkerError {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// Runs `f` repeatedly, at most once per `period`, until it breaks.
+///
+/// Unlike with an [`Interval`](crate::utils::time::Interval), runs that were
+/// missed, because `f` took longer than `period`, aren't made up for.
+pub async fn throttled_loop<F, Fut, B>(period: Duration, mut f: F) -> B
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ControlFlow<B>>,
+{
+    loop {
+        // the timer starts before `f` runs, so that its time counts towards the period.
+        let throttle = sleep(period);
+        if let ControlFlow::Break(output) = f().await {
+            return output;
+        }
+        throttle.await;
+    }
 }
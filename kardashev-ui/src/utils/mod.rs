@@ -1,6 +1,10 @@
 pub mod any_cache;
+pub mod clipboard;
+pub mod console;
+pub mod easing;
 pub mod futures;
 pub mod small_linear_map;
 pub mod thread_local_cell;
 pub mod time;
+pub mod trace;
 pub mod web_fs;
@@ -8,35 +8,61 @@ use std::{
     time::Duration,
 };
 
-use futures::{
-    FutureExt,
-    StreamExt,
+use chrono::{
+    DateTime,
+    TimeDelta,
+    Utc,
 };
+use futures::FutureExt;
+#[cfg(target_arch = "wasm32")]
+use futures::StreamExt;
+use kardashev_protocol::ServerStatus;
 pub use web_time::Instant;
 
+#[cfg(target_arch = "wasm32")]
 fn duration_to_millis(duration: Duration) -> u32 {
     duration.as_millis().try_into().expect("duration too long")
 }
 
+/// Ticks periodically, with browser timers or, natively, tokio's.
 #[derive(Debug)]
 pub struct Interval {
+    #[cfg(target_arch = "wasm32")]
     inner: gloo_timers::future::IntervalStream,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    inner: tokio::time::Interval,
 }
 
 impl Interval {
+    #[cfg(target_arch = "wasm32")]
     fn new(period: Duration) -> Self {
         Self {
             inner: gloo_timers::future::IntervalStream::new(duration_to_millis(period)),
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new(period: Duration) -> Self {
+        // like the browser's intervals, the first tick is after one period.
+        Self {
+            inner: tokio::time::interval_at(tokio::time::Instant::now() + period, period),
+        }
+    }
+
     pub async fn tick(&mut self) {
-        self.inner.next().await.unwrap()
+        std::future::poll_fn(|cx| self.poll_tick(cx)).await
     }
 
+    #[cfg(target_arch = "wasm32")]
     pub fn poll_tick(&mut self, cx: &mut Context) -> Poll<()> {
         self.inner.poll_next_unpin(cx).map(|result| result.unwrap())
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll_tick(&mut self, cx: &mut Context) -> Poll<()> {
+        self.inner.poll_tick(cx).map(|_| ())
+    }
 }
 
 pub fn interval(period: Duration) -> Interval {
@@ -45,15 +71,27 @@ pub fn interval(period: Duration) -> Interval {
 
 #[derive(Debug)]
 pub struct Sleep {
+    #[cfg(target_arch = "wasm32")]
     inner: gloo_timers::future::TimeoutFuture,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    inner: Pin<Box<tokio::time::Sleep>>,
 }
 
 impl Sleep {
+    #[cfg(target_arch = "wasm32")]
     fn new(duration: Duration) -> Sleep {
         Self {
             inner: gloo_timers::future::TimeoutFuture::new(duration_to_millis(duration)),
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn new(duration: Duration) -> Sleep {
+        Self {
+            inner: Box::pin(tokio::time::sleep(duration)),
+        }
+    }
 }
 
 impl Future for Sleep {
@@ -117,3 +155,28 @@ impl TicksPerSecond {
         self.measurement
     }
 }
+
+/// The server's clock, as estimated from the browser's.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerClock {
+    offset: TimeDelta,
+}
+
+impl ServerClock {
+    /// Estimates the server's clock from its status, assuming that it
+    /// responded halfway through the request.
+    ///
+    /// Returns `None` if the server doesn't send its time.
+    pub fn from_status(
+        status: &ServerStatus,
+        sent_at: DateTime<Utc>,
+        received_at: DateTime<Utc>,
+    ) -> Option<Self> {
+        let offset = status.time? - (sent_at + (received_at - sent_at) / 2);
+        Some(Self { offset })
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset
+    }
+}
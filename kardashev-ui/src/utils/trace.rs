@@ -0,0 +1,294 @@
+//! Span timings for the browser's profiling tools.
+//!
+//! [`PerformanceLayer`] mirrors tracing spans into the [Performance API][1]:
+//! Entering a span sets a mark, and exiting it measures the time since, so
+//! the spans show up under "Timings" in the DevTools performance panel. The
+//! spans can also be recorded into a [`ChromeTrace`], which can be downloaded
+//! and opened in `chrome://tracing` or Perfetto.
+//!
+//! Both are off by default, since they're called for every span.
+//!
+//! A span is named after its `label` field if it has one, e.g. the systems'
+//! spans, and otherwise after the span itself.
+//!
+//! [1]: https://developer.mozilla.org/en-US/docs/Web/API/Performance_API
+
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    time::Duration,
+};
+
+use gloo_file::{
+    Blob,
+    ObjectUrl,
+};
+use serde::Serialize;
+use tracing::{
+    field::{
+        Field,
+        Visit,
+    },
+    span::{
+        Attributes,
+        Id,
+    },
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::Context,
+    registry::LookupSpan,
+    Layer,
+};
+use wasm_bindgen::{
+    JsCast,
+    JsValue,
+};
+use web_sys::{
+    HtmlAnchorElement,
+    Performance,
+};
+
+use crate::utils::time::sleep;
+
+/// How many spans a [`ChromeTrace`] records at most.
+const MAX_TRACE_EVENTS: usize = 1_000_000;
+
+static MARKS_ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static PERFORMANCE: Option<Performance> = gloo_utils::window().performance();
+    static TRACE: RefCell<Option<ChromeTrace>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("trace error")]
+pub enum Error {
+    Json(#[from] serde_json::Error),
+    #[error("javascript error: {message}")]
+    Js {
+        message: String,
+    },
+}
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        Self::Js {
+            message: format!("{value:?}"),
+        }
+    }
+}
+
+/// Enables or disables performance marks.
+pub fn set_marks_enabled(enabled: bool) {
+    MARKS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn marks_enabled() -> bool {
+    MARKS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Starts recording spans into a new [`ChromeTrace`], discarding one that is
+/// being recorded.
+pub fn start_recording() {
+    TRACE.with_borrow_mut(|trace| *trace = Some(ChromeTrace::default()));
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+/// Stops recording and returns the trace, if one was being recorded.
+pub fn stop_recording() -> Option<ChromeTrace> {
+    RECORDING.store(false, Ordering::Relaxed);
+    TRACE.with_borrow_mut(Option::take)
+}
+
+fn is_active() -> bool {
+    marks_enabled() || RECORDING.load(Ordering::Relaxed)
+}
+
+fn now() -> Option<f64> {
+    PERFORMANCE.with(|performance| performance.as_ref().map(Performance::now))
+}
+
+/// Spans recorded in the [Trace Event Format][1].
+///
+/// [1]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    trace_events: Vec<TraceEvent>,
+}
+
+/// A complete event, i.e. one with a duration.
+#[derive(Debug, Serialize)]
+struct TraceEvent {
+    name: String,
+    cat: &'static str,
+    ph: &'static str,
+
+    /// Start time in microseconds.
+    ts: f64,
+
+    /// Duration in microseconds.
+    dur: f64,
+
+    pid: u32,
+    tid: u32,
+}
+
+impl ChromeTrace {
+    pub fn is_empty(&self) -> bool {
+        self.trace_events.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Triggers a download of the trace as JSON in the browser.
+    pub async fn download(&self, filename: &str) -> Result<(), Error> {
+        let json = self.to_json()?;
+        let url = ObjectUrl::from(Blob::new_with_options(&*json, Some("application/json")));
+
+        let anchor: HtmlAnchorElement =
+            gloo_utils::document().create_element("a")?.unchecked_into();
+        anchor.set_href(&url);
+        anchor.set_download(filename);
+        anchor.click();
+
+        // give the browser some time to start the download before revoking the URL.
+        sleep(Duration::from_secs(1)).await;
+
+        Ok(())
+    }
+
+    /// Returns a filename for the trace, containing the current time.
+    pub fn default_filename() -> String {
+        format!(
+            "kardashev-trace-{}.json",
+            chrono::Local::now().format("%Y-%m-%d-%H%M%S")
+        )
+    }
+
+    fn push(&mut self, name: &str, target: &'static str, start: f64, end: f64) {
+        if self.trace_events.len() < MAX_TRACE_EVENTS {
+            self.trace_events.push(TraceEvent {
+                name: name.to_owned(),
+                cat: target,
+                ph: "X",
+                ts: start * 1000.0,
+                dur: (end - start) * 1000.0,
+                pid: 1,
+                tid: 1,
+            });
+        }
+    }
+}
+
+/// Tracing layer that mirrors spans into the Performance API and the
+/// [`ChromeTrace`] being recorded.
+pub struct PerformanceLayer;
+
+/// The value of a span's `label` field.
+struct SpanLabel(String);
+
+/// Stored in the span while it's entered.
+struct Entered {
+    mark: String,
+    start: f64,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for PerformanceLayer {
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        if attributes.metadata().fields().field("label").is_none() {
+            return;
+        }
+
+        let mut visitor = LabelVisitor(None);
+        attributes.record(&mut visitor);
+        if let (Some(label), Some(span)) = (visitor.0, context.span(id)) {
+            span.extensions_mut().insert(SpanLabel(label));
+        }
+    }
+
+    fn on_enter(&self, id: &Id, context: Context<'_, S>) {
+        if !is_active() {
+            return;
+        }
+        let (Some(span), Some(start)) = (context.span(id), now())
+        else {
+            return;
+        };
+
+        let mark = format!("span-{}", id.into_u64());
+        if marks_enabled() {
+            PERFORMANCE.with(|performance| {
+                if let Some(performance) = performance {
+                    let _ = performance.mark(&mark);
+                }
+            });
+        }
+
+        span.extensions_mut().replace(Entered { mark, start });
+    }
+
+    fn on_exit(&self, id: &Id, context: Context<'_, S>) {
+        let Some(span) = context.span(id)
+        else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(Entered { mark, start }) = extensions.remove::<Entered>()
+        else {
+            return;
+        };
+        let Some(end) = now()
+        else {
+            return;
+        };
+
+        let name = extensions
+            .get::<SpanLabel>()
+            .map_or(span.name(), |label| &label.0);
+
+        if marks_enabled() {
+            PERFORMANCE.with(|performance| {
+                if let Some(performance) = performance {
+                    // DevTools records the measure when it's made, so it can be cleared right
+                    // away, instead of filling the performance buffer.
+                    let _ = performance.measure_with_start_mark(name, &mark);
+                    performance.clear_marks_with_mark_name(&mark);
+                    performance.clear_measures_with_measure_name(name);
+                }
+            });
+        }
+
+        if RECORDING.load(Ordering::Relaxed) {
+            TRACE.with_borrow_mut(|trace| {
+                if let Some(trace) = trace {
+                    trace.push(name, span.metadata().target(), start, end);
+                }
+            });
+        }
+    }
+}
+
+struct LabelVisitor(Option<String>);
+
+impl Visit for LabelVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "label" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "label" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
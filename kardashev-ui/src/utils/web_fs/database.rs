@@ -205,6 +205,15 @@ impl<'t> Transaction<'t> {
         Ok(inode_id)
     }
 
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_inode(&self, inode_id: InodeId) -> Result<(), Error> {
+        tracing::trace!("delete_inode");
+        let inodes_store = self.transaction.object_store("inodes")?;
+        let query = serde_wasm_bindgen::to_value(&inode_id)?;
+        inodes_store.delete(query)?.await?;
+        Ok(())
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn get_blob(&self, blob_id: BlobId) -> Result<Option<GetBlob>, Error> {
         tracing::trace!("get_blob");
@@ -230,6 +239,15 @@ impl<'t> Transaction<'t> {
         let blob_id = serde_wasm_bindgen::from_value(value)?;
         Ok(blob_id)
     }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_blob(&self, blob_id: BlobId) -> Result<(), Error> {
+        tracing::trace!("delete_blob");
+        let blobs_store = self.transaction.object_store("blobs")?;
+        let query = serde_wasm_bindgen::to_value(&blob_id)?;
+        blobs_store.delete(query)?.await?;
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
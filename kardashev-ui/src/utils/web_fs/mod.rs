@@ -8,7 +8,12 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     fmt::Debug,
+    pin::Pin,
     sync::Arc,
+    task::{
+        Context,
+        Poll,
+    },
 };
 
 use bitflags::bitflags;
@@ -16,6 +21,7 @@ use bytes::{
     Bytes,
     BytesMut,
 };
+use futures::Stream;
 use gloo_file::Blob;
 use serde::{
     de::DeserializeOwned,
@@ -264,6 +270,159 @@ impl WebFs {
             was_created,
         })
     }
+
+    /// Returns the entries of the directory at `path`.
+    ///
+    /// The entries are read all at once, so changes to the directory while
+    /// the [`ReadDir`] is consumed don't show up in it.
+    pub async fn read_dir(&self, path: impl AsRef<Path>) -> Result<ReadDir, Error> {
+        let path = path.as_ref();
+
+        tracing::debug!(%path, "reading directory");
+
+        let transaction = self
+            .database
+            .transaction(Scope::INODES, idb::TransactionMode::ReadOnly)?;
+        let directory = self.resolve_directory(&transaction, path).await?;
+        let entries = transaction.get_inodes(Some(directory.id)).await?;
+        transaction.commit()?;
+
+        Ok(ReadDir {
+            entries: entries.into_iter(),
+        })
+    }
+
+    /// Removes the directory at `path` and everything in it, including the
+    /// contents of the files.
+    ///
+    /// Files in the directory that are still open must not be written
+    /// afterwards.
+    pub async fn remove_dir_all(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        tracing::debug!(%path, "removing directory");
+
+        let transaction = self
+            .database
+            .transaction(Scope::ALL, idb::TransactionMode::ReadWrite)?;
+        let directory = self.resolve_directory(&transaction, path).await?;
+        if directory.parent.is_none() {
+            return Err(Error::IsRootDirectory {
+                path: path.to_owned(),
+            });
+        }
+
+        let mut directories = vec![directory.id];
+        while let Some(directory_id) = directories.pop() {
+            for inode in transaction
+                .get_inodes::<Metadata>(Some(directory_id))
+                .await?
+            {
+                match inode.kind {
+                    InodeKind::File { blob_id } => {
+                        // blobs aren't referenced by anything else, so they'd leak otherwise.
+                        if let Some(blob_id) = blob_id {
+                            transaction.delete_blob(blob_id).await?;
+                        }
+                        transaction.delete_inode(inode.id).await?;
+                    }
+                    InodeKind::Directory => directories.push(inode.id),
+                }
+            }
+            transaction.delete_inode(directory_id).await?;
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    async fn resolve_directory(
+        &self,
+        transaction: &Transaction<'_>,
+        path: &Path,
+    ) -> Result<GetInode<Metadata>, Error> {
+        let current_directory = self.state.current_directory.read().await;
+
+        let inode = match resolve_inode(
+            transaction,
+            &self.state.root_directory,
+            &current_directory,
+            path,
+        )
+        .await
+        {
+            Ok(inode) => inode.into_owned(),
+            Err(ResolveInodeError::Database(database_error)) => {
+                return Err(Error::Database(database_error));
+            }
+            Err(ResolveInodeError::NotADirectory { components, .. }) => {
+                return Err(Error::NotADirectory {
+                    path: components.consumed_path().to_owned(),
+                });
+            }
+            Err(ResolveInodeError::FileNotFound { .. }) => {
+                return Err(Error::FileNotFound {
+                    path: path.to_owned(),
+                });
+            }
+        };
+
+        match inode.kind {
+            InodeKind::Directory => Ok(inode),
+            InodeKind::File { .. } => {
+                Err(Error::NotADirectory {
+                    path: path.to_owned(),
+                })
+            }
+        }
+    }
+}
+
+/// Entries of a directory, returned by [`WebFs::read_dir`].
+#[derive(Debug)]
+pub struct ReadDir {
+    entries: std::vec::IntoIter<GetInode<Metadata>>,
+}
+
+impl Stream for ReadDir {
+    type Item = DirEntry;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(
+            self.get_mut()
+                .entries
+                .next()
+                .map(|inode| DirEntry { inode }),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    inode: GetInode<Metadata>,
+}
+
+impl DirEntry {
+    pub fn file_name(&self) -> &str {
+        &self.inode.file_name
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self.inode.kind, InodeKind::Directory)
+    }
+
+    pub fn is_file(&self) -> bool {
+        matches!(self.inode.kind, InodeKind::File { .. })
+    }
+
+    pub fn meta_data(&self) -> &Metadata {
+        &self.inode.meta_data
+    }
 }
 
 #[derive(Debug)]
@@ -519,6 +678,10 @@ pub enum Error {
     AlreadyExists {
         path: PathBuf,
     },
+    #[error("is the root directory: {path}")]
+    IsRootDirectory {
+        path: PathBuf,
+    },
 }
 
 async fn resolve_inode<'t, 'i, 'p>(
@@ -0,0 +1,3 @@
+DROP TABLE fleet_order;
+DROP TYPE fleet_order_kind;
+DROP TABLE fleet;
@@ -0,0 +1,25 @@
+-- fleets
+
+CREATE TABLE fleet (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    position vec3 NOT NULL,
+    speed REAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT utc_now()
+);
+
+
+-- fleet order queues
+
+CREATE TYPE fleet_order_kind AS ENUM ('move', 'survey', 'colonize');
+
+CREATE TABLE fleet_order (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    fleet_id UUID NOT NULL REFERENCES fleet(id) ON DELETE CASCADE,
+    queue_index INT NOT NULL,
+    kind fleet_order_kind NOT NULL,
+    star_id UUID NOT NULL REFERENCES star(id),
+    UNIQUE (fleet_id, queue_index)
+);
+
+CREATE INDEX index_fleet_order_by_fleet_id ON fleet_order(fleet_id);
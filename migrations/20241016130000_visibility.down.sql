@@ -0,0 +1,4 @@
+DROP TABLE explored_star;
+DROP INDEX index_fleet_by_faction_id;
+ALTER TABLE fleet DROP COLUMN sensor_range;
+ALTER TABLE fleet DROP COLUMN faction_id;
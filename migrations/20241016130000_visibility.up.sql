@@ -0,0 +1,16 @@
+-- fleet ownership and sensors
+
+ALTER TABLE fleet ADD COLUMN faction_id UUID REFERENCES faction(faction_id);
+ALTER TABLE fleet ADD COLUMN sensor_range REAL NOT NULL DEFAULT 0.0;
+
+CREATE INDEX index_fleet_by_faction_id ON fleet(faction_id);
+
+
+-- stars explored by factions
+
+CREATE TABLE explored_star (
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    star_id UUID NOT NULL REFERENCES star(id),
+    explored_at TIMESTAMPTZ NOT NULL DEFAULT utc_now(),
+    PRIMARY KEY (faction_id, star_id)
+);
@@ -0,0 +1,11 @@
+-- colonies
+
+CREATE TABLE colony (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    star_id UUID NOT NULL REFERENCES star(id),
+    name TEXT NOT NULL,
+    founded_at TIMESTAMPTZ NOT NULL DEFAULT utc_now()
+);
+
+CREATE INDEX index_colony_by_faction_id ON colony(faction_id);
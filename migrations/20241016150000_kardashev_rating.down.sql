@@ -0,0 +1 @@
+DROP TABLE kardashev_rating;
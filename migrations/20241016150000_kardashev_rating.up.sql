@@ -0,0 +1,11 @@
+-- history of the factions' ratings on the kardashev scale
+
+CREATE TABLE kardashev_rating (
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    computed_at TIMESTAMPTZ NOT NULL,
+    power DOUBLE PRECISION NOT NULL,
+    rating DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY (faction_id, computed_at)
+);
+
+CREATE INDEX index_kardashev_rating_by_computed_at ON kardashev_rating(computed_at);
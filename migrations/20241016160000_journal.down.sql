@@ -0,0 +1,2 @@
+DROP TABLE journal_entry;
+DROP TYPE journal_event_kind;
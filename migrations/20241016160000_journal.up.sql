@@ -0,0 +1,13 @@
+-- append-only journal of events per faction
+
+CREATE TYPE journal_event_kind AS ENUM ('battle', 'discovery', 'construction-finished');
+
+CREATE TABLE journal_entry (
+    id BIGSERIAL NOT NULL PRIMARY KEY,
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    time TIMESTAMPTZ NOT NULL DEFAULT utc_now(),
+    kind journal_event_kind NOT NULL,
+    event JSONB NOT NULL
+);
+
+CREATE INDEX index_journal_entry_by_faction_id ON journal_entry(faction_id, id);
@@ -0,0 +1,4 @@
+-- values can't be removed from enums, so 'colony-founded' stays in journal_event_kind.
+
+ALTER TABLE fleet DROP COLUMN order_work;
+DROP TABLE simulation_state;
@@ -0,0 +1,17 @@
+-- state of the simulation. there's only ever one row.
+
+CREATE TABLE simulation_state (
+    id BOOLEAN NOT NULL PRIMARY KEY DEFAULT TRUE CHECK (id),
+    epoch BIGINT NOT NULL,
+    epoch_at TIMESTAMPTZ NOT NULL
+);
+
+
+-- days of work a fleet has done on its current order
+
+ALTER TABLE fleet ADD COLUMN order_work REAL NOT NULL DEFAULT 0.0;
+
+
+-- journal events for colonization
+
+ALTER TYPE journal_event_kind ADD VALUE 'colony-founded';
@@ -0,0 +1,3 @@
+-- whether a star's name was generated, instead of taken from a catalog
+
+ALTER TABLE star ADD COLUMN name_generated BOOLEAN NOT NULL DEFAULT FALSE;
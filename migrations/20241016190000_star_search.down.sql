@@ -0,0 +1,7 @@
+DROP INDEX index_star_id_gl;
+DROP INDEX index_star_id_hr;
+DROP INDEX index_star_id_hd;
+DROP INDEX index_star_id_hip;
+DROP INDEX index_star_id_hyg;
+DROP INDEX index_star_id_bf_trgm;
+DROP INDEX index_star_name_trgm;
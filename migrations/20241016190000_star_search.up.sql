@@ -0,0 +1,11 @@
+-- indices for searching stars by name and catalog IDs
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX index_star_name_trgm ON star USING GIN (name gin_trgm_ops);
+CREATE INDEX index_star_id_bf_trgm ON star USING GIN (id_bf gin_trgm_ops);
+CREATE INDEX index_star_id_hyg ON star(id_hyg);
+CREATE INDEX index_star_id_hip ON star(id_hip);
+CREATE INDEX index_star_id_hd ON star(id_hd);
+CREATE INDEX index_star_id_hr ON star(id_hr);
+CREATE INDEX index_star_id_gl ON star(lower(id_gl));
@@ -0,0 +1,13 @@
+-- saved camera views
+
+CREATE TABLE bookmark (
+    id UUID NOT NULL,
+    faction_id UUID NOT NULL REFERENCES faction(faction_id),
+    name TEXT NOT NULL,
+    position vec3 NOT NULL,
+    -- unit quaternion as (i, j, k, w)
+    rotation REAL[4] NOT NULL,
+    star_id UUID REFERENCES star(id),
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (faction_id, id)
+);
@@ -0,0 +1,11 @@
+-- regions of high star density, computed by clustering the stars
+
+CREATE TABLE region (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    center vec3 NOT NULL,
+    -- outline on the XZ-plane as array of [x, z] pairs
+    hull JSONB NOT NULL,
+    num_stars INTEGER NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT utc_now()
+);
@@ -0,0 +1,12 @@
+-- precomputed impostors for rendering distant stars, one per chunk of space
+
+CREATE TABLE star_chunk (
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    z INTEGER NOT NULL,
+    num_stars INTEGER NOT NULL,
+    -- array of impostor points with position, color and luminosity
+    points JSONB NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT utc_now(),
+    PRIMARY KEY (x, y, z)
+);
@@ -0,0 +1,19 @@
+-- planets around stars, e.g. real exoplanets imported from the NASA Exoplanet
+-- Archive
+
+CREATE TABLE planet (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    star_id UUID NOT NULL REFERENCES star(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- real exoplanets are confirmed, generated planets are not
+    confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    semi_major_axis REAL,
+    eccentricity REAL,
+    orbital_period REAL,
+    mass REAL,
+    radius REAL,
+    equilibrium_temperature REAL,
+    discovery_year INTEGER,
+    -- also serves as index for looking up the planets of a star
+    UNIQUE (star_id, name)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE star DROP COLUMN habitable_zone_outer;
+ALTER TABLE star DROP COLUMN habitable_zone_inner;
@@ -0,0 +1,5 @@
+-- habitable zone in AU, derived from the luminosity. stars imported before
+-- this are filled in by auditing the stars with `--fix`.
+
+ALTER TABLE star ADD COLUMN habitable_zone_inner REAL;
+ALTER TABLE star ADD COLUMN habitable_zone_outer REAL;
@@ -0,0 +1,16 @@
+-- worlds hosted by the server. each world's tables are in its own schema,
+-- except for the default world, whose tables are in the public schema.
+--
+-- the migrations are also run for every world's schema, so the table is always
+-- created in the public schema.
+
+CREATE TABLE IF NOT EXISTS public.world (
+    id UUID NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    schema_name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT utc_now()
+);
+
+INSERT INTO public.world (id, name, schema_name)
+VALUES ('00000000-0000-0000-0000-000000000000', 'main', 'public')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1 @@
+ALTER TABLE star DROP COLUMN variability;
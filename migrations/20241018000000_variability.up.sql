@@ -0,0 +1,4 @@
+-- peak-to-peak variability in magnitudes, for stars that are flagged variable
+-- in the catalog.
+
+ALTER TABLE star ADD COLUMN variability REAL;
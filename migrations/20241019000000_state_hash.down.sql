@@ -0,0 +1 @@
+ALTER TABLE simulation_state DROP COLUMN state_hash;
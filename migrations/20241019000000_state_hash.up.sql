@@ -0,0 +1,3 @@
+-- hash of the simulation state after the last epoch
+
+ALTER TABLE simulation_state ADD COLUMN state_hash BYTEA;
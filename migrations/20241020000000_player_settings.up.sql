@@ -0,0 +1,9 @@
+-- UI settings of a faction, synced between browsers
+
+CREATE TABLE player_settings (
+    faction_id UUID PRIMARY KEY REFERENCES faction(faction_id),
+    -- incremented on every write, for the clients to detect conflicts
+    version BIGINT NOT NULL,
+    settings JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
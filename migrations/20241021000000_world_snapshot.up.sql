@@ -0,0 +1,10 @@
+-- snapshots of the world after some epochs, for replays
+
+CREATE TABLE world_snapshot (
+    epoch BIGINT PRIMARY KEY,
+    taken_at TIMESTAMPTZ NOT NULL,
+    -- array of fleets, with their order queues
+    fleets JSONB NOT NULL,
+    -- array of colonies, with the positions of their stars
+    colonies JSONB NOT NULL
+);
@@ -0,0 +1,9 @@
+-- urls to which the world's events are posted
+
+CREATE TABLE webhook (
+    id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+    url TEXT NOT NULL,
+    -- names of the events that are sent, e.g. 'war-declared'
+    events TEXT[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT utc_now()
+);
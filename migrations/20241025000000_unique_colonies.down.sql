@@ -0,0 +1 @@
+ALTER TABLE colony DROP CONSTRAINT colony_star_id_key;
@@ -0,0 +1,13 @@
+-- every star has at most one colony. the oldest one is kept.
+
+DELETE FROM colony
+WHERE id IN (
+    SELECT id
+    FROM (
+        SELECT id, row_number() OVER (PARTITION BY star_id ORDER BY founded_at, id) AS n
+        FROM colony
+    ) AS numbered
+    WHERE n > 1
+);
+
+ALTER TABLE colony ADD CONSTRAINT colony_star_id_key UNIQUE (star_id);
@@ -0,0 +1 @@
+ALTER TYPE journal_event_kind ADD VALUE 'construction-finished';
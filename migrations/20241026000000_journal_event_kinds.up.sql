@@ -0,0 +1,9 @@
+-- nothing is constructed yet, so 'construction-finished' is removed from journal_event_kind.
+-- values can't be removed from enums, so the type is replaced.
+
+DELETE FROM journal_entry WHERE kind = 'construction-finished';
+
+ALTER TYPE journal_event_kind RENAME TO journal_event_kind_old;
+CREATE TYPE journal_event_kind AS ENUM ('battle', 'discovery', 'colony-founded');
+ALTER TABLE journal_entry ALTER COLUMN kind TYPE journal_event_kind USING kind::TEXT::journal_event_kind;
+DROP TYPE journal_event_kind_old;